@@ -0,0 +1,56 @@
+//! Records which commit muffin was built from and when, for `muffin --version`
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // A git checkout, as opposed to a tarball or Nix's copy of the sources
+    let git_dir = Path::new("../.git");
+    for path in ["HEAD", "refs", "packed-refs"] {
+        if git_dir.join(path).exists() {
+            println!("cargo:rerun-if-changed=../.git/{path}");
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // Reproducible builds pin the date
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+
+    let out = Path::new(&std::env::var("OUT_DIR").unwrap()).join("build_info.rs");
+    let contents = format!(
+        "pub const GIT_HASH: &str = {git_hash:?};\npub const BUILD_DATE: &str = {:?};\n",
+        civil_date(seconds / 86400)
+    );
+    std::fs::write(out, contents).unwrap();
+}
+
+/// `YYYY-MM-DD` of the day `days` after 1970-01-01, after Howard Hinnant's `civil_from_days`
+fn civil_date(days: u64) -> String {
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
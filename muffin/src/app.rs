@@ -1,3 +1,22 @@
+pub mod actions;
+pub mod auto_name;
 pub mod driver;
+pub mod header;
+pub mod history;
+pub mod icons;
+pub mod keymap;
+pub mod loading;
 pub mod menus;
+pub mod mru;
+pub mod notifications;
+pub mod preflight;
+pub mod preset_list;
+pub mod project;
+pub mod startup;
+pub mod state;
+#[cfg(test)]
+pub mod testing;
+pub mod theme;
+pub mod trash;
 pub mod utils;
+pub mod widgets;
@@ -0,0 +1,269 @@
+use crate::app::driver::{AppEvent, AppMode, AppState, Panel};
+use crate::app::notifications::{Level, notify};
+
+/// What an action does, handed to the menu of its panel as an
+/// [`AppEvent::Action`](crate::app::driver::AppEvent::Action)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionId {
+    CreateSession,
+    ToggleSort,
+    ShowPanes,
+    EditNote,
+    KillOtherSessions,
+    ToggleUnsafe,
+    ToggleFollow,
+    KillServer,
+    StartServer,
+    ReloadPresets,
+    ExportPreset,
+    PlanPreset,
+    DiffPreset,
+    ShowDiagnostics,
+    Launch(String),
+    LaunchPreset,
+    Top,
+    Bottom,
+    ToggleTheme,
+    About,
+}
+
+/// Something the command palette lists, which often has a key of its own too
+pub struct Action {
+    pub id: ActionId,
+    /// What the config file's `keys` block binds it by
+    pub name: &'static str,
+    pub title: &'static str,
+    /// The key doing the same in the action's menu, shown next to it
+    pub key: Option<&'static str>,
+    /// The menu that carries it out, or `None` for actions that don't need one
+    pub panel: Option<Panel>,
+    /// Whether it makes sense right now
+    pub applies: fn(&AppState) -> bool,
+}
+
+/// Every action there is, in the order the palette lists them before anything is typed
+pub const ACTIONS: &[Action] = &[
+    Action {
+        id: ActionId::CreateSession,
+        name: "create-session",
+        title: "Create a session",
+        key: Some("a"),
+        panel: Some(Panel::Sessions),
+        applies: |_| true,
+    },
+    Action {
+        id: ActionId::ToggleSort,
+        name: "toggle-sort",
+        title: "Sort sessions by recency or name",
+        key: Some("m"),
+        panel: Some(Panel::Sessions),
+        applies: |_| true,
+    },
+    Action {
+        id: ActionId::ShowPanes,
+        name: "show-panes",
+        title: "Zoom, break out or join the session's panes",
+        key: Some("l"),
+        panel: Some(Panel::Sessions),
+        applies: |state| state.selected_session.is_some(),
+    },
+    Action {
+        id: ActionId::EditNote,
+        name: "edit-note",
+        title: "Leave a note on the session",
+        key: Some("n"),
+        panel: Some(Panel::Sessions),
+        applies: |state| state.selected_session.is_some(),
+    },
+    Action {
+        id: ActionId::KillOtherSessions,
+        name: "kill-other-sessions",
+        title: "Kill all sessions except the current one",
+        key: None,
+        panel: Some(Panel::Sessions),
+        applies: |state| state.sessions.iter().any(|s| s.is_current) && state.sessions.len() > 1,
+    },
+    Action {
+        id: ActionId::ToggleUnsafe,
+        name: "toggle-unsafe",
+        title: "Delete sessions without asking, or ask again",
+        key: Some("!"),
+        panel: Some(Panel::Sessions),
+        applies: |_| true,
+    },
+    Action {
+        id: ActionId::ToggleFollow,
+        name: "toggle-follow",
+        title: "Keep the attached session highlighted, or stop",
+        key: Some("f"),
+        panel: Some(Panel::Sessions),
+        applies: |_| true,
+    },
+    Action {
+        id: ActionId::KillServer,
+        name: "kill-server",
+        title: "Kill the tmux server",
+        key: Some("ctrl+k"),
+        panel: Some(Panel::Sessions),
+        applies: |state| state.server_running,
+    },
+    Action {
+        id: ActionId::StartServer,
+        name: "start-server",
+        title: "Start the tmux server",
+        key: Some("ctrl+s"),
+        panel: Some(Panel::Sessions),
+        applies: |state| !state.server_running,
+    },
+    Action {
+        id: ActionId::ReloadPresets,
+        name: "reload-presets",
+        title: "Reload presets",
+        key: Some("R"),
+        panel: Some(Panel::Presets),
+        applies: |_| true,
+    },
+    Action {
+        id: ActionId::ExportPreset,
+        name: "export-preset",
+        title: "Export preset to a script",
+        key: Some("e"),
+        panel: Some(Panel::Presets),
+        applies: |state| state.highlighted_preset().is_some(),
+    },
+    Action {
+        id: ActionId::PlanPreset,
+        name: "plan-preset",
+        title: "Show what launching the preset runs",
+        key: Some("p"),
+        panel: Some(Panel::Presets),
+        applies: |state| state.highlighted_preset().is_some(),
+    },
+    Action {
+        id: ActionId::DiffPreset,
+        name: "diff-preset",
+        title: "Compare the running session with its preset",
+        key: Some("="),
+        panel: Some(Panel::Presets),
+        applies: |state| {
+            state
+                .highlighted_preset()
+                .is_some_and(|preset| preset.running)
+        },
+    },
+    Action {
+        id: ActionId::LaunchPreset,
+        name: "launch-preset",
+        title: "Launch the highlighted preset",
+        key: Some("enter"),
+        panel: Some(Panel::Presets),
+        applies: |state| state.highlighted_preset().is_some(),
+    },
+    Action {
+        id: ActionId::ShowDiagnostics,
+        name: "show-diagnostics",
+        title: "List problems with the presets files",
+        key: Some("W"),
+        panel: Some(Panel::Presets),
+        applies: |state| !state.diagnostics.is_empty(),
+    },
+    Action {
+        id: ActionId::Top,
+        name: "top",
+        title: "Go to the top of the list",
+        key: Some("g"),
+        panel: None,
+        applies: |_| true,
+    },
+    Action {
+        id: ActionId::Bottom,
+        name: "bottom",
+        title: "Go to the bottom of the list",
+        key: Some("G"),
+        panel: None,
+        applies: |_| true,
+    },
+    Action {
+        id: ActionId::ToggleTheme,
+        name: "toggle-theme",
+        title: "Switch to the next theme",
+        key: None,
+        panel: None,
+        applies: |_| true,
+    },
+    Action {
+        id: ActionId::About,
+        name: "about",
+        title: "About muffin: version, tmux and the files read",
+        key: None,
+        panel: None,
+        applies: |_| true,
+    },
+];
+
+/// One line of the palette
+pub struct Entry {
+    pub id: ActionId,
+    pub title: String,
+    pub key: Option<&'static str>,
+    pub panel: Option<Panel>,
+}
+
+/// The actions that apply right now, followed by launching each preset
+pub fn available(state: &AppState) -> Vec<Entry> {
+    let actions = ACTIONS
+        .iter()
+        .filter(|action| (action.applies)(state))
+        .map(|action| Entry {
+            id: action.id.clone(),
+            title: action.title.to_string(),
+            key: action.key,
+            panel: action.panel,
+        });
+    let launches = state.presets.iter().map(|preset| Entry {
+        id: ActionId::Launch(preset.name.clone()),
+        title: format!("launch: {}", preset.name),
+        key: None,
+        panel: Some(Panel::Presets),
+    });
+    actions.chain(launches).collect()
+}
+
+/// Carries out `id` in the menu of `panel`, switching to it first, or right away for actions
+/// without one
+pub fn dispatch(id: ActionId, panel: Option<Panel>, state: &mut AppState) {
+    let Some(panel) = panel else {
+        return run_global(&id, state);
+    };
+    state.mode = panel.mode();
+    let _ = state.event_handler.tx.send(AppEvent::Action(id));
+}
+
+/// Carries out `id` as bound to a key, unless it doesn't make sense right now
+pub fn run_bound(id: ActionId, state: &mut AppState) {
+    let action = ACTIONS.iter().find(|action| action.id == id);
+    if let Some(action) = action
+        && !(action.applies)(state)
+    {
+        let msg = format!("Can't {} right now", action.name.replace('-', " "));
+        return notify(state, Level::Info, msg);
+    }
+    let panel = match action {
+        Some(action) => action.panel,
+        None => Some(Panel::Presets),
+    };
+    dispatch(id, panel, state);
+}
+
+/// Carries out the actions without a panel
+fn run_global(id: &ActionId, state: &mut AppState) {
+    match id {
+        ActionId::ToggleTheme => state.theme = state.theme.next(),
+        ActionId::About => state.mode = AppMode::About,
+        // Moving around is up to whichever menu is shown
+        ActionId::Top | ActionId::Bottom => {
+            let _ = state.event_handler.tx.send(AppEvent::Action(id.clone()));
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use tmux::Session;
+
+/// Names sessions after what runs in them (`cmd@dir`), like tmux's `automatic-rename` does for
+/// windows, when `auto_name_sessions` is on.
+///
+/// Only sessions marked with [`Session::auto_name`] are touched, which muffin does for those it
+/// creates without a name. A session named otherwise than the marker says was renamed by
+/// someone else, which is for keeps: the marker is dropped.
+#[derive(Debug, Default)]
+pub struct AutoNamer {
+    /// Name each marked session (by id) would get, as of the last refresh. A session is only
+    /// renamed once it wants the same name two refreshes in a row, so a command that's over in a
+    /// moment doesn't make its name flicker.
+    wanted: HashMap<String, String>,
+}
+
+/// What to do about a marked session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutoName {
+    /// Rename the session (by id) to the name, and mark it with that
+    Rename { session: String, name: String },
+    /// Drop the session's (by id) marker, as it was renamed by someone else
+    Release { session: String },
+}
+
+impl AutoNamer {
+    /// Goes over the sessions tmux just listed, returning what to do about the marked ones
+    pub fn update(&mut self, sessions: &[Session]) -> Vec<AutoName> {
+        let mut changes = vec![];
+        let mut wanted = HashMap::new();
+        for session in sessions {
+            let Some(marker) = &session.auto_name else {
+                continue;
+            };
+            if *marker != session.name {
+                changes.push(AutoName::Release {
+                    session: session.id.clone(),
+                });
+                continue;
+            }
+            let Some(name) = name_after_command(session) else {
+                continue;
+            };
+            // Two sessions can't have the same name, so one running the same as another waits
+            if sessions.iter().any(|s| s.name == name) {
+                continue;
+            }
+            if self.wanted.get(&session.id) == Some(&name) {
+                changes.push(AutoName::Rename {
+                    session: session.id.clone(),
+                    name,
+                });
+            } else {
+                wanted.insert(session.id.clone(), name);
+            }
+        }
+        self.wanted = wanted;
+        changes
+    }
+}
+
+/// `cmd@dir`, from the command and last directory component of the session's active pane, with
+/// the characters tmux reads as target separators replaced
+fn name_after_command(session: &Session) -> Option<String> {
+    let command = session.command.as_deref()?;
+    let dir = session
+        .path
+        .as_deref()
+        .and_then(|path| Path::new(path).file_name())
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "/".to_string());
+    Some(format!("{command}@{dir}").replace([':', '.'], "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing::session;
+
+    /// A session muffin created as `name`, running `command` in `path`
+    fn marked(name: &str, command: &str, path: &str) -> Session {
+        Session {
+            auto_name: Some(name.to_string()),
+            command: Some(command.to_string()),
+            path: Some(path.to_string()),
+            ..session(name)
+        }
+    }
+
+    fn rename(session: &str, name: &str) -> AutoName {
+        AutoName::Rename {
+            session: session.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn renamed_once_stable_for_two_refreshes() {
+        let mut namer = AutoNamer::default();
+        let fresh = [marked("3", "nvim", "/home/me/proj")];
+        assert_eq!(namer.update(&fresh), []);
+        assert_eq!(namer.update(&fresh), [rename("$3", "nvim@proj")]);
+
+        // Once renamed it stays put
+        let renamed = [Session {
+            id: "$3".to_string(),
+            ..marked("nvim@proj", "nvim", "/home/me/proj")
+        }];
+        for _ in 0..3 {
+            assert_eq!(namer.update(&renamed), []);
+        }
+    }
+
+    #[test]
+    fn flickering_commands_are_left_alone() {
+        let mut namer = AutoNamer::default();
+        for command in ["zsh", "ls", "zsh", "git", "zsh"] {
+            assert_eq!(namer.update(&[marked("3", command, "/proj")]), []);
+        }
+        assert_eq!(
+            namer.update(&[marked("3", "zsh", "/proj")]),
+            [rename("$3", "zsh@proj")]
+        );
+    }
+
+    #[test]
+    fn changing_directory_restarts_the_wait() {
+        let mut namer = AutoNamer::default();
+        assert_eq!(namer.update(&[marked("3", "zsh", "/a")]), []);
+        assert_eq!(namer.update(&[marked("3", "zsh", "/b")]), []);
+        assert_eq!(
+            namer.update(&[marked("3", "zsh", "/b")]),
+            [rename("$3", "zsh@b")]
+        );
+    }
+
+    #[test]
+    fn a_session_gone_for_a_refresh_starts_over() {
+        let mut namer = AutoNamer::default();
+        let fresh = [marked("3", "nvim", "/proj")];
+        assert_eq!(namer.update(&fresh), []);
+        assert_eq!(namer.update(&[]), []);
+        assert_eq!(namer.update(&fresh), []);
+        assert_eq!(namer.update(&fresh), [rename("$3", "nvim@proj")]);
+    }
+
+    #[test]
+    fn unmarked_sessions_are_never_renamed() {
+        let mut namer = AutoNamer::default();
+        let unmarked = Session {
+            auto_name: None,
+            ..marked("work", "nvim", "/proj")
+        };
+        for _ in 0..3 {
+            assert_eq!(namer.update(std::slice::from_ref(&unmarked)), []);
+        }
+    }
+
+    #[test]
+    fn sessions_renamed_elsewhere_are_released() {
+        let mut namer = AutoNamer::default();
+        let fresh = marked("3", "nvim", "/proj");
+        assert_eq!(namer.update(std::slice::from_ref(&fresh)), []);
+        let renamed = Session {
+            name: "mine".to_string(),
+            ..fresh
+        };
+        let release = AutoName::Release {
+            session: "$3".to_string(),
+        };
+        assert_eq!(namer.update(std::slice::from_ref(&renamed)), [release]);
+    }
+
+    #[test]
+    fn taken_names_wait() {
+        let mut namer = AutoNamer::default();
+        let sessions = [marked("3", "nvim", "/proj"), session("nvim@proj")];
+        for _ in 0..3 {
+            assert_eq!(namer.update(&sessions), []);
+        }
+        assert_eq!(namer.update(&sessions[..1]), []);
+        assert_eq!(namer.update(&sessions[..1]), [rename("$3", "nvim@proj")]);
+    }
+
+    #[test]
+    fn each_session_waits_on_its_own() {
+        let mut namer = AutoNamer::default();
+        let a = marked("1", "nvim", "/a");
+        assert_eq!(namer.update(std::slice::from_ref(&a)), []);
+        let b = marked("2", "htop", "/");
+        assert_eq!(
+            namer.update(&[a.clone(), b.clone()]),
+            [rename("$1", "nvim@a")]
+        );
+        assert_eq!(namer.update(&[b]), [rename("$2", "htop@/")]);
+    }
+
+    #[test]
+    fn names_without_target_separators() {
+        let mut namer = AutoNamer::default();
+        let fresh = [marked("3", "python3.12", "/home/me/my.site:8080")];
+        namer.update(&fresh);
+        assert_eq!(
+            namer.update(&fresh),
+            [rename("$3", "python3_12@my_site_8080")]
+        );
+    }
+}
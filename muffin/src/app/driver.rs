@@ -1,22 +1,60 @@
-use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use futures::{FutureExt, StreamExt};
-use tokio::sync::mpsc;
-use tokio::task::JoinHandle;
+use futures::{FutureExt, Stream, StreamExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{JoinHandle, JoinSet};
 
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use ratatui::DefaultTerminal;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::Terminal;
+use ratatui::backend::Backend;
+use ratatui::layout::{Constraint, Layout, Position, Rect};
 
-use tmux::{self, Preset, Session};
+use parser::{Config, Diagnostic, Source};
+use tmux::{LiveWindow, Preset, PresetSpawn, Server, Session, TmuxError};
 
-use crate::app::menus::Menu;
-use crate::app::menus::create::CreateMenu;
+use crate::app::actions::{ActionId, run_bound};
+use crate::app::auto_name::{AutoName, AutoNamer};
+use crate::app::header::render_header;
+use crate::app::history::NameHistory;
+use crate::app::icons::Icons;
+use crate::app::keymap::{Dispatch, Keymap};
+use crate::app::loading::{LoadState, render_loading};
+use crate::app::menus::about::AboutMenu;
+use crate::app::menus::adjust::AdjustMenu;
+use crate::app::menus::append::AppendMenu;
+use crate::app::menus::conflict::ConflictMenu;
+use crate::app::menus::create::{Completion, CreateMenu};
 use crate::app::menus::delete::DeleteMenu;
+use crate::app::menus::diagnostics::DiagnosticsMenu;
+use crate::app::menus::diff::DiffMenu;
+use crate::app::menus::kill_server::KillServerMenu;
+use crate::app::menus::new_window::NewWindowMenu;
+use crate::app::menus::not_found::NotFoundMenu;
+use crate::app::menus::note::NoteMenu;
+use crate::app::menus::palette::PaletteMenu;
+use crate::app::menus::panes::PanesMenu;
+use crate::app::menus::plan::PlanMenu;
 use crate::app::menus::presets::PresetsMenu;
 use crate::app::menus::rename::RenameMenu;
 use crate::app::menus::sessions::SessionsMenu;
+use crate::app::menus::vars::VarsMenu;
+use crate::app::menus::{Menu, Target};
+use crate::app::mru::Mru;
+use crate::app::notifications::{DesktopNotifier, Level, NotificationSink, Notifications, notify};
+use crate::app::preset_list::{PresetList, read_presets};
+use crate::app::project::ProjectCache;
+use crate::app::startup::Launch;
+use crate::app::state::UiState;
+use crate::app::theme::Theme;
+use crate::app::trash::{Trash, is_trash};
+use crate::app::utils::{list_preset_sockets, list_sockets, mark_running_presets, preset_sockets};
+use crate::paths;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub enum AppMode {
     #[default]
     Sessions,
@@ -24,59 +62,359 @@ pub enum AppMode {
     Create,
     Rename,
     Delete,
+    RenamePreset,
+    DeletePreset,
+    NewWindow,
+    /// The highlighted session's windows and panes
+    Panes,
+    /// The note on the highlighted session
+    Note,
+    Plan,
+    /// How the highlighted preset's running session drifted from it
+    Diff,
+    Append,
+    Vars,
+    Adjust,
+    Diagnostics,
+    KillServer,
+    /// Launching a preset named like a session it didn't launch
+    Conflict,
+    /// Launching a preset whose pane commands aren't all on PATH
+    NotFound,
+    /// The command palette, opened over whichever panel has the keys
+    Palette,
+    /// Which muffin build this is, opened over whichever panel has the keys
+    About,
 }
 
+impl AppMode {
+    /// The main menu this mode belongs to, `None` for popups that come up over either
+    pub fn panel(&self) -> Option<Panel> {
+        let panel = match self {
+            AppMode::Sessions
+            | AppMode::Create
+            | AppMode::Rename
+            | AppMode::Delete
+            | AppMode::NewWindow
+            | AppMode::Panes
+            | AppMode::Note
+            | AppMode::KillServer => Panel::Sessions,
+            AppMode::Presets
+            | AppMode::RenamePreset
+            | AppMode::DeletePreset
+            | AppMode::Plan
+            | AppMode::Diff
+            | AppMode::Append
+            | AppMode::Vars
+            | AppMode::Adjust
+            | AppMode::Diagnostics
+            | AppMode::Conflict
+            | AppMode::NotFound => Panel::Presets,
+            AppMode::Palette | AppMode::About => return None,
+        };
+        Some(panel)
+    }
+}
+
+/// The two main menus, which wide terminals show side by side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Panel {
+    Sessions,
+    Presets,
+}
+
+impl Panel {
+    /// The panel's own menu, without any popup over it
+    pub fn mode(self) -> AppMode {
+        match self {
+            Panel::Sessions => AppMode::Sessions,
+            Panel::Presets => AppMode::Presets,
+        }
+    }
+}
+
+/// Terminal width from which both panels are shown, unless configured otherwise
+const SIDE_BY_SIDE_WIDTH: u16 = 140;
+
+/// How often tmux's sessions are listed again without a key calling for it, to pick up what
+/// changed outside muffin
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
 pub struct App {
     pub state: AppState,
+    /// Loads started by [`App::start_loading`] that haven't reported back yet
+    awaiting_sessions: bool,
+    awaiting_presets: bool,
+    /// Session to highlight once sessions are first listed, instead of the current one
+    initial_session: Option<String>,
+    /// Where each panel was drawn last, while they're side by side
+    panel_areas: Option<(Rect, Rect)>,
+    /// Where the UI was left last time, until it's restored once everything is loaded
+    saved_ui: Option<UiState>,
+    /// What tmux listed on the last refresh, see [`App::refresh_sessions`]
+    snapshot: SessionSnapshot,
+    /// Refreshes in a row that tmux failed, by not answering in time or otherwise
+    failures: u32,
+    /// Refreshes left to skip after a failure, so a hung server doesn't stall every key press
+    skipped_refreshes: u32,
+    /// Names sessions after what runs in them, with `auto_name_sessions`
+    auto_namer: AutoNamer,
+}
+
+/// Sessions as tmux listed them, before the trash is left out and presets fill in their colors
+/// and tags
+#[derive(Default, PartialEq)]
+struct SessionSnapshot {
+    /// `None` without a server
+    sessions: Option<Vec<Session>>,
+    /// See [`AppState::preset_sockets`]
+    preset_sockets: HashMap<String, Vec<Session>>,
+}
+
+/// Every menu the app can show, looked up by the mode they belong to
+struct Menus<'a> {
+    sessions: SessionsMenu<'a>,
+    presets: PresetsMenu,
+    create: CreateMenu<'a>,
+    rename: RenameMenu<'a>,
+    delete: DeleteMenu,
+    rename_preset: RenameMenu<'a>,
+    delete_preset: DeleteMenu,
+    new_window: NewWindowMenu<'a>,
+    panes: PanesMenu,
+    note: NoteMenu<'a>,
+    plan: PlanMenu,
+    diff: DiffMenu,
+    append: AppendMenu<'a>,
+    vars: VarsMenu<'a>,
+    adjust: AdjustMenu,
+    diagnostics: DiagnosticsMenu,
+    kill_server: KillServerMenu<'a>,
+    conflict: ConflictMenu,
+    not_found: NotFoundMenu,
+    palette: PaletteMenu<'a>,
+    about: AboutMenu,
+}
+
+impl<'a> Menus<'a> {
+    fn get(&mut self, mode: &AppMode) -> &mut dyn Menu {
+        match mode {
+            AppMode::Sessions => &mut self.sessions,
+            AppMode::Presets => &mut self.presets,
+            AppMode::Create => &mut self.create,
+            AppMode::Rename => &mut self.rename,
+            AppMode::Delete => &mut self.delete,
+            AppMode::RenamePreset => &mut self.rename_preset,
+            AppMode::DeletePreset => &mut self.delete_preset,
+            AppMode::NewWindow => &mut self.new_window,
+            AppMode::Panes => &mut self.panes,
+            AppMode::Note => &mut self.note,
+            AppMode::Plan => &mut self.plan,
+            AppMode::Diff => &mut self.diff,
+            AppMode::Append => &mut self.append,
+            AppMode::Vars => &mut self.vars,
+            AppMode::Adjust => &mut self.adjust,
+            AppMode::Diagnostics => &mut self.diagnostics,
+            AppMode::KillServer => &mut self.kill_server,
+            AppMode::Conflict => &mut self.conflict,
+            AppMode::NotFound => &mut self.not_found,
+            AppMode::Palette => &mut self.palette,
+            AppMode::About => &mut self.about,
+        }
+    }
 }
 
 pub struct AppState {
     pub event_handler: EventHandler,
     pub sessions: Vec<Session>,
-    pub presets: BTreeMap<String, Preset>,
-    pub presets_path: String,
+    pub presets: PresetList,
+    /// Problems found while parsing the presets files that didn't stop them from loading, from
+    /// the last time they were read
+    pub diagnostics: Vec<Diagnostic>,
+    /// Where presets are read from, as given on the command line (files, directories, stdin or
+    /// inline documents)
+    pub preset_sources: Vec<Source>,
+    /// Where the presets menu offers to write a starter presets file, while there's none to read
+    /// from
+    pub starter_path: Option<PathBuf>,
     pub selected_session: Option<usize>,
-    pub selected_preset: Option<usize>,
+    /// Name of the highlighted preset, which stays put when presets are added or removed
+    pub selected_preset: Option<String>,
+    /// Session to highlight once it shows up in the sessions list
+    pub focus_session: Option<String>,
     pub exit: bool,
     pub exit_on_switch: bool,
+    /// Running in a tmux popup: switching quits, as does Esc in the sessions and presets menus,
+    /// and there's no header taking up room
+    pub popup: bool,
     pub mode: AppMode,
+    pub icons: &'static Icons,
+    pub theme: &'static Theme,
+    pub config: Config,
+    /// The config file `config` was read from, if any
+    pub config_path: Option<PathBuf>,
+    pub server: Server,
+    /// Unset while there's no tmux server, until something starts one
+    pub server_running: bool,
+    /// Sessions on the other sockets presets launch into (see [`Preset::socket`]), listed once
+    /// per socket along with `sessions`
+    pub preset_sockets: HashMap<String, Vec<Session>>,
+    /// A session on another server to attach to once the app exits, see [`App::take_attach`]
+    pub attach: Option<(Server, String)>,
+    /// Launched presets whose held-back pane commands are still being sent, each resolving to
+    /// its warnings
+    pub startups: JoinSet<Vec<String>>,
+    /// Deleted sessions that can still be restored
+    pub trash: Trash,
+    /// What kind of project each session's directory holds
+    pub projects: ProjectCache,
+    /// Menus are only shown once sessions and presets are loaded
+    pub load_state: LoadState,
+    /// Shown by whichever menu is active, see [`notify`]
+    pub notifications: Notifications,
+    /// Where errors and the like also go with `desktop_notifications`
+    pub desktop: Option<Box<dyn NotificationSink>>,
+    /// Sessions most recently switched to through muffin
+    pub mru: Mru,
+    /// Names recently given to sessions in the create popup
+    pub history: NameHistory,
+    /// Sessions and presets are both shown, `focus` having the keyboard
+    pub side_by_side: bool,
+    /// The main menu that has the keys, which popups are drawn over and go back to. Follows
+    /// `mode` into either menu and its popups.
+    pub focus: Panel,
+    /// `d` deletes sessions without asking, except attached ones. Toggled with `!`.
+    pub unsafe_mode: bool,
+    /// The sessions list keeps the attached session highlighted as it changes, unless the
+    /// selection was just moved by hand. Toggled with `f`.
+    pub following: bool,
+    /// Set by the presets menu for the next launch to be followed by adjusting its panes
+    pub adjust_on_launch: bool,
+    /// The preset whose panes are being adjusted, as launched (variables filled in)
+    pub adjusting: Option<Preset>,
+    /// The preset whose session is being created, one at a time
+    pub launching: Option<Launch>,
+    /// Pane (by id) to move into another window with `M` in the panes popup, see
+    /// [`PanesMenu`]
+    pub marked_pane: Option<String>,
+    /// A preset held back from launching by a session of the same name it didn't launch, as
+    /// launched (variables filled in)
+    pub conflicting: Option<Preset>,
+    /// A preset held back from launching by pane commands that can't be found, as launched, along
+    /// with those commands
+    pub not_found: Option<(Preset, Vec<String>)>,
+    /// Choosing a name for `muffin pick` to print: enter hands it back instead of switching or
+    /// launching, and nothing else touches tmux
+    pub picking: bool,
+    /// The name chosen while picking, see [`App::pick`]
+    pub picked: Option<String>,
+    /// Key sequences from the config file, which the sessions and presets menus get their keys
+    /// through
+    pub keymap: Keymap,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub enum AppEvent {
     Error,
     Key(KeyEvent),
+    Mouse(MouseEvent),
+    /// Text pasted into the terminal, as one event rather than a key per character
+    Paste(String),
     Redraw,
-    ShowNotification(String),
-    ClearNotification,
+    /// A notification timer ran out
+    ExpireNotifications,
+    Completions(Vec<Completion>),
+    /// Windows of the named session, fetched in the background
+    SessionWindows(String, Result<Vec<LiveWindow>, TmuxError>),
+    /// tmux's sessions, listed in the background while starting up, `None` without a server
+    SessionsUpdated(Result<Option<Vec<Session>>, TmuxError>),
+    /// The presets and their warnings, read in the background while starting up
+    PresetsLoaded(Result<(PresetList, Vec<Diagnostic>), String>),
+    /// An action chosen in the command palette, for the menu of its panel to carry out
+    Action(ActionId),
+    /// The keys typed of a sequence waited as long as they do for the next one, see
+    /// [`Keymap::expire`]
+    ChordTimeout,
+    /// The launching preset is about to run its `step`th tmux command out of `total`
+    SpawnProgress {
+        preset: String,
+        step: usize,
+        total: usize,
+        detail: String,
+    },
+    /// The launching preset's session is set up, or was stopped halfway, or failed
+    SpawnFinished {
+        preset: String,
+        result: Result<ControlFlow<(), PresetSpawn>, TmuxError>,
+    },
 }
 
 #[derive(Debug)]
 pub struct EventHandler {
     pub tx: mpsc::UnboundedSender<AppEvent>,
     rx: mpsc::UnboundedReceiver<AppEvent>,
-    _task: Option<JoinHandle<()>>,
+    /// Reads terminal events until told to stop through `cancel`
+    task: Option<JoinHandle<()>>,
+    cancel: Option<oneshot::Sender<()>>,
 }
 
 impl EventHandler {
+    /// Passes on the events sent through `tx`, and terminal events once [`EventHandler::listen`]
+    /// is called
     pub fn new() -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
-        let _tx = tx.clone();
+        Self {
+            tx,
+            rx,
+            task: None,
+            cancel: None,
+        }
+    }
+
+    /// Starts reading terminal events, which needs a terminal to read them from
+    pub fn listen(&mut self) {
+        self.read(crossterm::event::EventStream::new());
+    }
 
+    /// Passes on the terminal events coming out of `reader`
+    fn read(
+        &mut self,
+        mut reader: impl Stream<Item = std::io::Result<crossterm::event::Event>>
+        + Send
+        + Unpin
+        + 'static,
+    ) {
+        // Sends fail once the receiver is gone, which only happens while shutting down
+        let tx = self.tx.clone();
+        let (cancel, mut cancelled) = oneshot::channel();
         let task = tokio::spawn(async move {
-            let mut reader = crossterm::event::EventStream::new();
             loop {
                 let crossterm_event = reader.next().fuse();
                 tokio::select! {
-                    Some(Ok(evt)) = crossterm_event => {
+                    _ = &mut cancelled => break,
+                    evt = crossterm_event => {
+                        let Some(Ok(evt)) = evt else {
+                            break;
+                        };
                         match evt {
-                            crossterm::event::Event::Key(key) => {
-                                if key.kind == crossterm::event::KeyEventKind::Press {
-                                    tx.send(AppEvent::Key(key)).unwrap();
-                                }
+                            crossterm::event::Event::Key(key)
+                                if key.kind == crossterm::event::KeyEventKind::Press => {
+                                let _ = tx.send(AppEvent::Key(key));
+                            },
+                            // Motion is reported too, but every event costs a tmux round trip
+                            crossterm::event::Event::Mouse(mouse) if matches!(
+                                mouse.kind,
+                                MouseEventKind::Down(MouseButton::Left)
+                                    | MouseEventKind::ScrollUp
+                                    | MouseEventKind::ScrollDown
+                            ) => {
+                                let _ = tx.send(AppEvent::Mouse(mouse));
+                            },
+                            crossterm::event::Event::Paste(text) => {
+                                let _ = tx.send(AppEvent::Paste(text));
                             },
                             crossterm::event::Event::Resize(_, _) | crossterm::event::Event::FocusGained => {
-                                tx.send(AppEvent::Redraw).unwrap();
+                                let _ = tx.send(AppEvent::Redraw);
                             },
                             _ => {},
                         }
@@ -85,133 +423,1343 @@ impl EventHandler {
             }
         });
 
-        Self {
-            tx: _tx,
-            rx,
-            _task: Some(task),
-        }
+        self.task = Some(task);
+        self.cancel = Some(cancel);
     }
 
     pub async fn next(&mut self) -> Result<AppEvent, ()> {
         self.rx.recv().await.ok_or(())
     }
+
+    /// Stops reading terminal events and waits for the reader to finish, so it can't outlive
+    /// the terminal it reads from. Events already queued are dropped.
+    pub async fn shutdown(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+        self.rx.close();
+        while self.rx.try_recv().is_ok() {}
+    }
+}
+
+impl Drop for EventHandler {
+    /// Without a chance to wait for it, the reader is aborted instead
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+impl AppState {
+    /// Digest of everything drawn from the app state, so the driver can tell when a redraw
+    /// would come out the same. Menus' own state (inputs, scroll offsets) isn't included, it
+    /// only changes while they handle an event, which always redraws.
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.sessions.hash(&mut hasher);
+        for preset in self.presets.iter() {
+            (&preset.name, preset.running).hash(&mut hasher);
+        }
+        self.selected_session.hash(&mut hasher);
+        self.selected_preset.hash(&mut hasher);
+        self.mode.hash(&mut hasher);
+        self.load_state.hash(&mut hasher);
+        self.notifications.hash(&mut hasher);
+        self.trash.len().hash(&mut hasher);
+        self.server_running.hash(&mut hasher);
+        self.side_by_side.hash(&mut hasher);
+        self.focus.hash(&mut hasher);
+        self.unsafe_mode.hash(&mut hasher);
+        self.following.hash(&mut hasher);
+        self.keymap.pending().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Whether `panel` gets the keys, which it always does when it's the only one shown
+    pub fn is_focused(&self, panel: Panel) -> bool {
+        !self.side_by_side || self.focus == panel
+    }
+
+    /// The server `preset` launches on, when it isn't the one whose sessions are listed
+    pub fn other_server(&self, preset: &Preset) -> Option<Server> {
+        Some(self.server.for_preset(preset)).filter(|server| *server != self.server)
+    }
+
+    /// The sessions on the server `preset` launches on
+    pub fn sessions_for(&self, preset: &Preset) -> &[Session] {
+        preset
+            .socket
+            .as_ref()
+            .and_then(|socket| self.preset_sockets.get(socket))
+            .unwrap_or(&self.sessions)
+    }
+
+    /// Lists the preset sockets' sessions again and marks the presets running in them or in
+    /// `sessions`
+    pub fn mark_running(&mut self) {
+        self.preset_sockets = list_preset_sockets(&self.server, &self.presets);
+        mark_running_presets(&mut self.presets, &mut self.sessions, &self.preset_sockets);
+    }
+
+    /// The highlighted preset, unless it's gone since it was highlighted
+    pub fn highlighted_preset(&self) -> Option<&Preset> {
+        self.selected_preset
+            .as_deref()
+            .and_then(|name| self.presets.get_by_name(name))
+    }
+
+    /// Re-reads the presets (picking up new files in preset directories), keeping `selected`
+    /// highlighted if it still exists, or else whichever preset took the highlighted one's place
+    pub fn reload_presets(&mut self, selected: Option<&str>) -> Result<(), String> {
+        if let Some(source) = self.preset_sources.iter().find(|s| s.path().is_none()) {
+            return Err(format!(
+                "Presets from {source} can't be reloaded, run muffin again to pick up changes"
+            ));
+        }
+        let (presets, diagnostics) = read_presets(&self.preset_sources)?;
+        let position = self
+            .selected_preset
+            .as_deref()
+            .and_then(|name| self.presets.iter().position(|p| p.name == name));
+        self.presets = presets;
+        self.diagnostics = diagnostics;
+        self.mark_running();
+
+        let kept = selected.filter(|name| self.presets.get_by_name(name).is_some());
+        self.selected_preset = match (kept, position) {
+            (Some(name), _) => Some(name.to_string()),
+            (None, position) => position
+                .map(|idx| idx.min(self.presets.len().saturating_sub(1)))
+                .and_then(|idx| self.presets.get(idx))
+                .map(|preset| preset.name.clone()),
+        };
+        Ok(())
+    }
 }
 
 impl App {
+    /// Sessions and presets start out empty, [`App::run`] loads them without holding up the first
+    /// frame
     pub fn new(
-        sessions: Vec<Session>,
-        presets: BTreeMap<String, Preset>,
-        presets_file: String,
-        exit_on_switch: bool
+        preset_sources: Vec<Source>,
+        exit_on_switch: bool,
+        popup: bool,
+        config: Config,
+        theme: &'static Theme,
+        server: Server,
+        initial_session: Option<String>,
     ) -> Self {
+        let saved_ui = UiState::load(&server);
+        // Only the default presets file goes missing without it being an error
+        let starter_path = match preset_sources.is_empty() {
+            true => paths::default_presets_path(),
+            false => None,
+        };
+        let unsafe_mode = config.confirm_destructive == Some(false);
+        let desktop = config
+            .desktop_notifications
+            .then(|| Box::new(DesktopNotifier) as Box<dyn NotificationSink>);
         Self {
             state: AppState {
                 mode: AppMode::Sessions,
                 exit: false,
-                exit_on_switch,
-                sessions,
+                // Switching away leaves nothing to look at in a popup
+                exit_on_switch: exit_on_switch || popup,
+                popup,
+                sessions: vec![],
                 selected_session: None,
-                presets,
-                presets_path: presets_file,
+                presets: PresetList::default(),
+                diagnostics: vec![],
+                starter_path,
+                preset_sources,
                 selected_preset: None,
+                focus_session: None,
                 event_handler: EventHandler::new(),
+                icons: Icons::from_env(),
+                theme,
+                config,
+                config_path: None,
+                mru: Mru::load(&server),
+                history: NameHistory::load(&server),
+                server,
+                server_running: true,
+                preset_sockets: HashMap::new(),
+                attach: None,
+                startups: JoinSet::new(),
+                trash: Trash::default(),
+                projects: ProjectCache::default(),
+                load_state: LoadState::Loading,
+                notifications: Notifications::default(),
+                desktop,
+                side_by_side: false,
+                focus: Panel::Sessions,
+                unsafe_mode,
+                following: false,
+                adjust_on_launch: false,
+                adjusting: None,
+                launching: None,
+                marked_pane: None,
+                conflicting: None,
+                not_found: None,
+                picking: false,
+                picked: None,
+                keymap: Keymap::default(),
             },
+            awaiting_sessions: false,
+            snapshot: SessionSnapshot::default(),
+            failures: 0,
+            skipped_refreshes: 0,
+            auto_namer: AutoNamer::default(),
+            awaiting_presets: false,
+            saved_ui: Some(saved_ui),
+            initial_session,
+            panel_areas: None,
+        }
+    }
+
+    /// Lists sessions and reads the presets in the background, each reporting back with an event
+    fn start_loading(&mut self) {
+        self.state.load_state = LoadState::Loading;
+        self.awaiting_sessions = true;
+        self.awaiting_presets = true;
+
+        let server = self.state.server.clone();
+        let tx = self.state.event_handler.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(AppEvent::SessionsUpdated(server.list_sessions_if_running()));
+        });
+
+        let sources = self.state.preset_sources.clone();
+        let tx = self.state.event_handler.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(AppEvent::PresetsLoaded(read_presets(&sources)));
+        });
+    }
+
+    fn on_sessions_loaded(&mut self, result: Result<Option<Vec<Session>>, TmuxError>) {
+        self.awaiting_sessions = false;
+        match result {
+            Ok(sessions) => {
+                self.state.server_running = sessions.is_some();
+                // For the next refresh to tell whether anything changed since, the server going
+                // away included
+                self.snapshot.sessions = sessions.clone();
+                let mut sessions = sessions.unwrap_or_default();
+                sessions.retain(|s| !is_trash(&s.name));
+                self.state.sessions = sessions;
+                self.state.mark_running();
+                self.state.mru.prune(&self.state.sessions);
+                // A session asked for on the command line is to be seen in the sessions menu
+                if self.initial_session.is_some()
+                    && let Some(ui) = &mut self.saved_ui
+                {
+                    ui.panel = None;
+                }
+                // Start out on the session asked for, or else the one left highlighted last
+                // time, or else the one muffin was opened from
+                let saved = self
+                    .saved_ui
+                    .as_ref()
+                    .and_then(|ui| ui.session.clone())
+                    .filter(|name| self.state.sessions.iter().any(|s| &s.name == name));
+                self.state.focus_session = self.initial_session.take().or(saved).or_else(|| {
+                    self.state
+                        .sessions
+                        .iter()
+                        .find(|s| s.is_current)
+                        .or_else(|| self.state.sessions.iter().find(|s| s.attached_count > 0))
+                        .map(|s| s.name.clone())
+                });
+                self.finish_loading();
+            }
+            Err(e) => self.state.load_state = LoadState::Failed(e.to_string()),
         }
     }
 
+    fn on_presets_loaded(&mut self, result: Result<(PresetList, Vec<Diagnostic>), String>) {
+        self.awaiting_presets = false;
+        match result {
+            Ok((presets, diagnostics)) => {
+                self.state.presets = presets;
+                self.state.diagnostics = diagnostics;
+                let saved = self
+                    .saved_ui
+                    .as_ref()
+                    .and_then(|ui| ui.preset.as_deref())
+                    .filter(|name| self.state.presets.get_by_name(name).is_some());
+                self.state.selected_preset = saved
+                    .map(String::from)
+                    .or_else(|| self.state.presets.get(0).map(|p| p.name.clone()));
+                self.state.mark_running();
+                self.finish_loading();
+            }
+            Err(e) => self.state.load_state = LoadState::Failed(e),
+        }
+    }
+
+    fn finish_loading(&mut self) {
+        // A failure sticks around until retried, even once the other load is done
+        if !self.awaiting_sessions
+            && !self.awaiting_presets
+            && self.state.load_state == LoadState::Loading
+        {
+            self.state.load_state = LoadState::Ready;
+        }
+    }
+
+    /// Records the config file the config was read from, for the about popup
+    pub fn with_config_path(mut self, path: Option<PathBuf>) -> Self {
+        self.state.config_path = path;
+        self
+    }
+
+    /// Binds the config file's key sequences, warning about those that wait on longer ones
+    pub fn with_keymap(mut self, keymap: Keymap) -> Self {
+        for conflict in keymap.conflicts() {
+            notify(&mut self.state, Level::Warn, conflict);
+        }
+        self.state.keymap = keymap;
+        self
+    }
+
+    /// Only lets a name be chosen from `panel`, stripped of everything but the list, for
+    /// [`App::pick`] to return. Where the UI was left last time isn't saved over.
+    pub fn picking(mut self, panel: Panel) -> Self {
+        self.state.picking = true;
+        self.state.focus = panel;
+        self.state.mode = panel.mode();
+        if let Some(ui) = &mut self.saved_ui {
+            ui.panel = None;
+        }
+        self
+    }
+
     /// runs the application's main loop until the user quits
-    pub async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), String> {
-        let active_index = self.state.sessions.iter().position(|s| s.attached);
-        self.state.selected_session = active_index;
-        self.state.selected_preset = if self.state.presets.is_empty() {
-            None
-        } else {
-            Some(0)
+    pub async fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<(), String> {
+        self.state.event_handler.listen();
+        let result = self.main_loop(terminal).await;
+        self.state.event_handler.shutdown().await;
+        result
+    }
+
+    /// Like [`App::run`], returning the name chosen, if any, once done [`App::picking`]
+    pub async fn pick<B: Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+    ) -> Result<Option<String>, String> {
+        self.run(terminal).await?;
+        Ok(self.state.picked.take())
+    }
+
+    async fn main_loop<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<(), String> {
+        self.start_loading();
+
+        let mut menus = Menus {
+            sessions: SessionsMenu::new(None),
+            presets: PresetsMenu::new(None),
+            create: CreateMenu::default(),
+            rename: RenameMenu::new(Target::Session),
+            delete: DeleteMenu::new(Target::Session),
+            rename_preset: RenameMenu::new(Target::Preset),
+            delete_preset: DeleteMenu::new(Target::Preset),
+            new_window: NewWindowMenu::default(),
+            panes: PanesMenu::default(),
+            note: NoteMenu::default(),
+            plan: PlanMenu::default(),
+            diff: DiffMenu::default(),
+            append: AppendMenu::default(),
+            vars: VarsMenu::default(),
+            adjust: AdjustMenu::default(),
+            diagnostics: DiagnosticsMenu::default(),
+            kill_server: KillServerMenu::default(),
+            conflict: ConflictMenu::default(),
+            not_found: NotFoundMenu::default(),
+            palette: PaletteMenu::default(),
+            about: AboutMenu::default(),
         };
+        menus.get(&self.state.mode).on_enter(&self.state);
 
-        let mut create_menu = CreateMenu::default();
-        let mut rename_menu = RenameMenu::default();
-        let mut delete_menu = DeleteMenu::default();
-        let mut sessions_menu = SessionsMenu::new(self.state.sessions.len(), active_index);
-        let mut presets_menu = PresetsMenu::new(active_index);
+        // Fingerprint of the state as last drawn, see `AppState::fingerprint`
+        let mut drawn = None;
+        // Set when something the fingerprint doesn't cover may have changed
+        let mut redraw = true;
+        let mut poll =
+            tokio::time::interval_at(tokio::time::Instant::now() + POLL_INTERVAL, POLL_INTERVAL);
+        poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
         while !self.state.exit {
-            match self.state.mode {
-                AppMode::Sessions => sessions_menu.pre_render(&mut self.state),
-                AppMode::Create => create_menu.pre_render(&mut self.state),
-                AppMode::Rename => rename_menu.pre_render(&mut self.state),
-                AppMode::Delete => delete_menu.pre_render(&mut self.state),
-                AppMode::Presets => presets_menu.pre_render(&mut self.state),
-            };
+            let width = terminal.size().map(|size| size.width).unwrap_or_default();
+            let min_width = self
+                .state
+                .config
+                .side_by_side_width
+                .unwrap_or(SIDE_BY_SIDE_WIDTH);
+            self.state.side_by_side = min_width > 0 && width >= min_width && !self.state.picking;
 
-            // Draw phase
-            terminal
-                .draw(|frame| {
-                    let area = frame.area();
+            let ready = self.state.load_state == LoadState::Ready;
+            if ready && let Some(ui) = self.saved_ui.take() {
+                self.restore_ui(&ui, &mut menus);
+            }
+            if ready {
+                // Both panels are on screen, and either can change while the other has the keys
+                if self.state.side_by_side {
+                    menus.sessions.pre_render(&mut self.state);
+                    menus.presets.pre_render(&mut self.state);
+                }
+                if !self.state.side_by_side
+                    || !matches!(self.state.mode, AppMode::Sessions | AppMode::Presets)
+                {
+                    menus.get(&self.state.mode).pre_render(&mut self.state);
+                }
+            }
 
-                    // unconditionally render sessions menu
-                    frame.render_stateful_widget(&mut sessions_menu, area, &mut self.state);
+            // Draw phase, skipped when it would come out the same as last time
+            let fingerprint = self.state.fingerprint();
+            if redraw || drawn != Some(fingerprint) {
+                self.draw(terminal, &mut menus, ready)?;
+                drawn = Some(fingerprint);
+                redraw = false;
+            }
 
-                    match self.state.mode {
-                        AppMode::Create => {
-                            frame.render_stateful_widget(&mut create_menu, area, &mut self.state)
-                        }
-                        AppMode::Rename => {
-                            frame.render_stateful_widget(&mut rename_menu, area, &mut self.state)
-                        }
-                        AppMode::Delete => {
-                            frame.render_stateful_widget(&mut delete_menu, area, &mut self.state)
-                        }
-                        AppMode::Sessions => {} // Nothing extra to draw
-                        AppMode::Presets => {
-                            frame.render_stateful_widget(&mut presets_menu, area, &mut self.state)
-                        }
+            // Get next event, reporting on finished preset startups in the meantime
+            let chord_deadline = self.state.keymap.deadline();
+            let event = tokio::select! {
+                event = self.state.event_handler.next() => {
+                    event.map_err(|_| "Error with event handler!".to_string())?
+                }
+                Some(result) = self.state.startups.join_next() => {
+                    let warnings = result.unwrap_or_default();
+                    if !warnings.is_empty() {
+                        notify(&mut self.state, Level::Warn, warnings.join("; "));
                     }
-                })
-                .map_err(|_| "Terminal rendering error".to_string())?;
+                    continue;
+                }
+                Some(id) = self.state.trash.next_expired() => {
+                    if let Err(e) = self.state.trash.expire(&self.state.server, &id) {
+                        notify(&mut self.state, Level::Error, e);
+                    }
+                    self.refresh_sessions().await?;
+                    continue;
+                }
+                _ = tokio::time::sleep_until(
+                    chord_deadline.map_or_else(tokio::time::Instant::now, Into::into)
+                ), if chord_deadline.is_some() => AppEvent::ChordTimeout,
+                _ = poll.tick() => {
+                    if ready {
+                        self.poll_sessions().await?;
+                    }
+                    continue;
+                }
+            };
 
-            // Get next event
-            let event = self
-                .state
-                .event_handler
-                .next()
-                .await
-                .map_err(|_| "Error with event handler!".to_string())?;
+            // Resizes and regained focus can leave the screen stale without changing anything
+            if matches!(event, AppEvent::Redraw) {
+                redraw = true;
+            }
 
+            // Quitting is all there is to it, nothing needs refreshing on the way out
             if matches!(event, AppEvent::Key(KeyEvent { modifiers, code, .. })
                 if modifiers == KeyModifiers::CONTROL
                 && code == KeyCode::Char('c'))
             {
                 self.state.exit = true;
+                continue;
+            }
+
+            // Startup results are the driver's business, and until they're in, so are keys
+            match event {
+                AppEvent::SessionsUpdated(result) => {
+                    self.on_sessions_loaded(result);
+                    continue;
+                }
+                AppEvent::PresetsLoaded(result) => {
+                    self.on_presets_loaded(result);
+                    continue;
+                }
+                AppEvent::ExpireNotifications => {
+                    self.state.notifications.expire();
+                    continue;
+                }
+                // An error stays up until it's been seen, so the key only dismisses it
+                AppEvent::Key(_) if ready && self.state.notifications.has_errors() => {
+                    self.state.notifications.dismiss_errors();
+                    continue;
+                }
+                AppEvent::Key(key_event) if !ready => {
+                    match key_event.code {
+                        KeyCode::Char('r')
+                            if matches!(self.state.load_state, LoadState::Failed(_)) =>
+                        {
+                            self.start_loading()
+                        }
+                        KeyCode::Char('q') | KeyCode::Esc => self.state.exit = true,
+                        _ => {}
+                    }
+                    continue;
+                }
+                _ if !ready => continue,
+                _ => {}
             }
 
-            // Handle said event
-            // TODO: This looks stupid
-            match self.state.mode {
-                AppMode::Sessions => sessions_menu.handle_event(event, &mut self.state),
-                AppMode::Create => create_menu.handle_event(event, &mut self.state),
-                AppMode::Rename => rename_menu.handle_event(event, &mut self.state),
-                AppMode::Delete => delete_menu.handle_event(event, &mut self.state),
-                AppMode::Presets => presets_menu.handle_event(event, &mut self.state),
+            // Clicking or scrolling the panel without the keys gives it them, and the mouse event
+            if let AppEvent::Mouse(mouse) = &event
+                && let Some((left, right)) = self.panel_areas
+                && matches!(self.state.mode, AppMode::Sessions | AppMode::Presets)
+            {
+                let position = Position::new(mouse.column, mouse.row);
+                let clicked = if left.contains(position) {
+                    Panel::Sessions
+                } else if right.contains(position) {
+                    Panel::Presets
+                } else {
+                    self.state.focus
+                };
+                if clicked != self.state.focus {
+                    menus.get(&self.state.mode).on_exit();
+                    self.state.focus = clicked;
+                    self.state.mode = clicked.mode();
+                    menus.get(&self.state.mode).on_enter(&self.state);
+                }
+            }
+
+            // Keys of the sessions and presets menus go through the keymap first, coming back as
+            // the actions they're bound to or as keys to handle as usual
+            redraw = true;
+            let dispatches = match event {
+                AppEvent::Key(key_event) if self.uses_keymap(&mut menus) => {
+                    self.state.keymap.press(key_event, Instant::now())
+                }
+                AppEvent::ChordTimeout => self.state.keymap.expire(Instant::now()),
+                event => {
+                    self.handle_event(event, &mut menus).await?;
+                    continue;
+                }
             };
+            for dispatch in dispatches {
+                match dispatch {
+                    Dispatch::Run(id) => {
+                        let previous_mode = self.state.mode.clone();
+                        run_bound(id, &mut self.state);
+                        self.on_mode_change(&previous_mode, &mut menus);
+                    }
+                    Dispatch::Pass(key_event) => {
+                        self.handle_event(AppEvent::Key(key_event), &mut menus)
+                            .await?
+                    }
+                }
+            }
+        }
+
+        // Quitting before everything loaded leaves nothing worth saving over last time's
+        if self.saved_ui.is_none() && !self.state.picking {
+            self.snapshot_ui(&menus).save(&self.state.server);
+        }
+        Ok(())
+    }
 
-            // Refresh tmux sessions on each keystroke
-            self.state.sessions = tmux::list_sessions()?;
+    /// Hands `event` to the menu that's up, launches reporting back to the presets menu whichever
+    /// it is
+    async fn handle_event(&mut self, event: AppEvent, menus: &mut Menus<'_>) -> Result<(), String> {
+        let previous_mode = self.state.mode.clone();
+        let menu: &mut dyn Menu = match event {
+            AppEvent::SpawnProgress { .. } | AppEvent::SpawnFinished { .. } => &mut menus.presets,
+            _ => menus.get(&previous_mode),
+        };
+        let handled = menu.handle_event(event, &mut self.state);
+        let mode_changed = self.on_mode_change(&previous_mode, menus);
 
-            // TODO: This hurts the time complexity part of my brain. Fix it?
-            for preset in self.state.presets.values_mut() {
-                preset.running = false;
+        // Refresh tmux sessions after anything but moving around, which the poll catches up on
+        if handled.needs_refresh || mode_changed {
+            self.refresh_sessions().await?;
+        }
+        Ok(())
+    }
+
+    /// Lets menus reset their transient state if the mode changed from `previous_mode`, and moves
+    /// the focus along with it, returning whether it did
+    fn on_mode_change(&mut self, previous_mode: &AppMode, menus: &mut Menus) -> bool {
+        let mode_changed = self.state.mode != *previous_mode;
+        if mode_changed {
+            if let Some(panel) = self.state.mode.panel() {
+                self.state.focus = panel;
             }
+            menus.get(previous_mode).on_exit();
+            menus.get(&self.state.mode).on_enter(&self.state);
+        }
+        mode_changed
+    }
 
-            // Required to update which presets are running and which are dead
-            // Fortunately, this uses a BTreeMap now so it's not as bad as a regular Vec<Preset>
-            for session in self.state.sessions.iter() {
-                if let Some(v) = self.state.presets.get_mut(&session.name) {
-                    v.running = true;
+    /// Whether keys go through the keymap before the menu: in the sessions and presets menus,
+    /// unless they're typed into a field or only pick a name
+    fn uses_keymap(&self, menus: &mut Menus) -> bool {
+        matches!(self.state.mode, AppMode::Sessions | AppMode::Presets)
+            && !self.state.picking
+            && !menus.get(&self.state.mode).takes_text()
+    }
+
+    /// Goes back to where the UI was left last time, as far as that still makes sense. The
+    /// highlighted session and preset were already picked when they were loaded.
+    fn restore_ui(&mut self, ui: &UiState, menus: &mut Menus) {
+        self.state.following = ui.follow.unwrap_or_default();
+        menus.sessions.restore(ui, &self.state);
+        menus.presets.restore(ui, &self.state);
+        if ui.panel == Some(Panel::Presets) {
+            menus.get(&self.state.mode).on_exit();
+            self.state.focus = Panel::Presets;
+            self.state.mode = AppMode::Presets;
+            menus.get(&self.state.mode).on_enter(&self.state);
+        }
+    }
+
+    fn snapshot_ui(&self, menus: &Menus) -> UiState {
+        let mut ui = UiState {
+            panel: Some(self.state.focus),
+            session: self
+                .state
+                .selected_session
+                .and_then(|index| self.state.sessions.get(index))
+                .map(|s| s.name.clone()),
+            preset: self.state.selected_preset.clone(),
+            follow: Some(self.state.following),
+            ..Default::default()
+        };
+        menus.sessions.snapshot(&mut ui);
+        menus.presets.snapshot(&mut ui);
+        ui
+    }
+
+    fn draw<B: Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        menus: &mut Menus,
+        ready: bool,
+    ) -> Result<(), String> {
+        terminal
+            .draw(|frame| {
+                // Everything below the header is the menus' area, popups are centered in it
+                let header_height = if self.state.popup || self.state.picking {
+                    0
+                } else {
+                    1
+                };
+                let [header_area, area] =
+                    Layout::vertical([Constraint::Length(header_height), Constraint::Fill(1)])
+                        .areas(frame.area());
+                render_header(header_area, frame.buffer_mut(), &self.state);
+
+                if !ready {
+                    render_loading(
+                        area,
+                        frame.buffer_mut(),
+                        &self.state.load_state,
+                        self.state.theme,
+                    );
+                    return;
                 }
+
+                if self.state.side_by_side {
+                    let [left, right] =
+                        Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(area);
+                    frame.render_stateful_widget(&mut menus.sessions, left, &mut self.state);
+                    frame.render_stateful_widget(&mut menus.presets, right, &mut self.state);
+                    let unfocused = match self.state.focus {
+                        Panel::Sessions => right,
+                        Panel::Presets => left,
+                    };
+                    frame
+                        .buffer_mut()
+                        .set_style(unfocused, self.state.theme.dim);
+                    self.panel_areas = Some((left, right));
+                } else {
+                    // The sessions menu is always underneath, covered by the presets' if it's up
+                    frame.render_stateful_widget(&mut menus.sessions, area, &mut self.state);
+                    if self.state.focus == Panel::Presets {
+                        frame.render_stateful_widget(&mut menus.presets, area, &mut self.state);
+                    }
+                    self.panel_areas = None;
+                }
+
+                // Popups go over the middle of the screen, whichever panel they're for
+                match self.state.mode {
+                    AppMode::Sessions | AppMode::Presets => {} // Nothing extra to draw
+                    AppMode::Create => {
+                        frame.render_stateful_widget(&mut menus.create, area, &mut self.state)
+                    }
+                    AppMode::Rename => {
+                        frame.render_stateful_widget(&mut menus.rename, area, &mut self.state)
+                    }
+                    AppMode::Delete => {
+                        frame.render_stateful_widget(&mut menus.delete, area, &mut self.state)
+                    }
+                    AppMode::NewWindow => {
+                        frame.render_stateful_widget(&mut menus.new_window, area, &mut self.state)
+                    }
+                    AppMode::Panes => {
+                        frame.render_stateful_widget(&mut menus.panes, area, &mut self.state)
+                    }
+                    AppMode::Note => {
+                        frame.render_stateful_widget(&mut menus.note, area, &mut self.state)
+                    }
+                    AppMode::RenamePreset => frame.render_stateful_widget(
+                        &mut menus.rename_preset,
+                        area,
+                        &mut self.state,
+                    ),
+                    AppMode::DeletePreset => frame.render_stateful_widget(
+                        &mut menus.delete_preset,
+                        area,
+                        &mut self.state,
+                    ),
+                    AppMode::Plan => {
+                        frame.render_stateful_widget(&mut menus.plan, area, &mut self.state)
+                    }
+                    AppMode::Diff => {
+                        frame.render_stateful_widget(&mut menus.diff, area, &mut self.state)
+                    }
+                    AppMode::Append => {
+                        frame.render_stateful_widget(&mut menus.append, area, &mut self.state)
+                    }
+                    AppMode::Vars => {
+                        frame.render_stateful_widget(&mut menus.vars, area, &mut self.state)
+                    }
+                    AppMode::Adjust => {
+                        frame.render_stateful_widget(&mut menus.adjust, area, &mut self.state)
+                    }
+                    AppMode::Diagnostics => {
+                        frame.render_stateful_widget(&mut menus.diagnostics, area, &mut self.state)
+                    }
+                    AppMode::KillServer => {
+                        frame.render_stateful_widget(&mut menus.kill_server, area, &mut self.state)
+                    }
+                    AppMode::Conflict => {
+                        frame.render_stateful_widget(&mut menus.conflict, area, &mut self.state)
+                    }
+                    AppMode::NotFound => {
+                        frame.render_stateful_widget(&mut menus.not_found, area, &mut self.state)
+                    }
+                    AppMode::Palette => {
+                        frame.render_stateful_widget(&mut menus.palette, area, &mut self.state)
+                    }
+                    AppMode::About => {
+                        frame.render_stateful_widget(&mut menus.about, area, &mut self.state)
+                    }
+                }
+            })
+            .map_err(|_| "Terminal rendering error".to_string())?;
+        Ok(())
+    }
+
+    /// [`App::refresh_sessions`] on the clock, telling about the server having stopped in the
+    /// meantime
+    async fn poll_sessions(&mut self) -> Result<(), String> {
+        let was_running = self.state.server_running;
+        self.refresh_sessions().await?;
+        // Nothing muffin did, or it would have been refreshed already
+        if was_running && !self.state.server_running {
+            let msg = format!("The tmux server ({}) stopped", self.state.server);
+            notify(&mut self.state, Level::Error, msg);
+        }
+        Ok(())
+    }
+
+    /// Lists tmux's sessions again, noticing the server going away or coming back (anything
+    /// that starts a session starts it too), and leaving out the ones in the trash.
+    ///
+    /// This runs after most keys and every [`POLL_INTERVAL`], and most of the time tmux lists the
+    /// same as last time, so nothing past listing is redone unless something changed.
+    ///
+    /// tmux failing (not answering in time, ssh losing the connection, output muffin can't make
+    /// sense of) is only reported, and the next few refreshes are skipped, more of them the
+    /// longer it keeps up. Listing happens on a blocking thread, so the events and
+    /// startups in flight aren't held up waiting for it.
+    async fn refresh_sessions(&mut self) -> Result<(), String> {
+        if self.skipped_refreshes > 0 {
+            self.skipped_refreshes -= 1;
+            return Ok(());
+        }
+        let server = self.state.server.clone();
+        let sockets = preset_sockets(&self.state.presets);
+        let (sessions, preset_sockets) = tokio::task::spawn_blocking(move || {
+            let sessions = server.list_sessions_if_running();
+            // Not worth asking the other servers when this one didn't answer
+            let preset_sockets = match sessions {
+                Ok(_) => list_sockets(&server, sockets),
+                Err(_) => HashMap::new(),
+            };
+            (sessions, preset_sockets)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        let sessions = match sessions {
+            Ok(sessions) => sessions,
+            Err(e) => {
+                self.failures += 1;
+                self.skipped_refreshes = (1 << self.failures.min(4)) - 1;
+                notify(&mut self.state, Level::Warn, e.to_string());
+                return Ok(());
             }
+        };
+        self.failures = 0;
+
+        if self.state.config.auto_name_sessions
+            && let Some(sessions) = &sessions
+        {
+            self.auto_name(sessions);
         }
 
+        let snapshot = SessionSnapshot {
+            sessions,
+            preset_sockets,
+        };
+        if snapshot == self.snapshot {
+            return Ok(());
+        }
+
+        self.state.server_running = snapshot.sessions.is_some();
+        self.state.sessions = snapshot.sessions.clone().unwrap_or_default();
+        self.state.sessions.retain(|s| !is_trash(&s.name));
+        self.state.preset_sockets = snapshot.preset_sockets.clone();
+
+        // Required to update which presets are running and which are dead
+        mark_running_presets(
+            &mut self.state.presets,
+            &mut self.state.sessions,
+            &self.state.preset_sockets,
+        );
+        self.snapshot = snapshot;
         Ok(())
     }
+
+    /// Renames the sessions [`AutoNamer`] says to, which the next refresh picks up
+    fn auto_name(&mut self, sessions: &[Session]) {
+        let sessions = sessions
+            .iter()
+            .filter(|s| !is_trash(&s.name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let server = self.state.server.clone();
+        for change in self.auto_namer.update(&sessions) {
+            let result = match &change {
+                AutoName::Rename { session, name } => server
+                    .rename_session(session, name)
+                    .and_then(|_| server.set_auto_name(session, Some(name))),
+                AutoName::Release { session } => server.set_auto_name(session, None),
+            };
+            if let Err(e) = result {
+                notify(&mut self.state, Level::Warn, e.to_string());
+            }
+        }
+    }
+
+    /// The session on another server that was launched on the way out, for attaching to once the
+    /// terminal is given back
+    pub fn take_attach(&mut self) -> Option<(Server, String)> {
+        self.state.attach.take()
+    }
+
+    /// Kills the sessions still in the trash, as nobody is left to undo their deletion once the
+    /// app exited
+    pub fn empty_trash(&mut self) -> Vec<String> {
+        self.state.trash.flush(&self.state.server)
+    }
+
+    /// Waits for launched presets to finish sending their pane commands, returning the warnings
+    /// of those still running when the app exited
+    pub async fn finish_startups(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.state.startups)
+            .join_all()
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing::{Recorder, TestServer, app};
+    use ratatui::backend::{CrosstermBackend, TestBackend};
+    use ratatui::{TerminalOptions, Viewport};
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+
+    /// Runs `app` on a test terminal through `events`, all sent up front, and quits
+    async fn run(app: &mut App, events: Vec<AppEvent>) -> Terminal<TestBackend> {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        run_on(app, &mut terminal, events).await;
+        terminal
+    }
+
+    /// [`run`] on a given terminal
+    async fn run_on<B: Backend>(app: &mut App, terminal: &mut Terminal<B>, events: Vec<AppEvent>) {
+        let quit = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        // Or else a second run would be over before it starts
+        app.state.exit = false;
+        for event in events.into_iter().chain([AppEvent::Key(quit)]) {
+            app.state.event_handler.tx.send(event).unwrap();
+        }
+        app.main_loop(terminal).await.unwrap();
+    }
+
+    /// Everything written to a terminal, to look at once it's done
+    #[derive(Default, Clone)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// What loading `server`'s sessions and no presets comes back with
+    fn loaded(server: &TestServer) -> Vec<AppEvent> {
+        vec![
+            AppEvent::SessionsUpdated(server.server.list_sessions_if_running()),
+            AppEvent::PresetsLoaded(read_presets(&[Source::Inline(String::new())])),
+        ]
+    }
+
+    /// Key presses of `keys`, each a character or `<esc>`
+    fn keys(keys: &str) -> Vec<AppEvent> {
+        let mut events = vec![];
+        for part in keys.split_inclusive('>') {
+            let (typed, esc) = match part.strip_suffix("<esc>") {
+                Some(typed) => (typed, true),
+                None => (part, false),
+            };
+            let codes = typed.chars().map(KeyCode::Char);
+            for code in codes.chain(esc.then_some(KeyCode::Esc)) {
+                events.push(AppEvent::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+            }
+        }
+        events
+    }
+
+    /// Everything on `terminal`'s screen, row after row
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    fn alive_tasks() -> usize {
+        tokio::runtime::Handle::current()
+            .metrics()
+            .num_alive_tasks()
+    }
+
+    #[tokio::test]
+    async fn event_handlers_leave_no_tasks_behind() {
+        // A terminal that never has anything to say, so only stopping the reader ends it
+        let idle = || {
+            let mut handler = EventHandler::new();
+            handler.read(futures::stream::pending());
+            handler
+        };
+        let before = alive_tasks();
+        for _ in 0..20 {
+            let mut handler = idle();
+            tokio::time::timeout(Duration::from_secs(1), handler.shutdown())
+                .await
+                .expect("the reader didn't stop");
+        }
+        for _ in 0..20 {
+            drop(idle());
+        }
+        // Aborted tasks are only cleaned up once the runtime gets to them
+        tokio::task::yield_now().await;
+        assert_eq!(alive_tasks(), before);
+    }
+
+    #[tokio::test]
+    async fn refreshes_leave_no_tasks_behind() {
+        let server = TestServer::new("refresh-tasks");
+        server.create("a");
+        let mut app = app(&server);
+        app.refresh_sessions().await.unwrap();
+        let before = alive_tasks();
+        for _ in 0..20 {
+            app.refresh_sessions().await.unwrap();
+        }
+        assert_eq!(alive_tasks(), before);
+        assert_eq!(app.state.sessions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unchanged_state_isnt_drawn_again() {
+        let quiet = TestServer::new("redraw-quiet");
+        quiet.create("a");
+        let drawn = run(&mut app(&quiet), loaded(&quiet))
+            .await
+            .get_frame()
+            .count();
+
+        let busy = TestServer::new("redraw-busy");
+        busy.create("a");
+        let mut events = loaded(&busy);
+        for _ in 0..50 {
+            events.push(AppEvent::SessionsUpdated(
+                busy.server.list_sessions_if_running(),
+            ));
+            events.push(AppEvent::ExpireNotifications);
+        }
+        let frames = run(&mut app(&busy), events).await.get_frame().count();
+        assert_eq!(frames, drawn);
+
+        // Unless asked to, as after a resize
+        let mut events = loaded(&busy);
+        events.push(AppEvent::Redraw);
+        let frames = run(&mut app(&busy), events).await.get_frame().count();
+        assert_eq!(frames, drawn + 1);
+    }
+
+    #[tokio::test]
+    async fn moving_around_doesnt_list_sessions() {
+        let server = TestServer::new("moving");
+        for name in ["a", "b", "c"] {
+            server.create(name);
+        }
+        let mut app = app(&server);
+        let mut events = loaded(&server);
+        let listed = server.count("list-sessions");
+        let down = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+        events.extend((0..100).map(|_| AppEvent::Key(down)));
+        run(&mut app, events).await;
+
+        // Loading lists them once more in the background, whether or not it's done by now
+        assert!(server.count("list-sessions") - listed <= 1);
+        assert_ne!(app.state.selected_session, Some(0));
+    }
+
+    #[tokio::test]
+    async fn sessions_renamed_elsewhere_stay_selected() {
+        let server = TestServer::new("renamed");
+        server.create("a");
+        let b = server.create("b");
+        server.create("c");
+        let mut app = app(&server);
+        app.state.mru.touch(&b).unwrap();
+
+        // Down onto `b`, which another client then renames before the next refresh
+        let mut events = loaded(&server);
+        let down = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+        events.push(AppEvent::Key(down));
+        server.server.rename_session("b", "z").unwrap();
+        events.push(AppEvent::SessionsUpdated(
+            server.server.list_sessions_if_running(),
+        ));
+        run(&mut app, events).await;
+
+        let selected = app
+            .state
+            .selected_session
+            .and_then(|idx| app.state.sessions.get(idx))
+            .unwrap();
+        assert_eq!(selected.id, b.id);
+        assert_eq!(selected.name, "z");
+        assert_eq!(app.state.mru.rank(selected), Some(0));
+    }
+
+    #[tokio::test]
+    async fn server_going_away_and_coming_back() {
+        let server = TestServer::new("server-exit");
+        let presets = r#"session name="api" cwd="/tmp""#;
+        let api = read_presets(&[Source::Inline(presets.to_string())])
+            .unwrap()
+            .0
+            .get_by_name("api")
+            .unwrap()
+            .clone();
+        server.server.spawn_preset(&api).unwrap();
+        server.create("scratch");
+        let mut app = App::new(
+            vec![Source::Inline(presets.to_string())],
+            false,
+            false,
+            Config::default(),
+            Theme::from_config(None).unwrap(),
+            server.server.clone(),
+            None,
+        );
+        let loaded = || {
+            let mut events = loaded(&server);
+            events[1] =
+                AppEvent::PresetsLoaded(read_presets(&[Source::Inline(presets.to_string())]));
+            events
+        };
+        run(&mut app, loaded()).await;
+        assert!(app.state.server_running);
+        assert_eq!(app.state.sessions.len(), 2);
+        assert!(app.state.presets.get_by_name("api").unwrap().running);
+
+        // Gone from under muffin, which finds out on the next refresh
+        server.server.kill_server().unwrap();
+        app.refresh_sessions().await.unwrap();
+        assert!(!app.state.server_running);
+        let terminal = run(&mut app, loaded()).await;
+        assert!(!app.state.server_running);
+        assert!(app.state.sessions.is_empty());
+        assert_eq!(app.state.selected_session, None);
+        assert!(!app.state.presets.get_by_name("api").unwrap().running);
+        let screen = screen(&terminal);
+        assert!(screen.contains("tmux server not running"), "{screen}");
+        assert!(app.state.notifications.is_empty());
+
+        // Anything creating a session starts it again
+        server.create("back");
+        app.refresh_sessions().await.unwrap();
+        assert!(app.state.server_running);
+        let names = app
+            .state
+            .sessions
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["back"]);
+    }
+
+    #[tokio::test]
+    async fn the_server_stopping_under_muffin_is_reported() {
+        let server = TestServer::new("server-stopped");
+        server.create("scratch");
+        let mut app = app(&server);
+        let desktop = Recorder::default();
+        app.state.desktop = Some(Box::new(desktop.clone()));
+        run(&mut app, loaded(&server)).await;
+        app.poll_sessions().await.unwrap();
+        assert!(app.state.notifications.is_empty());
+
+        server.server.kill_server().unwrap();
+        app.poll_sessions().await.unwrap();
+        let msg = format!("The tmux server ({}) stopped", server.server);
+        assert_eq!(desktop.take(), [(Level::Error, msg)]);
+        assert!(app.state.notifications.has_errors());
+
+        // Only the once
+        app.poll_sessions().await.unwrap();
+        assert_eq!(desktop.take(), []);
+    }
+
+    #[tokio::test]
+    async fn no_color_draws_without_any_colors() {
+        let server = TestServer::new("no-color");
+        let presets = r#"session name="api" cwd="/tmp" color="red""#;
+        let api = read_presets(&[Source::Inline(presets.to_string())])
+            .unwrap()
+            .0
+            .get_by_name("api")
+            .unwrap()
+            .clone();
+        server.server.spawn_preset(&api).unwrap();
+        server.create("scratch");
+        let theme = Theme::resolve(Some("high-contrast"), Some("1".into())).unwrap();
+        let mut app = App::new(
+            vec![Source::Inline(presets.to_string())],
+            false,
+            false,
+            Config::default(),
+            theme,
+            server.server.clone(),
+            None,
+        );
+        let mut events = loaded(&server);
+        events[1] = AppEvent::PresetsLoaded(read_presets(&[Source::Inline(presets.to_string())]));
+        // Both menus and a popup
+        for key in [KeyCode::Tab, KeyCode::Tab, KeyCode::Char('a')] {
+            events.push(AppEvent::Key(KeyEvent::new(key, KeyModifiers::NONE)));
+        }
+        let output = Output::default();
+        let backend = CrosstermBackend::new(output.clone());
+        let viewport = Viewport::Fixed(Rect::new(0, 0, 80, 24));
+        let mut terminal = Terminal::with_options(backend, TerminalOptions { viewport }).unwrap();
+        run_on(&mut app, &mut terminal, events).await;
+
+        let output = String::from_utf8(output.0.take()).unwrap();
+        assert!(output.contains("api") && output.contains("scratch"));
+        // Every SGR sequence, none of which may set a color other than the default one
+        let mut sgr = 0;
+        for sequence in output.split("\x1b[").skip(1) {
+            let Some(end) = sequence.find(|c: char| !c.is_ascii_digit() && c != ';') else {
+                continue;
+            };
+            if !sequence[end..].starts_with('m') {
+                continue;
+            }
+            sgr += 1;
+            for param in sequence[..end].split(';') {
+                let param = param.parse::<u8>().unwrap_or_default();
+                assert!(
+                    !matches!(param, 30..=38 | 40..=48 | 90..=97 | 100..=107),
+                    "colored with `\\x1b[{}m`",
+                    &sequence[..end]
+                );
+            }
+        }
+        // Still bold or reversed where color would tell things apart
+        assert!(sgr > 0);
+    }
+
+    #[tokio::test]
+    async fn popups_open_empty_every_time() {
+        let server = TestServer::new("lifecycle");
+        for name in ["aaa", "bbb"] {
+            server.create(name);
+        }
+
+        // Left with Esc, then opened again
+        let mut events = loaded(&server);
+        events.extend(keys("ahalf<esc>a"));
+        let drawn = screen(&run(&mut app(&server), events).await);
+        assert!(drawn.contains("start typing!"), "{drawn}");
+
+        // Through the presets menu and back
+        let mut events = loaded(&server);
+        events.extend(keys("ahalf<esc>"));
+        events.extend(
+            [KeyCode::Tab, KeyCode::Tab]
+                .map(|code| AppEvent::Key(KeyEvent::new(code, KeyModifiers::NONE))),
+        );
+        events.extend(keys("a"));
+        let drawn = screen(&run(&mut app(&server), events).await);
+        assert!(drawn.contains("start typing!"), "{drawn}");
+
+        // Opened again on another session
+        let mut events = loaded(&server);
+        events.extend(keys("rxyz<esc>jr"));
+        let mut app = app(&server);
+        let drawn = screen(&run(&mut app, events).await);
+        assert!(drawn.contains("Rename session"), "{drawn}");
+        assert!(drawn.contains("start typing!"), "{drawn}");
+        assert!(matches!(app.state.mode, AppMode::Rename));
+    }
+
+    #[tokio::test]
+    async fn popups_come_up_over_the_panel_with_the_keys_and_go_back_to_it() {
+        let server = TestServer::new("focus");
+        server.create("aaa");
+        let mut app = app(&server);
+        let mut terminal = Terminal::new(TestBackend::new(160, 40)).unwrap();
+        let tab = || AppEvent::Key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+
+        let mut events = loaded(&server);
+        events.push(tab());
+        events.extend(keys(":"));
+        run_on(&mut app, &mut terminal, events).await;
+        assert!(app.state.side_by_side);
+        assert_eq!(app.state.mode, AppMode::Palette);
+        assert_eq!(app.state.focus, Panel::Presets);
+        assert!(app.state.is_focused(Panel::Presets));
+        assert!(!app.state.is_focused(Panel::Sessions));
+
+        run_on(&mut app, &mut terminal, keys("<esc>")).await;
+        assert_eq!(app.state.mode, AppMode::Presets);
+
+        // The sessions menu's popups take the keys with them
+        let mut events = vec![tab()];
+        events.extend(keys("a"));
+        run_on(&mut app, &mut terminal, events).await;
+        assert_eq!(app.state.mode, AppMode::Create);
+        assert_eq!(app.state.focus, Panel::Sessions);
+        run_on(&mut app, &mut terminal, keys("<esc>:<esc>")).await;
+        assert_eq!(app.state.mode, AppMode::Sessions);
+
+        // Clicking the other panel hands it the keys
+        let click = AppEvent::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: 120,
+            row: 10,
+            modifiers: KeyModifiers::NONE,
+        });
+        run_on(&mut app, &mut terminal, vec![click]).await;
+        assert_eq!(app.state.focus, Panel::Presets);
+        assert_eq!(app.state.mode, AppMode::Presets);
+    }
+
+    #[tokio::test]
+    async fn unsafe_mode_deletes_without_asking() {
+        let server = TestServer::new("unsafe");
+        for name in ["aaa", "bbb"] {
+            server.create(name);
+        }
+        let names = |app: &App| {
+            app.state
+                .sessions
+                .iter()
+                .map(|s| s.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // Asked first, as usual
+        let mut asking = app(&server);
+        let mut events = loaded(&server);
+        events.extend(keys("d"));
+        run(&mut asking, events).await;
+        assert!(matches!(asking.state.mode, AppMode::Delete));
+        assert_eq!(asking.state.trash.len(), 0);
+
+        let mut trusting = app(&server);
+        let mut events = loaded(&server);
+        events.extend(keys("!d"));
+        let renamed = server.count("rename-session");
+        run(&mut trusting, events).await;
+        assert!(trusting.state.unsafe_mode);
+        assert!(matches!(trusting.state.mode, AppMode::Sessions));
+        // Sent to the trash, `u` bringing it back
+        assert_eq!(server.count("rename-session"), renamed + 1);
+        assert_eq!(trusting.state.trash.len(), 1);
+        assert_eq!(names(&trusting), ["bbb"]);
+
+        // Toggled back off
+        let mut careful = app(&server);
+        let mut events = loaded(&server);
+        events.extend(keys("!!d"));
+        run(&mut careful, events).await;
+        assert!(!careful.state.unsafe_mode);
+        assert!(matches!(careful.state.mode, AppMode::Delete));
+        assert_eq!(careful.state.trash.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn unchanged_listings_cost_no_more_than_listing() {
+        let server = TestServer::new("refresh-cost");
+        for i in 0..20 {
+            server.create(&format!("s{i:02}"));
+        }
+        let mut app = app(&server);
+        app.refresh_sessions().await.unwrap();
+        // Only ever overwritten by a listing that changed
+        app.state.sessions[0].note = Some("untouched".to_string());
+
+        // Counted in tmux commands rather than time, which a loaded machine throws off
+        let before = server.commands();
+        server.server.list_sessions_if_running().unwrap();
+        let listing = server.commands() - before;
+        const RUNS: usize = 20;
+        let before = server.commands();
+        for _ in 0..RUNS {
+            app.refresh_sessions().await.unwrap();
+        }
+        assert_eq!(app.state.sessions[0].note.as_deref(), Some("untouched"));
+        // Listing is all there is to it
+        assert_eq!(server.commands() - before, RUNS * listing);
+
+        server.create("s20");
+        app.refresh_sessions().await.unwrap();
+        assert_eq!(app.state.sessions.len(), 21);
+        assert_eq!(app.state.sessions[0].note, None);
+    }
 }
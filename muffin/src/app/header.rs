@@ -0,0 +1,78 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::Widget,
+};
+
+use crate::app::driver::{AppMode, AppState};
+use crate::app::theme::Theme;
+
+/// What the header calls each mode, and the accent style of its popup (if it has one)
+fn describe_mode(mode: &AppMode, theme: &Theme) -> (&'static str, Option<Style>) {
+    match mode {
+        AppMode::Sessions => ("Sessions", None),
+        AppMode::Presets => ("Presets", None),
+        AppMode::Create => ("Create", Some(theme.create)),
+        AppMode::Rename => ("Rename", Some(theme.rename)),
+        AppMode::Delete => ("Delete", Some(theme.delete)),
+        AppMode::RenamePreset => ("Rename preset", Some(theme.rename)),
+        AppMode::DeletePreset => ("Delete preset", Some(theme.delete)),
+        AppMode::NewWindow => ("New window", Some(theme.create)),
+        AppMode::Panes => ("Panes", Some(theme.accent)),
+        AppMode::Note => ("Note", Some(theme.rename)),
+        AppMode::Plan => ("Plan", Some(theme.plan)),
+        AppMode::Diff => ("Drift", Some(theme.plan)),
+        AppMode::Append => ("Append preset", Some(theme.create)),
+        AppMode::Vars => ("Launch preset", Some(theme.create)),
+        AppMode::Adjust => ("Adjust panes", Some(theme.create)),
+        AppMode::Diagnostics => ("Problems", Some(theme.warn)),
+        AppMode::KillServer => ("Kill server", Some(theme.delete)),
+        AppMode::Conflict => ("Name taken", Some(theme.warn)),
+        AppMode::NotFound => ("Not found", Some(theme.warn)),
+        AppMode::Palette => ("Actions", Some(theme.accent)),
+        AppMode::About => ("About", Some(theme.accent)),
+    }
+}
+
+/// One-line breadcrumb above the menus: server and its tmux version, attached session, current
+/// mode, how many deleted sessions can still be restored and whether deleting asks first
+pub fn render_header(area: Rect, buf: &mut Buffer, state: &AppState) {
+    let attached = state
+        .sessions
+        .iter()
+        .find(|s| s.is_current)
+        .map(|s| s.name.as_str())
+        .unwrap_or("none");
+
+    let version = match state.server.tmux_version() {
+        Ok(version) => format!(" (tmux {version})"),
+        Err(_) => String::new(),
+    };
+
+    let (mode, accent) = describe_mode(&state.mode, state.theme);
+    let mode = Span::styled(mode, accent.unwrap_or_default()).bold();
+
+    let mut spans = vec![
+        "muffin".bold(),
+        format!(
+            " · server: {}{version} · attached: {attached} · ",
+            state.server
+        )
+        .into(),
+        mode,
+    ];
+    // Sessions in the trash are hidden from the list, but still alive
+    if state.trash.len() > 0 {
+        spans.push(format!(" · {} in trash", state.trash.len()).into());
+    }
+    if state.following {
+        spans.push(" · following".into());
+    }
+    if state.unsafe_mode {
+        spans.push(" · ".into());
+        spans.push(Span::styled("UNSAFE", state.theme.delete).bold());
+    }
+
+    Line::from(spans).style(state.theme.dim).render(area, buf);
+}
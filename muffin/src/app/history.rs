@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use tmux::Server;
+
+use crate::app::state::{read_names, state_path, write_names};
+
+/// How many names [`NameHistory`] keeps
+const HISTORY_LENGTH: usize = 20;
+
+/// Names of the sessions created through the create popup, most recent first, kept in a state
+/// file across runs like the [`Mru`](crate::app::mru::Mru) list
+#[derive(Debug, Default)]
+pub struct NameHistory {
+    names: Vec<String>,
+    /// Where the history is saved, if a state directory could be found
+    path: Option<PathBuf>,
+}
+
+impl NameHistory {
+    /// Reads the history saved for `server`. A missing or unreadable file is just an empty
+    /// history, overwritten on the next creation.
+    pub fn load(server: &Server) -> Self {
+        let path = state_path(server, "created");
+        let mut history = Self {
+            names: vec![],
+            path,
+        };
+        // Written by hand or by another version, the file may repeat itself or run long
+        for name in read_names(history.path.as_deref()).into_iter().rev() {
+            history.push(name);
+        }
+        history
+    }
+
+    /// Moves `name` to the front, or adds it there, and saves the history
+    pub fn record(&mut self, name: &str) -> Result<(), String> {
+        self.push(name.to_string());
+        write_names(self.path.as_deref(), self.names.iter().map(String::as_str))
+    }
+
+    /// The names starting with `prefix`, most recent first
+    pub fn matching<'h>(&'h self, prefix: &'h str) -> impl Iterator<Item = &'h str> {
+        self.names
+            .iter()
+            .map(String::as_str)
+            .filter(move |name| name.starts_with(prefix))
+    }
+
+    fn push(&mut self, name: String) {
+        self.names.retain(|n| *n != name);
+        self.names.insert(0, name);
+        self.names.truncate(HISTORY_LENGTH);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing::TestServer;
+
+    fn names(history: &NameHistory) -> Vec<&str> {
+        history.matching("").collect()
+    }
+
+    #[test]
+    fn recorded_names_go_first_once() {
+        let mut history = NameHistory::default();
+        for name in ["a", "b", "c", "a"] {
+            history.record(name).unwrap();
+        }
+        assert_eq!(names(&history), ["a", "c", "b"]);
+        history.record("b").unwrap();
+        assert_eq!(names(&history), ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn only_the_latest_are_kept() {
+        let mut history = NameHistory::default();
+        for i in 0..HISTORY_LENGTH + 5 {
+            history.record(&format!("s{i}")).unwrap();
+        }
+        let names = names(&history);
+        assert_eq!(names.len(), HISTORY_LENGTH);
+        assert_eq!(names[0], format!("s{}", HISTORY_LENGTH + 4));
+        assert_eq!(names[HISTORY_LENGTH - 1], "s5");
+    }
+
+    #[test]
+    fn matching_by_prefix() {
+        let mut history = NameHistory::default();
+        for name in ["work/api", "home", "work/web", "Work/old", "work"] {
+            history.record(name).unwrap();
+        }
+        assert_eq!(
+            history.matching("work").collect::<Vec<_>>(),
+            ["work", "work/web", "work/api"]
+        );
+        assert_eq!(history.matching("work/w").collect::<Vec<_>>(), ["work/web"]);
+        assert_eq!(history.matching("nope").count(), 0);
+        assert_eq!(history.matching("").count(), 5);
+    }
+
+    #[test]
+    fn saved_across_runs_without_repeats() {
+        let test = TestServer::new("history");
+        let path = state_path(&test.server, "created").unwrap();
+        // Written by hand, repeating itself
+        std::fs::write(&path, "b\na\n\nb\nc\n").unwrap();
+        let mut history = NameHistory::load(&test.server);
+        assert_eq!(names(&history), ["b", "a", "c"]);
+
+        history.record("c").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "c\nb\na\n");
+        assert_eq!(names(&NameHistory::load(&test.server)), ["c", "b", "a"]);
+        let _ = std::fs::remove_file(path);
+    }
+}
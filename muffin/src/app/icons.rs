@@ -0,0 +1,81 @@
+/// The set of glyphs used when rendering lists.
+///
+/// Nerd Font glyphs look nice, but render as tofu boxes on terminals without a patched font, so a
+/// plain ASCII fallback is always available.
+#[derive(Debug, Clone, Copy)]
+pub struct Icons {
+    /// Shown next to window counts
+    pub windows: &'static str,
+    /// Prefix of the highlighted list row
+    pub highlight: &'static str,
+    /// Marks the current session and running presets
+    pub running: &'static str,
+    /// Marks sessions attached by other clients
+    pub attached: &'static str,
+    /// Marks pinned presets
+    pub pinned: &'static str,
+    /// Prefixes warnings
+    pub warning: &'static str,
+    /// Badge of sessions with a bell in one of their windows
+    pub bell: &'static str,
+    /// Badge of sessions with activity in one of their windows
+    pub activity: &'static str,
+    /// Drawn in a preset's color before its name
+    pub dot: &'static str,
+    /// Prefix of a collapsed group of sessions
+    pub collapsed: &'static str,
+    /// Prefix of an expanded group of sessions
+    pub expanded: &'static str,
+    /// Marks sessions by the kind of project they're in, see
+    /// [`ProjectKind`](crate::app::project::ProjectKind)
+    pub rust: &'static str,
+    pub node: &'static str,
+    pub nix: &'static str,
+    pub git: &'static str,
+}
+
+pub const NERD_FONT: Icons = Icons {
+    windows: "\u{f2d2}",
+    highlight: "\u{f054} ",
+    running: "\u{f444}",
+    attached: "\u{f0c1}",
+    pinned: "\u{f005}",
+    warning: "\u{f071}",
+    bell: "\u{f0f3}",
+    activity: "\u{f0e7}",
+    dot: "\u{f111}",
+    collapsed: "\u{f0da}",
+    expanded: "\u{f0d7}",
+    rust: "\u{e7a8}",
+    node: "\u{e718}",
+    nix: "\u{f313}",
+    git: "\u{e702}",
+};
+
+pub const ASCII: Icons = Icons {
+    windows: "[w]",
+    highlight: "> ",
+    running: "*",
+    attached: "~",
+    pinned: "^",
+    warning: "!",
+    bell: "!",
+    activity: "~",
+    dot: "o",
+    collapsed: "+",
+    expanded: "-",
+    rust: "rs",
+    node: "js",
+    nix: "nx",
+    git: "git",
+};
+
+impl Icons {
+    /// Picks the icon set to use, honoring `MUFFIN_ASCII=1`
+    pub fn from_env() -> &'static Icons {
+        match std::env::var("MUFFIN_ASCII") {
+            Ok(value) if !value.is_empty() && value != "0" => &ASCII,
+            _ => &NERD_FONT,
+        }
+    }
+}
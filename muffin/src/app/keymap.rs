@@ -0,0 +1,637 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use parser::Config;
+use ratatui::{
+    style::Styled,
+    text::{Line, Span},
+};
+
+use crate::app::actions::{ACTIONS, ActionId};
+use crate::app::theme::Theme;
+
+/// How long a chord waits for its next key, unless configured otherwise
+pub const CHORD_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Keys the sessions and presets menus handle that no action of the [`ACTIONS`] registry stands
+/// for, along with what they do
+const MENU_KEYS: &[(&str, &str)] = &[
+    ("enter", "switching to a session or folding a group"),
+    ("space", "switching to a session or folding a group"),
+];
+
+/// A key as bindings name it: `g`, `G`, `space`, `ctrl+k`, `alt+enter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    /// Terminals send `G` as a shifted `g` or not depending on the protocol, the character says
+    /// it either way
+    fn from_event(key: &KeyEvent) -> Self {
+        let mut modifiers = key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if !matches!(key.code, KeyCode::Char(_)) {
+            modifiers |= key.modifiers & KeyModifiers::SHIFT;
+        }
+        Self {
+            code: key.code,
+            modifiers,
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        // A `+` with nothing after it is the key itself (`ctrl++`), not a modifier waiting for one
+        while let Some((modifier, key)) = rest.split_once('+').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match modifier {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier `{modifier}` in `{text}`")),
+            };
+            rest = key;
+        }
+        let code = match rest {
+            "space" => KeyCode::Char(' '),
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            key => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => KeyCode::Char(ch),
+                    _ => return Err(format!("unknown key `{key}` in `{text}`")),
+                }
+            }
+        };
+        if matches!(code, KeyCode::Char(_)) && modifiers.contains(KeyModifiers::SHIFT) {
+            return Err(format!(
+                "`{text}` is written as the shifted character instead"
+            ));
+        }
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(ch) => write!(f, "{ch}"),
+            KeyCode::Esc => f.write_str("esc"),
+            code => f.write_str(&code.to_string().to_lowercase().replace(' ', "")),
+        }
+    }
+}
+
+/// The keys of a binding, separated by spaces (ex: `g g`, `space p l`)
+fn parse_sequence(text: &str) -> Result<Vec<KeyPress>, String> {
+    let keys = text
+        .split_whitespace()
+        .map(KeyPress::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    match keys.is_empty() {
+        true => Err("a binding needs at least one key".to_string()),
+        false => Ok(keys),
+    }
+}
+
+fn show_sequence(keys: &[KeyPress]) -> String {
+    keys.iter()
+        .map(KeyPress::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What's left to do after a key went through the keymap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dispatch {
+    /// Carry out the action a sequence is bound to
+    Run(ActionId),
+    /// Hand the key to the menu as if there were no keymap
+    Pass(KeyEvent),
+}
+
+/// Sequences of keys bound to actions of the [`ACTIONS`] registry, from the config file's `keys`
+/// block.
+///
+/// The driver hands it the keys of the sessions and presets menus before they see them. Keys
+/// that aren't bound, or that turn out not to lead anywhere, are handed back to be handled as
+/// usual. A sequence that's both bound and the start of a longer one (`g` and `g g`) waits for
+/// the next key until the timeout; `esc` drops whatever was typed so far.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyPress>, ActionId)>,
+    timeout: Duration,
+    /// Keys typed so far of a sequence that isn't complete yet, and when the last one was
+    pending: Vec<(KeyPress, KeyEvent)>,
+    pressed_at: Option<Instant>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: vec![],
+            timeout: CHORD_TIMEOUT,
+            pending: vec![],
+            pressed_at: None,
+        }
+    }
+}
+
+impl Keymap {
+    /// Reads the config's bindings, turning down keys muffin doesn't know and actions that don't
+    /// exist
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut keymap = Self {
+            timeout: config
+                .chord_timeout_ms
+                .map_or(CHORD_TIMEOUT, Duration::from_millis),
+            ..Default::default()
+        };
+        for (keys, action) in &config.keys {
+            let sequence = parse_sequence(keys).map_err(|e| format!("In `keys`: {e}"))?;
+            let id = action_by_name(action).ok_or_else(|| {
+                let names = ACTIONS.iter().map(|a| a.name).collect::<Vec<_>>();
+                format!(
+                    "In `keys`: unknown action `{action}` for `{keys}`, expected `launch:<preset>` \
+                     or one of `{}`",
+                    names.join("`, `")
+                )
+            })?;
+            keymap.bind(sequence, id);
+        }
+        Ok(keymap)
+    }
+
+    /// Binds `keys` to `id`, in place of what they were bound to
+    fn bind(&mut self, keys: Vec<KeyPress>, id: ActionId) {
+        self.bindings.retain(|(bound, _)| *bound != keys);
+        self.bindings.push((keys, id));
+    }
+
+    /// Bindings, and the menus' own keys (those of the [`ACTIONS`] registry and [`MENU_KEYS`]),
+    /// that can only be reached by waiting out the timeout as they start a longer binding,
+    /// described for a warning
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = vec![];
+        for (keys, _) in &self.bindings {
+            if let Some(conflict) = self.conflict(format!("`{}`", show_sequence(keys)), keys) {
+                conflicts.push(conflict);
+            }
+        }
+
+        // A key doing several things is warned about once, with all of them
+        let mut built_in = Vec::<(KeyPress, Vec<&str>)>::new();
+        let actions = ACTIONS
+            .iter()
+            .filter_map(|action| Some((action.key?, action.name)));
+        for (key, does) in actions.chain(MENU_KEYS.iter().copied()) {
+            let Ok(key) = KeyPress::parse(key) else {
+                continue;
+            };
+            match built_in.iter_mut().find(|(other, _)| *other == key) {
+                Some((_, doing)) => doing.push(does),
+                None => built_in.push((key, vec![does])),
+            }
+        }
+        for (key, doing) in built_in {
+            // Bound over, it's already been warned about above
+            if self.bound(&[key]).is_some() {
+                continue;
+            }
+            let shown = format!("`{key}` ({})", doing.join(", "));
+            if let Some(conflict) = self.conflict(shown, &[key]) {
+                conflicts.push(conflict);
+            }
+        }
+        conflicts
+    }
+
+    /// The warning for `keys`, described as `shown`, if longer bindings start with them
+    fn conflict(&self, shown: String, keys: &[KeyPress]) -> Option<String> {
+        let longer = self
+            .bindings
+            .iter()
+            .filter(|(other, _)| other.len() > keys.len() && other.starts_with(keys))
+            .map(|(other, _)| format!("`{}`", show_sequence(other)))
+            .collect::<Vec<_>>();
+        Some(format!(
+            "{shown} also starts {}, so it waits {}ms for another key",
+            longer.join(", "),
+            self.timeout.as_millis(),
+        ))
+        .filter(|_| !longer.is_empty())
+    }
+
+    /// The keys typed so far of an unfinished sequence
+    pub fn pending(&self) -> Option<String> {
+        let keys = self.pending.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+        Some(show_sequence(&keys)).filter(|_| !keys.is_empty())
+    }
+
+    /// When the unfinished sequence gives up on its next key, see [`Keymap::expire`]
+    pub fn deadline(&self) -> Option<Instant> {
+        Some(self.pressed_at? + self.timeout).filter(|_| !self.pending.is_empty())
+    }
+
+    /// Takes `key`, pressed at `now`. Nothing comes back while it's part of a sequence that isn't
+    /// complete yet, or when it's `esc` dropping one.
+    pub fn press(&mut self, key: KeyEvent, now: Instant) -> Vec<Dispatch> {
+        if key.code == KeyCode::Esc && !self.pending.is_empty() {
+            self.clear();
+            return vec![];
+        }
+
+        let press = KeyPress::from_event(&key);
+        let mut sequence = self.pending.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+        sequence.push(press);
+
+        if self.continues(&sequence) {
+            self.pending.push((press, key));
+            self.pressed_at = Some(now);
+            return vec![];
+        }
+        if let Some(id) = self.bound(&sequence) {
+            self.clear();
+            return vec![Dispatch::Run(id)];
+        }
+        if self.pending.is_empty() {
+            return vec![Dispatch::Pass(key)];
+        }
+        // What was typed so far doesn't go on with this key, so it's done with, and the key
+        // starts over on its own
+        let mut dispatches = self.flush();
+        dispatches.extend(self.press(key, now));
+        dispatches
+    }
+
+    /// Gives up waiting on the next key once the timeout has passed by `now`, running what was
+    /// typed so far if it's bound and handing its keys back otherwise
+    pub fn expire(&mut self, now: Instant) -> Vec<Dispatch> {
+        match self.deadline() {
+            Some(deadline) if deadline <= now => self.flush(),
+            _ => vec![],
+        }
+    }
+
+    /// The line shown in place of the menu's instructions while a sequence is unfinished
+    pub fn pending_line(&self, theme: &Theme) -> Option<Line<'static>> {
+        let pending = self.pending()?;
+        Some(Line::from(vec![
+            pending.set_style(theme.accent),
+            Span::from(" …  esc:cancel").set_style(theme.dim),
+        ]))
+    }
+
+    fn flush(&mut self) -> Vec<Dispatch> {
+        let sequence = self.pending.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+        let dispatches = match self.bound(&sequence) {
+            Some(id) => vec![Dispatch::Run(id)],
+            None => self
+                .pending
+                .iter()
+                .map(|(_, key)| Dispatch::Pass(*key))
+                .collect(),
+        };
+        self.clear();
+        dispatches
+    }
+
+    fn clear(&mut self) {
+        self.pending.clear();
+        self.pressed_at = None;
+    }
+
+    fn bound(&self, sequence: &[KeyPress]) -> Option<ActionId> {
+        self.bindings
+            .iter()
+            .find(|(keys, _)| keys == sequence)
+            .map(|(_, id)| id.clone())
+    }
+
+    /// Whether a longer sequence starts with `sequence`
+    fn continues(&self, sequence: &[KeyPress]) -> bool {
+        self.bindings
+            .iter()
+            .any(|(keys, _)| keys.len() > sequence.len() && keys.starts_with(sequence))
+    }
+}
+
+/// The action bindings call `name`, or `launch:<preset>` for launching that preset
+fn action_by_name(name: &str) -> Option<ActionId> {
+    if let Some(preset) = name.strip_prefix("launch:") {
+        return Some(ActionId::Launch(preset.trim().to_string()));
+    }
+    ACTIONS
+        .iter()
+        .find(|action| action.name == name)
+        .map(|action| action.id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(keys: &[(&str, &str)]) -> Keymap {
+        let config = Config {
+            keys: keys
+                .iter()
+                .map(|(keys, action)| (keys.to_string(), action.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        Keymap::from_config(&config).unwrap()
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ch(ch: char) -> KeyEvent {
+        key(KeyCode::Char(ch))
+    }
+
+    #[test]
+    fn sequences_parse_and_show_back() {
+        for text in [
+            "g",
+            "g g",
+            "space p l",
+            "ctrl+k",
+            "alt+enter",
+            "ctrl++",
+            "shift+tab",
+        ] {
+            assert_eq!(show_sequence(&parse_sequence(text).unwrap()), text);
+        }
+        // Spaces between keys don't matter
+        assert_eq!(
+            parse_sequence("  g   g ").unwrap(),
+            parse_sequence("g g").unwrap()
+        );
+        let ctrl_k = parse_sequence("ctrl+k").unwrap();
+        assert_eq!(
+            ctrl_k,
+            [KeyPress::from_event(&KeyEvent::new(
+                KeyCode::Char('k'),
+                KeyModifiers::CONTROL
+            ))]
+        );
+    }
+
+    #[test]
+    fn bad_sequences_are_turned_down() {
+        assert!(parse_sequence("").is_err());
+        assert!(parse_sequence("   ").is_err());
+        assert!(parse_sequence("hyper+k").unwrap_err().contains("`hyper`"));
+        assert!(parse_sequence("g foo").unwrap_err().contains("`foo`"));
+        // Written as `G`
+        assert!(parse_sequence("shift+g").is_err());
+    }
+
+    #[test]
+    fn unknown_actions_are_turned_down() {
+        let config = Config {
+            keys: vec![("g g".to_string(), "fly".to_string())],
+            ..Default::default()
+        };
+        let error = Keymap::from_config(&config).unwrap_err();
+        assert!(error.contains("`fly`"), "{error}");
+        assert_eq!(
+            action_by_name("launch:work"),
+            Some(ActionId::Launch("work".to_string()))
+        );
+    }
+
+    #[test]
+    fn shifted_characters_match_either_way() {
+        let mut keymap = keymap(&[("G", "bottom")]);
+        let now = Instant::now();
+        for event in [
+            ch('G'),
+            KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT),
+        ] {
+            assert_eq!(keymap.press(event, now), [Dispatch::Run(ActionId::Bottom)]);
+        }
+    }
+
+    #[test]
+    fn unbound_keys_pass_through() {
+        let mut keymap = keymap(&[("g g", "top")]);
+        let now = Instant::now();
+        assert_eq!(keymap.press(ch('j'), now), [Dispatch::Pass(ch('j'))]);
+        assert_eq!(keymap.pending(), None);
+    }
+
+    #[test]
+    fn chords_run_once_complete() {
+        let mut keymap = keymap(&[("space p l", "launch-preset")]);
+        let now = Instant::now();
+        assert!(keymap.press(ch(' '), now).is_empty());
+        assert!(keymap.press(ch('p'), now).is_empty());
+        assert_eq!(keymap.pending().as_deref(), Some("space p"));
+        assert_eq!(
+            keymap.press(ch('l'), now),
+            [Dispatch::Run(ActionId::LaunchPreset)]
+        );
+        assert_eq!(keymap.pending(), None);
+        assert_eq!(keymap.deadline(), None);
+    }
+
+    #[test]
+    fn prefixes_going_nowhere_are_handed_back() {
+        let mut keymap = keymap(&[("g g", "top"), ("space p l", "launch-preset")]);
+        let now = Instant::now();
+        assert!(keymap.press(ch('g'), now).is_empty());
+        // `g j` isn't bound, so both keys go to the menu
+        assert_eq!(
+            keymap.press(ch('j'), now),
+            [Dispatch::Pass(ch('g')), Dispatch::Pass(ch('j'))]
+        );
+        // and the key that broke a sequence off can start the next one
+        assert!(keymap.press(ch(' '), now).is_empty());
+        assert_eq!(keymap.press(ch('g'), now), [Dispatch::Pass(ch(' '))]);
+        assert_eq!(keymap.pending().as_deref(), Some("g"));
+    }
+
+    #[test]
+    fn bound_prefixes_wait_for_the_next_key() {
+        let mut keymap = keymap(&[("g", "bottom"), ("g g", "top")]);
+        let now = Instant::now();
+        assert!(keymap.press(ch('g'), now).is_empty());
+        assert_eq!(keymap.press(ch('g'), now), [Dispatch::Run(ActionId::Top)]);
+
+        // Another key settles it for the shorter one
+        assert!(keymap.press(ch('g'), now).is_empty());
+        assert_eq!(
+            keymap.press(ch('j'), now),
+            [Dispatch::Run(ActionId::Bottom), Dispatch::Pass(ch('j'))]
+        );
+    }
+
+    #[test]
+    fn chords_time_out() {
+        let mut keymap = keymap(&[
+            ("g", "bottom"),
+            ("g g", "top"),
+            ("space p l", "launch-preset"),
+        ]);
+        let start = Instant::now();
+        assert!(keymap.press(ch('g'), start).is_empty());
+        assert_eq!(keymap.deadline(), Some(start + CHORD_TIMEOUT));
+        assert!(keymap.expire(start + CHORD_TIMEOUT / 2).is_empty());
+        assert_eq!(
+            keymap.expire(start + CHORD_TIMEOUT),
+            [Dispatch::Run(ActionId::Bottom)]
+        );
+        assert_eq!(keymap.deadline(), None);
+
+        // Unbound keys typed so far go back to the menu
+        assert!(keymap.press(ch(' '), start).is_empty());
+        assert!(keymap.press(ch('p'), start).is_empty());
+        assert_eq!(
+            keymap.expire(start + CHORD_TIMEOUT),
+            [Dispatch::Pass(ch(' ')), Dispatch::Pass(ch('p'))]
+        );
+        assert_eq!(keymap.pending(), None);
+    }
+
+    #[test]
+    fn each_key_resets_the_timeout() {
+        let mut keymap = keymap(&[("space p l", "launch-preset")]);
+        let start = Instant::now();
+        let later = start + CHORD_TIMEOUT * 3 / 4;
+        assert!(keymap.press(ch(' '), start).is_empty());
+        assert!(keymap.press(ch('p'), later).is_empty());
+        assert_eq!(keymap.deadline(), Some(later + CHORD_TIMEOUT));
+        // Past the first key's deadline, but not the second's
+        assert!(keymap.expire(start + CHORD_TIMEOUT).is_empty());
+        assert_eq!(
+            keymap.press(ch('l'), start + CHORD_TIMEOUT),
+            [Dispatch::Run(ActionId::LaunchPreset)]
+        );
+    }
+
+    #[test]
+    fn timeout_comes_from_the_config() {
+        let config = Config {
+            keys: vec![("g g".to_string(), "top".to_string())],
+            chord_timeout_ms: Some(800),
+            ..Default::default()
+        };
+        let mut keymap = Keymap::from_config(&config).unwrap();
+        let start = Instant::now();
+        keymap.press(ch('g'), start);
+        assert_eq!(keymap.deadline(), Some(start + Duration::from_millis(800)));
+    }
+
+    #[test]
+    fn esc_drops_the_pending_keys() {
+        let mut keymap = keymap(&[("g g", "top")]);
+        let now = Instant::now();
+        assert!(keymap.press(ch('g'), now).is_empty());
+        assert!(keymap.press(key(KeyCode::Esc), now).is_empty());
+        assert_eq!(keymap.pending(), None);
+        assert!(keymap.expire(now + CHORD_TIMEOUT).is_empty());
+        // With nothing pending, it's the menu's
+        assert_eq!(
+            keymap.press(key(KeyCode::Esc), now),
+            [Dispatch::Pass(key(KeyCode::Esc))]
+        );
+    }
+
+    #[test]
+    fn later_bindings_take_the_place_of_earlier_ones() {
+        let mut keymap = keymap(&[("g g", "top"), ("g g", "bottom")]);
+        let now = Instant::now();
+        keymap.press(ch('g'), now);
+        assert_eq!(
+            keymap.press(ch('g'), now),
+            [Dispatch::Run(ActionId::Bottom)]
+        );
+    }
+
+    #[test]
+    fn prefixes_of_longer_bindings_conflict() {
+        assert!(
+            keymap(&[("z z", "top"), (", p l", "launch-preset")])
+                .conflicts()
+                .is_empty()
+        );
+        assert_eq!(
+            keymap(&[(", p", "reload-presets"), (", p l", "launch-preset")]).conflicts(),
+            ["`, p` also starts `, p l`, so it waits 500ms for another key"]
+        );
+    }
+
+    #[test]
+    fn built_in_keys_held_back_by_bindings_conflict() {
+        // `g` is the menus' own key for the top of the list
+        assert_eq!(
+            keymap(&[("g g", "top"), ("g e", "edit-note")]).conflicts(),
+            ["`g` (top) also starts `g g`, `g e`, so it waits 500ms for another key"]
+        );
+        // Bound over, it's only warned about as a binding
+        assert_eq!(
+            keymap(&[("g", "bottom"), ("g g", "top")]).conflicts(),
+            ["`g` also starts `g g`, so it waits 500ms for another key"]
+        );
+        // Bound to something else by itself, it doesn't wait on anything
+        assert!(keymap(&[("g", "bottom")]).conflicts().is_empty());
+    }
+
+    #[test]
+    fn keys_the_menus_handle_without_an_action_conflict_too() {
+        assert_eq!(
+            keymap(&[("space p l", "launch-preset")]).conflicts(),
+            [
+                "`space` (switching to a session or folding a group) also starts `space p l`, so \
+                 it waits 500ms for another key"
+            ]
+        );
+        // `enter` is also `launch-preset`'s key
+        assert_eq!(
+            keymap(&[("enter enter", "top")]).conflicts(),
+            [
+                "`enter` (launch-preset, switching to a session or folding a group) also starts \
+                 `enter enter`, so it waits 500ms for another key"
+            ]
+        );
+    }
+
+    #[test]
+    fn built_in_keys_held_back_still_reach_the_menu() {
+        let mut keymap = keymap(&[("g g", "top")]);
+        let start = Instant::now();
+        assert!(keymap.press(ch('g'), start).is_empty());
+        assert_eq!(
+            keymap.expire(start + CHORD_TIMEOUT),
+            [Dispatch::Pass(ch('g'))]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Stylize,
+    text::{Line, Span, Text},
+    widgets::{Block, Clear, Paragraph, Widget, Wrap},
+};
+
+use crate::app::theme::Theme;
+use crate::app::utils::{centered_fixed_rect, make_instructions};
+
+/// How far along reading tmux's sessions and the presets is
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum LoadState {
+    /// Still waiting on the sessions, the presets, or both
+    #[default]
+    Loading,
+    Ready,
+    /// Shown in place of the menus until the user retries
+    Failed(String),
+}
+
+/// Stands in for the menus until everything they show has been loaded
+pub fn render_loading(area: Rect, buf: &mut Buffer, load_state: &LoadState, theme: &Theme) {
+    match load_state {
+        LoadState::Ready => {}
+        LoadState::Loading => {
+            let [_, line_area, _] = Layout::vertical([
+                Constraint::Fill(1),
+                Constraint::Length(1),
+                Constraint::Fill(1),
+            ])
+            .areas(area);
+            Line::from(Span::styled("loading…", theme.dim).italic())
+                .centered()
+                .render(line_area, buf);
+        }
+        LoadState::Failed(error) => {
+            let area = centered_fixed_rect(area, 60, 12);
+            Clear.render(area, buf);
+
+            let block = Block::bordered()
+                .border_style(theme.delete)
+                .title(Span::styled(" Could not load ", theme.delete).bold());
+            let [message_area, instructions_area] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)])
+                    .margin(1)
+                    .areas(block.inner(area));
+
+            Paragraph::new(Text::from(error.as_str()))
+                .wrap(Wrap { trim: true })
+                .centered()
+                .render(message_area, buf);
+            Paragraph::new(make_instructions(
+                vec![("r", "retry"), ("q", "quit")],
+                theme,
+            ))
+            .centered()
+            .render(instructions_area, buf);
+
+            block.render(area, buf);
+        }
+    }
+}
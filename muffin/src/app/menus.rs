@@ -1,17 +1,67 @@
+pub mod about;
+pub mod adjust;
+pub mod append;
+pub mod conflict;
 pub mod create;
 pub mod delete;
+pub mod diagnostics;
+pub mod diff;
+pub mod kill_server;
+pub mod new_window;
+pub mod not_found;
+pub mod note;
+pub mod palette;
+pub mod panes;
+pub mod plan;
 pub mod presets;
 pub mod rename;
 pub mod sessions;
+pub mod vars;
 
 use crate::app::driver::{AppEvent, AppState};
 
+/// What a popup shared between the sessions and presets menus acts on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Target {
+    #[default]
+    Session,
+    Preset,
+}
+
+/// What came of a menu handling an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handled {
+    /// Whether tmux's sessions should be listed again.
+    ///
+    /// Events that only move around or filter what's listed can say no, as key repeat sends
+    /// plenty of them; the sessions are refreshed every
+    /// [`POLL_INTERVAL`](crate::app::driver::POLL_INTERVAL) anyway.
+    pub needs_refresh: bool,
+}
+
+impl Handled {
+    /// Something may have changed in tmux
+    pub const REFRESH: Self = Self {
+        needs_refresh: true,
+    };
+    /// Only what's shown changed
+    pub const QUIET: Self = Self {
+        needs_refresh: false,
+    };
+}
+
 pub trait Menu {
     /// How the menu should handle the event.
     ///
     /// This can involve manipulating state, which can result in state transitions
     /// (i.e) on Escape, a menu can set state.mode = AppMode::Sessions
-    fn handle_event(&mut self, event: AppEvent, state: &mut AppState);
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled;
+
+    /// Whether keys are being typed into a field, where they're text rather than anything bound
+    /// in the [`Keymap`](crate::app::keymap::Keymap)
+    fn takes_text(&self) -> bool {
+        false
+    }
 
     /// Update logic that should be run before the rendering phase
     ///
@@ -19,4 +69,14 @@ pub trait Menu {
     /// after a previous menu switched modes but before they have to render.
     #[allow(unused_variables)]
     fn pre_render(&mut self, state: &mut AppState) {}
+
+    /// Called by the driver right after the app switches into this menu's mode
+    #[allow(unused_variables)]
+    fn on_enter(&mut self, state: &AppState) {}
+
+    /// Called by the driver right after the app switches away from this menu's mode.
+    ///
+    /// Menus should drop any transient state here (typed input, pending notifications) so it
+    /// can't leak into the next time they're opened.
+    fn on_exit(&mut self) {}
 }
@@ -0,0 +1,110 @@
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppState},
+    utils::popup_rect,
+    widgets::instructions::Instructions,
+};
+use crate::build_info;
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::{Line, Text},
+    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget, Wrap},
+};
+
+/// Which muffin build this is, along with the tmux it talks to and the files it read, for
+/// telling which of them a problem comes from
+#[derive(Default)]
+pub struct AboutMenu {
+    /// Label and value of each line below the title
+    lines: Vec<(&'static str, String)>,
+}
+
+impl StatefulWidget for &mut AboutMenu {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let height = 7 + self.lines.len() as u16;
+        let area = popup_rect(area, 70, height, state);
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.accent);
+        let inner_area = block.inner(area);
+
+        let [title_area, lines_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        // Render title
+        Line::from(build_info::version_line().set_style(state.theme.accent))
+            .centered()
+            .render(title_area, buf);
+
+        // Render what muffin found, labels aligned
+        let label_width = self
+            .lines
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or_default();
+        let lines = self
+            .lines
+            .iter()
+            .map(|(label, value)| {
+                Line::from(vec![
+                    format!("{label:>label_width$}  ").set_style(state.theme.dim),
+                    value.clone().into(),
+                ])
+            })
+            .collect::<Vec<_>>();
+        Paragraph::new(Text::from(lines))
+            .wrap(Wrap { trim: false })
+            .render(lines_area, buf);
+
+        // Render instructions
+        Instructions::new(vec![("esc", "back")], state.theme).render(instructions_area, buf);
+
+        block.render(area, buf);
+    }
+}
+
+impl Menu for AboutMenu {
+    fn on_enter(&mut self, state: &AppState) {
+        let tmux = match state.server.tmux_version() {
+            Ok(version) => version.to_string(),
+            Err(e) => e.reason(),
+        };
+        let config = match &state.config_path {
+            Some(path) => path.display().to_string(),
+            None => "none, all defaults".to_string(),
+        };
+        self.lines = vec![
+            ("tmux", tmux),
+            ("server", state.server.to_string()),
+            ("config", config),
+        ];
+        for (i, source) in state.preset_sources.iter().enumerate() {
+            let label = if i == 0 { "presets" } else { "" };
+            self.lines.push((label, source.to_string()));
+        }
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        if let AppEvent::Key(key_event) = event
+            && matches!(key_event.code, KeyCode::Esc | KeyCode::Char('q'))
+        {
+            state.mode = state.focus.mode();
+        }
+        Handled::REFRESH
+    }
+}
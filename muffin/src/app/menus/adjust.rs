@@ -0,0 +1,388 @@
+use std::path::PathBuf;
+
+use super::presets::finish_launch;
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppState},
+    notifications::{Level, notify},
+    utils::popup_rect,
+    widgets::instructions::Instructions,
+};
+use crossterm::event::KeyCode;
+use parser::PresetEdit;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::Line,
+    widgets::{
+        Block, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph, StatefulWidget,
+        Widget, Wrap,
+    },
+};
+use tmux::{LivePane, Preset, ResizeDirection, Server, exact_target};
+
+/// Cells a pane's border moves per key press
+const RESIZE_STEP: u16 = 2;
+
+/// Resizes the panes of a just launched (or running) preset from the keyboard, and saves their
+/// sizes back to the presets file. Panes kept open by `remain_on_exit` can be respawned from it.
+#[derive(Default)]
+pub struct AdjustMenu {
+    /// As launched, so with the session's and windows' actual names
+    preset: Option<Preset>,
+    /// Name of the preset in the file it came from, which `{var}`s can make differ from the
+    /// session's
+    source_name: String,
+    source: Option<PathBuf>,
+    /// The server the preset launched on, which is its own if it names one
+    server: Server,
+    /// `-t` target of each of the preset's windows, once looked up
+    windows: Vec<String>,
+    window: usize,
+    /// Panes of the current window, as of the last render
+    panes: Vec<LivePane>,
+    /// Index of the highlighted pane in `panes`
+    pane: usize,
+    /// Why the panes couldn't be listed, shown in their place
+    error: Option<String>,
+}
+
+impl AdjustMenu {
+    /// Looks up where the preset's windows ended up, then lists the current one's panes
+    fn refresh(&mut self) {
+        let Some(preset) = &self.preset else {
+            return;
+        };
+        if self.windows.is_empty() {
+            let live = match self.server.list_windows(&preset.name) {
+                Ok(live) => live,
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    return;
+                }
+            };
+            // Indices are safer targets than names, which tmux also matches as prefixes
+            let windows = preset
+                .windows
+                .iter()
+                .map(|window| live.iter().find(|w| w.name == window.name))
+                .map(|window| window.map(|w| format!("{}:{}", exact_target(&preset.name), w.index)))
+                .collect::<Option<Vec<_>>>();
+            match windows {
+                Some(windows) => self.windows = windows,
+                None => {
+                    self.error = Some(format!("Some windows of '{}' are gone", preset.name));
+                    return;
+                }
+            }
+        }
+
+        let Some(window) = self.windows.get(self.window) else {
+            return;
+        };
+        match self.server.list_panes(window) {
+            Ok(panes) => {
+                self.panes = panes;
+                self.pane = self.pane.min(self.panes.len().saturating_sub(1));
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    fn pane_target(&self) -> Option<String> {
+        let window = self.windows.get(self.window)?;
+        let pane = self.panes.get(self.pane)?;
+        Some(format!("{window}.{}", pane.index))
+    }
+
+    fn resize(&mut self, direction: ResizeDirection, state: &mut AppState) {
+        let Some(target) = self.pane_target() else {
+            return;
+        };
+        if let Err(e) = self.server.resize_pane(&target, direction, RESIZE_STEP) {
+            notify(state, Level::Error, e.to_string());
+        }
+    }
+
+    /// Starts the highlighted pane again once its command has exited, with the command the preset
+    /// gave it
+    fn respawn(&mut self, state: &mut AppState) {
+        let (Some(preset), Some(target)) = (&self.preset, self.pane_target()) else {
+            return;
+        };
+        let pane = &self.panes[self.pane];
+        if !pane.dead {
+            notify(
+                state,
+                Level::Info,
+                format!("Pane {} is still running", pane.index),
+            );
+            return;
+        }
+        // Panes are matched to the preset's by position, which only holds if none were added or
+        // closed since
+        let planned = preset
+            .windows
+            .get(self.window)
+            .map(tmux::window_panes)
+            .unwrap_or_default();
+        let Some(&(cwd, command)) = planned
+            .get(self.pane)
+            .filter(|_| planned.len() == self.panes.len())
+        else {
+            notify(
+                state,
+                Level::Warn,
+                "Panes were added or closed, so which command to run is unknown",
+            );
+            return;
+        };
+        match self.server.respawn_pane(&target, cwd, command) {
+            Ok(_) => notify(state, Level::Info, format!("Respawned pane {}", pane.index)),
+            Err(e) => notify(state, Level::Error, e.to_string()),
+        }
+        self.refresh();
+    }
+
+    /// Highlights the pane `delta` away, making it the active one so it shows in tmux too
+    fn cycle_pane(&mut self, delta: isize, state: &mut AppState) {
+        if self.panes.is_empty() {
+            return;
+        }
+        let count = self.panes.len() as isize;
+        self.pane = (self.pane as isize + delta).rem_euclid(count) as usize;
+        if let Some(target) = self.pane_target()
+            && let Err(e) = self.server.select_pane(&target)
+        {
+            notify(state, Level::Error, e.to_string());
+        }
+    }
+
+    fn next_window(&mut self, state: &mut AppState) {
+        if self.windows.len() < 2 {
+            return;
+        }
+        self.window = (self.window + 1) % self.windows.len();
+        self.pane = 0;
+        if let Err(e) = self.server.select_window(&self.windows[self.window]) {
+            notify(state, Level::Error, e.to_string());
+        }
+        self.refresh();
+        self.pane = self.panes.iter().position(|p| p.active).unwrap_or_default();
+    }
+
+    /// Writes the sizes every window's panes have now back to the preset, then moves on
+    fn save(&mut self, state: &mut AppState) {
+        let Some(preset) = &self.preset else {
+            return;
+        };
+        let Some(source) = &self.source else {
+            notify(
+                state,
+                Level::Error,
+                format!("'{}' wasn't read from a file", self.source_name),
+            );
+            return;
+        };
+
+        let mut windows = vec![];
+        let mut skipped = vec![];
+        for (index, (window, target)) in preset.windows.iter().zip(&self.windows).enumerate() {
+            let sizes = self
+                .server
+                .list_panes(target)
+                .ok()
+                .and_then(|panes| tmux::measure_layout(&window.layout, &panes));
+            match sizes {
+                Some(sizes) => windows.push((index, sizes)),
+                None => skipped.push(window.name.as_str()),
+            }
+        }
+
+        let edit = PresetEdit::Resize {
+            name: self.source_name.clone(),
+            windows,
+        };
+        if let Err(e) = parser::edit_presets_file(source, &edit)
+            .and_then(|_| state.reload_presets(Some(&self.source_name)))
+        {
+            notify(state, Level::Error, e);
+            return;
+        }
+
+        if skipped.is_empty() {
+            notify(
+                state,
+                Level::Info,
+                format!("Saved the pane sizes of '{}'", self.source_name),
+            );
+        } else {
+            notify(
+                state,
+                Level::Warn,
+                format!(
+                    "Saved the pane sizes of '{}', except in {} (panes were added or closed)",
+                    self.source_name,
+                    skipped.join(", ")
+                ),
+            );
+        }
+        self.done(state);
+    }
+
+    /// Moves on like launching without adjusting would have
+    fn done(&mut self, state: &mut AppState) {
+        if let Some(preset) = &self.preset {
+            state.adjusting = None;
+            finish_launch(state, preset);
+        }
+    }
+}
+
+impl StatefulWidget for &mut AdjustMenu {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let height = 9 + self.panes.len().max(1) as u16;
+        let area = popup_rect(area, 60, height, state);
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.create);
+        let inner_area = block.inner(area);
+
+        let [title_area, panes_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(2),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        // Render title
+        if state.notifications.is_empty() {
+            let (session, window) = match &self.preset {
+                Some(preset) => (
+                    preset.name.as_str(),
+                    preset
+                        .windows
+                        .get(self.window)
+                        .map(|w| w.name.as_str())
+                        .unwrap_or_default(),
+                ),
+                None => ("", ""),
+            };
+            let content = format!(
+                "Adjusting '{session}', window {window} ({}/{})",
+                self.window + 1,
+                self.windows.len().max(1)
+            );
+            Line::from(content.set_style(state.theme.create))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        // Render panes, with their current size
+        if let Some(error) = &self.error {
+            Paragraph::new(error.as_str().set_style(state.theme.error))
+                .wrap(Wrap { trim: true })
+                .centered()
+                .render(panes_area, buf);
+        } else {
+            let items = self
+                .panes
+                .iter()
+                .map(|pane| {
+                    let item = ListItem::new(format!(
+                        "{:>2}  {:>3}x{:<3}  {}{}",
+                        pane.index,
+                        pane.width,
+                        pane.height,
+                        pane.command,
+                        if pane.dead { " (dead)" } else { "" }
+                    ));
+                    match pane.dead {
+                        true => item.style(state.theme.error),
+                        false => item,
+                    }
+                })
+                .collect::<Vec<_>>();
+            StatefulWidget::render(
+                List::new(items)
+                    .highlight_symbol(state.icons.highlight)
+                    .highlight_spacing(HighlightSpacing::Always)
+                    .highlight_style(state.theme.highlight),
+                panes_area,
+                buf,
+                &mut ListState::default().with_selected(Some(self.pane)),
+            );
+        }
+
+        // Render instructions
+        {
+            let mut instructions = vec![
+                ("h/j/k/l", "resize"),
+                ("tab", "next pane"),
+                ("s", "save sizes"),
+                ("esc", "done"),
+            ];
+            if self.windows.len() > 1 {
+                instructions.insert(2, ("w", "next window"));
+            }
+            if self.panes.iter().any(|pane| pane.dead) {
+                instructions.insert(instructions.len() - 2, ("x", "respawn"));
+            }
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl Menu for AdjustMenu {
+    fn pre_render(&mut self, _state: &mut AppState) {
+        // Sizes can also change from tmux itself, ex: the terminal being resized
+        self.refresh();
+    }
+
+    fn on_enter(&mut self, state: &AppState) {
+        self.preset = state.adjusting.clone();
+        self.server = match &self.preset {
+            Some(preset) => state.server.for_preset(preset),
+            None => state.server.clone(),
+        };
+        if let Some(preset) = state.highlighted_preset() {
+            self.source_name = preset.name.clone();
+            self.source = preset.source.clone();
+        }
+        self.refresh();
+        self.pane = self.panes.iter().position(|p| p.active).unwrap_or_default();
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        if let AppEvent::Key(key_event) = event {
+            match key_event.code {
+                KeyCode::Left | KeyCode::Char('h') => self.resize(ResizeDirection::Left, state),
+                KeyCode::Down | KeyCode::Char('j') => self.resize(ResizeDirection::Down, state),
+                KeyCode::Up | KeyCode::Char('k') => self.resize(ResizeDirection::Up, state),
+                KeyCode::Right | KeyCode::Char('l') => self.resize(ResizeDirection::Right, state),
+                KeyCode::Tab => self.cycle_pane(1, state),
+                KeyCode::BackTab => self.cycle_pane(-1, state),
+                KeyCode::Char('w') => self.next_window(state),
+                KeyCode::Char('s') => self.save(state),
+                KeyCode::Char('x') => self.respawn(state),
+                KeyCode::Esc => self.done(state),
+                _ => {}
+            }
+        }
+        Handled::REFRESH
+    }
+}
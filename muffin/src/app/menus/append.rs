@@ -0,0 +1,152 @@
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    notifications::{Level, notify},
+    startup::start_pending,
+    utils::popup_rect,
+    widgets::{
+        input::{Input, session_name},
+        instructions::Instructions,
+    },
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::Line,
+    widgets::{Block, Clear, StatefulWidget, Widget},
+};
+
+/// Adds the selected preset's windows to an existing session rather than starting its own
+pub struct AppendMenu<'a> {
+    preset: String,
+    /// Name of the session the windows go into
+    input: Input<'a>,
+}
+
+impl Default for AppendMenu<'_> {
+    fn default() -> Self {
+        Self {
+            preset: String::new(),
+            input: Input::default()
+                .placeholder("session name")
+                .validator(session_name),
+        }
+    }
+}
+
+impl<'a> AppendMenu<'a> {
+    fn append(&mut self, state: &mut AppState) {
+        let Some(preset) = state.highlighted_preset() else {
+            return;
+        };
+        if self.input.error().is_some() {
+            return;
+        }
+        let session = self.input.value().to_string();
+
+        match state.server.append_preset(preset, &session) {
+            Ok(append) => {
+                start_pending(state, state.server.clone(), append.pending, append.warnings);
+                notify(
+                    state,
+                    Level::Info,
+                    format!("Added windows {} to '{session}'", append.added.join(", ")),
+                );
+                state.focus_session = Some(session);
+                state.mode = AppMode::Sessions;
+            }
+            Err(e) => notify(state, Level::Error, e.to_string()),
+        }
+    }
+}
+
+impl<'a> StatefulWidget for &mut AppendMenu<'a> {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(area, 40, 15, state);
+
+        let block = Block::bordered().border_style(state.theme.create);
+        let inner_area = block.inner(area);
+        Clear.render(area, buf);
+
+        let [title_area, input_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        if state.notifications.is_empty() {
+            Line::from(
+                format!("Add '{}' to session...", self.preset).set_style(state.theme.create),
+            )
+            .centered()
+            .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        // Render input field
+        {
+            let [input_area, error_area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(input_area);
+            let [first_char, rest] =
+                Layout::horizontal([Constraint::Length(2), Constraint::Fill(1)])
+                    .horizontal_margin(3)
+                    .areas(input_area);
+
+            "> ".set_style(state.theme.create).render(first_char, buf);
+            self.input.render(rest, buf, state.theme, true);
+            self.input.render_error(error_area, buf, state.theme);
+        }
+
+        // Render instructions
+        {
+            let instructions = vec![("esc", "cancel"), ("enter", "add windows")];
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl<'a> Menu for AppendMenu<'a> {
+    fn on_enter(&mut self, state: &AppState) {
+        let Some(preset) = state.highlighted_preset() else {
+            return;
+        };
+        self.preset = preset.name.clone();
+
+        // Default to the session muffin was opened from
+        if let Some(session) = state
+            .sessions
+            .iter()
+            .find(|s| s.is_current)
+            .or_else(|| state.sessions.iter().find(|s| s.attached_count > 0))
+        {
+            self.input.set_value(&session.name);
+        }
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        match event {
+            AppEvent::Key(key_event) => match key_event.code {
+                KeyCode::Esc => state.mode = AppMode::Presets,
+                KeyCode::Enter => self.append(state),
+                _ => _ = self.input.handle_key(key_event),
+            },
+            AppEvent::Paste(text) => _ = self.input.paste(&text),
+            _ => {}
+        }
+        Handled::REFRESH
+    }
+}
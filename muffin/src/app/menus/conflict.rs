@@ -0,0 +1,145 @@
+use super::presets::{finish_launch, launch};
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    utils::popup_rect,
+    widgets::instructions::Instructions,
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::{Line, Text},
+    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget, Wrap},
+};
+use tmux::Preset;
+
+/// Asks what to do about launching a preset named like a session muffin didn't launch from it:
+/// switch to that session anyway, launch under another name, or not at all
+#[derive(Default)]
+pub struct ConflictMenu {
+    /// As launched, so with the session's actual name
+    preset: Option<Preset>,
+    /// First `<name>-N` no session on the preset's server has
+    free_name: String,
+}
+
+impl ConflictMenu {
+    fn switch_anyway(&mut self, state: &mut AppState) {
+        if let Some(preset) = self.preset.take() {
+            state.conflicting = None;
+            state.adjust_on_launch = false;
+            state.mode = AppMode::Presets;
+            finish_launch(state, &preset);
+        }
+    }
+
+    fn launch_renamed(&mut self, state: &mut AppState) {
+        if let Some(preset) = self.preset.take() {
+            state.conflicting = None;
+            let renamed = Preset {
+                name: self.free_name.clone(),
+                ..preset
+            };
+            launch(state, &renamed);
+        }
+    }
+
+    fn cancel(&mut self, state: &mut AppState) {
+        state.conflicting = None;
+        state.adjust_on_launch = false;
+        state.mode = AppMode::Presets;
+    }
+}
+
+/// `name-2`, `name-3`, ... whichever comes first that `taken` doesn't have
+fn free_name<'a>(name: &str, taken: impl Iterator<Item = &'a str> + Clone) -> String {
+    (2..)
+        .map(|n| format!("{name}-{n}"))
+        .find(|candidate| !taken.clone().any(|taken| taken == candidate))
+        .expect("there are only so many sessions")
+}
+
+impl StatefulWidget for &mut ConflictMenu {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(area, 60, 11, state);
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.warn);
+        let inner_area = block.inner(area);
+
+        let [title_area, explanation_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(2),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        let name = self
+            .preset
+            .as_ref()
+            .map(|preset| preset.name.as_str())
+            .unwrap_or_default();
+
+        // Render title
+        if state.notifications.is_empty() {
+            Line::from(format!("'{name}' is taken").set_style(state.theme.warn))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        // Render what's in the way
+        Paragraph::new(Text::from(format!(
+            "A session named '{name}' exists, but it wasn't launched from this preset"
+        )))
+        .wrap(Wrap { trim: true })
+        .centered()
+        .render(explanation_area, buf);
+
+        // Render instructions
+        {
+            let launch_as = format!("launch as '{}'", self.free_name);
+            let instructions = vec![
+                ("s", "switch to it anyway"),
+                ("n", launch_as.as_str()),
+                ("esc", "cancel"),
+            ];
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl Menu for ConflictMenu {
+    fn on_enter(&mut self, state: &AppState) {
+        self.preset = state.conflicting.clone();
+        if let Some(preset) = &self.preset {
+            let taken = state.sessions_for(preset).iter().map(|s| s.name.as_str());
+            self.free_name = free_name(&preset.name, taken);
+        }
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        if let AppEvent::Key(key_event) = event {
+            match key_event.code {
+                KeyCode::Char('s') => self.switch_anyway(state),
+                KeyCode::Char('n') => self.launch_renamed(state),
+                KeyCode::Esc | KeyCode::Char('q') => self.cancel(state),
+                _ => {}
+            }
+        }
+        Handled::REFRESH
+    }
+}
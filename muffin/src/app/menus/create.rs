@@ -1,51 +1,316 @@
-use super::Menu;
+use super::{Handled, Menu};
 use crate::app::{
     driver::{AppEvent, AppMode, AppState},
-    utils::{centered_fixed_rect, make_instructions, send_timed_notification},
+    notifications::{Level, notify},
+    startup::start_pending,
+    utils::{fuzzy_score, popup_rect},
+    widgets::{
+        input::{Input, new_session_name},
+        instructions::Instructions,
+    },
 };
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyModifiers};
 use ratatui::{
     prelude::{Buffer, Constraint, Layout, Rect},
-    style::{Style, Stylize},
-    text::Line,
-    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget, Wrap},
+    style::{Styled, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListItem, ListState, StatefulWidget, Widget},
 };
-use tui_textarea::TextArea;
 
-#[derive(Default)]
+/// A session name suggested from one of the configured completion directories
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub name: String,
+    pub path: String,
+}
+
 pub struct CreateMenu<'a> {
-    text_area: TextArea<'a>,
-    notification: Option<String>,
+    input: Input<'a>,
+    /// Every candidate found by the directory scan
+    completions: Vec<Completion>,
+    /// Indices into `completions` matching the current input, best match first
+    matches: Vec<usize>,
+    list_state: ListState,
+    /// The completion last accepted into the input, whose directory becomes the cwd
+    accepted: Option<Completion>,
+    /// What was typed before the input was replaced by a name from the history, which the
+    /// history is filtered by and that going past the most recent name brings back
+    typed: Option<String>,
+    /// The name from the history in the input, as an index into the ones that can be recalled
+    recalled: Option<usize>,
+}
+
+impl Default for CreateMenu<'_> {
+    fn default() -> Self {
+        Self {
+            input: Input::default()
+                .placeholder("start typing!")
+                .validator(new_session_name),
+            completions: vec![],
+            matches: vec![],
+            list_state: ListState::default(),
+            accepted: None,
+            typed: None,
+            recalled: None,
+        }
+    }
+}
+
+impl<'a> CreateMenu<'a> {
+    fn update_matches(&mut self) {
+        let query = self.input.value();
+        let mut scored = self
+            .completions
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, c)| fuzzy_score(query, &c.name).map(|score| (score, idx)))
+            .collect::<Vec<_>>();
+        scored.sort_by(|(a_score, a_idx), (b_score, b_idx)| {
+            b_score.cmp(a_score).then(
+                self.completions[*a_idx]
+                    .name
+                    .cmp(&self.completions[*b_idx].name),
+            )
+        });
+        self.matches = scored.into_iter().map(|(_, idx)| idx).collect();
+        self.list_state.select(None);
+    }
+
+    fn select_next(&mut self) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let next = match self.list_state.selected() {
+            Some(idx) => (idx + 1) % self.matches.len(),
+            None => 0,
+        };
+        self.list_state.select(Some(next));
+    }
+
+    fn select_previous(&mut self) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let previous = match self.list_state.selected() {
+            Some(0) | None => self.matches.len() - 1,
+            Some(idx) => idx - 1,
+        };
+        self.list_state.select(Some(previous));
+    }
+
+    /// Names from the history starting with what was typed, most recent first, and whether a
+    /// session is already named so
+    fn suggestions<'s>(&'s self, state: &'s AppState) -> Vec<(&'s str, bool)> {
+        let prefix = self.typed.as_deref().unwrap_or(self.input.value());
+        state
+            .history
+            .matching(prefix)
+            .map(|name| (name, state.sessions.iter().any(|s| s.name == name)))
+            .collect()
+    }
+
+    /// Puts the next older name from the history that's still free in the input, like a shell
+    fn recall_older(&mut self, state: &AppState) {
+        let free = self.free_suggestions(state);
+        let older = self.recalled.map_or(0, |idx| idx + 1);
+        let Some(name) = free.get(older).or(free.last()).cloned() else {
+            return;
+        };
+        if self.typed.is_none() {
+            self.typed = Some(self.input.value().to_string());
+        }
+        self.recalled = Some(older.min(free.len() - 1));
+        self.input.set_value(&name);
+        self.update_matches();
+    }
+
+    /// Puts the next newer name from the history in the input, and past the most recent one what
+    /// was typed
+    fn recall_newer(&mut self, state: &AppState) {
+        let name = match self.recalled {
+            None => return,
+            Some(0) => {
+                self.recalled = None;
+                self.typed.take().unwrap_or_default()
+            }
+            Some(idx) => {
+                self.recalled = Some(idx - 1);
+                let free = self.free_suggestions(state);
+                let Some(name) = free.get(idx - 1).cloned() else {
+                    return;
+                };
+                name
+            }
+        };
+        self.input.set_value(&name);
+        self.update_matches();
+    }
+
+    /// [`CreateMenu::suggestions`] no session is named after, which are the ones recalled
+    fn free_suggestions(&self, state: &AppState) -> Vec<String> {
+        self.suggestions(state)
+            .into_iter()
+            .filter(|(_, taken)| !taken)
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    /// Forgets the name recalled from the history, once the input is edited
+    fn stop_recalling(&mut self) {
+        self.typed = None;
+        self.recalled = None;
+    }
+
+    /// Creates the session typed in, laid out like the configured `default_session` unless
+    /// `plain` is set, there isn't one or no name was typed
+    fn create(&mut self, plain: bool, state: &mut AppState) {
+        if self.input.error().is_some() {
+            return;
+        }
+        let name = self.input.value().to_string();
+        // Without a completion, it starts where muffin was opened from, when that's in tmux
+        let opened_in_tmux = state.sessions.iter().any(|s| s.is_current);
+        let cwd = self
+            .accepted
+            .as_ref()
+            .filter(|c| c.name == name)
+            .map(|c| c.path.clone())
+            .or_else(|| opened_in_tmux.then(|| tmux::INHERIT_CWD.to_string()));
+
+        // Presets need a name, sessions left for tmux to name are kept plain
+        let template = state
+            .config
+            .default_session
+            .as_ref()
+            .filter(|_| !plain && !name.is_empty());
+        if let Some(template) = template {
+            let preset = parser::from_template(template, &name, cwd.as_deref());
+            match state.server.spawn_preset(&preset) {
+                Ok(spawn) => {
+                    remember(state, &name);
+                    let server = state.server.for_preset(&preset);
+                    start_pending(state, server, spawn.pending, spawn.warnings);
+                    state.mode = AppMode::Sessions;
+                    return;
+                }
+                // Unless the session was created before the template went wrong, it's still
+                // worth creating a plain one
+                Err(e) => {
+                    let created = state
+                        .server
+                        .list_sessions()
+                        .is_ok_and(|sessions| sessions.iter().any(|s| s.name == name));
+                    if created {
+                        remember(state, &name);
+                        notify(state, Level::Error, e.to_string());
+                        return;
+                    }
+                    notify(
+                        state,
+                        Level::Warn,
+                        format!("default_session couldn't be used ({e}), created a plain session"),
+                    );
+                }
+            }
+        }
+
+        match state.server.create_session(&name, cwd.as_deref()) {
+            Ok(_) if !name.is_empty() => {
+                remember(state, &name);
+                state.mode = AppMode::Sessions;
+            }
+            // Left for muffin to name after what runs in it
+            Ok(created) if state.config.auto_name_sessions => {
+                if let Err(e) = state.server.set_auto_name(&created, Some(&created)) {
+                    notify(state, Level::Warn, e.to_string());
+                }
+                state.mode = AppMode::Sessions;
+            }
+            Ok(_) => state.mode = AppMode::Sessions,
+            Err(e) => notify(state, Level::Error, e.to_string()),
+        }
+    }
+
+    fn accept_selected(&mut self, idx: usize) {
+        let completion = self.completions[self.matches[idx]].clone();
+        self.input.set_value(&completion.name);
+        self.accepted = Some(completion);
+        self.update_matches();
+    }
+}
+
+/// Adds `name` to the history of created sessions, which failing to save is only a warning about
+fn remember(state: &mut AppState, name: &str) {
+    if let Err(e) = state.history.record(name) {
+        notify(state, Level::Warn, e);
+    }
+}
+
+/// Lists the subdirectories of every configured completion directory.
+///
+/// Unreadable or missing directories are silently skipped.
+fn scan_completion_dirs(dirs: &[String]) -> Vec<Completion> {
+    let mut completions = vec![];
+    for dir in dirs {
+        // Accept both `~/projects` and `~/projects/*`
+        let dir = dir.trim_end_matches('*').trim_end_matches('/');
+        let Ok(dir) = shellexpand::full(dir) else {
+            continue;
+        };
+        let Ok(entries) = std::fs::read_dir(dir.as_ref()) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || !entry.path().is_dir() {
+                continue;
+            }
+            completions.push(Completion {
+                name,
+                path: entry.path().to_string_lossy().to_string(),
+            });
+        }
+    }
+    completions.sort_by(|a, b| a.name.cmp(&b.name).then(a.path.cmp(&b.path)));
+    completions.dedup_by(|a, b| a.path == b.path);
+    completions
 }
 
 impl<'a> StatefulWidget for &mut CreateMenu<'a> {
     type State = AppState;
 
-    fn render(self, area: Rect, buf: &mut Buffer, _state: &mut AppState) {
-        let area = centered_fixed_rect(area, 40, 15);
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(area, 40, 15, state);
 
-        let block = Block::bordered().border_style(Style::new().blue());
+        let block = Block::bordered().border_style(state.theme.create);
         let inner_area = block.inner(area);
         Clear.render(area, buf);
 
-        let [title_area, input_area, instructions_area] = Layout::vertical([
+        let [
+            title_area,
+            input_area,
+            error_area,
+            history_area,
+            completions_area,
+            instructions_area,
+        ] = Layout::vertical([
             Constraint::Length(2),
-            Constraint::Fill(1),
             Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(2),
         ])
         .vertical_margin(1)
         .horizontal_margin(1)
         .areas(inner_area);
 
-        {
-            let content = match self.notification.clone() {
-                Some(msg) => msg,
-                _ => "Name new session".to_string(),
-            };
-
-            Line::from(content.blue())
+        if state.notifications.is_empty() {
+            Line::from("Name new session".set_style(state.theme.create))
                 .centered()
                 .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
         }
 
         // Render input field
@@ -55,22 +320,72 @@ impl<'a> StatefulWidget for &mut CreateMenu<'a> {
                     .horizontal_margin(3)
                     .areas(input_area);
 
-            "> ".blue().render(first_char, buf);
+            "> ".set_style(state.theme.create).render(first_char, buf);
+            self.input.render(rest, buf, state.theme, true);
+            self.input.render_error(error_area, buf, state.theme);
+        }
+
+        // Render the names from the history, taken ones struck through as they can't be recalled
+        let suggestions = self.suggestions(state);
+        if !suggestions.is_empty() {
+            let [_, history_area] =
+                Layout::horizontal([Constraint::Length(2), Constraint::Fill(1)])
+                    .horizontal_margin(3)
+                    .areas(history_area);
 
-            self.text_area.set_placeholder_text("start typing!");
-            self.text_area
-                .set_placeholder_style(Style::new().dark_gray());
-            self.text_area.render(rest, buf);
+            let recalled = self.recalled.map(|_| self.input.value());
+            let mut spans = vec![Span::styled("recent:", state.theme.dim)];
+            for (name, taken) in &suggestions {
+                let style = if *taken {
+                    state.theme.dim.crossed_out()
+                } else if recalled == Some(*name) {
+                    state.theme.create.bold()
+                } else {
+                    state.theme.dim
+                };
+                spans.push(" ".into());
+                spans.push(Span::styled(*name, style));
+            }
+            Line::from(spans).render(history_area, buf);
+        }
+        let can_recall = suggestions.iter().any(|(_, taken)| !taken);
+
+        // Render completions
+        {
+            let [_, list_area] = Layout::horizontal([Constraint::Length(2), Constraint::Fill(1)])
+                .horizontal_margin(3)
+                .areas(completions_area);
+
+            let items = self
+                .matches
+                .iter()
+                .map(|&idx| ListItem::new(Line::from(self.completions[idx].name.as_str())))
+                .collect::<Vec<ListItem>>();
+
+            StatefulWidget::render(
+                List::new(items)
+                    .set_style(state.theme.dim)
+                    .highlight_style(state.theme.create.bold()),
+                list_area,
+                buf,
+                &mut self.list_state,
+            );
         }
 
         // Render instructions
         {
-            let instructions = vec![("esc", "cancel"), ("enter", "create")];
+            let mut instructions = vec![("esc", "cancel"), ("enter", "create")];
+            if state.config.default_session.is_some() {
+                instructions.push(("alt+enter", "empty"));
+            }
+            if !self.matches.is_empty() {
+                instructions.push(("tab", "complete"));
+            }
+            if can_recall {
+                instructions.push(("↑/↓", "history"));
+            }
 
-            Paragraph::new(make_instructions(instructions))
-                .wrap(Wrap { trim: true })
-                .centered()
-                .render(instructions_area, buf);
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
         }
 
         block.render(area, buf);
@@ -78,25 +393,106 @@ impl<'a> StatefulWidget for &mut CreateMenu<'a> {
 }
 
 impl<'a> Menu for CreateMenu<'a> {
-    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) {
+    fn on_enter(&mut self, state: &AppState) {
+        // Scan once per visit, off the UI task, so slow filesystems can't stall rendering
+        let dirs = state.config.completion_dirs.clone();
+        let tx = state.event_handler.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(AppEvent::Completions(scan_completion_dirs(&dirs)));
+        });
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
         match event {
             AppEvent::Key(key_event) => match key_event.code {
-                KeyCode::Esc => {
-                    self.text_area = TextArea::default();
-                    state.mode = AppMode::Sessions;
+                KeyCode::Esc => state.mode = AppMode::Sessions,
+                KeyCode::Tab => self.select_next(),
+                KeyCode::BackTab => self.select_previous(),
+                KeyCode::Up => self.recall_older(state),
+                KeyCode::Down => self.recall_newer(state),
+                KeyCode::Char('n') if key_event.modifiers == KeyModifiers::CONTROL => {
+                    self.select_next()
+                }
+                KeyCode::Char('p') if key_event.modifiers == KeyModifiers::CONTROL => {
+                    self.select_previous()
+                }
+                KeyCode::Enter => {
+                    // The first Enter accepts the highlighted completion, the second creates
+                    if let Some(idx) = self.list_state.selected() {
+                        self.accept_selected(idx);
+                        return Handled::REFRESH;
+                    }
+                    self.create(key_event.modifiers.contains(KeyModifiers::ALT), state);
                 }
-                KeyCode::Enter => match tmux::create_session(&self.text_area.lines().join("\n")) {
-                    Ok(_) => {
-                        self.text_area = TextArea::default();
-                        state.mode = AppMode::Sessions;
+                _ => {
+                    if self.input.handle_key(key_event) {
+                        self.stop_recalling();
+                        self.update_matches();
                     }
-                    Err(s) => send_timed_notification(&state.event_handler, s),
-                },
-                _ => _ = self.text_area.input(key_event),
+                }
             },
-            AppEvent::ShowNotification(msg) => self.notification = Some(msg),
-            AppEvent::ClearNotification => self.notification = None,
+            AppEvent::Paste(text) => {
+                self.input.paste(&text);
+                self.stop_recalling();
+                self.update_matches();
+            }
+            AppEvent::Completions(completions) => {
+                self.completions = completions;
+                self.update_matches();
+            }
             _ => {}
         }
+        Handled::REFRESH
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing::{self, TestServer};
+    use crossterm::event::KeyEvent;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> AppEvent {
+        AppEvent::Key(KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn pasted_names_land_at_the_cursor() {
+        let server = TestServer::new("create-paste");
+        let mut app = testing::app(&server);
+        let state = &mut app.state;
+        state.mode = AppMode::Create;
+        let mut menu = CreateMenu::default();
+
+        // Copied along with its line break, which would end it
+        menu.handle_event(AppEvent::Paste("api\n".to_string()), state);
+        assert_eq!(menu.input.value(), "api");
+        menu.handle_event(AppEvent::Paste("-v\r\n2\tnext".to_string()), state);
+        assert_eq!(menu.input.value(), "api-v 2 next");
+
+        // Editing keys reach the input, and pastes go where its cursor is
+        for event in [
+            key(KeyCode::Char('a'), KeyModifiers::CONTROL),
+            key(KeyCode::Right, KeyModifiers::NONE),
+            key(KeyCode::Right, KeyModifiers::NONE),
+            key(KeyCode::Right, KeyModifiers::NONE),
+            AppEvent::Paste("web".to_string()),
+            key(KeyCode::Home, KeyModifiers::NONE),
+            AppEvent::Paste("my-".to_string()),
+            key(KeyCode::Char('e'), KeyModifiers::CONTROL),
+            key(KeyCode::Backspace, KeyModifiers::NONE),
+            key(KeyCode::Left, KeyModifiers::NONE),
+            AppEvent::Paste("X".to_string()),
+            key(KeyCode::End, KeyModifiers::NONE),
+            AppEvent::Paste("!".to_string()),
+        ] {
+            menu.handle_event(event, state);
+        }
+        assert_eq!(menu.input.value(), "my-apiweb-v 2 neXx!");
+        assert!(matches!(state.mode, AppMode::Create));
     }
 }
@@ -1,85 +1,339 @@
-use super::Menu;
+use super::{Handled, Menu, Target};
 use crate::app::{
     driver::{AppEvent, AppMode, AppState},
-    utils::{centered_fixed_rect, make_instructions, send_timed_notification},
+    notifications::{Level, notify},
+    theme::Theme,
+    utils::popup_rect,
+    widgets::instructions::Instructions,
 };
 use crossterm::event::KeyCode;
+use parser::PresetEdit;
 use ratatui::{
     prelude::{Buffer, Constraint, Layout, Rect},
-    style::{Style, Stylize},
-    text::Line,
+    style::{Styled, Stylize},
+    text::{Line, Text},
     widgets::{Block, Clear, Paragraph, StatefulWidget, Widget, Wrap},
 };
-use tui_textarea::TextArea;
+
+/// Most windows listed in the confirmation before collapsing the rest into "+N more"
+const MAX_LISTED_WINDOWS: usize = 8;
+
+/// What the popup knows about the windows that are about to be lost
+#[derive(Default)]
+enum Preview {
+    #[default]
+    Loading,
+    /// One description per window, ex: `editor (nvim)`
+    Loaded(Vec<String>),
+    /// Fetching failed, so just fall back to asking about the name
+    Unavailable,
+}
+
+/// Where the client goes before its own session is deleted out from under it
+enum Landing {
+    Session(Box<tmux::Session>),
+    /// There's no other session, so a new one is created for it
+    NewSession,
+}
 
 #[derive(Default)]
-pub struct DeleteMenu<'a> {
-    text_area: TextArea<'a>,
-    notification: Option<String>,
+pub struct DeleteMenu {
+    target: Target,
+    preview: Preview,
+    /// Set when the session is the one muffin's client is on
+    landing: Option<Landing>,
 }
 
-impl<'a> StatefulWidget for &mut DeleteMenu<'a> {
+impl DeleteMenu {
+    pub fn new(target: Target) -> Self {
+        Self {
+            target,
+            ..Default::default()
+        }
+    }
+
+    fn target_name(&self, state: &AppState) -> Option<String> {
+        match self.target {
+            Target::Session => state
+                .selected_session
+                .and_then(|index| state.sessions.get(index))
+                .map(|s| s.name.clone()),
+            Target::Preset => state.highlighted_preset().map(|p| p.name.clone()),
+        }
+    }
+
+    fn return_mode(&self) -> AppMode {
+        match self.target {
+            Target::Session => AppMode::Sessions,
+            Target::Preset => AppMode::Presets,
+        }
+    }
+
+    fn delete_preset(&self, state: &mut AppState) {
+        let Some(preset) = state.highlighted_preset() else {
+            return;
+        };
+        let Some(source) = preset.source.clone() else {
+            return;
+        };
+        let name = preset.name.clone();
+        match parser::edit_presets_file(&source, &PresetEdit::Delete { name })
+            .and_then(|_| state.reload_presets(None))
+        {
+            Ok(_) => state.mode = AppMode::Presets,
+            Err(e) => notify(state, Level::Error, e),
+        }
+    }
+
+    fn delete_session(&self, state: &mut AppState) {
+        let Some(session) = state
+            .selected_session
+            .and_then(|index| state.sessions.get(index))
+            .cloned()
+        else {
+            return;
+        };
+
+        // Deleting the session the client is on would take the client (and muffin) down with it
+        if let Some(landing) = &self.landing {
+            let switched = match landing {
+                Landing::Session(other) => state
+                    .server
+                    .switch_session(&other.id)
+                    .map(|_| state.mru.touch(other))
+                    .map(|touched| (other.name.clone(), touched)),
+                Landing::NewSession => state.server.create_session("", None).and_then(|name| {
+                    state.server.switch_session(&name)?;
+                    let touched = state.mru.touch_name(&name);
+                    Ok((name, touched))
+                }),
+            };
+            match switched {
+                Ok((name, touched)) => {
+                    if let Err(e) = touched {
+                        notify(state, Level::Warn, e);
+                    }
+                    state.focus_session = Some(name);
+                }
+                Err(e) => {
+                    let msg = format!("Kept '{}', switching away failed: {e}", session.name);
+                    notify(state, Level::Error, msg);
+                    return;
+                }
+            }
+        }
+
+        match state.trash.delete(&state.server, &session) {
+            Ok(_) => state.mode = AppMode::Sessions,
+            Err(e) => notify(state, Level::Error, e),
+        }
+    }
+
+    /// Warning shown when the session is the current one
+    fn landing_notice(&self) -> Option<String> {
+        let destination = match self.landing.as_ref()? {
+            Landing::Session(session) => format!("'{}'", session.name),
+            Landing::NewSession => "a new session".to_string(),
+        };
+        Some(format!(
+            "This is your current session — muffin will switch you to {destination} first"
+        ))
+    }
+
+    fn preview_lines(&self, theme: &Theme) -> Vec<Line<'_>> {
+        match &self.preview {
+            Preview::Loading => vec![Line::from("loading…".set_style(theme.dim).italic())],
+            Preview::Unavailable => vec![],
+            Preview::Loaded(windows) => {
+                let mut lines = vec![Line::from(format!(
+                    "{} window{}:",
+                    windows.len(),
+                    if windows.len() == 1 { "" } else { "s" }
+                ))];
+                lines.extend(
+                    windows
+                        .iter()
+                        .take(MAX_LISTED_WINDOWS)
+                        .map(|w| Line::from(format!("  {w}"))),
+                );
+                if windows.len() > MAX_LISTED_WINDOWS {
+                    lines.push(Line::from(
+                        format!("  +{} more", windows.len() - MAX_LISTED_WINDOWS)
+                            .set_style(theme.dim),
+                    ));
+                }
+                lines
+            }
+        }
+    }
+}
+
+impl StatefulWidget for &mut DeleteMenu {
     type State = AppState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
-        let area = centered_fixed_rect(area, 40, 15);
+        let preview = self.preview_lines(state.theme);
+        let notice = self.landing_notice();
+
+        // Wider, to fit the notice in a few lines
+        let width = if notice.is_some() { 50 } else { 40 };
+        // Wrapped at word boundaries, it may take a row more than its length says, or else that
+        // row separates it from the preview
+        let notice_height = notice.as_ref().map_or(0, |notice| {
+            notice.chars().count().div_ceil(width as usize - 4) as u16 + 1
+        });
+
+        // Borders, margins, title and instructions, plus one row per preview line
+        let height =
+            7 + notice_height + preview.len() as u16 + if preview.is_empty() { 0 } else { 1 };
+        let area = popup_rect(area, width, height.max(9), state);
         Clear.render(area, buf);
 
-        let block = Block::bordered().border_style(Style::new().red());
+        let block = Block::bordered().border_style(state.theme.delete);
+        let kind = match self.target {
+            Target::Session => "session",
+            Target::Preset => "preset",
+        };
         let inner_area = block.inner(area);
 
-        let [title_area, instructions_area] =
-            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)])
-                .vertical_margin(1)
-                .horizontal_margin(1)
-                .areas(inner_area);
+        let [title_area, notice_area, preview_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(notice_height),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
 
         // Render title
+        if state.notifications.is_empty() {
+            let content = format!(
+                "Delete {kind} '{}'?",
+                self.target_name(state).unwrap_or_default()
+            );
+            Line::from(content.set_style(state.theme.delete))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        if let Some(notice) = notice {
+            Paragraph::new(notice.set_style(state.theme.warn))
+                .wrap(Wrap { trim: true })
+                .centered()
+                .render(notice_area, buf);
+        }
+
+        // Render the windows that would be lost
         {
-            let index = state.selected_session.unwrap();
-            let content = match self.notification.clone() {
-                Some(msg) => msg,
-                _ => format!("Delete session '{}'?", state.sessions[index].name),
-            };
+            let [_, list_area] = Layout::horizontal([Constraint::Length(2), Constraint::Fill(1)])
+                .horizontal_margin(1)
+                .areas(preview_area);
 
-            Line::from(content.red()).centered().render(title_area, buf);
+            Paragraph::new(Text::from(preview)).render(list_area, buf);
         }
 
         // Render instructions
         {
             let instructions = vec![("y/enter", "delete"), ("n/esc", "cancel")];
 
-            Paragraph::new(make_instructions(instructions))
-                .wrap(Wrap { trim: true })
-                .centered()
-                .render(instructions_area, buf);
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
         }
 
         block.render(area, buf);
     }
 }
 
-impl<'a> Menu for DeleteMenu<'a> {
-    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) {
+impl Menu for DeleteMenu {
+    fn on_enter(&mut self, state: &AppState) {
+        if self.target == Target::Preset {
+            // Presets are already in memory, so there's nothing to wait for
+            if let Some(preset) = state.highlighted_preset() {
+                self.preview = Preview::Loaded(
+                    preset
+                        .windows
+                        .iter()
+                        .map(|w| {
+                            let panes = w.layout.pane_count();
+                            format!(
+                                "{} ({panes} pane{})",
+                                w.name,
+                                if panes == 1 { "" } else { "s" }
+                            )
+                        })
+                        .collect(),
+                );
+            }
+            return;
+        }
+
+        let Some(session) = state
+            .selected_session
+            .and_then(|index| state.sessions.get(index))
+        else {
+            return;
+        };
+
+        if session.is_current {
+            // The session switched to most recently, or else the first one listed
+            self.landing = Some(
+                state
+                    .sessions
+                    .iter()
+                    .filter(|s| s.id != session.id)
+                    .min_by_key(|s| state.mru.rank(s).unwrap_or(usize::MAX))
+                    .map_or(Landing::NewSession, |s| {
+                        Landing::Session(Box::new(s.clone()))
+                    }),
+            );
+        }
+
+        // Fetch in the background so a slow server can't hold up the popup
+        let session = session.id.clone();
+        let server = state.server.clone();
+        let tx = state.event_handler.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let windows = server.list_windows(&session);
+            let _ = tx.send(AppEvent::SessionWindows(session, windows));
+        });
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::new(self.target);
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
         match event {
             AppEvent::Key(key_event) => match key_event.code {
-                KeyCode::Char('y') | KeyCode::Enter => {
-                    if let Some(index) = state.selected_session {
-                        match tmux::delete_session(&state.sessions[index].name) {
-                            Ok(_) => {
-                                self.text_area = TextArea::default();
-                                state.mode = AppMode::Sessions;
-                            }
-                            Err(s) => send_timed_notification(&state.event_handler, s),
-                        }
-                    };
+                KeyCode::Char('y') | KeyCode::Enter if self.target == Target::Preset => {
+                    self.delete_preset(state)
                 }
-                KeyCode::Char('n') | KeyCode::Esc => state.mode = AppMode::Sessions,
+                KeyCode::Char('y') | KeyCode::Enter => self.delete_session(state),
+                KeyCode::Char('n') | KeyCode::Esc => state.mode = self.return_mode(),
                 _ => {}
             },
-            AppEvent::ShowNotification(msg) => self.notification = Some(msg),
-            AppEvent::ClearNotification => self.notification = None,
+            AppEvent::SessionWindows(session, windows) => {
+                // Ignore results for a session we're no longer asking about
+                let is_current = state
+                    .selected_session
+                    .and_then(|index| state.sessions.get(index))
+                    .is_some_and(|s| s.id == session);
+                if is_current {
+                    self.preview = match windows {
+                        Ok(windows) => Preview::Loaded(
+                            windows
+                                .iter()
+                                .map(|w| format!("{} ({})", w.name, w.command))
+                                .collect(),
+                        ),
+                        Err(_) => Preview::Unavailable,
+                    };
+                }
+            }
             _ => {}
         }
+        Handled::REFRESH
     }
 }
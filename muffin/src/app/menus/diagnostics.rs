@@ -0,0 +1,162 @@
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    notifications::{Level, notify},
+    utils::popup_rect,
+    widgets::instructions::Instructions,
+};
+use crossterm::event::KeyCode;
+use parser::Severity;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::{Line, Text},
+    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget},
+};
+
+/// Lists what's wrong with the presets files, as of the last time they were read
+#[derive(Default)]
+pub struct DiagnosticsMenu {
+    /// Index of the first line shown
+    scroll: usize,
+    /// How many lines fit in the popup, as of the last render
+    page_height: usize,
+    /// How many lines the diagnostics take, as of the last render
+    line_count: usize,
+}
+
+impl DiagnosticsMenu {
+    fn scroll_by(&mut self, delta: isize) {
+        let max = self.line_count.saturating_sub(self.page_height);
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max);
+    }
+
+    /// Reads the presets files again, for checking a fix without leaving the list
+    fn reload(&mut self, state: &mut AppState) {
+        let selected = state.selected_preset.clone();
+        match state.reload_presets(selected.as_deref()) {
+            Ok(_) if state.diagnostics.is_empty() => {
+                notify(state, Level::Info, "No problems left in the presets files");
+                state.mode = AppMode::Presets;
+            }
+            Ok(_) => self.scroll = 0,
+            Err(e) => notify(state, Level::Error, e),
+        }
+    }
+}
+
+impl StatefulWidget for &mut DiagnosticsMenu {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(
+            area,
+            area.width.saturating_sub(4).min(100),
+            area.height.saturating_sub(2),
+            state,
+        );
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.warn);
+        let inner_area = block.inner(area);
+
+        let [title_area, lines_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        // Each diagnostic, followed by where it is when that's known
+        let lines = state
+            .diagnostics
+            .iter()
+            .flat_map(|diagnostic| {
+                let (label, style) = match diagnostic.severity {
+                    Severity::Warning => {
+                        (format!("{} warning", state.icons.warning), state.theme.warn)
+                    }
+                    Severity::Note => ("note".to_string(), state.theme.dim),
+                };
+                let mut lines = vec![Line::from(vec![
+                    label.set_style(style),
+                    format!(" {}", diagnostic.message).into(),
+                ])];
+                let source = diagnostic.source();
+                if !source.is_empty() {
+                    lines.push(Line::from(
+                        format!("  at {source}").set_style(state.theme.dim),
+                    ));
+                }
+                lines
+            })
+            .collect::<Vec<_>>();
+
+        self.line_count = lines.len();
+        self.page_height = lines_area.height as usize;
+        self.scroll_by(0);
+
+        // Render title, or a notification about reloading
+        if state.notifications.is_empty() {
+            let count = state.diagnostics.len();
+            let content = format!(
+                "{count} problem{} with the presets",
+                if count == 1 { "" } else { "s" }
+            );
+            Line::from(content.set_style(state.theme.warn))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        Paragraph::new(Text::from(
+            lines
+                .into_iter()
+                .skip(self.scroll)
+                .take(self.page_height)
+                .collect::<Vec<_>>(),
+        ))
+        .render(lines_area, buf);
+
+        // Render instructions
+        {
+            let instructions = vec![
+                ("esc", "back"),
+                ("j/↓", "down"),
+                ("k/↑", "up"),
+                ("pgdn/pgup", "page"),
+                ("R", "reload presets"),
+            ];
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl Menu for DiagnosticsMenu {
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        let page = self.page_height.max(1) as isize;
+        if let AppEvent::Key(key_event) = event {
+            match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q') => state.mode = AppMode::Presets,
+                KeyCode::Down | KeyCode::Char('j') => self.scroll_by(1),
+                KeyCode::Up | KeyCode::Char('k') => self.scroll_by(-1),
+                KeyCode::PageDown => self.scroll_by(page),
+                KeyCode::PageUp => self.scroll_by(-page),
+                KeyCode::Char('g') => self.scroll = 0,
+                KeyCode::Char('G') => self.scroll_by(self.line_count as isize),
+                KeyCode::Char('R') => self.reload(state),
+                _ => {}
+            }
+        }
+        Handled::REFRESH
+    }
+}
@@ -0,0 +1,192 @@
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    theme::Theme,
+    utils::popup_rect,
+    widgets::instructions::Instructions,
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::{Line, Text},
+    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget},
+};
+use tmux::PresetDiff;
+
+/// Shows how the highlighted preset's running session has drifted from the preset: windows
+/// only one of them has, and pane counts and directories of the windows both have
+#[derive(Default)]
+pub struct DiffMenu {
+    preset: String,
+    /// Whether the session still looks like the preset, `None` if it couldn't be compared
+    same: Option<bool>,
+    lines: Vec<Line<'static>>,
+    /// Index of the first line shown
+    scroll: usize,
+    /// How many lines fit in the popup, as of the last render
+    page_height: usize,
+}
+
+impl DiffMenu {
+    fn scroll_by(&mut self, delta: isize) {
+        let max = self.lines.len().saturating_sub(self.page_height);
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max);
+    }
+}
+
+/// One line per difference, under a line per window the session and preset both have
+fn diff_lines(diff: &PresetDiff, theme: &Theme) -> Vec<Line<'static>> {
+    let mut lines = vec![];
+    for name in diff.missing.iter() {
+        lines.push(Line::from(vec![
+            "- ".set_style(theme.delete),
+            name.clone().into(),
+            "  only in the preset".set_style(theme.dim),
+        ]));
+    }
+    for name in diff.extra.iter() {
+        lines.push(Line::from(vec![
+            "+ ".set_style(theme.create),
+            name.clone().into(),
+            "  only in the session".set_style(theme.dim),
+        ]));
+    }
+    for window in diff.windows.iter() {
+        if window.is_same() {
+            lines.push(Line::from(vec![
+                "  ".into(),
+                window.name.clone().into(),
+                "  as in the preset".set_style(theme.dim),
+            ]));
+            continue;
+        }
+        let mut line = vec!["~ ".set_style(theme.warn), window.name.clone().into()];
+        if window.preset_panes != window.live_panes {
+            line.push(
+                format!(
+                    "  {} panes, the preset has {}",
+                    window.live_panes, window.preset_panes
+                )
+                .set_style(theme.warn),
+            );
+        }
+        lines.push(Line::from(line));
+        for (index, expected, actual) in window.moved.iter() {
+            lines.push(Line::from(vec![
+                format!("    pane {index} in ").into(),
+                actual.clone().set_style(theme.warn),
+                format!(", the preset has {expected}").set_style(theme.dim),
+            ]));
+        }
+    }
+    lines
+}
+
+impl StatefulWidget for &mut DiffMenu {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(
+            area,
+            area.width.saturating_sub(4).min(100),
+            area.height.saturating_sub(2),
+            state,
+        );
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.plan);
+        let inner_area = block.inner(area);
+
+        let [title_area, lines_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        self.page_height = lines_area.height as usize;
+        self.scroll_by(0);
+
+        // Render title, or why the session couldn't be compared
+        if state.notifications.is_empty() {
+            let content = match self.same {
+                Some(true) => format!("'{}' is as its preset describes it", self.preset),
+                Some(false) => format!("'{}' has drifted from its preset", self.preset),
+                None => format!("Couldn't compare '{}' with its preset", self.preset),
+            };
+            Line::from(content.set_style(state.theme.plan))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        Paragraph::new(Text::from(
+            self.lines
+                .iter()
+                .skip(self.scroll)
+                .take(self.page_height)
+                .cloned()
+                .collect::<Vec<_>>(),
+        ))
+        .render(lines_area, buf);
+
+        // Render instructions
+        {
+            let instructions = vec![
+                ("esc", "back"),
+                ("j/↓", "down"),
+                ("k/↑", "up"),
+                ("pgdn/pgup", "page"),
+            ];
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl Menu for DiffMenu {
+    fn on_enter(&mut self, state: &AppState) {
+        let Some(preset) = state.highlighted_preset() else {
+            return;
+        };
+        self.preset = preset.name.clone();
+        // The session was launched with the variables filled in, which defaults are the best
+        // guess for
+        let filled = parser::apply_vars(preset, &Default::default());
+        match state.server.diff_preset(filled.as_ref().unwrap_or(preset)) {
+            Ok(diff) => {
+                self.same = Some(diff.is_empty());
+                self.lines = diff_lines(&diff, state.theme);
+            }
+            Err(e) => {
+                self.lines = vec![Line::from(e.to_string().set_style(state.theme.error))];
+            }
+        }
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        let page = self.page_height.max(1) as isize;
+        if let AppEvent::Key(key_event) = event {
+            match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q') => state.mode = AppMode::Presets,
+                KeyCode::Down | KeyCode::Char('j') => self.scroll_by(1),
+                KeyCode::Up | KeyCode::Char('k') => self.scroll_by(-1),
+                KeyCode::PageDown => self.scroll_by(page),
+                KeyCode::PageUp => self.scroll_by(-page),
+                KeyCode::Char('g') => self.scroll = 0,
+                KeyCode::Char('G') => self.scroll_by(self.lines.len() as isize),
+                _ => {}
+            }
+        }
+        Handled::REFRESH
+    }
+}
@@ -0,0 +1,128 @@
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    notifications::{Level, notify},
+    utils::popup_rect,
+    widgets::{confirm::Confirm, instructions::Instructions},
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::{Line, Text},
+    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget, Wrap},
+};
+
+/// Asks before killing the whole tmux server, which takes every session down with it
+#[derive(Default)]
+pub struct KillServerMenu<'a> {
+    confirm: Confirm<'a>,
+}
+
+impl KillServerMenu<'_> {
+    fn kill_server(&mut self, state: &mut AppState) {
+        // Checked again in case the server changed since the popup opened
+        if state.server.hosts_muffin() {
+            notify(
+                state,
+                Level::Error,
+                "Refusing to kill the server muffin runs in",
+            );
+            return;
+        }
+        match state.server.kill_server() {
+            Ok(_) => {
+                // Whatever was in the trash went down with the server
+                state.trash.clear();
+                state.mode = AppMode::Sessions;
+            }
+            Err(e) => notify(state, Level::Error, e.to_string()),
+        }
+    }
+}
+
+impl StatefulWidget for &mut KillServerMenu<'_> {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(area, 50, 11, state);
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.delete);
+        let inner_area = block.inner(area);
+
+        let [title_area, warning_area, input_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        // Render title
+        if state.notifications.is_empty() {
+            Line::from("Kill the tmux server?".set_style(state.theme.delete))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        // Render what would be lost
+        {
+            let count = state.sessions.len();
+            let warning = format!(
+                "{count} session{} and everything running in {} will be lost",
+                if count == 1 { "" } else { "s" },
+                if count == 1 { "it" } else { "them" },
+            );
+            Paragraph::new(Text::from(warning))
+                .wrap(Wrap { trim: true })
+                .centered()
+                .render(warning_area, buf);
+        }
+
+        // Render input field
+        {
+            let [input_area] = Layout::vertical([Constraint::Length(1)]).areas(input_area);
+            let [first_char, rest] =
+                Layout::horizontal([Constraint::Length(2), Constraint::Fill(1)])
+                    .horizontal_margin(3)
+                    .areas(input_area);
+
+            "> ".set_style(state.theme.delete).render(first_char, buf);
+            self.confirm.render(rest, buf, state.theme);
+        }
+
+        // Render instructions
+        {
+            let instructions = vec![("esc", "cancel"), ("enter", "kill server")];
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl Menu for KillServerMenu<'_> {
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        match event {
+            AppEvent::Key(key_event) => match key_event.code {
+                KeyCode::Esc => state.mode = AppMode::Sessions,
+                KeyCode::Enter if self.confirm.is_confirmed() => self.kill_server(state),
+                KeyCode::Enter => notify(state, Level::Warn, "Type yes to kill the server"),
+                _ => _ = self.confirm.handle_key(key_event),
+            },
+            AppEvent::Paste(text) => _ = self.confirm.paste(&text),
+            _ => {}
+        }
+        Handled::REFRESH
+    }
+}
@@ -0,0 +1,191 @@
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    notifications::{Level, notify},
+    utils::popup_rect,
+    widgets::{input::Input, instructions::Instructions},
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::Line,
+    widgets::{Block, Clear, StatefulWidget, Widget},
+};
+
+/// Which of the popup's inputs is being typed into
+#[derive(Default, PartialEq, Eq)]
+enum Field {
+    #[default]
+    Name,
+    Cwd,
+}
+
+/// Adds a window to the selected session
+pub struct NewWindowMenu<'a> {
+    /// The session the window goes into, picked when the popup opens
+    session: String,
+    name: Input<'a>,
+    cwd: Input<'a>,
+    focus: Field,
+}
+
+impl Default for NewWindowMenu<'_> {
+    fn default() -> Self {
+        Self {
+            session: String::new(),
+            name: Input::default().placeholder("tmux default"),
+            cwd: Input::default().placeholder("current pane's directory"),
+            focus: Field::default(),
+        }
+    }
+}
+
+impl<'a> NewWindowMenu<'a> {
+    fn focused(&mut self) -> &mut Input<'a> {
+        match self.focus {
+            Field::Name => &mut self.name,
+            Field::Cwd => &mut self.cwd,
+        }
+    }
+
+    fn create(&mut self, state: &mut AppState) {
+        let name = self.name.value();
+        let cwd = match shellexpand::full(self.cwd.value()) {
+            Ok(cwd) => cwd.to_string(),
+            Err(e) => {
+                notify(state, Level::Error, format!("Invalid directory: {e}"));
+                return;
+            }
+        };
+
+        // Left empty, tmux starts it wherever the session's current pane is
+        let cwd = match cwd.as_str() {
+            "" => tmux::INHERIT_CWD,
+            cwd => cwd,
+        };
+
+        let id = match state.server.new_window(
+            &self.session,
+            Some(name).filter(|n| !n.is_empty()),
+            Some(cwd),
+        ) {
+            Ok(id) => id,
+            Err(e) => {
+                notify(state, Level::Error, e.to_string());
+                return;
+            }
+        };
+
+        // The id is only useful to tmux, people know windows by index and name
+        let window = state
+            .server
+            .display_message(&id, "#{window_index}:#{window_name}")
+            .unwrap_or(id);
+        notify(
+            state,
+            Level::Info,
+            format!("Created window {window} in '{}'", self.session),
+        );
+        state.mode = AppMode::Sessions;
+    }
+}
+
+impl<'a> StatefulWidget for &mut NewWindowMenu<'a> {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(area, 40, 15, state);
+
+        let block = Block::bordered().border_style(state.theme.create);
+        let inner_area = block.inner(area);
+        Clear.render(area, buf);
+
+        let [title_area, name_area, cwd_area, _, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(2),
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(2),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        if state.notifications.is_empty() {
+            Line::from(format!("New window in '{}'", self.session).set_style(state.theme.create))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        // Render both input fields, the focused one with a cursor
+        for (field, label, input, field_area) in [
+            (Field::Name, "name", &mut self.name, name_area),
+            (Field::Cwd, "dir", &mut self.cwd, cwd_area),
+        ] {
+            let [label_area, first_char, rest] = Layout::horizontal([
+                Constraint::Length(5),
+                Constraint::Length(2),
+                Constraint::Fill(1),
+            ])
+            .horizontal_margin(1)
+            .areas(field_area);
+
+            label.set_style(state.theme.dim).render(label_area, buf);
+            if self.focus == field {
+                "> ".set_style(state.theme.create).render(first_char, buf);
+            }
+            input.render(rest, buf, state.theme, self.focus == field);
+        }
+
+        // Render instructions
+        {
+            let instructions = vec![
+                ("esc", "cancel"),
+                ("tab", "next field"),
+                ("enter", "create"),
+            ];
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl<'a> Menu for NewWindowMenu<'a> {
+    fn on_enter(&mut self, state: &AppState) {
+        let Some(session) = state
+            .selected_session
+            .and_then(|index| state.sessions.get(index))
+        else {
+            return;
+        };
+        self.session = session.name.clone();
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        match event {
+            AppEvent::Key(key_event) => match key_event.code {
+                KeyCode::Esc => state.mode = AppMode::Sessions,
+                KeyCode::Tab | KeyCode::BackTab => {
+                    self.focus = match self.focus {
+                        Field::Name => Field::Cwd,
+                        Field::Cwd => Field::Name,
+                    }
+                }
+                KeyCode::Enter => self.create(state),
+                _ => _ = self.focused().handle_key(key_event),
+            },
+            AppEvent::Paste(text) => _ = self.focused().paste(&text),
+            _ => {}
+        }
+        Handled::REFRESH
+    }
+}
@@ -0,0 +1,119 @@
+use super::presets::spawn;
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    utils::popup_rect,
+    widgets::instructions::Instructions,
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::{Line, Text},
+    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget, Wrap},
+};
+use tmux::Preset;
+
+/// Asks whether to launch a preset anyway when some of its pane commands can't be found, which
+/// would otherwise only show as "command not found" in panes nobody is looking at yet
+#[derive(Default)]
+pub struct NotFoundMenu {
+    /// As launched, so with the session's actual name
+    preset: Option<Preset>,
+    missing: Vec<String>,
+}
+
+impl NotFoundMenu {
+    fn launch_anyway(&mut self, state: &mut AppState) {
+        if let Some(preset) = self.preset.take() {
+            state.not_found = None;
+            spawn(state, &preset);
+        }
+    }
+
+    fn cancel(&mut self, state: &mut AppState) {
+        state.not_found = None;
+        state.adjust_on_launch = false;
+        state.mode = AppMode::Presets;
+    }
+}
+
+impl StatefulWidget for &mut NotFoundMenu {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(area, 60, 11, state);
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.warn);
+        let inner_area = block.inner(area);
+
+        let [title_area, explanation_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(2),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        // Render title
+        if state.notifications.is_empty() {
+            let content = match self.missing.len() {
+                1 => format!("1 command not found: {}", self.missing[0]),
+                n => format!("{n} commands not found: {}", self.missing.join(", ")),
+            };
+            Line::from(content.set_style(state.theme.warn))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        // Render what's wrong with them
+        let name = self
+            .preset
+            .as_ref()
+            .map(|preset| preset.name.as_str())
+            .unwrap_or_default();
+        Paragraph::new(Text::from(format!(
+            "Some panes of '{name}' start programs that aren't on PATH, launch it anyway?"
+        )))
+        .wrap(Wrap { trim: true })
+        .centered()
+        .render(explanation_area, buf);
+
+        // Render instructions
+        {
+            let instructions = vec![("y", "launch anyway"), ("n/esc", "cancel")];
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl Menu for NotFoundMenu {
+    fn on_enter(&mut self, state: &AppState) {
+        if let Some((preset, missing)) = &state.not_found {
+            self.preset = Some(preset.clone());
+            self.missing = missing.clone();
+        }
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        if let AppEvent::Key(key_event) = event {
+            match key_event.code {
+                KeyCode::Char('y') => self.launch_anyway(state),
+                KeyCode::Char('n' | 'q') | KeyCode::Esc => self.cancel(state),
+                _ => {}
+            }
+        }
+        Handled::REFRESH
+    }
+}
@@ -0,0 +1,167 @@
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    notifications::{Level, notify},
+    utils::popup_rect,
+    widgets::instructions::Instructions,
+};
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::{Style, Styled},
+    text::Line,
+    widgets::{Block, Clear, StatefulWidget, Widget},
+};
+use tui_textarea::{CursorMove, TextArea};
+
+/// Longest note accepted, in characters, a few screens' worth of breadcrumbs
+const MAX_NOTE: usize = 1024;
+
+/// Writes a note on the selected session, kept by tmux in the session itself so it's there for as
+/// long as the session is, whichever muffin looks at it
+#[derive(Default)]
+pub struct NoteMenu<'a> {
+    /// Id of the session, which stays the same if it's renamed meanwhile
+    session_id: String,
+    session: String,
+    text_area: TextArea<'a>,
+}
+
+impl NoteMenu<'_> {
+    fn note(&self) -> String {
+        self.text_area.lines().join("\n").trim().to_string()
+    }
+
+    fn len(&self) -> usize {
+        self.text_area
+            .lines()
+            .iter()
+            .map(|line| line.chars().count() + 1)
+            .sum::<usize>()
+            - 1
+    }
+
+    fn save(&mut self, state: &mut AppState) {
+        let note = self.note();
+        match state.server.set_note(&self.session_id, &note) {
+            Ok(_) => {
+                let msg = match note.is_empty() {
+                    true => format!("Cleared the note on '{}'", self.session),
+                    false => format!("Left a note on '{}'", self.session),
+                };
+                state.mode = AppMode::Sessions;
+                notify(state, Level::Info, msg);
+            }
+            Err(e) => notify(state, Level::Error, e.to_string()),
+        }
+    }
+}
+
+impl StatefulWidget for &mut NoteMenu<'_> {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(area, 60, 16, state);
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.rename);
+        let inner_area = block.inner(area);
+
+        let [title_area, text_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(2),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        // Render title
+        if state.notifications.is_empty() {
+            Line::from(format!("Note on '{}'", self.session).set_style(state.theme.rename))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        // Render the note being written
+        {
+            let style = match self.len() >= MAX_NOTE {
+                true => state.theme.warn,
+                false => Style::default(),
+            };
+            self.text_area.set_style(style);
+            self.text_area.set_placeholder_style(state.theme.dim);
+            self.text_area.set_cursor_line_style(Style::default());
+            self.text_area.set_cursor_style(state.theme.cursor);
+            self.text_area.render(text_area, buf);
+        }
+
+        // Render instructions
+        Instructions::new(
+            vec![
+                ("esc", "cancel"),
+                ("enter", "save"),
+                ("alt+enter", "new line"),
+            ],
+            state.theme,
+        )
+        .priority(&["enter", "esc"])
+        .render(instructions_area, buf);
+
+        block.render(area, buf);
+    }
+}
+
+impl Menu for NoteMenu<'_> {
+    fn on_enter(&mut self, state: &AppState) {
+        let Some(session) = state
+            .selected_session
+            .and_then(|index| state.sessions.get(index))
+        else {
+            return;
+        };
+        self.session_id = session.id.clone();
+        self.session = session.name.clone();
+
+        let note = session.note.as_deref().unwrap_or_default();
+        self.text_area = TextArea::new(note.lines().map(String::from).collect());
+        self.text_area
+            .set_placeholder_text("waiting on review, what's left to do, ...");
+        self.text_area.move_cursor(CursorMove::Bottom);
+        self.text_area.move_cursor(CursorMove::End);
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        let room = MAX_NOTE.saturating_sub(self.len());
+        let key_event = match event {
+            AppEvent::Key(key_event) => key_event,
+            AppEvent::Paste(text) => {
+                let text = text.replace("\r\n", "\n").replace('\r', "\n");
+                self.text_area
+                    .insert_str(text.chars().take(room).collect::<String>());
+                return Handled::REFRESH;
+            }
+            _ => return Handled::REFRESH,
+        };
+
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key_event.modifiers.contains(KeyModifiers::ALT);
+        match key_event.code {
+            KeyCode::Esc => state.mode = AppMode::Sessions,
+            KeyCode::Enter if alt && room > 0 => self.text_area.insert_newline(),
+            KeyCode::Enter if alt => {}
+            KeyCode::Enter => self.save(state),
+            // tui-textarea breaks the line on these too, which only alt+enter is meant to
+            KeyCode::Char('m' | 'j') if ctrl => {}
+            KeyCode::Char(_) if !ctrl && !alt && room == 0 => {}
+            _ => _ = self.text_area.input(key_event),
+        }
+        Handled::REFRESH
+    }
+}
@@ -0,0 +1,206 @@
+use super::{Handled, Menu};
+use crate::app::{
+    actions::{Entry, available, dispatch},
+    driver::{AppEvent, AppState},
+    utils::{fuzzy_score, popup_rect},
+    widgets::{input::Input, instructions::Instructions},
+};
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::{Styled, Stylize},
+    text::Line,
+    widgets::{Block, Clear, List, ListItem, ListState, StatefulWidget, Widget},
+};
+
+/// Every action by name, for the ones without a key and the keys no one remembers
+pub struct PaletteMenu<'a> {
+    input: Input<'a>,
+    /// Everything that applied when the palette was opened
+    entries: Vec<Entry>,
+    /// Indices into `entries` matching the input, best match first
+    matches: Vec<usize>,
+    list_state: ListState,
+}
+
+impl Default for PaletteMenu<'_> {
+    fn default() -> Self {
+        Self {
+            input: Input::default().placeholder("type to search actions"),
+            entries: vec![],
+            matches: vec![],
+            list_state: ListState::default(),
+        }
+    }
+}
+
+impl<'a> PaletteMenu<'a> {
+    fn update_matches(&mut self) {
+        let query = self.input.value();
+        let mut scored = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, e)| fuzzy_score(query, &e.title).map(|score| (score, idx)))
+            .collect::<Vec<_>>();
+        // A stable sort, so equally good matches stay in the registry's order
+        scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+        self.matches = scored.into_iter().map(|(_, idx)| idx).collect();
+        self.list_state
+            .select((!self.matches.is_empty()).then_some(0));
+    }
+
+    fn select_next(&mut self) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let next = match self.list_state.selected() {
+            Some(idx) => (idx + 1) % self.matches.len(),
+            None => 0,
+        };
+        self.list_state.select(Some(next));
+    }
+
+    fn select_previous(&mut self) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let previous = match self.list_state.selected() {
+            Some(0) | None => self.matches.len() - 1,
+            Some(idx) => idx - 1,
+        };
+        self.list_state.select(Some(previous));
+    }
+
+    /// Closes the palette, handing the highlighted action to the menu that carries it out
+    fn run_selected(&mut self, state: &mut AppState) {
+        let Some(entry) = self
+            .list_state
+            .selected()
+            .and_then(|idx| self.matches.get(idx))
+            .map(|&idx| &self.entries[idx])
+        else {
+            return;
+        };
+        state.mode = entry.panel.unwrap_or(state.focus).mode();
+        dispatch(entry.id.clone(), entry.panel, state);
+    }
+}
+
+impl<'a> StatefulWidget for &mut PaletteMenu<'a> {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(area, 60, 20, state);
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.accent);
+        let inner_area = block.inner(area);
+
+        let [title_area, input_area, actions_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        if state.notifications.is_empty() {
+            Line::from("Run an action".set_style(state.theme.accent))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        // Render input field
+        {
+            let [input_area, _] =
+                Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(input_area);
+            let [first_char, rest] =
+                Layout::horizontal([Constraint::Length(2), Constraint::Fill(1)])
+                    .horizontal_margin(1)
+                    .areas(input_area);
+
+            ": ".set_style(state.theme.accent).render(first_char, buf);
+            self.input.render(rest, buf, state.theme, true);
+        }
+
+        // Render matching actions, with their keys
+        {
+            let [list_area] = Layout::horizontal([Constraint::Fill(1)])
+                .horizontal_margin(1)
+                .areas(actions_area);
+
+            let items = self
+                .matches
+                .iter()
+                .map(|&idx| {
+                    let entry = &self.entries[idx];
+                    let mut line = Line::from(entry.title.as_str());
+                    if let Some(key) = entry.key {
+                        line.push_span(format!("  {key}").set_style(state.theme.dim));
+                    }
+                    ListItem::new(line)
+                })
+                .collect::<Vec<ListItem>>();
+
+            StatefulWidget::render(
+                List::new(items).highlight_style(state.theme.accent.bold()),
+                list_area,
+                buf,
+                &mut self.list_state,
+            );
+        }
+
+        // Render instructions
+        {
+            let instructions = vec![("esc", "cancel"), ("enter", "run"), ("↑/↓", "choose")];
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl<'a> Menu for PaletteMenu<'a> {
+    fn on_enter(&mut self, state: &AppState) {
+        self.entries = available(state);
+        self.update_matches();
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        match event {
+            AppEvent::Key(key_event) => match key_event.code {
+                KeyCode::Esc => state.mode = state.focus.mode(),
+                KeyCode::Down | KeyCode::Tab => self.select_next(),
+                KeyCode::Up | KeyCode::BackTab => self.select_previous(),
+                KeyCode::Char('n') if key_event.modifiers == KeyModifiers::CONTROL => {
+                    self.select_next()
+                }
+                KeyCode::Char('p') if key_event.modifiers == KeyModifiers::CONTROL => {
+                    self.select_previous()
+                }
+                KeyCode::Enter => self.run_selected(state),
+                _ => {
+                    if self.input.handle_key(key_event) {
+                        self.update_matches();
+                    }
+                }
+            },
+            AppEvent::Paste(text) => {
+                self.input.paste(&text);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        Handled::REFRESH
+    }
+}
@@ -0,0 +1,391 @@
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    notifications::{Level, notify},
+    utils::{popup_rect, wrap_to_width},
+    widgets::instructions::Instructions,
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::{Line, Span},
+    widgets::{
+        Block, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph, StatefulWidget,
+        Widget, Wrap,
+    },
+};
+use tmux::{LivePane, LiveWindow, Relayout, exact_target};
+
+/// A line of the list: a window, or one of its panes under it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    /// Index in `windows`
+    Window(usize),
+    /// Indices in `windows` and that window's panes
+    Pane(usize, usize),
+}
+
+/// Lists the selected session's windows and their panes, which can be zoomed, broken out into
+/// windows of their own, or marked and joined into another window, and windows launched from
+/// presets laid out again, all without attaching. The session's note goes above them.
+#[derive(Default)]
+pub struct PanesMenu {
+    session: String,
+    /// Left on the session with `n` in the sessions menu
+    note: Option<String>,
+    windows: Vec<(LiveWindow, Vec<LivePane>)>,
+    rows: Vec<Row>,
+    list_state: ListState,
+    /// Why the windows couldn't be listed, shown in their place
+    error: Option<String>,
+}
+
+impl PanesMenu {
+    /// Lists the windows again, keeping the highlighted pane (wherever it moved) or window
+    /// highlighted
+    fn refresh(&mut self, state: &AppState) {
+        let selected = self.selected_row();
+        let pane_id = self.selected_pane().map(|pane| pane.id.clone());
+        let window_index = selected.map(|row| match row {
+            Row::Window(w) | Row::Pane(w, _) => self.windows[w].0.index,
+        });
+
+        match state.server.list_session_panes(&self.session) {
+            Ok(windows) => {
+                self.windows = windows;
+                self.error = None;
+            }
+            Err(e) => {
+                self.windows = vec![];
+                self.error = Some(e.reason());
+            }
+        }
+        self.rows = self
+            .windows
+            .iter()
+            .enumerate()
+            .flat_map(|(w, (_, panes))| {
+                std::iter::once(Row::Window(w))
+                    .chain((0..panes.len()).map(move |p| Row::Pane(w, p)))
+            })
+            .collect();
+
+        let position = pane_id
+            .and_then(|id| {
+                self.rows.iter().position(|row| match *row {
+                    Row::Pane(w, p) => self.windows[w].1[p].id == id,
+                    Row::Window(_) => false,
+                })
+            })
+            .or_else(|| {
+                self.rows.iter().position(|row| {
+                    matches!(*row, Row::Window(w) if Some(self.windows[w].0.index) == window_index)
+                })
+            });
+        let fallback = self
+            .list_state
+            .selected()
+            .map(|index| index.min(self.rows.len().saturating_sub(1)));
+        self.list_state.select(match self.rows.is_empty() {
+            true => None,
+            false => position.or(fallback).or(Some(0)),
+        });
+    }
+
+    fn selected_row(&self) -> Option<Row> {
+        self.list_state
+            .selected()
+            .and_then(|index| self.rows.get(index))
+            .copied()
+    }
+
+    fn selected_pane(&self) -> Option<&LivePane> {
+        match self.selected_row()? {
+            Row::Pane(w, p) => self.windows[w].1.get(p),
+            Row::Window(_) => None,
+        }
+    }
+
+    /// `-t` target of the highlighted window, or of the highlighted pane's window
+    fn selected_window_target(&self) -> Option<String> {
+        let (Row::Window(w) | Row::Pane(w, _)) = self.selected_row()?;
+        let (window, _) = &self.windows[w];
+        Some(format!("{}:{}", exact_target(&self.session), window.index))
+    }
+
+    fn select_by(&mut self, delta: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let last = self.rows.len() - 1;
+        let index = self.list_state.selected().unwrap_or_default();
+        self.list_state
+            .select(Some(index.saturating_add_signed(delta).min(last)));
+    }
+
+    /// The highlighted pane, telling the user to pick one while a window is highlighted
+    fn pane_or_notify(&self, state: &mut AppState) -> Option<LivePane> {
+        let pane = self.selected_pane().cloned();
+        if pane.is_none() {
+            notify(state, Level::Info, "Highlight a pane first");
+        }
+        pane
+    }
+
+    fn zoom(&mut self, state: &mut AppState) {
+        let Some(pane) = self.pane_or_notify(state) else {
+            return;
+        };
+        if let Err(e) = state.server.zoom_pane(&pane.id) {
+            notify(state, Level::Error, e.reason());
+        }
+        self.refresh(state);
+    }
+
+    fn break_out(&mut self, state: &mut AppState) {
+        let Some(pane) = self.pane_or_notify(state) else {
+            return;
+        };
+        match state.server.break_pane(&pane.id, &self.session) {
+            Ok(_) => notify(
+                state,
+                Level::Info,
+                format!("Moved pane {} to a window of its own", pane.index),
+            ),
+            Err(e) => notify(state, Level::Error, e.reason()),
+        }
+        self.refresh(state);
+    }
+
+    /// Marks the highlighted pane for joining, or unmarks it if it already is
+    fn toggle_mark(&mut self, state: &mut AppState) {
+        let Some(pane) = self.pane_or_notify(state) else {
+            return;
+        };
+        if state.marked_pane.as_ref() == Some(&pane.id) {
+            state.marked_pane = None;
+            notify(state, Level::Info, format!("Unmarked pane {}", pane.index));
+        } else {
+            state.marked_pane = Some(pane.id);
+            let msg = format!("Marked pane {}, M joins it into a window", pane.index);
+            notify(state, Level::Info, msg);
+        }
+    }
+
+    /// Resizes the highlighted window's panes back to its preset's proportions, see
+    /// [`tmux::Server::relayout_window`]
+    fn relayout(&mut self, state: &mut AppState) {
+        let (Some(target), Some(Row::Window(w) | Row::Pane(w, _))) =
+            (self.selected_window_target(), self.selected_row())
+        else {
+            return;
+        };
+        let name = self.windows[w].0.name.clone();
+        match state.server.relayout_window(&target) {
+            Ok(Relayout::Applied) => notify(state, Level::Info, format!("Laid '{name}' out again")),
+            Ok(skipped) => notify(
+                state,
+                Level::Warn,
+                format!("Left '{name}' as is, {skipped}"),
+            ),
+            Err(e) => notify(state, Level::Error, e.reason()),
+        }
+        self.refresh(state);
+    }
+
+    /// Moves the marked pane into the highlighted window
+    fn join_marked(&mut self, state: &mut AppState) {
+        let Some(marked) = state.marked_pane.clone() else {
+            notify(state, Level::Info, "No pane is marked, m marks one");
+            return;
+        };
+        let Some(window) = self.selected_window_target() else {
+            return;
+        };
+        match state.server.join_pane(&marked, &window) {
+            Ok(_) => {
+                state.marked_pane = None;
+                notify(state, Level::Info, "Joined the marked pane into the window");
+            }
+            Err(e) => notify(state, Level::Error, e.reason()),
+        }
+        self.refresh(state);
+    }
+}
+
+impl StatefulWidget for &mut PanesMenu {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let width = area.width.saturating_sub(4).min(80);
+        // Within the borders and margins, and never more than a third of the screen
+        let note = match &self.note {
+            Some(note) => wrap_to_width(
+                note,
+                width.saturating_sub(4) as usize,
+                (area.height / 3).max(1) as usize,
+            ),
+            None => vec![],
+        };
+        let note_height = match note.len() {
+            0 => 0,
+            lines => lines as u16 + 1,
+        };
+        let height = 8 + note_height + self.rows.len().max(1) as u16;
+        let area = popup_rect(
+            area,
+            width,
+            height.min(area.height.saturating_sub(2)),
+            state,
+        );
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.accent);
+        let inner_area = block.inner(area);
+
+        let [title_area, note_area, list_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(note_height),
+            Constraint::Fill(1),
+            Constraint::Length(2),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        // Render title
+        if state.notifications.is_empty() {
+            Line::from(format!("Panes of '{}'", self.session).set_style(state.theme.accent))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        // Render the note, with a line between it and the windows
+        Paragraph::new(
+            note.into_iter()
+                .map(|line| Line::from(line.set_style(state.theme.dim)))
+                .collect::<Vec<_>>(),
+        )
+        .render(note_area, buf);
+
+        // Render windows with their panes under them
+        if let Some(error) = &self.error {
+            Paragraph::new(error.as_str().set_style(state.theme.error))
+                .wrap(Wrap { trim: true })
+                .centered()
+                .render(list_area, buf);
+        } else {
+            let items = self
+                .rows
+                .iter()
+                .map(|row| match *row {
+                    Row::Window(w) => {
+                        let (window, _) = &self.windows[w];
+                        ListItem::new(Line::from(vec![
+                            format!("{} {} ", state.icons.windows, window.index).into(),
+                            window.name.clone().set_style(state.theme.accent),
+                        ]))
+                    }
+                    Row::Pane(w, p) => {
+                        let pane = &self.windows[w].1[p];
+                        let mut spans = vec![Span::from(format!(
+                            "    {:>2}  {:>3}x{:<3}  {}",
+                            pane.index, pane.width, pane.height, pane.command
+                        ))];
+                        if pane.zoomed {
+                            spans.push("  zoomed".set_style(state.theme.accent));
+                        }
+                        if pane.dead {
+                            spans.push("  dead".set_style(state.theme.error));
+                        }
+                        if state.marked_pane.as_ref() == Some(&pane.id) {
+                            spans.push("  marked".set_style(state.theme.warn));
+                        }
+                        ListItem::new(Line::from(spans))
+                    }
+                })
+                .collect::<Vec<_>>();
+            StatefulWidget::render(
+                List::new(items)
+                    .highlight_symbol(state.icons.highlight)
+                    .highlight_spacing(HighlightSpacing::Always)
+                    .highlight_style(state.theme.highlight),
+                list_area,
+                buf,
+                &mut self.list_state,
+            );
+        }
+
+        // Render instructions
+        {
+            let mut instructions = vec![
+                ("z", "zoom"),
+                ("b", "break out"),
+                ("m", "mark"),
+                ("r", "relayout"),
+                ("esc", "back"),
+            ];
+            if state.marked_pane.is_some() {
+                instructions.insert(3, ("M", "join marked here"));
+            }
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl Menu for PanesMenu {
+    fn on_enter(&mut self, state: &AppState) {
+        let Some(session) = state
+            .selected_session
+            .and_then(|index| state.sessions.get(index))
+        else {
+            return;
+        };
+        self.session = session.name.clone();
+        self.note = session.note.clone();
+        self.refresh(state);
+        // Start out on the session's current pane
+        let current = self
+            .windows
+            .iter()
+            .position(|(window, _)| window.active)
+            .and_then(|w| {
+                let p = self.windows[w].1.iter().position(|pane| pane.active)?;
+                self.rows.iter().position(|row| *row == Row::Pane(w, p))
+            });
+        if current.is_some() {
+            self.list_state.select(current);
+        }
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        if let AppEvent::Key(key_event) = event {
+            match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q' | 'h') | KeyCode::Left => {
+                    state.mode = AppMode::Sessions
+                }
+                KeyCode::Down | KeyCode::Char('j') => self.select_by(1),
+                KeyCode::Up | KeyCode::Char('k') => self.select_by(-1),
+                KeyCode::Char('g') => self.select_by(-(self.rows.len() as isize)),
+                KeyCode::Char('G') => self.select_by(self.rows.len() as isize),
+                KeyCode::Char('z') => self.zoom(state),
+                KeyCode::Char('b') => self.break_out(state),
+                KeyCode::Char('m') => self.toggle_mark(state),
+                KeyCode::Char('M') => self.join_marked(state),
+                KeyCode::Char('r') => self.relayout(state),
+                _ => {}
+            }
+        }
+        Handled::REFRESH
+    }
+}
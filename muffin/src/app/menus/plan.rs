@@ -0,0 +1,141 @@
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    utils::popup_rect,
+    widgets::instructions::Instructions,
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::{Line, Text},
+    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget},
+};
+
+/// Lists the tmux commands launching the selected preset would run, without running them
+#[derive(Default)]
+pub struct PlanMenu {
+    preset: String,
+    lines: Vec<String>,
+    /// Index of the first line shown
+    scroll: usize,
+    /// How many lines fit in the popup, as of the last render
+    page_height: usize,
+}
+
+impl PlanMenu {
+    fn scroll_by(&mut self, delta: isize) {
+        let max = self.lines.len().saturating_sub(self.page_height);
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max);
+    }
+}
+
+impl StatefulWidget for &mut PlanMenu {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let area = popup_rect(
+            area,
+            area.width.saturating_sub(4).min(100),
+            area.height.saturating_sub(2),
+            state,
+        );
+        Clear.render(area, buf);
+
+        let block = Block::bordered().border_style(state.theme.plan);
+        let inner_area = block.inner(area);
+
+        let [title_area, lines_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        self.page_height = lines_area.height as usize;
+        self.scroll_by(0);
+
+        // Render title
+        {
+            let shown = self.lines.len().min(self.scroll + self.page_height);
+            let content = format!(
+                "Launching '{}' runs ({}-{shown} of {})",
+                self.preset,
+                (self.scroll + 1).min(shown),
+                self.lines.len()
+            );
+            Line::from(content.set_style(state.theme.plan))
+                .centered()
+                .render(title_area, buf);
+        }
+
+        // Render commands, in the order they run
+        {
+            let lines = self
+                .lines
+                .iter()
+                .skip(self.scroll)
+                .take(self.page_height)
+                .map(|line| match line.rsplit_once("  # ") {
+                    // Commands held back by their startup options say what they wait on
+                    Some((command, note)) => Line::from(vec![
+                        command.into(),
+                        format!("  # {note}").set_style(state.theme.dim),
+                    ]),
+                    None => Line::from(line.as_str()),
+                })
+                .collect::<Vec<_>>();
+            Paragraph::new(Text::from(lines)).render(lines_area, buf);
+        }
+
+        // Render instructions
+        {
+            let instructions = vec![
+                ("esc", "back"),
+                ("j/↓", "down"),
+                ("k/↑", "up"),
+                ("pgdn/pgup", "page"),
+            ];
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl Menu for PlanMenu {
+    fn on_enter(&mut self, state: &AppState) {
+        let Some(preset) = state.highlighted_preset() else {
+            return;
+        };
+        self.preset = preset.name.clone();
+        // A layout tmux can't be asked for is said in place of the commands
+        self.lines = state
+            .server
+            .dry_run(preset)
+            .unwrap_or_else(|e| vec![format!("Cannot launch '{}': {e}", preset.name)]);
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        let page = self.page_height.max(1) as isize;
+        if let AppEvent::Key(key_event) = event {
+            match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q') => state.mode = AppMode::Presets,
+                KeyCode::Down | KeyCode::Char('j') => self.scroll_by(1),
+                KeyCode::Up | KeyCode::Char('k') => self.scroll_by(-1),
+                KeyCode::PageDown => self.scroll_by(page),
+                KeyCode::PageUp => self.scroll_by(-page),
+                KeyCode::Char('g') => self.scroll = 0,
+                KeyCode::Char('G') => self.scroll_by(self.lines.len() as isize),
+                _ => {}
+            }
+        }
+        Handled::REFRESH
+    }
+}
@@ -1,23 +1,82 @@
-use super::Menu;
+use std::collections::{BTreeSet, HashSet};
+use std::ops::{Bound, ControlFlow};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use super::{Handled, Menu};
 use crate::app::{
-    driver::{AppEvent, AppMode, AppState},
-    utils::{make_instructions, send_timed_notification},
+    actions::ActionId,
+    driver::{AppEvent, AppMode, AppState, Panel},
+    notifications::{Level, notify, notify_desktop},
+    preflight,
+    startup::{Launch, start_pending},
+    state::UiState,
+    utils::{
+        ClickTracker, accent_color, count_column_width, fit_to_width, is_navigation, list_row_at,
+        make_title, name_column_width, truncate_to_width, wrap_to_width,
+    },
+    widgets::instructions::Instructions,
 };
-use crossterm::event::KeyCode;
+use crate::{export, starter};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use parser::Source;
 use ratatui::{
     prelude::{Buffer, Constraint, Layout, Rect},
-    style::{Style, Stylize},
+    style::{Styled, Stylize},
     symbols::border,
     text::Line,
     widgets::{
-        Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph,
+        Block, Borders, Clear, HighlightSpacing, LineGauge, List, ListItem, ListState, Paragraph,
         StatefulWidget, Widget, Wrap,
     },
 };
+use tmux::{Preset, PresetSpawn, PresetUpdate, SpawnProgress, TmuxError};
+use tokio::time::Instant;
+use unicode_width::UnicodeWidthStr;
+
+/// Room after a preset's name for whether it's running
+const RUNNING_WIDTH: u16 = 11;
+
+/// How long a launch takes for its end to be worth a desktop notification
+const LONG_LAUNCH: Duration = Duration::from_secs(2);
+
+/// What the list shows
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+enum ListLevel {
+    #[default]
+    Presets,
+    /// The windows of the named preset, each of which can be started on its own
+    Windows(String),
+}
+
+/// A row of the presets list
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Row {
+    /// Presets read from `path`, and how many of them are shown. Only listed when presets come
+    /// from more than one file.
+    File { path: PathBuf, count: usize },
+    /// Index into `state.presets`
+    Preset(usize),
+}
 
 pub struct PresetsMenu {
     list_state: ListState,
-    notification: Option<String>,
+    /// The list's rows: file headers, and the presets shown, which are only those with
+    /// `tag_filter` if there is one
+    rows: Vec<Row>,
+    tag_filter: Option<String>,
+    level: ListLevel,
+    /// Highlighted window while a preset is expanded
+    window_state: ListState,
+    /// Where the list was last drawn, to map mouse clicks onto rows
+    list_area: Rect,
+    /// Rows the highlighted preset's description took under it, as of the last render
+    description_height: usize,
+    clicks: ClickTracker,
+    /// Files whose presets are hidden
+    collapsed: HashSet<PathBuf>,
 }
 
 impl PresetsMenu {
@@ -26,46 +85,706 @@ impl PresetsMenu {
         list_state.select(index);
         Self {
             list_state,
-            notification: None,
+            rows: vec![],
+            tag_filter: None,
+            level: ListLevel::Presets,
+            window_state: ListState::default(),
+            list_area: Rect::default(),
+            description_height: 0,
+            clicks: ClickTracker::default(),
+            collapsed: HashSet::new(),
         }
     }
 
-    pub fn select_next(&mut self, length: usize) -> Option<usize> {
-        self.list_state.select_next();
-        self.list_state
-            .selected()
-            .map(|idx| idx.clamp(0, length.saturating_sub(1)))
+    pub fn select_next(&mut self, state: &AppState) -> Option<String> {
+        let next = match self.list_state.selected() {
+            Some(current) => self.stops().find(|&index| index > current),
+            None => self.stops().next(),
+        };
+        if next.is_some() {
+            self.list_state.select(next);
+        }
+        self.selected(state)
     }
 
-    pub fn select_previous(&mut self, length: usize) -> Option<usize> {
-        self.list_state.select_previous();
-        self.list_state
-            .selected()
-            .map(|idx| idx.clamp(0, length.saturating_sub(1)))
+    pub fn select_previous(&mut self, state: &AppState) -> Option<String> {
+        let previous = match self.list_state.selected() {
+            Some(current) => self.stops().rfind(|&index| index < current),
+            None => self.stops().last(),
+        };
+        if previous.is_some() {
+            self.list_state.select(previous);
+        }
+        self.selected(state)
     }
 
-    pub fn select_first(&mut self, length: usize) -> Option<usize> {
-        self.list_state.select_first();
-        self.list_state
-            .selected()
-            .map(|idx| idx.clamp(0, length.saturating_sub(1)))
+    pub fn select_first(&mut self, state: &AppState) -> Option<String> {
+        let first = self.stops().next();
+        if first.is_some() {
+            self.list_state.select(first);
+        }
+        self.selected(state)
     }
 
-    pub fn select_middle(&mut self, length: usize) -> Option<usize> {
-        if length > 0 {
-            let new_index = (length.saturating_sub(1)).div_ceil(2);
-            self.list_state.select(Some(new_index));
+    pub fn select_middle(&mut self, state: &AppState) -> Option<String> {
+        let stops = self.stops().collect::<Vec<_>>();
+        if !stops.is_empty() {
+            let middle = (stops.len() - 1).div_ceil(2);
+            self.list_state.select(Some(stops[middle]));
         }
+        self.selected(state)
+    }
+
+    pub fn select_last(&mut self, state: &AppState) -> Option<String> {
+        let last = self.stops().last();
+        if last.is_some() {
+            self.list_state.select(last);
+        }
+        self.selected(state)
+    }
+
+    /// Rows moving up and down lands on: every preset, and the headers of collapsed files, which
+    /// stand in for their presets. The others are passed over.
+    fn stops(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        (0..self.rows.len()).filter(|&index| match &self.rows[index] {
+            Row::Preset(_) => true,
+            Row::File { .. } => !matches!(self.rows.get(index + 1), Some(Row::Preset(_))),
+        })
+    }
+
+    /// Name of the highlighted preset, as of the last time the displayed presets were computed.
+    /// `None` on a file header.
+    fn selected(&self, state: &AppState) -> Option<String> {
+        self.list_state.selected().and_then(|idx| {
+            let idx = idx.clamp(0, self.rows.len().saturating_sub(1));
+            let Row::Preset(idx) = self.rows.get(idx)? else {
+                return None;
+            };
+            let preset = state.presets.get(*idx)?;
+            Some(preset.name.clone())
+        })
+    }
+
+    /// Row of the preset named `name`, if it's displayed
+    fn position(&self, name: &str, state: &AppState) -> Option<usize> {
+        self.rows.iter().position(|row| {
+            matches!(row, Row::Preset(i) if state.presets.get(*i).is_some_and(|p| p.name == name))
+        })
+    }
+
+    /// Highlights the preset named `name`, if it's displayed
+    fn highlight(&mut self, name: Option<&str>, state: &AppState) {
         self.list_state
+            .select(name.and_then(|name| self.position(name, state)));
+    }
+
+    /// Launches the highlighted preset, or collapses or expands the highlighted file
+    fn activate_selected(&mut self, state: &mut AppState) {
+        let highlighted = self.list_state.selected().and_then(|i| self.rows.get(i));
+        if matches!(highlighted, Some(Row::File { .. })) {
+            self.toggle_selected_file(state);
+        } else {
+            self.launch_selected(state);
+        }
+    }
+
+    /// Collapses or expands the highlighted file, or the file the highlighted preset is listed
+    /// under, leaving its header highlighted
+    fn toggle_selected_file(&mut self, state: &mut AppState) {
+        let grouped = self.rows.iter().any(|row| matches!(row, Row::File { .. }));
+        let path = match self.list_state.selected().and_then(|i| self.rows.get(i)) {
+            Some(Row::File { path, .. }) => path.clone(),
+            Some(Row::Preset(idx)) if grouped => {
+                match state.presets.get(*idx).and_then(|p| p.source.clone()) {
+                    Some(path) => path,
+                    None => return,
+                }
+            }
+            _ => return,
+        };
+        if !self.collapsed.remove(&path) {
+            self.collapsed.insert(path.clone());
+            // The highlighted preset would keep its file expanded
+            state.selected_preset = None;
+        }
+        self.filter(state);
+        self.list_state.select(
+            self.rows
+                .iter()
+                .position(|row| matches!(row, Row::File { path: file, .. } if *file == path)),
+        );
+    }
+
+    /// Picks up the tag filter and collapsed files saved last time, unless no preset has that
+    /// tag or comes from that file anymore
+    pub fn restore(&mut self, ui: &UiState, state: &AppState) {
+        self.tag_filter = ui
+            .tag_filter
+            .clone()
+            .filter(|tag| state.presets.iter().any(|p| p.tags.contains(tag)));
+        self.collapsed = ui
+            .collapsed_files
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| {
+                state
+                    .presets
+                    .iter()
+                    .any(|p| p.source.as_ref() == Some(path))
+            })
+            .collect();
+        self.filter(state);
+    }
+
+    /// Saves the tag filter and collapsed files into `ui`
+    pub fn snapshot(&self, ui: &mut UiState) {
+        ui.tag_filter = self.tag_filter.clone();
+        ui.collapsed_files = self
+            .collapsed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        ui.collapsed_files.sort();
+    }
+
+    /// Recomputes which presets are displayed under the current tag filter, and under which
+    /// file headers
+    fn filter(&mut self, state: &AppState) {
+        let displayed = state
+            .presets
+            .iter()
+            .enumerate()
+            .filter(|(_, preset)| {
+                self.tag_filter
+                    .as_ref()
+                    .is_none_or(|tag| preset.tags.contains(tag))
+            })
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+
+        // The highlighted preset can't be hidden, ex: when it's launched from the palette
+        if let Some(source) = state
+            .selected_preset
+            .as_deref()
+            .and_then(|name| state.presets.get_by_name(name))
+            .and_then(|preset| preset.source.as_ref())
+        {
+            self.collapsed.remove(source);
+        }
+
+        // Filtering looks into every file, collapsed or not
+        let collapsed = if self.tag_filter.is_none() {
+            &self.collapsed
+        } else {
+            &HashSet::new()
+        };
+        // Stay on the same file header if the presets around it changed
+        let selected_file = self
+            .list_state
             .selected()
-            .map(|idx| idx.clamp(0, length.saturating_sub(1)))
+            .and_then(|index| self.rows.get(index))
+            .and_then(|row| match row {
+                Row::File { path, .. } => Some(path.clone()),
+                Row::Preset(_) => None,
+            });
+        self.rows = file_rows(&displayed, state, collapsed);
+        if let Some(index) = selected_file.and_then(|selected| {
+            self.rows
+                .iter()
+                .position(|row| matches!(row, Row::File { path, .. } if *path == selected))
+        }) {
+            self.list_state.select(Some(index));
+        }
     }
 
-    pub fn select_last(&mut self, length: usize) -> Option<usize> {
-        self.list_state.select_last();
-        self.list_state
+    /// Moves the tag filter on to the next known tag, then back to showing everything
+    fn cycle_tag_filter(&mut self, state: &mut AppState) {
+        let tags = state
+            .presets
+            .iter()
+            .flat_map(|preset| preset.tags.iter())
+            .collect::<BTreeSet<_>>();
+        self.tag_filter = match &self.tag_filter {
+            None => tags.first().map(|tag| tag.to_string()),
+            Some(current) => tags
+                .range::<String, _>((Bound::Excluded(current), Bound::Unbounded))
+                .next()
+                .map(|tag| tag.to_string()),
+        };
+
+        self.filter(state);
+        state.selected_preset = self.select_first(state);
+    }
+
+    /// Launches the highlighted preset, first asking for its variables if it has any
+    fn launch_selected(&mut self, state: &mut AppState) {
+        let Some(name) = state.selected_preset.clone() else {
+            return;
+        };
+        // Reloading the presets can take it away from under the highlight
+        let Some(preset) = state.presets.get_by_name(&name) else {
+            notify(
+                state,
+                Level::Error,
+                format!("Preset '{name}' no longer exists"),
+            );
+            return;
+        };
+        if state.picking {
+            state.picked = Some(name);
+            state.exit = true;
+            return;
+        }
+        if let Some(launch) = &state.launching {
+            let msg = format!("Already launching '{}'", launch.preset.name);
+            notify(state, Level::Info, msg);
+            return;
+        }
+        if preset.vars.is_empty() {
+            launch(state, &preset.clone());
+        } else {
+            state.mode = AppMode::Vars;
+        }
+    }
+
+    /// Reads the presets file again, keeping the same preset highlighted if it's still there
+    fn reload(&mut self, state: &mut AppState) {
+        let selected = state.selected_preset.clone();
+        let (level, msg) = match state.reload_presets(selected.as_deref()) {
+            Ok(_) => (
+                Level::Info,
+                format!("Reloaded {} presets", state.presets.len()),
+            ),
+            Err(e) => (Level::Error, e),
+        };
+        self.filter(state);
+        self.highlight(state.selected_preset.as_deref(), state);
+        notify(state, level, msg);
+    }
+
+    /// Writes the starter presets file where the default one is missing, and reads the presets
+    /// from it from then on
+    fn write_starter(&mut self, state: &mut AppState) {
+        let Some(path) = state.starter_path.clone() else {
+            return;
+        };
+        if let Err(e) = starter::write_starter(&path) {
+            notify(state, Level::Error, e);
+            return;
+        }
+        state.starter_path = None;
+        state.preset_sources = vec![Source::File(path.clone())];
+
+        let (level, msg) = match state.reload_presets(None) {
+            Ok(_) => (
+                Level::Info,
+                format!(
+                    "Wrote {} example presets to {}",
+                    state.presets.len(),
+                    path.display()
+                ),
+            ),
+            Err(e) => (Level::Error, e),
+        };
+        self.filter(state);
+        self.highlight(state.selected_preset.as_deref(), state);
+        notify(state, level, msg);
+    }
+
+    /// Carries out an action chosen in the command palette
+    fn run_action(&mut self, id: ActionId, state: &mut AppState) {
+        match id {
+            ActionId::ReloadPresets => self.reload(state),
+            ActionId::ExportPreset => export_highlighted(state),
+            ActionId::PlanPreset if state.selected_preset.is_some() => state.mode = AppMode::Plan,
+            ActionId::DiffPreset => diff_highlighted(state),
+            ActionId::ShowDiagnostics => state.mode = AppMode::Diagnostics,
+            ActionId::LaunchPreset => {
+                state.adjust_on_launch = false;
+                self.launch_selected(state);
+            }
+            ActionId::Top if self.level == ListLevel::Presets => {
+                state.selected_preset = self.select_first(state)
+            }
+            ActionId::Bottom if self.level == ListLevel::Presets => {
+                state.selected_preset = self.select_last(state)
+            }
+            ActionId::Launch(name) => {
+                // The tag filter could be hiding it, or its file be collapsed
+                state.selected_preset = Some(name.clone());
+                self.filter(state);
+                if self.position(&name, state).is_none() {
+                    self.tag_filter = None;
+                    self.filter(state);
+                }
+                self.highlight(Some(&name), state);
+                state.adjust_on_launch = false;
+                self.launch_selected(state);
+            }
+            _ => {}
+        }
+    }
+
+    /// Lists the highlighted preset's windows in place of the presets
+    fn expand_selected(&mut self, state: &AppState) {
+        if let Some(preset) = state
+            .highlighted_preset()
+            .filter(|preset| !preset.windows.is_empty())
+        {
+            self.level = ListLevel::Windows(preset.name.clone());
+            self.window_state.select(Some(0));
+        }
+    }
+
+    /// Starts only the highlighted window of the expanded preset: in the preset's session if it
+    /// exists, else in a new session holding just that window
+    fn spawn_selected_window(&mut self, state: &mut AppState) {
+        let ListLevel::Windows(name) = &self.level else {
+            return;
+        };
+        let Some(preset) = state.presets.get_by_name(name) else {
+            return;
+        };
+        let Some(window) = self
+            .window_state
             .selected()
-            .map(|idx| idx.clamp(0, length.saturating_sub(1)))
+            .and_then(|index| preset.windows.get(index))
+        else {
+            return;
+        };
+
+        // Variables can only be asked for when launching the whole preset, so defaults it is
+        let single = Preset {
+            windows: vec![window.clone()],
+            ..preset.clone()
+        };
+        let single = match parser::apply_vars(&single, &Default::default()) {
+            Ok(single) => single,
+            Err(e) => {
+                notify(
+                    state,
+                    Level::Error,
+                    format!("{e}, launch the whole preset to fill it in"),
+                );
+                return;
+            }
+        };
+
+        // The preset's own server, if it names one
+        let server = state.server.for_preset(&single);
+        let elsewhere = state.other_server(&single).is_some();
+        let result = if preset.running {
+            server.append_preset(&single, &single.name).map(|append| {
+                let msg = format!(
+                    "Added window {} to '{}'",
+                    append.added.join(", "),
+                    single.name
+                );
+                (append.pending, append.warnings, msg)
+            })
+        } else {
+            server.spawn_preset(&single).map(|spawn| {
+                let msg = format!("Started '{}' with only window {}", single.name, window.name);
+                (spawn.pending, spawn.warnings, msg)
+            })
+        };
+        match result {
+            Ok((pending, warnings, msg)) => {
+                start_pending(state, server, pending, warnings);
+                notify(state, Level::Info, msg);
+                // Sessions on other servers aren't listed
+                if !elsewhere {
+                    state.focus_session = Some(single.name);
+                    state.mode = AppMode::Sessions;
+                }
+            }
+            Err(e) => notify(state, Level::Error, e.to_string()),
+        }
+    }
+
+    fn handle_window_key(&mut self, key_event: KeyEvent, state: &mut AppState) {
+        match key_event.code {
+            KeyCode::Down | KeyCode::Char('j') => self.window_state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.window_state.select_previous(),
+            KeyCode::Char('g') => self.window_state.select_first(),
+            KeyCode::Char('G') => self.window_state.select_last(),
+            KeyCode::Esc | KeyCode::Left | KeyCode::Char('h') => self.level = ListLevel::Presets,
+            KeyCode::Enter => self.spawn_selected_window(state),
+            KeyCode::Tab => state.mode = AppMode::Sessions,
+            KeyCode::Char('q') => state.exit = true,
+            _ => {}
+        }
+    }
+
+    /// Picking only chooses a preset, so moving around and filtering is all there is besides
+    fn handle_pick_key(&mut self, key_event: KeyEvent, state: &mut AppState) {
+        match key_event.code {
+            KeyCode::Down | KeyCode::Char('j') => state.selected_preset = self.select_next(state),
+            KeyCode::Up | KeyCode::Char('k') => state.selected_preset = self.select_previous(state),
+            KeyCode::Char('g') => state.selected_preset = self.select_first(state),
+            KeyCode::Char('M') => state.selected_preset = self.select_middle(state),
+            KeyCode::Char('G') => state.selected_preset = self.select_last(state),
+            KeyCode::Char('t') => self.cycle_tag_filter(state),
+            KeyCode::Esc | KeyCode::Char('q') => state.exit = true,
+            KeyCode::Enter => self.activate_selected(state),
+            KeyCode::Char(' ') => self.toggle_selected_file(state),
+            _ => {}
+        }
+    }
+
+    fn handle_window_mouse(&mut self, mouse: MouseEvent, state: &mut AppState) {
+        match mouse.kind {
+            MouseEventKind::ScrollDown => self.window_state.select_next(),
+            MouseEventKind::ScrollUp => self.window_state.select_previous(),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(index) = list_row_at(
+                    self.list_area,
+                    self.window_state.offset(),
+                    mouse.column,
+                    mouse.row,
+                ) else {
+                    return;
+                };
+                self.window_state.select(Some(index));
+                if self.clicks.click(index) {
+                    self.spawn_selected_window(state);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, state: &mut AppState) {
+        if self.level != ListLevel::Presets {
+            return self.handle_window_mouse(mouse, state);
+        }
+        match mouse.kind {
+            MouseEventKind::ScrollDown => state.selected_preset = self.select_next(state),
+            MouseEventKind::ScrollUp => state.selected_preset = self.select_previous(state),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(index) = list_row_at(
+                    self.list_area,
+                    self.list_state.offset(),
+                    mouse.column,
+                    mouse.row,
+                )
+                // The highlighted preset's description pushes the ones after it down
+                .map(|row| match self.list_state.selected() {
+                    Some(highlighted) if row > highlighted => {
+                        row.saturating_sub(self.description_height).max(highlighted)
+                    }
+                    _ => row,
+                })
+                .filter(|&index| index < self.rows.len()) else {
+                    return;
+                };
+                self.list_state.select(Some(index));
+                state.selected_preset = self.selected(state);
+                if self.clicks.click(index) {
+                    state.adjust_on_launch = false;
+                    self.activate_selected(state);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Lays `presets` (indices into `state.presets`, in display order) out in rows. Presets from more
+/// than one file are listed under a header per file, in the order the files were read, and
+/// within it in the order they're defined. Collapsed files only get their header.
+fn file_rows(presets: &[usize], state: &AppState, collapsed: &HashSet<PathBuf>) -> Vec<Row> {
+    let sources = state
+        .presets
+        .iter()
+        .filter_map(|preset| preset.source.as_deref())
+        .collect::<HashSet<&Path>>();
+    if sources.len() < 2 {
+        return presets.iter().copied().map(Row::Preset).collect();
+    }
+
+    let source_of = |idx: usize| state.presets.get(idx).and_then(|p| p.source.as_deref());
+    // Presets that didn't come from a file go first, without a header
+    let mut rows = presets
+        .iter()
+        .copied()
+        .filter(|&idx| source_of(idx).is_none())
+        .map(Row::Preset)
+        .collect::<Vec<_>>();
+    for file in state.presets.files() {
+        let mut members = presets
+            .iter()
+            .copied()
+            .filter(|&idx| source_of(idx) == Some(file.as_path()))
+            .collect::<Vec<_>>();
+        members.sort_by_key(|&idx| state.presets.get(idx).map(|p| p.position));
+        if members.is_empty() {
+            continue;
+        }
+        rows.push(Row::File {
+            path: file.clone(),
+            count: members.len(),
+        });
+        if !collapsed.contains(file) {
+            rows.extend(members.into_iter().map(Row::Preset));
+        }
+    }
+    rows
+}
+
+/// Launches `preset`, then either switches to it and quits or highlights it in the sessions menu.
+///
+/// When asked for with [`AppState::adjust_on_launch`], its panes are adjusted first.
+///
+/// A preset that's already running is only switched to (or adjusted), while a session of the
+/// same name muffin didn't launch from it is left for the user to decide about first (see
+/// [`ConflictMenu`](super::conflict::ConflictMenu)), as are pane commands that can't be found
+/// (see [`NotFoundMenu`](super::not_found::NotFoundMenu)).
+pub(super) fn launch(state: &mut AppState, preset: &Preset) {
+    if let Some(launch) = &state.launching {
+        let msg = format!("Already launching '{}'", launch.preset.name);
+        notify(state, Level::Info, msg);
+        return;
+    }
+
+    let taken = state
+        .sessions_for(preset)
+        .iter()
+        .find(|s| s.name == preset.name);
+    match taken {
+        Some(session) if session.is_preset(&preset.name) => {
+            // Nothing to launch, but its panes can still be adjusted or respawned
+            if std::mem::take(&mut state.adjust_on_launch) {
+                state.adjusting = Some(preset.clone());
+                state.mode = AppMode::Adjust;
+            } else {
+                finish_launch(state, preset);
+            }
+            return;
+        }
+        Some(_) => {
+            state.conflicting = Some(preset.clone());
+            state.mode = AppMode::Conflict;
+            return;
+        }
+        None => {}
+    }
+
+    // Commands are looked up here, which says nothing about another machine
+    if state.server.for_preset(preset).is_local() {
+        let missing = preflight::missing_commands(preset);
+        if !missing.is_empty() {
+            state.not_found = Some((preset.clone(), missing));
+            state.mode = AppMode::NotFound;
+            return;
+        }
+    }
+
+    spawn(state, preset);
+}
+
+/// Creates `preset`'s session in the background, reporting its progress to the presets menu,
+/// which takes it from there once it's done (see [`finish_spawn`])
+pub(super) fn spawn(state: &mut AppState, preset: &Preset) {
+    // Not worth stopping over, tmux lays out as much as fits
+    let cramped = preflight::cramped_windows(preset, &state.server.for_preset(preset));
+    if !cramped.is_empty() {
+        notify(state, Level::Warn, cramped.join("; "));
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let server = state.server.clone();
+    let tx = state.event_handler.tx.clone();
+    let launched = preset.clone();
+    let stopped = stop.clone();
+    tokio::task::spawn_blocking(move || {
+        let name = launched.name.clone();
+        let result = server.spawn_preset_with_progress(&launched, |progress| {
+            let _ = tx.send(AppEvent::SpawnProgress {
+                preset: name.clone(),
+                step: progress.step,
+                total: progress.total,
+                detail: progress.detail,
+            });
+            match stopped.load(Ordering::Relaxed) {
+                true => ControlFlow::Break(()),
+                false => ControlFlow::Continue(()),
+            }
+        });
+        let _ = tx.send(AppEvent::SpawnFinished {
+            preset: name,
+            result,
+        });
+    });
+
+    state.launching = Some(Launch {
+        preset: preset.clone(),
+        progress: None,
+        adjust: std::mem::take(&mut state.adjust_on_launch),
+        stop,
+        started: Instant::now(),
+    });
+    // Where the progress shows
+    state.mode = AppMode::Presets;
+}
+
+/// Takes a launch from where the background spawn left it: adjusting its panes, switching to it
+/// or reporting why it didn't get there
+fn finish_spawn(state: &mut AppState, result: Result<ControlFlow<(), PresetSpawn>, TmuxError>) {
+    let Some(launch) = state.launching.take() else {
+        return;
+    };
+    let name = launch.preset.name.clone();
+    match result {
+        Ok(ControlFlow::Continue(spawn)) => {
+            // Long enough to have looked away in the meantime
+            if launch.started.elapsed() >= LONG_LAUNCH {
+                notify_desktop(state, Level::Info, &format!("Launched '{name}'"));
+            }
+            let server = state.server.for_preset(&launch.preset);
+            start_pending(state, server, spawn.pending, spawn.warnings);
+            match state.mode {
+                _ if launch.adjust => {
+                    state.adjusting = Some(launch.preset);
+                    state.mode = AppMode::Adjust;
+                }
+                AppMode::Sessions | AppMode::Presets => finish_launch(state, &launch.preset),
+                // Something else was started in the meantime, which isn't to be taken away
+                _ => notify(state, Level::Info, format!("Launched '{name}'")),
+            }
+        }
+        Ok(ControlFlow::Break(())) => notify(
+            state,
+            Level::Warn,
+            format!("Stopped launching '{name}', its session is left as far as it got"),
+        ),
+        Err(e) => notify(state, Level::Error, e.to_string()),
+    }
+}
+
+/// Switches to the launched preset's session and quits, or highlights it in the sessions menu.
+///
+/// Clients can't be switched over to another server, so a session on one is attached to once
+/// muffin quits instead.
+pub(super) fn finish_launch(state: &mut AppState, preset: &Preset) {
+    let session = preset.name.as_str();
+    if let Some(server) = state.other_server(preset) {
+        state.attach = Some((server, session.to_string()));
+        state.exit = true;
+    } else if state.exit_on_switch {
+        match state.server.switch_session(session) {
+            Ok(_) => {
+                // Set first, for a warning said on the way out to go to the desktop
+                state.exit = true;
+                if let Err(e) = state.mru.touch_name(session) {
+                    notify(state, Level::Warn, e);
+                }
+            }
+            Err(e) => notify(state, Level::Error, e.to_string()),
+        }
+    } else {
+        state.focus_session = Some(session.to_string());
+        state.mode = AppMode::Sessions;
     }
 }
 
@@ -74,7 +793,18 @@ impl StatefulWidget for &mut PresetsMenu {
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
         Clear.render(area, buf);
-        let block = Block::bordered().border_set(border::THICK);
+        let focused = state.is_focused(Panel::Presets);
+        // Picking gets nothing but the list, there's nothing to do besides choosing
+        let stripped = state.picking;
+        let block = if stripped {
+            Block::new()
+        } else {
+            Block::bordered().border_set(if focused {
+                border::THICK
+            } else {
+                border::PLAIN
+            })
+        };
 
         let inner_area = block.inner(area);
 
@@ -84,27 +814,116 @@ impl StatefulWidget for &mut PresetsMenu {
             presets_area,
             instructions_area,
         ] = Layout::vertical([
-            Constraint::Length(2),
+            Constraint::Length(if stripped { 0 } else { 2 }),
             Constraint::Max(2),
             Constraint::Fill(1),
-            Constraint::Length(2),
+            Constraint::Length(if stripped { 0 } else { 2 }),
         ])
         .spacing(1)
         .areas(inner_area);
 
         // Render title
-        {
-            Paragraph::new(Line::from("Presets").underlined().bold().italic())
+        if !stripped {
+            let mut title = make_title("Presets", &state.server, state.theme);
+            if !state.diagnostics.is_empty() {
+                title.push_span(
+                    format!(" {} {}", state.icons.warning, state.diagnostics.len())
+                        .set_style(state.theme.warn),
+                );
+            }
+            Paragraph::new(title)
                 .centered()
                 .block(Block::new().borders(Borders::BOTTOM))
                 .render(title_area, buf);
         }
 
-        // Render notification
-        {
-            let content = match self.notification.clone() {
-                Some(msg) => msg.red(),
-                None => format!("Reading presets from {}", state.presets_path).into(),
+        // Render notifications, or else how far a launch got, or else the tag filter and the
+        // presets' warnings
+        if focused && !state.notifications.is_empty() {
+            state
+                .notifications
+                .render(notification_area, buf, state.theme);
+        } else if let Some(launch) = &state.launching {
+            let [text_area, gauge_area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Length(1)])
+                    .areas(notification_area);
+            let [_, gauge_area, _] = Layout::horizontal([
+                Constraint::Fill(1),
+                Constraint::Length(40),
+                Constraint::Fill(1),
+            ])
+            .areas(gauge_area);
+
+            let (detail, ratio, label) = match &launch.progress {
+                Some(progress) => (
+                    format!(": {}", progress.detail),
+                    progress.step as f64 / progress.total.max(1) as f64,
+                    format!("{}/{}", progress.step, progress.total),
+                ),
+                None => (String::new(), 0.0, String::new()),
+            };
+            Paragraph::new(Line::from(
+                format!("Launching '{}'{detail} (esc stops)", launch.preset.name)
+                    .set_style(state.theme.accent)
+                    .italic(),
+            ))
+            .centered()
+            .render(text_area, buf);
+            LineGauge::default()
+                .filled_style(state.theme.accent)
+                .unfilled_style(state.theme.dim)
+                .ratio(ratio.min(1.0))
+                .label(label)
+                .render(gauge_area, buf);
+        } else {
+            let expanded = match &self.level {
+                ListLevel::Windows(name) => Some(name),
+                ListLevel::Presets => None,
+            };
+            let content = match state.diagnostics.as_slice() {
+                _ if expanded.is_some() => format!(
+                    "Windows of '{}', enter starts just the highlighted one",
+                    expanded.map(String::as_str).unwrap_or_default()
+                )
+                .set_style(state.theme.accent),
+                _ if self.tag_filter.is_some() => format!(
+                    "Showing presets tagged '{}' (t for the next tag)",
+                    self.tag_filter.as_deref().unwrap_or_default()
+                )
+                .set_style(state.theme.accent),
+                [] if state.starter_path.is_some() => "No presets file yet".into(),
+                [] => match state.presets.files() {
+                    [file] => format!("Reading presets from {}", file.display()).into(),
+                    // Only stdin and inline documents, or an empty directory
+                    [] => format!(
+                        "Reading presets from {}",
+                        state
+                            .preset_sources
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                    .into(),
+                    files => format!(
+                        "Loaded {} presets from {} files",
+                        state.presets.len(),
+                        files.len()
+                    )
+                    .into(),
+                },
+                [diagnostic] => format!(
+                    "{} {} (W for details)",
+                    state.icons.warning, diagnostic.message
+                )
+                .set_style(state.theme.warn),
+                [diagnostic, rest @ ..] => format!(
+                    "{} {} (+{} more, W lists them)",
+                    state.icons.warning,
+                    diagnostic.message,
+                    rest.len()
+                )
+                .set_style(state.theme.warn),
             };
             Paragraph::new(Line::from(content.italic()))
                 .centered()
@@ -112,70 +931,234 @@ impl StatefulWidget for &mut PresetsMenu {
                 .render(notification_area, buf);
         }
 
-        // Render presets
-        {
-            let sessions_width = 20;
-            let [_, presets_area, running_status_area, _] = Layout::horizontal([
+        // Render the expanded preset's windows
+        if let ListLevel::Windows(name) = &self.level {
+            let windows_width = name_column_width(presets_area.width);
+            let [_, windows_area, panes_area, _] = Layout::horizontal([
                 Constraint::Fill(1),
-                Constraint::Length(sessions_width),
+                Constraint::Length(windows_width),
                 Constraint::Length(11),
                 Constraint::Fill(1),
             ])
             .areas(presets_area);
 
-            let presets = state
+            let windows = state
                 .presets
-                .values()
-                .map(|s| {
-                    let truncated_name = if s.name.len() > sessions_width as usize - 8 {
-                        let mut name = s.name.clone();
-                        name.truncate(sessions_width as usize - 11);
-                        format!("{}...", name)
-                    } else {
-                        s.name.clone()
-                    };
-                    let text = format!("{:>2}  - {}", s.windows.len(), truncated_name);
-                    let item = Line::from(text.clone());
-                    ListItem::new(item)
+                .get_by_name(name)
+                .map(|preset| preset.windows.as_slice())
+                .unwrap_or_default();
+            let name_width =
+                (windows_width as usize).saturating_sub(3 + state.icons.highlight.width());
+            let items = windows
+                .iter()
+                .enumerate()
+                .map(|(index, window)| {
+                    ListItem::new(format!(
+                        "{index:>2} {}",
+                        truncate_to_width(&window.name, name_width)
+                    ))
                 })
                 .collect::<Vec<ListItem>>();
 
+            self.list_area = windows_area;
             StatefulWidget::render(
-                List::new(presets)
-                    .highlight_symbol("")
+                List::new(items)
+                    .highlight_symbol(state.icons.highlight)
                     .highlight_spacing(HighlightSpacing::Always)
-                    .highlight_style(Style::new().italic().bold().cyan()),
-                presets_area,
+                    .highlight_style(state.theme.highlight),
+                windows_area,
                 buf,
-                &mut self.list_state,
+                &mut self.window_state,
             );
 
             Paragraph::new(
-                state
-                    .presets
-                    .values()
-                    .map(|s| if s.running { "   running" } else { "" })
-                    .collect::<Vec<&str>>()
+                windows
+                    .iter()
+                    .map(|window| match window.layout.pane_count() {
+                        1 => " 1 pane".to_string(),
+                        count => format!(" {count} panes"),
+                    })
+                    .collect::<Vec<String>>()
                     .join("\n"),
             )
-            .green()
-            .render(running_status_area, buf);
+            .style(state.theme.dim)
+            .render(panes_area, buf);
+        }
+        // Render presets
+        else {
+            // Nothing to list on a first run, but a file to start from
+            if state.presets.is_empty()
+                && let Some(path) = &state.starter_path
+            {
+                let mut lines = vec![
+                    Line::from("No presets yet".set_style(state.theme.dim)),
+                    Line::from(
+                        format!("{} doesn't exist", path.display()).set_style(state.theme.dim),
+                    ),
+                ];
+                if !stripped {
+                    lines.extend(
+                        Instructions::new(vec![("c", "write a starter file there")], state.theme)
+                            .lines(presets_area.width, 1),
+                    );
+                }
+                Paragraph::new(lines)
+                    .centered()
+                    .wrap(Wrap { trim: true })
+                    .render(presets_area, buf);
+            }
+
+            let sessions_width = name_column_width(presets_area.width);
+            // Whether it's running goes on the same row, to highlight and scroll with it
+            let [_, presets_area, _] = Layout::horizontal([
+                Constraint::Fill(1),
+                Constraint::Length(sessions_width + RUNNING_WIDTH),
+                Constraint::Fill(1),
+            ])
+            .areas(presets_area);
+
+            let icons = state.icons;
+            let highlighted = self.list_state.selected();
+            // Counts are right-aligned to the largest one listed
+            let count_width = count_column_width(self.rows.iter().filter_map(|row| match row {
+                Row::Preset(idx) => Some(state.presets.get(*idx)?.windows.len()),
+                Row::File { .. } => None,
+            }));
+            // Only the highlighted preset shows its description, under its name
+            self.description_height = 0;
+            let presets = self
+                .rows
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, row)| {
+                    let preset = match row {
+                        Row::File { path, count } => {
+                            let icon = if self.collapsed.contains(path) {
+                                icons.collapsed
+                            } else {
+                                icons.expanded
+                            };
+                            let name = path.file_name().unwrap_or(path.as_os_str());
+                            let label = format!("{icon} {} ({count})", name.to_string_lossy());
+                            return Some(ListItem::new(
+                                truncate_to_width(
+                                    &label,
+                                    (sessions_width as usize)
+                                        .saturating_sub(icons.highlight.width()),
+                                )
+                                .set_style(state.theme.dim),
+                            ));
+                        }
+                        Row::Preset(idx) => *idx,
+                    };
+                    let s = state.presets.get(preset)?;
+                    let mut prefix =
+                        format!("{:>count_width$} {} ", s.windows.len(), icons.windows);
+                    if s.pinned {
+                        prefix.push_str(icons.pinned);
+                        prefix.push(' ');
+                    }
+                    let dot = match accent_color(s.color.as_deref(), state.theme) {
+                        Some(color) => format!("{} ", icons.dot).fg(color),
+                        None => "".into(),
+                    };
+                    // The highlight symbol is always reserved, so leave room for it too
+                    let name_width = (sessions_width as usize)
+                        .saturating_sub(prefix.width() + dot.width() + icons.highlight.width());
+                    let indent = " ".repeat(prefix.width() + dot.width());
+                    let running = match s.running {
+                        true => {
+                            format!(" {} running", icons.running).set_style(state.theme.current)
+                        }
+                        false => "".into(),
+                    };
+                    let mut lines = vec![Line::from(vec![
+                        prefix.into(),
+                        dot,
+                        fit_to_width(&s.name, name_width).into(),
+                        running,
+                    ])];
+                    if highlighted == Some(idx)
+                        && let Some(description) = &s.description
+                    {
+                        let description = wrap_to_width(description, name_width, 3);
+                        self.description_height = description.len();
+                        lines.extend(description.into_iter().map(|line| {
+                            Line::from(format!("{indent}{line}").set_style(state.theme.dim))
+                        }));
+                    }
+                    Some(ListItem::new(lines))
+                })
+                .collect::<Vec<ListItem>>();
+
+            self.list_area = presets_area;
+            StatefulWidget::render(
+                List::new(presets)
+                    .highlight_symbol(icons.highlight)
+                    .highlight_spacing(HighlightSpacing::Always)
+                    .highlight_style(state.theme.highlight),
+                presets_area,
+                buf,
+                &mut self.list_state,
+            );
         }
 
         // Render instructions
-        {
+        if stripped {
+            // Nothing to do besides choosing
+        } else if let Some(pending) = state.keymap.pending_line(state.theme) {
+            pending.centered().render(instructions_area, buf);
+        } else if self.level != ListLevel::Presets {
             let instructions = vec![
-                ("enter", "launch"),
+                ("enter", "start window"),
+                ("h/←/esc", "back"),
                 ("q", "quit"),
                 ("j/↓", "next"),
                 ("k/↑", "prev"),
                 ("tab", "view sessions"),
             ];
 
-            Paragraph::new(make_instructions(instructions))
-                .wrap(Wrap { trim: true })
-                .dark_gray()
-                .centered()
+            Instructions::new(instructions, state.theme)
+                .priority(&["enter", "h/←/esc", "tab"])
+                .style(state.theme.dim)
+                .render(instructions_area, buf);
+        } else {
+            let mut instructions = vec![
+                (
+                    "enter",
+                    if state.exit_on_switch {
+                        "launch & quit"
+                    } else {
+                        "launch"
+                    },
+                ),
+                ("ctrl+enter/L", "launch & adjust panes"),
+                ("u", "update"),
+                ("r", "rename"),
+                ("d", "delete"),
+                ("e", "export"),
+                ("p", "plan"),
+                ("=", "drift"),
+                ("A", "append to session"),
+                ("R", "reload"),
+                ("t", "filter by tag"),
+                ("l/→", "windows"),
+                (":", "actions"),
+                ("q", "quit"),
+                ("j/↓", "next"),
+                ("k/↑", "prev"),
+                ("tab", "view sessions"),
+            ];
+            if !state.diagnostics.is_empty() {
+                instructions.push(("W", "problems"));
+            }
+            if state.starter_path.is_some() {
+                instructions.push(("c", "starter presets"));
+            }
+
+            Instructions::new(instructions, state.theme)
+                .priority(&["enter", "c", "q", "tab", ":", "W"])
+                .style(state.theme.dim)
                 .render(instructions_area, buf);
         }
 
@@ -184,59 +1167,273 @@ impl StatefulWidget for &mut PresetsMenu {
 }
 
 impl Menu for PresetsMenu {
-    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) {
+    fn pre_render(&mut self, state: &mut AppState) {
+        self.filter(state);
+        // The highlight can move from elsewhere, ex: renaming or reloading presets from a popup
+        let position = state
+            .selected_preset
+            .as_deref()
+            .and_then(|name| self.position(name, state));
+        if position.is_some() {
+            self.list_state.select(position);
+        }
+        state.selected_preset = self.selected(state);
+    }
+
+    fn on_enter(&mut self, state: &AppState) {
+        // Editing the presets file can move or remove the highlighted preset
+        self.filter(state);
+        self.highlight(state.selected_preset.as_deref(), state);
+    }
+
+    fn on_exit(&mut self) {
+        self.level = ListLevel::Presets;
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        // Moving around, and in and out of a preset's windows, only changes what's shown
+        let quiet = match &event {
+            AppEvent::Key(key_event) => {
+                is_navigation(key_event)
+                    || matches!(
+                        key_event.code,
+                        KeyCode::Char('t' | 'h' | 'l' | ' ') | KeyCode::Left | KeyCode::Right
+                    )
+            }
+            AppEvent::Mouse(mouse) => matches!(
+                mouse.kind,
+                MouseEventKind::ScrollDown | MouseEventKind::ScrollUp
+            ),
+            _ => false,
+        };
         match event {
+            AppEvent::SpawnProgress {
+                preset,
+                step,
+                total,
+                detail,
+            } => {
+                if let Some(launch) = &mut state.launching
+                    && launch.preset.name == preset
+                {
+                    launch.progress = Some(SpawnProgress {
+                        step,
+                        total,
+                        detail,
+                    });
+                }
+                // Launching reports its progress a step at a time, the session only matters
+                // once done
+                return Handled::QUIET;
+            }
+            AppEvent::SpawnFinished { result, .. } => finish_spawn(state, result),
+            AppEvent::Key(key_event) if state.picking => self.handle_pick_key(key_event, state),
+            AppEvent::Key(KeyEvent {
+                code: KeyCode::Esc, ..
+            }) if state.launching.is_some() => {
+                if let Some(launch) = &state.launching {
+                    launch.stop.store(true, Ordering::Relaxed);
+                }
+                notify(state, Level::Info, "Stopping the launch…");
+            }
+            AppEvent::Key(key_event) if self.level != ListLevel::Presets => {
+                self.handle_window_key(key_event, state)
+            }
             AppEvent::Key(key_event) => match key_event.code {
                 // Movement
                 KeyCode::Down | KeyCode::Char('j') => {
-                    state.selected_preset = self.select_next(state.presets.len())
+                    state.selected_preset = self.select_next(state)
                 }
                 KeyCode::Up | KeyCode::Char('k') => {
-                    state.selected_preset = self.select_previous(state.presets.len())
-                }
-                KeyCode::Char('g') => {
-                    state.selected_preset = self.select_first(state.presets.len())
-                }
-                KeyCode::Char('M') => {
-                    state.selected_preset = self.select_middle(state.presets.len())
+                    state.selected_preset = self.select_previous(state)
                 }
-                KeyCode::Char('G') => state.selected_preset = self.select_last(state.presets.len()),
+                KeyCode::Char('g') => state.selected_preset = self.select_first(state),
+                KeyCode::Char('M') => state.selected_preset = self.select_middle(state),
+                KeyCode::Char('G') => state.selected_preset = self.select_last(state),
+                KeyCode::Char('t') => self.cycle_tag_filter(state),
+                KeyCode::Esc if state.popup => state.exit = true,
+                KeyCode::Right | KeyCode::Char('l') => self.expand_selected(state),
 
                 // Mode switching
                 KeyCode::Tab => state.mode = AppMode::Sessions,
 
                 // Control
                 KeyCode::Char('q') => state.exit = true,
-                KeyCode::Enter => {
-                    if let Some(index) = state.selected_preset {
-                        match tmux::spawn_preset(state.presets.values().nth(index).unwrap()) {
-                            Ok(_) => {
-                                if state.exit_on_switch {
-                                    match tmux::switch_session(
-                                        &state.presets.values().nth(index).unwrap().name,
-                                    ) {
-                                        Ok(_) => {
-                                            if state.exit_on_switch {
-                                                state.exit = true;
-                                            }
-                                        }
-                                        Err(msg) => {
-                                            send_timed_notification(&state.event_handler, msg)
-                                        }
-                                    }
-                                } else {
-                                    state.mode = AppMode::Sessions;
+                // Most terminals send ctrl+enter as a plain enter, hence `L`
+                KeyCode::Enter | KeyCode::Char('L') => {
+                    state.adjust_on_launch = key_event.code == KeyCode::Char('L')
+                        || key_event.modifiers.contains(KeyModifiers::CONTROL);
+                    self.activate_selected(state)
+                }
+                KeyCode::Char(' ') => self.toggle_selected_file(state),
+                KeyCode::Char('r') if state.selected_preset.is_some() => {
+                    state.mode = AppMode::RenamePreset
+                }
+                KeyCode::Char('d') if state.selected_preset.is_some() => {
+                    state.mode = AppMode::DeletePreset
+                }
+                KeyCode::Char('p') if state.selected_preset.is_some() => state.mode = AppMode::Plan,
+                KeyCode::Char('=') => diff_highlighted(state),
+                KeyCode::Char('A') if state.selected_preset.is_some() => {
+                    state.mode = AppMode::Append
+                }
+                KeyCode::Char('R') => self.reload(state),
+                KeyCode::Char('c') if state.starter_path.is_some() => self.write_starter(state),
+                KeyCode::Char('W') if state.diagnostics.is_empty() => {
+                    notify(state, Level::Info, "No problems with the presets files")
+                }
+                KeyCode::Char('W') => state.mode = AppMode::Diagnostics,
+                KeyCode::Char('e') => export_highlighted(state),
+                KeyCode::Char(':') => state.mode = AppMode::Palette,
+                KeyCode::Char('u') => {
+                    if let Some(preset) = state.highlighted_preset() {
+                        let (level, msg) = if !preset.running {
+                            (Level::Warn, format!("'{}' is not running", preset.name))
+                        } else {
+                            match state.server.update_preset(preset) {
+                                Ok(update) => {
+                                    let msg = describe_update(&update);
+                                    let server = state.server.for_preset(preset);
+                                    start_pending(state, server, update.pending, update.warnings);
+                                    (Level::Info, msg)
                                 }
+                                Err(e) => (Level::Error, e.to_string()),
                             }
-                            Err(s) => send_timed_notification(&state.event_handler, s),
-                        }
-                    };
+                        };
+                        notify(state, level, msg);
+                    }
                 }
                 _ => {}
             },
-            AppEvent::ShowNotification(msg) => self.notification = Some(msg),
-            AppEvent::ClearNotification => self.notification = None,
+            AppEvent::Mouse(mouse) => self.handle_mouse(mouse, state),
+            AppEvent::Action(id) => self.run_action(id, state),
             _ => {}
         }
+        Handled {
+            needs_refresh: !quiet,
+        }
+    }
+}
+
+/// Compares the highlighted preset with its session, if it's running
+fn diff_highlighted(state: &mut AppState) {
+    match state.highlighted_preset() {
+        Some(preset) if preset.running => state.mode = AppMode::Diff,
+        Some(preset) => {
+            let msg = format!("'{}' is not running", preset.name);
+            notify(state, Level::Warn, msg);
+        }
+        None => {}
+    }
+}
+
+/// Writes the highlighted preset out as a shell script in the working directory
+fn export_highlighted(state: &mut AppState) {
+    if let Some(preset) = state.highlighted_preset() {
+        let (level, msg) = match export::write_script(preset, &export::default_path(preset)) {
+            Ok(path) => (Level::Info, format!("Exported to {}", path.display())),
+            Err(e) => (Level::Error, e),
+        };
+        notify(state, level, msg);
+    }
+}
+
+fn describe_update(update: &PresetUpdate) -> String {
+    let mut parts = vec![];
+    if !update.added.is_empty() {
+        parts.push(format!("added windows: {}", update.added.join(", ")));
+    }
+    if !update.extra.is_empty() {
+        parts.push(format!("not in preset: {}", update.extra.join(", ")));
+    }
+    if parts.is_empty() {
+        "Session is already up to date".to_string()
+    } else {
+        parts.join("; ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::preset_list::read_presets;
+    use crate::app::testing::{self, Recorder, TestServer};
+
+    // Info is expired from a task of its own
+    #[tokio::test]
+    async fn only_long_launches_are_announced_on_the_desktop() {
+        let server = TestServer::new("presets-long-launch");
+        let mut app = testing::app(&server);
+        let state = &mut app.state;
+        let desktop = Recorder::default();
+        state.desktop = Some(Box::new(desktop.clone()));
+        let presets = read_presets(&[Source::Inline(r#"session name="api""#.to_string())]);
+        let api = presets.unwrap().0.get_by_name("api").unwrap().clone();
+        let finish = |state: &mut AppState, took: Duration| {
+            state.launching = Some(Launch {
+                preset: api.clone(),
+                progress: None,
+                adjust: false,
+                stop: Arc::default(),
+                started: Instant::now() - took,
+            });
+            let spawn = PresetSpawn {
+                pending: vec![],
+                warnings: vec![],
+            };
+            finish_spawn(state, Ok(ControlFlow::Continue(spawn)));
+            assert!(state.launching.is_none());
+            desktop.take()
+        };
+
+        assert_eq!(finish(state, Duration::ZERO), []);
+        assert_eq!(
+            finish(state, LONG_LAUNCH),
+            [(Level::Info, "Launched 'api'".to_string())]
+        );
+    }
+
+    #[test]
+    fn files_list_their_presets_as_written_and_moving_passes_over_open_headers() {
+        let dir = std::env::temp_dir().join(format!("muffin-presets-files-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("home.kdl"), r#"session name="notes""#).unwrap();
+        std::fs::write(
+            dir.join("work.kdl"),
+            r#"session name="web"; session name="api""#,
+        )
+        .unwrap();
+        let server = TestServer::new("presets-files");
+        let mut app = testing::app(&server);
+        let state = &mut app.state;
+        state.presets = read_presets(&[Source::File(dir.clone())]).unwrap().0;
+        let mut menu = PresetsMenu::new(None);
+        menu.filter(state);
+        let row = |menu: &PresetsMenu, state: &AppState| match menu.list_state.selected() {
+            Some(index) => match &menu.rows[index] {
+                Row::File { path, .. } => path.file_name().unwrap().to_string_lossy().into_owned(),
+                Row::Preset(idx) => state.presets.get(*idx).unwrap().name.clone(),
+            },
+            None => String::new(),
+        };
+
+        // Headers of files with their presets showing are passed over
+        assert_eq!(menu.select_first(state).as_deref(), Some("notes"));
+        assert_eq!(menu.select_next(state).as_deref(), Some("web"));
+        assert_eq!(menu.select_next(state).as_deref(), Some("api"));
+        assert_eq!(menu.select_next(state).as_deref(), Some("api"));
+        assert_eq!(menu.select_previous(state).as_deref(), Some("web"));
+        assert_eq!(menu.select_last(state).as_deref(), Some("api"));
+
+        // Collapsing a preset's file leaves its header to stop on in its place
+        menu.toggle_selected_file(state);
+        assert_eq!(row(&menu, state), "work.kdl");
+        assert_eq!(menu.select_previous(state).as_deref(), Some("notes"));
+        assert_eq!(menu.select_next(state), None);
+        assert_eq!(row(&menu, state), "work.kdl");
+        menu.activate_selected(state);
+        assert_eq!(menu.select_next(state).as_deref(), Some("web"));
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
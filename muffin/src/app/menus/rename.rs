@@ -1,31 +1,105 @@
-use super::Menu;
+use super::{Handled, Menu, Target};
 use crate::app::{
     driver::{AppEvent, AppMode, AppState},
-    utils::{centered_fixed_rect, make_instructions, send_timed_notification},
+    notifications::{Level, notify},
+    utils::popup_rect,
+    widgets::{
+        input::{Input, session_name},
+        instructions::Instructions,
+    },
 };
 use crossterm::event::KeyCode;
+use parser::PresetEdit;
 use ratatui::{
     prelude::{self, Buffer, Constraint, Layout},
-    style::{Style, Stylize},
+    style::Styled,
     text::Line,
-    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget, Wrap},
+    widgets::{Block, Clear, StatefulWidget, Widget},
 };
-use tui_textarea::TextArea;
+use tmux::Server;
 
-#[derive(Default)]
 pub struct RenameMenu<'a> {
-    text_area: TextArea<'a>,
-    notification: Option<String>,
+    target: Target,
+    input: Input<'a>,
+    /// A preset rename already written to disk whose session is still running under the old name,
+    /// on the preset's server
+    pending_session_rename: Option<(Server, String, String)>,
+}
+
+impl<'a> RenameMenu<'a> {
+    pub fn new(target: Target) -> Self {
+        Self {
+            target,
+            // Presets are launched under their own name, so they follow the same rules
+            input: Input::default()
+                .placeholder("start typing!")
+                .validator(session_name),
+            pending_session_rename: None,
+        }
+    }
+
+    fn target_name(&self, state: &AppState) -> Option<String> {
+        match self.target {
+            Target::Session => state
+                .selected_session
+                .and_then(|index| state.sessions.get(index))
+                .map(|s| s.name.clone()),
+            Target::Preset => state.highlighted_preset().map(|p| p.name.clone()),
+        }
+    }
+
+    fn return_mode(&self) -> AppMode {
+        match self.target {
+            Target::Session => AppMode::Sessions,
+            Target::Preset => AppMode::Presets,
+        }
+    }
+
+    fn rename_preset(&mut self, state: &mut AppState) {
+        let Some(preset) = state.highlighted_preset() else {
+            return;
+        };
+        let from = preset.name.clone();
+        let running = preset.running;
+        let server = state.server.for_preset(preset);
+        let to = self.input.value().to_string();
+        let Some(source) = preset.source.clone() else {
+            return;
+        };
+
+        // The file only knows about its own presets, but names must be unique across all of them
+        if from != to && state.presets.get_by_name(&to).is_some() {
+            notify(state, Level::Error, format!("Preset '{to}' already exists"));
+            return;
+        }
+
+        let edit = PresetEdit::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        };
+        if let Err(e) =
+            parser::edit_presets_file(&source, &edit).and_then(|_| state.reload_presets(Some(&to)))
+        {
+            notify(state, Level::Error, e);
+            return;
+        }
+
+        if running && from != to {
+            self.pending_session_rename = Some((server, from, to));
+        } else {
+            state.mode = AppMode::Presets;
+        }
+    }
 }
 
 impl<'a> StatefulWidget for &mut RenameMenu<'a> {
     type State = AppState;
 
     fn render(self, area: prelude::Rect, buf: &mut Buffer, state: &mut AppState) {
-        let area = centered_fixed_rect(area, 40, 15);
+        let area = popup_rect(area, 40, 15, state);
         Clear.render(area, buf);
 
-        let block = Block::bordered().border_style(Style::new().light_green());
+        let block = Block::bordered().border_style(state.theme.rename);
         let inner_area = block.inner(area);
 
         let [title_area, input_area, instructions_area] = Layout::vertical([
@@ -38,41 +112,49 @@ impl<'a> StatefulWidget for &mut RenameMenu<'a> {
         .areas(inner_area);
 
         // Render title
-        {
-            let index = state.selected_session.unwrap();
-            let content = match self.notification.clone() {
-                Some(msg) => msg,
-                _ => format!("Rename session '{}' to...", state.sessions[index].name),
+        if !state.notifications.is_empty() {
+            state.notifications.render(title_area, buf, state.theme);
+        } else {
+            let content = match &self.pending_session_rename {
+                Some((_, from, _)) => format!("Also rename running session '{from}'?"),
+                None => {
+                    let kind = match self.target {
+                        Target::Session => "session",
+                        Target::Preset => "preset",
+                    };
+                    let name = self.target_name(state).unwrap_or_default();
+                    format!("Rename {kind} '{name}' to...")
+                }
             };
 
-            Line::from(content.light_green())
+            Line::from(content.set_style(state.theme.rename))
                 .centered()
                 .render(title_area, buf);
         }
 
         // Render input field
-        {
+        if self.pending_session_rename.is_none() {
+            let [input_area, error_area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(input_area);
             let [first_char, rest] =
                 Layout::horizontal([Constraint::Length(2), Constraint::Fill(1)])
                     .horizontal_margin(3)
                     .areas(input_area);
 
-            "> ".light_green().render(first_char, buf);
-
-            self.text_area.set_placeholder_text("start typing!");
-            self.text_area
-                .set_placeholder_style(Style::new().dark_gray());
-            self.text_area.render(rest, buf);
+            "> ".set_style(state.theme.rename).render(first_char, buf);
+            self.input.render(rest, buf, state.theme, true);
+            self.input.render_error(error_area, buf, state.theme);
         }
 
         // Render instructions
         {
-            let instructions = vec![("esc", "cancel"), ("enter", "rename")];
+            let instructions = if self.pending_session_rename.is_some() {
+                vec![("y/enter", "rename session"), ("n/esc", "keep name")]
+            } else {
+                vec![("esc", "cancel"), ("enter", "rename")]
+            };
 
-            Paragraph::new(make_instructions(instructions))
-                .wrap(Wrap { trim: true })
-                .centered()
-                .render(instructions_area, buf);
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
         }
 
         block.render(area, buf);
@@ -80,32 +162,73 @@ impl<'a> StatefulWidget for &mut RenameMenu<'a> {
 }
 
 impl<'a> Menu for RenameMenu<'a> {
-    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) {
-        match event {
-            AppEvent::Key(key_event) => match key_event.code {
-                KeyCode::Esc => {
-                    self.text_area = TextArea::default();
-                    state.mode = AppMode::Sessions;
-                }
-                KeyCode::Enter => {
-                    if let Some(index) = state.selected_session {
-                        match tmux::rename_session(
-                            &state.sessions[index].name,
-                            &self.text_area.lines().join(""),
-                        ) {
-                            Ok(_) => {
-                                self.text_area = TextArea::default();
-                                state.mode = AppMode::Sessions;
-                            }
-                            Err(s) => send_timed_notification(&state.event_handler, s),
-                        }
-                    };
-                }
-                _ => _ = self.text_area.input(key_event),
-            },
-            AppEvent::ShowNotification(msg) => self.notification = Some(msg),
-            AppEvent::ClearNotification => self.notification = None,
-            _ => {}
+    fn on_exit(&mut self) {
+        *self = Self::new(self.target);
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        let key_event = match event {
+            AppEvent::Key(key_event) => key_event,
+            AppEvent::Paste(text) if self.pending_session_rename.is_none() => {
+                self.input.paste(&text);
+                return Handled::REFRESH;
+            }
+            _ => return Handled::REFRESH,
+        };
+
+        // The preset file is already renamed at this point, only the live session is left
+        if let Some((server, from, to)) = self.pending_session_rename.clone() {
+            match key_event.code {
+                KeyCode::Char('y') | KeyCode::Enter => match server.rename_session(&from, &to) {
+                    Ok(_) => state.mode = AppMode::Presets,
+                    Err(e) => notify(state, Level::Error, e.to_string()),
+                },
+                KeyCode::Char('n') | KeyCode::Esc => state.mode = AppMode::Presets,
+                _ => {}
+            }
+            return Handled::REFRESH;
         }
+
+        match key_event.code {
+            KeyCode::Esc => state.mode = self.return_mode(),
+            // The reason is already showing under the input
+            KeyCode::Enter if self.input.error().is_some() => {}
+            KeyCode::Enter if self.target == Target::Preset => self.rename_preset(state),
+            KeyCode::Enter => {
+                if let Some(index) = state.selected_session {
+                    match state
+                        .server
+                        .rename_session(&state.sessions[index].id, self.input.value())
+                    {
+                        Ok(_) => state.mode = AppMode::Sessions,
+                        Err(e) => notify(state, Level::Error, e.to_string()),
+                    }
+                };
+            }
+            _ => _ = self.input.handle_key(key_event),
+        }
+        Handled::REFRESH
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing::{self, TestServer};
+
+    #[test]
+    fn pasted_names_are_put_on_one_line() {
+        let server = TestServer::new("rename-paste");
+        let mut app = testing::app(&server);
+        let state = &mut app.state;
+        state.mode = AppMode::Rename;
+        let mut menu = RenameMenu::new(Target::Session);
+
+        menu.handle_event(AppEvent::Paste("new\nname\n".to_string()), state);
+        assert_eq!(menu.input.value(), "new name");
+        // Nothing to do with pasting, control characters are dropped
+        menu.handle_event(AppEvent::Paste("\x1b[2~-2".to_string()), state);
+        assert_eq!(menu.input.value(), "new name[2~-2");
+        assert!(matches!(state.mode, AppMode::Rename));
     }
 }
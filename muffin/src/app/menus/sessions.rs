@@ -1,28 +1,56 @@
-use super::Menu;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::{Handled, Menu};
 use crate::app::{
-    driver::{AppEvent, AppMode, AppState},
-    utils::{make_instructions, send_timed_notification},
+    actions::ActionId,
+    driver::{AppEvent, AppMode, AppState, Panel},
+    notifications::{Level, notify},
+    state::UiState,
+    utils::{
+        ClickTracker, accent_color, count_column_width, fit_to_width, is_navigation, list_row_at,
+        make_title, name_column_width, truncate_to_width,
+    },
+    widgets::{input::Input, instructions::Instructions},
 };
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{
     prelude::{Buffer, Constraint, Layout, Rect},
-    style::{Style, Stylize},
+    style::{Styled, Stylize},
     symbols::border,
-    text::{Line, Text},
+    text::Line,
     widgets::{
         Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph,
-        StatefulWidget, Widget, Wrap,
+        StatefulWidget, Widget,
     },
 };
-use tmux::Session;
-use tui_textarea::TextArea;
+use tmux::{Alert, Session};
+use unicode_width::UnicodeWidthStr;
+
+/// Room after a session's name for whether it's current or has clients attached
+const STATUS_WIDTH: u16 = 12;
+/// How long after the selection is moved by hand follow mode leaves it alone
+const FOLLOW_PAUSE: Duration = Duration::from_secs(5);
 
 pub struct SessionsMenu<'a> {
     list_state: ListState,
-    notification: Option<String>,
-    displayed_sessions: Vec<usize>,
-    search_bar: TextArea<'a>,
+    /// The list's rows: group headers, and the sessions shown as indices into `state.sessions`
+    rows: Vec<Row>,
+    /// Ids of `state.sessions` as of the last refresh, to find the sessions `rows` point at once
+    /// the list is read again and their indices shift
+    listed_ids: Vec<String>,
+    search_bar: Input<'a>,
     mode: MenuMode,
+    /// Where the list was last drawn, to map mouse clicks onto rows
+    list_area: Rect,
+    clicks: ClickTracker,
+    /// Lists sessions most recently switched to first instead of by name
+    by_recency: bool,
+    /// Groups whose sessions are hidden, kept across refreshes
+    collapsed: HashSet<String>,
+    /// When the selection was last moved by hand, which holds off following for a while
+    moved_at: Option<Instant>,
 }
 
 enum MenuMode {
@@ -30,84 +58,377 @@ enum MenuMode {
     Normal,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Row {
+    /// Sessions named `<group><separator>...`, and how many of them there are
+    Group {
+        name: String,
+        count: usize,
+    },
+    Session(usize),
+}
+
 impl<'a> SessionsMenu<'a> {
-    pub fn new(total_session: usize, index: Option<usize>) -> Self {
+    pub fn new(index: Option<usize>) -> Self {
         let mut list_state = ListState::default();
         list_state.select(index);
         Self {
             list_state,
-            notification: None,
-            displayed_sessions: (0..total_session).collect(),
-            search_bar: TextArea::default(),
+            rows: vec![],
+            listed_ids: vec![],
+            search_bar: Input::default(),
             mode: MenuMode::Normal,
+            list_area: Rect::default(),
+            clicks: ClickTracker::default(),
+            by_recency: false,
+            collapsed: HashSet::new(),
+            moved_at: None,
         }
     }
 
     pub fn select_next(&mut self, state: &mut AppState) -> Option<usize> {
         self.list_state.select_next();
-        self.verify_index(
-            self.list_state
-                .selected()
-                .map(|idx| idx.clamp(0, self.displayed_sessions.len().saturating_sub(1))),
-            state,
-        )
+        self.selected_session(state)
     }
 
     pub fn select_previous(&mut self, state: &mut AppState) -> Option<usize> {
         self.list_state.select_previous();
-        self.verify_index(
-            self.list_state
-                .selected()
-                .map(|idx| idx.clamp(0, self.displayed_sessions.len().saturating_sub(1))),
-            state,
-        )
+        self.selected_session(state)
     }
 
     pub fn select_first(&mut self, state: &mut AppState) -> Option<usize> {
         self.list_state.select_first();
-        self.verify_index(
-            self.list_state
-                .selected()
-                .map(|idx| idx.clamp(0, self.displayed_sessions.len().saturating_sub(1))),
-            state,
-        )
+        self.selected_session(state)
     }
 
     pub fn select_middle(&mut self, state: &mut AppState) -> Option<usize> {
-        if !self.displayed_sessions.is_empty() {
-            let new_index = (self.displayed_sessions.len().saturating_sub(1)).div_ceil(2);
+        if !self.rows.is_empty() {
+            let new_index = (self.rows.len().saturating_sub(1)).div_ceil(2);
             self.list_state.select(Some(new_index));
         }
-        self.verify_index(
-            self.list_state
-                .selected()
-                .map(|idx| idx.clamp(0, self.displayed_sessions.len().saturating_sub(1))),
-            state,
-        )
+        self.selected_session(state)
     }
 
     pub fn select_last(&mut self, state: &mut AppState) -> Option<usize> {
         self.list_state.select_last();
-        self.verify_index(
-            self.list_state
-                .selected()
-                .map(|idx| idx.clamp(0, self.displayed_sessions.len().saturating_sub(1))),
-            state,
-        )
+        self.selected_session(state)
     }
 
-    fn verify_index(&mut self, x: Option<usize>, state: &mut AppState) -> Option<usize> {
-        x.and_then(|idx| {
-            if self
-                .displayed_sessions
-                .get(idx)
-                .is_some_and(|&i| i < state.sessions.len())
-            {
-                Some(idx)
-            } else {
-                None
+    /// The session on the highlighted row, as an index into `state.sessions`. `None` on a group
+    /// header.
+    fn selected_session(&mut self, state: &AppState) -> Option<usize> {
+        // Moving past either end leaves the selection out of bounds until the next render
+        let index = self
+            .list_state
+            .selected()?
+            .min(self.rows.len().saturating_sub(1));
+        match self.rows.get(index)? {
+            Row::Session(idx) if *idx < state.sessions.len() => Some(*idx),
+            _ => None,
+        }
+    }
+
+    /// Switches to the highlighted session, or collapses or expands the highlighted group
+    fn activate_selected(&mut self, state: &mut AppState) {
+        let Some(row) = self
+            .list_state
+            .selected()
+            .and_then(|index| self.rows.get(index))
+        else {
+            return;
+        };
+        let Row::Group { name, .. } = row else {
+            return self.switch_to_selected(state);
+        };
+        if !self.collapsed.remove(name) {
+            self.collapsed.insert(name.clone());
+        }
+    }
+
+    /// Switches to the highlighted session
+    fn switch_to_selected(&mut self, state: &mut AppState) {
+        let Some(session) = state
+            .selected_session
+            .and_then(|index| state.sessions.get(index))
+        else {
+            return;
+        };
+        let session = session.clone();
+        let name = session.name.clone();
+        if state.picking {
+            state.picked = Some(name);
+            state.exit = true;
+            return;
+        }
+        match state.server.switch_session(&session.id) {
+            Ok(_) => {
+                // Set first, for a warning said on the way out to go to the desktop
+                if state.exit_on_switch {
+                    state.exit = true;
+                } else {
+                    state.focus_session = Some(name);
+                }
+                if let Err(e) = state.mru.touch(&session) {
+                    notify(state, Level::Warn, e);
+                }
             }
+            Err(e) => notify(state, Level::Error, e.to_string()),
+        }
+    }
+
+    /// Picks up the sort, filter and collapsed groups saved last time, leaving out groups that
+    /// are gone
+    pub fn restore(&mut self, ui: &UiState, state: &AppState) {
+        self.by_recency = ui.by_recency.unwrap_or_default();
+        if let Some(search) = &ui.search {
+            self.search_bar.set_value(search);
+        }
+        let separator = group_separator(state);
+        self.collapsed = ui
+            .collapsed
+            .iter()
+            .filter(|group| {
+                state
+                    .sessions
+                    .iter()
+                    .any(|s| group_of(&s.name, separator) == Some(group.as_str()))
+            })
+            .cloned()
+            .collect();
+    }
+
+    /// Saves the sort, filter and collapsed groups into `ui`
+    pub fn snapshot(&self, ui: &mut UiState) {
+        ui.by_recency = Some(self.by_recency);
+        ui.search = Some(self.search_bar.value().to_string()).filter(|s| !s.is_empty());
+        ui.collapsed = self.collapsed.iter().cloned().collect();
+        ui.collapsed.sort();
+    }
+
+    fn toggle_sort(&mut self, state: &mut AppState) {
+        self.by_recency = !self.by_recency;
+        // Stay on the same session, wherever it ends up
+        state.focus_session = state
+            .selected_session
+            .and_then(|idx| state.sessions.get(idx))
+            .map(|s| s.name.clone());
+    }
+
+    /// Carries out an action chosen in the command palette
+    fn run_action(&mut self, id: ActionId, state: &mut AppState) {
+        match id {
+            ActionId::CreateSession => state.mode = AppMode::Create,
+            ActionId::ToggleSort => self.toggle_sort(state),
+            ActionId::ShowPanes => state.mode = AppMode::Panes,
+            ActionId::EditNote => state.mode = AppMode::Note,
+            ActionId::Top => state.selected_session = self.select_first(state),
+            ActionId::Bottom => state.selected_session = self.select_last(state),
+            ActionId::KillOtherSessions => kill_other_sessions(state),
+            ActionId::ToggleUnsafe => toggle_unsafe(state),
+            ActionId::ToggleFollow => toggle_follow(state),
+            ActionId::KillServer => kill_server(state),
+            ActionId::StartServer => start_server(state),
+            _ => {}
+        }
+    }
+
+    /// Whether the selection should jump to the followed session, see [`AppState::following`]
+    fn follows(&self, state: &AppState) -> bool {
+        state.following
+            && !state.picking
+            && self
+                .moved_at
+                .is_none_or(|moved_at| moved_at.elapsed() >= FOLLOW_PAUSE)
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, state: &mut AppState) {
+        if matches!(
+            mouse.kind,
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp | MouseEventKind::Down(_)
+        ) {
+            self.moved_at = Some(Instant::now());
+        }
+        match mouse.kind {
+            MouseEventKind::ScrollDown => state.selected_session = self.select_next(state),
+            MouseEventKind::ScrollUp => state.selected_session = self.select_previous(state),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(index) = list_row_at(
+                    self.list_area,
+                    self.list_state.offset(),
+                    mouse.column,
+                    mouse.row,
+                )
+                .filter(|&index| index < self.rows.len()) else {
+                    return;
+                };
+                self.list_state.select(Some(index));
+                state.selected_session = self.selected_session(state);
+                if self.clicks.click(index) {
+                    self.activate_selected(state);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Lays `sessions` (indices into `all`, in display order) out in rows: sessions outside of any
+/// group first, then each group in the order its first session appears, headed by its name.
+/// Collapsed groups only get their header.
+fn group_rows(
+    sessions: &[usize],
+    all: &[Session],
+    separator: &str,
+    collapsed: &HashSet<String>,
+) -> Vec<Row> {
+    let mut ungrouped = vec![];
+    let mut groups = Vec::<(&str, Vec<usize>)>::new();
+    for &idx in sessions {
+        let Some(session) = all.get(idx) else {
+            continue;
+        };
+        match group_of(&session.name, separator) {
+            Some(group) => match groups.iter_mut().find(|(name, _)| *name == group) {
+                Some((_, members)) => members.push(idx),
+                None => groups.push((group, vec![idx])),
+            },
+            None => ungrouped.push(idx),
+        }
+    }
+
+    let mut rows = ungrouped.into_iter().map(Row::Session).collect::<Vec<_>>();
+    for (name, members) in groups {
+        rows.push(Row::Group {
+            name: name.to_string(),
+            count: members.len(),
+        });
+        if !collapsed.contains(name) {
+            rows.extend(members.into_iter().map(Row::Session));
+        }
+    }
+    rows
+}
+
+/// The group `name` belongs to, if it has a non-empty one
+fn group_of<'n>(name: &'n str, separator: &str) -> Option<&'n str> {
+    if separator.is_empty() {
+        return None;
+    }
+    name.split_once(separator)
+        .map(|(group, _)| group)
+        .filter(|group| !group.is_empty())
+}
+
+/// Asks before deleting the highlighted session, or in unsafe mode sends it to the trash right
+/// away unless a client is attached to it
+fn delete_selected(state: &mut AppState) {
+    let Some(session) = state
+        .selected_session
+        .and_then(|index| state.sessions.get(index))
+        .cloned()
+    else {
+        return;
+    };
+    if !state.unsafe_mode || session.is_current || session.attached_count > 0 {
+        state.mode = AppMode::Delete;
+        return;
+    }
+    match state.trash.delete(&state.server, &session) {
+        Ok(_) => notify(
+            state,
+            Level::Info,
+            format!("Deleted '{}', u restores it", session.name),
+        ),
+        Err(e) => notify(state, Level::Error, e),
+    }
+}
+
+/// The session follow mode keeps highlighted: the one muffin's own client is on, or when muffin
+/// doesn't run in tmux, the attached session most recently active
+fn followed_session(state: &AppState) -> Option<usize> {
+    state
+        .sessions
+        .iter()
+        .position(|s| s.is_current)
+        .or_else(|| {
+            state
+                .sessions
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.attached_count > 0)
+                .max_by_key(|(_, s)| s.activity)
+                .map(|(idx, _)| idx)
         })
+}
+
+/// Turns follow mode on or off, see [`AppState::following`]
+fn toggle_follow(state: &mut AppState) {
+    state.following = !state.following;
+    let msg = match state.following {
+        true => "Following the attached session",
+        false => "Stopped following the attached session",
+    };
+    notify(state, Level::Info, msg);
+}
+
+/// Turns unsafe mode on or off, see [`AppState::unsafe_mode`]
+fn toggle_unsafe(state: &mut AppState) {
+    state.unsafe_mode = !state.unsafe_mode;
+    let (level, msg) = match state.unsafe_mode {
+        true => (
+            Level::Warn,
+            "Unsafe mode: d deletes without asking, but for attached sessions",
+        ),
+        false => (Level::Info, "d asks before deleting again"),
+    };
+    notify(state, level, msg);
+}
+
+/// Sends every session but the current one to the trash, where `u` can still bring them back
+fn kill_other_sessions(state: &mut AppState) {
+    let others = state
+        .sessions
+        .iter()
+        .filter(|s| !s.is_current)
+        .cloned()
+        .collect::<Vec<_>>();
+    for session in &others {
+        if let Err(e) = state.trash.delete(&state.server, session) {
+            notify(state, Level::Error, e);
+            return;
+        }
+    }
+    notify(
+        state,
+        Level::Info,
+        match others.len() {
+            1 => "Killed 1 session, u restores it".to_string(),
+            count => format!("Killed {count} sessions, u restores them one by one"),
+        },
+    );
+}
+
+/// Asks to kill the server, unless muffin would go down with it
+fn kill_server(state: &mut AppState) {
+    if !state.server_running {
+        notify(state, Level::Info, "The tmux server isn't running");
+    } else if state.server.hosts_muffin() {
+        notify(
+            state,
+            Level::Warn,
+            "muffin runs inside this tmux server, so killing it would kill muffin too",
+        );
+    } else {
+        state.mode = AppMode::KillServer;
+    }
+}
+
+/// Starts the server back up, which only sticks if the tmux config leaves it something to run
+fn start_server(state: &mut AppState) {
+    if state.server_running {
+        notify(state, Level::Info, "The tmux server is already running");
+    } else if let Err(e) = state.server.start_server() {
+        notify(state, Level::Error, e.to_string());
     }
 }
 
@@ -116,7 +437,23 @@ impl<'a> StatefulWidget for &mut SessionsMenu<'a> {
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
         Clear.render(area, buf);
-        let block = Block::bordered().border_set(border::THICK);
+        let focused = state.is_focused(Panel::Sessions);
+        // Picking gets nothing but the list, there's nothing to do besides choosing
+        let stripped = state.picking;
+        let block = if stripped {
+            Block::new()
+        } else {
+            Block::bordered().border_set(if focused {
+                border::THICK
+            } else {
+                border::PLAIN
+            })
+        };
+        // Hard to miss while `d` deletes without asking
+        let block = match state.unsafe_mode {
+            true => block.border_style(state.theme.delete),
+            false => block,
+        };
 
         let inner_area = block.inner(area);
 
@@ -125,20 +462,26 @@ impl<'a> StatefulWidget for &mut SessionsMenu<'a> {
             subtitle_area,
             search_area,
             sessions_area,
+            totals_area,
             instructions_area,
         ] = Layout::vertical([
-            Constraint::Length(2),
-            Constraint::Length(1),
+            Constraint::Length(if stripped { 0 } else { 2 }),
+            Constraint::Max(2),
             Constraint::Length(1),
             Constraint::Fill(1),
-            Constraint::Length(2),
+            Constraint::Length(if stripped { 0 } else { 1 }),
+            Constraint::Length(if stripped { 0 } else { 2 }),
         ])
         .spacing(1)
         .areas(inner_area);
 
         // Render title
-        {
-            Paragraph::new(Line::from("Sessions").underlined().bold().italic())
+        if !stripped {
+            let mut title = make_title("Sessions", &state.server, state.theme);
+            if state.unsafe_mode {
+                title.push_span(" UNSAFE".set_style(state.theme.delete).bold());
+            }
+            Paragraph::new(title)
                 .centered()
                 .block(Block::new().borders(Borders::BOTTOM))
                 .render(title_area, buf);
@@ -166,20 +509,31 @@ impl<'a> StatefulWidget for &mut SessionsMenu<'a> {
                             .areas(text_area);
 
                     "Filter: ".render(first, buf);
-                    self.search_bar
-                        .set_cursor_style(Style::default().on_white());
-                    self.search_bar.render(rest, buf);
+                    self.search_bar.render(rest, buf, state.theme, true);
                 }
-                // In normal mode, render notification (if any, else message)
+                // In normal mode, render notifications (if any, else message)
                 // then filter (if any)
                 MenuMode::Normal => {
-                    let content = match self.notification.clone() {
-                        Some(msg) => msg.red(),
-                        None => "Select a session!".into(),
-                    };
-                    Paragraph::new(Line::from(content.italic()))
-                        .centered()
-                        .render(subtitle_area, buf);
+                    // Notifications go with the keys that caused them
+                    if state.notifications.is_empty() || !focused {
+                        let content = match state.trash.latest() {
+                            // Nothing else is worth saying until there's a server again
+                            _ if !state.server_running => "tmux server not running — launching \
+                                 a preset or creating a session will start it"
+                                .set_style(state.theme.warn),
+                            Some(deleted) => format!("Deleted '{deleted}' — press u to undo")
+                                .set_style(state.theme.warn),
+                            None if self.by_recency => {
+                                "Select a session! (most recent first)".into()
+                            }
+                            None => "Select a session!".into(),
+                        };
+                        Paragraph::new(Line::from(content.italic()))
+                            .centered()
+                            .render(subtitle_area, buf);
+                    } else {
+                        state.notifications.render(subtitle_area, buf, state.theme);
+                    }
 
                     if !self.search_bar.is_empty() {
                         let [_, text_area, _] = Layout::horizontal([
@@ -194,8 +548,7 @@ impl<'a> StatefulWidget for &mut SessionsMenu<'a> {
                                 .areas(text_area);
 
                         "Filter: ".render(first, buf);
-                        self.search_bar.set_cursor_style(Style::default());
-                        self.search_bar.render(rest, buf);
+                        self.search_bar.render(rest, buf, state.theme, false);
                     }
                 }
             }
@@ -203,101 +556,266 @@ impl<'a> StatefulWidget for &mut SessionsMenu<'a> {
 
         // Render sessions
         {
-            let sessions_width = 20;
-            let [_, sessions_area, active_status_area, _] = Layout::horizontal([
+            let sessions_width = name_column_width(sessions_area.width);
+            let list_area = sessions_area;
+            // Whose clients are attached goes on the same row, to highlight and scroll with it
+            let [_, sessions_area, _] = Layout::horizontal([
                 Constraint::Fill(1),
-                Constraint::Length(sessions_width),
-                Constraint::Max(10),
+                Constraint::Length(sessions_width + STATUS_WIDTH),
                 Constraint::Fill(1),
             ])
             .areas(sessions_area);
 
-            let sessions = self
-                .displayed_sessions
+            let icons = state.icons;
+            let theme = state.theme;
+            // Remote sessions are in directories this machine can't look into
+            let projects = if state.server.is_local() {
+                state
+                    .sessions
+                    .iter()
+                    .map(|s| {
+                        s.path
+                            .as_deref()
+                            .and_then(|path| state.projects.get(Path::new(path)))
+                    })
+                    .collect()
+            } else {
+                vec![]
+            };
+            // Only given a column once there's something to put in it
+            let project_width = projects
                 .iter()
-                .filter_map(|idx| {
-                    let Some(session) = &state.sessions.get(*idx) else {
-                        return None;
-                    };
-                    let truncated_name = if session.name.len() > sessions_width as usize - 8 {
-                        let mut name = session.name.clone();
-                        name.truncate(sessions_width as usize - 11);
-                        format!("{}...", name)
-                    } else {
-                        session.name.clone()
-                    };
-                    let text = format!("{:>2}  - {}", session.windows, truncated_name);
-                    let mut item = Line::from(text.clone());
-                    if session.active {
-                        item = item.green();
-                    }
-                    Some(ListItem::new(item))
-                })
-                .collect::<Vec<ListItem>>();
-
-            Paragraph::new(Text::from(
-                self.displayed_sessions
+                .flatten()
+                .map(|kind| kind.icon(icons).width() + 1)
+                .max()
+                .unwrap_or_default();
+            let separator = group_separator(state);
+            // Counts are right-aligned to the largest one listed
+            let count_width = count_column_width(self.rows.iter().filter_map(|row| match row {
+                Row::Session(idx) => Some(state.sessions.get(*idx)?.windows as usize),
+                Row::Group { .. } => None,
+            }));
+            let sessions =
+                self.rows
                     .iter()
-                    .filter_map(|idx| {
-                        let Some(session) = &state.sessions.get(*idx) else {
-                            return None;
+                    .filter_map(|row| {
+                        let idx = match row {
+                            Row::Group { name, count } => {
+                                let icon = if self.collapsed.contains(name) {
+                                    icons.collapsed
+                                } else {
+                                    icons.expanded
+                                };
+                                let label = format!("{icon} {name} ({count})");
+                                return Some(ListItem::new(
+                                    truncate_to_width(
+                                        &label,
+                                        (sessions_width as usize)
+                                            .saturating_sub(icons.highlight.width()),
+                                    )
+                                    .set_style(theme.accent),
+                                ));
+                            }
+                            Row::Session(idx) => *idx,
+                        };
+                        let session = state.sessions.get(idx)?;
+                        // Members of a group are indented under it, without repeating its name
+                        let (indent, name) = match group_of(&session.name, separator) {
+                            Some(group) => ("  ", &session.name[group.len() + separator.len()..]),
+                            None => ("", session.name.as_str()),
+                        };
+                        let prefix = format!(
+                            "{indent}{:>count_width$} {} ",
+                            session.windows, icons.windows
+                        );
+                        // Badges get a column of their own so names stay aligned
+                        let badge = match session.alert {
+                            Some(Alert::Bell) => format!("{} ", icons.bell).set_style(theme.error),
+                            Some(Alert::Activity) => {
+                                format!("{} ", icons.activity).set_style(theme.warn)
+                            }
+                            None => " "
+                                .repeat(icons.bell.width().max(icons.activity.width()) + 1)
+                                .into(),
                         };
-                        Some(Line::from(if session.active {
-                            // Color ACTIVE (attached & current terminal) green
-                            "   active".green()
-                        } else if session.attached {
-                            // Color ATTACHED (attached in diff terminal) dark gray
-                            "  attached".dark_gray()
-                        } else {
-                            "\n".into()
-                        }))
+                        // Sessions started from a colored preset carry its dot
+                        let dot = match accent_color(session.color.as_deref(), theme) {
+                            Some(color) => format!("{} ", icons.dot).fg(color),
+                            None => "".into(),
+                        };
+                        let project = match projects.get(idx).copied().flatten() {
+                            Some(kind) => {
+                                let icon = kind.icon(icons);
+                                format!("{icon}{}", " ".repeat(project_width - icon.width()))
+                            }
+                            None => " ".repeat(project_width),
+                        };
+                        // The highlight symbol is always reserved, so leave room for it too
+                        let name_width = (sessions_width as usize).saturating_sub(
+                            prefix.width()
+                                + badge.width()
+                                + dot.width()
+                                + project.width()
+                                + icons.highlight.width(),
+                        );
+                        let status = match session.attached_count {
+                            // The one muffin runs in, whoever else has it attached
+                            _ if session.is_current => {
+                                format!(" {} current", icons.running).set_style(theme.current)
+                            }
+                            0 => "".into(),
+                            1 => format!(" {} 1 client", icons.attached).set_style(theme.attached),
+                            count => format!(" {} {count} clients", icons.attached)
+                                .set_style(theme.attached),
+                        };
+                        let mut item = Line::from(vec![
+                            prefix.into(),
+                            badge,
+                            dot,
+                            project.set_style(theme.dim),
+                            fit_to_width(name, name_width).into(),
+                            status,
+                        ]);
+                        if session.is_current {
+                            item = item.style(theme.current);
+                        }
+                        Some(ListItem::new(item))
                     })
-                    .collect::<Vec<Line>>(),
-            ))
-            .green()
-            .render(active_status_area, buf);
+                    .collect::<Vec<ListItem>>();
 
+            // Killing the last session leaves nothing to list, but plenty to do
+            if state.sessions.is_empty() {
+                let mut lines = vec![Line::from("No sessions".set_style(theme.dim))];
+                if !stripped {
+                    lines.extend(
+                        Instructions::new(vec![("a", "create one"), ("tab", "presets")], theme)
+                            .lines(list_area.width, 1),
+                    );
+                }
+                Paragraph::new(lines).centered().render(list_area, buf);
+            }
+
+            self.list_area = sessions_area;
             StatefulWidget::render(
                 List::new(sessions)
-                    .highlight_symbol("")
+                    .highlight_symbol(icons.highlight)
                     .highlight_spacing(HighlightSpacing::Always)
-                    .highlight_style(Style::new().italic().bold().cyan()),
+                    .highlight_style(theme.highlight),
                 sessions_area,
                 buf,
                 &mut self.list_state,
             );
+
+            // The first line of the highlighted session's note goes in the margin next to it
+            let highlighted = self.list_state.selected().and_then(|index| {
+                let row = index.checked_sub(self.list_state.offset())?;
+                let Row::Session(idx) = self.rows.get(index)? else {
+                    return None;
+                };
+                let note = state.sessions.get(*idx)?.note.as_deref()?;
+                Some((row as u16, note.lines().next().unwrap_or_default()))
+            });
+            let note_x = sessions_area.right() + 1;
+            if let Some((row, note)) = highlighted
+                && row < sessions_area.height
+                && note_x < list_area.right()
+            {
+                let width = list_area.right() - note_x;
+                Line::from(truncate_to_width(note, width as usize).set_style(theme.dim))
+                    .render(Rect::new(note_x, sessions_area.y + row, width, 1), buf);
+            }
+        }
+
+        // Render totals, over every session whatever is filtered out
+        if !stripped && !state.sessions.is_empty() {
+            let count = |n: usize, what: &str| match n {
+                1 => format!("1 {what}"),
+                n => format!("{n} {what}s"),
+            };
+            let windows = state.sessions.iter().map(|s| s.windows as usize).sum();
+            let attached = state
+                .sessions
+                .iter()
+                .filter(|s| s.attached_count > 0)
+                .count();
+            Paragraph::new(format!(
+                "{} · {} · {attached} attached",
+                count(state.sessions.len(), "session"),
+                count(windows, "window"),
+            ))
+            .style(state.theme.dim)
+            .centered()
+            .render(totals_area, buf);
         }
 
         // Render instructions
-        {
+        if !stripped {
             let instructions = vec![
-                ("enter", "switch"),
+                (
+                    "enter",
+                    if state.exit_on_switch {
+                        "switch & quit"
+                    } else {
+                        "switch"
+                    },
+                ),
                 ("q", "quit"),
                 ("j/↓", "next"),
                 ("k/↑", "prev"),
                 ("a", "create"),
                 ("r", "rename"),
+                ("w", "new window"),
+                ("l/→", "panes"),
+                ("n", "note"),
+                (
+                    "m",
+                    if self.by_recency {
+                        "sort by name"
+                    } else {
+                        "sort by recent"
+                    },
+                ),
+                (
+                    "f",
+                    if state.following {
+                        "unfollow"
+                    } else {
+                        "follow"
+                    },
+                ),
                 ("/", "search"),
+                (":", "actions"),
                 ("tab", "view presets"),
             ];
 
-            Paragraph::new(make_instructions(instructions))
-                .wrap(Wrap { trim: true })
-                .dark_gray()
-                .centered()
-                .render(instructions_area, buf);
+            match state.keymap.pending_line(state.theme) {
+                Some(pending) => pending.centered().render(instructions_area, buf),
+                None => Instructions::new(instructions, state.theme)
+                    .priority(&["enter", "q", "tab", ":", "a"])
+                    .style(state.theme.dim)
+                    .render(instructions_area, buf),
+            }
         }
 
         block.render(area, buf);
     }
 }
 
+/// The configured group separator, `/` by default
+fn group_separator(state: &AppState) -> &str {
+    state.config.group_separator.as_deref().unwrap_or("/")
+}
+
 impl<'a> Menu for SessionsMenu<'a> {
+    fn takes_text(&self) -> bool {
+        matches!(self.mode, MenuMode::SearchInsert)
+    }
+
     fn pre_render(&mut self, state: &mut AppState) {
-        self.displayed_sessions = if self.search_bar.is_empty() {
+        let mut displayed_sessions: Vec<usize> = if self.search_bar.is_empty() {
             (0..state.sessions.len()).collect()
         } else {
-            let search_query = self.search_bar.lines().join("");
+            let search_query = self.search_bar.value();
             state
                 .sessions
                 .iter()
@@ -308,13 +826,115 @@ impl<'a> Menu for SessionsMenu<'a> {
                         .then_some(idx)
                 })
                 .collect()
+        };
+        if self.by_recency {
+            // A stable sort, so sessions never switched to stay in tmux's (name) order
+            displayed_sessions.sort_by_key(|&idx| {
+                state
+                    .sessions
+                    .get(idx)
+                    .and_then(|s| state.mru.rank(s))
+                    .unwrap_or(usize::MAX)
+            });
+        }
+
+        // Follow a session that just became active or was just created, into its group if need be
+        let focus = state.focus_session.take();
+        let separator = group_separator(state);
+        if let Some(group) = focus.as_deref().and_then(|name| group_of(name, separator)) {
+            self.collapsed.remove(group);
+        }
+
+        // Filtering looks into every group, collapsed or not
+        let collapsed = if self.search_bar.is_empty() {
+            &self.collapsed
+        } else {
+            &HashSet::new()
+        };
+        // Stay on the same session if it moved, even when renamed elsewhere, or else on the same
+        // group header
+        let selected_row = self
+            .list_state
+            .selected()
+            .and_then(|index| self.rows.get(index))
+            .and_then(|row| match row {
+                Row::Session(idx) => {
+                    let id = self.listed_ids.get(*idx)?;
+                    state
+                        .sessions
+                        .iter()
+                        .position(|s| s.id == *id)
+                        .map(Row::Session)
+                }
+                group => Some(group.clone()),
+            });
+        self.listed_ids = state.sessions.iter().map(|s| s.id.clone()).collect();
+        self.rows = group_rows(&displayed_sessions, &state.sessions, separator, collapsed);
+
+        // Sessions in collapsed groups or filtered out aren't followed, the selection stays put
+        let followed = followed_session(state)
+            .filter(|_| self.follows(state))
+            .and_then(|idx| self.rows.iter().position(|row| *row == Row::Session(idx)));
+        let focus_row = match focus {
+            Some(name) => self.rows.iter().position(|row| {
+                matches!(row, Row::Session(idx) if state.sessions.get(*idx).is_some_and(|s| s.name == name))
+            }),
+            None => followed.or_else(|| {
+                selected_row.and_then(|selected| self.rows.iter().position(|row| *row == selected))
+            }),
+        };
+        if let Some(index) = focus_row {
+            self.list_state.select(Some(index));
+        } else if self.list_state.selected().is_none() && !self.rows.is_empty() {
+            // Nothing was highlighted while there was nothing to list
+            self.list_state.select_first();
         }
+        state.selected_session = self.selected_session(state);
     }
 
-    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) {
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        if let AppEvent::Key(key_event) = &event
+            && matches!(self.mode, MenuMode::Normal)
+            && is_navigation(key_event)
+        {
+            self.moved_at = Some(Instant::now());
+        }
+        // Moving around and typing a search only change what's listed
+        let quiet = match &event {
+            AppEvent::Key(_) | AppEvent::Paste(_)
+                if matches!(self.mode, MenuMode::SearchInsert) =>
+            {
+                true
+            }
+            AppEvent::Key(key_event) => is_navigation(key_event),
+            AppEvent::Mouse(mouse) => matches!(
+                mouse.kind,
+                MouseEventKind::ScrollDown | MouseEventKind::ScrollUp
+            ),
+            _ => false,
+        };
         match event {
             AppEvent::Key(key_event) => match self.mode {
                 MenuMode::Normal => match key_event.code {
+                    // Picking only chooses a session, leaving tmux alone
+                    KeyCode::Char('a' | 'r' | 'd' | 'w' | 'u' | '!' | 'l' | 'n')
+                    | KeyCode::Right
+                    | KeyCode::Tab
+                        if state.picking => {}
+                    // Before `k`, which moves up whatever the modifiers
+                    KeyCode::Char('k' | 'K')
+                        if key_event.modifiers.contains(KeyModifiers::CONTROL)
+                            && !state.picking =>
+                    {
+                        kill_server(state)
+                    }
+                    KeyCode::Char('s' | 'S')
+                        if key_event.modifiers.contains(KeyModifiers::CONTROL)
+                            && !state.picking =>
+                    {
+                        start_server(state)
+                    }
+
                     // Movement
                     KeyCode::Down | KeyCode::Char('j') => {
                         state.selected_session = self.select_next(state)
@@ -326,35 +946,51 @@ impl<'a> Menu for SessionsMenu<'a> {
                     KeyCode::Char('M') => state.selected_session = self.select_middle(state),
                     KeyCode::Char('G') => state.selected_session = self.select_last(state),
                     KeyCode::Char('/') => self.mode = MenuMode::SearchInsert,
-                    KeyCode::Esc => self.search_bar = TextArea::default(),
+                    // Closes the popup once there's no filter left to clear
+                    KeyCode::Esc
+                        if (state.popup || state.picking) && self.search_bar.is_empty() =>
+                    {
+                        state.exit = true
+                    }
+                    KeyCode::Esc => self.search_bar = Input::default(),
+                    KeyCode::Char('m') => self.toggle_sort(state),
+                    KeyCode::Char('!') => toggle_unsafe(state),
+                    KeyCode::Char('f') => toggle_follow(state),
+                    KeyCode::Char(':') if !state.picking => state.mode = AppMode::Palette,
+
+                    KeyCode::Enter | KeyCode::Char(' ' | 'r' | 'd' | 'w' | 'n')
+                        if state.sessions.is_empty() =>
+                    {
+                        notify(state, Level::Info, "No sessions yet, press a to create one")
+                    }
 
                     // Mode switching
                     KeyCode::Char('a') => state.mode = AppMode::Create,
-                    KeyCode::Char('r') => state.mode = AppMode::Rename,
-                    KeyCode::Char('d') => state.mode = AppMode::Delete,
+                    KeyCode::Char('r') if state.selected_session.is_some() => {
+                        state.mode = AppMode::Rename
+                    }
+                    KeyCode::Char('d') if state.selected_session.is_some() => {
+                        delete_selected(state)
+                    }
+                    KeyCode::Char('w') if state.selected_session.is_some() => {
+                        state.mode = AppMode::NewWindow
+                    }
+                    KeyCode::Right | KeyCode::Char('l') if state.selected_session.is_some() => {
+                        state.mode = AppMode::Panes
+                    }
+                    KeyCode::Char('n') if state.selected_session.is_some() => {
+                        state.mode = AppMode::Note
+                    }
                     KeyCode::Tab => state.mode = AppMode::Presets,
 
                     // Control
                     KeyCode::Char('q') => state.exit = true,
-                    KeyCode::Enter => {
-                        // Get the locally selected index
-                        // (since session menu may be applying a filter)
-                        if let Some(local_selected_index) = self.list_state.selected() {
-                            // Convert that to a global index, which indexes into the global array
-                            // of tmux sessions
-                            let global_selected_index =
-                                self.displayed_sessions[local_selected_index];
-                            match tmux::switch_session(&state.sessions[global_selected_index].name)
-                            {
-                                Ok(_) => {
-                                    if state.exit_on_switch {
-                                        state.exit = true;
-                                    }
-                                }
-                                Err(msg) => send_timed_notification(&state.event_handler, msg),
-                            }
-                        };
-                    }
+                    KeyCode::Enter | KeyCode::Char(' ') => self.activate_selected(state),
+                    KeyCode::Char('u') => match state.trash.undo(&state.server) {
+                        Some(Ok(name)) => notify(state, Level::Info, format!("Restored '{name}'")),
+                        Some(Err(e)) => notify(state, Level::Error, e),
+                        None => {}
+                    },
                     _ => {}
                 },
                 MenuMode::SearchInsert => match key_event.code {
@@ -364,17 +1000,395 @@ impl<'a> Menu for SessionsMenu<'a> {
                     }
                     KeyCode::Esc => {
                         // Empty the search bar and reset displayed sessions
-                        self.search_bar = TextArea::default();
+                        self.search_bar = Input::default();
                         self.mode = MenuMode::Normal;
                     }
-                    _ => {
-                        self.search_bar.input(key_event);
-                    }
+                    _ => _ = self.search_bar.handle_key(key_event),
                 },
             },
-            AppEvent::ShowNotification(msg) => self.notification = Some(msg),
-            AppEvent::ClearNotification => self.notification = None,
+            AppEvent::Paste(text) if matches!(self.mode, MenuMode::SearchInsert) => {
+                self.search_bar.paste(&text);
+            }
+            AppEvent::Mouse(mouse) => self.handle_mouse(mouse, state),
+            AppEvent::Action(id) => self.run_action(id, state),
             _ => {}
         }
+        Handled {
+            needs_refresh: !quiet,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        icons::{ASCII, Icons, NERD_FONT},
+        state::state_path,
+        testing::{self, Recorder, TestServer},
+    };
+    use crossterm::event::KeyEvent;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    fn session(name: &str, windows: u32, attached_count: usize) -> Session {
+        Session {
+            windows,
+            attached_count,
+            ..testing::session(name)
+        }
+    }
+
+    /// The rows of the sessions menu drawn over `sessions` on a `width` x 20 terminal, along with
+    /// the icon after window counts
+    fn render(sessions: Vec<Session>, width: u16) -> (Vec<String>, &'static str) {
+        let rows = render_with(sessions, width, Icons::from_env(), None);
+        (rows, Icons::from_env().windows)
+    }
+
+    /// Like [`render`], with `icons` and the `selected` row highlighted. Cells covered by a
+    /// double-width character are left out, so a row's width up to some text is the column it's
+    /// drawn at.
+    fn render_with(
+        sessions: Vec<Session>,
+        width: u16,
+        icons: &'static Icons,
+        selected: Option<usize>,
+    ) -> Vec<String> {
+        // Nothing is asked of tmux, the server only has to be somewhere
+        let server = TestServer::new("sessions-render");
+        let mut app = testing::app(&server);
+        let state = &mut app.state;
+        state.sessions = sessions;
+        state.icons = icons;
+        let mut menu = SessionsMenu::new(None);
+        menu.list_state.select(selected);
+        menu.pre_render(state);
+        let mut terminal = Terminal::new(TestBackend::new(width, 20)).unwrap();
+        terminal
+            .draw(|frame| frame.render_stateful_widget(&mut menu, frame.area(), state))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                let mut row = String::new();
+                let mut x = 0;
+                while x < buffer.area.width {
+                    let symbol = buffer[(x, y)].symbol();
+                    row.push_str(symbol);
+                    x += symbol.width().max(1) as u16;
+                }
+                row
+            })
+            .collect()
+    }
+
+    #[test]
+    fn grouped_rows() {
+        let all = [
+            "work/api",
+            "scratch",
+            "home/notes",
+            "work/web",
+            "/root",
+            "home/",
+            "misc",
+        ]
+        .map(testing::session);
+        let group = |name: &str, count| Row::Group {
+            name: name.to_string(),
+            count,
+        };
+        // Ungrouped first (an empty group isn't one), then groups as they first come up
+        let everything = (0..all.len()).collect::<Vec<_>>();
+        assert_eq!(
+            group_rows(&everything, &all, "/", &HashSet::new()),
+            [
+                Row::Session(1),
+                Row::Session(4),
+                Row::Session(6),
+                group("work", 2),
+                Row::Session(0),
+                Row::Session(3),
+                group("home", 2),
+                Row::Session(2),
+                Row::Session(5),
+            ]
+        );
+
+        // Only what's shown, in the order given; collapsed groups still count their sessions
+        let collapsed = HashSet::from(["work".to_string()]);
+        assert_eq!(
+            group_rows(&[3, 2, 0, 9], &all, "/", &collapsed),
+            [group("work", 2), group("home", 1), Row::Session(2)]
+        );
+
+        // No separator, no groups
+        assert_eq!(
+            group_rows(&[0, 1], &all, "", &HashSet::new()),
+            [Row::Session(0), Row::Session(1)]
+        );
+    }
+
+    #[test]
+    fn window_counts_are_aligned_to_the_widest() {
+        let (rows, icon) = render(
+            vec![
+                session("one", 7, 1),
+                session("two", 42, 0),
+                session("three", 123, 0),
+            ],
+            60,
+        );
+        // Where each session's count ends, and its window icon starts, in cells
+        let columns = ["one", "two", "three"].map(|name| {
+            let row = rows.iter().find(|row| row.contains(name)).unwrap();
+            let before_icon = &row[..row.find(icon).unwrap()];
+            (before_icon.trim_end().width(), before_icon.width())
+        });
+        assert!(
+            columns.iter().all(|&column| column == columns[0]),
+            "{rows:#?}"
+        );
+
+        let counts = ["  7", " 42", "123"];
+        for (name, count) in ["one", "two", "three"].into_iter().zip(counts) {
+            let row = rows.iter().find(|row| row.contains(name)).unwrap();
+            assert!(row.contains(&format!("{count} {icon} ")), "{row}");
+        }
+        assert!(
+            rows.iter()
+                .any(|row| row.contains("3 sessions · 172 windows · 1 attached")),
+            "{rows:#?}"
+        );
+    }
+
+    #[test]
+    fn small_window_counts_keep_two_columns() {
+        let (rows, icon) = render(vec![session("one", 1, 0), session("two", 9, 2)], 60);
+        for (name, count) in [("one", " 1"), ("two", " 9")] {
+            let row = rows.iter().find(|row| row.contains(name)).unwrap();
+            assert!(row.contains(&format!("{count} {icon} ")), "{row}");
+        }
+        assert!(
+            rows.iter()
+                .any(|row| row.contains("2 sessions · 10 windows · 1 attached")),
+            "{rows:#?}"
+        );
+    }
+
+    /// The column `text` starts at in `row`
+    fn column(row: &str, text: &str) -> usize {
+        row[..row
+            .find(text)
+            .unwrap_or_else(|| panic!("{text:?} not in {row:?}"))]
+            .width()
+    }
+
+    #[test]
+    fn wide_names_are_fitted_to_their_column() {
+        for icons in [&NERD_FONT, &ASCII] {
+            let sessions = vec![
+                session("api", 1, 0),
+                session("日本語のセッション名がとても長いです", 2, 1),
+                session("🚀🚀 launch pad for everything and more", 3, 2),
+                Session {
+                    is_current: true,
+                    ..session("混ざったmix", 4, 0)
+                },
+            ];
+            let rows = render_with(sessions, 60, icons, None);
+            let row = |start: &str| {
+                rows.iter()
+                    .find(|row| row.contains(start))
+                    .unwrap_or_else(|| panic!("{start:?} not in {rows:#?}"))
+            };
+            let cjk = row("日本語");
+            let emoji = row("🚀🚀");
+            let mixed = row("混ざった");
+            let short = row("api");
+
+            // Names start at the same column whatever their width
+            let name = column(short, "api");
+            assert_eq!(column(cjk, "日本語"), name, "{rows:#?}");
+            assert_eq!(column(emoji, "🚀🚀"), name, "{rows:#?}");
+            assert_eq!(column(mixed, "混ざった"), name, "{rows:#?}");
+
+            // Long ones are cut short rather than pushing whose clients are attached out of line
+            assert!(cjk.contains("...") && emoji.contains("..."), "{rows:#?}");
+            assert!(!mixed.contains("..."), "{rows:#?}");
+            let status = column(cjk, &format!(" {} 1 client", icons.attached));
+            assert_eq!(
+                column(emoji, &format!(" {} 2 clients", icons.attached)),
+                status
+            );
+            assert_eq!(
+                column(mixed, &format!(" {} current", icons.running)),
+                status
+            );
+            assert!(column(cjk, "...") + 3 <= status && column(emoji, "...") + 3 <= status);
+            for row in &rows {
+                assert!(row.width() <= 60, "{row:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn statuses_scroll_with_their_sessions() {
+        let sessions = (0..30)
+            .map(|i| Session {
+                is_current: i == 27,
+                ..session(&format!("s{i:02}"), 1, if i % 7 == 0 { 1 } else { 0 })
+            })
+            .collect();
+        let rows = render_with(sessions, 60, &ASCII, Some(28));
+        let listed = rows
+            .iter()
+            .filter(|row| row.contains("[w]"))
+            .collect::<Vec<_>>();
+
+        // Scrolled down to the highlighted session, with only so many fitting on screen
+        assert!(listed.len() < 30, "{rows:#?}");
+        assert!(!listed.iter().any(|row| row.contains("s00")), "{rows:#?}");
+        let highlighted = listed.iter().find(|row| row.contains("> ")).unwrap();
+        assert!(highlighted.contains("s28"), "{rows:#?}");
+
+        for row in listed {
+            let name = row
+                .split_whitespace()
+                .find(|word| word.starts_with('s'))
+                .unwrap();
+            let i = name[1..].parse::<usize>().unwrap();
+            let status = if i == 27 {
+                Some("* current")
+            } else if i % 7 == 0 {
+                Some("~ 1 client")
+            } else {
+                None
+            };
+            match status {
+                Some(status) => assert!(row.contains(status), "{row:?}"),
+                None => assert!(!row.contains('*') && !row.contains('~'), "{row:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn attached_sessions_are_asked_about_in_unsafe_mode() {
+        let server = TestServer::new("sessions-unsafe");
+        let mut app = testing::app(&server);
+        let state = &mut app.state;
+        state.unsafe_mode = true;
+        state.sessions = vec![
+            session("other-client", 1, 1),
+            Session {
+                is_current: true,
+                ..session("mine", 1, 0)
+            },
+        ];
+        for index in 0..state.sessions.len() {
+            state.mode = AppMode::Sessions;
+            state.selected_session = Some(index);
+            delete_selected(state);
+            assert!(matches!(state.mode, AppMode::Delete), "{index}");
+        }
+        assert_eq!(state.trash.len(), 0);
+        assert_eq!(server.count("rename-session"), 0);
+        assert_eq!(server.count("kill-session"), 0);
+    }
+
+    // Toggling follow mode notifies, which needs a runtime to expire it on
+    #[tokio::test]
+    async fn follow_mode_keeps_up_with_the_current_session() {
+        let server = TestServer::new("sessions-follow");
+        let mut app = testing::app(&server);
+        let state = &mut app.state;
+        let mut menu = SessionsMenu::new(Some(0));
+        // Listed again as a refresh would, with `current` being the session muffin's client is on
+        let refresh = |menu: &mut SessionsMenu, state: &mut AppState, current: &str| {
+            state.sessions = ["api", "db", "web"]
+                .map(|name| Session {
+                    is_current: name == current,
+                    ..session(name, 1, 1)
+                })
+                .into();
+            menu.pre_render(state);
+            state.selected_session
+        };
+
+        // Only while following
+        assert_eq!(refresh(&mut menu, state, "db"), Some(0));
+        toggle_follow(state);
+        assert!(state.following);
+        assert_eq!(refresh(&mut menu, state, "db"), Some(1));
+        assert_eq!(refresh(&mut menu, state, "web"), Some(2));
+
+        // Moved by hand, the selection is left there for a while
+        let up = AppEvent::Key(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
+        menu.handle_event(up, state);
+        assert_eq!(state.selected_session, Some(1));
+        assert!(menu.moved_at.is_some());
+        assert_eq!(refresh(&mut menu, state, "api"), Some(1));
+
+        // And followed again once it's been long enough
+        menu.moved_at = Some(Instant::now() - FOLLOW_PAUSE);
+        assert_eq!(refresh(&mut menu, state, "api"), Some(0));
+
+        toggle_follow(state);
+        menu.moved_at = None;
+        assert_eq!(refresh(&mut menu, state, "web"), Some(0));
+    }
+
+    // The warning is expired from a task of its own
+    #[tokio::test]
+    async fn a_warning_on_switching_and_quitting_reaches_the_desktop() {
+        let server = TestServer::new("sessions-quit");
+        server.create("keep");
+        server.create("work");
+        let _client = server.attach("keep");
+        // Where the list of recent sessions goes, for saving it to fail
+        std::fs::create_dir_all(state_path(&server.server, "mru").unwrap()).unwrap();
+        let mut app = testing::app(&server);
+        let state = &mut app.state;
+        let desktop = Recorder::default();
+        state.desktop = Some(Box::new(desktop.clone()));
+        state.exit_on_switch = true;
+        state.sessions = server.server.list_sessions().unwrap();
+        let mut menu = SessionsMenu::new(None);
+        state.selected_session = state.sessions.iter().position(|s| s.name == "work");
+        menu.pre_render(state);
+
+        let enter = AppEvent::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        menu.handle_event(enter, state);
+        assert!(state.exit);
+        let delivered = desktop.take();
+        assert_eq!(delivered.len(), 1, "{delivered:?}");
+        assert_eq!(delivered[0].0, Level::Warn);
+        assert!(
+            delivered[0].1.starts_with("Could not write"),
+            "{delivered:?}"
+        );
+    }
+
+    #[test]
+    fn only_what_changes_tmux_asks_for_a_refresh() {
+        let server = TestServer::new("sessions-handled");
+        let mut app = testing::app(&server);
+        let state = &mut app.state;
+        state.sessions = vec![session("api", 1, 0), session("db", 1, 0)];
+        let mut menu = SessionsMenu::new(Some(0));
+        menu.pre_render(state);
+        let mut press = |code| {
+            let key = AppEvent::Key(KeyEvent::new(code, KeyModifiers::NONE));
+            menu.handle_event(key, state).needs_refresh
+        };
+
+        assert!(!press(KeyCode::Char('j')));
+        assert!(!press(KeyCode::Up));
+        // Typing a search, including what would be bound otherwise
+        assert!(press(KeyCode::Char('/')));
+        assert!(!press(KeyCode::Char('d')));
+        assert!(!press(KeyCode::Enter));
+        assert!(press(KeyCode::Char('m')));
     }
 }
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+use super::presets::launch;
+use super::{Handled, Menu};
+use crate::app::{
+    driver::{AppEvent, AppMode, AppState},
+    notifications::{Level, notify},
+    utils::popup_rect,
+    widgets::{input::Input, instructions::Instructions},
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::{Buffer, Constraint, Layout, Rect},
+    style::Styled,
+    text::Line,
+    widgets::{Block, Clear, StatefulWidget, Widget},
+};
+
+/// Asks for the values of the selected preset's variables before launching it
+#[derive(Default)]
+pub struct VarsMenu<'a> {
+    preset: String,
+    /// One input per variable, in the order they're declared, prefilled with their defaults
+    fields: Vec<(String, Input<'a>)>,
+    focus: usize,
+}
+
+impl<'a> VarsMenu<'a> {
+    fn launch(&mut self, state: &mut AppState) {
+        let Some(preset) = state.presets.get_by_name(&self.preset) else {
+            return;
+        };
+        let values = self
+            .fields
+            .iter()
+            .map(|(name, input)| (name.clone(), input.value().to_string()))
+            .collect::<BTreeMap<_, _>>();
+
+        match parser::apply_vars(preset, &values) {
+            Ok(preset) => launch(state, &preset),
+            Err(e) => notify(state, Level::Error, e),
+        }
+    }
+}
+
+impl<'a> StatefulWidget for &mut VarsMenu<'a> {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut AppState) {
+        let height = 8 + 2 * self.fields.len() as u16;
+        let area = popup_rect(area, 50, height, state);
+
+        let block = Block::bordered().border_style(state.theme.create);
+        let inner_area = block.inner(area);
+        Clear.render(area, buf);
+
+        let [title_area, fields_area, instructions_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(2),
+        ])
+        .vertical_margin(1)
+        .horizontal_margin(1)
+        .areas(inner_area);
+
+        if state.notifications.is_empty() {
+            Line::from(format!("Launch '{}' with...", self.preset).set_style(state.theme.create))
+                .centered()
+                .render(title_area, buf);
+        } else {
+            state.notifications.render(title_area, buf, state.theme);
+        }
+
+        // Render one input per variable, the focused one with a cursor
+        let label_width = self
+            .fields
+            .iter()
+            .map(|(name, _)| name.len() as u16 + 1)
+            .max()
+            .unwrap_or_default();
+        let field_areas =
+            Layout::vertical(vec![Constraint::Length(2); self.fields.len()]).split(fields_area);
+        for (index, ((name, input), field_area)) in
+            self.fields.iter_mut().zip(field_areas.iter()).enumerate()
+        {
+            let [label_area, first_char, rest] = Layout::horizontal([
+                Constraint::Length(label_width),
+                Constraint::Length(2),
+                Constraint::Fill(1),
+            ])
+            .horizontal_margin(1)
+            .areas(*field_area);
+
+            name.as_str()
+                .set_style(state.theme.dim)
+                .render(label_area, buf);
+            if self.focus == index {
+                "> ".set_style(state.theme.create).render(first_char, buf);
+            }
+            input.render(rest, buf, state.theme, self.focus == index);
+        }
+
+        // Render instructions
+        {
+            let instructions = vec![
+                ("esc", "cancel"),
+                ("tab", "next field"),
+                ("enter", "launch"),
+            ];
+
+            Instructions::new(instructions, state.theme).render(instructions_area, buf);
+        }
+
+        block.render(area, buf);
+    }
+}
+
+impl<'a> Menu for VarsMenu<'a> {
+    fn on_enter(&mut self, state: &AppState) {
+        let Some(preset) = state.highlighted_preset() else {
+            return;
+        };
+        self.preset = preset.name.clone();
+        self.fields = preset
+            .vars
+            .iter()
+            .map(|var| {
+                let input = Input::new(var.default.as_deref().unwrap_or_default());
+                (var.name.clone(), input.placeholder("required"))
+            })
+            .collect();
+    }
+
+    fn on_exit(&mut self) {
+        *self = Self::default();
+    }
+
+    fn handle_event(&mut self, event: AppEvent, state: &mut AppState) -> Handled {
+        let count = self.fields.len().max(1);
+        let focused = self.fields.get_mut(self.focus).map(|(_, input)| input);
+        match event {
+            AppEvent::Key(key_event) => match key_event.code {
+                KeyCode::Esc => state.mode = AppMode::Presets,
+                KeyCode::Tab | KeyCode::Down => self.focus = (self.focus + 1) % count,
+                KeyCode::BackTab | KeyCode::Up => self.focus = (self.focus + count - 1) % count,
+                KeyCode::Enter => self.launch(state),
+                _ => {
+                    if let Some(input) = focused {
+                        input.handle_key(key_event);
+                    }
+                }
+            },
+            AppEvent::Paste(text) => {
+                if let Some(input) = focused {
+                    input.paste(&text);
+                }
+            }
+            _ => {}
+        }
+        Handled::REFRESH
+    }
+}
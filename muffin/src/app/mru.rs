@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use tmux::{Server, Session};
+
+use crate::app::state::{read_names, state_path, write_names};
+
+/// A session in the [`Mru`] list
+#[derive(Debug)]
+struct Entry {
+    /// Unknown for the names read from the state file until they're seen in a listing, as ids
+    /// don't outlive the server
+    id: Option<String>,
+    name: String,
+}
+
+/// Sessions switched to through muffin, most recent first, kept in a state file across runs.
+///
+/// Sessions are followed by id, so they keep their place when renamed; only their names are
+/// saved.
+#[derive(Debug, Default)]
+pub struct Mru {
+    entries: Vec<Entry>,
+    /// Where the list is saved, if a state directory could be found
+    path: Option<PathBuf>,
+}
+
+impl Mru {
+    /// Reads the list saved for `server`. A missing or unreadable file is just an empty list,
+    /// overwritten on the next switch.
+    pub fn load(server: &Server) -> Self {
+        let path = state_path(server, "mru");
+        let entries = read_names(path.as_deref())
+            .into_iter()
+            .map(|name| Entry { id: None, name })
+            .collect();
+        Self { entries, path }
+    }
+
+    /// Forgets the sessions that no longer exist, and catches up with the ones renamed since
+    pub fn prune(&mut self, sessions: &[Session]) {
+        self.entries.retain_mut(|entry| {
+            let session = match &entry.id {
+                Some(id) => sessions.iter().find(|s| &s.id == id),
+                None => sessions.iter().find(|s| s.name == entry.name),
+            };
+            let Some(session) = session else {
+                return false;
+            };
+            entry.id = Some(session.id.clone());
+            entry.name = session.name.clone();
+            true
+        });
+    }
+
+    /// Moves `session` to the front and saves the list
+    pub fn touch(&mut self, session: &Session) -> Result<(), String> {
+        self.entries
+            .retain(|entry| entry.id.as_ref() != Some(&session.id) && entry.name != session.name);
+        self.entries.insert(
+            0,
+            Entry {
+                id: Some(session.id.clone()),
+                name: session.name.clone(),
+            },
+        );
+        self.save()
+    }
+
+    /// [`Mru::touch`] for a session only known by name, like one just created, whose id is
+    /// picked up by the next [`Mru::prune`]
+    pub fn touch_name(&mut self, name: &str) -> Result<(), String> {
+        self.entries.retain(|entry| entry.name != name);
+        self.entries.insert(
+            0,
+            Entry {
+                id: None,
+                name: name.to_string(),
+            },
+        );
+        self.save()
+    }
+
+    /// How recently `session` was switched to, `0` being the most recent
+    pub fn rank(&self, session: &Session) -> Option<usize> {
+        self.entries.iter().position(|entry| match &entry.id {
+            Some(id) => id == &session.id,
+            None => entry.name == session.name,
+        })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        write_names(
+            self.path.as_deref(),
+            self.entries.iter().map(|entry| entry.name.as_str()),
+        )
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::time::Duration;
+
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::Style,
+    text::{Line, Span, Text},
+    widgets::{Paragraph, Widget},
+};
+use tokio::time::Instant;
+
+use crate::app::driver::{AppEvent, AppState};
+use crate::app::theme::Theme;
+
+/// How many notifications are shown at once, older ones make room for new ones
+const MAX_SHOWN: usize = 2;
+
+/// How long info and warning notifications stay up, errors stay until a key is pressed
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Somewhere notifications are delivered to
+pub trait NotificationSink: Send {
+    fn deliver(&mut self, level: Level, text: &str);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn style(self, theme: &Theme) -> Style {
+        match self {
+            Level::Info => theme.info,
+            Level::Warn => theme.warn,
+            Level::Error => theme.error,
+        }
+    }
+}
+
+#[derive(Debug, Hash)]
+struct Notification {
+    level: Level,
+    text: String,
+    created_at: Instant,
+}
+
+/// The notifications currently shown by whichever menu is active, oldest first. Every
+/// notification goes there.
+#[derive(Debug, Default, Hash)]
+pub struct Notifications {
+    queue: VecDeque<Notification>,
+}
+
+impl Notifications {
+    fn push(&mut self, level: Level, text: String) {
+        if self.queue.len() == MAX_SHOWN {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(Notification {
+            level,
+            text,
+            created_at: Instant::now(),
+        });
+    }
+
+    /// Drops the info and warnings that have been up for long enough
+    pub fn expire(&mut self) {
+        self.queue
+            .retain(|n| n.level == Level::Error || n.created_at.elapsed() < TIMEOUT);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.queue.iter().any(|n| n.level == Level::Error)
+    }
+
+    /// Drops every error, which would otherwise stay up for good
+    pub fn dismiss_errors(&mut self) {
+        self.queue.retain(|n| n.level != Level::Error);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Renders the notifications one per line, styled by level. Errors say how to dismiss them.
+    pub fn render(&self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let lines = self
+            .queue
+            .iter()
+            .map(|n| {
+                let mut line = Line::from(Span::styled(n.text.as_str(), n.level.style(theme)));
+                if n.level == Level::Error {
+                    line.push_span(Span::styled(" (any key)", theme.dim));
+                }
+                line
+            })
+            .collect::<Vec<_>>();
+        // The newest one wins when there's only room for one
+        let skip = lines.len().saturating_sub(area.height as usize);
+
+        Paragraph::new(Text::from(lines.into_iter().skip(skip).collect::<Vec<_>>()))
+            .centered()
+            .render(area, buf);
+    }
+}
+
+impl NotificationSink for Notifications {
+    fn deliver(&mut self, level: Level, text: &str) {
+        self.push(level, text.to_string());
+    }
+}
+
+/// Pops notifications up on the desktop through `notify-send`, for `desktop_notifications`.
+/// Where it's missing or there's no notification daemon, they're only logged.
+#[derive(Debug, Default)]
+pub struct DesktopNotifier;
+
+impl NotificationSink for DesktopNotifier {
+    fn deliver(&mut self, level: Level, text: &str) {
+        let urgency = match level {
+            Level::Info | Level::Warn => "normal",
+            Level::Error => "critical",
+        };
+        let mut command = tokio::process::Command::new("notify-send");
+        command
+            .args(["--app-name", "muffin", "--urgency", urgency, "muffin", text])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        tokio::spawn(async move {
+            match command.status().await {
+                Ok(status) if status.success() => {}
+                Ok(status) => tmux::log_message(&format!("notify-send failed ({status})")),
+                Err(e) => tmux::log_message(&format!("Could not run notify-send: {e}")),
+            }
+        });
+    }
+}
+
+/// Whether a notification also goes to the desktop: errors, which muffin may not be around to
+/// show for long, and anything said as it quits, which it won't show at all
+fn escalates(level: Level, exiting: bool) -> bool {
+    level == Level::Error || exiting
+}
+
+/// Shows `msg` in the active menu. Info and warnings go away on their own, errors once a key is
+/// pressed.
+///
+/// With `desktop_notifications`, errors and whatever is said once muffin is set to quit go to
+/// the desktop too.
+pub fn notify(state: &mut AppState, level: Level, msg: impl Into<String>) {
+    let msg = msg.into();
+    if escalates(level, state.exit)
+        && let Some(desktop) = &mut state.desktop
+    {
+        desktop.deliver(level, &msg);
+    }
+    state.notifications.deliver(level, &msg);
+    if level == Level::Error {
+        return;
+    }
+
+    let tx = state.event_handler.tx.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(TIMEOUT).await;
+        let _ = tx.send(AppEvent::ExpireNotifications);
+    });
+}
+
+/// Sends `msg` to the desktop only, with `desktop_notifications`, for what's worth hearing about
+/// wherever muffin is, like a long launch finishing
+pub fn notify_desktop(state: &mut AppState, level: Level, msg: &str) {
+    if let Some(desktop) = &mut state.desktop {
+        desktop.deliver(level, msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing::{self, Recorder, TestServer};
+
+    fn shown(notifications: &Notifications) -> Vec<&str> {
+        notifications
+            .queue
+            .iter()
+            .map(|n| n.text.as_str())
+            .collect()
+    }
+
+    // Info and warnings are expired from a task of their own
+    #[tokio::test]
+    async fn errors_and_what_is_said_on_quitting_reach_the_desktop() {
+        let server = TestServer::new("notify-desktop");
+        let mut app = testing::app(&server);
+        let state = &mut app.state;
+        let desktop = Recorder::default();
+        state.desktop = Some(Box::new(desktop.clone()));
+
+        notify(state, Level::Info, "info");
+        notify(state, Level::Warn, "warn");
+        notify(state, Level::Error, "error");
+        assert_eq!(desktop.take(), [(Level::Error, "error".to_string())]);
+
+        state.exit = true;
+        notify(state, Level::Info, "bye");
+        notify(state, Level::Warn, "careful");
+        assert_eq!(
+            desktop.take(),
+            [
+                (Level::Info, "bye".to_string()),
+                (Level::Warn, "careful".to_string())
+            ]
+        );
+
+        // Everything is shown in the app all the same, only so much of it at once
+        assert_eq!(shown(&state.notifications), ["bye", "careful"]);
+        assert_eq!(state.notifications.queue.len(), MAX_SHOWN);
+    }
+}
@@ -0,0 +1,293 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use tmux::{INHERIT_CWD, Preset, Server};
+
+/// Shell builtins and keywords, which run without anything on PATH. A command starting with a
+/// keyword isn't a simple one, so it isn't looked into any further either.
+const BUILTINS: &[&str] = &[
+    "!", ".", ":", "[", "[[", "alias", "bg", "bind", "break", "builtin", "case", "cd", "command",
+    "continue", "coproc", "declare", "dirs", "disown", "echo", "eval", "exec", "exit", "export",
+    "false", "fc", "fg", "for", "function", "getopts", "hash", "help", "history", "if", "jobs",
+    "kill", "let", "local", "logout", "popd", "printf", "pushd", "pwd", "read", "readonly",
+    "return", "select", "set", "shift", "shopt", "source", "test", "time", "times", "trap", "true",
+    "type", "typeset", "ulimit", "umask", "unalias", "unset", "until", "wait", "while",
+];
+
+/// Programs `preset`'s panes start that can't be found, each once, in the order the panes come.
+///
+/// This is best effort: only the first program of each command is looked at (`cd x && make`
+/// checks `cd`), and commands it can't be sure about, like ones starting with a variable or a
+/// subshell, are let through. Neither aliases nor shell functions are known, so those come up
+/// as missing.
+pub fn missing_commands(preset: &Preset) -> Vec<String> {
+    let mut missing = vec![];
+    for window in preset.windows.iter() {
+        for (cwd, command) in tmux::window_panes(window) {
+            let Some(program) = command.and_then(program_of) else {
+                continue;
+            };
+            if !exists(&program, cwd) && !missing.contains(&program) {
+                missing.push(program);
+            }
+        }
+    }
+    missing
+}
+
+/// Warnings about `preset`'s windows needing more room than the terminal they'll show in has,
+/// going by [`tmux::min_window_size`], as tmux won't split them that far. `server` is the one
+/// the preset launches on.
+pub fn cramped_windows(preset: &Preset, server: &Server) -> Vec<String> {
+    let Some((width, height)) = server
+        .client_size()
+        .or_else(|| crossterm::terminal::size().ok())
+    else {
+        return vec![];
+    };
+    cramped_in(preset, width, height)
+}
+
+/// [`cramped_windows`] in a terminal of `width` by `height` cells
+fn cramped_in(preset: &Preset, width: u16, height: u16) -> Vec<String> {
+    preset
+        .windows
+        .iter()
+        .filter_map(|window| {
+            let (min_width, min_height) = tmux::min_window_size(&window.layout);
+            (min_width > width || min_height > height).then(|| {
+                format!(
+                    "Window '{}' needs at least {min_width}x{min_height} cells, but the terminal \
+                     is {width}x{height}",
+                    window.name
+                )
+            })
+        })
+        .collect()
+}
+
+/// The program the first simple command of `command` runs, past any `NAME=value` assignments
+/// and with quotes removed, or `None` if that can't be told without a shell: expansions, globs,
+/// subshells, redirections first, and unfilled `{var}`s
+pub fn program_of(command: &str) -> Option<String> {
+    let mut chars = command.chars();
+    let mut word = String::new();
+    let mut in_word = false;
+
+    loop {
+        let c = chars.next();
+        match c {
+            Some('\'') => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            Some('"') => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '$' | '`' => return None,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            '\n' => {}
+                            c => word.extend(['\\', c]),
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            Some('\\') => match chars.next()? {
+                '\n' => {}
+                c => {
+                    in_word = true;
+                    word.push(c);
+                }
+            },
+            Some('$' | '`' | '*' | '?' | '[' | '{' | '}') => return None,
+            Some(' ' | '\t') if !in_word => {}
+            // The end of a word, and maybe of the whole simple command
+            Some(' ' | '\t' | '\n' | ';' | '&' | '|' | '<' | '>' | '(' | ')') | None => {
+                if !in_word {
+                    return None;
+                }
+                if is_assignment(&word) && matches!(c, Some(' ' | '\t')) {
+                    word.clear();
+                    in_word = false;
+                    continue;
+                }
+                if is_assignment(&word) || BUILTINS.contains(&word.as_str()) {
+                    return None;
+                }
+                // `~user` depends on the user database
+                if word.starts_with('~') && !word.starts_with("~/") && word != "~" {
+                    return None;
+                }
+                return Some(word);
+            }
+            Some(c) => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+}
+
+/// `NAME=value`, which sets a variable for the command after it
+fn is_assignment(word: &str) -> bool {
+    let Some((name, _)) = word.split_once('=') else {
+        return false;
+    };
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether `program` can be run from a pane starting in `cwd`: an executable on PATH, or a path
+/// to something that exists (relative ones are trusted where the pane's directory isn't known)
+fn exists(program: &str, cwd: &str) -> bool {
+    if !program.contains('/') {
+        return std::env::var_os("PATH").is_some_and(|paths| {
+            std::env::split_paths(&paths).any(|dir| is_executable(&dir.join(program)))
+        });
+    }
+
+    let path = expand_home(program);
+    if path.is_absolute() {
+        return path.exists();
+    }
+    if cwd == INHERIT_CWD {
+        return true;
+    }
+    expand_home(cwd).join(path).exists()
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tmux::{Layout, Window};
+
+    #[test]
+    fn program_of_command_lines() {
+        for (command, program) in [
+            ("nvim", Some("nvim")),
+            ("  nvim  src/main.rs", Some("nvim")),
+            ("cargo watch -x test", Some("cargo")),
+            // Assignments before the program are skipped
+            ("RUST_LOG=debug cargo run", Some("cargo")),
+            ("A=1 B='two words' make", Some("make")),
+            ("A=1", None),
+            ("sudo systemctl restart nginx", Some("sudo")),
+            ("env FOO=1 make", Some("env")),
+            // Quotes are removed, escapes followed
+            (
+                "'/opt/my tools/bin/run' --fast",
+                Some("/opt/my tools/bin/run"),
+            ),
+            ("\"/opt/my tools/run\"", Some("/opt/my tools/run")),
+            ("/opt/my\\ tools/run", Some("/opt/my tools/run")),
+            ("\"say \\\"hi\\\"\"", Some("say \"hi\"")),
+            ("n'v'\"im\"", Some("nvim")),
+            // Only the first simple command of the first pipeline
+            ("cd src && make", None),
+            ("make && ./run", Some("make")),
+            ("htop | less", Some("htop")),
+            ("tail -f log|grep error", Some("tail")),
+            ("npm start; bash", Some("npm")),
+            ("sleep 1 &", Some("sleep")),
+            ("cat <input", Some("cat")),
+            ("./bin/dev", Some("./bin/dev")),
+            ("~/bin/dev", Some("~/bin/dev")),
+            ("~alice/bin/dev", None),
+            // Builtins and keywords run without anything on PATH
+            ("echo hi", None),
+            ("source .env && npm start", None),
+            ("if true; then vim; fi", None),
+            ("exec zsh", None),
+            // Can't be told without a shell
+            ("$EDITOR", None),
+            ("\"$EDITOR\" file", None),
+            ("`which vim`", None),
+            ("(cd src && make)", None),
+            ("> log make", None),
+            ("nvim{,x}", None),
+            ("{editor} .", None),
+            ("bin/*", None),
+            ("'unterminated", None),
+            ("", None),
+            ("   ", None),
+        ] {
+            assert_eq!(program_of(command).as_deref(), program, "{command:?}");
+        }
+    }
+
+    #[test]
+    fn missing_commands_once_each() {
+        let preset = Preset::builder("proj")
+            .cwd("/")
+            .window(Window::builder("a").layout(Layout::hsplit([
+                Layout::pane().cmd("nivm src"),
+                Layout::pane().cmd("ls -la"),
+                Layout::pane().cmd("carggo build"),
+            ])))
+            .window(Window::builder("b").layout(Layout::vsplit([
+                Layout::pane().cmd("RUST_LOG=1 nivm"),
+                Layout::pane().cmd("cd src && nope"),
+                Layout::pane().cmd("./not-there"),
+                Layout::pane().cmd("./bin/sh"),
+                Layout::pane(),
+            ])))
+            .build()
+            .unwrap();
+        assert_eq!(missing_commands(&preset), ["nivm", "carggo", "./not-there"]);
+    }
+
+    #[test]
+    fn relative_paths_are_looked_for_in_the_pane_cwd() {
+        let preset = |cwd: &str| {
+            Preset::builder("proj")
+                .cwd(cwd)
+                .window(Window::builder("a").layout(Layout::pane().cmd("./sh")))
+                .build()
+                .unwrap()
+        };
+        assert!(missing_commands(&preset("/bin")).is_empty());
+        assert_eq!(missing_commands(&preset("/")), ["./sh"]);
+    }
+
+    #[test]
+    fn windows_too_big_for_the_terminal_are_cramped() {
+        let preset = Preset::builder("proj")
+            .window(Window::builder("one"))
+            // Needs 3x5
+            .window(Window::builder("grid").layout(Layout::hsplit([
+                Layout::pane(),
+                Layout::vsplit([Layout::pane(), Layout::pane(), Layout::pane()]),
+            ])))
+            .build()
+            .unwrap();
+        assert!(cramped_in(&preset, 80, 24).is_empty());
+        assert!(cramped_in(&preset, 3, 5).is_empty());
+        let cramped = ["Window 'grid' needs at least 3x5 cells, but the terminal is 3x4"];
+        assert_eq!(cramped_in(&preset, 3, 4), cramped);
+        assert_eq!(cramped_in(&preset, 2, 24).len(), 1);
+    }
+}
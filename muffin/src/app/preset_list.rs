@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use parser::{Diagnostic, Source};
+use tmux::Preset;
+
+/// Presets in display order, with lookup by name.
+///
+/// Pinned presets come first, then presets with an explicit `order` (ascending), then everything
+/// else; ties are broken by name.
+#[derive(Debug, Default)]
+pub struct PresetList {
+    presets: Vec<Preset>,
+    by_name: HashMap<String, usize>,
+    /// Files the presets were read from, including those without any presets
+    files: Vec<PathBuf>,
+}
+
+impl PresetList {
+    pub fn new(presets: impl IntoIterator<Item = Preset>) -> Self {
+        let mut presets = presets.into_iter().collect::<Vec<_>>();
+        presets.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then_with(|| match (a.order, b.order) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => std::cmp::Ordering::Equal,
+                })
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let by_name = presets
+            .iter()
+            .enumerate()
+            .map(|(idx, preset)| (preset.name.clone(), idx))
+            .collect();
+
+        Self {
+            presets,
+            by_name,
+            files: vec![],
+        }
+    }
+
+    /// Records the files the presets were read from
+    pub fn with_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.files = files;
+        self
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn len(&self) -> usize {
+        self.presets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.presets.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Preset> {
+        self.presets.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Preset> {
+        self.presets.iter_mut()
+    }
+
+    /// The preset at `index` in display order
+    pub fn get(&self, index: usize) -> Option<&Preset> {
+        self.presets.get(index)
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&Preset> {
+        self.by_name.get(name).map(|&idx| &self.presets[idx])
+    }
+
+    pub fn get_by_name_mut(&mut self, name: &str) -> Option<&mut Preset> {
+        self.by_name.get(name).map(|&idx| &mut self.presets[idx])
+    }
+}
+
+/// Reads and parses the presets in `sources` (files, directories of `*.kdl` files, stdin or
+/// inline documents), along with any diagnostics found while parsing
+pub fn read_presets(sources: &[Source]) -> Result<(PresetList, Vec<Diagnostic>), String> {
+    let loaded = parser::load_presets_from(sources)
+        .map_err(|e| format!("Failed to parse configuration file: {e}"))?;
+    let presets = PresetList::new(loaded.presets.into_values()).with_files(loaded.files);
+    Ok((presets, loaded.diagnostics))
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::app::icons::Icons;
+
+/// How long a directory's project type is trusted before its marker files are checked again
+const TTL: Duration = Duration::from_secs(30);
+
+/// What kind of project a directory holds, going by the files at its root
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProjectKind {
+    Rust,
+    Node,
+    Nix,
+    /// Any other git repository
+    Git,
+}
+
+impl ProjectKind {
+    /// Marker files, most specific first: a Rust crate with a `flake.nix` is a Rust project, and
+    /// about every project is also a git repository
+    const MARKERS: [(&str, ProjectKind); 4] = [
+        ("Cargo.toml", ProjectKind::Rust),
+        ("package.json", ProjectKind::Node),
+        ("flake.nix", ProjectKind::Nix),
+        (".git", ProjectKind::Git),
+    ];
+
+    /// The kind of project at `dir`, if it has any of the marker files
+    pub fn detect(dir: &Path) -> Option<Self> {
+        Self::MARKERS
+            .iter()
+            .find(|(marker, _)| dir.join(marker).exists())
+            .map(|(_, kind)| *kind)
+    }
+
+    pub fn icon(self, icons: &Icons) -> &'static str {
+        match self {
+            ProjectKind::Rust => icons.rust,
+            ProjectKind::Node => icons.node,
+            ProjectKind::Nix => icons.nix,
+            ProjectKind::Git => icons.git,
+        }
+    }
+}
+
+/// Project types of the directories sessions are in, so redraws don't keep checking the
+/// filesystem
+#[derive(Default)]
+pub struct ProjectCache {
+    entries: HashMap<PathBuf, (Option<ProjectKind>, Instant)>,
+}
+
+impl ProjectCache {
+    /// The kind of project at `dir`, checked again once the cached answer is older than [`TTL`]
+    pub fn get(&mut self, dir: &Path) -> Option<ProjectKind> {
+        if let Some((kind, checked_at)) = self.entries.get(dir)
+            && checked_at.elapsed() < TTL
+        {
+            return *kind;
+        }
+        let kind = ProjectKind::detect(dir);
+        self.entries
+            .insert(dir.to_path_buf(), (kind, Instant::now()));
+        kind
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of the test's own holding `markers`
+    fn project(name: &str, markers: &[&str]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("muffin-project-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Files all, which `.git` also is in worktrees and submodules
+        for marker in markers {
+            std::fs::write(dir.join(marker), "").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn detected_by_marker_files() {
+        for (name, markers, kind) in [
+            ("rust", &["Cargo.toml"][..], Some(ProjectKind::Rust)),
+            ("node", &["package.json"], Some(ProjectKind::Node)),
+            ("nix", &["flake.nix"], Some(ProjectKind::Nix)),
+            ("git", &[".git"], Some(ProjectKind::Git)),
+            ("none", &["README.md", "Makefile"], None),
+            ("empty", &[], None),
+        ] {
+            let dir = project(name, markers);
+            assert_eq!(ProjectKind::detect(&dir), kind, "{markers:?}");
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn most_specific_marker_wins() {
+        for (name, markers, kind) in [
+            (
+                "rust-nix",
+                &["flake.nix", "Cargo.toml", ".git"][..],
+                ProjectKind::Rust,
+            ),
+            (
+                "rust-node",
+                &["package.json", "Cargo.toml"],
+                ProjectKind::Rust,
+            ),
+            (
+                "node-nix",
+                &[".git", "flake.nix", "package.json"],
+                ProjectKind::Node,
+            ),
+            ("nix-git", &[".git", "flake.nix"], ProjectKind::Nix),
+        ] {
+            let dir = project(name, markers);
+            assert_eq!(ProjectKind::detect(&dir), Some(kind), "{markers:?}");
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn git_directories_count() {
+        let dir = project("git-dir", &[]);
+        std::fs::create_dir(dir.join(".git")).unwrap();
+        assert_eq!(ProjectKind::detect(&dir), Some(ProjectKind::Git));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn markers_in_subdirectories_dont_count() {
+        let dir = project("nested", &[]);
+        std::fs::create_dir(dir.join("crate")).unwrap();
+        std::fs::write(dir.join("crate/Cargo.toml"), "").unwrap();
+        assert_eq!(ProjectKind::detect(&dir), None);
+        assert_eq!(ProjectKind::detect(&dir.join("missing")), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cached_until_stale() {
+        let dir = project("cached", &[]);
+        let mut cache = ProjectCache::default();
+        assert_eq!(cache.get(&dir), None);
+        std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+        // Still the cached answer, without looking at the files again
+        assert_eq!(cache.get(&dir), None);
+
+        cache.entries.get_mut(&dir).unwrap().1 -= TTL;
+        assert_eq!(cache.get(&dir), Some(ProjectKind::Rust));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use tmux::{PendingCommand, Preset, Server, SpawnProgress, WaitFor};
+use tokio::time::Instant;
+
+use crate::app::driver::AppState;
+
+/// How often an unmet `wait_for` condition is checked again
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A preset whose session is being created in the background
+pub struct Launch {
+    /// As launched (variables filled in)
+    pub preset: Preset,
+    /// The last step reported, `None` until the first one is
+    pub progress: Option<SpawnProgress>,
+    /// Whether to adjust its panes once it's up
+    pub adjust: bool,
+    /// Set to skip the steps that are left
+    pub stop: Arc<AtomicBool>,
+    /// When it was launched, to tell long launches from quick ones
+    pub started: Instant,
+}
+
+/// Leaves the commands still waiting on their startup options to run in the background, on
+/// `server` where their panes are.
+///
+/// `warnings` from setting the preset up are reported along with the commands' own.
+pub fn start_pending(
+    state: &mut AppState,
+    server: Server,
+    pending: Vec<PendingCommand>,
+    warnings: Vec<String>,
+) {
+    if !pending.is_empty() || !warnings.is_empty() {
+        state.startups.spawn(async move {
+            let mut warnings = warnings;
+            warnings.extend(send_pending(server, pending).await);
+            warnings
+        });
+    }
+}
+
+/// Command phase of spawning a preset: sends every pending command once its delay has passed and
+/// its `wait_for` condition holds.
+///
+/// Panes wait independently of one another. Returns one warning per command that could not be
+/// sent, rather than failing the preset as a whole.
+pub async fn send_pending(server: Server, pending: Vec<PendingCommand>) -> Vec<String> {
+    let mut tasks = tokio::task::JoinSet::new();
+    for command in pending {
+        let server = server.clone();
+        tasks.spawn(async move { send_when_ready(&server, &command).await });
+    }
+
+    tasks
+        .join_all()
+        .await
+        .into_iter()
+        .filter_map(|result| result.err())
+        .collect()
+}
+
+async fn send_when_ready(server: &Server, pending: &PendingCommand) -> Result<(), String> {
+    let startup = &pending.startup;
+    tokio::time::sleep(startup.delay).await;
+
+    if let Some(wait_for) = &startup.wait_for {
+        let deadline = Instant::now() + startup.timeout;
+        while !is_met(server, wait_for).await {
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "{}: gave up on `{}` after waiting {}s for {wait_for}",
+                    pending.label,
+                    pending.command,
+                    startup.timeout.as_secs_f32()
+                ));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    server
+        .send_command(pending)
+        .map_err(|e| format!("{}: {e}", pending.label))
+}
+
+/// Checks a condition on the machine `server` runs on, where the panes waiting on it are
+async fn is_met(server: &Server, wait_for: &WaitFor) -> bool {
+    if !server.is_local() {
+        let (server, wait_for) = (server.clone(), wait_for.clone());
+        return tokio::task::spawn_blocking(move || server.wait_for_holds(&wait_for))
+            .await
+            .unwrap_or(false);
+    }
+    match wait_for {
+        WaitFor::Port(port) => tokio::time::timeout(
+            POLL_INTERVAL,
+            tokio::net::TcpStream::connect(("127.0.0.1", *port)),
+        )
+        .await
+        .is_ok_and(|connected| connected.is_ok()),
+        WaitFor::File(path) => {
+            let path = shellexpand::full(path)
+                .map(|p| p.to_string())
+                .unwrap_or_else(|_| path.clone());
+            tokio::fs::try_exists(path).await.unwrap_or(false)
+        }
+    }
+}
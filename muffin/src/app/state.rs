@@ -0,0 +1,289 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value, json};
+use tmux::Server;
+
+use crate::app::driver::Panel;
+use crate::paths;
+
+/// Where the UI was left when muffin last exited, to pick up from there on the next run.
+///
+/// Every field is optional, so a file that's partly invalid or from another version still
+/// restores what it can. What it names is checked against the sessions and presets there are
+/// once they're loaded.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UiState {
+    pub panel: Option<Panel>,
+    pub session: Option<String>,
+    pub preset: Option<String>,
+    /// Sessions listed most recently switched to first
+    pub by_recency: Option<bool>,
+    /// Follow mode was on, see [`AppState::following`](crate::app::driver::AppState::following)
+    pub follow: Option<bool>,
+    /// What the sessions were filtered by
+    pub search: Option<String>,
+    pub tag_filter: Option<String>,
+    pub collapsed: Vec<String>,
+    /// Presets files whose presets were hidden
+    pub collapsed_files: Vec<String>,
+}
+
+impl UiState {
+    /// Reads the state saved for `server`. A missing file restores nothing, an unreadable one is
+    /// only logged, as it gets overwritten on exit anyway.
+    pub fn load(server: &Server) -> Self {
+        let Some(path) = state_path(server, "ui.json") else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Self::from_json(&content).unwrap_or_else(|e| {
+                tmux::log_message(&format!("Ignoring '{}': {e}", path.display()));
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                tmux::log_message(&format!("Could not read '{}': {e}", path.display()));
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes the state for `server` to read back next time, logging rather than reporting
+    /// failures, as they're no reason not to exit
+    pub fn save(&self, server: &Server) {
+        let Some(path) = state_path(server, "ui.json") else {
+            return;
+        };
+        if let Err(e) = paths::write_file(&path, &self.to_json()) {
+            tmux::log_message(&e);
+        }
+    }
+
+    /// Reads what `json` has of the fields, skipping the ones of the wrong type
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value = serde_json::from_str::<Value>(json).map_err(|e| e.to_string())?;
+        let object = value.as_object().ok_or("expected an object")?;
+        let string = |key| object.get(key).and_then(Value::as_str).map(String::from);
+        let strings = |key| {
+            object
+                .get(key)
+                .and_then(Value::as_array)
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        Ok(Self {
+            panel: object
+                .get("panel")
+                .and_then(Value::as_str)
+                .and_then(|panel| match panel {
+                    "sessions" => Some(Panel::Sessions),
+                    "presets" => Some(Panel::Presets),
+                    _ => None,
+                }),
+            session: string("session"),
+            preset: string("preset"),
+            by_recency: object.get("by_recency").and_then(Value::as_bool),
+            follow: object.get("follow").and_then(Value::as_bool),
+            search: string("search"),
+            tag_filter: string("tag_filter"),
+            collapsed: strings("collapsed"),
+            collapsed_files: strings("collapsed_files"),
+        })
+    }
+
+    /// The fields that are set, as [`UiState::from_json`] reads them back
+    pub fn to_json(&self) -> String {
+        let mut object = Map::new();
+        if let Some(panel) = self.panel {
+            let panel = match panel {
+                Panel::Sessions => "sessions",
+                Panel::Presets => "presets",
+            };
+            object.insert("panel".into(), json!(panel));
+        }
+        for (key, value) in [
+            ("session", &self.session),
+            ("preset", &self.preset),
+            ("search", &self.search),
+            ("tag_filter", &self.tag_filter),
+        ] {
+            if let Some(value) = value {
+                object.insert(key.into(), json!(value));
+            }
+        }
+        if let Some(by_recency) = self.by_recency {
+            object.insert("by_recency".into(), json!(by_recency));
+        }
+        if let Some(follow) = self.follow {
+            object.insert("follow".into(), json!(follow));
+        }
+        for (key, values) in [
+            ("collapsed", &self.collapsed),
+            ("collapsed_files", &self.collapsed_files),
+        ] {
+            if !values.is_empty() {
+                object.insert(key.into(), json!(values));
+            }
+        }
+        format!("{:#}\n", Value::Object(object))
+    }
+}
+
+/// `<name>` in the [state directory](paths::state_dir), with servers other than the default one
+/// getting files of their own (`mru-<server>`, `ui-<server>.json`), as their sessions are
+/// unrelated
+pub fn state_path(server: &Server, name: &str) -> Option<PathBuf> {
+    let state_dir = paths::state_dir()?;
+
+    let server = server.to_string();
+    let file_name = if server == "default" {
+        name.to_string()
+    } else {
+        let server = server.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "_");
+        match name.split_once('.') {
+            Some((stem, extension)) => format!("{stem}-{server}.{extension}"),
+            None => format!("{name}-{server}"),
+        }
+    };
+    Some(state_dir.join(file_name))
+}
+
+/// The names saved one per line at `path`, blank lines skipped. A missing or unreadable file is
+/// no names, as it gets overwritten on the next save anyway.
+pub fn read_names(path: Option<&Path>) -> Vec<String> {
+    path.and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|content| {
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Saves `names` one per line for [`read_names`], doing nothing without a state directory
+pub fn write_names<'n>(
+    path: Option<&Path>,
+    names: impl IntoIterator<Item = &'n str>,
+) -> Result<(), String> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let content = names
+        .into_iter()
+        .map(|name| format!("{name}\n"))
+        .collect::<String>();
+    paths::write_file(path, &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full() -> UiState {
+        UiState {
+            panel: Some(Panel::Presets),
+            session: Some("my proj".to_string()),
+            preset: Some("api".to_string()),
+            by_recency: Some(true),
+            follow: Some(false),
+            search: Some("we\"b ü".to_string()),
+            tag_filter: Some("work".to_string()),
+            collapsed: vec!["work".to_string(), "home".to_string()],
+            collapsed_files: vec!["/etc/muffin/presets.kdl".to_string()],
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        for state in [
+            full(),
+            UiState::default(),
+            UiState {
+                panel: Some(Panel::Sessions),
+                by_recency: Some(false),
+                ..Default::default()
+            },
+        ] {
+            assert_eq!(UiState::from_json(&state.to_json()).unwrap(), state);
+        }
+    }
+
+    #[test]
+    fn unset_fields_are_left_out() {
+        assert_eq!(UiState::default().to_json(), "{}\n");
+        let state = UiState {
+            session: Some("a".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(state.to_json(), "{\n  \"session\": \"a\"\n}\n");
+    }
+
+    #[test]
+    fn malformed_json_is_an_error() {
+        for json in ["", "{", "not json", "{\"panel\": }"] {
+            assert!(UiState::from_json(json).is_err(), "{json}");
+        }
+        // Valid, but not what's saved
+        for json in ["[]", "\"sessions\"", "null", "3"] {
+            assert_eq!(
+                UiState::from_json(json),
+                Err("expected an object".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn fields_of_the_wrong_type_are_skipped() {
+        let json = r#"{
+            "panel": "windows",
+            "session": 3,
+            "preset": "api",
+            "by_recency": "yes",
+            "follow": true,
+            "collapsed": ["work", 1, null, "home"],
+            "collapsed_files": "not a list"
+        }"#;
+        assert_eq!(
+            UiState::from_json(json).unwrap(),
+            UiState {
+                preset: Some("api".to_string()),
+                follow: Some(true),
+                collapsed: vec!["work".to_string(), "home".to_string()],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn older_and_newer_files_restore_what_they_can() {
+        // From before follow mode and tag filters, with a field since dropped
+        let older = r#"{"panel": "sessions", "session": "a", "sort": "name"}"#;
+        assert_eq!(
+            UiState::from_json(older).unwrap(),
+            UiState {
+                panel: Some(Panel::Sessions),
+                session: Some("a".to_string()),
+                ..Default::default()
+            }
+        );
+        // From a later version, with fields this one doesn't know
+        let newer = r#"{"version": 2, "preset": "api", "layout": {"split": 0.5}}"#;
+        assert_eq!(
+            UiState::from_json(newer).unwrap(),
+            UiState {
+                preset: Some("api".to_string()),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! What tests that talk to tmux share
+
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use parser::{Config, Source};
+use tmux::{Server, Session};
+
+use crate::app::driver::App;
+use crate::app::notifications::{Level, NotificationSink};
+use crate::app::theme::Theme;
+
+/// Points muffin's state directory and tmux's log at a scratch directory of this test binary's
+/// own, so tests leave the user's state alone and can tell which tmux commands they ran. Only
+/// done once, before the first tmux command, as the log can't be moved afterwards.
+fn scratch_dir() -> &'static PathBuf {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("muffin-tests-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // SAFETY: nothing else reads the environment while tests are starting their servers
+        unsafe { std::env::set_var("XDG_STATE_HOME", &dir) };
+        tmux::set_log_file(dir.join("tmux.log").to_str().unwrap()).unwrap();
+        dir
+    })
+}
+
+/// A tmux server of the test's own, killed along with its sessions once done
+pub struct TestServer {
+    pub server: Server,
+    socket: String,
+}
+
+impl TestServer {
+    pub fn new(name: &str) -> Self {
+        scratch_dir();
+        let socket = format!("muffin-{name}-{}", std::process::id());
+        Self {
+            server: Server::socket(&socket),
+            socket,
+        }
+    }
+
+    pub fn create(&self, name: &str) -> Session {
+        self.server.create_session(name, None).unwrap();
+        self.server
+            .list_sessions()
+            .unwrap()
+            .into_iter()
+            .find(|session| session.name == name)
+            .unwrap()
+    }
+
+    /// Attaches a control mode client to `session`, for `switch-client` to have one to move.
+    /// Detached once dropped.
+    pub fn attach(&self, session: &str) -> Client {
+        let child = Command::new("tmux")
+            .args(["-L", &self.socket, "-C", "attach-session", "-t", session])
+            // Kept open, or the client would take it as being told to go
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let attached = || {
+            let output = Command::new("tmux")
+                .args(["-L", &self.socket, "list-clients"])
+                .output()
+                .unwrap();
+            !output.stdout.is_empty()
+        };
+        while !attached() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        Client(child)
+    }
+
+    /// How many times `subcommand` was run on this server so far
+    pub fn count(&self, subcommand: &str) -> usize {
+        self.count_matching(&format!("tmux -u -L {} {subcommand} ", self.socket))
+    }
+
+    /// How many tmux commands of any kind were run on this server so far
+    pub fn commands(&self) -> usize {
+        self.count_matching(&format!("tmux -u -L {} ", self.socket))
+    }
+
+    fn count_matching(&self, command: &str) -> usize {
+        let log = std::fs::read_to_string(scratch_dir().join("tmux.log")).unwrap_or_default();
+        log.lines().filter(|line| line.contains(command)).count()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.server.kill_server();
+    }
+}
+
+/// A client attached by [`TestServer::attach`]
+pub struct Client(Child);
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Keeps the notifications it's given, for tests to look at
+#[derive(Clone, Default)]
+pub struct Recorder(Arc<Mutex<Vec<(Level, String)>>>);
+
+impl NotificationSink for Recorder {
+    fn deliver(&mut self, level: Level, text: &str) {
+        self.0.lock().unwrap().push((level, text.to_string()));
+    }
+}
+
+impl Recorder {
+    /// What was delivered since the last time
+    pub fn take(&self) -> Vec<(Level, String)> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+/// An app on `server` without any presets
+pub fn app(server: &TestServer) -> App {
+    App::new(
+        vec![Source::Inline(String::new())],
+        false,
+        false,
+        Config::default(),
+        Theme::from_config(None).unwrap(),
+        server.server.clone(),
+        None,
+    )
+}
+
+/// A session named `name` with a window and nothing else to it, as tmux would list it
+pub fn session(name: &str) -> Session {
+    Session {
+        id: format!("${name}"),
+        name: name.to_string(),
+        windows: 1,
+        created: None,
+        activity: None,
+        attached_count: 0,
+        is_current: false,
+        alert: None,
+        path: None,
+        preset: None,
+        auto_name: None,
+        note: None,
+        command: None,
+        color: None,
+        tags: vec![],
+    }
+}
@@ -0,0 +1,185 @@
+use std::ffi::OsString;
+
+use ratatui::style::{Color, Modifier, Style};
+
+/// The styles used when rendering, so nothing has to pick a color inline.
+///
+/// The default theme assumes a dark terminal background. `light` sticks to colors that stay
+/// readable on a light one, `high-contrast` to bright and bold ones, and `none` draws without any
+/// color at all, for monochrome terminals and screen readers.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// What the config file's `theme` calls it
+    pub name: &'static str,
+    /// Border and title of the popups that create something (sessions, windows, launches)
+    pub create: Style,
+    /// Border and title of the rename popups
+    pub rename: Style,
+    /// Border and title of the delete popups, and of fatal errors
+    pub delete: Style,
+    /// Border and title of the launch plan popup
+    pub plan: Style,
+    /// The highlighted list row
+    pub highlight: Style,
+    /// Hints about what a menu is showing, like the tag being filtered on
+    pub accent: Style,
+    /// Keys in instructions
+    pub key: Style,
+    /// Anything secondary: instructions, placeholders, the header
+    pub dim: Style,
+    /// Cursor of the sessions menu's search bar
+    pub cursor: Style,
+    /// The current session and running presets
+    pub current: Style,
+    /// Sessions attached by other clients
+    pub attached: Style,
+    pub info: Style,
+    pub warn: Style,
+    pub error: Style,
+    /// Whether presets' own `color`s are drawn
+    pub preset_colors: bool,
+}
+
+const fn fg(color: Color) -> Style {
+    Style::new().fg(color)
+}
+
+pub const DEFAULT: Theme = Theme {
+    name: "default",
+    create: fg(Color::Blue),
+    rename: fg(Color::LightGreen),
+    delete: fg(Color::Red),
+    plan: fg(Color::Cyan),
+    highlight: fg(Color::Cyan).add_modifier(Modifier::BOLD.union(Modifier::ITALIC)),
+    accent: fg(Color::Cyan),
+    key: fg(Color::Gray),
+    dim: fg(Color::DarkGray),
+    cursor: Style::new().bg(Color::White),
+    current: fg(Color::Green),
+    attached: fg(Color::Yellow),
+    info: fg(Color::Green),
+    warn: fg(Color::Yellow),
+    error: fg(Color::Red),
+    preset_colors: true,
+};
+
+pub const LIGHT: Theme = Theme {
+    name: "light",
+    create: fg(Color::Blue),
+    rename: fg(Color::Green),
+    delete: fg(Color::Red),
+    plan: fg(Color::Magenta),
+    highlight: fg(Color::Blue).add_modifier(Modifier::BOLD.union(Modifier::ITALIC)),
+    accent: fg(Color::Magenta),
+    key: fg(Color::Black),
+    dim: fg(Color::DarkGray),
+    cursor: Style::new().bg(Color::Black).fg(Color::White),
+    current: fg(Color::Green),
+    attached: fg(Color::Magenta),
+    info: fg(Color::Green),
+    warn: fg(Color::Magenta),
+    error: fg(Color::Red),
+    preset_colors: true,
+};
+
+pub const HIGH_CONTRAST: Theme = Theme {
+    name: "high-contrast",
+    create: fg(Color::LightBlue).add_modifier(Modifier::BOLD),
+    rename: fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+    delete: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+    plan: fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+    highlight: Style::new().add_modifier(Modifier::BOLD.union(Modifier::REVERSED)),
+    accent: fg(Color::LightCyan),
+    key: fg(Color::White).add_modifier(Modifier::BOLD),
+    dim: fg(Color::White),
+    cursor: Style::new().add_modifier(Modifier::REVERSED),
+    current: fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+    attached: fg(Color::LightYellow),
+    info: fg(Color::LightGreen),
+    warn: fg(Color::LightYellow),
+    error: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+    preset_colors: true,
+};
+
+/// Modifiers only, which tell things apart without relying on color
+pub const NONE: Theme = Theme {
+    name: "none",
+    create: Style::new(),
+    rename: Style::new(),
+    delete: Style::new(),
+    plan: Style::new(),
+    highlight: Style::new().add_modifier(Modifier::BOLD.union(Modifier::REVERSED)),
+    accent: Style::new().add_modifier(Modifier::ITALIC),
+    key: Style::new().add_modifier(Modifier::BOLD),
+    dim: Style::new(),
+    cursor: Style::new().add_modifier(Modifier::REVERSED),
+    current: Style::new().add_modifier(Modifier::BOLD),
+    attached: Style::new(),
+    info: Style::new(),
+    warn: Style::new().add_modifier(Modifier::BOLD),
+    error: Style::new().add_modifier(Modifier::BOLD),
+    preset_colors: false,
+};
+
+/// Every theme, in the order [`Theme::next`] goes through them
+const THEMES: [&Theme; 4] = [&DEFAULT, &LIGHT, &HIGH_CONTRAST, &NONE];
+
+impl Theme {
+    /// Picks the theme named in the config file, `default` if it doesn't name one. Setting
+    /// `NO_COLOR` picks `none` whatever the config says.
+    pub fn from_config(name: Option<&str>) -> Result<&'static Theme, String> {
+        Self::resolve(name, std::env::var_os("NO_COLOR"))
+    }
+
+    /// [`Theme::from_config`] with the value of `NO_COLOR` given
+    pub(crate) fn resolve(
+        name: Option<&str>,
+        no_color: Option<OsString>,
+    ) -> Result<&'static Theme, String> {
+        if no_color.is_some_and(|value| !value.is_empty()) {
+            return Ok(&NONE);
+        }
+        let name = name.unwrap_or(DEFAULT.name);
+        THEMES
+            .into_iter()
+            .find(|theme| theme.name == name)
+            .ok_or_else(|| {
+                let names = THEMES.map(|theme| theme.name);
+                format!(
+                    "Unknown theme `{name}`, expected one of `{}`",
+                    names.join("`, `")
+                )
+            })
+    }
+
+    /// The theme after this one, back to the first after the last
+    pub fn next(&self) -> &'static Theme {
+        let position = THEMES
+            .iter()
+            .position(|theme| theme.name == self.name)
+            .unwrap_or_default();
+        THEMES[(position + 1) % THEMES.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggling_goes_through_every_theme_and_back() {
+        let mut theme = Theme::resolve(None, None).unwrap();
+        let mut names = vec![];
+        for _ in 0..=THEMES.len() {
+            names.push(theme.name);
+            theme = theme.next();
+        }
+        assert_eq!(
+            names,
+            ["default", "light", "high-contrast", "none", "default"]
+        );
+        // A copy is still the theme it was copied from
+        let copy = HIGH_CONTRAST;
+        assert_eq!(copy.next().name, "none");
+    }
+}
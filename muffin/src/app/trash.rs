@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tmux::{Server, Session};
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::time::Instant;
+
+/// Deleted sessions are hidden under names starting with this until they're killed for good
+const TRASH_PREFIX: &str = "__muffin_trash_";
+
+/// How long a deleted session can still be brought back
+pub const GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Whether `session` is one muffin is about to kill, and so shouldn't be listed
+pub fn is_trash(session: &str) -> bool {
+    session.starts_with(TRASH_PREFIX)
+}
+
+struct PendingDeletion {
+    /// The session's original name, which it gets back if restored
+    name: String,
+    deleted_at: Instant,
+    timer: AbortHandle,
+}
+
+/// Sessions deleted through muffin, kept alive under a hidden name for a [`GRACE_PERIOD`] so the
+/// deletion can be undone
+pub struct Trash {
+    /// Keyed by the session's id, which it keeps under the name it's hidden under. Names aren't
+    /// unique in the trash, as a new session can take one and be deleted in turn.
+    pending: HashMap<String, PendingDeletion>,
+    /// One per pending deletion, each resolving to its session's id once the grace period is over
+    timers: JoinSet<String>,
+    grace_period: Duration,
+}
+
+impl Default for Trash {
+    fn default() -> Self {
+        Self {
+            pending: HashMap::new(),
+            timers: JoinSet::new(),
+            grace_period: GRACE_PERIOD,
+        }
+    }
+}
+
+impl Trash {
+    /// Hides `session` and starts its grace period, killing it right away if it can't be hidden
+    pub fn delete(&mut self, server: &Server, session: &Session) -> Result<(), String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        // The id tells apart namesakes deleted within the same second
+        let trash_name = format!(
+            "{TRASH_PREFIX}{}_{}_{timestamp}",
+            session.name,
+            session.id.trim_start_matches('$')
+        );
+
+        if server.rename_session(&session.id, &trash_name).is_err() {
+            return server
+                .delete_session(&session.id)
+                .map_err(|e| e.to_string());
+        }
+
+        let id = session.id.clone();
+        let grace_period = self.grace_period;
+        let timer = self.timers.spawn(async move {
+            tokio::time::sleep(grace_period).await;
+            id
+        });
+        let replaced = self.pending.insert(
+            session.id.clone(),
+            PendingDeletion {
+                name: session.name.clone(),
+                deleted_at: Instant::now(),
+                timer,
+            },
+        );
+        // Only if tmux reused the id of a session that's gone by now, whose timer has nothing
+        // left to kill
+        if let Some(replaced) = replaced {
+            replaced.timer.abort();
+        }
+        Ok(())
+    }
+
+    /// Original name of the most recently deleted session that can still be restored
+    pub fn latest(&self) -> Option<&str> {
+        self.latest_id().map(|id| self.pending[id].name.as_str())
+    }
+
+    fn latest_id(&self) -> Option<&str> {
+        self.pending
+            .iter()
+            .max_by_key(|(_, deletion)| deletion.deleted_at)
+            .map(|(id, _)| id.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Restores the most recently deleted session under its original name, returning that name.
+    ///
+    /// On failure (ex: a new session took the name in the meantime) it stays in the trash.
+    pub fn undo(&mut self, server: &Server) -> Option<Result<String, String>> {
+        let id = self.latest_id()?.to_string();
+        let name = self.pending[&id].name.clone();
+        if let Err(e) = server.rename_session(&id, &name) {
+            return Some(Err(format!("Could not restore '{name}': {e}")));
+        }
+
+        if let Some(deletion) = self.pending.remove(&id) {
+            deletion.timer.abort();
+        }
+        Some(Ok(name))
+    }
+
+    /// Waits for the next grace period to run out, returning the session's id
+    pub async fn next_expired(&mut self) -> Option<String> {
+        loop {
+            match self.timers.join_next().await? {
+                Ok(name) => return Some(name),
+                // Aborted by an undo
+                Err(_) => continue,
+            }
+        }
+    }
+
+    /// Kills the session with id `id`, whose grace period ran out
+    pub fn expire(&mut self, server: &Server, id: &str) -> Result<(), String> {
+        let Some(deletion) = self.pending.remove(id) else {
+            return Ok(());
+        };
+        server
+            .delete_session(id)
+            .map_err(|e| format!("Could not delete '{}': {e}", deletion.name))
+    }
+
+    /// Forgets every pending deletion, for when their sessions are already gone (ex: the server
+    /// was killed)
+    pub fn clear(&mut self) {
+        self.timers.abort_all();
+        self.pending.clear();
+    }
+
+    /// Kills every session still in the trash without waiting, returning the errors
+    pub fn flush(&mut self, server: &Server) -> Vec<String> {
+        self.timers.abort_all();
+        let ids = self.pending.keys().cloned().collect::<Vec<_>>();
+        ids.iter()
+            .filter_map(|id| self.expire(server, id).err())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing::TestServer;
+
+    fn ids(server: &TestServer) -> Vec<(String, bool)> {
+        server
+            .server
+            .list_sessions()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|session| (session.id, is_trash(&session.name)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn deleting_a_namesake_keeps_both_in_the_trash() {
+        let server = TestServer::new("trash");
+        // Keeps the server up once both are killed
+        server.create("keep");
+        let mut trash = Trash {
+            grace_period: Duration::from_millis(300),
+            ..Default::default()
+        };
+
+        let first = server.create("api");
+        trash.delete(&server.server, &first).unwrap();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let second = server.create("api");
+        assert_ne!(first.id, second.id);
+        trash.delete(&server.server, &second).unwrap();
+        assert_eq!(trash.len(), 2);
+        assert!(ids(&server).contains(&(first.id.clone(), true)));
+        assert!(ids(&server).contains(&(second.id.clone(), true)));
+
+        // The first grace period runs out first, and only kills the first session
+        let expired = trash.next_expired().await.unwrap();
+        assert_eq!(expired, first.id);
+        trash.expire(&server.server, &expired).unwrap();
+        assert_eq!(trash.len(), 1);
+        assert!(!ids(&server).iter().any(|(id, _)| *id == first.id));
+        assert!(ids(&server).contains(&(second.id.clone(), true)));
+
+        // The second is still there to restore
+        assert_eq!(trash.latest(), Some("api"));
+        assert_eq!(trash.undo(&server.server).unwrap().unwrap(), "api");
+        assert_eq!(trash.len(), 0);
+        assert!(ids(&server).contains(&(second.id.clone(), false)));
+    }
+
+    #[tokio::test]
+    async fn flush_kills_every_namesake() {
+        let server = TestServer::new("flush");
+        server.create("keep");
+        let mut trash = Trash::default();
+
+        let first = server.create("api");
+        trash.delete(&server.server, &first).unwrap();
+        let second = server.create("api");
+        trash.delete(&server.server, &second).unwrap();
+
+        assert!(trash.flush(&server.server).is_empty());
+        assert_eq!(trash.len(), 0);
+        assert!(!ids(&server).iter().any(|(_, trashed)| *trashed));
+    }
+}
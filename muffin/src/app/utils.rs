@@ -1,12 +1,19 @@
-use std::time::Duration;
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
 
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
-    layout::{Constraint, Flex, Layout, Rect},
-    style::Stylize,
+    layout::{Constraint, Flex, Layout, Position, Rect},
+    style::{Color, Styled, Stylize},
     text::{Line, Span},
 };
 
-use crate::app::driver::{AppEvent, EventHandler};
+use tmux::{Preset, Server, Session};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::app::driver::AppState;
+use crate::app::preset_list::PresetList;
+use crate::app::theme::Theme;
 
 #[allow(unused)]
 /// helper function to create a centered rect using up certain percentage of the available rect `r`
@@ -35,28 +42,441 @@ pub fn centered_fixed_rect(r: Rect, width: u16, height: u16) -> Rect {
     .split(popup_area)[1]
 }
 
-#[allow(unused)]
-pub fn make_instructions<'a>(instructions: Vec<(&'a str, &'a str)>) -> Line<'a> {
-    Line::from(
-        instructions
-            .iter()
-            .flat_map(|(key, desc)| {
-                vec![format!(" {}", key).gray(), format!(":{desc} ").dark_gray()]
-            })
-            .collect::<Vec<Span>>(),
-    )
+/// Where a popup of `width` by `height` goes: centered in `area`, or all of it when muffin itself
+/// runs in a (small) tmux popup
+pub fn popup_rect(area: Rect, width: u16, height: u16, state: &AppState) -> Rect {
+    if state.popup {
+        area
+    } else {
+        centered_fixed_rect(area, width, height)
+    }
 }
 
-#[allow(unused)]
-pub fn send_timed_notification(event_handler: &EventHandler, msg: String) {
-    let tx = event_handler.tx.clone();
+/// A menu title, followed by the targeted tmux server when it isn't the local default
+pub fn make_title<'a>(title: &'a str, server: &Server, theme: &Theme) -> Line<'a> {
+    let mut line = Line::from(title.underlined().bold().italic());
+    if *server != Server::local() {
+        line.push_span(format!(" ({server})").set_style(theme.dim).italic());
+    }
+    line
+}
+
+/// One `key:description` line per pair, laid out by
+/// [`Instructions`](crate::app::widgets::instructions::Instructions)
+pub fn make_instructions<'a>(
+    instructions: Vec<(&'a str, &'a str)>,
+    theme: &Theme,
+) -> Vec<Line<'a>> {
+    instructions
+        .into_iter()
+        .map(|(key, desc)| {
+            Line::from(vec![
+                Span::styled(key, theme.key),
+                Span::styled(format!(":{desc}"), theme.dim),
+            ])
+        })
+        .collect()
+}
+
+/// Width of the column of names in a list drawn across `width` cells: a share of it, so names
+/// get more room on wide screens, and at least 20 cells where there's room for them
+pub fn name_column_width(width: u16) -> u16 {
+    (width * 2 / 5).clamp(20.min(width), 40)
+}
+
+/// Width of a column of `counts` right-aligned, wide enough for the largest of them and never
+/// narrower than 2, so lists of small counts keep their usual layout
+pub fn count_column_width(counts: impl IntoIterator<Item = usize>) -> usize {
+    counts
+        .into_iter()
+        .max()
+        .map_or(2, |max| max.to_string().len().max(2))
+}
+
+/// Whether `key` only moves the highlight of a list, asking nothing of tmux
+pub fn is_navigation(key: &KeyEvent) -> bool {
+    !key.modifiers.contains(KeyModifiers::CONTROL)
+        && matches!(
+            key.code,
+            KeyCode::Up
+                | KeyCode::Down
+                | KeyCode::PageUp
+                | KeyCode::PageDown
+                | KeyCode::Home
+                | KeyCode::End
+                | KeyCode::Char('j' | 'k' | 'g' | 'G' | 'M')
+        )
+}
+
+/// Shortens `text` so it occupies at most `max_width` terminal columns, ending it with "..." when
+/// anything had to be cut.
+///
+/// Counts display columns rather than chars, so wide characters (CJK, emoji) can't overflow.
+pub fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+
+    let budget = max_width.saturating_sub(3);
+    let mut used = 0;
+    let mut truncated = String::new();
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > budget {
+            break;
+        }
+        used += w;
+        truncated.push(c);
+    }
+    truncated.push_str(&"..."[..max_width.min(3)]);
+    truncated
+}
+
+/// Like [`truncate_to_width`], but padded with spaces to exactly `width` columns, so whatever
+/// follows it on the line lines up
+pub fn fit_to_width(text: &str, width: usize) -> String {
+    let mut fitted = truncate_to_width(text, width);
+    fitted.push_str(&" ".repeat(width.saturating_sub(fitted.width())));
+    fitted
+}
+
+/// Breaks `text` into lines of at most `max_width` columns at word boundaries, keeping its own
+/// line breaks but not its blank lines.
+///
+/// Past `max_lines`, the rest is cut and the last line ends with "..." instead.
+pub fn wrap_to_width(text: &str, max_width: usize, max_lines: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines().filter(|line| !line.trim().is_empty()) {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.width() + 1 + word.width() > max_width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+
+    let cut = lines.len() > max_lines;
+    lines.truncate(max_lines);
+    if cut && let Some(last) = lines.last_mut() {
+        *last = truncate_to_width(&format!("{last}..."), max_width);
+    }
+    lines
+        .iter()
+        .map(|line| truncate_to_width(line, max_width))
+        .collect()
+}
+
+/// Scores how well `query` matches `candidate` as a case-insensitive subsequence.
+///
+/// Returns `None` when it doesn't match at all; otherwise higher is better, favoring matches at the
+/// start of the candidate and runs of consecutive characters.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
+    let candidate = candidate.to_lowercase();
+    let mut positions = candidate.chars().enumerate();
+    let mut last_position = None;
+    let mut score = 0;
+
+    for q in query.to_lowercase().chars() {
+        let (position, _) = positions.find(|(_, c)| *c == q)?;
+        score += match last_position {
+            Some(last) if last + 1 == position => 3,
+            None if position == 0 => 5,
+            _ => 1,
+        };
+        last_position = Some(position);
+    }
+
+    Some(score)
+}
+
+/// The name in `names` that best matches `query`: an exact match, or else the best
+/// [`fuzzy_score`], the earliest listed winning ties
+pub fn best_match<'a>(query: &str, names: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let mut best = None;
+    for name in names {
+        if name == query {
+            return Some(name);
+        }
+        if let Some(score) = fuzzy_score(query, name)
+            && best.is_none_or(|(best_score, _)| score > best_score)
+        {
+            best = Some((score, name));
+        }
+    }
+    best.map(|(_, name)| name)
+}
+
+/// Lists the sessions on every socket the presets launch into besides `server`'s, once per
+/// socket. Sockets whose server isn't running (or can't be reached) have none.
+pub fn list_preset_sockets(server: &Server, presets: &PresetList) -> HashMap<String, Vec<Session>> {
+    list_sockets(server, preset_sockets(presets))
+}
+
+/// The sockets the presets launch into, each once
+pub fn preset_sockets(presets: &PresetList) -> BTreeSet<String> {
+    presets
+        .iter()
+        .filter_map(|preset| preset.socket.clone())
+        .collect()
+}
+
+/// Like [`list_preset_sockets`], for `sockets` gathered with [`preset_sockets`]
+pub fn list_sockets(server: &Server, sockets: BTreeSet<String>) -> HashMap<String, Vec<Session>> {
+    sockets
+        .into_iter()
+        .map(|socket| {
+            let other = server.clone().with_socket(&socket);
+            (socket, other)
+        })
+        .filter(|(_, other)| other != server)
+        .map(|(socket, other)| {
+            let sessions = other
+                .list_sessions_if_running()
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+            (socket, sessions)
+        })
+        .collect()
+}
+
+/// Marks every preset that has a live tmux session of the same name as running, and gives those
+/// sessions the preset's color and tags. Only sessions muffin launched from the preset count (see
+/// [`Session::is_preset`]), not ones that happen to be named the same.
+///
+/// Presets on a socket of their own are looked for in `preset_sockets` (see
+/// [`AppState::preset_sockets`]) rather than in `sessions`. Sessions whose preset is gone, or
+/// that were renamed away from it, lose its color and tags.
+pub fn mark_running_presets(
+    presets: &mut PresetList,
+    sessions: &mut [Session],
+    preset_sockets: &HashMap<String, Vec<Session>>,
+) {
+    for preset in presets.iter_mut() {
+        preset.running = preset
+            .socket
+            .as_ref()
+            .and_then(|socket| preset_sockets.get(socket))
+            .is_some_and(|sessions| sessions.iter().any(|s| s.is_preset(&preset.name)));
+    }
+
+    // Lookups by name are a map access rather than a scan
+    for session in sessions.iter_mut() {
+        let preset = presets
+            .get_by_name_mut(&session.name)
+            .filter(|preset| !is_elsewhere(preset, preset_sockets))
+            .filter(|preset| session.is_preset(&preset.name));
+        match preset {
+            Some(preset) => {
+                preset.running = true;
+                session.color = preset.color.clone();
+                session.tags = preset.tags.clone();
+            }
+            None => {
+                session.color = None;
+                session.tags.clear();
+            }
+        }
+    }
+}
+
+/// Whether `preset` launches on another server than the one whose sessions are listed
+fn is_elsewhere(preset: &Preset, preset_sockets: &HashMap<String, Vec<Session>>) -> bool {
+    preset
+        .socket
+        .as_ref()
+        .is_some_and(|socket| preset_sockets.contains_key(socket))
+}
+
+/// The color a preset or session is drawn with, if it has a valid one and the theme has colors
+pub fn accent_color(color: Option<&str>, theme: &Theme) -> Option<Color> {
+    color
+        .filter(|_| theme.preset_colors)
+        .and_then(|color| color.parse().ok())
+}
+
+/// Two clicks on the same row closer together than this count as a double click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Maps a mouse position onto the index of the list item drawn there, if any.
+///
+/// `area` is where the list was last rendered and `offset` its scroll offset (from `ListState`).
+pub fn list_row_at(area: Rect, offset: usize, column: u16, row: u16) -> Option<usize> {
+    area.contains(Position::new(column, row))
+        .then(|| offset + (row - area.y) as usize)
+}
+
+/// Remembers the last clicked list row to recognize double clicks
+#[derive(Debug, Default)]
+pub struct ClickTracker {
+    last: Option<(usize, Instant)>,
+}
+
+impl ClickTracker {
+    /// Records a click on `index`, returning whether it completes a double click
+    pub fn click(&mut self, index: usize) -> bool {
+        let now = Instant::now();
+        let is_double = self
+            .last
+            .is_some_and(|(last, at)| last == index && now - at <= DOUBLE_CLICK_INTERVAL);
+        // A third click starts over rather than counting as another double click
+        self.last = if is_double { None } else { Some((index, now)) };
+        is_double
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing;
+
+    #[test]
+    fn best_matches() {
+        let names = ["api", "api-docs", "web", "Database", "db"];
+        for (query, expected) in [
+            // Exact, ahead of anything scoring higher
+            ("api", Some("api")),
+            ("db", Some("db")),
+            // Prefixes, the earliest listed winning ties
+            ("ap", Some("api")),
+            ("api-", Some("api-docs")),
+            ("we", Some("web")),
+            // Fuzzy, in any case
+            ("dbs", Some("Database")),
+            ("adcs", Some("api-docs")),
+            ("DATA", Some("Database")),
+            ("", Some("api")),
+            ("xyz", None),
+            ("bew", None),
+        ] {
+            assert_eq!(best_match(query, names), expected, "{query}");
+        }
+        assert_eq!(best_match("api", []), None);
+    }
+
+    #[test]
+    fn wrapped_to_width() {
+        let text = "Runs the api\n\n  and its database, with the seed data loaded";
+        // Words stay whole, the text's own line breaks are kept and blank lines dropped
+        assert_eq!(
+            wrap_to_width(text, 16, 5),
+            [
+                "Runs the api",
+                "and its",
+                "database, with",
+                "the seed data",
+                "loaded"
+            ]
+        );
+        // Cut after the last line that fits, which ends with an ellipsis
+        assert_eq!(
+            wrap_to_width(text, 16, 3),
+            ["Runs the api", "and its", "database, wit..."]
+        );
+        // Words too long for a line of their own are truncated
+        assert_eq!(wrap_to_width("supercalifragilistic", 8, 2), ["super..."]);
+        assert!(wrap_to_width("", 8, 2).is_empty());
+    }
+
+    /// A session named `name`, launched from `preset` if given
+    fn session(name: &str, preset: Option<&str>) -> Session {
+        Session {
+            preset: preset.map(String::from),
+            ..testing::session(name)
+        }
+    }
+
+    fn presets() -> PresetList {
+        PresetList::new(["api", "web", "docs"].map(|name| {
+            Preset::builder(name)
+                .color("red")
+                .tag("work")
+                .build()
+                .unwrap()
+        }))
+    }
+
+    fn running(presets: &PresetList) -> Vec<&str> {
+        presets
+            .iter()
+            .filter(|preset| preset.running)
+            .map(|preset| preset.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn sessions_launched_from_a_preset_mark_it_running() {
+        let mut presets = presets();
+        let mut sessions = [session("api", Some("api")), session("misc", None)];
+        mark_running_presets(&mut presets, &mut sessions, &HashMap::new());
+
+        assert_eq!(running(&presets), ["api"]);
+        assert_eq!(sessions[0].color.as_deref(), Some("red"));
+        assert_eq!(sessions[0].tags, ["work"]);
+        assert_eq!(sessions[1].color, None);
+    }
+
+    #[test]
+    fn renamed_sessions_no_longer_count() {
+        let mut presets = presets();
+        // Launched as `api`, then renamed, and tagged before that
+        let mut renamed = session("api-old", Some("api"));
+        renamed.color = Some("red".to_string());
+        renamed.tags = vec!["work".to_string()];
+        let mut sessions = [renamed];
+        mark_running_presets(&mut presets, &mut sessions, &HashMap::new());
+
+        assert!(running(&presets).is_empty());
+        assert_eq!(sessions[0].color, None);
+        assert!(sessions[0].tags.is_empty());
+    }
+
+    #[test]
+    fn namesakes_not_launched_from_the_preset_dont_count() {
+        let mut presets = presets();
+        let mut sessions = [session("web", None), session("docs", Some("api"))];
+        mark_running_presets(&mut presets, &mut sessions, &HashMap::new());
+
+        assert!(running(&presets).is_empty());
+        assert!(
+            sessions
+                .iter()
+                .all(|s| s.color.is_none() && s.tags.is_empty())
+        );
+    }
+
+    #[test]
+    fn presets_without_a_session_stop_running() {
+        let mut presets = presets();
+        let mut sessions = [session("api", Some("api"))];
+        mark_running_presets(&mut presets, &mut sessions, &HashMap::new());
+        assert_eq!(running(&presets), ["api"]);
+
+        mark_running_presets(&mut presets, &mut [], &HashMap::new());
+        assert!(running(&presets).is_empty());
+    }
 
-    // Immediately show notification
-    let _ = tx.send(AppEvent::ShowNotification(msg));
+    #[test]
+    fn presets_on_another_socket_are_looked_for_there() {
+        let mut presets = PresetList::new([
+            Preset::builder("jobs").socket("jobs").build().unwrap(),
+            Preset::builder("idle").socket("idle").build().unwrap(),
+        ]);
+        // The namesake on the listed server isn't the one the preset launched
+        let mut sessions = [session("jobs", Some("jobs"))];
+        let preset_sockets = HashMap::from([
+            ("jobs".to_string(), vec![session("jobs", Some("jobs"))]),
+            ("idle".to_string(), vec![]),
+        ]);
+        mark_running_presets(&mut presets, &mut sessions, &preset_sockets);
 
-    // Spawn a background task to clear it after 3 seconds
-    tokio::spawn(async move {
-        tokio::time::sleep(Duration::from_secs(2)).await;
-        let _ = tx.send(AppEvent::ClearNotification);
-    });
+        assert_eq!(running(&presets), ["jobs"]);
+        mark_running_presets(&mut presets, &mut [], &HashMap::new());
+        assert!(running(&presets).is_empty());
+    }
 }
@@ -0,0 +1,3 @@
+pub mod confirm;
+pub mod input;
+pub mod instructions;
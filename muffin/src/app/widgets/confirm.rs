@@ -0,0 +1,41 @@
+use crossterm::event::KeyEvent;
+use ratatui::prelude::{Buffer, Rect};
+
+use crate::app::{theme::Theme, widgets::input::Input};
+
+/// What has to be typed to go through with the action
+const CONFIRMATION: &str = "yes";
+
+/// Confirmation for actions too destructive to hang on a single keypress: nothing happens until
+/// "yes" is typed out in full
+pub struct Confirm<'a> {
+    input: Input<'a>,
+}
+
+impl Default for Confirm<'_> {
+    fn default() -> Self {
+        Self {
+            input: Input::default().placeholder(&format!("type {CONFIRMATION} to confirm")),
+        }
+    }
+}
+
+impl<'a> Confirm<'a> {
+    pub fn is_confirmed(&self) -> bool {
+        self.input.value().trim().eq_ignore_ascii_case(CONFIRMATION)
+    }
+
+    /// Edits what's typed according to `key`, returning whether it changed
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        self.input.handle_key(key)
+    }
+
+    pub fn paste(&mut self, text: &str) -> bool {
+        self.input.paste(text)
+    }
+
+    /// Draws what's typed so far
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        self.input.render(area, buf, theme, true);
+    }
+}
@@ -0,0 +1,160 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Style, Styled},
+    text::Line,
+    widgets::Widget,
+};
+use tui_textarea::{CursorMove, TextArea};
+
+use crate::app::theme::Theme;
+
+/// Longest value accepted, well past anything that still fits in a popup
+const MAX_LEN: usize = 256;
+
+/// Why a value isn't acceptable, if it isn't
+pub type Validator = fn(&str) -> Result<(), String>;
+
+/// A single line of text input for the popups.
+///
+/// Enter, tab and the arrows moving between lines are left to the popup, pasted newlines become
+/// spaces, and nothing past [`MAX_LEN`] characters is taken in (the text turns to the warning
+/// color once it's full). Besides tui-textarea's emacs-like bindings, `ctrl+u` clears up to the
+/// cursor and `ctrl+←`/`ctrl+→` jump words.
+#[derive(Default)]
+pub struct Input<'a> {
+    text_area: TextArea<'a>,
+    validator: Option<Validator>,
+}
+
+impl<'a> Input<'a> {
+    /// Starts out holding `value`, with the cursor at its end
+    pub fn new(value: &str) -> Self {
+        let mut input = Self::default();
+        input.set_value(value);
+        input
+    }
+
+    /// Checks the value as it's typed, see [`Input::error`]
+    pub fn validator(mut self, validator: Validator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    pub fn placeholder(mut self, placeholder: &str) -> Self {
+        self.text_area.set_placeholder_text(placeholder);
+        self
+    }
+
+    pub fn value(&self) -> &str {
+        // A text area always has at least one line, and this one never more
+        &self.text_area.lines()[0]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value().is_empty()
+    }
+
+    /// Replaces the value, moving the cursor to its end
+    pub fn set_value(&mut self, value: &str) {
+        let value = single_line(value).chars().take(MAX_LEN).collect::<String>();
+        let mut text_area = TextArea::from([value]);
+        text_area.set_placeholder_text(self.text_area.placeholder_text());
+        text_area.move_cursor(CursorMove::End);
+        self.text_area = text_area;
+    }
+
+    /// Why the current value isn't acceptable, shown under the input by [`Input::render_error`]
+    pub fn error(&self) -> Option<String> {
+        self.validator
+            .and_then(|validator| validator(self.value()).err())
+    }
+
+    fn is_full(&self) -> bool {
+        self.value().chars().count() >= MAX_LEN
+    }
+
+    /// Edits the value according to `key`, returning whether it changed
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            // What these would insert or move to doesn't exist on a single line
+            KeyCode::Enter | KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => false,
+            KeyCode::Char('m' | 'n' | 'p') if ctrl => false,
+            KeyCode::Char('u') if ctrl => self.text_area.delete_line_by_head(),
+            KeyCode::Left if ctrl => {
+                self.text_area.move_cursor(CursorMove::WordBack);
+                false
+            }
+            KeyCode::Right if ctrl => {
+                self.text_area.move_cursor(CursorMove::WordForward);
+                false
+            }
+            KeyCode::Char(_) if !ctrl && !alt && self.is_full() => false,
+            _ => self.text_area.input(key),
+        }
+    }
+
+    /// Inserts pasted `text` at the cursor, on one line and cut short at [`MAX_LEN`]
+    pub fn paste(&mut self, text: &str) -> bool {
+        let room = MAX_LEN.saturating_sub(self.value().chars().count());
+        let text = single_line(text).chars().take(room).collect::<String>();
+        self.text_area.insert_str(text)
+    }
+
+    /// Draws the value, or the placeholder while it's empty, with a cursor if `focused`
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer, theme: &Theme, focused: bool) {
+        let style = if self.is_full() {
+            theme.warn
+        } else {
+            Style::default()
+        };
+        self.text_area.set_style(style);
+        self.text_area.set_placeholder_style(theme.dim);
+        self.text_area.set_cursor_line_style(Style::default());
+        self.text_area.set_cursor_style(if focused {
+            theme.cursor
+        } else {
+            Style::default()
+        });
+        self.text_area.render(area, buf);
+    }
+
+    /// Draws why the value isn't acceptable, if it isn't
+    pub fn render_error(&self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        if let Some(error) = self.error() {
+            Line::from(error.set_style(theme.error))
+                .centered()
+                .render(area, buf);
+        }
+    }
+}
+
+/// `text` with line breaks as spaces (a trailing one dropped) and other control characters left
+/// out, as they'd only end up in tmux names
+fn single_line(text: &str) -> String {
+    text.trim_end_matches(['\r', '\n'])
+        .replace("\r\n", " ")
+        .chars()
+        .filter_map(|c| match c {
+            '\n' | '\r' | '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Session names, which tmux would otherwise quietly change or misread, see
+/// [`tmux::validate_session_name`]
+pub fn session_name(name: &str) -> Result<(), String> {
+    tmux::validate_session_name(name).map_err(|e| e.to_string())
+}
+
+/// Like [`session_name`], but left empty for tmux to pick one
+pub fn new_session_name(name: &str) -> Result<(), String> {
+    match name {
+        "" => Ok(()),
+        name => session_name(name),
+    }
+}
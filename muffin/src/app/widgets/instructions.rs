@@ -0,0 +1,279 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::Widget,
+};
+
+use crate::app::{theme::Theme, utils::make_instructions};
+
+/// Space between two pairs on a line
+const GAP: &str = "  ";
+
+/// The `key:description` pairs at the bottom of the menus and popups.
+///
+/// Each line gets as many whole pairs as fit, centered. A pair is never split across lines: when
+/// they take more lines than there are, the least important ones are left out, see
+/// [`Instructions::priority`].
+pub struct Instructions<'a> {
+    /// Keys along with their pair, in the order they're shown
+    pairs: Vec<(&'a str, Line<'a>)>,
+    /// Keys kept the longest, most important first
+    priority: Vec<&'a str>,
+    /// Laid over the whole footer, under the pairs' own styles
+    style: Style,
+}
+
+impl<'a> Instructions<'a> {
+    pub fn new(instructions: Vec<(&'a str, &'a str)>, theme: &Theme) -> Self {
+        let keys = instructions.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+        Self {
+            pairs: keys
+                .into_iter()
+                .zip(make_instructions(instructions, theme))
+                .collect(),
+            priority: vec![],
+            style: Style::default(),
+        }
+    }
+
+    /// Styles the footer's whole area, the gaps between pairs included, like
+    /// [`Paragraph::style`](ratatui::widgets::Paragraph::style)
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// The pairs of `keys` are the last to be left out, the first of them last of all. Pairs
+    /// that aren't listed go before, from the end.
+    pub fn priority(mut self, keys: &[&'a str]) -> Self {
+        self.priority = keys.to_vec();
+        self
+    }
+
+    /// The pairs packed into at most `height` lines of `width` cells
+    pub fn lines(&self, width: u16, height: u16) -> Vec<Line<'a>> {
+        let mut shown = (0..self.pairs.len()).collect::<Vec<_>>();
+        let mut left_out = self.drop_order().into_iter();
+        loop {
+            let lines = self.pack(&shown, width);
+            let fits = lines.len() <= height as usize
+                && lines.iter().all(|line| line.width() <= width as usize);
+            if fits || shown.len() <= 1 {
+                return lines.into_iter().take(height as usize).collect();
+            }
+            let Some(index) = left_out.next() else {
+                return lines;
+            };
+            shown.retain(|&i| i != index);
+        }
+    }
+
+    /// Indices of the pairs, least important first
+    fn drop_order(&self) -> Vec<usize> {
+        // 0 for unlisted pairs, which go first, then more for the more important ones
+        let rank = |key: &str| {
+            self.priority
+                .iter()
+                .position(|&k| k == key)
+                .map_or(0, |position| self.priority.len() - position)
+        };
+        let mut order = (0..self.pairs.len()).rev().collect::<Vec<_>>();
+        order.sort_by_key(|&i| rank(self.pairs[i].0));
+        order
+    }
+
+    /// Lays out the pairs at `shown` greedily, starting a new line whenever the next one doesn't
+    /// fit on the current line
+    fn pack(&self, shown: &[usize], width: u16) -> Vec<Line<'a>> {
+        let width = width as usize;
+        let mut lines: Vec<Line<'a>> = vec![];
+        for &i in shown {
+            let pair = &self.pairs[i].1;
+            match lines.last_mut() {
+                Some(line) if line.width() + GAP.len() + pair.width() <= width => {
+                    line.push_span(Span::raw(GAP));
+                    line.extend(pair.spans.iter().cloned());
+                }
+                _ => lines.push(pair.clone()),
+            }
+        }
+        lines
+    }
+}
+
+impl Widget for Instructions<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        buf.set_style(area, self.style);
+        for (line, row) in self
+            .lines(area.width, area.height)
+            .into_iter()
+            .zip(area.rows())
+        {
+            line.centered().render(row, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    /// Like the sessions menu's
+    const PAIRS: &[(&str, &str)] = &[
+        ("enter", "switch"),
+        ("q", "quit"),
+        ("j/↓", "next"),
+        ("k/↑", "prev"),
+        ("a", "create"),
+        ("r", "rename"),
+        ("w", "new window"),
+        ("/", "search"),
+        (":", "actions"),
+        ("tab", "view presets"),
+    ];
+    const PRIORITY: &[&str] = &["enter", "q", "tab", ":", "a"];
+
+    /// The footer drawn `width` cells wide over two lines, trimmed
+    fn render(width: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, 2)).unwrap();
+        terminal
+            .draw(|frame| {
+                let theme = Theme::from_config(None).unwrap();
+                frame.render_widget(
+                    Instructions::new(PAIRS.to_vec(), theme).priority(PRIORITY),
+                    frame.area(),
+                )
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    /// The pairs shown on `rows`, which are all whole
+    fn shown(rows: &[String]) -> Vec<&str> {
+        let pairs = PAIRS
+            .iter()
+            .map(|(key, description)| format!("{key}:{description}"))
+            .collect::<Vec<_>>();
+        let shown = rows
+            .iter()
+            .flat_map(|row| row.split(GAP).filter(|pair| !pair.is_empty()))
+            .collect::<Vec<_>>();
+        for pair in &shown {
+            assert!(
+                pairs.iter().any(|p| p == pair),
+                "`{pair}` split in {rows:#?}"
+            );
+        }
+        shown
+    }
+
+    #[test]
+    fn wide_footer_shows_every_pair() {
+        let rows = render(80);
+        assert_eq!(
+            rows,
+            [
+                "enter:switch  q:quit  j/↓:next  k/↑:prev  a:create  r:rename  w:new window",
+                "/:search  ::actions  tab:view presets",
+            ]
+        );
+        assert_eq!(shown(&rows).len(), PAIRS.len());
+    }
+
+    #[test]
+    fn narrower_footer_leaves_out_unlisted_pairs_from_the_end() {
+        let rows = render(50);
+        assert_eq!(
+            rows,
+            [
+                "enter:switch  q:quit  j/↓:next  k/↑:prev  a:create",
+                "r:rename  ::actions  tab:view presets",
+            ]
+        );
+        // `w` and `/` went first, as the last of those not listed
+        assert_eq!(
+            shown(&rows),
+            [
+                "enter:switch",
+                "q:quit",
+                "j/↓:next",
+                "k/↑:prev",
+                "a:create",
+                "r:rename",
+                "::actions",
+                "tab:view presets"
+            ]
+        );
+    }
+
+    #[test]
+    fn narrow_footer_keeps_the_most_important_pairs() {
+        let rows = render(30);
+        assert_eq!(
+            rows,
+            [
+                "enter:switch  q:quit  a:create",
+                "::actions  tab:view presets"
+            ]
+        );
+        // Nothing but the listed ones is left
+        let mut shown = shown(&rows)
+            .into_iter()
+            .map(|pair| pair.split_at(pair.rfind(':').unwrap()).0)
+            .collect::<Vec<_>>();
+        shown.sort();
+        let mut priority = PRIORITY.to_vec();
+        priority.sort();
+        assert_eq!(shown, priority);
+
+        // Shorter still, the listed ones go from the end of the list
+        assert_eq!(render(20), ["enter:switch  q:quit", "tab:view presets"]);
+    }
+
+    #[test]
+    fn pairs_are_kept_in_their_order() {
+        // Left out by priority, but shown where they were declared
+        let rows = render(50);
+        let shown = shown(&rows);
+        let declared = |pair: &&str| PAIRS.iter().position(|(key, _)| pair.starts_with(key));
+        let positions = shown.iter().map(declared).collect::<Vec<_>>();
+        assert!(positions.is_sorted(), "{shown:?}");
+    }
+
+    #[test]
+    fn the_footers_style_goes_under_the_keys() {
+        let theme = Theme::from_config(None).unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(30, 1)).unwrap();
+        terminal
+            .draw(|frame| {
+                frame.render_widget(
+                    Instructions::new(vec![("q", "quit"), ("tab", "presets")], theme)
+                        .style(theme.dim),
+                    frame.area(),
+                )
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let row = (0..30).map(|x| &buffer[(x, 0)]).collect::<Vec<_>>();
+        let text = row.iter().map(|cell| cell.symbol()).collect::<String>();
+        assert_eq!(text.trim(), "q:quit  tab:presets");
+        // Margins and gaps are dimmed along with the descriptions, and keys still stand out
+        let fg = |x: usize| row[x].fg;
+        let dim = theme.dim.fg;
+        assert_eq!(Some(fg(0)), dim);
+        assert_eq!(Some(fg(text.find("  tab").unwrap())), dim);
+        assert_eq!(Some(fg(text.find(":quit").unwrap())), dim);
+        assert_eq!(Some(fg(text.find('q').unwrap())), theme.key.fg);
+    }
+}
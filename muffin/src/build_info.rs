@@ -0,0 +1,9 @@
+include!(concat!(env!("OUT_DIR"), "/build_info.rs"));
+
+/// ex: `muffin 0.1.0 (1ab128e, built 2026-10-15)`
+pub fn version_line() -> String {
+    format!(
+        "muffin {} ({GIT_HASH}, built {BUILD_DATE})",
+        env!("CARGO_PKG_VERSION")
+    )
+}
@@ -0,0 +1,580 @@
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crossterm::{cursor, terminal};
+use parser::{Severity, Source};
+use tmux::{Server, TmuxError, Version};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::icons::Icons;
+use crate::app::preset_list::read_presets;
+
+/// Smaller terminals cut off muffin's larger popups
+const MIN_TERMINAL_SIZE: (u16, u16) = (60, 20);
+
+/// How a check went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    /// Works, but not as well as it could
+    Warn,
+    /// Keeps muffin from working
+    Fail,
+}
+
+impl Outcome {
+    pub fn label(self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Warn => "warn",
+            Outcome::Fail => "fail",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub outcome: Outcome,
+    /// What was found, and what to do about it when it isn't a pass
+    pub detail: String,
+}
+
+impl CheckResult {
+    pub fn pass(detail: impl Into<String>) -> Self {
+        Self {
+            outcome: Outcome::Pass,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn warn(detail: impl Into<String>) -> Self {
+        Self {
+            outcome: Outcome::Warn,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn fail(detail: impl Into<String>) -> Self {
+        Self {
+            outcome: Outcome::Fail,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// One thing `muffin doctor` looks at
+pub trait Check {
+    fn name(&self) -> &'static str;
+    fn run(&self) -> CheckResult;
+}
+
+/// tmux is installed, and new enough
+pub struct TmuxVersion<'a> {
+    pub server: &'a Server,
+}
+
+impl Check for TmuxVersion<'_> {
+    fn name(&self) -> &'static str {
+        "tmux"
+    }
+
+    fn run(&self) -> CheckResult {
+        match self.server.tmux_version() {
+            Ok(version) if version < Version::MINIMUM => CheckResult::fail(format!(
+                "tmux {version} is older than {}, the oldest muffin works with",
+                Version::MINIMUM
+            )),
+            Ok(version) => CheckResult::pass(format!("tmux {version}")),
+            Err(TmuxError::Spawn { .. }) => {
+                CheckResult::fail("tmux isn't installed, or isn't on $PATH")
+            }
+            // Development builds don't report a number
+            Err(TmuxError::Parse { .. }) => {
+                CheckResult::warn("Unrecognized tmux version, assuming a recent one")
+            }
+            Err(e) => CheckResult::fail(e.to_string()),
+        }
+    }
+}
+
+/// The tmux server answers, if it's running at all
+pub struct ServerReachable<'a> {
+    pub server: &'a Server,
+}
+
+impl Check for ServerReachable<'_> {
+    fn name(&self) -> &'static str {
+        "server"
+    }
+
+    fn run(&self) -> CheckResult {
+        match self.server.list_sessions_if_running() {
+            Ok(Some(sessions)) => CheckResult::pass(format!(
+                "{} is running with {} sessions",
+                self.server,
+                sessions.len()
+            )),
+            Ok(None) => CheckResult::pass(format!(
+                "{} isn't running, creating a session starts it",
+                self.server
+            )),
+            Err(e) => CheckResult::fail(e.to_string()),
+        }
+    }
+}
+
+/// The presets files exist and parse
+pub struct PresetsFiles {
+    pub sources: Vec<Source>,
+    /// Set when no path was given, in which case a missing file is replaced by an example one
+    pub default: bool,
+}
+
+impl Check for PresetsFiles {
+    fn name(&self) -> &'static str {
+        "presets"
+    }
+
+    fn run(&self) -> CheckResult {
+        if self.sources.is_empty() {
+            return CheckResult::fail("No home directory to look for presets in, set $HOME");
+        }
+        let missing = self
+            .sources
+            .iter()
+            .filter_map(Source::path)
+            .filter(|path| !path.exists())
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return match self.default {
+                true => CheckResult::warn(format!(
+                    "No presets file yet, muffin writes an example one to {}",
+                    missing.join(", ")
+                )),
+                false => CheckResult::fail(format!("{} doesn't exist", missing.join(", "))),
+            };
+        }
+
+        let (presets, diagnostics) = match read_presets(&self.sources) {
+            Ok(loaded) => loaded,
+            Err(e) => return CheckResult::fail(e),
+        };
+        let files = presets
+            .files()
+            .iter()
+            .map(|file| file.display().to_string())
+            .collect::<Vec<_>>();
+        let summary = format!("{} presets from {}", presets.len(), files.join(", "));
+        let warnings = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .count();
+        let notes = diagnostics.len() - warnings;
+        match (warnings, notes) {
+            (0, 0) => CheckResult::pass(summary),
+            (0, notes) => CheckResult::pass(format!(
+                "{summary}, {notes} notes (muffin check lists them)"
+            )),
+            (warnings, notes) => CheckResult::warn(format!(
+                "{summary}, {warnings} warnings and {notes} notes (muffin check lists them)"
+            )),
+        }
+    }
+}
+
+/// muffin can write to a directory it keeps files in, creating it if need be
+pub struct WritableDir {
+    pub name: &'static str,
+    /// Where it resolved to, if there's a home directory to put it in
+    pub dir: Option<PathBuf>,
+}
+
+impl Check for WritableDir {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn run(&self) -> CheckResult {
+        let Some(dir) = &self.dir else {
+            return CheckResult::fail("No home directory to put it in, set $HOME");
+        };
+        // A missing directory is created in the closest one that exists
+        let Some(existing) = dir.ancestors().find(|dir| dir.exists()) else {
+            return CheckResult::fail(format!("Nothing of {} exists", dir.display()));
+        };
+        if !existing.is_dir() {
+            return CheckResult::fail(format!(
+                "{} is in the way, it isn't a directory",
+                existing.display()
+            ));
+        }
+        if let Err(e) = probe_write(existing) {
+            return CheckResult::fail(format!("Can't write to {}: {e}", existing.display()));
+        }
+        if existing == dir {
+            CheckResult::pass(format!("{} is writable", dir.display()))
+        } else {
+            CheckResult::pass(format!("{} can be created", dir.display()))
+        }
+    }
+}
+
+/// Creates and removes a file in `dir`
+fn probe_write(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".muffin-doctor-{}", std::process::id()));
+    std::fs::write(&probe, "")?;
+    std::fs::remove_file(&probe)
+}
+
+/// Whether muffin runs inside tmux, which decides how it takes you to a session
+pub struct InsideTmux;
+
+impl Check for InsideTmux {
+    fn name(&self) -> &'static str {
+        "inside tmux"
+    }
+
+    fn run(&self) -> CheckResult {
+        match std::env::var("TMUX") {
+            Ok(tmux) if !tmux.is_empty() => {
+                CheckResult::pass("Yes, sessions are switched to in this client")
+            }
+            _ => CheckResult::pass("No, sessions are attached to in this terminal"),
+        }
+    }
+}
+
+/// The terminal fits muffin's popups
+pub struct TerminalSize;
+
+impl Check for TerminalSize {
+    fn name(&self) -> &'static str {
+        "terminal size"
+    }
+
+    fn run(&self) -> CheckResult {
+        let (min_width, min_height) = MIN_TERMINAL_SIZE;
+        match terminal::size() {
+            Ok((width, height)) if width < min_width || height < min_height => CheckResult::warn(
+                format!("{width}x{height}, popups are cut off under {min_width}x{min_height}"),
+            ),
+            Ok((width, height)) => CheckResult::pass(format!("{width}x{height}")),
+            Err(_) => CheckResult::warn("Not a terminal, skipped"),
+        }
+    }
+}
+
+/// The terminal says it shows 24-bit colors, which preset colors like `#1e90ff` need
+pub struct TrueColor;
+
+impl Check for TrueColor {
+    fn name(&self) -> &'static str {
+        "truecolor"
+    }
+
+    fn run(&self) -> CheckResult {
+        match std::env::var("COLORTERM").as_deref() {
+            Ok("truecolor" | "24bit") => CheckResult::pass("COLORTERM says so"),
+            _ => CheckResult::warn(
+                "COLORTERM isn't truecolor or 24bit, preset colors like #1e90ff may look off",
+            ),
+        }
+    }
+}
+
+/// The icons take as many cells in the terminal as muffin lays them out with, which goes wrong
+/// with fonts missing the Nerd Font glyphs
+pub struct GlyphWidths {
+    pub icons: &'static Icons,
+}
+
+impl Check for GlyphWidths {
+    fn name(&self) -> &'static str {
+        "icons"
+    }
+
+    fn run(&self) -> CheckResult {
+        if !std::io::stdout().is_terminal() || !std::io::stdin().is_terminal() {
+            return CheckResult::warn("Not a terminal, skipped");
+        }
+        let icons = self.icons;
+        let glyphs = [
+            icons.windows,
+            icons.highlight,
+            icons.running,
+            icons.attached,
+            icons.pinned,
+            icons.warning,
+            icons.bell,
+            icons.activity,
+            icons.dot,
+            icons.collapsed,
+            icons.expanded,
+            icons.rust,
+            icons.node,
+            icons.nix,
+            icons.git,
+        ];
+
+        let mut wrong = vec![];
+        for glyph in glyphs {
+            match measure(glyph) {
+                Ok(width) if width != glyph.width() => {
+                    wrong.push(format!("{} takes {width}", glyph.trim()))
+                }
+                Ok(_) => {}
+                Err(e) => return CheckResult::warn(format!("Couldn't measure the icons: {e}")),
+            }
+        }
+
+        if wrong.is_empty() {
+            CheckResult::pass(
+                "Icons take the expected width (if they show as boxes, set MUFFIN_ASCII=1)",
+            )
+        } else {
+            CheckResult::warn(format!(
+                "{} cells, set MUFFIN_ASCII=1 or use a Nerd Font",
+                wrong.join(", ")
+            ))
+        }
+    }
+}
+
+/// How many cells the terminal advances the cursor by for `glyph`, printed at the start of the
+/// line and erased right after
+fn measure(glyph: &str) -> std::io::Result<usize> {
+    let mut stdout = std::io::stdout();
+    terminal::enable_raw_mode()?;
+    let result = write!(stdout, "\r{glyph}")
+        .and_then(|_| stdout.flush())
+        .and_then(|_| cursor::position());
+    let _ = write!(
+        stdout,
+        "\r{}",
+        terminal::Clear(terminal::ClearType::CurrentLine)
+    );
+    let _ = stdout.flush();
+    terminal::disable_raw_mode()?;
+    result.map(|(column, _)| column as usize)
+}
+
+/// One line per check: `pass  name  detail`, with the names lined up
+pub fn format_plain(results: &[(&'static str, CheckResult)]) -> String {
+    let width = results
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    results
+        .iter()
+        .map(|(name, result)| {
+            format!(
+                "{}  {name:<width$}  {}\n",
+                result.outcome.label(),
+                result.detail
+            )
+        })
+        .collect()
+}
+
+/// The same information as [`format_plain`], as a single-line JSON array
+pub fn format_json(results: &[(&'static str, CheckResult)]) -> String {
+    let entries = results
+        .iter()
+        .map(|(name, result)| {
+            serde_json::json!({
+                "name": name,
+                "outcome": result.outcome.label(),
+                "detail": result.detail,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    format!("{}\n", serde_json::Value::Array(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A check that always comes out as `result`
+    struct Fake {
+        name: &'static str,
+        result: CheckResult,
+    }
+
+    impl Check for Fake {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn run(&self) -> CheckResult {
+            self.result.clone()
+        }
+    }
+
+    /// What `muffin doctor` would print from a passing, a warning and a failing check
+    fn results() -> Vec<(&'static str, CheckResult)> {
+        let checks: Vec<Box<dyn Check>> = vec![
+            Box::new(Fake {
+                name: "tmux",
+                result: CheckResult::pass("tmux 3.4"),
+            }),
+            Box::new(Fake {
+                name: "true color",
+                result: CheckResult::warn("COLORTERM isn't set"),
+            }),
+            Box::new(Fake {
+                name: "server",
+                result: CheckResult::fail("Not \"reachable\""),
+            }),
+        ];
+        checks
+            .iter()
+            .map(|check| (check.name(), check.run()))
+            .collect()
+    }
+
+    /// A directory of the test's own, removed first in case a previous run left it
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("muffin-doctor-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn outcome(check: &dyn Check) -> (&'static str, String) {
+        let result = check.run();
+        (result.outcome.label(), result.detail)
+    }
+
+    #[test]
+    fn presets_files() {
+        let dir = scratch("presets");
+        let file = |name: &str, contents: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            Source::File(path)
+        };
+        let check = |sources: Vec<Source>, default| outcome(&PresetsFiles { sources, default });
+        let good = file("good.kdl", br#"session name="api""#);
+        let missing = Source::File(dir.join("missing.kdl"));
+
+        assert_eq!(
+            check(vec![good.clone()], false),
+            ("pass", format!("1 presets from {good}"))
+        );
+        assert_eq!(
+            check(vec![missing.clone()], true),
+            (
+                "warn",
+                format!("No presets file yet, muffin writes an example one to {missing}")
+            )
+        );
+        assert_eq!(
+            check(vec![good.clone(), missing.clone()], false),
+            ("fail", format!("{missing} doesn't exist"))
+        );
+        assert_eq!(
+            check(vec![], true),
+            (
+                "fail",
+                "No home directory to look for presets in, set $HOME".to_string()
+            )
+        );
+
+        // Files that can't be read or don't parse
+        let unreadable = file("unreadable.kdl", &[0xff, 0xfe]);
+        let (label, detail) = check(vec![unreadable.clone()], false);
+        assert_eq!(label, "fail");
+        assert!(detail.contains(&unreadable.to_string()), "{detail}");
+        let broken = file("broken.kdl", b"session name=\"web\" {");
+        let (label, detail) = check(vec![broken.clone()], false);
+        assert_eq!(label, "fail");
+        assert!(detail.contains(&broken.to_string()), "{detail}");
+
+        // Loaded, with something to say about it
+        let warned = file("warned.kdl", br#"session name="web" no_such_property=1"#);
+        let (label, detail) = check(vec![warned.clone()], false);
+        assert_eq!(label, "warn");
+        assert!(
+            detail.starts_with(&format!("1 presets from {warned}, 1 warnings")),
+            "{detail}"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writable_dirs() {
+        let dir = scratch("writable");
+        let check = |dir: Option<PathBuf>| {
+            outcome(&WritableDir {
+                name: "state dir",
+                dir,
+            })
+        };
+
+        assert_eq!(
+            check(Some(dir.clone())),
+            ("pass", format!("{} is writable", dir.display()))
+        );
+        let missing = dir.join("a/b");
+        assert_eq!(
+            check(Some(missing.clone())),
+            ("pass", format!("{} can be created", missing.display()))
+        );
+        // Nothing was left behind by looking
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        // A file where a directory should be can't be written through, whoever runs the tests
+        let file = dir.join("file");
+        std::fs::write(&file, "").unwrap();
+        assert_eq!(
+            check(Some(file.join("muffin"))),
+            (
+                "fail",
+                format!("{} is in the way, it isn't a directory", file.display())
+            )
+        );
+        assert_eq!(
+            check(None),
+            (
+                "fail",
+                "No home directory to put it in, set $HOME".to_string()
+            )
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn labels() {
+        let labels = results()
+            .iter()
+            .map(|(_, result)| result.outcome.label())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["pass", "warn", "fail"]);
+    }
+
+    #[test]
+    fn plain_lines_line_up_the_names() {
+        assert_eq!(
+            format_plain(&results()),
+            "pass  tmux        tmux 3.4\n\
+             warn  true color  COLORTERM isn't set\n\
+             fail  server      Not \"reachable\"\n"
+        );
+        assert_eq!(format_plain(&[]), "");
+    }
+
+    #[test]
+    fn json_array() {
+        assert_eq!(
+            format_json(&results()),
+            "[{\"detail\":\"tmux 3.4\",\"name\":\"tmux\",\"outcome\":\"pass\"},\
+             {\"detail\":\"COLORTERM isn't set\",\"name\":\"true color\",\"outcome\":\"warn\"},\
+             {\"detail\":\"Not \\\"reachable\\\"\",\"name\":\"server\",\"outcome\":\"fail\"}]\n"
+        );
+        assert_eq!(format_json(&[]), "[]\n");
+    }
+}
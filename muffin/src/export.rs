@@ -0,0 +1,37 @@
+use std::path::{Path, PathBuf};
+
+use tmux::Preset;
+
+/// Where a preset is exported to when no path is given: `<name>.sh` in the working directory
+pub fn default_path(preset: &Preset) -> PathBuf {
+    let file_name = preset
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    PathBuf::from(format!("{file_name}.sh"))
+}
+
+/// Writes the shell script recreating `preset` to `path` and makes it executable.
+///
+/// Returns the absolute path written to, for reporting back to the user.
+pub fn write_script(preset: &Preset, path: &Path) -> Result<PathBuf, String> {
+    let script = tmux::export_script(preset)
+        .map_err(|e| format!("Could not export '{}': {e}", preset.name))?;
+    std::fs::write(path, script)
+        .map_err(|e| format!("Could not write to '{}': {e}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755));
+    }
+
+    Ok(std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()))
+}
@@ -0,0 +1,249 @@
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use tmux::Session;
+#[cfg(feature = "serde")]
+use tmux::{LayoutNode, Preset};
+
+use crate::app::preset_list::PresetList;
+
+/// A preset as `muffin list presets --json` reports it
+#[cfg(feature = "serde")]
+#[derive(Debug, Serialize)]
+pub struct PresetEntry<'a> {
+    pub name: &'a str,
+    /// The file it was read from, `null` for stdin and inline documents
+    pub source: Option<&'a Path>,
+    pub running: bool,
+    pub color: Option<&'a str>,
+    pub tags: &'a [String],
+    pub description: Option<&'a str>,
+    pub windows: Vec<WindowEntry<'a>>,
+}
+
+/// One of [`PresetEntry::windows`]
+#[cfg(feature = "serde")]
+#[derive(Debug, Serialize)]
+pub struct WindowEntry<'a> {
+    pub name: &'a str,
+    pub panes: usize,
+    /// Tagged by `type`, `pane` or `split`, see [`LayoutNode`]
+    pub layout: &'a LayoutNode,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> PresetEntry<'a> {
+    pub fn new(preset: &'a Preset) -> Self {
+        Self {
+            name: &preset.name,
+            source: preset.source.as_deref(),
+            running: preset.running,
+            color: preset.color.as_deref(),
+            tags: &preset.tags,
+            description: preset.description.as_deref(),
+            windows: preset
+                .windows
+                .iter()
+                .map(|window| WindowEntry {
+                    name: &window.name,
+                    panes: window.layout.pane_count(),
+                    layout: &window.layout,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Every session as a single-line JSON array, with the fields of [`Session`]
+#[cfg(feature = "serde")]
+pub fn sessions_json(sessions: &[Session]) -> String {
+    format!("{}\n", serde_json::to_string(sessions).unwrap_or_default())
+}
+
+/// Every preset, in display order, as a single-line JSON array of [`PresetEntry`]
+#[cfg(feature = "serde")]
+pub fn presets_json(presets: &PresetList) -> String {
+    let entries = presets.iter().map(PresetEntry::new).collect::<Vec<_>>();
+    format!("{}\n", serde_json::to_string(&entries).unwrap_or_default())
+}
+
+/// One line per session: `id windows attached current|- name`, columns padded to line up and the
+/// name last, as it's the only one that can have spaces
+pub fn sessions_plain(sessions: &[Session]) -> String {
+    let rows = sessions
+        .iter()
+        .map(|session| {
+            let current = if session.is_current { "current" } else { "-" };
+            [
+                session.id.clone(),
+                session.windows.to_string(),
+                session.attached_count.to_string(),
+                current.to_string(),
+                session.name.clone(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    align(&rows)
+}
+
+/// One line per preset: `running|stopped windows panes name`, like [`sessions_plain`]
+pub fn presets_plain(presets: &PresetList) -> String {
+    let rows = presets
+        .iter()
+        .map(|preset| {
+            let running = if preset.running { "running" } else { "stopped" };
+            let panes = preset
+                .windows
+                .iter()
+                .map(|window| window.layout.pane_count())
+                .sum::<usize>();
+            [
+                running.to_string(),
+                preset.windows.len().to_string(),
+                panes.to_string(),
+                preset.name.clone(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    align(&rows)
+}
+
+/// Lines of `rows`' columns separated by two spaces, every column but the last padded to its
+/// widest value
+fn align<const N: usize>(rows: &[[String; N]]) -> String {
+    let mut widths = [0; N];
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    let mut output = String::new();
+    for row in rows {
+        for (i, value) in row.iter().enumerate() {
+            if i + 1 == N {
+                output.push_str(value);
+            } else {
+                output.push_str(&format!("{value:<width$}  ", width = widths[i]));
+            }
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::app::testing::session;
+    use tmux::{Alert, Layout, WaitFor, Window};
+
+    #[test]
+    fn session_fields() {
+        let sessions = [
+            Session {
+                created: Some(1700000000),
+                activity: Some(1700000600),
+                attached_count: 2,
+                is_current: true,
+                alert: Some(Alert::Bell),
+                path: Some("/home/me/api".to_string()),
+                preset: Some("api".to_string()),
+                auto_name: Some("nvim@api".to_string()),
+                note: Some("deploy on friday".to_string()),
+                command: Some("nvim".to_string()),
+                color: Some("blue".to_string()),
+                tags: vec!["work".to_string()],
+                windows: 3,
+                ..session("api")
+            },
+            session("scratch"),
+        ];
+        assert_eq!(
+            sessions_json(&sessions),
+            concat!(
+                r#"[{"id":"$api","name":"api","windows":3,"created":1700000000,"#,
+                r#""activity":1700000600,"attached_count":2,"is_current":true,"alert":"bell","#,
+                r#""path":"/home/me/api","preset":"api","auto_name":"nvim@api","#,
+                r#""note":"deploy on friday","command":"nvim","color":"blue","tags":["work"]},"#,
+                r#"{"id":"$scratch","name":"scratch","windows":1,"created":null,"#,
+                r#""activity":null,"attached_count":0,"is_current":false,"alert":null,"#,
+                r#""path":null,"preset":null,"auto_name":null,"note":null,"command":null,"#,
+                r#""color":null,"tags":[]}]"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn preset_fields() {
+        let mut api = Preset::builder("api")
+            .cwd("~/api")
+            .color("blue")
+            .tag("work")
+            .description("The backend")
+            .window(
+                Window::builder("dev").layout(Layout::hsplit([
+                    Layout::pane().cmd("nvim").pct(60),
+                    Layout::vsplit([
+                        Layout::pane()
+                            .cmd("cargo run")
+                            .delay(Duration::from_millis(500))
+                            .remain_on_exit(true),
+                        Layout::pane()
+                            .cells(10)
+                            .wait_for(WaitFor::Port(8080), Duration::from_secs(5)),
+                    ])
+                    .even(true),
+                ])),
+            )
+            .build()
+            .unwrap();
+        api.source = Some(PathBuf::from("/home/me/.config/muffin/presets.kdl"));
+        api.running = true;
+        let notes = Preset::builder("notes").build().unwrap();
+
+        let pane = |cwd, command, size, startup, remain_on_exit| {
+            format!(
+                r#"{{"type":"pane","cwd":"{cwd}","command":{command},"size":{size},"startup":{startup},"remain_on_exit":{remain_on_exit}}}"#
+            )
+        };
+        let immediate = r#"{"delay_ms":0,"wait_for":null,"timeout_ms":10000}"#;
+        let layout = format!(
+            r#"{{"type":"split","direction":"horizontal","children":[{},{{"type":"split","direction":"vertical","children":[{},{}],"size":"auto","even":true}}],"size":{{"percent":100}},"even":null}}"#,
+            pane("~/api", r#""nvim""#, r#"{"percent":60}"#, immediate, false),
+            pane(
+                "~/api",
+                r#""cargo run""#,
+                r#""auto""#,
+                r#"{"delay_ms":500,"wait_for":null,"timeout_ms":10000}"#,
+                true
+            ),
+            pane(
+                "~/api",
+                "null",
+                r#"{"cells":10}"#,
+                r#"{"delay_ms":0,"wait_for":{"port":8080},"timeout_ms":5000}"#,
+                false
+            ),
+        );
+        let expected = format!(
+            concat!(
+                r#"[{{"name":"api","source":"/home/me/.config/muffin/presets.kdl","running":true,"#,
+                r#""color":"blue","tags":["work"],"description":"The backend","#,
+                r#""windows":[{{"name":"dev","panes":3,"layout":{}}}]}},"#,
+                r#"{{"name":"notes","source":null,"running":false,"color":null,"tags":[],"#,
+                r#""description":null,"windows":[{{"name":"main","panes":1,"layout":{}}}]}}]"#,
+                "\n"
+            ),
+            layout,
+            pane("~", "null", r#"{"percent":100}"#, immediate, false),
+        );
+        assert_eq!(presets_json(&PresetList::new([api, notes])), expected);
+    }
+}
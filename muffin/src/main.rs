@@ -1,41 +1,152 @@
-use app::driver::App;
-use tmux::Preset;
+use std::collections::BTreeMap;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use app::driver::{App, Panel};
+use app::icons::Icons;
+use app::keymap::Keymap;
+use app::mru::Mru;
+use app::preflight::{cramped_windows, missing_commands};
+use app::preset_list::{PresetList, read_presets};
+use app::startup::send_pending;
+use app::theme::Theme;
+use app::trash::is_trash;
+use app::utils::{best_match, list_preset_sockets, mark_running_presets};
+use parser::{Config, Diagnostic, ImportFormat, Severity, Source, closest_match};
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use tmux::{Preset, Relayout, Server, Version, exact_target};
 mod app;
+mod build_info;
+mod doctor;
+mod export;
+mod list;
+mod paths;
+mod starter;
+mod status;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> () {
-    let mut args = std::env::args();
+    let mut args = std::env::args().peekable();
     let arg0 = args.next().unwrap();
+
+    if args.peek().is_some_and(|arg| arg == "status") {
+        args.next();
+        run_status(&arg0, args).await;
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "list") {
+        args.next();
+        run_list(&arg0, args);
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "relayout") {
+        args.next();
+        run_relayout(&arg0, args);
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "pick") {
+        args.next();
+        run_pick(&arg0, args).await;
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "popup") {
+        args.next();
+        run_popup(args);
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "import") {
+        args.next();
+        run_import(&arg0, args);
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "check") {
+        args.next();
+        run_check(&arg0, args);
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "doctor") {
+        args.next();
+        run_doctor(&arg0, args);
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "export") {
+        args.next();
+        run_export(&arg0, args);
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "launch") {
+        args.next();
+        run_launch(&arg0, args).await;
+        return;
+    }
+
     let mut list_presets = false;
     let mut start_preset = None;
-    let mut custom_preset = None;
+    let mut custom_presets = vec![];
+    let mut custom_config = None;
     let mut exit_on_switch = false;
-    let dot_config_muffin = shellexpand::full("~/.config/muffin").unwrap().to_string();
+    let mut force = false;
+    let mut socket = None;
+    let mut remote = None;
+    let mut session = None;
+    let mut create = false;
+    let mut switch = false;
+    // Popups get tmux's environment but, unlike panes, no pane of their own
+    let mut popup = std::env::var_os("TMUX").is_some() && std::env::var_os("TMUX_PANE").is_none();
 
     while let Some(arg) = args.next() {
         match arg.as_str() {
             "--presets" | "-p" => {
-                custom_preset = Some(args.next().unwrap_or_else(|| {
-                    eprintln!("Error: {arg} expects a path");
-                    std::process::exit(1);
-                }));
+                custom_presets.push(expect_value(&arg, &mut args, "a path"));
+            }
+            "--session" | "-S" => {
+                session = Some(expect_value(&arg, &mut args, "a session name"));
+            }
+            "--create" => create = true,
+            "--popup" => popup = true,
+            "--switch" => switch = true,
+            "--config" | "-c" => {
+                custom_config = Some(expect_value(&arg, &mut args, "a path"));
             }
             "--help" | "-h" => {
                 print_help(&arg0);
                 std::process::exit(1);
             }
+            "--version" | "-V" => {
+                println!("{}", build_info::version_line());
+                return;
+            }
             "--list-presets" | "-l" => {
                 list_presets = true;
             }
             "--start-preset" | "-s" => {
-                start_preset = Some(args.next().unwrap_or_else(|| {
-                    eprintln!("Error: {arg} expects a preset name");
-                    std::process::exit(1);
-                }));
+                start_preset = Some(expect_value(&arg, &mut args, "a preset name"));
             }
-            "--exit-on-switch" | "-e" => {
+            "--exit-on-switch" | "-e" | "--once" => {
                 exit_on_switch = true;
             }
+            "--force" => force = true,
+            "--socket" | "-L" => {
+                socket = Some(expect_value(&arg, &mut args, "a socket name"));
+            }
+            "--remote" => {
+                remote = Some(expect_value(&arg, &mut args, "a host"));
+            }
+            "--log" => {
+                let path = expect_value(&arg, &mut args, "a path");
+                set_log_file(&path);
+            }
             x => {
                 eprintln!("Unknown flag or value '{x}'. Run '{arg0} --help' for usage.");
                 std::process::exit(1);
@@ -43,112 +154,1360 @@ async fn main() -> () {
         }
     }
 
-    let presets_str = match custom_preset {
-        Some(s) => {
-            let presets_path = shellexpand::full(&s)
-                .expect("Failed to expand environment variables in path")
-                .to_string();
-            std::fs::read(&presets_path)
-                .ok()
-                .and_then(|x| String::from_utf8(x).ok())
-                .unwrap_or_else(|| {
-                    eprintln!("Could not read from'{presets_path}'");
-                    std::process::exit(1);
-                })
-        }
-        None => {
-            if std::fs::exists(format!("{dot_config_muffin}/presets.kdl")).unwrap() {
-                std::fs::read(format!("{dot_config_muffin}/presets.kdl"))
-                    .ok()
-                    .and_then(|x| String::from_utf8(x).ok())
-                    .unwrap_or_else(|| {
-                        eprintln!(
-                            "Could not open path '{dot_config_muffin}/presets.kdl'. Does it exist?"
-                        );
-                        std::process::exit(1);
-                    })
-            } else {
-                std::fs::create_dir_all(format!("{dot_config_muffin}")).unwrap();
-                std::fs::write(
-                    format!("{dot_config_muffin}/presets.kdl"),
-                    EXAMPLE_PRESET_CONTENT,
-                )
-                .unwrap();
-                EXAMPLE_PRESET_CONTENT.into()
-            }
-        }
-    };
-
-    let presets = parser::parse_config(&presets_str).unwrap_or_else(|_| {
-        eprintln!("Failed to parse configuration file.");
+    let preset_sources = preset_sources(&custom_presets, &[]);
+    let config_path = resolve_config_path(custom_config.as_deref());
+    let config = load_config(config_path.as_deref());
+    let server = with_config_options(make_server(socket.as_deref(), remote.as_deref()), &config);
+    let theme = Theme::from_config(config.theme.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    let keymap = Keymap::from_config(&config).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
         std::process::exit(1);
     });
 
     if list_presets {
+        let (presets, diagnostics) = load_presets(&preset_sources);
+        print_diagnostics(&diagnostics);
         for Preset {
             name, cwd, windows, ..
-        } in presets.values()
+        } in presets.iter()
         {
             println!("Session: {name}, {} windows, cwd: {cwd}", windows.len());
         }
         return;
     }
 
+    if session.is_none() && (create || switch) {
+        eprintln!("Error: --create and --switch only work along with --session");
+        std::process::exit(1);
+    }
+
+    check_tmux_version(&server);
+
+    if let Some(session) = &session {
+        ensure_session(&server, session, create);
+        if switch {
+            // Attaching blocks until detached, so remember the switch up front
+            if let Err(e) = Mru::load(&server).touch_name(session) {
+                print_warnings(&[e]);
+            }
+            server.enter_session(session).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                std::process::exit(1);
+            });
+            return;
+        }
+    }
+
     if let Some(preset_name) = start_preset {
-        let preset_to_start = presets.get(&preset_name).unwrap_or_else(|| {
+        let (presets, diagnostics) = load_presets(&preset_sources);
+        print_diagnostics(&diagnostics);
+        let preset_to_start = presets.get_by_name(&preset_name).unwrap_or_else(|| {
             eprintln!("Preset does not exist!");
             std::process::exit(1);
         });
-        tmux::spawn_preset(preset_to_start).unwrap_or_else(|e| {
-            eprintln!("{e}");
+        launch_preset(
+            server,
+            &resolve_vars(
+                preset_to_start,
+                &BTreeMap::new(),
+                &start_preset_hint(preset_to_start),
+            ),
+            force,
+        )
+        .await;
+        return;
+    }
+
+    // Sessions and presets are loaded once the UI is up, so a slow tmux or filesystem doesn't
+    // leave a blank terminal behind
+    let mut app = App::new(
+        preset_sources,
+        exit_on_switch || config.exit_on_switch,
+        popup,
+        config,
+        theme,
+        server,
+        session,
+    )
+    .with_config_path(config_path)
+    .with_keymap(keymap);
+
+    let mut terminal = init_terminal(std::io::stdout);
+    enable_mouse_capture(std::io::stdout);
+    let app_result = app.run(&mut terminal).await;
+    let trash_errors = app.empty_trash();
+
+    disable_mouse_capture(std::io::stdout);
+    restore_terminal(std::io::stdout);
+    app_result.unwrap();
+    print_warnings(&trash_errors);
+
+    // Attaching blocks until detached, by which time the pane commands have long been sent
+    if let Some((server, session)) = app.take_attach() {
+        attach_elsewhere(server, session, async move { app.finish_startups().await }).await;
+        return;
+    }
+
+    // Exiting right after launching a preset mustn't drop the pane commands it's still holding
+    print_warnings(&app.finish_startups().await);
+}
+
+/// Launches `preset` and switches to it, or attaches to it if it's on another server, exiting
+/// the process on errors. Unless `force`d, refuses presets whose pane commands aren't on PATH.
+async fn launch_preset(server: Server, preset_to_start: &Preset, force: bool) {
+    // Commands are looked up here, which says nothing about another machine
+    if !force && server.for_preset(preset_to_start).is_local() {
+        let missing = missing_commands(preset_to_start);
+        if !missing.is_empty() {
+            eprintln!(
+                "Error: {} command{} not found: {} (pass --force to launch anyway)",
+                missing.len(),
+                if missing.len() == 1 { "" } else { "s" },
+                missing.join(", ")
+            );
+            std::process::exit(1);
+        }
+    }
+    print_warnings(&cramped_windows(
+        preset_to_start,
+        &server.for_preset(preset_to_start),
+    ));
+    let spawn = server.spawn_preset(preset_to_start).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    print_warnings(&spawn.warnings);
+
+    // Clients can't be switched over to another server, so a session on one is attached to
+    let preset_server = server.for_preset(preset_to_start);
+    if preset_server != server {
+        let pending = send_pending(preset_server.clone(), spawn.pending);
+        attach_elsewhere(preset_server, preset_to_start.name.clone(), pending).await;
+        return;
+    }
+    let switched = server.switch_session(&preset_to_start.name);
+    if switched.is_ok()
+        && let Err(e) = Mru::load(&server).touch_name(&preset_to_start.name)
+    {
+        print_warnings(&[e]);
+    }
+    // The session exists either way, so finish setting it up before reporting
+    print_warnings(&send_pending(server, spawn.pending).await);
+    switched.unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+}
+
+/// Attaches to `session` on a server muffin doesn't run in while `startups` (pane commands still
+/// to send) carries on in the background, exiting the process if attaching fails
+async fn attach_elsewhere(
+    server: Server,
+    session: String,
+    startups: impl Future<Output = Vec<String>> + Send + 'static,
+) {
+    let startups = tokio::spawn(startups);
+    let attached = tokio::task::spawn_blocking(move || server.enter_session(&session)).await;
+    print_warnings(&startups.await.unwrap_or_default());
+    if let Ok(Err(e)) = attached {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+/// Where presets are read from: each path may be a file or a directory of `*.kdl` files, or `-`
+/// for stdin, followed by the documents given with `--inline`.
+///
+/// Without any, falls back to `~/.config/muffin/presets.kdl`, or to no presets at all until
+/// that's created (the presets menu offers to write a starter one). Paths that were given have to
+/// exist.
+fn preset_sources(custom_presets: &[String], inline: &[String]) -> Vec<Source> {
+    if custom_presets.is_empty() && inline.is_empty() {
+        let default_path = paths::default_presets_path().unwrap_or_else(|| {
+            eprintln!("Error: no home directory to keep the presets in, pass --presets");
             std::process::exit(1);
         });
-        tmux::switch_session(&preset_to_start.name).unwrap_or_else(|e| {
+        return match default_path.exists() {
+            true => vec![Source::File(default_path)],
+            false => vec![],
+        };
+    }
+
+    // Stdin can only be read once, however many times it's asked for
+    let mut stdin = None;
+    let mut sources = vec![];
+    for s in custom_presets {
+        sources.push(match s.as_str() {
+            "-" => Source::Stdin(stdin.get_or_insert_with(read_stdin).clone()),
+            s => Source::File(presets_path(s).unwrap_or_else(|e| {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            })),
+        });
+    }
+    sources.extend(inline.iter().cloned().map(Source::Inline));
+    sources
+}
+
+/// The file or directory given with `--presets`, which has to exist
+fn presets_path(given: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(
+        shellexpand::full(given)
+            .expect("Failed to expand environment variables in path")
+            .to_string(),
+    );
+    match path.exists() {
+        true => Ok(path),
+        false => Err(format!(
+            "there's no presets file at '{}' (given with --presets)",
+            path.display()
+        )),
+    }
+}
+
+/// All of stdin, exiting the process if it's a terminal (nothing was piped in) or can't be read
+fn read_stdin() -> String {
+    if std::io::stdin().is_terminal() {
+        eprintln!("Error: presets were to be read from stdin, but nothing was piped in");
+        std::process::exit(1);
+    }
+    std::io::read_to_string(std::io::stdin()).unwrap_or_else(|e| {
+        eprintln!("Could not read from stdin: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Reads and parses every presets file, exiting the process on failure
+fn load_presets(preset_sources: &[Source]) -> (PresetList, Vec<Diagnostic>) {
+    read_presets(preset_sources).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    })
+}
+
+/// Fills in `preset`'s variables from `--var` flags, exiting the process if one is left without a
+/// value. `hint` says how to give it one from wherever muffin was started.
+fn resolve_vars(preset: &Preset, vars: &BTreeMap<String, String>, hint: &str) -> Preset {
+    fill_vars(preset, vars, hint).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    })
+}
+
+fn fill_vars(
+    preset: &Preset,
+    vars: &BTreeMap<String, String>,
+    hint: &str,
+) -> Result<Preset, String> {
+    parser::apply_vars(preset, vars).map_err(|e| format!("{e}, {hint}"))
+}
+
+/// How to give variables a value with `-s`, which has no flag for them
+fn start_preset_hint(preset: &Preset) -> String {
+    format!(
+        "give it a default in `vars`, or a value with `muffin launch {} --var NAME=VALUE`",
+        preset.name
+    )
+}
+
+/// Exits the process if `server`'s tmux is too old for muffin
+fn check_tmux_version(server: &Server) {
+    // Anything older misses flags muffin relies on. Unknown versions get the benefit of the doubt.
+    if let Ok(version) = server.tmux_version()
+        && version < Version::MINIMUM
+    {
+        eprintln!(
+            "Error: muffin needs tmux {} or newer, but found tmux {version}",
+            Version::MINIMUM
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Makes sure `session` exists, creating it if `create` is set and exiting the process otherwise
+fn ensure_session(server: &Server, session: &str, create: bool) {
+    // No tmux server just means no sessions yet
+    let sessions = server.list_sessions().unwrap_or_default();
+    if sessions.iter().any(|s| s.name == session) {
+        return;
+    }
+    if create {
+        server.create_session(session, None).unwrap_or_else(|e| {
             eprintln!("{e}");
             std::process::exit(1);
         });
         return;
     }
 
-    let sessions = tmux::list_sessions().unwrap_or_else(|e| {
+    eprint!("Error: no session named '{session}'");
+    match closest_match(session, sessions.iter().map(|s| s.name.as_str())) {
+        Some(name) => eprintln!(", did you mean '{name}'?"),
+        None => eprintln!(" (pass --create to create it)"),
+    }
+    std::process::exit(1);
+}
+
+/// The config file to read: the explicit path, or else `~/.config/muffin/config.kdl` if it
+/// exists. It's entirely optional, so without either there's none.
+fn resolve_config_path(custom_config: Option<&str>) -> Option<PathBuf> {
+    match custom_config {
+        Some(s) => Some(PathBuf::from(
+            shellexpand::full(s)
+                .expect("Failed to expand environment variables in path")
+                .to_string(),
+        )),
+        None => paths::config_path().filter(|default_path| default_path.exists()),
+    }
+}
+
+/// Reads and parses muffin's config file, exiting the process on failure and warning about the
+/// settings left out. Without one, the settings are the defaults.
+fn load_config(config_path: Option<&Path>) -> Config {
+    let Some(config_path) = config_path else {
+        return Config::default();
+    };
+
+    let config_str = std::fs::read(config_path)
+        .ok()
+        .and_then(|x| String::from_utf8(x).ok())
+        .unwrap_or_else(|| {
+            eprintln!("Could not read from '{}'", config_path.display());
+            std::process::exit(1);
+        });
+
+    let (config, diagnostics) = parser::parse_muffin_config(&config_str).unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to parse config file '{}': {e}",
+            config_path.display()
+        );
+        std::process::exit(1);
+    });
+    let diagnostics = diagnostics
+        .into_iter()
+        .map(|diagnostic| Diagnostic {
+            file: Some(config_path.display().to_string()),
+            ..diagnostic
+        })
+        .collect::<Vec<_>>();
+    print_diagnostics(&diagnostics);
+    config
+}
+
+/// Switches the terminal to raw mode and the alternate screen, drawing through `out`, and makes
+/// sure a panic switches it back. `muffin pick` draws on stderr, keeping stdout for its result.
+fn init_terminal<W: Write + 'static>(out: fn() -> W) -> Terminal<CrosstermBackend<W>> {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal(out);
+        hook(info);
+    }));
+    let terminal = crossterm::terminal::enable_raw_mode()
+        .and_then(|_| crossterm::execute!(out(), crossterm::terminal::EnterAlternateScreen))
+        .and_then(|_| Terminal::new(CrosstermBackend::new(out())));
+    terminal.unwrap_or_else(|e| {
+        restore_terminal(out);
+        eprintln!("Could not set up the terminal: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Leaves raw mode and the alternate screen
+fn restore_terminal<W: Write>(out: fn() -> W) {
+    let _ = crossterm::terminal::disable_raw_mode();
+    let _ = crossterm::execute!(
+        out(),
+        crossterm::terminal::LeaveAlternateScreen,
+        crossterm::cursor::Show
+    );
+}
+
+/// Starts reporting mouse events and pastes, making sure a panic turns them back off along with
+/// the rest of the terminal state [`init_terminal`] already restores
+fn enable_mouse_capture<W: Write + 'static>(out: fn() -> W) {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        disable_mouse_capture(out);
+        hook(info);
+    }));
+    let _ = crossterm::execute!(
+        out(),
+        crossterm::event::EnableMouseCapture,
+        crossterm::event::EnableBracketedPaste
+    );
+}
+
+/// Hands mouse clicks and pastes back to the terminal
+fn disable_mouse_capture<W: Write>(out: fn() -> W) {
+    let _ = crossterm::execute!(
+        out(),
+        crossterm::event::DisableMouseCapture,
+        crossterm::event::DisableBracketedPaste
+    );
+}
+
+fn make_server(socket: Option<&str>, remote: Option<&str>) -> Server {
+    let server = match remote {
+        Some(host) => Server::ssh(host),
+        None => Server::local(),
+    };
+    match socket {
+        Some(name) => server.with_socket(name),
+        None => server,
+    }
+}
+
+/// Gives `server` the timeout set in the config file, if any, and whether it evens out splits
+fn with_config_options(server: Server, config: &Config) -> Server {
+    let server = server.with_even_splits(config.even_splits);
+    match config.tmux_timeout_ms {
+        Some(millis) => server.with_timeout(Duration::from_millis(millis)),
+        None => server,
+    }
+}
+
+/// Sends the tmux invocation log to `path`, exiting the process on failure
+fn set_log_file(path: &str) {
+    let path = shellexpand::full(path)
+        .expect("Failed to expand environment variables in path")
+        .to_string();
+    tmux::set_log_file(&path).unwrap_or_else(|e| {
         eprintln!("{e}");
         std::process::exit(1);
     });
-    let mut app = App::new(
-        sessions,
-        presets,
-        dot_config_muffin.to_string(),
-        exit_on_switch,
+}
+
+/// The value following flag `arg`, exiting the process if there's none
+fn expect_value(arg: &str, args: &mut impl Iterator<Item = String>, what: &str) -> String {
+    args.next().unwrap_or_else(|| {
+        eprintln!("Error: {arg} expects {what}");
+        std::process::exit(1);
+    })
+}
+
+fn print_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("Warning: {warning}");
+    }
+}
+
+fn print_diagnostics(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        match diagnostic.severity {
+            Severity::Warning => eprintln!("Warning: {diagnostic}"),
+            Severity::Note => eprintln!("Note: {diagnostic}"),
+        }
+    }
+}
+
+/// `muffin status`: report which presets are running without starting the TUI
+async fn run_status(arg0: &str, mut args: impl Iterator<Item = String>) {
+    let mut custom_presets = vec![];
+    let mut json = false;
+    let mut watch = false;
+    let mut socket = None;
+    let mut remote = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--presets" | "-p" => {
+                custom_presets.push(expect_value(&arg, &mut args, "a path"));
+            }
+            "--json" => json = true,
+            "--watch" => watch = true,
+            "--socket" | "-L" => {
+                socket = Some(expect_value(&arg, &mut args, "a socket name"));
+            }
+            "--remote" => {
+                remote = Some(expect_value(&arg, &mut args, "a host"));
+            }
+            "--log" => {
+                let path = expect_value(&arg, &mut args, "a path");
+                set_log_file(&path);
+            }
+            x => {
+                eprintln!("Unknown flag or value '{x}'. Run '{arg0} --help' for usage.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let server = make_server(socket.as_deref(), remote.as_deref());
+    let preset_sources = preset_sources(&custom_presets, &[]);
+    let mut last_output = None;
+    let mut last_error = None;
+    let mut interval = tokio::time::interval(Duration::from_secs(2));
+
+    loop {
+        interval.tick().await;
+
+        let mut presets = match read_presets(&preset_sources) {
+            Ok((presets, _)) => presets,
+            // A bad save of the presets file doesn't end the watch a bar depends on, the last
+            // snapshot stands until it's fixed
+            Err(e) if watch => {
+                if last_error.as_ref() != Some(&e) {
+                    eprintln!("{e}");
+                    last_error = Some(e);
+                }
+                continue;
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        };
+        last_error = None;
+        // No tmux server simply means nothing is running
+        let mut sessions = server.list_sessions().unwrap_or_default();
+        let preset_sockets = list_preset_sockets(&server, &presets);
+        mark_running_presets(&mut presets, &mut sessions, &preset_sockets);
+
+        let statuses = status::snapshot(&presets);
+        let output = if json {
+            status::format_json(&statuses)
+        } else {
+            status::format_plain(&statuses)
+        };
+
+        // Only emit a new snapshot when something actually changed
+        if last_output.as_ref() != Some(&output) {
+            print!("{output}");
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+            last_output = Some(output);
+        }
+
+        if !watch {
+            break;
+        }
+    }
+}
+
+/// `muffin list sessions|presets`: print every session or preset, for scripts to build on
+fn run_list(arg0: &str, mut args: impl Iterator<Item = String>) {
+    let mut custom_presets = vec![];
+    let mut json = false;
+    let mut socket = None;
+    let mut remote = None;
+    let mut what = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--presets" | "-p" => {
+                custom_presets.push(expect_value(&arg, &mut args, "a path"));
+            }
+            "--json" if cfg!(feature = "serde") => json = true,
+            "--json" => {
+                eprintln!(
+                    "Error: this muffin was built without the `serde` feature `--json` needs"
+                );
+                std::process::exit(1);
+            }
+            "--socket" | "-L" => {
+                socket = Some(expect_value(&arg, &mut args, "a socket name"));
+            }
+            "--remote" => {
+                remote = Some(expect_value(&arg, &mut args, "a host"));
+            }
+            "sessions" | "presets" if what.is_none() => what = Some(arg),
+            x => {
+                eprintln!("Unknown flag or value '{x}'. Run '{arg0} --help' for usage.");
+                std::process::exit(1);
+            }
+        }
+    }
+    let Some(what) = what else {
+        eprintln!("Error: list expects either sessions or presets");
+        std::process::exit(1);
+    };
+
+    let server = make_server(socket.as_deref(), remote.as_deref());
+    let (mut presets, diagnostics) = load_presets(&preset_sources(&custom_presets, &[]));
+    print_diagnostics(&diagnostics);
+    // No tmux server simply means no sessions
+    let mut sessions = server.list_sessions().unwrap_or_default();
+    let preset_sockets = list_preset_sockets(&server, &presets);
+    mark_running_presets(&mut presets, &mut sessions, &preset_sockets);
+
+    let output = match (what.as_str(), json) {
+        #[cfg(feature = "serde")]
+        ("sessions", true) => list::sessions_json(&sessions),
+        #[cfg(feature = "serde")]
+        (_, true) => list::presets_json(&presets),
+        #[cfg(not(feature = "serde"))]
+        (_, true) => unreachable!("`--json` is refused above"),
+        ("sessions", false) => list::sessions_plain(&sessions),
+        (_, false) => list::presets_plain(&presets),
+    };
+    print!("{output}");
+}
+
+/// `muffin relayout [SESSION[:WINDOW]]`: bring windows launched from presets back to their
+/// layout's proportions, for the size they have now. Without a target, every window of the
+/// session muffin runs in.
+fn run_relayout(arg0: &str, mut args: impl Iterator<Item = String>) {
+    let mut socket = None;
+    let mut remote = None;
+    let mut target = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--socket" | "-L" => {
+                socket = Some(expect_value(&arg, &mut args, "a socket name"));
+            }
+            "--remote" => {
+                remote = Some(expect_value(&arg, &mut args, "a host"));
+            }
+            "--log" => {
+                let path = expect_value(&arg, &mut args, "a path");
+                set_log_file(&path);
+            }
+            x if !x.starts_with('-') && target.is_none() => target = Some(arg),
+            x => {
+                eprintln!("Unknown flag or value '{x}'. Run '{arg0} --help' for usage.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let server = make_server(socket.as_deref(), remote.as_deref());
+    let current = || {
+        let sessions = server.list_sessions().unwrap_or_default();
+        sessions.into_iter().find(|s| s.is_current).map(|s| s.name)
+    };
+    // Session names can't hold a `:`, so the first one starts the window
+    let (session, window) = match target.as_deref().map(|t| t.split_once(':')) {
+        Some(Some((session, window))) => (session.to_string(), Some(window)),
+        Some(None) => (target.clone().unwrap_or_default(), None),
+        None => match current() {
+            Some(session) => (session, None),
+            None => {
+                eprintln!("Error: relayout expects a session when not run inside tmux");
+                std::process::exit(1);
+            }
+        },
+    };
+
+    let windows = match server.list_windows(&session) {
+        Ok(windows) => windows,
+        Err(e) => {
+            eprintln!("Error: {}", e.reason());
+            std::process::exit(1);
+        }
+    };
+    let windows = windows
+        .into_iter()
+        .filter(|w| window.is_none_or(|window| w.name == window || w.index.to_string() == window))
+        .collect::<Vec<_>>();
+    if let (Some(window), true) = (window, windows.is_empty()) {
+        eprintln!("Error: session '{session}' has no window '{window}'");
+        std::process::exit(1);
+    }
+
+    let mut failed = false;
+    for window in windows {
+        let target = format!("{}:{}", exact_target(&session), window.index);
+        let label = format!("{session}:{}", window.name);
+        match server.relayout_window(&target) {
+            Ok(Relayout::Applied) => println!("{label}: {}", Relayout::Applied),
+            Ok(skipped) => eprintln!("Note: skipped {label}, {skipped}"),
+            Err(e) => {
+                eprintln!("Error: {label}: {}", e.reason());
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// `muffin pick`: choose a session (or preset) and print its name, for shell integration like
+/// `tmux switch-client -t "$(muffin pick)"`. Cancelling prints nothing and exits with 130.
+///
+/// Without a terminal to choose in, the first line of stdin is the filter and the best match is
+/// printed.
+async fn run_pick(arg0: &str, mut args: impl Iterator<Item = String>) {
+    let mut custom_presets = vec![];
+    let mut custom_config = None;
+    let mut panel = Panel::Sessions;
+    let mut socket = None;
+    let mut remote = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // `--presets` picks among them, so their paths only go by `-p`
+            "--presets" => panel = Panel::Presets,
+            "-p" => custom_presets.push(expect_value(&arg, &mut args, "a path")),
+            "--config" | "-c" => {
+                custom_config = Some(expect_value(&arg, &mut args, "a path"));
+            }
+            "--socket" | "-L" => {
+                socket = Some(expect_value(&arg, &mut args, "a socket name"));
+            }
+            "--remote" => {
+                remote = Some(expect_value(&arg, &mut args, "a host"));
+            }
+            "--log" => {
+                let path = expect_value(&arg, &mut args, "a path");
+                set_log_file(&path);
+            }
+            x => {
+                eprintln!("Unknown flag or value '{x}'. Run '{arg0} --help' for usage.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let server = make_server(socket.as_deref(), remote.as_deref());
+    let preset_sources = preset_sources(&custom_presets, &[]);
+
+    if !std::io::stdin().is_terminal() {
+        match pick_from(
+            &mut std::io::stdin().lock(),
+            panel,
+            &server,
+            &preset_sources,
+        ) {
+            Ok(name) => println!("{name}"),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let config = load_config(resolve_config_path(custom_config.as_deref()).as_deref());
+    let theme = Theme::from_config(config.theme.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    let server = with_config_options(server, &config);
+    let mut app =
+        App::new(preset_sources, false, false, config, theme, server, None).picking(panel);
+
+    // stdout is likely captured by the shell, so the UI goes to stderr
+    let mut terminal = init_terminal(std::io::stderr);
+    enable_mouse_capture(std::io::stderr);
+    let picked = app.pick(&mut terminal).await;
+    disable_mouse_capture(std::io::stderr);
+    restore_terminal(std::io::stderr);
+
+    match picked {
+        Ok(Some(name)) => println!("{name}"),
+        // What shells report for ctrl+c, so scripts can tell cancelling apart from failing
+        Ok(None) => std::process::exit(130),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// [`run_pick`] without a terminal: the best match for the first line of `input`
+fn pick_from(
+    input: &mut impl BufRead,
+    panel: Panel,
+    server: &Server,
+    preset_sources: &[Source],
+) -> Result<String, String> {
+    let mut query = String::new();
+    input
+        .read_line(&mut query)
+        .map_err(|e| format!("Could not read from stdin: {e}"))?;
+    let query = query.trim();
+    // Presets also match by their descriptions, when none does by name
+    let (names, descriptions) = match panel {
+        // No tmux server just means no sessions to pick from
+        Panel::Sessions => server
+            .list_sessions()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|s| (s.name, None))
+            .filter(|(name, _)| !is_trash(name))
+            .unzip::<_, _, Vec<_>, Vec<_>>(),
+        Panel::Presets => {
+            let (presets, diagnostics) = load_presets(preset_sources);
+            print_diagnostics(&diagnostics);
+            presets
+                .iter()
+                .map(|p| (p.name.clone(), p.description.clone()))
+                .unzip()
+        }
+    };
+    let picked = best_match(query, names.iter().map(String::as_str)).or_else(|| {
+        let description = best_match(query, descriptions.iter().flatten().map(String::as_str))?;
+        names
+            .iter()
+            .zip(&descriptions)
+            .find(|(_, d)| d.as_deref() == Some(description))
+            .map(|(name, _)| name.as_str())
+    });
+    picked
+        .map(String::from)
+        .ok_or_else(|| format!("nothing matches '{query}'"))
+}
+
+/// `muffin popup`: open muffin in a tmux popup over the current client, passing it the remaining
+/// arguments
+fn run_popup(mut args: impl Iterator<Item = String>) {
+    let mut width = "80%".to_string();
+    let mut height = "80%".to_string();
+    let mut forwarded = vec![];
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => width = expect_value(&arg, &mut args, "a size"),
+            "--height" => height = expect_value(&arg, &mut args, "a size"),
+            _ => forwarded.push(arg),
+        }
+    }
+
+    let exe = std::env::current_exe().unwrap_or_else(|e| {
+        eprintln!("Could not find muffin's own executable: {e}");
+        std::process::exit(1);
+    });
+    let command = [exe.to_string_lossy().into_owned(), "--popup".to_string()]
+        .into_iter()
+        .chain(forwarded)
+        .collect::<Vec<_>>();
+    Server::local()
+        .display_popup(&command, &width, &height)
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+}
+
+/// `muffin import`: turn tmuxinator or tmuxp project files into presets, printed as KDL
+fn run_import(arg0: &str, mut args: impl Iterator<Item = String>) {
+    let mut format = None;
+    let mut out = None;
+    let mut files = vec![];
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => {
+                let name = expect_value(&arg, &mut args, "tmuxinator or tmuxp");
+                format = Some(ImportFormat::from_name(&name).unwrap_or_else(|| {
+                    eprintln!("Error: can't import from '{name}', only tmuxinator or tmuxp");
+                    std::process::exit(1);
+                }));
+            }
+            "--out" | "-o" => out = Some(expect_value(&arg, &mut args, "a path")),
+            x if !x.starts_with('-') => files.push(arg),
+            x => {
+                eprintln!("Unknown flag or value '{x}'. Run '{arg0} --help' for usage.");
+                std::process::exit(1);
+            }
+        }
+    }
+    let Some(format) = format else {
+        eprintln!("Error: import expects --from tmuxinator or --from tmuxp");
+        std::process::exit(1);
+    };
+    if files.is_empty() {
+        eprintln!("Error: import expects at least one file");
+        std::process::exit(1);
+    }
+
+    // Carry on past files that fail, so one run reports on all of them
+    let mut presets = vec![];
+    let mut failed = false;
+    for file in &files {
+        let imported = std::fs::read_to_string(file)
+            .map_err(|e| format!("Could not read: {e}"))
+            .and_then(|yaml| parser::import_preset(&yaml, format));
+        match imported {
+            Ok((preset, warnings)) => {
+                eprintln!(
+                    "{file}: imported '{}' ({} windows)",
+                    preset.name,
+                    preset.windows.len()
+                );
+                for warning in warnings {
+                    eprintln!("  Warning: {warning}");
+                }
+                presets.push(preset);
+            }
+            Err(e) => {
+                eprintln!("{file}: {e}");
+                failed = true;
+            }
+        }
+    }
+
+    let kdl = parser::presets_to_kdl(&presets);
+    match out {
+        Some(out) => {
+            let out = shellexpand::full(&out)
+                .expect("Failed to expand environment variables in path")
+                .to_string();
+            std::fs::write(&out, kdl).unwrap_or_else(|e| {
+                eprintln!("Could not write '{out}': {e}");
+                std::process::exit(1);
+            });
+        }
+        None => print!("{kdl}"),
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// `muffin check`: parse the presets and report every problem found, exiting nonzero on errors
+/// (and on warnings too with `--strict`)
+fn run_check(arg0: &str, mut args: impl Iterator<Item = String>) {
+    let mut custom_presets = vec![];
+    let mut inline = vec![];
+    let mut strict = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--presets" | "-p" => {
+                custom_presets.push(expect_value(&arg, &mut args, "a path"));
+            }
+            "--stdin" => custom_presets.push("-".to_string()),
+            "--inline" => inline.push(expect_value(&arg, &mut args, "a KDL document")),
+            "--strict" => strict = true,
+            x => {
+                eprintln!("Unknown flag or value '{x}'. Run '{arg0} --help' for usage.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let sources = preset_sources(&custom_presets, &inline);
+    let (presets, mut diagnostics) = load_presets(&sources);
+    diagnostics.extend(presets.iter().flat_map(|preset| {
+        missing_commands(preset)
+            .into_iter()
+            .map(|program| Diagnostic {
+                file: preset
+                    .source
+                    .as_ref()
+                    .map(|file| file.display().to_string()),
+                ..Diagnostic::warning(format!(
+                    "`{program}` isn't a command on PATH (preset `{}`)",
+                    preset.name
+                ))
+            })
+    }));
+    if strict {
+        for diagnostic in &diagnostics {
+            eprintln!("Error: {diagnostic}");
+        }
+    } else {
+        print_diagnostics(&diagnostics);
+    }
+    // Stdin and inline documents count as files of their own
+    let piped = sources.iter().filter(|s| s.path().is_none()).count();
+    eprintln!(
+        "Checked {} presets from {} files: {} warnings",
+        presets.len(),
+        presets.files().len() + piped,
+        diagnostics.len()
     );
+    if strict && !diagnostics.is_empty() {
+        std::process::exit(1);
+    }
+}
 
-    let mut terminal = ratatui::init();
-    let app_result = app.run(&mut terminal).await;
+/// `muffin doctor`: check everything muffin depends on, exiting nonzero if something fails
+fn run_doctor(arg0: &str, mut args: impl Iterator<Item = String>) {
+    let mut custom_presets = vec![];
+    let mut json = false;
+    let mut socket = None;
+    let mut remote = None;
 
-    ratatui::restore();
-    app_result.unwrap();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--presets" | "-p" => {
+                custom_presets.push(expect_value(&arg, &mut args, "a path"));
+            }
+            "--json" => json = true,
+            "--socket" | "-L" => {
+                socket = Some(expect_value(&arg, &mut args, "a socket name"));
+            }
+            "--remote" => {
+                remote = Some(expect_value(&arg, &mut args, "a host"));
+            }
+            x => {
+                eprintln!("Unknown flag or value '{x}'. Run '{arg0} --help' for usage.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let server = make_server(socket.as_deref(), remote.as_deref());
+    // Unlike everywhere else, a missing default presets file is reported rather than created
+    let presets = match custom_presets.is_empty() {
+        true => doctor::PresetsFiles {
+            sources: paths::default_presets_path()
+                .into_iter()
+                .map(Source::File)
+                .collect(),
+            default: true,
+        },
+        false => doctor::PresetsFiles {
+            sources: preset_sources(&custom_presets, &[]),
+            default: false,
+        },
+    };
+    let mut checks: Vec<Box<dyn doctor::Check>> = vec![
+        Box::new(doctor::TmuxVersion { server: &server }),
+        Box::new(doctor::ServerReachable { server: &server }),
+        Box::new(presets),
+        Box::new(doctor::WritableDir {
+            name: "config dir",
+            dir: paths::config_dir(),
+        }),
+        Box::new(doctor::WritableDir {
+            name: "state dir",
+            dir: paths::state_dir(),
+        }),
+    ];
+    checks.extend([
+        Box::new(doctor::InsideTmux) as Box<dyn doctor::Check>,
+        Box::new(doctor::TerminalSize),
+        Box::new(doctor::TrueColor),
+        Box::new(doctor::GlyphWidths {
+            icons: Icons::from_env(),
+        }),
+    ]);
+
+    let results = checks
+        .iter()
+        .map(|check| (check.name(), check.run()))
+        .collect::<Vec<_>>();
+    if json {
+        print!("{}", doctor::format_json(&results));
+    } else {
+        print!("{}", doctor::format_plain(&results));
+    }
+    if results
+        .iter()
+        .any(|(_, result)| result.outcome == doctor::Outcome::Fail)
+    {
+        std::process::exit(1);
+    }
+}
+
+/// `muffin export`: write presets out as standalone shell scripts
+fn run_export(arg0: &str, mut args: impl Iterator<Item = String>) {
+    let mut custom_presets = vec![];
+    let mut out = None;
+    let mut all = false;
+    let mut preset_name = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--presets" | "-p" => {
+                custom_presets.push(expect_value(&arg, &mut args, "a path"));
+            }
+            "--out" | "-o" => {
+                out = Some(expect_value(&arg, &mut args, "a path"));
+            }
+            "--all" => all = true,
+            x if !x.starts_with('-') && preset_name.is_none() => preset_name = Some(arg),
+            x => {
+                eprintln!("Unknown flag or value '{x}'. Run '{arg0} --help' for usage.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let (presets, diagnostics) = load_presets(&preset_sources(&custom_presets, &[]));
+    print_diagnostics(&diagnostics);
+
+    let out = out.map(|out| {
+        shellexpand::full(&out)
+            .expect("Failed to expand environment variables in path")
+            .to_string()
+    });
+
+    let write = |preset: &Preset, path: &std::path::Path| {
+        let written = export::write_script(preset, path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+        eprintln!("Exported '{}' to {}", preset.name, written.display());
+    };
+
+    match (all, preset_name) {
+        // With `--all`, `--out` is a directory to put one script per preset in
+        (true, None) => {
+            let dir = std::path::PathBuf::from(out.unwrap_or_else(|| ".".to_string()));
+            std::fs::create_dir_all(&dir).unwrap_or_else(|e| {
+                eprintln!("Could not create '{}': {e}", dir.display());
+                std::process::exit(1);
+            });
+            for preset in presets.iter() {
+                write(preset, &dir.join(export::default_path(preset)));
+            }
+        }
+        (false, Some(name)) => {
+            let preset = presets.get_by_name(&name).unwrap_or_else(|| {
+                eprintln!("Preset does not exist!");
+                std::process::exit(1);
+            });
+            match out {
+                Some(out) => write(preset, std::path::Path::new(&out)),
+                None => match tmux::export_script(preset) {
+                    Ok(script) => print!("{script}"),
+                    Err(e) => {
+                        eprintln!("Could not export '{}': {e}", preset.name);
+                        std::process::exit(1);
+                    }
+                },
+            }
+        }
+        _ => {
+            eprintln!("Error: export expects either a preset name or --all");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `muffin launch`: launch a preset and switch to it, or print what launching it would run
+async fn run_launch(arg0: &str, mut args: impl Iterator<Item = String>) {
+    let mut custom_presets = vec![];
+    let mut inline = vec![];
+    let mut custom_config = None;
+    let mut preset_name = None;
+    let mut dry_run = false;
+    let mut force = false;
+    let mut vars = BTreeMap::new();
+    let mut socket = None;
+    let mut remote = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--presets" | "-p" => {
+                custom_presets.push(expect_value(&arg, &mut args, "a path"));
+            }
+            "--stdin" => custom_presets.push("-".to_string()),
+            "--inline" => inline.push(expect_value(&arg, &mut args, "a KDL document")),
+            "--config" | "-c" => {
+                custom_config = Some(expect_value(&arg, &mut args, "a path"));
+            }
+            "--dry-run" | "-n" => dry_run = true,
+            "--force" => force = true,
+            "--var" => {
+                let var = expect_value(&arg, &mut args, "NAME=VALUE");
+                let Some((name, value)) = var.split_once('=') else {
+                    eprintln!("Error: {arg} expects NAME=VALUE, got '{var}'");
+                    std::process::exit(1);
+                };
+                vars.insert(name.to_string(), value.to_string());
+            }
+            "--socket" | "-L" => {
+                socket = Some(expect_value(&arg, &mut args, "a socket name"));
+            }
+            "--remote" => {
+                remote = Some(expect_value(&arg, &mut args, "a host"));
+            }
+            "--log" => {
+                let path = expect_value(&arg, &mut args, "a path");
+                set_log_file(&path);
+            }
+            x if !x.starts_with('-') && preset_name.is_none() => preset_name = Some(arg),
+            x => {
+                eprintln!("Unknown flag or value '{x}'. Run '{arg0} --help' for usage.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let Some(preset_name) = preset_name else {
+        eprintln!("Error: launch expects a preset name");
+        std::process::exit(1);
+    };
+    let config = load_config(resolve_config_path(custom_config.as_deref()).as_deref());
+    let server = with_config_options(make_server(socket.as_deref(), remote.as_deref()), &config);
+    let (presets, diagnostics) = load_presets(&preset_sources(&custom_presets, &inline));
+    print_diagnostics(&diagnostics);
+    let preset = presets.get_by_name(&preset_name).unwrap_or_else(|| {
+        eprintln!("Preset does not exist!");
+        std::process::exit(1);
+    });
+    let preset = &resolve_vars(preset, &vars, "pass one with --var NAME=VALUE");
+
+    if dry_run {
+        let lines = server.dry_run(preset).unwrap_or_else(|e| {
+            eprintln!("Could not plan '{}': {e}", preset.name);
+            std::process::exit(1);
+        });
+        for line in lines {
+            println!("{line}");
+        }
+        return;
+    }
+    check_tmux_version(&server);
+    launch_preset(server, preset, force).await;
 }
 
 fn print_help(arg0: &str) {
     eprintln!(
         r"
 Usage: {arg0} [OPTIONS]
+       {arg0} launch <NAME> [--dry-run] [--force] [--var <NAME=VALUE>]...
+       {arg0} status [--json] [--watch] [-p <PATH>]
+       {arg0} list <sessions|presets> [--json] [-p <PATH>]
+       {arg0} export <NAME|--all> [--out <PATH>] [-p <PATH>]
+       {arg0} check [--strict] [-p <PATH>] [--stdin] [--inline <KDL>]
+       {arg0} doctor [--json] [-p <PATH>] [-L <NAME>] [--remote <HOST>]
+       {arg0} import --from <tmuxinator|tmuxp> <FILE>... [--out <PATH>]
+       {arg0} popup [--width <SIZE>] [--height <SIZE>] [OPTIONS]
+       {arg0} pick [--presets] [-p <PATH>]
+       {arg0} relayout [SESSION[:WINDOW]] [-L <NAME>] [--remote <HOST>]
+
+SUBCOMMANDS:
+    launch                      Launch preset NAME and switch to it (or attach to it, when it's
+                                on another server); takes -p, -c, -L, --remote and --log as
+                                for OPTIONS
+        -n, --dry-run           Print the tmux commands instead of running them
+        --force                 Launch even if some pane commands aren't on PATH
+        --var <NAME=VALUE>      The value of the preset's variable NAME; may be repeated
+        --stdin                 Read presets from stdin, same as -p -
+        --inline <KDL>          Read presets from KDL given as is; may be repeated
+    status                      Print whether each preset is running, one per line
+        --json                  Print the same information as a JSON array
+        --watch                 Keep running, printing a new snapshot whenever it changes
+    list sessions               Print every session: id, windows, attached clients, whether
+                                muffin runs in it and name, lined up in columns
+    list presets                Print every preset: running or stopped, windows, panes and name
+        --json                  Print either as a JSON array instead, see the README
+    export                      Print a shell script that recreates preset NAME with plain tmux
+        --out <PATH>            Write the script to PATH instead (a directory with --all)
+        --all                   Export every preset, one <NAME>.sh script each
+    check                       Parse the presets and report any problems with them
+        --strict                Treat warnings (like unknown attributes) as errors, including
+                                pane commands that aren't on PATH
+        --stdin, --inline <KDL> Check presets from stdin or given as is, as for launch
+    doctor                      Check tmux, the presets, muffin's directories and the terminal,
+                                printing pass, warn or fail for each
+        --json                  Print the same information as a JSON array
+    import                      Print presets converted from tmuxinator or tmuxp project files
+        --from <FORMAT>         Which of the two the files are for
+        --out <PATH>            Write the presets to PATH instead
+    popup                       Open muffin with OPTIONS in a tmux popup over the current client
+        --width <SIZE>          Width of the popup, in cells or a percentage [default: 80%]
+        --height <SIZE>         Height of the popup, in cells or a percentage [default: 80%]
+    pick                        Choose a session and print its name (for shell scripts); without
+                                a terminal, print the best match for the first line of stdin
+        --presets               Choose a preset instead
+    relayout                    Resize the panes of windows launched from presets back to their
+                                layout's proportions, after the terminal changed size; every
+                                window of SESSION, or of the current session without one
 
 OPTIONS:
     -s, --start-preset <NAME>   Start preset
+        --force                 With -s, launch even if some pane commands aren't on PATH
+    -S, --session <NAME>        Open with session NAME highlighted
+        --create                With -S, create the session if it doesn't exist
+        --switch                With -S, switch to the session (or attach to it outside tmux)
+                                instead of opening muffin
     -l, --list-presets          List presets information
-    -p, --presets <FILE>        Path to presets file [default: ~/.config/muffin/presets.kdl]
+    -p, --presets <PATH>        Presets file, or directory of *.kdl files, or - for stdin; may
+                                be repeated [default: ~/.config/muffin/presets.kdl]
+    -c, --config <FILE>         Path to config file [default: ~/.config/muffin/config.kdl]
     -e, --exit-on-switch        Close muffin after switching to a session/preset
+        --once                  Same as --exit-on-switch, overriding the config file
+        --popup                 Behave as in a tmux popup: close after switching or on Esc
+                                [default: on when run in a popup]
+    -L, --socket <NAME>         Talk to the tmux server on socket NAME (like `tmux -L`)
+        --remote <HOST>         Manage the tmux server on HOST over ssh
+        --log <FILE>            Append every tmux invocation to FILE (also: MUFFIN_LOG=<FILE>)
+    -V, --version               Print the version, commit and build date
     -h, --help                  Print help",
     );
 }
 
-const EXAMPLE_PRESET_CONTENT: &'static str = r#"
-session name="foo" cwd="~" {
-  window {
-    split direction="h" {
-      pane command="echo Hello,"
-      pane command="echo World!"
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_vars_are_asked_for_where_muffin_was_started() {
+        let (presets, _) = parser::parse_config(
+            r#"session name="client" cwd="{dir}" { vars { dir; port default="8080" }; }"#,
+        )
+        .unwrap();
+        let preset = &presets["client"];
+
+        let launched = fill_vars(preset, &BTreeMap::new(), "pass one with --var NAME=VALUE");
+        assert_eq!(
+            launched.unwrap_err(),
+            "No value given for `dir`, pass one with --var NAME=VALUE"
+        );
+        // `-s` takes no values
+        let started = fill_vars(preset, &BTreeMap::new(), &start_preset_hint(preset));
+        assert_eq!(
+            started.unwrap_err(),
+            "No value given for `dir`, give it a default in `vars`, or a value with \
+             `muffin launch client --var NAME=VALUE`"
+        );
+
+        let values = BTreeMap::from([("dir".to_string(), "/srv".to_string())]);
+        assert_eq!(fill_vars(preset, &values, "").unwrap().cwd, "/srv");
+    }
+
+    #[test]
+    fn broken_default_sessions_still_load_the_config() {
+        let path = std::env::temp_dir().join(format!("muffin-config-{}.kdl", std::process::id()));
+        std::fs::write(
+            &path,
+            "exit_on_switch #true\ndefault_session name=\"x\" { window; }\n",
+        )
+        .unwrap();
+        let config = load_config(Some(&path));
+        let _ = std::fs::remove_file(&path);
+        assert!(config.exit_on_switch);
+        assert!(config.default_session.is_none());
+    }
+
+    #[test]
+    fn presets_paths_given_have_to_exist() {
+        let dir = std::env::temp_dir();
+        assert_eq!(presets_path(dir.to_str().unwrap()), Ok(dir.clone()));
+
+        let missing = dir.join(format!("muffin-missing-{}.kdl", std::process::id()));
+        assert_eq!(
+            presets_path(missing.to_str().unwrap()).unwrap_err(),
+            format!(
+                "there's no presets file at '{}' (given with --presets)",
+                missing.display()
+            )
+        );
+    }
+
+    #[test]
+    fn picking_without_a_terminal_takes_the_best_match_for_the_first_line() {
+        let server = app::testing::TestServer::new("pick-stdin");
+        for name in ["api", "api-docs", "database"] {
+            server.create(name);
+        }
+        let pick = |input: &str, panel, sources: &[Source]| {
+            pick_from(&mut input.as_bytes(), panel, &server.server, sources)
+        };
+        assert_eq!(pick("api\nignored\n", Panel::Sessions, &[]).unwrap(), "api");
+        assert_eq!(pick("  dtb  \n", Panel::Sessions, &[]).unwrap(), "database");
+        assert_eq!(
+            pick("zzz", Panel::Sessions, &[]).unwrap_err(),
+            "nothing matches 'zzz'"
+        );
+
+        // Presets by name, or else by description
+        let presets = [Source::Inline(
+            r#"session name="web" description="Frontend dev server"
+            session name="blog" description="Static site""#
+                .to_string(),
+        )];
+        assert_eq!(pick("we", Panel::Presets, &presets).unwrap(), "web");
+        assert_eq!(pick("static", Panel::Presets, &presets).unwrap(), "blog");
     }
-  }
 }
-"#;
@@ -0,0 +1,139 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// `$XDG_CONFIG_HOME/muffin` (`~/.config/muffin` by default), holding the presets and config files
+pub fn config_dir() -> Option<PathBuf> {
+    Some(base_dir("XDG_CONFIG_HOME", ".config")?.join("muffin"))
+}
+
+/// `$XDG_STATE_HOME/muffin` (`~/.local/state/muffin` by default), holding what muffin remembers
+/// between runs
+pub fn state_dir() -> Option<PathBuf> {
+    Some(base_dir("XDG_STATE_HOME", ".local/state")?.join("muffin"))
+}
+
+/// The presets file read when none is given with `--presets`
+pub fn default_presets_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("presets.kdl"))
+}
+
+/// The config file read when none is given with `--config`
+pub fn config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("config.kdl"))
+}
+
+/// The directory in `var`, or else `fallback` in the home directory, see [`resolve_base_dir`]
+fn base_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    resolve_base_dir(std::env::var_os(var), std::env::var_os("HOME"), fallback)
+}
+
+/// `dir`, the value of an XDG variable, or else `fallback` in `home`.
+///
+/// Like the XDG spec says, relative paths in `dir` are ignored. macOS gets the same defaults, as
+/// command line tools there keep to `~/.config` rather than `~/Library`.
+fn resolve_base_dir(
+    dir: Option<OsString>,
+    home: Option<OsString>,
+    fallback: &str,
+) -> Option<PathBuf> {
+    dir.map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| {
+            let home = home.filter(|home| !home.is_empty())?;
+            Some(PathBuf::from(home).join(fallback))
+        })
+}
+
+/// Writes `contents` to `path`, first creating the directories leading up to it
+pub fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Could not create '{}': {e}", dir.display()))?;
+    }
+    std::fs::write(path, contents).map_err(|e| format!("Could not write '{}': {e}", path.display()))
+}
+
+/// Like [`write_file`], but fails rather than overwrite a file already at `path`
+pub fn write_new_file(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Could not create '{}': {e}", dir.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                format!("'{}' already exists, leaving it as is", path.display())
+            }
+            _ => format!("Could not write '{}': {e}", path.display()),
+        })?;
+    std::io::Write::write_all(&mut file, contents.as_bytes())
+        .map_err(|e| format!("Could not write '{}': {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_dirs() {
+        for (dir, home, expected) in [
+            (Some("/xdg/config"), Some("/home/me"), Some("/xdg/config")),
+            (Some("/xdg/config"), None, Some("/xdg/config")),
+            (Some("/xdg/config/"), Some(""), Some("/xdg/config/")),
+            (None, Some("/home/me"), Some("/home/me/.config")),
+            // Empty or relative, the variable is as good as unset
+            (Some(""), Some("/home/me"), Some("/home/me/.config")),
+            (
+                Some("xdg/config"),
+                Some("/home/me"),
+                Some("/home/me/.config"),
+            ),
+            (
+                Some("~/.config"),
+                Some("/home/me"),
+                Some("/home/me/.config"),
+            ),
+            (None, None, None),
+            (None, Some(""), None),
+            (Some("relative"), None, None),
+        ] {
+            assert_eq!(
+                resolve_base_dir(dir.map(OsString::from), home.map(OsString::from), ".config"),
+                expected.map(PathBuf::from),
+                "{dir:?} {home:?}"
+            );
+        }
+    }
+
+    /// A directory of the test's own, removed first in case a previous run left it
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("muffin-paths-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn directories_are_created_on_write() {
+        let dir = scratch("create");
+        let path = dir.join("a/b/presets.kdl");
+        write_file(&path, "one").unwrap();
+        write_file(&path, "two").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "two");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unwritable_directories_are_reported() {
+        let dir = scratch("unwritable");
+        write_file(&dir.join("file"), "").unwrap();
+        // A file where a directory should be fails whoever runs the tests, root included
+        let path = dir.join("file/presets.kdl");
+        let message = format!("Could not create '{}': ", dir.join("file").display());
+        assert!(write_file(&path, "").unwrap_err().starts_with(&message));
+        assert!(write_new_file(&path, "").unwrap_err().starts_with(&message));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
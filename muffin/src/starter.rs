@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use tmux::{Layout, Preset, Window};
+
+use crate::paths;
+
+/// Put at the top of the starter file, as the serializer doesn't write comments
+const HEADER: &str = "\
+// Presets muffin launches as tmux sessions, from the presets menu or with `muffin -s <name>`.
+//
+// Each `session` has `window`s, and each window a single `pane` or a `split` of panes and
+// splits, side by side (`direction=\"columns\"`) or stacked (`direction=\"rows\"`). `cwd` is
+// where they start, `command` what's typed into a pane once it's open, and `size` its share of
+// the split. Edit these examples or add your own, then press R in muffin to read them again.
+
+";
+
+/// A couple of presets showing off windows, splits, directories and commands
+fn starter_presets() -> Vec<Preset> {
+    let scratch = Preset::builder("scratch")
+        .cwd("~")
+        .description("A shell with a system monitor next to it")
+        .window(Window::builder("main").layout(Layout::hsplit([
+            Layout::pane().pct(60),
+            Layout::pane().cmd("top"),
+        ])));
+    let project = Preset::builder("project")
+        .cwd("~")
+        .description("An editor, a shell over a listing, and a window in /tmp")
+        .window(Window::builder("editor").layout(Layout::pane().cmd("${EDITOR:-vi} .")))
+        .window(Window::builder("shell").layout(Layout::vsplit([
+            Layout::pane(),
+            Layout::pane().pct(30).cmd("ls -la"),
+        ])))
+        .window(Window::builder("tmp").cwd("/tmp"));
+
+    [scratch, project]
+        .into_iter()
+        .map(|preset| preset.build().expect("starter presets are valid"))
+        .collect()
+}
+
+/// The starter presets file: the starter presets as muffin writes presets out, under a header
+/// explaining them
+pub fn starter_file() -> String {
+    format!("{HEADER}{}", parser::presets_to_kdl(&starter_presets()))
+}
+
+/// Writes the starter presets file to `path`, creating its directory, unless there's a file
+/// there already
+pub fn write_starter(path: &Path) -> Result<(), String> {
+    paths::write_new_file(path, &starter_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_starter_file_reads_back_as_the_starter_presets() {
+        let presets = starter_presets();
+        let (parsed, diagnostics) = parser::parse_config(&starter_file()).unwrap();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!(parsed.len(), presets.len());
+        let parsed = presets.iter().map(|preset| &parsed[&preset.name]);
+        assert_eq!(
+            parser::presets_to_kdl(parsed),
+            parser::presets_to_kdl(&presets)
+        );
+    }
+
+    #[test]
+    fn the_starter_file_is_only_written_where_there_is_none() {
+        let dir = std::env::temp_dir().join(format!("muffin-starter-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("muffin/presets.kdl");
+        write_starter(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), starter_file());
+
+        std::fs::write(&path, "session name=\"mine\"").unwrap();
+        assert_eq!(
+            write_starter(&path).unwrap_err(),
+            format!("'{}' already exists, leaving it as is", path.display())
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "session name=\"mine\""
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,128 @@
+use crate::app::preset_list::PresetList;
+
+/// A point-in-time view of a single preset, as reported to external tooling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresetStatus {
+    pub name: String,
+    pub running: bool,
+    pub windows: usize,
+}
+
+/// Collects the status of every preset in display order.
+///
+/// Expects the `running` flags to already be reconciled against the live tmux sessions.
+pub fn snapshot(presets: &PresetList) -> Vec<PresetStatus> {
+    presets
+        .iter()
+        .map(|preset| PresetStatus {
+            name: preset.name.clone(),
+            running: preset.running,
+            windows: preset.windows.len(),
+        })
+        .collect()
+}
+
+/// One line per preset: `name\trunning|stopped\twindow_count`
+pub fn format_plain(statuses: &[PresetStatus]) -> String {
+    statuses
+        .iter()
+        .map(|status| {
+            format!(
+                "{}\t{}\t{}\n",
+                status.name,
+                if status.running { "running" } else { "stopped" },
+                status.windows
+            )
+        })
+        .collect()
+}
+
+/// The same information as [`format_plain`], as a single-line JSON array
+pub fn format_json(statuses: &[PresetStatus]) -> String {
+    let entries = statuses
+        .iter()
+        .map(|status| {
+            serde_json::json!({
+                "name": status.name,
+                "running": status.running,
+                "windows": status.windows,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    format!("{}\n", serde_json::Value::Array(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{testing::session, utils::mark_running_presets};
+    use std::collections::HashMap;
+    use tmux::{Layout, Preset, Session, Window};
+
+    /// `api` running with 2 windows, `web` stopped with 12, `docs` stopped with 1
+    fn statuses() -> Vec<PresetStatus> {
+        let preset = |name: &str, windows: usize| {
+            let mut builder = Preset::builder(name);
+            for i in 0..windows {
+                builder = builder.window(Window::builder(format!("w{i}")).layout(Layout::pane()));
+            }
+            builder.build().unwrap()
+        };
+        let mut presets = PresetList::new([preset("api", 2), preset("web", 12), preset("docs", 1)]);
+        let mut sessions = [
+            Session {
+                preset: Some("api".to_string()),
+                ..session("api")
+            },
+            // Named like the preset, but not launched from it
+            session("web"),
+        ];
+        mark_running_presets(&mut presets, &mut sessions, &HashMap::new());
+        snapshot(&presets)
+    }
+
+    #[test]
+    fn snapshot_in_display_order() {
+        assert_eq!(
+            statuses(),
+            [
+                PresetStatus {
+                    name: "api".to_string(),
+                    running: true,
+                    windows: 2,
+                },
+                PresetStatus {
+                    name: "docs".to_string(),
+                    running: false,
+                    windows: 1,
+                },
+                PresetStatus {
+                    name: "web".to_string(),
+                    running: false,
+                    windows: 12,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn plain_lines() {
+        assert_eq!(
+            format_plain(&statuses()),
+            "api\trunning\t2\ndocs\tstopped\t1\nweb\tstopped\t12\n"
+        );
+        assert_eq!(format_plain(&[]), "");
+    }
+
+    #[test]
+    fn json_array() {
+        assert_eq!(
+            format_json(&statuses()),
+            "[{\"name\":\"api\",\"running\":true,\"windows\":2},\
+             {\"name\":\"docs\",\"running\":false,\"windows\":1},\
+             {\"name\":\"web\",\"running\":false,\"windows\":12}]\n"
+        );
+        assert_eq!(format_json(&[]), "[]\n");
+    }
+}
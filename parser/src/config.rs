@@ -0,0 +1,349 @@
+use kdl::{KdlDocument, KdlNode};
+use tmux::{LayoutNode, Preset};
+
+use crate::Diagnostic;
+
+/// Settings read from muffin's own config file (`~/.config/muffin/config.kdl`)
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Directories whose subdirectories are offered as names when creating a session
+    pub completion_dirs: Vec<String>,
+    /// Quit after switching to a session or launching a preset, for when muffin isn't kept open in
+    /// a dedicated popup or window
+    pub exit_on_switch: bool,
+    /// Name of the color theme, checked once the app starts
+    pub theme: Option<String>,
+    /// What separates a session's group from the rest of its name (`work` in `work/api`), `/` if
+    /// unset. Empty turns grouping off.
+    pub group_separator: Option<String>,
+    /// Terminal width from which sessions and presets are shown side by side, 140 if unset. 0
+    /// turns it off.
+    pub side_by_side_width: Option<u16>,
+    /// What sessions created from the sessions menu are laid out like, instead of a single pane.
+    /// Named `default_session` until [`from_template`] gives it the new session's name.
+    pub default_session: Option<Preset>,
+    /// Whether `d` in the sessions menu asks before deleting a session, #true if unset. Starting
+    /// with #false is like pressing `!` right away; attached sessions are asked about either way.
+    pub confirm_destructive: Option<bool>,
+    /// How many milliseconds a tmux command gets before muffin gives up on it, 5000 if unset
+    pub tmux_timeout_ms: Option<u64>,
+    /// Name sessions created without a name after their active pane's command and directory,
+    /// following along until they're renamed
+    pub auto_name_sessions: bool,
+    /// Also send errors, and what's said as muffin quits, to the desktop's notification daemon
+    pub desktop_notifications: bool,
+    /// Even out the splits of launched presets that don't say otherwise with `even`, see
+    /// [`tmux::Server::with_even_splits`]
+    pub even_splits: bool,
+    /// Key sequences (ex: `g g`) and the names of the actions they're bound to, checked once the
+    /// app starts
+    pub keys: Vec<(String, String)>,
+    /// How many milliseconds a key sequence waits for its next key, 500 if unset
+    pub chord_timeout_ms: Option<u64>,
+}
+
+/// Reads muffin's config file, along with warnings about the settings that were left out for
+/// being broken but don't keep muffin from starting
+pub fn parse_muffin_config(doc_str: &str) -> Result<(Config, Vec<Diagnostic>), String> {
+    let doc: KdlDocument = doc_str
+        .parse()
+        .map_err(|_| "Error parsing file".to_string())?;
+
+    let mut config = Config::default();
+    let mut diagnostics = vec![];
+
+    for node in doc.nodes() {
+        match node.name().value() {
+            // ex: completion_dirs "~/projects/*" "~/work"
+            "completion_dirs" => {
+                for entry in node.entries().iter().filter(|e| e.name().is_none()) {
+                    let dir = entry
+                        .value()
+                        .as_string()
+                        .ok_or("`completion_dirs` expects string arguments")?;
+                    config.completion_dirs.push(dir.to_string());
+                }
+            }
+            // ex: exit_on_switch #true
+            "exit_on_switch" => {
+                config.exit_on_switch = node
+                    .get(0)
+                    .and_then(|value| value.as_bool())
+                    .ok_or("`exit_on_switch` expects #true or #false")?;
+            }
+            // ex: theme "light"
+            "theme" => {
+                let theme = node
+                    .get(0)
+                    .and_then(|value| value.as_string())
+                    .ok_or("`theme` expects a string")?;
+                config.theme = Some(theme.to_string());
+            }
+            // ex: group_separator "."
+            "group_separator" => {
+                let separator = node
+                    .get(0)
+                    .and_then(|value| value.as_string())
+                    .ok_or("`group_separator` expects a string")?;
+                config.group_separator = Some(separator.to_string());
+            }
+            // ex: side_by_side_width 180
+            "side_by_side_width" => {
+                let width = node
+                    .get(0)
+                    .and_then(|value| value.as_integer())
+                    .and_then(|width| u16::try_from(width).ok())
+                    .ok_or("`side_by_side_width` expects a number of columns")?;
+                config.side_by_side_width = Some(width);
+            }
+            // ex: confirm_destructive #false
+            "confirm_destructive" => {
+                let confirm = node
+                    .get(0)
+                    .and_then(|value| value.as_bool())
+                    .ok_or("`confirm_destructive` expects #true or #false")?;
+                config.confirm_destructive = Some(confirm);
+            }
+            // ex: auto_name_sessions #true
+            "auto_name_sessions" => {
+                config.auto_name_sessions = node
+                    .get(0)
+                    .and_then(|value| value.as_bool())
+                    .ok_or("`auto_name_sessions` expects #true or #false")?;
+            }
+            // ex: desktop_notifications #true
+            "desktop_notifications" => {
+                config.desktop_notifications = node
+                    .get(0)
+                    .and_then(|value| value.as_bool())
+                    .ok_or("`desktop_notifications` expects #true or #false")?;
+            }
+            // ex: even_splits #true
+            "even_splits" => {
+                config.even_splits = node
+                    .get(0)
+                    .and_then(|value| value.as_bool())
+                    .ok_or("`even_splits` expects #true or #false")?;
+            }
+            // ex: tmux_timeout_ms 10000
+            "tmux_timeout_ms" => {
+                let timeout = node
+                    .get(0)
+                    .and_then(|value| value.as_integer())
+                    .and_then(|timeout| u64::try_from(timeout).ok())
+                    .filter(|&timeout| timeout > 0)
+                    .ok_or("`tmux_timeout_ms` expects a positive number of milliseconds")?;
+                config.tmux_timeout_ms = Some(timeout);
+            }
+            // ex: chord_timeout_ms 800
+            "chord_timeout_ms" => {
+                let timeout = node
+                    .get(0)
+                    .and_then(|value| value.as_integer())
+                    .and_then(|timeout| u64::try_from(timeout).ok())
+                    .filter(|&timeout| timeout > 0)
+                    .ok_or("`chord_timeout_ms` expects a positive number of milliseconds")?;
+                config.chord_timeout_ms = Some(timeout);
+            }
+            // ex: keys { "g g" "top"; "space p l" "launch-preset" }
+            "keys" => {
+                for binding in node.children().map(|c| c.nodes()).unwrap_or_default() {
+                    let action = binding
+                        .get(0)
+                        .and_then(|value| value.as_string())
+                        .ok_or_else(|| {
+                            format!(
+                                "In `keys`: `{}` expects the name of an action",
+                                binding.name().value()
+                            )
+                        })?;
+                    config
+                        .keys
+                        .push((binding.name().value().to_string(), action.to_string()));
+                }
+            }
+            // ex: default_session { window { split direction="columns" { pane; pane } } }
+            // Sessions can still be created without it, as a single pane
+            "default_session" => match parse_template(node) {
+                Ok(template) => config.default_session = Some(template),
+                Err(e) => diagnostics.push(
+                    Diagnostic::warning(format!(
+                        "In `default_session`: {e}, sessions are created with a single pane \
+                         until it's fixed"
+                    ))
+                    .at(doc_str, node.span().offset()),
+                ),
+            },
+            x => return Err(format!("Unknown config option: `{x}`")),
+        }
+    }
+
+    Ok((config, diagnostics))
+}
+
+/// Reads `default_session` like a `session` node, which it is but for its name
+fn parse_template(node: &KdlNode) -> Result<Preset, String> {
+    if node.get("name").is_some() {
+        return Err("sessions are named when created, leave out `name`".to_string());
+    }
+    let mut session = node.clone();
+    session.set_name("session");
+    session.insert("name", "default_session");
+
+    let template = crate::parse_session(&session, "~", Default::default(), &mut vec![])?;
+    if !template.vars.is_empty() {
+        return Err("there's nothing to ask for `vars` when creating a session".to_string());
+    }
+    Ok(template)
+}
+
+/// The preset to create session `name` with from `template` (see [`Config::default_session`]),
+/// in `cwd` if given.
+///
+/// The template's windows and panes that were left in its own cwd move to `cwd` along with it,
+/// the ones given a directory of their own stay there.
+pub fn from_template(template: &Preset, name: &str, cwd: Option<&str>) -> Preset {
+    fn move_panes(node: &mut LayoutNode, from: &str, to: &str) {
+        match node {
+            LayoutNode::Pane { cwd, .. } if cwd == from => *cwd = to.to_string(),
+            LayoutNode::Pane { .. } => {}
+            LayoutNode::Split { children, .. } => {
+                for child in children {
+                    move_panes(child, from, to);
+                }
+            }
+        }
+    }
+
+    let mut preset = template.clone();
+    preset.name = name.to_string();
+    if let Some(cwd) = cwd {
+        preset.cwd = cwd.to_string();
+        for window in &mut preset.windows {
+            if window.cwd == template.cwd {
+                window.cwd = cwd.to_string();
+            }
+            move_panes(&mut window.layout, &template.cwd, cwd);
+        }
+    }
+    preset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tmux::INHERIT_CWD;
+
+    fn template(body: &str) -> Preset {
+        parse_muffin_config(&format!("default_session {body}"))
+            .unwrap()
+            .0
+            .default_session
+            .unwrap()
+    }
+
+    /// The cwd of every pane of every window, in the order tmux creates them
+    fn pane_cwds(preset: &Preset) -> Vec<Vec<String>> {
+        fn collect(node: &LayoutNode, out: &mut Vec<String>) {
+            match node {
+                LayoutNode::Pane { cwd, .. } => out.push(cwd.clone()),
+                LayoutNode::Split { children, .. } => {
+                    children.iter().for_each(|child| collect(child, out))
+                }
+            }
+        }
+        preset
+            .windows
+            .iter()
+            .map(|window| {
+                let mut out = vec![];
+                collect(&window.layout, &mut out);
+                out
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_session_is_read_like_a_session() {
+        let template = template(
+            r#"{
+                window name="edit" { split direction="columns" { pane; pane } }
+                window name="logs" cwd="/var/log"
+            }"#,
+        );
+        assert_eq!(template.name, "default_session");
+        assert_eq!(template.cwd, "~");
+        let names = template.windows.iter().map(|w| w.name.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), ["edit", "logs"]);
+        assert_eq!(pane_cwds(&template), [vec!["~", "~"], vec!["/var/log"]]);
+
+        assert!(parse_muffin_config("").unwrap().0.default_session.is_none());
+    }
+
+    #[test]
+    fn malformed_default_sessions_are_left_out() {
+        for (template, error) in [
+            (r#"default_session name="x""#, "leave out `name`"),
+            (r#"default_session { window index=0 }"#, "must be 1 or more"),
+            (
+                r#"default_session { vars { project } }"#,
+                "nothing to ask for `vars`",
+            ),
+        ] {
+            // The rest of the config is still read
+            let doc = format!("exit_on_switch #true\n{template}\ntheme \"light\"");
+            let (config, diagnostics) = parse_muffin_config(&doc).unwrap();
+            assert!(config.default_session.is_none(), "{template}");
+            assert!(config.exit_on_switch);
+            assert_eq!(config.theme.as_deref(), Some("light"));
+
+            assert_eq!(diagnostics.len(), 1, "{template}: {diagnostics:?}");
+            let diagnostic = &diagnostics[0];
+            assert!(diagnostic.message.contains(error), "{diagnostic:?}");
+            assert!(
+                diagnostic.message.contains("created with a single pane"),
+                "{diagnostic:?}"
+            );
+            assert_eq!(diagnostic.location, Some((2, 1)));
+        }
+    }
+
+    #[test]
+    fn templates_are_named_and_moved_to_the_given_cwd() {
+        let template = template(
+            r#"{
+                window name="edit" { split { pane; pane cwd="/etc" } }
+                window name="logs" cwd="/var/log"
+            }"#,
+        );
+
+        let preset = from_template(&template, "api", Some("/srv/api"));
+        assert_eq!(preset.name, "api");
+        assert_eq!(preset.cwd, "/srv/api");
+        assert_eq!(preset.windows[0].cwd, "/srv/api");
+        // Directories of their own are kept
+        assert_eq!(preset.windows[1].cwd, "/var/log");
+        assert_eq!(
+            pane_cwds(&preset),
+            [vec!["/srv/api", "/etc"], vec!["/var/log"]]
+        );
+        // The template is left for the next session
+        assert_eq!(template.name, "default_session");
+        assert_eq!(pane_cwds(&template), [vec!["~", "/etc"], vec!["/var/log"]]);
+
+        // Without a cwd, it stays where the template says
+        let preset = from_template(&template, "web", None);
+        assert_eq!(preset.name, "web");
+        assert_eq!(preset.cwd, "~");
+        assert_eq!(pane_cwds(&preset), pane_cwds(&template));
+    }
+
+    #[test]
+    fn templates_can_inherit_the_current_cwd() {
+        let template = template(r#"{ window { split { pane; pane cwd="/etc" } } }"#);
+        let preset = from_template(&template, "api", Some(INHERIT_CWD));
+        assert_eq!(preset.cwd, INHERIT_CWD);
+        assert_eq!(preset.windows[0].cwd, INHERIT_CWD);
+        assert_eq!(pane_cwds(&preset), [vec![INHERIT_CWD, "/etc"]]);
+    }
+}
@@ -0,0 +1,201 @@
+use tmux::INHERIT_CWD;
+
+use crate::diagnostic::Diagnostic;
+
+/// The directories a session's relative cwds resolve against, see [`CwdScope::resolve`]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CwdScope<'a> {
+    /// The preset, named in warnings
+    pub preset: &'a str,
+    /// The session's cwd, which windows and panes aren't expected to climb out of
+    pub session: &'a str,
+    /// The nearest ancestor's own cwd, never [`INHERIT_CWD`]
+    pub parent: &'a str,
+}
+
+impl<'a> CwdScope<'a> {
+    /// The scope of the children of a node in `cwd`
+    pub fn within(self, cwd: &'a str) -> Self {
+        Self {
+            parent: cwd,
+            ..self
+        }
+    }
+
+    /// `cwd` of a window or pane resolved against the parent's, see [`resolve_cwd`]. Relative
+    /// ones whose `..` climb out of the session's cwd are warned about, as that's rarely meant.
+    pub fn resolve(self, cwd: &str, warnings: &mut Vec<Diagnostic>) -> String {
+        let resolved = resolve_cwd(cwd, self.parent);
+        if is_relative(cwd)
+            && is_within(self.parent, self.session)
+            && !is_within(&resolved, self.session)
+        {
+            warnings.push(Diagnostic::warning(format!(
+                "`cwd=\"{cwd}\"` in preset `{}` resolves to `{resolved}`, outside the session's \
+                 `{}`",
+                self.preset, self.session
+            )));
+        }
+        resolved
+    }
+}
+
+/// `cwd` as an absolute path, with relative ones (`backend`, `./api`, `../lib`) taken from
+/// `base` and `.` and `..` worked out.
+///
+/// Paths starting with `~`, a placeholder, a shell parameter or a tmux format are only known at
+/// launch (on the machine the session runs on, for `~`), so they stay anchored to where they
+/// start. [`INHERIT_CWD`] is left for tmux.
+pub(crate) fn resolve_cwd(cwd: &str, base: &str) -> String {
+    if cwd == INHERIT_CWD {
+        return cwd.to_string();
+    }
+    match is_relative(cwd) {
+        true => normalize(&format!("{base}/{cwd}")),
+        false => normalize(cwd),
+    }
+}
+
+fn is_relative(cwd: &str) -> bool {
+    !cwd.starts_with(['/', '~', '$', '{', '#'])
+}
+
+/// `path` without `.`, `..` that can be worked out, repeated and trailing slashes. The first
+/// component of a path that doesn't start at `/` stands for a directory not known yet, which
+/// `..` doesn't climb out of.
+fn normalize(path: &str) -> String {
+    let rooted = path.starts_with('/');
+    let mut parts = Vec::<&str>::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => match parts.last() {
+                Some(&last) if last != ".." && (rooted || parts.len() > 1) => {
+                    parts.pop();
+                }
+                // There's nothing above `/`
+                None if rooted => {}
+                _ => parts.push(".."),
+            },
+            part => parts.push(part),
+        }
+    }
+    match rooted {
+        true => format!("/{}", parts.join("/")),
+        false => parts.join("/"),
+    }
+}
+
+/// Whether `path` is `dir` or somewhere inside it, both normalized
+fn is_within(path: &str, dir: &str) -> bool {
+    path == dir
+        || dir == "/" && path.starts_with('/')
+        || path
+            .strip_prefix(dir)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_works_out_dots_and_slashes() {
+        for (path, normalized) in [
+            ("/a/./b//c/", "/a/b/c"),
+            ("/a/b/../c", "/a/c"),
+            ("/a/../../b", "/b"),
+            ("/..", "/"),
+            ("/", "/"),
+            ("~/a/../b", "~/b"),
+            // What `~` or a placeholder stands for isn't known, so `..` can't climb out of it
+            ("~/..", "~/.."),
+            ("~/a/../../b", "~/../b"),
+            ("{root}/../x", "{root}/../x"),
+            ("$DIR/a/..", "$DIR"),
+        ] {
+            assert_eq!(normalize(path), normalized, "{path}");
+        }
+    }
+
+    #[test]
+    fn is_within_compares_whole_components() {
+        assert!(is_within("/a/b", "/a/b"));
+        assert!(is_within("/a/b/c", "/a/b"));
+        assert!(!is_within("/a/bc", "/a/b"));
+        assert!(!is_within("/a", "/a/b"));
+        assert!(is_within("/anything", "/"));
+        assert!(is_within("~/src/x", "~"));
+        assert!(is_within("~", "~"));
+        assert!(!is_within("~/..", "~/src"));
+        assert!(!is_within("/home/me/src", "~"));
+    }
+
+    #[test]
+    fn resolve_cwd_keeps_home_and_anchors_for_launch() {
+        for (cwd, base, resolved) in [
+            ("/srv/app", "~/src", "/srv/app"),
+            ("~", "/srv", "~"),
+            ("~/proj/", "/srv", "~/proj"),
+            ("~other/proj", "/srv", "~other/proj"),
+            ("backend", "~/src", "~/src/backend"),
+            ("./api", "/srv/app", "/srv/app/api"),
+            ("../lib", "~/src/app", "~/src/lib"),
+            ("../..", "~/src", "~/.."),
+            (".", "/srv", "/srv"),
+            ("$PROJECT/web", "/srv", "$PROJECT/web"),
+            ("{dir}/web", "/srv", "{dir}/web"),
+            ("#{pane_current_path}", "/srv", "#{pane_current_path}"),
+            (INHERIT_CWD, "/srv", INHERIT_CWD),
+        ] {
+            assert_eq!(resolve_cwd(cwd, base), resolved, "{cwd} in {base}");
+        }
+    }
+
+    fn resolve(scope: CwdScope, cwd: &str) -> (String, Vec<Diagnostic>) {
+        let mut warnings = vec![];
+        (scope.resolve(cwd, &mut warnings), warnings)
+    }
+
+    #[test]
+    fn scope_warns_only_about_relative_cwds_leaving_the_session() {
+        let session = CwdScope {
+            preset: "p",
+            session: "~/src/app",
+            parent: "~/src/app",
+        };
+        let window = session.within("~/src/app/web");
+
+        assert_eq!(
+            resolve(session, "api"),
+            ("~/src/app/api".to_string(), vec![])
+        );
+        assert_eq!(resolve(window, ".."), ("~/src/app".to_string(), vec![]));
+        // Absolute and `~` paths elsewhere are taken to be meant
+        assert!(resolve(session, "/tmp").1.is_empty());
+        assert!(resolve(window, "~/notes").1.is_empty());
+
+        let (resolved, warnings) = resolve(session, "../lib");
+        assert_eq!(resolved, "~/src/lib");
+        assert_eq!(
+            warnings,
+            vec![Diagnostic::warning(
+                "`cwd=\"../lib\"` in preset `p` resolves to `~/src/lib`, outside the session's \
+                 `~/src/app`"
+            )]
+        );
+        let (resolved, warnings) = resolve(window, "../../x");
+        assert_eq!(resolved, "~/src/x");
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn scope_doesnt_warn_below_a_parent_already_outside_the_session() {
+        let scope = CwdScope {
+            preset: "p",
+            session: "/srv/app",
+            parent: "/tmp/scratch",
+        };
+        assert_eq!(resolve(scope, "../x"), ("/tmp/x".to_string(), vec![]));
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+
+use crate::Source;
+
+/// How much a [`Diagnostic`] matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Something muffin worked around on its own, like renaming a duplicate window
+    Note,
+    /// Something likely not meant, like an attribute muffin doesn't know and ignores
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem with a presets file that didn't prevent it from loading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The file it's in (or `<stdin>`, or `<inline>`), once known. Only the name is kept, as a
+    /// document piped in would otherwise be copied into each of its diagnostics
+    pub file: Option<String>,
+    /// Line and column in that file, both counted from 1
+    pub location: Option<(usize, usize)>,
+}
+
+impl Diagnostic {
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            file: None,
+            location: None,
+        }
+    }
+
+    pub fn note(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Note,
+            ..Self::warning(message)
+        }
+    }
+
+    /// Points the diagnostic at byte `offset` of `doc_str`
+    pub(crate) fn at(mut self, doc_str: &str, offset: usize) -> Self {
+        self.location = Some(line_column(doc_str, offset));
+        self
+    }
+
+    pub(crate) fn in_source(mut self, source: &Source) -> Self {
+        self.file = Some(source.to_string());
+        self
+    }
+
+    /// Where it is, ex: `presets.kdl: line 3, column 5` or `<stdin>: line 1, column 9`, empty if
+    /// that isn't known
+    pub fn source(&self) -> String {
+        let file = self.file.clone();
+        let location = self
+            .location
+            .map(|(line, column)| format!("line {line}, column {column}"));
+        [file, location]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(": ")
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.source().as_str() {
+            "" => write!(f, "{}", self.message),
+            source => write!(f, "{source}: {}", self.message),
+        }
+    }
+}
+
+/// Line and column of byte `offset` in `doc_str`, both counted from 1
+pub(crate) fn line_column(doc_str: &str, offset: usize) -> (usize, usize) {
+    let before = &doc_str[..offset.min(doc_str.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
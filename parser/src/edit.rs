@@ -0,0 +1,409 @@
+use std::path::Path;
+
+use kdl::{KdlDocument, KdlEntry, KdlNode};
+use tmux::Size;
+
+use crate::{parse_size, session_name};
+
+/// A change to a single preset in a presets file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresetEdit {
+    Rename {
+        from: String,
+        to: String,
+    },
+    Delete {
+        name: String,
+    },
+    /// Sets the sizes of the panes and splits in some of the preset's windows. Windows are given
+    /// by position, with their sizes in the order they're listed (depth first, the root left out),
+    /// see [`tmux::measure_layout`].
+    Resize {
+        name: String,
+        windows: Vec<(usize, Vec<Size>)>,
+    },
+}
+
+/// Applies `edit` to the presets document in `doc_str`, returning the new document.
+///
+/// The document is edited in place, so comments and formatting of every untouched node survive.
+pub fn apply_edit(doc_str: &str, edit: &PresetEdit) -> Result<String, String> {
+    let mut doc: KdlDocument = doc_str
+        .parse()
+        .map_err(|_| "Error parsing file".to_string())?;
+
+    match edit {
+        PresetEdit::Rename { from, to } => {
+            if to.is_empty() {
+                return Err("Preset name can't be empty".to_string());
+            }
+            if from != to && doc.nodes().iter().any(|node| is_session(node, to)) {
+                return Err(format!("Preset '{to}' already exists"));
+            }
+
+            let entry = doc
+                .nodes_mut()
+                .iter_mut()
+                .find(|node| is_session(node, from))
+                .and_then(|node| node.entry_mut("name"))
+                .ok_or(format!("Preset '{from}' not found"))?;
+
+            entry.set_value(to.as_str());
+            // Keep the surrounding whitespace, only the value itself needs re-rendering
+            if let Some(format) = entry.format_mut() {
+                format.value_repr = quote(to);
+            }
+        }
+        PresetEdit::Delete { name } => {
+            let before = doc.nodes().len();
+            doc.nodes_mut().retain(|node| !is_session(node, name));
+            if doc.nodes().len() == before {
+                return Err(format!("Preset '{name}' not found"));
+            }
+        }
+        PresetEdit::Resize { name, windows } => {
+            let session = doc
+                .nodes_mut()
+                .iter_mut()
+                .find(|node| is_session(node, name))
+                .ok_or(format!("Preset '{name}' not found"))?;
+            let mut window_nodes = session
+                .children_mut()
+                .as_mut()
+                .map(|children| children.nodes_mut().iter_mut().collect::<Vec<_>>())
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|node| node.name().value() == "window")
+                .collect::<Vec<_>>();
+
+            for (index, sizes) in windows {
+                let window = window_nodes
+                    .get_mut(*index)
+                    .ok_or(format!("Preset '{name}' has no window {}", index + 1))?;
+                // Without a root node, the window is a single pane with nothing to size
+                if let Some(root) = layout_children(window).into_iter().next() {
+                    resize_children(root, &mut sizes.iter());
+                }
+            }
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Applies `edit` to the presets file at `path`.
+///
+/// The new contents are written to a temporary file next to it and renamed over the original, so
+/// the file is never left half-written.
+pub fn edit_presets_file(path: &Path, edit: &PresetEdit) -> Result<(), String> {
+    let doc_str = std::fs::read_to_string(path)
+        .map_err(|e| format!("Could not read from '{}': {e}", path.display()))?;
+    let new_doc = apply_edit(&doc_str, edit)?;
+
+    // Write through symlinks (ex: presets managed by a dotfiles repo) instead of replacing them
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = path.as_path();
+    let file_name = path
+        .file_name()
+        .ok_or(format!("'{}' is not a file", path.display()))?;
+    let tmp_path = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    std::fs::write(&tmp_path, new_doc)
+        .and_then(|_| std::fs::rename(&tmp_path, path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&tmp_path);
+            format!("Could not write to '{}': {e}", path.display())
+        })
+}
+
+/// Gives every `pane` and `split` below `node` the next of `sizes`, depth first
+fn resize_children<'a>(node: &mut KdlNode, sizes: &mut impl Iterator<Item = &'a Size>) {
+    for child in layout_children(node) {
+        if let Some(size) = sizes.next() {
+            set_size(child, *size);
+        }
+        resize_children(child, sizes);
+    }
+}
+
+fn layout_children(node: &mut KdlNode) -> Vec<&mut KdlNode> {
+    node.children_mut()
+        .as_mut()
+        .map(|children| children.nodes_mut().iter_mut().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|child| matches!(child.name().value(), "pane" | "split"))
+        .collect()
+}
+
+/// Rewrites `node`'s `size` or `size_cells`, unless it already says as much (in whichever form)
+fn set_size(node: &mut KdlNode, size: Size) {
+    if parse_size(node) == Ok(size) {
+        return;
+    }
+    node.entries_mut().retain(|entry| {
+        entry
+            .name()
+            .is_none_or(|name| !matches!(name.value(), "size" | "size_cells"))
+    });
+    match size {
+        Size::Percent(percent) => node
+            .entries_mut()
+            .push(KdlEntry::new_prop("size", percent.get() as i128)),
+        Size::Cells(cells) => node
+            .entries_mut()
+            .push(KdlEntry::new_prop("size_cells", cells as i128)),
+        Size::Auto => {}
+    }
+}
+
+fn is_session(node: &KdlNode, name: &str) -> bool {
+    node.name().value() == "session" && session_name(node).as_deref() == Some(name)
+}
+
+/// Renders `s` as a quoted KDL string, even where a bare identifier would be valid, to match how
+/// presets files are usually written
+fn quote(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"// Work projects
+session   name="api" cwd="~/api" {
+    window name="edit" // where the code goes
+}
+
+/* kept as is */
+session name=web {
+    window   name="logs"
+}
+session name="db"
+"#;
+
+    fn rename(from: &str, to: &str) -> PresetEdit {
+        PresetEdit::Rename {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    fn delete(name: &str) -> PresetEdit {
+        PresetEdit::Delete {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn renaming_only_touches_the_name() {
+        assert_eq!(
+            apply_edit(DOC, &rename("api", "backend")).unwrap(),
+            DOC.replace(r#"name="api""#, r#"name="backend""#)
+        );
+        // Written bare, it comes back quoted like the others
+        assert_eq!(
+            apply_edit(DOC, &rename("web", "front")).unwrap(),
+            DOC.replace("name=web", r#"name="front""#)
+        );
+        // Renaming to its own name changes nothing
+        assert_eq!(apply_edit(DOC, &rename("db", "db")).unwrap(), DOC);
+    }
+
+    #[test]
+    fn names_are_quoted_as_needed() {
+        for (to, written) in [
+            ("my api", r#""my api""#),
+            (r#"say "hi""#, r#""say \"hi\"""#),
+            ("#true", r##""#true""##),
+            ("1", r#""1""#),
+        ] {
+            let doc = apply_edit(DOC, &rename("db", to)).unwrap();
+            assert_eq!(doc, DOC.replace(r#""db""#, written), "{to}");
+            // And read back as they were given
+            let (presets, _) = crate::parse_config(&doc).unwrap();
+            assert!(presets.contains_key(to), "{to}: {doc}");
+        }
+    }
+
+    #[test]
+    fn renames_are_refused_rather_than_clobbering() {
+        assert_eq!(
+            apply_edit(DOC, &rename("api", "web")).unwrap_err(),
+            "Preset 'web' already exists"
+        );
+        assert_eq!(
+            apply_edit(DOC, &rename("nope", "other")).unwrap_err(),
+            "Preset 'nope' not found"
+        );
+        assert_eq!(
+            apply_edit(DOC, &rename("api", "")).unwrap_err(),
+            "Preset name can't be empty"
+        );
+    }
+
+    #[test]
+    fn deleting_leaves_the_other_nodes_as_written() {
+        let doc = apply_edit(DOC, &delete("api")).unwrap();
+        assert!(!doc.contains("~/api"), "{doc}");
+        assert!(
+            doc.ends_with(&DOC[DOC.find("/* kept as is */").unwrap()..]),
+            "{doc}"
+        );
+
+        let doc = apply_edit(DOC, &delete("db")).unwrap();
+        assert!(
+            doc.starts_with(&DOC[..DOC.find(r#"session name="db""#).unwrap()]),
+            "{doc}"
+        );
+        assert!(!doc.contains(r#""db""#), "{doc}");
+
+        assert_eq!(
+            apply_edit(DOC, &delete("nope")).unwrap_err(),
+            "Preset 'nope' not found"
+        );
+        assert_eq!(
+            apply_edit("session {", &delete("api")).unwrap_err(),
+            "Error parsing file"
+        );
+    }
+
+    /// A directory of the test's own, removed first in case a previous run left it
+    fn scratch(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("muffin-edit-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_are_edited_through_symlinks() {
+        let dir = scratch("symlink");
+        let target = dir.join("dotfiles.kdl");
+        let link = dir.join("presets.kdl");
+        std::fs::write(&target, DOC).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        edit_presets_file(&link, &rename("api", "backend")).unwrap();
+        assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            DOC.replace(r#"name="api""#, r#"name="backend""#)
+        );
+        // Refused edits leave it alone
+        assert!(edit_presets_file(&link, &delete("api")).is_err());
+
+        let mut files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, ["dotfiles.kdl", "presets.kdl"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    const SPLITS: &str = r#"session name="proj" {
+    // the editor
+    window name="edit" {
+        split direction="columns" {
+            pane size=60 // main
+            split {
+                pane size="50%"
+                pane
+            }
+        }
+    }
+    window name="shell"
+    window name="logs" {
+        split { pane; pane size_cells=5; }
+    }
+}
+"#;
+
+    fn percent(value: i64) -> Size {
+        Size::Percent(value.try_into().unwrap())
+    }
+
+    fn resize(windows: Vec<(usize, Vec<Size>)>) -> PresetEdit {
+        PresetEdit::Resize {
+            name: "proj".to_string(),
+            windows,
+        }
+    }
+
+    #[test]
+    fn sizes_are_written_back_depth_first() {
+        let doc = apply_edit(
+            SPLITS,
+            &resize(vec![(
+                0,
+                vec![percent(70), Size::Auto, percent(50), Size::Cells(10)],
+            )]),
+        )
+        .unwrap();
+        // The split's own size was already left out, and 50% said the same as "50%"
+        assert_eq!(
+            doc,
+            SPLITS
+                .replace("size=60 // main", "size=70 // main")
+                .replace(
+                    "                pane\n",
+                    "                pane size_cells=10\n"
+                )
+        );
+    }
+
+    #[test]
+    fn bare_windows_are_skipped_and_missing_ones_refused() {
+        let doc = apply_edit(
+            SPLITS,
+            &resize(vec![
+                (1, vec![percent(40)]),
+                (2, vec![percent(30), Size::Auto]),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            doc,
+            SPLITS.replace(
+                "split { pane; pane size_cells=5; }",
+                "split { pane size=30; pane; }"
+            )
+        );
+
+        // Counted from 0 in the edit, from 1 in what's said about it
+        assert_eq!(
+            apply_edit(SPLITS, &resize(vec![(3, vec![percent(40)])])).unwrap_err(),
+            "Preset 'proj' has no window 4"
+        );
+        assert_eq!(
+            apply_edit(
+                SPLITS,
+                &PresetEdit::Resize {
+                    name: "nope".to_string(),
+                    windows: vec![],
+                }
+            )
+            .unwrap_err(),
+            "Preset 'nope' not found"
+        );
+    }
+}
@@ -0,0 +1,560 @@
+use serde_yaml::{Mapping, Value};
+use tmux::{Layout, Preset, Window, WindowBuilder};
+
+/// Session managers whose project files can be turned into presets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Tmuxinator,
+    Tmuxp,
+}
+
+impl ImportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tmuxinator" => Some(ImportFormat::Tmuxinator),
+            "tmuxp" => Some(ImportFormat::Tmuxp),
+            _ => None,
+        }
+    }
+}
+
+/// Keys of either format with nothing to map them onto
+const SKIPPED: &[&str] = &[
+    // tmuxinator
+    "on_project_start",
+    "on_project_first_start",
+    "on_project_restart",
+    "on_project_exit",
+    "on_project_stop",
+    "pre",
+    "post",
+    "tmux_options",
+    "tmux_command",
+    "socket_name",
+    "startup_window",
+    "startup_pane",
+    "attach",
+    "enable_pane_titles",
+    "pane_title_position",
+    "pane_title_format",
+    // tmuxp
+    "before_script",
+    "global_options",
+    "environment",
+    "plugins",
+    "suppress_history",
+];
+
+/// Reads a tmuxinator or tmuxp project file into a preset, along with warnings about everything
+/// in it that couldn't be carried over
+pub fn import_preset(yaml: &str, format: ImportFormat) -> Result<(Preset, Vec<String>), String> {
+    let mut warnings = vec![];
+    if yaml.contains("<%") {
+        warnings.push("ERB templating isn't supported, `<% %>` tags are kept as is".to_string());
+    }
+
+    let doc = serde_yaml::from_str::<Value>(yaml).map_err(|e| format!("Invalid YAML: {e}"))?;
+    let doc = doc
+        .as_mapping()
+        .ok_or("Expected a mapping at the top of the file")?;
+    let keys = match format {
+        ImportFormat::Tmuxinator => Keys::TMUXINATOR,
+        ImportFormat::Tmuxp => Keys::TMUXP,
+    };
+
+    let name = doc
+        .get(keys.name)
+        .and_then(scalar)
+        .ok_or(format!("Missing `{}`", keys.name))?;
+    let mut preset = Preset::builder(&name);
+    if let Some(cwd) = doc.get(keys.root).and_then(scalar) {
+        preset = preset.cwd(cwd);
+    }
+    let before = commands(doc.get(keys.before));
+    for (name, value) in options(
+        keys.options.and_then(|key| doc.get(key)),
+        "the session",
+        &mut warnings,
+    ) {
+        preset = preset.option(name, value);
+    }
+
+    for (key, _) in doc {
+        let key = key.as_str().unwrap_or_default();
+        if SKIPPED.contains(&key) {
+            warnings.push(format!("`{key}` has no equivalent in muffin, skipped"));
+        } else if ![keys.name, keys.root, keys.before, "windows", "tabs"].contains(&key)
+            && keys.options != Some(key)
+        {
+            warnings.push(format!("Unknown key `{key}`, skipped"));
+        }
+    }
+
+    let windows = doc
+        .get("windows")
+        .or_else(|| doc.get("tabs"))
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default();
+    for (index, window) in windows.iter().enumerate() {
+        let window = match format {
+            ImportFormat::Tmuxinator => tmuxinator_window(window, &before, &mut warnings),
+            ImportFormat::Tmuxp => tmuxp_window(window, &before, &mut warnings),
+        };
+        match window {
+            Some(window) => preset = preset.window(window),
+            None => warnings.push(format!("Window {} isn't understood, skipped", index + 1)),
+        }
+    }
+
+    let preset = preset.build().map_err(String::from)?;
+    Ok((preset, warnings))
+}
+
+/// Where the two formats keep the same things
+struct Keys {
+    name: &'static str,
+    root: &'static str,
+    /// Commands run in every pane before its own
+    before: &'static str,
+    /// Session options, which only tmuxp has
+    options: Option<&'static str>,
+}
+
+impl Keys {
+    const TMUXINATOR: Keys = Keys {
+        name: "name",
+        root: "root",
+        before: "pre_window",
+        options: None,
+    };
+    const TMUXP: Keys = Keys {
+        name: "session_name",
+        root: "start_directory",
+        before: "shell_command_before",
+        options: Some("options"),
+    };
+}
+
+/// ex: `- editor: vim`, or
+/// ```yaml
+/// - editor:
+///     layout: main-vertical
+///     root: ~/src
+///     panes: [vim, guard]
+/// ```
+fn tmuxinator_window(
+    window: &Value,
+    before: &[String],
+    warnings: &mut Vec<String>,
+) -> Option<WindowBuilder> {
+    let (name, body) = window.as_mapping()?.iter().next()?;
+    let name = window_name(&scalar(name)?, warnings);
+
+    let Some(body) = body.as_mapping() else {
+        // A single pane running whatever is given, if anything
+        let command = join_commands(before, &commands(Some(body)));
+        return Some(Window::builder(name).layout(pane(command)));
+    };
+
+    let mut window = Window::builder(&name);
+    if let Some(root) = body.get("root").and_then(scalar) {
+        window = window.cwd(root);
+    }
+    let before = [before, &commands(body.get("pre"))].concat();
+    let panes = body
+        .get("panes")
+        .and_then(Value::as_sequence)
+        .map(|panes| {
+            panes
+                .iter()
+                // Panes can be named, ex: `- logs: tail -f log`, the name goes unused
+                .map(|pane| match pane.as_mapping() {
+                    Some(named) => commands(named.values().next()),
+                    None => commands(Some(pane)),
+                })
+                .map(|commands| join_commands(&before, &commands))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_else(|| vec![join_commands(&before, &[])]);
+
+    warn_unknown(
+        body,
+        &["root", "pre", "panes", "layout", "synchronize"],
+        &name,
+        warnings,
+    );
+    if body
+        .get("synchronize")
+        .is_some_and(|sync| sync != &Value::Bool(false))
+    {
+        window = window.option("synchronize-panes", "on");
+    }
+    let layout = body.get("layout").and_then(scalar);
+    Some(window.layout(arrange(panes, layout.as_deref(), &name, warnings)))
+}
+
+/// ex:
+/// ```yaml
+/// - window_name: editor
+///   layout: main-vertical
+///   start_directory: ~/src
+///   panes:
+///     - vim
+///     - shell_command: [cd src, make watch]
+/// ```
+fn tmuxp_window(
+    window: &Value,
+    before: &[String],
+    warnings: &mut Vec<String>,
+) -> Option<WindowBuilder> {
+    let window = window.as_mapping()?;
+    let name = window
+        .get("window_name")
+        .and_then(scalar)
+        .unwrap_or_else(|| "win".to_string());
+    let name = window_name(&name, warnings);
+
+    let mut builder = Window::builder(&name);
+    if let Some(root) = window.get("start_directory").and_then(scalar) {
+        builder = builder.cwd(root);
+    }
+    let before = [before, &commands(window.get("shell_command_before"))].concat();
+    let panes = window
+        .get("panes")
+        .and_then(Value::as_sequence)
+        .map(|panes| {
+            panes
+                .iter()
+                .map(|pane| match pane {
+                    Value::Mapping(pane) => commands(pane.get("shell_command")),
+                    // tmuxp's spellings of a pane that runs nothing of its own
+                    Value::String(s) if s == "blank" || s == "pane" => vec![],
+                    pane => commands(Some(pane)),
+                })
+                .map(|commands| join_commands(&before, &commands))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_else(|| vec![join_commands(&before, &[])]);
+
+    warn_unknown(
+        window,
+        &[
+            "window_name",
+            "start_directory",
+            "shell_command_before",
+            "panes",
+            "layout",
+            "focus",
+            "options",
+        ],
+        &name,
+        warnings,
+    );
+    let context = format!("window `{name}`");
+    for (option, value) in options(window.get("options"), &context, warnings) {
+        builder = builder.option(option, value);
+    }
+    let layout = window.get("layout").and_then(scalar);
+    Some(builder.layout(arrange(panes, layout.as_deref(), &name, warnings)))
+}
+
+/// Lays out `panes` like tmux's builtin layout of the given name would, or evenly for the ones
+/// that can't be expressed as splits
+fn arrange(
+    panes: Vec<Option<String>>,
+    layout: Option<&str>,
+    window: &str,
+    warnings: &mut Vec<String>,
+) -> Layout {
+    let mut panes = panes.into_iter().map(pane).collect::<Vec<_>>();
+    if panes.len() <= 1 {
+        return panes.pop().unwrap_or_else(Layout::pane);
+    }
+
+    match layout {
+        Some("even-horizontal") => Layout::hsplit(panes),
+        None | Some("even-vertical") => Layout::vsplit(panes),
+        // The main pane takes the left or top, the others share the rest
+        Some("main-vertical") => {
+            let rest = panes.split_off(1);
+            Layout::hsplit([panes.remove(0), Layout::vsplit(rest)])
+        }
+        Some("main-horizontal") => {
+            let rest = panes.split_off(1);
+            Layout::vsplit([panes.remove(0), Layout::hsplit(rest)])
+        }
+        // Rows of two
+        Some("tiled") => {
+            let mut rows = vec![];
+            while !panes.is_empty() {
+                let mut row = panes.drain(..panes.len().min(2)).collect::<Vec<_>>();
+                rows.push(match row.len() {
+                    1 => row.remove(0),
+                    _ => Layout::hsplit(row),
+                });
+            }
+            Layout::vsplit(rows)
+        }
+        Some(layout) => {
+            warnings.push(format!(
+                "Window `{window}` has layout `{layout}`, which muffin can't reproduce, \
+                 stacking its panes evenly instead"
+            ));
+            Layout::vsplit(panes)
+        }
+    }
+}
+
+fn pane(command: Option<String>) -> Layout {
+    match command {
+        Some(command) => Layout::pane().cmd(command),
+        None => Layout::pane(),
+    }
+}
+
+/// tmux reads `:` and `.` as separators in targets, so they can't be in window names
+fn window_name(name: &str, warnings: &mut Vec<String>) -> String {
+    if !name.contains([':', '.']) {
+        return name.to_string();
+    }
+    let renamed = name.replace([':', '.'], "_");
+    warnings.push(format!("Window `{name}` renamed to `{renamed}`"));
+    renamed
+}
+
+fn warn_unknown(map: &Mapping, known: &[&str], window: &str, warnings: &mut Vec<String>) {
+    for key in map.keys().filter_map(Value::as_str) {
+        if !known.contains(&key) {
+            warnings.push(format!(
+                "Window `{window}`: `{key}` isn't supported, skipped"
+            ));
+        }
+    }
+}
+
+/// tmuxp's `options`, ex: `options: {automatic-rename: on}`. Booleans stand for `on` and `off`.
+fn options(
+    value: Option<&Value>,
+    context: &str,
+    warnings: &mut Vec<String>,
+) -> Vec<(String, String)> {
+    let Some(map) = value.and_then(Value::as_mapping) else {
+        return vec![];
+    };
+    let mut options = vec![];
+    for (name, value) in map {
+        let value = match value {
+            Value::Bool(true) => Some("on".to_string()),
+            Value::Bool(false) => Some("off".to_string()),
+            value => scalar(value),
+        };
+        match (scalar(name), value) {
+            (Some(name), Some(value)) => options.push((name, value)),
+            _ => warnings.push(format!("An option of {context} isn't understood, skipped")),
+        }
+    }
+    options
+}
+
+/// Commands given as one string or a list of them, blank ones left out
+fn commands(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Sequence(commands)) => commands.iter().filter_map(scalar).collect(),
+        Some(value) => scalar(value).into_iter().collect(),
+        None => vec![],
+    }
+    .into_iter()
+    .filter(|command| !command.trim().is_empty())
+    .collect()
+}
+
+/// What a pane runs, one command after the other
+fn join_commands(before: &[String], commands: &[String]) -> Option<String> {
+    let all = [before, commands].concat();
+    (!all.is_empty()).then(|| all.join("; "))
+}
+
+/// Strings, numbers and booleans as text, as YAML doesn't insist on quoting them
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tmux::{LayoutNode, SplitDirection};
+
+    const TMUXINATOR: &str = include_str!("../tests/fixtures/tmuxinator.yml");
+    const TMUXP: &str = include_str!("../tests/fixtures/tmuxp.yaml");
+
+    /// `layout` written out with splits as `h(..)` or `v(..)` and panes as their command, `-`
+    /// for a shell
+    fn shape(layout: &LayoutNode) -> String {
+        match layout {
+            LayoutNode::Pane { command, .. } => command.clone().unwrap_or("-".to_string()),
+            LayoutNode::Split {
+                direction,
+                children,
+                ..
+            } => {
+                let direction = match direction {
+                    SplitDirection::Horizontal => "h",
+                    SplitDirection::Vertical => "v",
+                };
+                let children = children.iter().map(shape).collect::<Vec<_>>();
+                format!("{direction}({})", children.join(", "))
+            }
+        }
+    }
+
+    fn windows(preset: &Preset) -> Vec<(&str, &str, String)> {
+        preset
+            .windows
+            .iter()
+            .map(|w| (w.name.as_str(), w.cwd.as_str(), shape(&w.layout)))
+            .collect()
+    }
+
+    fn option(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn tmuxinator_project() {
+        let (preset, warnings) = import_preset(TMUXINATOR, ImportFormat::Tmuxinator).unwrap();
+        assert_eq!(preset.name, "blog");
+        assert_eq!(preset.cwd, "~/src/blog");
+        assert_eq!(
+            windows(&preset),
+            [
+                (
+                    "editor",
+                    "~/src/blog",
+                    "h(nvm use 18; vim, v(nvm use 18; guard, nvm use 18; tail -f \
+                     log/development.log))"
+                        .to_string()
+                ),
+                (
+                    "server",
+                    "~/src/blog",
+                    "nvm use 18; bundle exec rails s".to_string()
+                ),
+                ("shell", "~/src/blog", "nvm use 18".to_string()),
+                (
+                    "db_console",
+                    "~/src/blog/db",
+                    "v(h(nvm use 18; cd migrations; psql, nvm use 18; cd migrations; ls), \
+                     nvm use 18; cd migrations; git status)"
+                        .to_string()
+                ),
+                (
+                    "deploy",
+                    "~/src/blog",
+                    "v(nvm use 18; cap staging deploy, nvm use 18; cap production deploy)"
+                        .to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            preset.windows[3].options,
+            [option("synchronize-panes", "on")]
+        );
+        assert_eq!(
+            warnings,
+            [
+                "`startup_window` has no equivalent in muffin, skipped",
+                "`attach` has no equivalent in muffin, skipped",
+                "Window `db.console` renamed to `db_console`",
+                "Window `deploy`: `unknown` isn't supported, skipped",
+                "Window `deploy` has layout `5f2a,200x50,0,0`, which muffin can't reproduce, \
+                 stacking its panes evenly instead",
+            ]
+        );
+    }
+
+    #[test]
+    fn tmuxp_project() {
+        let (preset, warnings) = import_preset(TMUXP, ImportFormat::Tmuxp).unwrap();
+        let activate = "source .venv/bin/activate";
+        assert_eq!(preset.name, "api");
+        assert_eq!(preset.cwd, "~/src/api");
+        assert_eq!(
+            preset.options,
+            [option("mouse", "on"), option("status-position", "top")]
+        );
+        assert_eq!(
+            windows(&preset),
+            [
+                (
+                    "editor",
+                    "~/src/api",
+                    format!("v({activate}; vim, h({activate}; cd tests; pytest -f, {activate}))")
+                ),
+                (
+                    "worker",
+                    "~/src/api/worker",
+                    format!(
+                        "h({activate}; export QUEUE=default; celery worker, \
+                         {activate}; export QUEUE=default)"
+                    )
+                ),
+                ("shell", "~/src/api", activate.to_string()),
+                ("win", "~/src/api", format!("{activate}; htop")),
+            ]
+        );
+        assert_eq!(
+            preset.windows[1].options,
+            [option("automatic-rename", "off")]
+        );
+        assert_eq!(
+            warnings,
+            ["`environment` has no equivalent in muffin, skipped"]
+        );
+    }
+
+    #[test]
+    fn tmuxp_blank_panes_run_nothing() {
+        let yaml = "session_name: x\nwindows:\n  - window_name: w\n    panes:\n      - blank\n      \
+                    - pane\n      - null\n      -\n      - shell_command:\n      - top\n";
+        let (preset, warnings) = import_preset(yaml, ImportFormat::Tmuxp).unwrap();
+        assert_eq!(
+            windows(&preset),
+            [("w", "~", "v(-, -, -, -, -, top)".to_string())]
+        );
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn erb_is_kept_with_a_warning() {
+        let yaml = "name: <%= @args[0] %>\nwindows:\n  - editor: vim\n";
+        let (preset, warnings) = import_preset(yaml, ImportFormat::Tmuxinator).unwrap();
+        assert_eq!(preset.name, "<%= @args[0] %>");
+        assert_eq!(
+            warnings,
+            ["ERB templating isn't supported, `<% %>` tags are kept as is"]
+        );
+    }
+
+    #[test]
+    fn missing_name_or_bad_yaml_is_an_error() {
+        assert_eq!(
+            import_preset("windows: []", ImportFormat::Tmuxp).unwrap_err(),
+            "Missing `session_name`"
+        );
+        assert!(
+            import_preset("name: [", ImportFormat::Tmuxinator)
+                .unwrap_err()
+                .starts_with("Invalid YAML")
+        );
+        assert_eq!(
+            import_preset("- a", ImportFormat::Tmuxinator).unwrap_err(),
+            "Expected a mapping at the top of the file"
+        );
+    }
+}
@@ -1,134 +1,700 @@
 use std::collections::BTreeMap;
+use std::path::Path;
+
+mod config;
+mod cwd;
+mod diagnostic;
+mod edit;
+mod import;
+mod load;
+mod requirement;
+mod schema;
+mod serialize;
+mod suggest;
+mod vars;
+
+pub use config::{Config, from_template, parse_muffin_config};
+pub use diagnostic::{Diagnostic, Severity};
+pub use edit::{PresetEdit, apply_edit, edit_presets_file};
+pub use import::{ImportFormat, import_preset};
+pub use load::{LoadedPresets, Source, load_presets_from};
+pub use requirement::MUFFIN_VERSION;
+pub use serialize::presets_to_kdl;
+pub use suggest::closest_match;
+pub use vars::apply_vars;
 
 use kdl::{KdlDocument, KdlNode};
-use tmux::{LayoutNode, Preset, SplitDirection, Window};
+use std::time::Duration;
+
+use cwd::{CwdScope, resolve_cwd};
+use diagnostic::line_column;
+use tmux::{
+    INHERIT_CWD, LayoutNode, Percent, Preset, Size, SplitDirection, Startup, Var, WaitFor, Window,
+    validate_session_name, verify_split_sizes,
+};
+use vars::{check_placeholders, parse_vars, substitute_cwd};
+
+/// Parses a presets file into presets keyed by name, along with any diagnostics worth showing
+/// the user (problems that don't prevent the presets from loading). Relative session cwds are
+/// taken from `~`.
+pub fn parse_config(doc_str: &str) -> Result<(BTreeMap<String, Preset>, Vec<Diagnostic>), String> {
+    let (map, mut diagnostics) = parse_presets(doc_str, "~")?;
+    diagnostics.extend(collect_diagnostics(&map));
+    Ok((map, diagnostics))
+}
 
-pub fn parse_config(doc_str: &str) -> Result<BTreeMap<String, Preset>, String> {
+/// Parses a presets file into presets keyed by name, along with diagnostics about individual
+/// presets. Relative session cwds are taken from `dir`, the file's directory.
+pub(crate) fn parse_presets(
+    doc_str: &str,
+    dir: &str,
+) -> Result<(BTreeMap<String, Preset>, Vec<Diagnostic>), String> {
     let doc: KdlDocument = doc_str
         .parse()
         .map_err(|_| "Error parsing file".to_string())?;
 
-    let nodes: &[KdlNode] = doc.nodes();
+    // ex: direction_convention "visual"
+    let (convention_nodes, nodes): (Vec<&KdlNode>, Vec<&KdlNode>) = doc
+        .nodes()
+        .iter()
+        .partition(|node| node.name().value() == "direction_convention");
+    let convention = match convention_nodes.last() {
+        Some(node) => Some(parse_direction_convention(node)?),
+        None => None,
+    };
+    // ex: muffin_version ">=0.3"
+    let (requirement_nodes, nodes): (Vec<&KdlNode>, Vec<&KdlNode>) = nodes
+        .into_iter()
+        .partition(|node| node.name().value() == "muffin_version");
+    // ex: layout_limits panes=24 depth=8
+    let (limit_nodes, nodes): (Vec<&KdlNode>, Vec<&KdlNode>) = nodes
+        .into_iter()
+        .partition(|node| node.name().value() == "layout_limits");
+    let limits = match limit_nodes.last() {
+        Some(node) => parse_layout_limits(node)?,
+        None => LayoutLimits::default(),
+    };
 
     let mut map = BTreeMap::<String, Preset>::new();
+    let mut diagnostics = schema::check_names(&doc, doc_str);
+    diagnostics.extend(
+        requirement_nodes
+            .into_iter()
+            .filter_map(|node| requirement::check_muffin_version(node, doc_str, MUFFIN_VERSION)),
+    );
+
+    // Picking a convention is saying which one is meant
+    if convention.is_none()
+        && let Some((direction, offset)) = first_bare_direction(&nodes)
+    {
+        diagnostics.push(
+            Diagnostic::warning(format!(
+                "`direction=\"{direction}\"` reads either way, say `columns` (side by side) or \
+                 `rows` (stacked), or set `direction_convention` for the whole file"
+            ))
+            .at(doc_str, offset),
+        );
+    }
 
-    // nodes.iter().map(|node| parse_session(node)).collect()
-    for node in nodes.iter().map(parse_session) {
-        let node = node?;
-        map.insert(node.name.clone(), node);
+    for (position, node) in nodes.into_iter().enumerate() {
+        check_session_name(node, doc_str)?;
+        let mut warnings = vec![];
+        let mut preset = parse_session(node, dir, convention.unwrap_or_default(), &mut warnings)?;
+        preset.position = position;
+        warnings.extend(check_layout_limits(&preset, limits));
+        diagnostics.extend(
+            warnings
+                .into_iter()
+                .map(|d| d.at(doc_str, node.span().offset())),
+        );
+        map.insert(preset.name.clone(), preset);
     }
-    Ok(map)
+
+    Ok((map, diagnostics))
 }
 
-fn parse_session(session: &KdlNode) -> Result<Preset, String> {
-    if session.name().value() != "session" {
-        return Err("Node is not a session".to_string());
+/// Problems with a set of presets that don't prevent them from loading
+pub(crate) fn collect_diagnostics(map: &BTreeMap<String, Preset>) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    // Presets sharing an explicit order still load, they're just sorted by name among themselves
+    let mut by_order = BTreeMap::<i64, Vec<&str>>::new();
+    for preset in map.values() {
+        if let Some(order) = preset.order {
+            by_order.entry(order).or_default().push(&preset.name);
+        }
+    }
+    for (order, names) in by_order.into_iter().filter(|(_, names)| names.len() > 1) {
+        diagnostics.push(Diagnostic::note(format!(
+            "Presets `{}` share order={order}",
+            names.join("`, `")
+        )));
     }
 
-    let session_name: &str = session
-        .get("name")
-        .and_then(|name| name.as_string())
-        .ok_or("Missing or invalid session name!")?;
+    diagnostics
+}
 
-    let session_cwd: &str = session
-        .get("cwd")
-        .and_then(|name| name.as_string())
-        .unwrap_or("~");
+/// How far a window's layout can go before it's likely a mistake (or generated), and more
+/// than tmux can fit on most screens, for a whole presets file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LayoutLimits {
+    /// Panes per window
+    panes: usize,
+    /// Splits nested in splits, see [`LayoutNode::depth`]
+    depth: usize,
+}
 
-    let windows: Vec<Window> = match session.children() {
-        Some(session_children) => parse_windows(session_children.nodes(), session_cwd)?,
+impl Default for LayoutLimits {
+    fn default() -> Self {
+        Self {
+            panes: 16,
+            depth: 6,
+        }
+    }
+}
 
-        // If the session does not specify any windows, assume single window with single pane that
-        // inherits cwd from session
-        None => vec![Window {
-            name: "main".to_string(),
-            cwd: session_cwd.to_string(),
-            layout: LayoutNode::Pane {
-                cwd: session_cwd.to_string(),
-                command: None,
-                size: 100,
-            },
-        }],
+fn parse_layout_limits(node: &KdlNode) -> Result<LayoutLimits, String> {
+    let mut limits = LayoutLimits::default();
+    for (name, limit) in [("panes", &mut limits.panes), ("depth", &mut limits.depth)] {
+        if let Some(value) = node.get(name) {
+            *limit = value
+                .as_integer()
+                .filter(|&value| value >= 1)
+                .ok_or(format!("`layout_limits {name}` must be 1 or more"))?
+                as usize;
+        }
+    }
+    Ok(limits)
+}
+
+/// Warns about each of `preset`'s windows whose layout goes past `limits`
+fn check_layout_limits(preset: &Preset, limits: LayoutLimits) -> Vec<Diagnostic> {
+    let mut warnings = vec![];
+    for window in preset.windows.iter() {
+        let panes = window.layout.pane_count();
+        if panes > limits.panes {
+            warnings.push(Diagnostic::warning(format!(
+                "Window `{}` of preset `{}` has {panes} panes, more than the {} a window \
+                 usually has room for; raise `layout_limits panes=...` if that's intended",
+                window.name, preset.name, limits.panes
+            )));
+        }
+        let depth = window.layout.depth();
+        if depth > limits.depth {
+            warnings.push(Diagnostic::warning(format!(
+                "Window `{}` of preset `{}` nests splits {depth} deep, more than {}; raise \
+                 `layout_limits depth=...` if that's intended",
+                window.name, preset.name, limits.depth
+            )));
+        }
+    }
+    warnings
+}
+
+/// What a split's `direction="h"` or `"v"` means, for a whole presets file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum DirectionConvention {
+    /// Like tmux's `split-window -h`: `h` puts the panes side by side
+    #[default]
+    Tmux,
+    /// Like vim's `:split`: `h` draws a horizontal line between the panes, stacking them
+    Visual,
+}
+
+fn parse_direction_convention(node: &KdlNode) -> Result<DirectionConvention, String> {
+    match node.get(0).and_then(|value| value.as_string()) {
+        Some("tmux") => Ok(DirectionConvention::Tmux),
+        Some("visual") => Ok(DirectionConvention::Visual),
+        _ => Err("`direction_convention` expects \"tmux\" or \"visual\"".to_string()),
+    }
+}
+
+/// The first split in `sessions` with a `direction` of `h` or `v` (or spelled out), along with
+/// where that is
+fn first_bare_direction(sessions: &[&KdlNode]) -> Option<(String, usize)> {
+    fn search(node: &KdlNode) -> Option<(String, usize)> {
+        if node.name().value() == "split"
+            && let Some(entry) = node.entry("direction")
+            && let Some(direction) = entry.value().as_string()
+            && matches!(direction, "h" | "v" | "horizontal" | "vertical")
+        {
+            return Some((direction.to_string(), entry.span().offset()));
+        }
+        node.children()
+            .map(|children| children.nodes())
+            .unwrap_or_default()
+            .iter()
+            .find_map(search)
+    }
+    sessions.iter().find_map(|node| search(node))
+}
+
+/// Parses a split's `direction`. `columns` and `rows` (or `side-by-side` and `stacked`) mean the
+/// same whatever the `convention`, which only decides what `h` and `v` do.
+fn parse_direction(
+    direction: &str,
+    convention: DirectionConvention,
+) -> Result<SplitDirection, String> {
+    use DirectionConvention::{Tmux, Visual};
+    match (direction, convention) {
+        ("columns" | "side-by-side", _) => Ok(SplitDirection::Horizontal),
+        ("rows" | "stacked", _) => Ok(SplitDirection::Vertical),
+        ("h" | "horizontal", Tmux) | ("v" | "vertical", Visual) => Ok(SplitDirection::Horizontal),
+        ("v" | "vertical", Tmux) | ("h" | "horizontal", Visual) => Ok(SplitDirection::Vertical),
+        _ => Err(format!(
+            "Invalid direction: `{direction}`, expected `columns`, `rows`, `h` or `v`"
+        )),
+    }
+}
+
+/// Parses a `session` node into its preset, reading `h` and `v` splits by `convention`.
+/// Diagnostics are pushed onto `warnings` without a location, for the caller to point at the
+/// node.
+///
+/// Every cwd ends up absolute, see [`resolve_cwd`]: a relative one is taken from the nearest
+/// ancestor's, so a pane's from its window's, a window's from the session's and the session's
+/// from `dir`.
+pub(crate) fn parse_session(
+    session: &KdlNode,
+    dir: &str,
+    convention: DirectionConvention,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Preset, String> {
+    let node_name = session.name().value();
+    if node_name != "session" {
+        return Err(match closest_match(node_name, ["session"]) {
+            Some(_) => format!("Unknown node `{node_name}`, did you mean `session`?"),
+            None => format!("Unknown node `{node_name}`, expected `session`"),
+        });
+    }
+
+    let session_name = session_name(session).ok_or("Missing or invalid session name!")?;
+
+    let session_cwd = resolve_cwd(
+        session
+            .get("cwd")
+            .and_then(|name| name.as_string())
+            .unwrap_or("~"),
+        &resolve_cwd(dir, "~"),
+    );
+
+    let order = match session.get("order") {
+        Some(value) => Some(value.as_integer().ok_or("`order` must be an integer")? as i64),
+        None => None,
+    };
+
+    let pinned = match session.get("pinned") {
+        Some(value) => value.as_bool().ok_or("`pinned` must be true or false")?,
+        None => false,
+    };
+
+    // Not worth refusing the whole file over, the preset just goes without
+    let color = match session.get("color") {
+        Some(value) => {
+            let color = value.as_string().ok_or("`color` must be a string")?;
+            let parsed = parse_color(color);
+            if parsed.is_none() {
+                warnings.push(Diagnostic::warning(format!(
+                    "Preset `{session_name}` has unknown color `{color}`, using the default"
+                )));
+            }
+            parsed
+        }
+        None => None,
+    };
+
+    let description = parse_description(session)?;
+
+    // ex: socket="jobs" tmux_config="~/.config/tmux/jobs.conf"
+    let socket = match session.get("socket") {
+        Some(value) => Some(value.as_string().ok_or("`socket` must be a string")?),
+        None => None,
+    };
+    if socket.is_some_and(|socket| socket.is_empty() || socket.contains('/')) {
+        return Err("`socket` must be a socket name, not a path".to_string());
+    }
+    let tmux_config = match session.get("tmux_config") {
+        Some(value) => Some(value.as_string().ok_or("`tmux_config` must be a string")?),
+        None => None,
+    };
+
+    // Panes inherit this command unless they (or their window) override it
+    let session_default_command = parse_default_command(session, None);
+
+    // ex: tags "work" "backend"
+    let (tag_nodes, window_nodes): (Vec<&KdlNode>, Vec<&KdlNode>) = session
+        .children()
+        .map(|children| children.nodes())
+        .unwrap_or_default()
+        .iter()
+        .partition(|node| node.name().value() == "tags");
+    let (var_nodes, window_nodes): (Vec<&KdlNode>, Vec<&KdlNode>) = window_nodes
+        .into_iter()
+        .partition(|node| node.name().value() == "vars");
+    let (option_nodes, window_nodes): (Vec<&KdlNode>, Vec<&KdlNode>) = window_nodes
+        .into_iter()
+        .partition(|node| node.name().value() == "options");
+    // Anything else was already warned about, see `schema::check_names`
+    let window_nodes = window_nodes
+        .into_iter()
+        .filter(|node| node.name().value() == "window")
+        .collect::<Vec<_>>();
+
+    let mut vars = Vec::<Var>::new();
+    for node in var_nodes {
+        for var in parse_vars(node)? {
+            if vars.iter().any(|v| v.name == var.name) {
+                return Err(format!(
+                    "Variable `{}` is declared more than once",
+                    var.name
+                ));
+            }
+            vars.push(var);
+        }
+    }
+
+    let mut options = vec![];
+    for node in option_nodes {
+        options.extend(parse_options(node)?);
+    }
+
+    let mut tags = Vec::<String>::new();
+    for entry in tag_nodes.iter().flat_map(|node| node.entries()) {
+        let tag = entry.value().as_string().ok_or("`tags` must be strings")?;
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+
+    // If the session does not specify any windows, assume single window with single pane that
+    // inherits cwd from session
+    let scope = CwdScope {
+        preset: &session_name,
+        session: &session_cwd,
+        parent: &session_cwd,
     };
+    let windows = parse_windows(
+        &window_nodes,
+        scope,
+        session_default_command,
+        convention,
+        warnings,
+    )?;
 
-    Ok(Preset {
-        name: session_name.to_string(),
-        cwd: session_cwd.to_string(),
+    let mut preset = Preset {
+        name: session_name,
+        cwd: session_cwd,
         windows,
         running: false,
+        source: None,
+        position: 0,
+        order,
+        pinned,
+        color,
+        tags,
+        description,
+        vars,
+        options,
+        socket: socket.map(String::from),
+        tmux_config: tmux_config.map(String::from),
+    };
+    check_placeholders(&mut preset)?;
+    Ok(preset)
+}
+
+/// Reads a session's description, given either as an attribute or, for longer text, as a child
+/// node: `description "first line\nsecond line"`
+fn parse_description(session: &KdlNode) -> Result<Option<String>, String> {
+    let attribute = match session.get("description") {
+        Some(value) => Some(value.as_string().ok_or("`description` must be a string")?),
+        None => None,
+    };
+
+    let nodes = session
+        .children()
+        .map(|children| children.nodes())
+        .unwrap_or_default()
+        .iter()
+        .filter(|node| node.name().value() == "description")
+        .collect::<Vec<_>>();
+    let node = match nodes.as_slice() {
+        [] => None,
+        [node] => Some(
+            node.get(0)
+                .and_then(|value| value.as_string())
+                .ok_or("`description` must be given a string")?,
+        ),
+        _ => return Err("`description` is given more than once".to_string()),
+    };
+
+    let description = match (attribute, node) {
+        (Some(_), Some(_)) => {
+            return Err(
+                "`description` is given both as an attribute and as a child node".to_string(),
+            );
+        }
+        (description, None) | (None, description) => description,
+    };
+    Ok(description
+        .map(str::trim)
+        .filter(|description| !description.is_empty())
+        .map(String::from))
+}
+
+/// Normalizes a color name (`light-blue`, `Grey`, ...) or `#rrggbb` hex code, or `None` if it's
+/// neither
+fn parse_color(color: &str) -> Option<String> {
+    const NAMES: &[&str] = &[
+        "black",
+        "red",
+        "green",
+        "yellow",
+        "blue",
+        "magenta",
+        "cyan",
+        "gray",
+        "darkgray",
+        "lightred",
+        "lightgreen",
+        "lightyellow",
+        "lightblue",
+        "lightmagenta",
+        "lightcyan",
+        "white",
+    ];
+
+    if let Some(hex) = color.strip_prefix('#') {
+        return (hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| color.to_ascii_lowercase());
+    }
+
+    let name = color
+        .to_ascii_lowercase()
+        .replace([' ', '-', '_'], "")
+        .replace("grey", "gray")
+        .replace("bright", "light");
+    NAMES.contains(&name.as_str()).then_some(name)
+}
+
+/// Fails on a session name tmux wouldn't take as is, pointing at it. Names that are missing
+/// altogether are left for `parse_session` to report.
+fn check_session_name(session: &KdlNode, doc_str: &str) -> Result<(), String> {
+    let Some(name) = session_name(session) else {
+        return Ok(());
+    };
+    validate_session_name(&name).map_err(|e| {
+        let offset = session
+            .entry("name")
+            .map_or(session.span(), |entry| entry.span())
+            .offset();
+        let (line, column) = line_column(doc_str, offset);
+        format!("line {line}, column {column}: Invalid session name `{name}`: {e}")
     })
 }
 
-fn parse_windows(windows: &[KdlNode], parent_cwd: &str) -> Result<Vec<Window>, String> {
+/// A session node's name, with `{cwd}` substituted like in window names. Presets are keyed by it.
+pub(crate) fn session_name(session: &KdlNode) -> Option<String> {
+    let name = session.get("name")?.as_string()?;
+    let cwd = session
+        .get("cwd")
+        .and_then(|cwd| cwd.as_string())
+        .unwrap_or("~");
+    Some(substitute_cwd(name, cwd))
+}
+
+/// Last component of `cwd` (`~` standing for `$HOME`), with tmux's target separators replaced
+fn cwd_basename(cwd: &str) -> String {
+    let cwd = match cwd.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{rest}", std::env::var("HOME").unwrap_or_default())
+        }
+        _ => cwd.to_string(),
+    };
+    Path::new(&cwd)
+        .file_name()
+        .map(|name| name.to_string_lossy().replace([':', '.'], "_"))
+        .unwrap_or_default()
+}
+
+/// Windows are named after their `name`, where `{cwd}` stands for the basename of their cwd and
+/// `{index}` for their position in the session (from 1). Unnamed windows are `win1`, `win2`, ...
+/// in order, and a name that's already taken gets a `-2`, `-3`, ... suffix.
+fn parse_windows(
+    windows: &[&KdlNode],
+    scope: CwdScope,
+    parent_default_command: Option<&str>,
+    convention: DirectionConvention,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Vec<Window>, String> {
     if windows.is_empty() {
         return Ok(vec![Window {
             name: "main".to_string(),
-            cwd: parent_cwd.to_string(),
+            cwd: scope.parent.to_string(),
             layout: LayoutNode::Pane {
-                cwd: parent_cwd.to_string(),
-                command: None,
-                size: 100,
+                cwd: scope.parent.to_string(),
+                command: parent_default_command.map(|s| s.to_string()),
+                size: Size::Percent(Percent::FULL),
+                startup: Startup::default(),
+                remain_on_exit: false,
             },
+            options: vec![],
+            index: None,
         }]);
     }
 
-    let mut ret = vec![];
+    let mut ret: Vec<Window> = vec![];
+    let mut unnamed = 0;
     for (idx, window) in windows.iter().enumerate() {
-        let node_name = window.name().value();
-        if node_name != "window" {
-            return Err(format!("Unknown session child node: `{node_name}`"));
-        } else {
-            // Extract window properties
-            // ex: window name="bobby" cwd="~/bobby/" { ... }
-            let window_cwd = window
-                .get("cwd")
-                .and_then(|cwd| cwd.as_string())
-                .unwrap_or(parent_cwd);
-
-            let idx_str = idx.to_string();
-
-            let window_name = window
-                .get("name")
-                .and_then(|cwd| cwd.as_string())
-                .unwrap_or(idx_str.as_str());
-
-            let panes: LayoutNode = match window.children() {
-                Some(window_children) => parse_panes(window_children.nodes(), window_cwd)?,
-                None => LayoutNode::Pane {
-                    cwd: window_cwd.to_string(),
-                    command: None,
-                    size: 100,
-                },
-            };
+        // Extract window properties
+        // ex: window name="bobby" cwd="~/bobby/" { ... }
+        let configured_cwd = match window.get("cwd").and_then(|cwd| cwd.as_string()) {
+            Some(cwd) => scope.resolve(cwd, warnings),
+            None => scope.parent.to_string(),
+        };
+        let window_cwd = match parse_inherit_cwd(window)? {
+            Some(true) => INHERIT_CWD,
+            _ => &configured_cwd,
+        };
 
-            ret.push(Window {
-                name: window_name.to_string(),
-                cwd: window_cwd.to_string(),
-                layout: panes,
-            });
+        let window_name = match window.get("name").and_then(|name| name.as_string()) {
+            Some(name) => {
+                // tmux reads these as separators in targets like `session:window.pane`
+                if name.contains([':', '.']) {
+                    return Err(format!("Window name `{name}` can't contain `:` or `.`"));
+                }
+                substitute_cwd(name, &configured_cwd).replace("{index}", &(idx + 1).to_string())
+            }
+            None => {
+                unnamed += 1;
+                format!("win{unnamed}")
+            }
+        };
+
+        // muffin tells windows apart by name when updating a running preset
+        let mut unique_name = window_name.clone();
+        let mut suffix = 1;
+        while ret.iter().any(|w| w.name == unique_name) {
+            suffix += 1;
+            unique_name = format!("{window_name}-{suffix}");
         }
-    }
-    // make a vec of windows, push each iterations generated value to it, return that value
-    if ret.is_empty() {
-        ret.push(Window {
-            name: "name".to_string(),
-            cwd: parent_cwd.to_string(),
-            layout: LayoutNode::Pane {
-                cwd: parent_cwd.to_string(),
-                command: None,
-                size: 100,
+        if unique_name != window_name {
+            warnings.push(Diagnostic::note(format!(
+                "Preset `{}` has more than one window named `{window_name}`, renaming one to \
+                 `{unique_name}`",
+                scope.preset
+            )));
+        }
+
+        let index = match window.get("index") {
+            Some(value) => match value.as_integer() {
+                Some(index) if index >= 1 => Some(index as usize),
+                _ => {
+                    return Err(format!(
+                        "`index` of window `{unique_name}` must be 1 or more"
+                    ));
+                }
             },
+            None => None,
+        };
+
+        let window_default_command = parse_default_command(window, parent_default_command);
+
+        let panes = parse_panes(
+            &layout_children(window),
+            window_cwd,
+            scope.within(&configured_cwd),
+            window_default_command,
+            convention,
+            warnings,
+        )?;
+
+        let mut options = vec![];
+        for node in window.children().map(|c| c.nodes()).unwrap_or_default() {
+            if node.name().value() == "options" {
+                options.extend(parse_options(node)?);
+            }
+        }
+
+        ret.push(Window {
+            name: unique_name,
+            cwd: window_cwd.to_string(),
+            layout: panes,
+            options,
+            index,
         });
     }
     Ok(ret)
 }
 
-fn parse_panes(window_children: &[KdlNode], window_cwd: &str) -> Result<LayoutNode, String> {
+/// Reads an `options` node, each child setting the tmux option it's named after
+/// ex: options { synchronize-panes "on"; history-limit 50000 }
+///
+/// Names are passed on to tmux as they are, so any option it knows works. Booleans stand for
+/// `on` and `off`.
+fn parse_options(node: &KdlNode) -> Result<Vec<(String, String)>, String> {
+    let mut options = vec![];
+    for option in node.children().map(|c| c.nodes()).unwrap_or_default() {
+        let name = option.name().value();
+        // tmux would take a leading `-` for a flag
+        if name.is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+            return Err(format!("Invalid option name `{name}`"));
+        }
+
+        let value = option
+            .entries()
+            .first()
+            .filter(|entry| entry.name().is_none())
+            .map(|entry| entry.value())
+            .ok_or(format!("Option `{name}` needs a value, ex: {name} \"on\""))?;
+        let value = match (value.as_bool(), value.as_integer(), value.as_string()) {
+            (Some(true), ..) => "on".to_string(),
+            (Some(false), ..) => "off".to_string(),
+            (_, Some(number), _) => number.to_string(),
+            (.., Some(value)) => value.to_string(),
+            _ => {
+                return Err(format!(
+                    "Option `{name}` must be a string, an integer or true/false"
+                ));
+            }
+        };
+        if value.contains(char::is_control) {
+            return Err(format!(
+                "Option `{name}` can't have control characters in its value"
+            ));
+        }
+        options.push((name.to_string(), value));
+    }
+    Ok(options)
+}
+
+/// A window's or split's `pane` and `split` children. Anything else was already warned about, see
+/// `schema::check_names`.
+fn layout_children(node: &KdlNode) -> Vec<&KdlNode> {
+    node.children()
+        .map(|children| children.nodes())
+        .unwrap_or_default()
+        .iter()
+        .filter(|child| matches!(child.name().value(), "pane" | "split"))
+        .collect()
+}
+
+/// Parses a window's layout, its panes starting in `window_cwd` unless they have a `cwd` of their
+/// own, which is resolved in `scope`
+fn parse_panes(
+    window_children: &[&KdlNode],
+    window_cwd: &str,
+    scope: CwdScope,
+    default_command: Option<&str>,
+    convention: DirectionConvention,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<LayoutNode, String> {
     if window_children.is_empty() {
         return Ok(LayoutNode::Pane {
             cwd: window_cwd.to_string(),
-            command: None,
-            size: 100,
+            command: default_command.map(|s| s.to_string()),
+            size: Size::Percent(Percent::FULL),
+            startup: Startup::default(),
+            remain_on_exit: false,
         });
     }
 
@@ -137,99 +703,950 @@ fn parse_panes(window_children: &[KdlNode], window_cwd: &str) -> Result<LayoutNo
     }
 
     // The root node of a window should always occupy 100%
-    let mut root_node = parse_node_recursive(&window_children[0], window_cwd)?;
-    set_size(&mut root_node, 100);
+    let mut root_node = parse_node_recursive(
+        window_children[0],
+        window_cwd,
+        scope,
+        default_command,
+        convention,
+        warnings,
+    )?;
+    set_size(&mut root_node, Size::Percent(Percent::FULL));
     Ok(root_node)
 }
 
-fn parse_node_recursive(node: &KdlNode, parent_cwd: &str) -> Result<LayoutNode, String> {
+fn parse_node_recursive(
+    node: &KdlNode,
+    parent_cwd: &str,
+    scope: CwdScope,
+    default_command: Option<&str>,
+    convention: DirectionConvention,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<LayoutNode, String> {
     let node_name = node.name().value();
 
-    // We try to get the size, but keep it as Option to know if it was omitted
-    let explicit_size = node
-        .get("size")
-        .and_then(|v| v.as_integer())
-        .map(|v| v as u8);
+    let size = parse_size(node)?;
 
     match node_name {
         "pane" => {
-            let cwd = node
-                .get("cwd")
-                .and_then(|v| v.as_string())
-                .unwrap_or(parent_cwd)
-                .to_string();
+            // Precedence: pane `cwd` > `inherit_cwd` > window `cwd` > session `cwd`
+            let inherit = parse_inherit_cwd(node)?;
+            let cwd = match (node.get("cwd").and_then(|v| v.as_string()), inherit) {
+                (Some(cwd), _) => scope.resolve(cwd, warnings),
+                (None, Some(true)) => INHERIT_CWD.to_string(),
+                // Even under a split that inherits
+                (None, Some(false)) => scope.parent.to_string(),
+                (None, None) => parent_cwd.to_string(),
+            };
 
-            let command = node
-                .get("command")
-                .and_then(|v| v.as_string())
-                .map(|s| s.to_string());
+            // Precedence: pane `command` > window `default_command` > session `default_command`
+            // An explicit `command=""` opts out of the inherited default for a plain shell
+            let command = match node.get("command").and_then(|v| v.as_string()) {
+                Some("") => None,
+                Some(cmd) => Some(cmd.to_string()),
+                None => default_command.map(|s| s.to_string()),
+            };
 
             Ok(LayoutNode::Pane {
                 cwd,
                 command,
-                size: explicit_size.unwrap_or(0), // Placeholder
+                size,
+                startup: parse_startup(node)?,
+                remain_on_exit: parse_remain_on_exit(node)?,
             })
         }
         "split" => {
-            let dir_str = node
-                .get("direction")
-                .and_then(|v| v.as_string())
-                .unwrap_or("v");
-
-            let direction = match dir_str {
-                "h" | "horizontal" => SplitDirection::Horizontal,
-                "v" | "vertical" => SplitDirection::Vertical,
-                _ => return Err(format!("Invalid direction: `{}`", dir_str)),
+            // Stacked unless told otherwise, whatever the convention
+            let direction = match node.get("direction") {
+                Some(value) => parse_direction(
+                    value.as_string().ok_or("`direction` must be a string")?,
+                    convention,
+                )?,
+                None => SplitDirection::Vertical,
             };
 
+            let cwd = match parse_inherit_cwd(node)? {
+                Some(true) => INHERIT_CWD,
+                Some(false) => scope.parent,
+                None => parent_cwd,
+            };
             let mut children = Vec::new();
-            let mut total_explicit = 0u8;
-            let mut missing_indices = Vec::new();
-
-            if let Some(document) = node.children() {
-                for (i, child_node) in document.nodes().iter().enumerate() {
-                    let mut layout_child = parse_node_recursive(child_node, parent_cwd)?;
-
-                    // Check if this specific child had a size defined
-                    if let Some(p) = child_node.get("size").and_then(|v| v.as_integer()) {
-                        let p = p as u8;
-                        set_size(&mut layout_child, p);
-                        total_explicit += p;
-                    } else {
-                        missing_indices.push(i);
-                    }
-                    children.push(layout_child);
-                }
-            }
-
-            if children.is_empty() {
-                return Err("Split nodes must contain children".into());
+            for child_node in layout_children(node) {
+                children.push(parse_node_recursive(
+                    child_node,
+                    cwd,
+                    scope,
+                    default_command,
+                    convention,
+                    warnings,
+                )?);
             }
 
-            // --- Equal Distribution Logic ---
-            if !missing_indices.is_empty() {
-                let remaining = 100_u8.saturating_sub(total_explicit);
-                let share = remaining / (missing_indices.len() as u8);
+            verify_split_sizes(&children)?;
 
-                for idx in missing_indices {
-                    set_size(&mut children[idx], share);
-                }
-            }
+            // ex: split even=#true
+            let even = match node.get("even") {
+                Some(value) => Some(value.as_bool().ok_or("`even` must be #true or #false")?),
+                None => None,
+            };
 
             Ok(LayoutNode::Split {
                 direction,
                 children,
-                size: explicit_size.unwrap_or(0), // Placeholder
+                size,
+                even,
             })
         }
         x => Err(format!("Unexpected node: `{x}`")),
     }
 }
 
+/// Whether a window's, split's or pane's panes start in the directory of the pane they're split
+/// from, see [`INHERIT_CWD`], or `None` to go along with its parent. Panes with a `cwd` of their
+/// own still start there, and `#false` under a node that inherits starts them in their window's.
+/// ex: split inherit_cwd=#true { pane; pane }
+fn parse_inherit_cwd(node: &KdlNode) -> Result<Option<bool>, String> {
+    let Some(value) = node.get("inherit_cwd") else {
+        return Ok(None);
+    };
+    let inherit = value
+        .as_bool()
+        .ok_or("`inherit_cwd` must be #true or #false")?;
+    // Nothing would be left to inherit, as the node's own `cwd` always wins
+    if inherit && node.get("cwd").is_some() {
+        return Err(format!(
+            "A `{}` can't have both `cwd` and `inherit_cwd=#true`, its `cwd` would always win",
+            node.name().value()
+        ));
+    }
+    Ok(Some(inherit))
+}
+
+// ex: pane command="cargo run" remain_on_exit=#true
+fn parse_remain_on_exit(node: &KdlNode) -> Result<bool, String> {
+    match node.get("remain_on_exit") {
+        Some(value) => value
+            .as_bool()
+            .ok_or_else(|| "`remain_on_exit` must be #true or #false".to_string()),
+        None => Ok(false),
+    }
+}
+
+// Reads a pane's `delay_ms`, `wait_for` and `timeout_ms`
+// ex: pane command="cargo run" wait_for="port:5432" timeout_ms=30000
+fn parse_startup(node: &KdlNode) -> Result<Startup, String> {
+    let millis = |key: &str| -> Result<Option<Duration>, String> {
+        match node.get(key) {
+            Some(value) => {
+                let ms = value
+                    .as_integer()
+                    .filter(|ms| *ms >= 0)
+                    .ok_or(format!("`{key}` must be a non-negative integer"))?;
+                Ok(Some(Duration::from_millis(ms as u64)))
+            }
+            None => Ok(None),
+        }
+    };
+
+    let wait_for = match node.get("wait_for") {
+        Some(value) => {
+            let value = value.as_string().ok_or("`wait_for` must be a string")?;
+            Some(match value.split_once(':') {
+                Some(("port", port)) => WaitFor::Port(
+                    port.parse()
+                        .map_err(|_| format!("Invalid port in `wait_for`: `{port}`"))?,
+                ),
+                Some(("file", path)) if !path.is_empty() => WaitFor::File(path.to_string()),
+                _ => {
+                    return Err(format!(
+                        "`wait_for` expects `port:<number>` or `file:<path>`, got `{value}`"
+                    ));
+                }
+            })
+        }
+        None => None,
+    };
+
+    let default = Startup::default();
+    Ok(Startup {
+        delay: millis("delay_ms")?.unwrap_or(default.delay),
+        wait_for,
+        timeout: millis("timeout_ms")?.unwrap_or(default.timeout),
+    })
+}
+
+// Resolves the `default_command` a node passes down to its panes.
+// An empty string clears whatever was inherited from the parent.
+fn parse_default_command<'a>(node: &'a KdlNode, inherited: Option<&'a str>) -> Option<&'a str> {
+    match node.get("default_command").and_then(|v| v.as_string()) {
+        Some("") => None,
+        Some(cmd) => Some(cmd),
+        None => inherited,
+    }
+}
+
+// Reads `size=30` / `size="30%"` (percent), `size="10c"` or `size_cells=10` (cells)
+pub(crate) fn parse_size(node: &KdlNode) -> Result<Size, String> {
+    let percent = |value: i128| -> Result<Size, String> {
+        i64::try_from(value)
+            .ok()
+            .and_then(|value| Percent::try_from(value).ok())
+            .map(Size::Percent)
+            .ok_or_else(|| format!("`size` must be between 1 and 100 percent, got {value}"))
+    };
+    let cells = |value: i128| -> Result<Size, String> {
+        match u16::try_from(value) {
+            Ok(cells) if cells > 0 => Ok(Size::Cells(cells)),
+            _ => Err(format!("A size in cells must be positive, got {value}")),
+        }
+    };
+
+    match (node.get("size"), node.get("size_cells")) {
+        (Some(_), Some(_)) => Err("Only one of `size` and `size_cells` can be given".into()),
+        (None, Some(value)) => cells(
+            value
+                .as_integer()
+                .ok_or("`size_cells` must be an integer")?,
+        ),
+        (Some(value), None) => {
+            if let Some(value) = value.as_integer() {
+                return percent(value);
+            }
+            let value = value
+                .as_string()
+                .ok_or("`size` must be an integer or a string like \"30%\" or \"10c\"")?;
+            let invalid = || format!("Invalid size: `{value}`, expected ex: \"30%\" or \"10c\"");
+            if let Some(number) = value.strip_suffix('%') {
+                percent(number.parse().map_err(|_| invalid())?)
+            } else if let Some(number) = value.strip_suffix('c') {
+                cells(number.parse().map_err(|_| invalid())?)
+            } else {
+                Err(invalid())
+            }
+        }
+        (None, None) => Ok(Size::Auto),
+    }
+}
+
 // Helper to set size regardless of enum variant
-fn set_size(node: &mut LayoutNode, val: u8) {
+fn set_size(node: &mut LayoutNode, val: Size) {
     match node {
         LayoutNode::Pane { size, .. } => *size = val,
         LayoutNode::Split { size, .. } => *size = val,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(doc: &str) -> Preset {
+        let (mut presets, _) = parse_config(doc).unwrap();
+        presets.pop_first().unwrap().1
+    }
+
+    /// The commands of each window's panes, in the order tmux creates them
+    fn commands(preset: &Preset) -> Vec<Vec<Option<String>>> {
+        fn collect(node: &LayoutNode, out: &mut Vec<Option<String>>) {
+            match node {
+                LayoutNode::Pane { command, .. } => out.push(command.clone()),
+                LayoutNode::Split { children, .. } => {
+                    children.iter().for_each(|child| collect(child, out))
+                }
+            }
+        }
+        preset
+            .windows
+            .iter()
+            .map(|window| {
+                let mut out = vec![];
+                collect(&window.layout, &mut out);
+                out
+            })
+            .collect()
+    }
+
+    fn some(command: &str) -> Option<String> {
+        Some(command.to_string())
+    }
+
+    #[test]
+    fn default_command_from_session_reaches_every_pane() {
+        let preset = preset(
+            r#"session name="x" default_command="nvim" {
+                window { split { pane; pane } }
+                window
+            }"#,
+        );
+        assert_eq!(
+            commands(&preset),
+            vec![vec![some("nvim"), some("nvim")], vec![some("nvim")]]
+        );
+    }
+
+    #[test]
+    fn default_command_without_windows() {
+        let preset = preset(r#"session name="x" default_command="htop""#);
+        assert_eq!(commands(&preset), vec![vec![some("htop")]]);
+    }
+
+    #[test]
+    fn window_default_command_overrides_session() {
+        let preset = preset(
+            r#"session name="x" default_command="nvim" {
+                window default_command="lazygit" { split { pane; pane } }
+                window
+            }"#,
+        );
+        assert_eq!(
+            commands(&preset),
+            vec![vec![some("lazygit"), some("lazygit")], vec![some("nvim")]]
+        );
+    }
+
+    #[test]
+    fn pane_command_overrides_defaults() {
+        let preset = preset(
+            r#"session name="x" default_command="nvim" {
+                window default_command="lazygit" {
+                    split { pane command="cargo watch"; pane }
+                }
+            }"#,
+        );
+        assert_eq!(
+            commands(&preset),
+            vec![vec![some("cargo watch"), some("lazygit")]]
+        );
+    }
+
+    #[test]
+    fn empty_command_opts_out_of_defaults() {
+        let preset = preset(
+            r#"session name="x" default_command="nvim" {
+                window { split { pane command=""; pane } }
+                window default_command="" { pane }
+            }"#,
+        );
+        assert_eq!(
+            commands(&preset),
+            vec![vec![None, some("nvim")], vec![None]]
+        );
+    }
+
+    #[test]
+    fn no_default_command_leaves_a_shell() {
+        let preset = preset(r#"session name="x" { window { split { pane; pane } } }"#);
+        assert_eq!(commands(&preset), vec![vec![None, None]]);
+    }
+
+    /// The cwd of each window's panes, in the order tmux creates them
+    fn pane_cwds(preset: &Preset) -> Vec<Vec<String>> {
+        fn collect(node: &LayoutNode, out: &mut Vec<String>) {
+            match node {
+                LayoutNode::Pane { cwd, .. } => out.push(cwd.clone()),
+                LayoutNode::Split { children, .. } => {
+                    children.iter().for_each(|child| collect(child, out))
+                }
+            }
+        }
+        preset
+            .windows
+            .iter()
+            .map(|window| {
+                let mut out = vec![];
+                collect(&window.layout, &mut out);
+                out
+            })
+            .collect()
+    }
+
+    fn outside_session(diagnostics: &[Diagnostic]) -> usize {
+        diagnostics
+            .iter()
+            .filter(|d| d.message.contains("outside the session's"))
+            .count()
+    }
+
+    #[test]
+    fn session_cwds_start_from_the_presets_file() {
+        let (presets, _) = parse_presets(
+            r#"session name="abs" cwd="/srv/app"
+            session name="home" cwd="~/src"
+            session name="rel" cwd="proj"
+            session name="up" cwd="../shared"
+            session name="none""#,
+            "/etc/muffin",
+        )
+        .unwrap();
+        let cwds = presets
+            .values()
+            .map(|preset| (preset.name.as_str(), preset.cwd.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cwds,
+            [
+                ("abs", "/srv/app"),
+                ("home", "~/src"),
+                ("none", "~"),
+                ("rel", "/etc/muffin/proj"),
+                ("up", "/etc/shared"),
+            ]
+        );
+    }
+
+    #[test]
+    fn home_stays_unexpanded_for_the_server_to_work_out() {
+        let (presets, _) = parse_presets(r#"session name="x" cwd="proj""#, "~").unwrap();
+        assert_eq!(presets["x"].cwd, "~/proj");
+        assert_eq!(pane_cwds(&presets["x"]), [["~/proj"]]);
+    }
+
+    #[test]
+    fn window_and_pane_cwds_start_from_their_parent() {
+        let (presets, diagnostics) = parse_presets(
+            r#"session name="x" cwd="~/src/app" {
+                window name="web" cwd="web" {
+                    split {
+                        pane
+                        pane cwd="./api"
+                        pane cwd=".."
+                        pane cwd="/tmp"
+                        pane cwd="~/notes"
+                    }
+                }
+                window name="abs" cwd="/srv" { pane cwd="logs"; }
+                window name="home" cwd="~" { pane cwd="src"; }
+                window name="plain"
+            }"#,
+            "/etc/muffin",
+        )
+        .unwrap();
+        let preset = &presets["x"];
+        let window_cwds = preset
+            .windows
+            .iter()
+            .map(|window| window.cwd.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(window_cwds, ["~/src/app/web", "/srv", "~", "~/src/app"]);
+        assert_eq!(
+            pane_cwds(preset),
+            [
+                vec![
+                    "~/src/app/web",
+                    "~/src/app/web/api",
+                    "~/src/app",
+                    "/tmp",
+                    "~/notes"
+                ],
+                vec!["/srv/logs"],
+                vec!["~/src"],
+                vec!["~/src/app"],
+            ]
+        );
+        // Windows outside the session's directory on purpose don't make their panes suspect
+        assert_eq!(outside_session(&diagnostics), 0);
+    }
+
+    #[test]
+    fn climbing_out_of_the_session_is_warned_about_at_every_level() {
+        let (presets, diagnostics) = parse_presets(
+            r#"session name="x" cwd="~/src/app" {
+                window name="up" cwd="../lib"
+                window name="deep" cwd="web" { pane cwd="../../.."; }
+                window name="fine" cwd="web/ui" { pane cwd="../.."; }
+            }"#,
+            "/etc/muffin",
+        )
+        .unwrap();
+        let preset = &presets["x"];
+        assert_eq!(preset.windows[0].cwd, "~/src/lib");
+        assert_eq!(pane_cwds(preset)[1], ["~"]);
+        assert_eq!(pane_cwds(preset)[2], ["~/src/app"]);
+        assert_eq!(outside_session(&diagnostics), 2);
+    }
+
+    fn window_names(preset: &Preset) -> Vec<&str> {
+        preset.windows.iter().map(|w| w.name.as_str()).collect()
+    }
+
+    #[test]
+    fn unnamed_windows_are_counted_apart_from_named_ones() {
+        let preset = preset(
+            r#"session name="x" {
+                window
+                window name="editor"
+                window
+                window
+            }"#,
+        );
+        assert_eq!(window_names(&preset), ["win1", "editor", "win2", "win3"]);
+    }
+
+    #[test]
+    fn names_substitute_cwd_and_index() {
+        let (presets, _) = parse_config(
+            r#"session name="{cwd}-dev" cwd="/srv/my.app" {
+                window name="{cwd}"
+                window name="{cwd}" cwd="/var/log"
+                window name="w{index}"
+                window name="{cwd}" cwd="~"
+            }"#,
+        )
+        .unwrap();
+        // With `.` swapped, as tmux reads it as the separator of windows and panes in targets
+        let preset = &presets["my_app-dev"];
+        let home = std::env::var("HOME").unwrap();
+        let home = Path::new(&home).file_name().unwrap().to_str().unwrap();
+        assert_eq!(window_names(preset), ["my_app", "log", "w3", home]);
+    }
+
+    #[test]
+    fn duplicate_window_names_get_a_suffix() {
+        let (presets, diagnostics) = parse_config(
+            r#"session name="x" cwd="/srv/api" {
+                window name="api"
+                window name="{cwd}"
+                window name="api"
+                window name="win1"
+                window
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            window_names(&presets["x"]),
+            ["api", "api-2", "api-3", "win1", "win1-2"]
+        );
+        let renamed = diagnostics
+            .iter()
+            .filter(|d| d.message.contains("more than one window named"))
+            .count();
+        assert_eq!(renamed, 3);
+    }
+
+    fn options(options: &[(String, String)]) -> Vec<(&str, &str)> {
+        options
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn options_of_sessions_and_windows() {
+        let preset = preset(
+            r#"session name="x" {
+                options { status-position "top"; base-index 1 }
+                window name="deploy" {
+                    options { synchronize-panes #true; "@custom" "a b" }
+                }
+                window name="logs" { options { monitor-activity #false } }
+                window name="plain"
+            }"#,
+        );
+        assert_eq!(
+            options(&preset.options),
+            [("status-position", "top"), ("base-index", "1")]
+        );
+        let windows = preset
+            .windows
+            .iter()
+            .map(|window| options(&window.options))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            windows,
+            [
+                vec![("synchronize-panes", "on"), ("@custom", "a b")],
+                vec![("monitor-activity", "off")],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_options() {
+        for (options, error) in [
+            (r#""-g" "on""#, "Invalid option name `-g`"),
+            (r#""two words" "on""#, "Invalid option name `two words`"),
+            (
+                "monitor-activity",
+                "Option `monitor-activity` needs a value",
+            ),
+            (
+                "monitor-activity 1.5",
+                "must be a string, an integer or true/false",
+            ),
+            (r#"status-left "a\nb""#, "can't have control characters"),
+        ] {
+            let doc = format!(r#"session name="x" {{ window {{ options {{ {options} }} }} }}"#);
+            let e = parse_config(&doc).unwrap_err();
+            assert!(e.contains(error), "{options}: {e}");
+        }
+    }
+
+    #[test]
+    fn invalid_session_names_are_pointed_at() {
+        let e = parse_config("session name=\"ok\"\n\nsession  name=\"a.b\"").unwrap_err();
+        assert_eq!(
+            e,
+            "line 3, column 10: Invalid session name `a.b`: Can't contain `.` (character 2)"
+        );
+        // `{cwd}` is substituted before the name is checked
+        let e = parse_config(r#"session name="{cwd}:dev" cwd="/srv/app""#).unwrap_err();
+        assert_eq!(
+            e,
+            "line 1, column 9: Invalid session name `app:dev`: Can't contain `:` (character 4)"
+        );
+    }
+
+    #[test]
+    fn inherit_cwd_precedence() {
+        let (presets, _) = parse_presets(
+            r#"session name="x" cwd="/srv" {
+                window cwd="/srv/web" {
+                    split {
+                        split inherit_cwd=#true {
+                            pane cwd="/srv/api"
+                            pane
+                            split { pane; pane inherit_cwd=#false }
+                            split inherit_cwd=#false { pane; pane inherit_cwd=#true }
+                        }
+                        pane
+                    }
+                }
+                window inherit_cwd=#true { split { pane; pane cwd="/tmp" } }
+                window { split { pane inherit_cwd=#true; pane } }
+            }"#,
+            "~",
+        )
+        .unwrap();
+        let preset = &presets["x"];
+        assert_eq!(
+            pane_cwds(preset),
+            [
+                // Their own cwd, then what they inherit from their split unless they turn it
+                // off, then their window's
+                vec![
+                    "/srv/api",
+                    INHERIT_CWD,
+                    INHERIT_CWD,
+                    "/srv/web",
+                    "/srv/web",
+                    INHERIT_CWD,
+                    "/srv/web"
+                ],
+                vec![INHERIT_CWD, "/tmp"],
+                vec![INHERIT_CWD, "/srv"],
+            ]
+        );
+        let windows = preset
+            .windows
+            .iter()
+            .map(|w| w.cwd.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(windows, ["/srv/web", INHERIT_CWD, "/srv"]);
+    }
+
+    #[test]
+    fn inherit_cwd_alongside_a_cwd_is_refused() {
+        for node in [
+            r#"pane cwd="/tmp" inherit_cwd=#true"#,
+            r#"split cwd="/tmp" inherit_cwd=#true { pane; pane }"#,
+        ] {
+            let doc = format!(r#"session name="x" {{ window {{ {node} }} }}"#);
+            let e = parse_config(&doc).unwrap_err();
+            assert!(
+                e.contains("can't have both `cwd` and `inherit_cwd=#true`"),
+                "{e}"
+            );
+        }
+        let e = parse_config(r#"session name="x" { window cwd="/tmp" inherit_cwd=#true }"#)
+            .unwrap_err();
+        assert!(e.contains("A `window` can't have both"), "{e}");
+        // Turned off, there's nothing to choose between
+        parse_config(r#"session name="x" { window { pane cwd="/tmp" inherit_cwd=#false } }"#)
+            .unwrap();
+    }
+
+    #[test]
+    fn out_of_range_sizes_dont_wrap() {
+        let split = |sizes: &str| {
+            let panes = sizes
+                .split(' ')
+                .map(|size| format!("pane size={size}"))
+                .collect::<Vec<_>>()
+                .join("; ");
+            parse_config(&format!(
+                r#"session name="x" {{ window {{ split {{ {panes} }} }} }}"#
+            ))
+        };
+        split(r#""30%" "70%""#).unwrap();
+        for (sizes, error) in [
+            (r#""200%" "200%""#, "between 1 and 100 percent, got 200"),
+            // 0 and 100 once wrapped around as a u8
+            ("256 10", "between 1 and 100 percent, got 256"),
+            (r#""356%" "10%""#, "between 1 and 100 percent, got 356"),
+            ("99999999999999999999 10", "between 1 and 100 percent"),
+            ("-20 10", "between 1 and 100 percent, got -20"),
+            (r#""100%" "100%" "56%""#, "Sizes in a split add up to 256%"),
+            ("60 60", "Sizes in a split add up to 120%"),
+        ] {
+            let e = split(sizes).unwrap_err();
+            assert!(e.contains(error), "{sizes}: {e}");
+        }
+    }
+
+    #[test]
+    fn cells_next_to_auto_panes_are_split_off_at_their_size() {
+        let splits = |panes: &str| {
+            let preset = preset(&format!(
+                r#"session name="x" {{ window {{ split direction="rows" {{ {panes} }} }} }}"#
+            ));
+            tmux::plan_preset(&preset)
+                .unwrap()
+                .commands
+                .iter()
+                .map(|command| command.argv_to(None, tmux::Version::new(3, 4)))
+                .filter(|argv| argv[0] == "split-window")
+                .map(|argv| argv[1..].join(" "))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(splits(r#"pane; pane size="10c""#), ["-v -l 10"]);
+        // Before the auto ones, which share what it leaves
+        assert_eq!(
+            splits(r#"pane size="10c"; pane; pane"#),
+            ["-b -v -l 10", "-v -l 50%"]
+        );
+        assert_eq!(
+            splits(r#"pane size_cells=10; pane size="30%"; pane"#),
+            ["-b -v -l 10", "-v -l 70%"]
+        );
+    }
+
+    #[test]
+    fn sizes_in_percent_and_cells() {
+        let size = |attrs: &str| {
+            let doc: KdlDocument = format!("pane {attrs}").parse().unwrap();
+            parse_size(&doc.nodes()[0])
+        };
+        let percent = |value: i64| Ok(Size::Percent(Percent::try_from(value).unwrap()));
+        for (attrs, expected) in [
+            ("", Ok(Size::Auto)),
+            ("size=30", percent(30)),
+            (r#"size="30%""#, percent(30)),
+            (r#"size="100%""#, percent(100)),
+            (r#"size="10c""#, Ok(Size::Cells(10))),
+            ("size_cells=10", Ok(Size::Cells(10))),
+        ] {
+            assert_eq!(size(attrs), expected, "{attrs}");
+        }
+        for (attrs, error) in [
+            (r#"size="30""#, "Invalid size: `30`"),
+            (r#"size="abc%""#, "Invalid size: `abc%`"),
+            (r#"size="10px""#, "Invalid size: `10px`"),
+            (r#"size="c""#, "Invalid size: `c`"),
+            (r#"size="0%""#, "between 1 and 100 percent, got 0"),
+            (r#"size="0c""#, "must be positive, got 0"),
+            (r#"size="70000c""#, "must be positive, got 70000"),
+            ("size_cells=-3", "must be positive, got -3"),
+            ("size=1.5", "must be an integer or a string"),
+            ("size_cells=\"10\"", "`size_cells` must be an integer"),
+            (
+                r#"size=30 size_cells=10"#,
+                "Only one of `size` and `size_cells`",
+            ),
+        ] {
+            let e = size(attrs).unwrap_err();
+            assert!(e.contains(error), "{attrs}: {e}");
+        }
+    }
+
+    #[test]
+    fn descriptions_as_attribute_or_child_node() {
+        let (presets, _) = parse_config(
+            r#"session name="attr" description="client X staging env, needs VPN"
+            session name="node" {
+                description """
+                    first line
+                    second line
+                    """
+            }
+            session name="escaped" { description "one\ntwo" }
+            session name="blank" description="  "
+            session name="none""#,
+        )
+        .unwrap();
+        let descriptions = presets
+            .values()
+            .map(|preset| (preset.name.as_str(), preset.description.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            descriptions,
+            [
+                ("attr", Some("client X staging env, needs VPN")),
+                ("blank", None),
+                ("escaped", Some("one\ntwo")),
+                ("node", Some("first line\nsecond line")),
+                ("none", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn descriptions_given_twice_are_refused() {
+        for (doc, error) in [
+            (
+                r#"session name="x" description="a" { description "b" }"#,
+                "given both as an attribute and as a child node",
+            ),
+            (
+                r#"session name="x" { description "a"; description "b" }"#,
+                "given more than once",
+            ),
+            (
+                r#"session name="x" { description 3 }"#,
+                "must be given a string",
+            ),
+        ] {
+            let e = parse_config(doc).unwrap_err();
+            assert!(e.contains(error), "{doc}: {e}");
+        }
+    }
+
+    #[test]
+    fn each_direction_spelling_picks_its_split_direction() {
+        for (convention, direction, expected) in [
+            ("", "columns", SplitDirection::Horizontal),
+            ("", "side-by-side", SplitDirection::Horizontal),
+            ("", "rows", SplitDirection::Vertical),
+            ("", "stacked", SplitDirection::Vertical),
+            ("", "h", SplitDirection::Horizontal),
+            ("", "horizontal", SplitDirection::Horizontal),
+            ("", "v", SplitDirection::Vertical),
+            ("", "vertical", SplitDirection::Vertical),
+            (
+                r#"direction_convention "tmux""#,
+                "h",
+                SplitDirection::Horizontal,
+            ),
+            (
+                r#"direction_convention "tmux""#,
+                "v",
+                SplitDirection::Vertical,
+            ),
+            (
+                r#"direction_convention "visual""#,
+                "h",
+                SplitDirection::Vertical,
+            ),
+            (
+                r#"direction_convention "visual""#,
+                "horizontal",
+                SplitDirection::Vertical,
+            ),
+            (
+                r#"direction_convention "visual""#,
+                "v",
+                SplitDirection::Horizontal,
+            ),
+            (
+                r#"direction_convention "visual""#,
+                "vertical",
+                SplitDirection::Horizontal,
+            ),
+            // Aliases mean the same whatever the convention
+            (
+                r#"direction_convention "visual""#,
+                "columns",
+                SplitDirection::Horizontal,
+            ),
+            (
+                r#"direction_convention "visual""#,
+                "rows",
+                SplitDirection::Vertical,
+            ),
+        ] {
+            let doc = format!(
+                r#"{convention}
+                session name="x" {{ window {{ split direction="{direction}" {{ pane; pane }} }} }}"#
+            );
+            let (presets, diagnostics) = parse_config(&doc).unwrap();
+            let plan = tmux::plan_preset(&presets["x"]).unwrap();
+            let splits = plan
+                .ops()
+                .filter_map(|op| match op {
+                    tmux::TmuxOp::Split { direction, .. } => Some(*direction),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(splits, [expected], "{convention} {direction}");
+
+            // Only `h` and `v` read either way, and only without a convention
+            let warned = diagnostics
+                .iter()
+                .any(|d| d.message.contains("reads either way"));
+            let bare = matches!(direction, "h" | "v" | "horizontal" | "vertical");
+            assert_eq!(
+                warned,
+                bare && convention.is_empty(),
+                "{convention} {direction}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_directions_and_conventions() {
+        let e =
+            parse_config(r#"session name="x" { window { split direction="diagonal" { pane } } }"#)
+                .unwrap_err();
+        assert!(e.contains("Invalid direction: `diagonal`"), "{e}");
+        let e = parse_config(r#"direction_convention "vim""#).unwrap_err();
+        assert!(e.contains("expects \"tmux\" or \"visual\""), "{e}");
+    }
+
+    #[test]
+    fn layouts_past_their_limits_are_warned_about() {
+        let session = r#"session name="x" {
+            window name="w" { split { pane; split direction="rows" { pane; pane } } }
+        }"#;
+        for (limits, expected) in [
+            // Three panes, two splits deep
+            ("", vec![]),
+            ("layout_limits panes=3 depth=2", vec![]),
+            (
+                "layout_limits panes=2",
+                vec!["Window `w` of preset `x` has 3 panes, more than the 2"],
+            ),
+            (
+                "layout_limits depth=1",
+                vec!["Window `w` of preset `x` nests splits 2 deep, more than 1"],
+            ),
+            (
+                "layout_limits panes=1 depth=1",
+                vec!["has 3 panes", "nests splits 2 deep"],
+            ),
+            // The last one counts
+            ("layout_limits panes=1\nlayout_limits panes=8", vec![]),
+        ] {
+            let (_, diagnostics) = parse_config(&format!("{limits}\n{session}")).unwrap();
+            let warnings = diagnostics
+                .iter()
+                .filter(|d| d.message.contains("layout_limits"))
+                .collect::<Vec<_>>();
+            assert_eq!(warnings.len(), expected.len(), "{limits}: {warnings:?}");
+            for (warning, expected) in warnings.iter().zip(expected) {
+                assert!(warning.message.contains(expected), "{limits}: {warning:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn layout_limits_are_checked() {
+        for limits in [
+            "layout_limits panes=0",
+            "layout_limits depth=-2",
+            r#"layout_limits depth="deep""#,
+        ] {
+            let e = parse_config(limits).unwrap_err();
+            assert!(e.contains("must be 1 or more"), "{limits}: {e}");
+        }
+    }
+}
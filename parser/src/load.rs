@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use tmux::Preset;
+
+use crate::{Diagnostic, collect_diagnostics, parse_presets};
+
+/// Where presets are read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// A file, or a directory of `*.kdl` files
+    File(PathBuf),
+    /// What was piped into muffin, read up front as it can only be read once
+    Stdin(String),
+    /// A document given on the command line
+    Inline(String),
+}
+
+impl Source {
+    /// The file, for presets that can be edited and read again
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Source::File(path) => Some(path),
+            Source::Stdin(_) | Source::Inline(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Stdin(_) => write!(f, "<stdin>"),
+            Source::Inline(_) => write!(f, "<inline>"),
+        }
+    }
+}
+
+/// Presets merged from every file they were loaded from
+#[derive(Debug, Default)]
+pub struct LoadedPresets {
+    pub presets: BTreeMap<String, Preset>,
+    /// Problems that didn't prevent the presets from loading
+    pub diagnostics: Vec<Diagnostic>,
+    /// Every file that was read, in the order they were read
+    pub files: Vec<PathBuf>,
+}
+
+/// Reads and merges presets from `sources`.
+///
+/// A directory stands for every `*.kdl` file directly inside it, sorted by file name. Each
+/// preset remembers the file it came from (none for stdin and inline documents), and a preset
+/// name defined in two sources is an error.
+pub fn load_presets_from(sources: &[Source]) -> Result<LoadedPresets, String> {
+    let mut loaded = LoadedPresets::default();
+
+    let mut expanded = vec![];
+    for source in sources {
+        match source {
+            Source::File(path) if path.is_dir() => {
+                expanded.extend(kdl_files_in(path)?.into_iter().map(Source::File));
+            }
+            source => expanded.push(source.clone()),
+        }
+    }
+    loaded.files = expanded
+        .iter()
+        .filter_map(|source| source.path().map(Path::to_path_buf))
+        .collect();
+
+    // Where each preset came from, for reporting one defined twice
+    let mut origins = BTreeMap::new();
+    for source in expanded.iter() {
+        let doc_str = match source {
+            Source::File(file) => std::fs::read_to_string(file)
+                .map_err(|e| format!("Could not read from '{}': {e}", file.display()))?,
+            Source::Stdin(doc_str) | Source::Inline(doc_str) => doc_str.clone(),
+        };
+        // Relative session cwds are taken from the file's directory, or `~` for the others
+        let dir = source
+            .path()
+            .and_then(|file| std::path::absolute(file).ok())
+            .and_then(|file| file.parent().map(|dir| dir.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "~".to_string());
+        let (presets, diagnostics) =
+            parse_presets(&doc_str, &dir).map_err(|e| format!("{source}: {e}"))?;
+        loaded.diagnostics.extend(
+            diagnostics
+                .into_iter()
+                .map(|diagnostic| diagnostic.in_source(source)),
+        );
+
+        for (name, mut preset) in presets {
+            if let Some(existing) = origins.get(&name) {
+                return Err(format!(
+                    "Preset `{name}` is defined in both '{existing}' and '{source}'"
+                ));
+            }
+            origins.insert(name.clone(), source);
+            preset.source = source.path().map(Path::to_path_buf);
+            loaded.presets.insert(name, preset);
+        }
+    }
+
+    loaded
+        .diagnostics
+        .extend(collect_diagnostics(&loaded.presets));
+    Ok(loaded)
+}
+
+fn kdl_files_in(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Could not read directory '{}': {e}", dir.display()))?;
+
+    let mut files = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "kdl"))
+        .collect::<Vec<_>>();
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_session_cwds_start_from_where_the_presets_came_from() {
+        let dir = std::env::temp_dir().join(format!("muffin-load-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("presets")).unwrap();
+        let doc = |name: &str| format!(r#"session name="{name}" cwd="../src/{name}""#);
+        std::fs::write(dir.join("presets/file.kdl"), doc("file")).unwrap();
+
+        let loaded = load_presets_from(&[
+            Source::File(dir.join("presets")),
+            Source::Stdin(doc("stdin")),
+            Source::Inline(r#"session name="inline" cwd="src""#.to_string()),
+        ])
+        .unwrap();
+        let cwd = |name: &str| loaded.presets[name].cwd.clone();
+        // The file's directory, however it was found
+        assert_eq!(cwd("file"), format!("{}/src/file", dir.display()));
+        // Anything else has no directory of its own, and `..` doesn't climb out of `~`
+        assert_eq!(cwd("stdin"), "~/../src/stdin");
+        assert_eq!(cwd("inline"), "~/src");
+        assert_eq!(loaded.files, [dir.join("presets/file.kdl")]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn diagnostics_name_where_they_came_from_without_holding_onto_it() {
+        let loaded = load_presets_from(&[Source::Stdin(
+            r#"session name="stdin" color="nope""#.to_string(),
+        )])
+        .unwrap();
+        let [diagnostic] = &loaded.diagnostics[..] else {
+            panic!("{:?}", loaded.diagnostics);
+        };
+        assert_eq!(diagnostic.file.as_deref(), Some("<stdin>"));
+        assert_eq!(
+            diagnostic.to_string(),
+            "<stdin>: line 1, column 1: Preset `stdin` has unknown color `nope`, using the default"
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::cmp::Ordering;
+
+use kdl::KdlNode;
+
+use crate::diagnostic::Diagnostic;
+
+/// The muffin version presets files are checked against. The crates are versioned along with
+/// muffin itself.
+pub const MUFFIN_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Warns if the file's `muffin_version ">=0.3"` requirement isn't met by `version`, or can't be
+/// read
+pub(crate) fn check_muffin_version(
+    node: &KdlNode,
+    doc_str: &str,
+    version: &str,
+) -> Option<Diagnostic> {
+    let requirement = node
+        .entries()
+        .iter()
+        .find(|entry| entry.name().is_none())
+        .and_then(|entry| entry.value().as_string());
+    let diagnostic = match requirement.map(|req| (req, matches(req, version))) {
+        Some((_, Some(true))) => return None,
+        Some((req, Some(false))) => Diagnostic::warning(format!(
+            "This file needs muffin {req}, but this is muffin {version}"
+        )),
+        Some((req, None)) => Diagnostic::warning(format!(
+            "Can't read `muffin_version \"{req}\"`, expected a requirement like `>=0.3`"
+        )),
+        None => Diagnostic::warning("`muffin_version` expects a requirement, ex: \">=0.3\""),
+    };
+    Some(diagnostic.at(doc_str, node.span().offset()))
+}
+
+/// Whether `version` meets `requirement`, comma separated comparisons like `>=0.3, <0.5` with
+/// the same meaning as in Cargo.toml (a bare `0.3` being `^0.3`), or `None` if either can't be
+/// read
+fn matches(requirement: &str, version: &str) -> Option<bool> {
+    let mut version = parse_version(version)?;
+    // As long as any part it's compared to
+    version.parts.resize(3, 0);
+    let mut all = true;
+    for comparison in requirement.split(',') {
+        let comparison = comparison.trim();
+        let (op, wanted) = [">=", "<=", ">", "<", "=", "^", "~"]
+            .into_iter()
+            .find_map(|op| Some((op, comparison.strip_prefix(op)?)))
+            .unwrap_or(("^", comparison));
+        let wanted = parse_version(wanted.trim())?;
+        let order = compare(&version, &wanted);
+        let below = |bound: Vec<u64>| version.parts[..wanted.parts.len()] < *bound;
+        all &= match op {
+            ">=" => order != Ordering::Less,
+            "<=" => order != Ordering::Greater,
+            ">" => order == Ordering::Greater,
+            "<" => order == Ordering::Less,
+            "=" => order == Ordering::Equal,
+            "~" => order != Ordering::Less && below(tilde_bound(&wanted.parts)),
+            _ => order != Ordering::Less && below(caret_bound(&wanted.parts)),
+        };
+    }
+    Some(all)
+}
+
+/// A version as far as it's written, ex: `0.3` or `1.0.0-beta.2`
+struct Version<'a> {
+    /// `[1, 2, 3]` for `1.2.3`, missing parts left out (`0.3` is `[0, 3]`)
+    parts: Vec<u64>,
+    /// What follows the `-`, ex: `beta.2`
+    pre: Option<&'a str>,
+}
+
+/// Reads a version, leaving out its build metadata (`+...`), which doesn't take part in
+/// comparisons
+fn parse_version(version: &str) -> Option<Version<'_>> {
+    let version = version
+        .split_once('+')
+        .map_or(version, |(version, _)| version);
+    let (numbers, pre) = match version.split_once('-') {
+        Some((numbers, pre)) if !pre.is_empty() => (numbers, Some(pre)),
+        Some(_) => return None,
+        None => (version, None),
+    };
+    let parts = numbers
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    (1..=3)
+        .contains(&parts.len())
+        .then_some(Version { parts, pre })
+}
+
+/// How `version` compares to `wanted`. Parts `wanted` leaves out match anything, ex: `<=0.3` lets
+/// 0.3.9 through, and a pre-release comes before the release it leads up to, ex: `0.4.0-beta` is
+/// below `0.4`.
+fn compare(version: &Version, wanted: &Version) -> Ordering {
+    let (compared, rest) = version.parts.split_at(wanted.parts.len());
+    let order = compared.cmp(&wanted.parts);
+    // Past the release `wanted` stands for, ex: `0.4.1-beta` for `0.4`
+    if order != Ordering::Equal || rest.iter().any(|&part| part != 0) {
+        return order;
+    }
+    match (version.pre, wanted.pre) {
+        (None, None) => Ordering::Equal,
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(pre), Some(wanted)) => compare_pre(pre, wanted),
+    }
+}
+
+/// Pre-releases compare each of their dot separated parts in turn, numbers as numbers and
+/// below words, ex: `alpha.2 < alpha.10 < beta`
+fn compare_pre(pre: &str, wanted: &str) -> Ordering {
+    let key = |part: &str| match part.parse::<u64>() {
+        Ok(number) => (0, number, String::new()),
+        Err(_) => (1, 0, part.to_string()),
+    };
+    pre.split('.').map(key).cmp(wanted.split('.').map(key))
+}
+
+/// First version `^wanted` no longer matches: its leftmost nonzero part bumped, ex: `0.4` for
+/// `^0.3` and `2` for `^1.2`
+fn caret_bound(wanted: &[u64]) -> Vec<u64> {
+    let bumped = wanted
+        .iter()
+        .position(|&part| part != 0)
+        .unwrap_or(wanted.len() - 1);
+    bump(wanted, bumped)
+}
+
+/// First version `~wanted` no longer matches: its minor version bumped, or its major one if
+/// that's all there is, ex: `1.3` for `~1.2.3` and `2` for `~1`
+fn tilde_bound(wanted: &[u64]) -> Vec<u64> {
+    bump(wanted, wanted.len().min(2) - 1)
+}
+
+fn bump(wanted: &[u64], part: usize) -> Vec<u64> {
+    let mut bound = wanted[..=part].to_vec();
+    bound[part] += 1;
+    bound
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requirements_read_like_cargo() {
+        for (requirement, version, expected) in [
+            // A bare version is a caret requirement, bumping the leftmost nonzero part
+            ("0.3", "0.3.0", true),
+            ("0.3", "0.3.9", true),
+            ("0.3", "0.4.0", false),
+            ("0.3", "0.2.9", false),
+            ("^0.0.3", "0.0.3", true),
+            ("^0.0.3", "0.0.4", false),
+            ("^0", "0.9.0", true),
+            ("^0", "1.0.0", false),
+            ("^1.2", "1.9.0", true),
+            ("^1.2", "2.0.0", false),
+            ("^1.2", "1.1.0", false),
+            // Tilde only lets patches through, or minors with only a major
+            ("~1.2.3", "1.2.9", true),
+            ("~1.2.3", "1.2.2", false),
+            ("~1.2.3", "1.3.0", false),
+            ("~0.3", "0.3.5", true),
+            ("~0.3", "0.4.0", false),
+            ("~1", "1.9.0", true),
+            ("~1", "2.0.0", false),
+            // Comparisons, parts left out matching anything
+            (">=0.3", "0.3.0", true),
+            (">=0.3", "0.2.9", false),
+            ("<=0.3", "0.3.9", true),
+            ("<=0.3", "0.4.0", false),
+            (">0.3", "0.3.9", false),
+            (">0.3", "0.4.0", true),
+            ("<0.5", "0.4.9", true),
+            ("<0.5", "0.5.0", false),
+            ("=0.3.1", "0.3.1", true),
+            ("=0.3.1", "0.3.2", false),
+            (">=0.3, <0.5", "0.4.2", true),
+            (">=0.3, <0.5", "0.5.0", false),
+            // Pre-releases come before their release, build metadata doesn't count
+            (">=0.4", "0.4.0-beta.1", false),
+            ("<0.4", "0.4.0-beta.1", true),
+            (">=0.4", "0.4.1-beta.1", true),
+            (">=0.4.0-beta.2", "0.4.0-beta.10", true),
+            (">=0.4.0-beta", "0.4.0-alpha", false),
+            ("=0.4.0", "0.4.0+abc123", true),
+            // Versions missing parts have them as 0
+            ("=0.4.0", "0.4", true),
+            ("^0.4", "0.4.2-rc.1+abc123", true),
+        ] {
+            assert_eq!(
+                matches(requirement, version),
+                Some(expected),
+                "{version} against {requirement}"
+            );
+        }
+    }
+
+    #[test]
+    fn unreadable_requirements() {
+        for (requirement, version) in [
+            ("latest", "0.3.0"),
+            (">=0.3.0.1", "0.3.0"),
+            (">=0.3-", "0.3.0"),
+            ("", "0.3.0"),
+            (">=0.3", "0.3.x"),
+        ] {
+            assert_eq!(matches(requirement, version), None, "{requirement}");
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use kdl::{KdlDocument, KdlNode};
+
+use crate::diagnostic::Diagnostic;
+use crate::suggest::closest_match;
+
+/// The attributes and child nodes a kind of node accepts
+struct NodeSchema {
+    name: &'static str,
+    attributes: &'static [&'static str],
+    children: &'static [&'static str],
+}
+
+const SCHEMAS: &[NodeSchema] = &[
+    NodeSchema {
+        name: "session",
+        attributes: &[
+            "name",
+            "cwd",
+            "default_command",
+            "order",
+            "pinned",
+            "color",
+            "description",
+            "socket",
+            "tmux_config",
+        ],
+        children: &["window", "tags", "description", "vars", "options"],
+    },
+    NodeSchema {
+        name: "window",
+        attributes: &["name", "cwd", "default_command", "inherit_cwd", "index"],
+        children: &["pane", "split", "options"],
+    },
+    NodeSchema {
+        name: "split",
+        attributes: &["direction", "size", "size_cells", "inherit_cwd", "even"],
+        children: &["pane", "split"],
+    },
+    NodeSchema {
+        name: "pane",
+        attributes: &[
+            "cwd",
+            "inherit_cwd",
+            "command",
+            "size",
+            "size_cells",
+            "delay_ms",
+            "wait_for",
+            "timeout_ms",
+            "remain_on_exit",
+        ],
+        children: &[],
+    },
+    NodeSchema {
+        name: "layout_limits",
+        attributes: &["panes", "depth"],
+        children: &[],
+    },
+    NodeSchema {
+        name: "tags",
+        attributes: &[],
+        children: &[],
+    },
+    NodeSchema {
+        name: "description",
+        attributes: &[],
+        children: &[],
+    },
+];
+
+/// Warns about every attribute and child node in `doc` that muffin doesn't know, which would
+/// otherwise be silently ignored. Each warning says where it is and what was likely meant.
+pub(crate) fn check_names(doc: &KdlDocument, doc_str: &str) -> Vec<Diagnostic> {
+    let mut warnings = vec![];
+    for node in doc.nodes() {
+        check_node(node, doc_str, &mut warnings);
+    }
+    warnings
+}
+
+fn check_node(node: &KdlNode, doc_str: &str, warnings: &mut Vec<Diagnostic>) {
+    let node_name = node.name().value();
+    let Some(schema) = SCHEMAS.iter().find(|schema| schema.name == node_name) else {
+        // `vars` declares arbitrary names, and nodes of unknown kinds are reported by their parent
+        return;
+    };
+
+    for entry in node.entries() {
+        let Some(name) = entry.name().map(|name| name.value()) else {
+            continue;
+        };
+        if !schema.attributes.contains(&name) {
+            warnings.push(
+                Diagnostic::warning(format!(
+                    "Unknown attribute `{name}` on `{node_name}`, {}",
+                    suggestion(name, schema.attributes),
+                ))
+                .at(doc_str, entry.span().offset()),
+            );
+        }
+    }
+
+    for child in node.children().map(|c| c.nodes()).unwrap_or_default() {
+        let name = child.name().value();
+        if schema.children.contains(&name) {
+            check_node(child, doc_str, warnings);
+        } else {
+            warnings.push(
+                Diagnostic::warning(format!(
+                    "Unknown node `{name}` in `{node_name}`, {}",
+                    suggestion(name, schema.children),
+                ))
+                .at(doc_str, child.span().offset()),
+            );
+        }
+    }
+}
+
+/// Points at the closest valid name if there's a plausible one, or lists them all
+fn suggestion(name: &str, valid: &[&str]) -> String {
+    match closest_match(name, valid.iter().copied()) {
+        Some(closest) => format!("did you mean `{closest}`?"),
+        None if valid.is_empty() => "it doesn't take any".to_string(),
+        None => format!("expected one of `{}`", valid.join("`, `")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings(doc_str: &str) -> Vec<(String, (usize, usize))> {
+        let doc: KdlDocument = doc_str.parse().unwrap();
+        check_names(&doc, doc_str)
+            .into_iter()
+            .map(|d| (d.message, d.location.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn unknown_attributes_suggest_the_closest_one() {
+        let doc = "session name=\"x\" {\n    window nmae=\"main\" cwd=\"/\"\n}";
+        assert_eq!(
+            warnings(doc),
+            [(
+                "Unknown attribute `nmae` on `window`, did you mean `name`?".to_string(),
+                (2, 12)
+            )]
+        );
+        // Nothing close enough, so they're all listed
+        assert_eq!(
+            warnings("layout_limits colour=1")[0].0,
+            "Unknown attribute `colour` on `layout_limits`, expected one of `panes`, `depth`"
+        );
+    }
+
+    #[test]
+    fn unknown_nodes_suggest_the_closest_one() {
+        let doc = "session name=\"x\" {\n    window {\n        spilt { pane; pane; }\n    }\n}";
+        assert_eq!(
+            warnings(doc),
+            [(
+                "Unknown node `spilt` in `window`, did you mean `split`?".to_string(),
+                (3, 9)
+            )]
+        );
+        assert_eq!(
+            warnings("session name=\"x\" { window { pane { extra; }; }; }")[0].0,
+            "Unknown node `extra` in `pane`, it doesn't take any"
+        );
+        // What's under `vars` is named by the preset
+        assert_eq!(warnings("session name=\"x\" { vars { anything; }; }"), []);
+    }
+}
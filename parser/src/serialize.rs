@@ -0,0 +1,194 @@
+use kdl::{KdlDocument, KdlEntry, KdlNode};
+use tmux::{INHERIT_CWD, LayoutNode, Preset, Size, SplitDirection, Startup, WaitFor};
+
+/// Writes `presets` out as a presets file muffin reads back into the same presets.
+///
+/// Everything is spelled out the way the parser resolved it: commands are per pane rather than
+/// `default_command`s, and directories are only given where they differ from their parent's.
+pub fn presets_to_kdl<'a>(presets: impl IntoIterator<Item = &'a Preset>) -> String {
+    let mut doc = KdlDocument::new();
+    for preset in presets {
+        doc.nodes_mut().push(session_node(preset));
+    }
+    doc.autoformat();
+    doc.to_string()
+}
+
+fn session_node(preset: &Preset) -> KdlNode {
+    let mut node = KdlNode::new("session");
+    push_prop(&mut node, "name", preset.name.as_str());
+    push_prop(&mut node, "cwd", preset.cwd.as_str());
+    if let Some(color) = &preset.color {
+        push_prop(&mut node, "color", color.as_str());
+    }
+    if let Some(order) = preset.order {
+        push_prop(&mut node, "order", order as i128);
+    }
+    if preset.pinned {
+        push_prop(&mut node, "pinned", true);
+    }
+    if let Some(socket) = &preset.socket {
+        push_prop(&mut node, "socket", socket.as_str());
+    }
+    if let Some(tmux_config) = &preset.tmux_config {
+        push_prop(&mut node, "tmux_config", tmux_config.as_str());
+    }
+    // Longer descriptions read better on a line of their own
+    if let Some(description) = &preset.description
+        && !description.contains('\n')
+    {
+        push_prop(&mut node, "description", description.as_str());
+    }
+
+    let mut children = KdlDocument::new();
+    if let Some(description) = &preset.description
+        && description.contains('\n')
+    {
+        let mut node = KdlNode::new("description");
+        node.entries_mut().push(KdlEntry::new(description.as_str()));
+        children.nodes_mut().push(node);
+    }
+    if !preset.tags.is_empty() {
+        let mut tags = KdlNode::new("tags");
+        for tag in &preset.tags {
+            tags.entries_mut().push(KdlEntry::new(tag.as_str()));
+        }
+        children.nodes_mut().push(tags);
+    }
+    if !preset.vars.is_empty() {
+        let mut vars = KdlDocument::new();
+        for var in &preset.vars {
+            let mut node = KdlNode::new(var.name.as_str());
+            if let Some(default) = &var.default {
+                push_prop(&mut node, "default", default.as_str());
+            }
+            vars.nodes_mut().push(node);
+        }
+        let mut node = KdlNode::new("vars");
+        node.set_children(vars);
+        children.nodes_mut().push(node);
+    }
+    if !preset.options.is_empty() {
+        children.nodes_mut().push(options_node(&preset.options));
+    }
+    for window in &preset.windows {
+        let mut node = KdlNode::new("window");
+        push_prop(&mut node, "name", window.name.as_str());
+        push_cwd(&mut node, &window.cwd, &preset.cwd);
+        if let Some(index) = window.index {
+            push_prop(&mut node, "index", index as i128);
+        }
+        let mut layout = KdlDocument::new();
+        layout
+            .nodes_mut()
+            .push(layout_node(&window.layout, &window.cwd, true));
+        if !window.options.is_empty() {
+            layout.nodes_mut().push(options_node(&window.options));
+        }
+        node.set_children(layout);
+        children.nodes_mut().push(node);
+    }
+    node.set_children(children);
+    node
+}
+
+fn options_node(options: &[(String, String)]) -> KdlNode {
+    let mut doc = KdlDocument::new();
+    for (name, value) in options {
+        let mut option = KdlNode::new(name.as_str());
+        option.entries_mut().push(KdlEntry::new(value.as_str()));
+        doc.nodes_mut().push(option);
+    }
+    let mut node = KdlNode::new("options");
+    node.set_children(doc);
+    node
+}
+
+fn layout_node(layout: &LayoutNode, parent_cwd: &str, is_root: bool) -> KdlNode {
+    let mut node = match layout {
+        LayoutNode::Pane {
+            cwd,
+            command,
+            startup,
+            remain_on_exit,
+            ..
+        } => {
+            let mut node = KdlNode::new("pane");
+            push_cwd(&mut node, cwd, parent_cwd);
+            // Without any `default_command` written out, no command means a plain shell
+            if let Some(command) = command {
+                push_prop(&mut node, "command", command.as_str());
+            }
+            push_startup(&mut node, startup);
+            if *remain_on_exit {
+                push_prop(&mut node, "remain_on_exit", true);
+            }
+            node
+        }
+        LayoutNode::Split {
+            direction,
+            children,
+            even,
+            ..
+        } => {
+            let mut node = KdlNode::new("split");
+            let direction = match direction {
+                // Read the same whatever the file's `direction_convention`
+                SplitDirection::Horizontal => "columns",
+                SplitDirection::Vertical => "rows",
+            };
+            push_prop(&mut node, "direction", direction);
+            if let Some(even) = even {
+                push_prop(&mut node, "even", *even);
+            }
+            let mut doc = KdlDocument::new();
+            for child in children {
+                doc.nodes_mut().push(layout_node(child, parent_cwd, false));
+            }
+            node.set_children(doc);
+            node
+        }
+    };
+
+    // The root always takes the whole window
+    if !is_root {
+        match layout.size() {
+            Size::Percent(percent) => push_prop(&mut node, "size", percent.get() as i128),
+            Size::Cells(cells) => push_prop(&mut node, "size_cells", cells as i128),
+            Size::Auto => {}
+        }
+    }
+    node
+}
+
+/// Only where `cwd` differs from the `parent_cwd` it would otherwise get
+fn push_cwd(node: &mut KdlNode, cwd: &str, parent_cwd: &str) {
+    if cwd == parent_cwd {
+        return;
+    }
+    match cwd {
+        INHERIT_CWD => push_prop(node, "inherit_cwd", true),
+        cwd => push_prop(node, "cwd", cwd),
+    }
+}
+
+fn push_startup(node: &mut KdlNode, startup: &Startup) {
+    let default = Startup::default();
+    if !startup.delay.is_zero() {
+        push_prop(node, "delay_ms", startup.delay.as_millis() as i128);
+    }
+    if let Some(wait_for) = &startup.wait_for {
+        let wait_for = match wait_for {
+            WaitFor::Port(port) => format!("port:{port}"),
+            WaitFor::File(path) => format!("file:{path}"),
+        };
+        push_prop(node, "wait_for", wait_for);
+    }
+    if startup.timeout != default.timeout {
+        push_prop(node, "timeout_ms", startup.timeout.as_millis() as i128);
+    }
+}
+
+fn push_prop(node: &mut KdlNode, key: &str, value: impl Into<kdl::KdlValue>) {
+    node.entries_mut().push(KdlEntry::new_prop(key, value));
+}
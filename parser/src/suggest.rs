@@ -0,0 +1,50 @@
+/// The number of single-character insertions, deletions and substitutions turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The candidate closest to `name`, if any is close enough to plausibly be what was meant
+pub fn closest_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(2);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_matches() {
+        let names = ["name", "cwd", "command", "default_command"];
+        for (name, expected) in [
+            ("nmae", Some("name")),
+            ("cdw", Some("cwd")),
+            ("comand", Some("command")),
+            ("default_comand", Some("default_command")),
+            // Too far from anything for a 3-letter name
+            ("xyz", None),
+            ("colour", None),
+        ] {
+            assert_eq!(closest_match(name, names), expected, "{name}");
+        }
+        assert_eq!(closest_match("name", []), None);
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::BTreeMap;
+
+use kdl::KdlNode;
+use tmux::{LayoutNode, Preset, Var};
+
+use crate::cwd_basename;
+use crate::suggest::closest_match;
+
+/// Placeholders window names already get on their own, see `parse_windows`
+const RESERVED: &[&str] = &["cwd", "index"];
+
+/// Reads a session's `vars` node, each child declaring one variable
+/// ex: vars { project_dir; port default="8080" }
+pub(crate) fn parse_vars(node: &KdlNode) -> Result<Vec<Var>, String> {
+    let mut vars = Vec::<Var>::new();
+    for var in node.children().map(|c| c.nodes()).unwrap_or_default() {
+        let name = var.name().value();
+        if !is_identifier(name) {
+            return Err(format!(
+                "Invalid variable name `{name}`, use letters, digits and `_`"
+            ));
+        }
+        if RESERVED.contains(&name) {
+            return Err(format!(
+                "`{{{name}}}` is already taken, name the variable otherwise"
+            ));
+        }
+
+        let default = match var.get("default") {
+            Some(value) => Some(match value.as_integer() {
+                Some(number) => number.to_string(),
+                None => value
+                    .as_string()
+                    .ok_or("A variable's `default` must be a string or an integer")?
+                    .to_string(),
+            }),
+            None => None,
+        };
+        vars.push(Var {
+            name: name.to_string(),
+            default,
+        });
+    }
+    Ok(vars)
+}
+
+/// Makes sure every placeholder in `preset` is one of its variables.
+///
+/// Braces around a name are a placeholder, which must be declared in `vars` (or be `{cwd}` in
+/// names, the basename of a cwd that's only known at launch). Braces around anything else, like
+/// `{}` or `{ print }`, are left as written. A name close to a declared one is most likely a typo
+/// for it, which the error points out.
+pub(crate) fn check_placeholders(preset: &mut Preset) -> Result<(), String> {
+    let declared = preset
+        .vars
+        .iter()
+        .map(|var| var.name.clone())
+        .collect::<Vec<_>>();
+    let name = preset.name.clone();
+    for (is_name, string) in strings_mut(preset) {
+        for var in placeholders(string) {
+            if declared.iter().any(|d| d == var) || is_name && var == "cwd" {
+                continue;
+            }
+            if var == "cwd" {
+                return Err(format!(
+                    "Preset `{name}` uses `{{cwd}}` outside of a name, where it doesn't stand \
+                     for anything"
+                ));
+            }
+            let hint = match closest_match(var, declared.iter().map(String::as_str)) {
+                Some(meant) => format!("did you mean `{{{meant}}}`?"),
+                None => format!(
+                    "declare it there, or write `{{ {var} }}` if the braces aren't a placeholder"
+                ),
+            };
+            return Err(format!(
+                "Preset `{name}` uses `{{{var}}}`, which isn't declared in its `vars`, {hint}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A copy of `preset` with its placeholders replaced by `values`, falling back to each
+/// variable's default. Fails on the first variable left without a value, or placeholder that
+/// isn't a variable.
+pub fn apply_vars(preset: &Preset, values: &BTreeMap<String, String>) -> Result<Preset, String> {
+    let mut resolved = preset.clone();
+    let lookup = preset
+        .vars
+        .iter()
+        .filter_map(|var| {
+            let value = values
+                .get(&var.name)
+                .filter(|value| !value.is_empty())
+                .or(var.default.as_ref())?;
+            Some((var.name.as_str(), value.as_str()))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    for (_, string) in strings_mut(&mut resolved) {
+        let mut result = String::new();
+        let mut rest = string.as_str();
+        while let Some((start, var)) = next_placeholder(rest) {
+            let end = start + var.len() + 2;
+            let value = match lookup.get(var) {
+                Some(value) => value,
+                // Left for once the cwd it stands for is resolved too
+                None if var == "cwd" => &rest[start..end],
+                None if preset.vars.iter().any(|v| v.name == var) => {
+                    return Err(format!("No value given for `{var}`"));
+                }
+                None => {
+                    return Err(format!(
+                        "`{{{var}}}` isn't one of the variables of preset `{}`",
+                        preset.name
+                    ));
+                }
+            };
+            result.push_str(&rest[..start]);
+            result.push_str(value);
+            rest = &rest[end..];
+        }
+        result.push_str(rest);
+        *string = result;
+    }
+
+    resolved.name = substitute_cwd(&resolved.name, &resolved.cwd);
+    // Checked as written in `parse_presets`, but the values could bring in anything
+    tmux::validate_session_name(&resolved.name)
+        .map_err(|e| format!("Invalid session name `{}`: {e}", resolved.name))?;
+    for window in &mut resolved.windows {
+        window.name = substitute_cwd(&window.name, &window.cwd);
+        // Same as in `parse_windows`, only the values weren't known then
+        if window.name.contains([':', '.']) {
+            return Err(format!(
+                "Window name `{}` can't contain `:` or `.`",
+                window.name
+            ));
+        }
+    }
+    Ok(resolved)
+}
+
+/// `name` with `{cwd}` standing for the basename of `cwd`, unless that's yet to be filled in
+pub(crate) fn substitute_cwd(name: &str, cwd: &str) -> String {
+    if placeholders(cwd).next().is_some() {
+        return name.to_string();
+    }
+    name.replace("{cwd}", &cwd_basename(cwd))
+}
+
+/// Names of what could be `{name}` placeholders in `s`. Shell parameters like `${HOME}` aren't
+/// ones.
+fn placeholders(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let (start, var) = next_placeholder(rest)?;
+        rest = &rest[start + var.len() + 2..];
+        Some(var)
+    })
+}
+
+/// Byte offset and name of the first placeholder in `s`
+fn next_placeholder(s: &str) -> Option<(usize, &str)> {
+    let mut from = 0;
+    while let Some(open) = s[from..].find('{').map(|i| i + from) {
+        from = open + 1;
+        // `${VAR}` is the shell's and `#{format}` tmux's
+        if s[..open].ends_with(['$', '#']) {
+            continue;
+        }
+        if let Some(close) = s[open + 1..].find('}') {
+            let name = &s[open + 1..open + 1 + close];
+            if is_identifier(name) {
+                return Some((open, name));
+            }
+        }
+    }
+    None
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Every string in `preset` placeholders can appear in: names, directories and commands, the
+/// names flagged as such
+fn strings_mut(preset: &mut Preset) -> Vec<(bool, &mut String)> {
+    fn layout<'a>(node: &'a mut LayoutNode, out: &mut Vec<(bool, &'a mut String)>) {
+        match node {
+            LayoutNode::Pane { cwd, command, .. } => {
+                out.push((false, cwd));
+                out.extend(command.as_mut().map(|command| (false, command)));
+            }
+            LayoutNode::Split { children, .. } => {
+                for child in children {
+                    layout(child, out);
+                }
+            }
+        }
+    }
+
+    let mut out = vec![(true, &mut preset.name), (false, &mut preset.cwd)];
+    for window in &mut preset.windows {
+        out.push((true, &mut window.name));
+        out.push((false, &mut window.cwd));
+        layout(&mut window.layout, &mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_config;
+
+    fn vars(doc: &str) -> Result<Vec<Var>, String> {
+        let doc = doc.parse::<kdl::KdlDocument>().unwrap();
+        parse_vars(&doc.nodes()[0])
+    }
+
+    fn preset(doc: &str) -> Result<Preset, String> {
+        let (mut presets, _) = parse_config(doc)?;
+        Ok(presets.pop_first().unwrap().1)
+    }
+
+    /// Commands of the first window's only pane, and its cwd
+    fn pane(preset: &Preset) -> (Option<&str>, &str) {
+        match &preset.windows[0].layout {
+            LayoutNode::Pane { cwd, command, .. } => (command.as_deref(), cwd),
+            LayoutNode::Split { .. } => panic!("not a single pane"),
+        }
+    }
+
+    fn values(values: &[(&str, &str)]) -> BTreeMap<String, String> {
+        values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn declared_vars() {
+        let declared = vars(r#"vars { project_dir; port default="8080"; retries default=3 }"#)
+            .unwrap()
+            .into_iter()
+            .map(|var| (var.name, var.default))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            declared,
+            [
+                ("project_dir".to_string(), None),
+                ("port".to_string(), Some("8080".to_string())),
+                ("retries".to_string(), Some("3".to_string())),
+            ]
+        );
+        assert!(vars("vars").unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_vars() {
+        for (doc, error) in [
+            (
+                "vars { project-dir }",
+                "Invalid variable name `project-dir`, use letters, digits and `_`",
+            ),
+            (
+                r#"vars { "2nd" }"#,
+                "Invalid variable name `2nd`, use letters, digits and `_`",
+            ),
+            (
+                "vars { cwd }",
+                "`{cwd}` is already taken, name the variable otherwise",
+            ),
+            (
+                "vars { index }",
+                "`{index}` is already taken, name the variable otherwise",
+            ),
+            (
+                "vars { port default=#true }",
+                "A variable's `default` must be a string or an integer",
+            ),
+        ] {
+            assert_eq!(vars(doc).unwrap_err(), error, "{doc}");
+        }
+        assert_eq!(
+            preset(r#"session name="x" { vars { a; }; vars { a; } }"#).unwrap_err(),
+            "Variable `a` is declared more than once"
+        );
+    }
+
+    #[test]
+    fn placeholders_of_declared_vars() {
+        let preset = preset(
+            r#"session name="{client}" cwd="~/src/{client}" {
+                vars { client; port default="8080" }
+                window name="{cwd}-{client}" { pane command="npm run dev -- --port {port}" }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(preset.name, "{client}");
+        assert_eq!(preset.windows[0].name, "{cwd}-{client}");
+        assert_eq!(pane(&preset).0, Some("npm run dev -- --port {port}"));
+    }
+
+    #[test]
+    fn other_braces_are_left_as_written() {
+        for command in [
+            "awk '{ print }' log",
+            "echo ${HOME} #{pane_id}",
+            "find . -exec rm {} +",
+            "echo {a,b}",
+        ] {
+            let doc = format!(r#"session name="x" {{ window {{ pane command="{command}" }} }}"#);
+            let preset = preset(&doc).unwrap();
+            assert_eq!(pane(&preset).0, Some(command));
+            let applied = apply_vars(&preset, &BTreeMap::new()).unwrap();
+            assert_eq!(pane(&applied).0, Some(command));
+        }
+    }
+
+    #[test]
+    fn misspelled_vars_are_errors() {
+        let doc = r#"session name="x" {
+            vars { project_dir }
+            window { pane command="cd {projct_dir} && awk '{ print }'" }
+        }"#;
+        assert_eq!(
+            preset(doc).unwrap_err(),
+            "Preset `x` uses `{projct_dir}`, which isn't declared in its `vars`, did you mean \
+             `{project_dir}`?"
+        );
+        // Not in names either, where `{cwd}` is
+        let doc = r#"session name="{clent}" { vars { client }; }"#;
+        assert_eq!(
+            preset(doc).unwrap_err(),
+            "Preset `{clent}` uses `{clent}`, which isn't declared in its `vars`, did you mean \
+             `{client}`?"
+        );
+    }
+
+    #[test]
+    fn undeclared_placeholders_are_errors() {
+        for (doc, error) in [
+            // Nothing close to suggest
+            (
+                r#"session name="x" { window { pane command="awk '{print}' log" } }"#,
+                "Preset `x` uses `{print}`, which isn't declared in its `vars`, declare it \
+                 there, or write `{ print }` if the braces aren't a placeholder",
+            ),
+            (
+                r#"session name="x" cwd="~/{dir}" { vars { port }; }"#,
+                "Preset `x` uses `{dir}`, which isn't declared in its `vars`, declare it there, \
+                 or write `{ dir }` if the braces aren't a placeholder",
+            ),
+            (
+                r#"session name="x" { window { pane command="echo {cwd}" } }"#,
+                "Preset `x` uses `{cwd}` outside of a name, where it doesn't stand for anything",
+            ),
+        ] {
+            assert_eq!(preset(doc).unwrap_err(), error, "{doc}");
+        }
+
+        // Presets that weren't parsed aren't let through either
+        let mut preset =
+            preset(r#"session name="x" { vars { port default="1" }; window; }"#).unwrap();
+        preset.cwd = "~/{dir}".to_string();
+        assert_eq!(
+            apply_vars(&preset, &BTreeMap::new()).unwrap_err(),
+            "`{dir}` isn't one of the variables of preset `x`"
+        );
+    }
+
+    #[test]
+    fn values_fill_placeholders() {
+        let preset = preset(
+            r#"session name="acme-{env}" cwd="~/src/{client}" {
+                vars { client; env default="dev"; port default="8080" }
+                window name="{cwd}" { pane command="run --port {port} --env={env} '{ print }'" }
+            }"#,
+        )
+        .unwrap();
+
+        let applied =
+            apply_vars(&preset, &values(&[("client", "acme"), ("port", "9000")])).unwrap();
+        assert_eq!(applied.name, "acme-dev");
+        assert_eq!(applied.cwd, "~/src/acme");
+        assert_eq!(applied.windows[0].name, "acme");
+        assert_eq!(
+            pane(&applied),
+            (Some("run --port 9000 --env=dev '{ print }'"), "~/src/acme")
+        );
+        // The preset itself is left for the next launch
+        assert_eq!(preset.cwd, "~/src/{client}");
+
+        // Empty values fall back to the default
+        let applied = apply_vars(&preset, &values(&[("client", "a"), ("env", "")])).unwrap();
+        assert_eq!(applied.name, "acme-dev");
+    }
+
+    #[test]
+    fn missing_values_are_errors() {
+        let preset =
+            preset(r#"session name="x" cwd="{dir}" { vars { dir; port default="1" }; }"#).unwrap();
+        for values in [
+            values(&[]),
+            values(&[("dir", "")]),
+            values(&[("port", "2")]),
+        ] {
+            assert_eq!(
+                apply_vars(&preset, &values).unwrap_err(),
+                "No value given for `dir`"
+            );
+        }
+    }
+
+    #[test]
+    fn values_must_make_valid_names() {
+        let preset = preset(
+            r#"session name="acme-{env}" {
+                vars { env; window default="main" }
+                window name="{window}"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            apply_vars(&preset, &values(&[("env", "a:b")])).unwrap_err(),
+            "Invalid session name `acme-a:b`: Can't contain `:` (character 7)"
+        );
+        assert_eq!(
+            apply_vars(&preset, &values(&[("env", "a"), ("window", "v1.2")])).unwrap_err(),
+            "Window name `v1.2` can't contain `:` or `.`"
+        );
+    }
+}
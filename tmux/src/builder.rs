@@ -0,0 +1,556 @@
+//! Builders for putting presets together in code rather than in a presets file.
+//!
+//! ```
+//! use tmux::{Layout, Preset, Window};
+//!
+//! let preset = Preset::builder("proj")
+//!     .cwd("~/proj")
+//!     .window(
+//!         Window::builder("dev").layout(Layout::hsplit([
+//!             Layout::pane().cmd("nvim").pct(60),
+//!             Layout::vsplit([Layout::pane().cmd("cargo watch"), Layout::pane()]),
+//!         ])),
+//!     )
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(preset.windows[0].layout.pane_count(), 3);
+//! ```
+//!
+//! Panes and splits without a size share what their sized siblings leave, just like in presets
+//! files, and directories and commands are inherited the same way too.
+
+use std::time::Duration;
+
+use crate::error::LayoutError;
+use crate::{LayoutNode, Percent, Preset, Size, SplitDirection, Startup, WaitFor, Window};
+
+/// A pane or split, see [`Layout::pane`], [`Layout::hsplit`] and [`Layout::vsplit`]
+#[derive(Debug, Clone)]
+pub struct Layout {
+    kind: LayoutKind,
+    /// Passed down to the panes that don't have one of their own
+    cwd: Option<String>,
+    /// Same as `cwd`, like `default_command` in presets files
+    command: Option<String>,
+    /// Invalid sizes are only reported once built
+    size: Result<Size, LayoutError>,
+    startup: Startup,
+    remain_on_exit: bool,
+    /// Only for splits
+    even: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+enum LayoutKind {
+    Pane,
+    Split {
+        direction: SplitDirection,
+        children: Vec<Layout>,
+    },
+}
+
+impl Layout {
+    fn new(kind: LayoutKind) -> Self {
+        Self {
+            kind,
+            cwd: None,
+            command: None,
+            size: Ok(Size::Auto),
+            startup: Startup::default(),
+            remain_on_exit: false,
+            even: None,
+        }
+    }
+
+    /// A single pane, running a plain shell unless given a command
+    pub fn pane() -> Self {
+        Self::new(LayoutKind::Pane)
+    }
+
+    /// Children side by side
+    pub fn hsplit(children: impl IntoIterator<Item = Layout>) -> Self {
+        Self::new(LayoutKind::Split {
+            direction: SplitDirection::Horizontal,
+            children: children.into_iter().collect(),
+        })
+    }
+
+    /// Children on top of each other
+    pub fn vsplit(children: impl IntoIterator<Item = Layout>) -> Self {
+        Self::new(LayoutKind::Split {
+            direction: SplitDirection::Vertical,
+            children: children.into_iter().collect(),
+        })
+    }
+
+    /// The pane's command, or the default of every pane in the split
+    pub fn cmd(mut self, command: impl Into<String>) -> Self {
+        self.command = Some(command.into());
+        self
+    }
+
+    /// The pane's directory, or the default of every pane in the split
+    pub fn cwd(mut self, cwd: impl Into<String>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Share of the room left by fixed-size siblings
+    pub fn pct(mut self, percent: u8) -> Self {
+        self.size = Percent::try_from(percent as i64).map(Size::Percent);
+        self
+    }
+
+    /// Fixed number of rows or columns
+    pub fn cells(mut self, cells: u16) -> Self {
+        self.size = match cells {
+            0 => Err(LayoutError::ZeroCells),
+            cells => Ok(Size::Cells(cells)),
+        };
+        self
+    }
+
+    /// Waits this long after the preset is set up before sending the pane's command
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.startup.delay = delay;
+        self
+    }
+
+    /// Holds the pane's command back until `wait_for` is met, or `timeout` has passed
+    pub fn wait_for(mut self, wait_for: WaitFor, timeout: Duration) -> Self {
+        self.startup.wait_for = Some(wait_for);
+        self.startup.timeout = timeout;
+        self
+    }
+
+    /// Keeps the pane open once its command exits, so it can be respawned
+    pub fn remain_on_exit(mut self, remain_on_exit: bool) -> Self {
+        self.remain_on_exit = remain_on_exit;
+        self
+    }
+
+    /// Whether the split's children are resized to their exact shares once created, see
+    /// [`LayoutNode::Split`]
+    pub fn even(mut self, even: bool) -> Self {
+        self.even = Some(even);
+        self
+    }
+
+    pub(crate) fn build(self, cwd: &str, command: Option<&str>) -> Result<LayoutNode, LayoutError> {
+        let size = self.size?;
+
+        let cwd = self.cwd.as_deref().unwrap_or(cwd);
+        let command = self.command.as_deref().or(command);
+        match self.kind {
+            LayoutKind::Pane => Ok(LayoutNode::Pane {
+                cwd: cwd.to_string(),
+                command: command.map(String::from),
+                size,
+                startup: self.startup,
+                remain_on_exit: self.remain_on_exit,
+            }),
+            LayoutKind::Split {
+                direction,
+                children,
+            } => {
+                let children = children
+                    .into_iter()
+                    .map(|child| child.build(cwd, command))
+                    .collect::<Result<Vec<_>, _>>()?;
+                crate::verify_split_sizes(&children)?;
+                Ok(LayoutNode::Split {
+                    direction,
+                    children,
+                    size,
+                    even: self.even,
+                })
+            }
+        }
+    }
+}
+
+/// A window under construction, see [`Window::builder`]
+#[derive(Debug, Clone)]
+pub struct WindowBuilder {
+    name: String,
+    cwd: Option<String>,
+    layout: Layout,
+    options: Vec<(String, String)>,
+    index: Option<usize>,
+}
+
+impl Window {
+    /// A window with a single pane, until given a [`WindowBuilder::layout`]
+    pub fn builder(name: impl Into<String>) -> WindowBuilder {
+        WindowBuilder {
+            name: name.into(),
+            cwd: None,
+            layout: Layout::pane(),
+            options: vec![],
+            index: None,
+        }
+    }
+}
+
+impl WindowBuilder {
+    /// The window's directory, passed down to its panes. The preset's by default.
+    pub fn cwd(mut self, cwd: impl Into<String>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// A window option set once the window is laid out, ex: `("synchronize-panes", "on")`
+    pub fn option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.push((name.into(), value.into()));
+        self
+    }
+
+    /// A fixed position in the session, counted from 1, see [`Window::index`]
+    pub fn index(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    fn build(self, cwd: &str) -> Result<Window, LayoutError> {
+        // tmux reads these as separators in targets like `session:window.pane`
+        if self.name.contains([':', '.']) {
+            return Err(LayoutError::InvalidWindowName(self.name));
+        }
+        if self.index == Some(0) {
+            return Err(LayoutError::ZeroWindowIndex(self.name));
+        }
+        let cwd = self.cwd.as_deref().unwrap_or(cwd);
+        let mut layout = self.layout.build(cwd, None)?;
+        // The root of a window always takes all of it
+        match &mut layout {
+            LayoutNode::Pane { size, .. } | LayoutNode::Split { size, .. } => {
+                *size = Size::Percent(Percent::FULL)
+            }
+        }
+        Ok(Window {
+            name: self.name,
+            cwd: cwd.to_string(),
+            layout,
+            options: self.options,
+            index: self.index,
+        })
+    }
+}
+
+/// A preset under construction, see [`Preset::builder`]
+#[derive(Debug, Clone)]
+pub struct PresetBuilder {
+    preset: Preset,
+    windows: Vec<WindowBuilder>,
+}
+
+impl Preset {
+    /// A preset starting in `~` with a single window, until given some with
+    /// [`PresetBuilder::window`]
+    pub fn builder(name: impl Into<String>) -> PresetBuilder {
+        PresetBuilder {
+            preset: Preset {
+                name: name.into(),
+                cwd: "~".to_string(),
+                running: false,
+                windows: vec![],
+                order: None,
+                pinned: false,
+                source: None,
+                position: 0,
+                color: None,
+                tags: vec![],
+                description: None,
+                vars: vec![],
+                options: vec![],
+                socket: None,
+                tmux_config: None,
+            },
+            windows: vec![],
+        }
+    }
+}
+
+impl PresetBuilder {
+    /// The session's directory, passed down to its windows
+    pub fn cwd(mut self, cwd: impl Into<String>) -> Self {
+        self.preset.cwd = cwd.into();
+        self
+    }
+
+    pub fn window(mut self, window: WindowBuilder) -> Self {
+        self.windows.push(window);
+        self
+    }
+
+    /// Accent color, ex: `blue` or `#1e90ff`
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.preset.color = Some(color.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.preset.tags.push(tag.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.preset.description = Some(description.into());
+        self
+    }
+
+    pub fn pinned(mut self, pinned: bool) -> Self {
+        self.preset.pinned = pinned;
+        self
+    }
+
+    pub fn order(mut self, order: i64) -> Self {
+        self.preset.order = Some(order);
+        self
+    }
+
+    /// A session option set once the session is created, ex: `("status-position", "top")`
+    pub fn option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.preset.options.push((name.into(), value.into()));
+        self
+    }
+
+    /// Launches the session on the tmux server at `socket` (`tmux -L <socket>`)
+    pub fn socket(mut self, socket: impl Into<String>) -> Self {
+        self.preset.socket = Some(socket.into());
+        self
+    }
+
+    /// Config file the preset's server reads if launching the session starts it
+    pub fn tmux_config(mut self, path: impl Into<String>) -> Self {
+        self.preset.tmux_config = Some(path.into());
+        self
+    }
+
+    /// Checks the layouts the same way presets files are checked, and puts the preset together
+    pub fn build(self) -> Result<Preset, LayoutError> {
+        let mut preset = self.preset;
+        let windows = if self.windows.is_empty() {
+            vec![Window::builder("main")]
+        } else {
+            self.windows
+        };
+        for window in windows {
+            let window = window.build(&preset.cwd)?;
+            // muffin tells windows apart by name when updating a running preset
+            if preset.windows.iter().any(|w| w.name == window.name) {
+                return Err(LayoutError::DuplicateWindow(window.name));
+            }
+            preset.windows.push(window);
+        }
+        Ok(preset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{plan_preset, verify_split_sizes, window_panes};
+
+    /// xorshift, so every run tries the same layouts
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+    }
+
+    /// A random layout, along with whether it's valid and the cwd and command each of its panes
+    /// should end up with, depth first
+    struct Generated {
+        layout: Layout,
+        valid: bool,
+        panes: Vec<(String, Option<String>)>,
+    }
+
+    /// Mostly sensible sizes, with now and then one that can't be
+    fn size(rng: &mut Rng, layout: Layout) -> (Layout, Size, bool) {
+        match rng.below(20) {
+            0..=9 => (layout, Size::Auto, true),
+            10..=16 => {
+                let percent = 1 + rng.below(60) as u8;
+                let size = Size::Percent(Percent::try_from(percent as i64).unwrap());
+                (layout.pct(percent), size, true)
+            }
+            17 | 18 => {
+                let cells = 1 + rng.below(30) as u16;
+                (layout.cells(cells), Size::Cells(cells), true)
+            }
+            _ => match rng.below(3) {
+                0 => (layout.pct(0), Size::Auto, false),
+                1 => (layout.pct(101 + rng.below(100) as u8), Size::Auto, false),
+                _ => (layout.cells(0), Size::Auto, false),
+            },
+        }
+    }
+
+    fn generate(
+        rng: &mut Rng,
+        depth: usize,
+        cwd: &str,
+        command: Option<&str>,
+    ) -> (Generated, Size) {
+        let own_cwd = (rng.below(4) == 0).then(|| format!("{cwd}/{}", rng.below(10)));
+        let own_command = (rng.below(4) == 0).then(|| format!("cmd{}", rng.below(10)));
+        let cwd = own_cwd.clone().unwrap_or(cwd.to_string());
+        let command = own_command.clone().or(command.map(String::from));
+
+        let (mut layout, mut valid, panes) = if depth == 0 || rng.below(3) == 0 {
+            (Layout::pane(), true, vec![(cwd.clone(), command.clone())])
+        } else {
+            // Now and then an empty one
+            let count = if rng.below(30) == 0 {
+                0
+            } else {
+                1 + rng.below(4)
+            };
+            let mut children = vec![];
+            let mut sizes = vec![];
+            let mut valid = count > 0;
+            let mut panes = vec![];
+            for _ in 0..count {
+                let (child, size) = generate(rng, depth - 1, &cwd, command.as_deref());
+                valid &= child.valid;
+                panes.extend(child.panes);
+                children.push(child.layout);
+                sizes.push(size);
+            }
+            let percents = sizes
+                .iter()
+                .map(|size| match size {
+                    Size::Percent(percent) => percent.get() as u32,
+                    _ => 0,
+                })
+                .sum::<u32>();
+            let has_auto = sizes.contains(&Size::Auto);
+            valid &= percents <= 100 && !(has_auto && percents >= 100);
+            let split = match rng.below(2) {
+                0 => Layout::hsplit(children),
+                _ => Layout::vsplit(children),
+            };
+            (split, valid, panes)
+        };
+        if let Some(cwd) = own_cwd {
+            layout = layout.cwd(cwd);
+        }
+        if let Some(command) = own_command {
+            layout = layout.cmd(command);
+        }
+        let (layout, size, size_valid) = size(rng, layout);
+        valid &= size_valid;
+        (
+            Generated {
+                layout,
+                valid,
+                panes,
+            },
+            size,
+        )
+    }
+
+    /// Checks every split of `node`, collecting its panes depth first
+    fn verified(node: &LayoutNode, panes: &mut Vec<(String, Option<String>)>) {
+        match node {
+            LayoutNode::Pane { cwd, command, .. } => panes.push((cwd.clone(), command.clone())),
+            LayoutNode::Split { children, .. } => {
+                verify_split_sizes(children).unwrap();
+                children.iter().for_each(|child| verified(child, panes));
+            }
+        }
+    }
+
+    #[test]
+    fn built_layouts_pass_verification() {
+        let (mut built, mut refused) = (0, 0);
+        for seed in 1..=500 {
+            let mut rng = Rng(seed);
+            let (generated, _) = generate(&mut rng, 3, "/proj", None);
+            let result = Preset::builder("proj")
+                .cwd("/proj")
+                .window(Window::builder("dev").layout(generated.layout))
+                .build();
+            let Ok(preset) = result else {
+                assert!(!generated.valid, "seed {seed}: {result:?}");
+                refused += 1;
+                continue;
+            };
+            assert!(
+                generated.valid,
+                "seed {seed}: {:?}",
+                preset.windows[0].layout
+            );
+            built += 1;
+
+            let layout = &preset.windows[0].layout;
+            assert_eq!(layout.size(), Size::Percent(Percent::FULL));
+            let mut panes = vec![];
+            verified(layout, &mut panes);
+            assert_eq!(panes, generated.panes, "seed {seed}");
+            assert_eq!(layout.pane_count(), panes.len());
+
+            // Laid out, every pane gets created once
+            plan_preset(&preset).unwrap();
+            let mut planned = window_panes(&preset.windows[0])
+                .into_iter()
+                .map(|(cwd, command)| (cwd.to_string(), command.map(String::from)))
+                .collect::<Vec<_>>();
+            planned.sort();
+            panes.sort();
+            assert_eq!(planned, panes, "seed {seed}");
+        }
+        // Both sides got a fair share of the layouts
+        assert!(
+            built > 100 && refused > 100,
+            "{built} built, {refused} refused"
+        );
+    }
+
+    #[test]
+    fn invalid_windows() {
+        let build = |windows: &[&str]| {
+            windows
+                .iter()
+                .fold(Preset::builder("proj"), |preset, name| {
+                    preset.window(Window::builder(*name))
+                })
+                .build()
+        };
+        assert_eq!(
+            build(&["a", "a.b"]).unwrap_err(),
+            LayoutError::InvalidWindowName("a.b".to_string())
+        );
+        assert_eq!(
+            build(&["a:b"]).unwrap_err(),
+            LayoutError::InvalidWindowName("a:b".to_string())
+        );
+        assert_eq!(
+            build(&["a", "b", "a"]).unwrap_err(),
+            LayoutError::DuplicateWindow("a".to_string())
+        );
+        assert_eq!(
+            Preset::builder("proj")
+                .window(Window::builder("a").index(0))
+                .build()
+                .unwrap_err(),
+            LayoutError::ZeroWindowIndex("a".to_string())
+        );
+        let preset = build(&[]).unwrap();
+        assert_eq!(preset.windows.len(), 1);
+        assert_eq!(preset.windows[0].name, "main");
+        assert_eq!(preset.windows[0].cwd, "~");
+    }
+}
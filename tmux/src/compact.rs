@@ -0,0 +1,235 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::{INHERIT_CWD, LayoutNode, Percent, Size, SplitDirection, Startup};
+
+impl LayoutNode {
+    /// The shape of the layout on a single line, leaving out what runs in the panes: `p` for a
+    /// pane, `h` or `v` for a split laid out side by side or stacked, each followed by its size
+    /// if it has one (`30%` or `12c`), and splits then by their children in parentheses.
+    ///
+    /// ex: `h(p30%,v60%(p,p10c),p)`
+    pub fn to_compact(&self) -> String {
+        let mut out = String::new();
+        write_node(self, &mut out);
+        out
+    }
+
+    /// Reads back what [`LayoutNode::to_compact`] wrote. Panes come back without a command,
+    /// starting in the current directory, and splits without [`LayoutNode::Split::even`].
+    pub fn from_compact(compact: &str) -> Result<LayoutNode, String> {
+        let mut chars = compact.trim().chars().peekable();
+        let node = read_node(&mut chars)?;
+        match chars.next() {
+            None => Ok(node),
+            Some(c) => Err(format!("Unexpected `{c}` after the layout")),
+        }
+    }
+}
+
+fn write_node(node: &LayoutNode, out: &mut String) {
+    match node {
+        LayoutNode::Pane { size, .. } => {
+            out.push('p');
+            write_size(*size, out);
+        }
+        LayoutNode::Split {
+            direction,
+            children,
+            size,
+            ..
+        } => {
+            out.push(match direction {
+                SplitDirection::Horizontal => 'h',
+                SplitDirection::Vertical => 'v',
+            });
+            write_size(*size, out);
+            out.push('(');
+            for (i, child) in children.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_node(child, out);
+            }
+            out.push(')');
+        }
+    }
+}
+
+fn write_size(size: Size, out: &mut String) {
+    match size {
+        Size::Percent(percent) => out.push_str(&format!("{percent}%")),
+        Size::Cells(cells) => out.push_str(&format!("{cells}c")),
+        Size::Auto => {}
+    }
+}
+
+fn read_node(chars: &mut Peekable<Chars>) -> Result<LayoutNode, String> {
+    let direction = match chars.next() {
+        Some('p') => {
+            return Ok(LayoutNode::Pane {
+                cwd: INHERIT_CWD.to_string(),
+                command: None,
+                size: read_size(chars)?,
+                startup: Startup::default(),
+                remain_on_exit: false,
+            });
+        }
+        Some('h') => SplitDirection::Horizontal,
+        Some('v') => SplitDirection::Vertical,
+        Some(c) => return Err(format!("Expected `p`, `h` or `v`, got `{c}`")),
+        None => return Err("Expected `p`, `h` or `v`, got the end".to_string()),
+    };
+    let size = read_size(chars)?;
+    if chars.next() != Some('(') {
+        return Err("Expected `(` after a split".to_string());
+    }
+
+    let mut children = vec![read_node(chars)?];
+    loop {
+        match chars.next() {
+            Some(',') => children.push(read_node(chars)?),
+            Some(')') => break,
+            _ => return Err("Expected `,` or `)` in a split".to_string()),
+        }
+    }
+    Ok(LayoutNode::Split {
+        direction,
+        children,
+        size,
+        even: None,
+    })
+}
+
+fn read_size(chars: &mut Peekable<Chars>) -> Result<Size, String> {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    if digits.is_empty() {
+        return Ok(Size::Auto);
+    }
+    let value = digits
+        .parse::<i64>()
+        .map_err(|_| format!("Size `{digits}` is too large"))?;
+    match chars.next() {
+        Some('%') => Ok(Size::Percent(Percent::try_from(value)?)),
+        Some('c') => u16::try_from(value)
+            .ok()
+            .filter(|&cells| cells > 0)
+            .map(Size::Cells)
+            .ok_or_else(|| format!("`{value}c` isn't a size in cells")),
+        _ => Err(format!("Expected `%` or `c` after `{digits}`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Layout;
+
+    /// `layout` as read back from its compact form: without commands, in the current directory
+    fn node(layout: Layout) -> LayoutNode {
+        layout.build(INHERIT_CWD, None).unwrap()
+    }
+
+    #[test]
+    fn to_compact() {
+        let layout = node(Layout::hsplit([
+            Layout::pane().pct(30),
+            Layout::vsplit([Layout::pane(), Layout::pane().cells(10)]).pct(60),
+            Layout::pane(),
+        ]));
+        assert_eq!(layout.to_compact(), "h(p30%,v60%(p,p10c),p)");
+        assert_eq!(node(Layout::pane()).to_compact(), "p");
+    }
+
+    #[test]
+    fn round_trips() {
+        for layout in [
+            Layout::pane(),
+            Layout::pane().pct(100),
+            Layout::hsplit([Layout::pane(), Layout::pane()]),
+            Layout::vsplit([Layout::pane().cells(1), Layout::pane().cells(65535)]),
+            Layout::hsplit([
+                Layout::pane().pct(30),
+                Layout::vsplit([
+                    Layout::pane(),
+                    Layout::hsplit([Layout::pane().cells(20), Layout::pane(), Layout::pane()])
+                        .pct(40),
+                ])
+                .pct(60),
+                Layout::pane(),
+            ]),
+            Layout::vsplit([Layout::hsplit([Layout::vsplit([
+                Layout::pane(),
+                Layout::pane(),
+            ])])]),
+        ] {
+            let layout = node(layout);
+            let compact = layout.to_compact();
+            assert_eq!(
+                LayoutNode::from_compact(&compact).unwrap(),
+                layout,
+                "{compact}"
+            );
+        }
+    }
+
+    #[test]
+    fn commands_and_cwds_are_left_out() {
+        let layout = Layout::hsplit([Layout::pane().cmd("vim").cwd("/src"), Layout::pane()])
+            .build("/proj", Some("ls"))
+            .unwrap();
+        let read = LayoutNode::from_compact(&layout.to_compact()).unwrap();
+        assert_eq!(read, node(Layout::hsplit([Layout::pane(), Layout::pane()])));
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_fine() {
+        assert_eq!(
+            LayoutNode::from_compact("  h(p,p)\n").unwrap(),
+            node(Layout::hsplit([Layout::pane(), Layout::pane()]))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        for (compact, error) in [
+            ("", "Expected `p`, `h` or `v`, got the end"),
+            ("x", "Expected `p`, `h` or `v`, got `x`"),
+            ("h", "Expected `(` after a split"),
+            ("hp", "Expected `(` after a split"),
+            ("h(", "Expected `p`, `h` or `v`, got the end"),
+            ("h(p", "Expected `,` or `)` in a split"),
+            ("h(p;p)", "Expected `,` or `)` in a split"),
+            ("h(p,)", "Expected `p`, `h` or `v`, got `)`"),
+            ("h()", "Expected `p`, `h` or `v`, got `)`"),
+            ("p)", "Unexpected `)` after the layout"),
+            ("h(p,p)p", "Unexpected `p` after the layout"),
+            ("p30", "Expected `%` or `c` after `30`"),
+            ("p30x", "Expected `%` or `c` after `30`"),
+            ("p0c", "`0c` isn't a size in cells"),
+            ("p65536c", "`65536c` isn't a size in cells"),
+            (
+                "p99999999999999999999%",
+                "Size `99999999999999999999` is too large",
+            ),
+        ] {
+            assert_eq!(
+                LayoutNode::from_compact(compact).unwrap_err(),
+                error,
+                "{compact}"
+            );
+        }
+        // Out of range percents say so like they do in presets
+        for compact in ["p0%", "p101%"] {
+            assert!(
+                LayoutNode::from_compact(compact)
+                    .unwrap_err()
+                    .contains("between 1 and 100 percent"),
+                "{compact}"
+            );
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use crate::{INHERIT_CWD, LivePane, LiveWindow, Preset, window_panes};
+
+/// How a running session has drifted from its preset, see [`compare_session`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PresetDiff {
+    /// Windows of the session the preset doesn't have
+    pub extra: Vec<String>,
+    /// Windows of the preset the session doesn't have
+    pub missing: Vec<String>,
+    /// Windows both have, in the preset's order
+    pub windows: Vec<WindowDiff>,
+}
+
+impl PresetDiff {
+    /// Whether the session still looks like the preset, as far as windows, pane counts and
+    /// directories go
+    pub fn is_empty(&self) -> bool {
+        self.extra.is_empty() && self.missing.is_empty() && self.windows.iter().all(|w| w.is_same())
+    }
+}
+
+/// A window the session and the preset both have
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowDiff {
+    pub name: String,
+    pub preset_panes: usize,
+    pub live_panes: usize,
+    /// Panes in another directory than the preset starts them in: their index among the
+    /// window's panes, the preset's directory and the pane's
+    pub moved: Vec<(usize, String, String)>,
+}
+
+impl WindowDiff {
+    pub fn is_same(&self) -> bool {
+        self.preset_panes == self.live_panes && self.moved.is_empty()
+    }
+}
+
+/// Compares `preset` with the windows of its running session, along with each window's panes as
+/// given by [`Server::list_panes`](crate::Server::list_panes).
+///
+/// Windows are matched by name, like [`Server::update_preset`](crate::Server::update_preset)
+/// does, so a renamed window shows up as both extra and missing. Panes are matched by index, and
+/// those inheriting their directory aren't compared, as it's only known once they're created.
+/// Neither are commands: tmux only knows what a pane is running now, not what started it.
+pub fn compare_session(preset: &Preset, live: &[(LiveWindow, Vec<LivePane>)]) -> PresetDiff {
+    let mut unmatched = live.iter().collect::<Vec<_>>();
+    let mut diff = PresetDiff::default();
+
+    for window_cfg in preset.windows.iter() {
+        let Some(position) = unmatched
+            .iter()
+            .position(|(w, _)| w.name == window_cfg.name)
+        else {
+            diff.missing.push(window_cfg.name.clone());
+            continue;
+        };
+        let (_, panes) = unmatched.remove(position);
+
+        let expected = window_panes(window_cfg);
+        let moved = expected
+            .iter()
+            .zip(panes.iter())
+            .enumerate()
+            .filter(|(_, ((cwd, _), _))| *cwd != INHERIT_CWD)
+            .filter(|(_, ((cwd, _), pane))| normalize(cwd) != normalize(&pane.cwd))
+            .map(|(index, ((cwd, _), pane))| (index, cwd.to_string(), pane.cwd.clone()))
+            .collect();
+        diff.windows.push(WindowDiff {
+            name: window_cfg.name.clone(),
+            preset_panes: expected.len(),
+            live_panes: panes.len(),
+            moved,
+        });
+    }
+
+    diff.extra = unmatched.into_iter().map(|(w, _)| w.name.clone()).collect();
+    diff
+}
+
+/// `path` with a leading `~` standing for `$HOME` and without a trailing `/`, as the shell would
+/// `cd` into it
+fn normalize(path: &str) -> String {
+    let path = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{rest}", std::env::var("HOME").unwrap_or_default())
+        }
+        _ => path.to_string(),
+    };
+    match path.trim_end_matches('/') {
+        "" => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Layout, Window};
+
+    fn preset() -> Preset {
+        Preset::builder("proj")
+            .cwd("/proj")
+            .window(Window::builder("editor").layout(Layout::hsplit([
+                Layout::pane().cmd("nvim"),
+                Layout::pane().cwd("/proj/src"),
+            ])))
+            .window(Window::builder("server").layout(Layout::pane().cmd("cargo run")))
+            .window(Window::builder("shell").cwd(INHERIT_CWD))
+            .build()
+            .unwrap()
+    }
+
+    fn window(index: usize, name: &str, panes: &[(&str, &str)]) -> (LiveWindow, Vec<LivePane>) {
+        let window = LiveWindow {
+            index,
+            name: name.to_string(),
+            command: panes[0].0.to_string(),
+            active: index == 0,
+        };
+        let panes = panes
+            .iter()
+            .enumerate()
+            .map(|(index, (command, cwd))| LivePane {
+                id: format!("%{index}"),
+                index,
+                command: command.to_string(),
+                cwd: cwd.to_string(),
+                active: index == 0,
+                dead: false,
+                zoomed: false,
+                left: 0,
+                top: 0,
+                width: 80,
+                height: 24,
+            })
+            .collect();
+        (window, panes)
+    }
+
+    /// The session as the preset starts it
+    fn launched() -> Vec<(LiveWindow, Vec<LivePane>)> {
+        vec![
+            window(0, "editor", &[("nvim", "/proj"), ("zsh", "/proj/src")]),
+            window(1, "server", &[("cargo", "/proj")]),
+            window(2, "shell", &[("zsh", "/home/me")]),
+        ]
+    }
+
+    fn same(name: &str, panes: usize) -> WindowDiff {
+        WindowDiff {
+            name: name.to_string(),
+            preset_panes: panes,
+            live_panes: panes,
+            moved: vec![],
+        }
+    }
+
+    #[test]
+    fn launched_session_is_the_same() {
+        let diff = compare_session(&preset(), &launched());
+        assert_eq!(
+            diff,
+            PresetDiff {
+                extra: vec![],
+                missing: vec![],
+                windows: vec![same("editor", 2), same("server", 1), same("shell", 1)],
+            }
+        );
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn added_window() {
+        let mut live = launched();
+        live.insert(1, window(3, "logs", &[("tail", "/var/log")]));
+        let diff = compare_session(&preset(), &live);
+        assert_eq!(diff.extra, ["logs"]);
+        assert!(diff.missing.is_empty());
+        assert_eq!(diff.windows.len(), 3);
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn removed_window() {
+        let mut live = launched();
+        live.remove(1);
+        let diff = compare_session(&preset(), &live);
+        assert!(diff.extra.is_empty());
+        assert_eq!(diff.missing, ["server"]);
+        assert_eq!(diff.windows, [same("editor", 2), same("shell", 1)]);
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn renamed_window_is_both_extra_and_missing() {
+        let mut live = launched();
+        live[1].0.name = "backend".to_string();
+        let diff = compare_session(&preset(), &live);
+        assert_eq!(diff.extra, ["backend"]);
+        assert_eq!(diff.missing, ["server"]);
+        assert_eq!(diff.windows, [same("editor", 2), same("shell", 1)]);
+    }
+
+    #[test]
+    fn reordered_windows_are_matched_by_name() {
+        let mut live = launched();
+        live.reverse();
+        assert!(compare_session(&preset(), &live).is_empty());
+    }
+
+    #[test]
+    fn changed_pane_count() {
+        let mut live = launched();
+        live[0] = window(0, "editor", &[("nvim", "/proj")]);
+        live[1] = window(1, "server", &[("cargo", "/proj"), ("zsh", "/proj")]);
+        let diff = compare_session(&preset(), &live);
+        assert_eq!(
+            diff.windows,
+            [
+                WindowDiff {
+                    name: "editor".to_string(),
+                    preset_panes: 2,
+                    live_panes: 1,
+                    moved: vec![],
+                },
+                WindowDiff {
+                    name: "server".to_string(),
+                    preset_panes: 1,
+                    live_panes: 2,
+                    moved: vec![],
+                },
+                same("shell", 1),
+            ]
+        );
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn changed_cwd() {
+        let mut live = launched();
+        live[0].1[1].cwd = "/proj/tests".to_string();
+        // Inherited directories aren't compared
+        live[2].1[0].cwd = "/tmp".to_string();
+        let diff = compare_session(&preset(), &live);
+        assert_eq!(
+            diff.windows[0].moved,
+            [(1, "/proj/src".to_string(), "/proj/tests".to_string())]
+        );
+        assert!(diff.windows[1].is_same() && diff.windows[2].is_same());
+    }
+
+    #[test]
+    fn trailing_slashes_and_home_are_the_same_cwd() {
+        let home = std::env::var("HOME").unwrap();
+        let preset = Preset::builder("proj")
+            .cwd("~/proj/")
+            .window(Window::builder("a"))
+            .build()
+            .unwrap();
+        let live = [window(0, "a", &[("zsh", &format!("{home}/proj"))])];
+        assert!(compare_session(&preset, &live).is_empty());
+    }
+
+    #[test]
+    fn changed_command_is_not_drift() {
+        let mut live = launched();
+        // The editor was quit, and something else runs in the server window
+        live[0].1[0].command = "zsh".to_string();
+        live[1].1[0].command = "htop".to_string();
+        assert!(compare_session(&preset(), &live).is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Everything that can go wrong while talking to tmux.
+///
+/// Every variant carries the full argv of the failing invocation so it's obvious which of the many
+/// commands issued while spawning a preset was the culprit.
+#[derive(Debug, Clone)]
+pub enum TmuxError {
+    /// The command could not be started at all (e.g. tmux is not installed)
+    Spawn {
+        command: Vec<String>,
+        message: String,
+    },
+    /// The command ran but exited unsuccessfully
+    Failed {
+        command: Vec<String>,
+        code: Option<i32>,
+        stderr: String,
+    },
+    /// ssh could not reach the remote host, so tmux never ran
+    Connection {
+        command: Vec<String>,
+        host: String,
+        stderr: String,
+    },
+    /// The command succeeded but its output was not what we expected
+    Parse {
+        command: Vec<String>,
+        message: String,
+    },
+    /// No tmux server is running on the socket, which is also what tmux reports once the last
+    /// session was killed
+    NoServer {
+        command: Vec<String>,
+        stderr: String,
+    },
+    /// tmux didn't exit in time, ex: a server stuck on a hook, and was killed
+    Timeout {
+        command: Vec<String>,
+        elapsed: Duration,
+    },
+    /// A session name tmux wouldn't take as is, caught before the command was run
+    InvalidName {
+        command: Vec<String>,
+        name: String,
+        error: NameError,
+    },
+    /// A layout that can't be laid out, caught while planning before any command was run
+    InvalidLayout {
+        command: Vec<String>,
+        error: LayoutError,
+    },
+}
+
+impl TmuxError {
+    /// The argv of the invocation that failed
+    pub fn command(&self) -> &[String] {
+        match self {
+            TmuxError::Spawn { command, .. } => command,
+            TmuxError::Failed { command, .. } => command,
+            TmuxError::Connection { command, .. } => command,
+            TmuxError::Parse { command, .. } => command,
+            TmuxError::NoServer { command, .. } => command,
+            TmuxError::Timeout { command, .. } => command,
+            TmuxError::InvalidName { command, .. } => command,
+            TmuxError::InvalidLayout { command, .. } => command,
+        }
+    }
+
+    /// What went wrong, without the command, ex: `Can't join a pane to its own window` for
+    /// showing in a notification
+    pub fn reason(&self) -> String {
+        let message = self.message();
+        let mut chars = message.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => message,
+        }
+    }
+
+    /// Whether tmux refused to split a pane for lack of room, which it reports as `no space for
+    /// new pane` (or `pane too small` before 3.0)
+    pub fn is_no_space(&self) -> bool {
+        matches!(self, TmuxError::Failed { stderr, .. }
+            if stderr.contains("no space for new pane") || stderr.contains("pane too small"))
+    }
+
+    fn message(&self) -> String {
+        match self {
+            TmuxError::Spawn { message, .. } => message.clone(),
+            TmuxError::Failed { stderr, .. } => stderr.trim().to_string(),
+            TmuxError::Connection { host, stderr, .. } => {
+                format!("Could not reach '{host}': {}", stderr.trim())
+            }
+            TmuxError::Parse { message, .. } => message.clone(),
+            TmuxError::NoServer { stderr, .. } => stderr.trim().to_string(),
+            TmuxError::Timeout { elapsed, .. } => {
+                format!("tmux didn't answer within {:.1}s", elapsed.as_secs_f32())
+            }
+            TmuxError::InvalidName { name, error, .. } => {
+                format!("Invalid session name `{name}`: {error}")
+            }
+            TmuxError::InvalidLayout { error, .. } => error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for TmuxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (`{}`)",
+            self.message(),
+            format_command(self.command())
+        )
+    }
+}
+
+impl std::error::Error for TmuxError {}
+
+impl From<TmuxError> for String {
+    fn from(err: TmuxError) -> Self {
+        err.to_string()
+    }
+}
+
+/// Renders an argv as a single readable line, quoting arguments that contain whitespace
+pub(crate) fn format_command(command: &[String]) -> String {
+    command
+        .iter()
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("{arg:?}")
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Why a layout can't be laid out, see [`crate::verify_split_sizes`] and [`crate::PresetBuilder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    /// A split without any panes or splits in it
+    EmptySplit,
+    /// Percents in a split adding up to more than all of it
+    PercentTotal(u32),
+    /// Percents in a split taking all of it while other children have no size
+    NoRoomForAuto,
+    /// A percent outside of 1 to 100
+    InvalidPercent(i64),
+    /// A size of zero cells
+    ZeroCells,
+    /// A window name tmux would read as a target separator
+    InvalidWindowName(String),
+    /// Two windows of a preset named the same
+    DuplicateWindow(String),
+    /// A window put at index 0, when they're counted from 1
+    ZeroWindowIndex(String),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::EmptySplit => write!(f, "Split nodes must contain children"),
+            LayoutError::PercentTotal(total) => {
+                write!(f, "Sizes in a split add up to {total}%")
+            }
+            LayoutError::NoRoomForAuto => {
+                write!(
+                    f,
+                    "Sizes in a split leave no room for the panes without one"
+                )
+            }
+            LayoutError::InvalidPercent(percent) => {
+                write!(f, "`size` must be between 1 and 100 percent, got {percent}")
+            }
+            LayoutError::ZeroCells => write!(f, "A size in cells must be positive, got 0"),
+            LayoutError::InvalidWindowName(name) => {
+                write!(f, "Window name `{name}` can't contain `:` or `.`")
+            }
+            LayoutError::DuplicateWindow(name) => {
+                write!(f, "More than one window is named `{name}`")
+            }
+            LayoutError::ZeroWindowIndex(name) => {
+                write!(f, "`index` of window `{name}` must be 1 or more")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+impl From<LayoutError> for String {
+    fn from(err: LayoutError) -> Self {
+        err.to_string()
+    }
+}
+
+/// Why a session name can't be used, see [`crate::validate_session_name`]. Positions are counted
+/// in characters, from 1.
+///
+/// Shown as is under a name being typed, so it doesn't repeat the name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    Empty,
+    /// `:` or `.`, which tmux reads as target separators, or `$` and `\`, which it escapes
+    Forbidden {
+        ch: char,
+        position: usize,
+    },
+    Control {
+        ch: char,
+        position: usize,
+    },
+    /// Whitespace at the start or the end
+    Whitespace {
+        ch: char,
+        position: usize,
+    },
+    TooLong {
+        len: usize,
+        max: usize,
+    },
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameError::Empty => write!(f, "Can't be empty"),
+            NameError::Forbidden { ch, position } => {
+                write!(f, "Can't contain `{ch}` (character {position})")
+            }
+            NameError::Control { ch, position } => {
+                write!(
+                    f,
+                    "Can't contain {} (character {position})",
+                    ch.escape_unicode()
+                )
+            }
+            NameError::Whitespace { ch, position: 1 } => write!(f, "Can't start with {ch:?}"),
+            NameError::Whitespace { ch, position } => {
+                write!(f, "Can't end with {ch:?} (character {position})")
+            }
+            NameError::TooLong { len, max } => {
+                write!(f, "Can't be longer than {max} characters, got {len}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NameError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(stderr: &str) -> TmuxError {
+        TmuxError::Failed {
+            command: vec!["split-window".to_string()],
+            code: Some(1),
+            stderr: stderr.to_string(),
+        }
+    }
+
+    #[test]
+    fn no_space_is_told_from_other_failures() {
+        assert!(failed("no space for new pane\n").is_no_space());
+        // What tmux said before 3.0
+        assert!(failed("create pane failed: pane too small\n").is_no_space());
+        assert!(!failed("can't find pane: %9\n").is_no_space());
+        // Only a failing tmux says so
+        let parse = TmuxError::Parse {
+            command: vec![],
+            message: "no space for new pane".to_string(),
+        };
+        assert!(!parse.is_no_space());
+    }
+}
@@ -0,0 +1,368 @@
+use std::fmt::Write;
+
+use crate::plan::plan_preset;
+use crate::plan::{PlannedCommand, PlannedPending, SpawnPlan, Target, TmuxOp};
+use crate::server::shell_quote;
+use crate::{LayoutError, Preset, Version, exact_target};
+
+/// Renders a POSIX shell script that recreates `preset` with plain tmux commands, for machines
+/// without muffin.
+///
+/// Commands held back by startup options each wait in a background job, like they would when
+/// spawned by muffin, and the script only exits once all of them were sent. The script sticks to
+/// flags the oldest supported tmux understands, as it may run anywhere.
+pub fn export_script(preset: &Preset) -> Result<String, LayoutError> {
+    let plan = plan_preset(preset)?;
+    let mut script = String::new();
+
+    // Writing to a String can't fail
+    let _ = writeln!(script, "#!/bin/sh");
+    let _ = writeln!(
+        script,
+        "# Recreates the tmux session `{}`, generated by muffin",
+        preset.name
+    );
+    let _ = writeln!(script, "set -e");
+    let _ = writeln!(script);
+
+    let _ = writeln!(
+        script,
+        "if tmux has-session -t {} 2>/dev/null; then",
+        shell_quote(&exact_target(&preset.name))
+    );
+    let _ = writeln!(
+        script,
+        "    echo {} >&2",
+        shell_quote(&format!("Session '{}' already exists", preset.name))
+    );
+    let _ = writeln!(script, "    exit 1");
+    let _ = writeln!(script, "fi");
+    let _ = writeln!(script);
+
+    let (lines, window_vars) = command_lines(&plan, &preset.name, Version::MINIMUM);
+    for line in lines {
+        let _ = writeln!(script, "{line}");
+    }
+
+    if plan.pending.is_empty() {
+        return Ok(script);
+    }
+
+    let _ = writeln!(script);
+    let _ = writeln!(
+        script,
+        "# Commands that wait on a delay or a condition first"
+    );
+    if plan.pending.iter().any(|p| p.startup.wait_for.is_some()) {
+        let _ = writeln!(script, "{WAIT_FOR_FUNCTION}");
+    }
+    for pending in plan.pending.iter() {
+        let _ = writeln!(script, "(");
+        write_pending(&mut script, pending, &window_vars);
+        let _ = writeln!(script, ") &");
+    }
+    let _ = writeln!(script, "wait");
+
+    Ok(script)
+}
+
+/// The tmux invocations launching `preset` on tmux `version` runs, one shell-quoted line each,
+/// for showing instead of running.
+///
+/// They're the lines [`export_script`] runs right away, rendered for `version`. Held-back
+/// commands come last, each noting what it waits on.
+pub fn dry_run(preset: &Preset, version: Version) -> Result<Vec<String>, LayoutError> {
+    let plan = plan_preset(preset)?;
+    let (mut lines, window_vars) = command_lines(&plan, &preset.name, version);
+
+    for pending in plan.pending.iter() {
+        let send = tmux_line(&send_pending(pending), &window_vars, version);
+        let startup = &pending.startup;
+        let mut waits = vec![];
+        if !startup.delay.is_zero() {
+            waits.push(format!("after {}ms", startup.delay.as_millis()));
+        }
+        if let Some(wait_for) = &startup.wait_for {
+            waits.push(format!(
+                "once {wait_for} is ready, giving up after {}s",
+                startup.timeout.as_secs()
+            ));
+        }
+        lines.push(format!("{send}  # sent {}", waits.join(" and ")));
+    }
+
+    Ok(lines)
+}
+
+/// The commands of `plan` that run right away as lines of shell for tmux `version`, along with
+/// the variables the windows' ids were captured into.
+///
+/// Window ids only exist once tmux created the windows, so they're captured into `$w0`, `$w1`,
+/// ... as the windows are created.
+fn command_lines(plan: &SpawnPlan, session: &str, version: Version) -> (Vec<String>, Vec<String>) {
+    let mut lines = vec![];
+    let mut window_vars = vec![];
+    for (i, command) in plan.commands.iter().enumerate() {
+        let line = tmux_line(command, &window_vars, version);
+        if command.creates_window() {
+            let var = format!("w{}", window_vars.len());
+            lines.push(format!("{var}=$({line})"));
+            window_vars.push(format!("\"${var}\""));
+        } else if command.optional {
+            // Like muffin, carry on when an option isn't known to this tmux
+            lines.push(format!("{line} || true"));
+        } else {
+            lines.push(line);
+        }
+        if i == 0 && plan.uses_indices() {
+            lines.push(base_line(session));
+        }
+    }
+    (lines, window_vars)
+}
+
+/// Polls a condition once a second, giving up after the given number of seconds
+const WAIT_FOR_FUNCTION: &str = r#"wait_for() {
+    remaining=$1
+    shift
+    until "$@" 2>/dev/null; do
+        [ "$remaining" -le 0 ] && return 1
+        remaining=$((remaining - 1))
+        sleep 1
+    done
+}"#;
+
+fn write_pending(script: &mut String, pending: &PlannedPending, window_vars: &[String]) {
+    let startup = &pending.startup;
+    let send = tmux_line(&send_pending(pending), window_vars, Version::MINIMUM);
+
+    if !startup.delay.is_zero() {
+        // POSIX sleep only takes whole seconds
+        let _ = writeln!(
+            script,
+            "    sleep {}",
+            startup.delay.as_millis().div_ceil(1000)
+        );
+    }
+
+    match &startup.wait_for {
+        None => {
+            let _ = writeln!(script, "    {send}");
+        }
+        Some(wait_for) => {
+            let condition = wait_for.shell_condition();
+            let gave_up = format!(
+                "{}: gave up on `{}` after waiting {}s for {wait_for}",
+                pending.label,
+                pending.command,
+                startup.timeout.as_secs()
+            );
+            let _ = writeln!(
+                script,
+                "    if wait_for {} {condition}; then",
+                startup.timeout.as_secs()
+            );
+            let _ = writeln!(script, "        {send}");
+            let _ = writeln!(script, "    else");
+            let _ = writeln!(script, "        echo {} >&2", shell_quote(&gave_up));
+            let _ = writeln!(script, "    fi");
+        }
+    }
+}
+
+/// Typing `pending`'s command into its pane, as a planned command
+fn send_pending(pending: &PlannedPending) -> PlannedCommand {
+    PlannedCommand {
+        op: TmuxOp::SendKeys {
+            keys: pending.command.clone(),
+        },
+        target: Some(pending.target.clone()),
+        optional: false,
+    }
+}
+
+/// Captures the session's `base-index` into `$base`, once the session exists, for the window
+/// indices counted from it
+fn base_line(session: &str) -> String {
+    format!(
+        "base=$(tmux display-message -p -t {} {})",
+        shell_quote(&format!("{}:", exact_target(session))),
+        shell_quote("#{base-index}")
+    )
+}
+
+/// Renders `command` for tmux `version` as a line of shell, targeting windows through their
+/// captured variables
+fn tmux_line(command: &PlannedCommand, window_vars: &[String], version: Version) -> String {
+    let mut line = vec!["tmux".to_string()];
+    let argv = command.argv_to(None, version);
+    let mut args = argv.iter().map(|arg| shell_quote(arg));
+    line.extend(args.next());
+    if let Some(target) = &command.target {
+        let target = match target {
+            Target::Session(_) => shell_quote(&target.resolve(window_vars, 0)),
+            // Counted from the `$base` captured by `base_line`
+            Target::Index(session, offset) => format!(
+                "{}\"$((base + {offset}))\"",
+                shell_quote(&format!("{}:", exact_target(session)))
+            ),
+            // Already a quoted variable, plus a plain pane index
+            Target::Window(_) | Target::Pane(..) => target.resolve(window_vars, 0),
+        };
+        line.extend(["-t".to_string(), target]);
+    }
+    line.extend(args);
+    line.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{Layout, WaitFor, Window};
+
+    fn script(preset: &Preset) -> Vec<String> {
+        export_script(preset)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn starts_with_a_shebang_and_set_e() {
+        let lines = script(&Preset::builder("proj").build().unwrap());
+        assert_eq!(lines[0], "#!/bin/sh");
+        assert_eq!(
+            lines[1],
+            "# Recreates the tmux session `proj`, generated by muffin"
+        );
+        assert_eq!(lines[2], "set -e");
+    }
+
+    #[test]
+    fn bails_if_the_session_exists() {
+        let lines = script(&Preset::builder("it's").build().unwrap());
+        assert_eq!(
+            lines[4..8],
+            [
+                "if tmux has-session -t '=it'\\''s' 2>/dev/null; then",
+                "    echo 'Session '\\''it'\\''s'\\'' already exists' >&2",
+                "    exit 1",
+                "fi",
+            ]
+        );
+        // Before anything is created
+        let guard = lines
+            .iter()
+            .position(|line| line.starts_with("if tmux has-session"));
+        let create = lines.iter().position(|line| line.contains("new-session"));
+        assert!(guard < create);
+    }
+
+    #[test]
+    fn quotes_cwds_and_commands() {
+        let preset = Preset::builder("proj")
+            .cwd("/my proj")
+            .window(Window::builder("dev").layout(Layout::hsplit([
+                Layout::pane().cmd("echo \"it's done\""),
+                Layout::pane().cwd("~/with space"),
+            ])))
+            .build()
+            .unwrap();
+        let lines = script(&preset);
+
+        assert!(lines.contains(
+            &"w0=$(tmux new-session -d -s proj -n dev -P -F '#{window_id}')".to_string()
+        ));
+        // Quoted once for the shell typing `cd`, then once more as an argument of tmux
+        assert!(
+            lines.contains(&r#"tmux send-keys -t "$w0".0 'cd '\''/my proj'\''' Enter"#.to_string())
+        );
+        assert!(
+            lines.contains(
+                &r#"tmux send-keys -t "$w0".1 'cd ~/'\''with space'\''' Enter"#.to_string()
+            )
+        );
+        assert!(
+            lines.contains(&r#"tmux send-keys -t "$w0".0 'echo "it'\''s done"' Enter"#.to_string())
+        );
+    }
+
+    #[test]
+    fn captures_the_base_index_for_later_windows() {
+        let preset = Preset::builder("proj")
+            .window(Window::builder("one"))
+            .window(Window::builder("two"))
+            .build()
+            .unwrap();
+        let lines = script(&preset);
+        let first = lines
+            .iter()
+            .position(|line| line.starts_with("w0="))
+            .unwrap();
+        assert_eq!(
+            lines[first + 1],
+            "base=$(tmux display-message -p -t =proj: '#{base-index}')"
+        );
+        assert!(
+            lines.contains(
+                &r#"w1=$(tmux new-window -t =proj:"$((base + 1))" -n two -P -F '#{window_id}')"#
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn held_back_commands_wait_in_background_jobs() {
+        let preset = Preset::builder("proj")
+            .window(
+                Window::builder("dev").layout(
+                    Layout::pane()
+                        .cmd("npm start")
+                        .wait_for(WaitFor::Port(5432), Duration::from_secs(30)),
+                ),
+            )
+            .build()
+            .unwrap();
+        let script = export_script(&preset).unwrap();
+        assert!(script.contains("wait_for() {"));
+        assert!(script.contains(
+            "(\n    if wait_for 30 nc -z 127.0.0.1 5432; then\n        tmux send-keys -t \"$w0\".0 'npm start' Enter\n"
+        ));
+        assert!(script.ends_with(") &\nwait\n"));
+    }
+
+    #[test]
+    fn dry_runs_show_what_the_script_runs_right_away() {
+        let preset = Preset::builder("proj")
+            .option("status-position", "top")
+            .window(
+                Window::builder("code").layout(Layout::vsplit([
+                    Layout::pane(),
+                    Layout::pane()
+                        .cmd("npm start")
+                        .delay(Duration::from_millis(500)),
+                ])),
+            )
+            .window(Window::builder("logs").index(3))
+            .build()
+            .unwrap();
+        let script = script(&preset);
+        let mut shown = dry_run(&preset, Version::MINIMUM).unwrap();
+
+        let held_back = shown.pop().unwrap();
+        assert_eq!(
+            held_back,
+            "tmux send-keys -t \"$w0\".1 'npm start' Enter  # sent after 500ms"
+        );
+        // Between the check for an existing session and the held-back commands
+        let start = script.iter().position(|line| line == "fi").unwrap() + 2;
+        let end = script[start..].iter().position(String::is_empty).unwrap() + start;
+        assert_eq!(shown, script[start..end]);
+        assert!(shown.iter().any(|line| line.ends_with("|| true")));
+        assert!(shown.iter().any(|line| line.starts_with("base=$(")));
+        assert!(shown.iter().any(|line| line.starts_with("w1=$(")));
+    }
+}
@@ -1,260 +1,643 @@
-use regex::Regex;
-use std::process::Command;
-
-#[derive(Debug, Clone)]
+mod builder;
+mod compact;
+mod diff;
+mod error;
+mod export;
+mod log;
+mod measure;
+mod name;
+mod note;
+mod plan;
+mod relayout;
+mod server;
+#[cfg(test)]
+mod testing;
+mod version;
+
+pub use builder::{Layout, PresetBuilder, WindowBuilder};
+pub use diff::{PresetDiff, WindowDiff, compare_session};
+pub use error::{LayoutError, NameError, TmuxError};
+pub use export::{dry_run, export_script};
+pub use log::{log_message, set_log_file};
+pub use measure::measure_layout;
+pub use name::{MAX_SESSION_NAME, validate_session_name};
+pub use plan::{
+    NewPane, OptionScope, PlannedCommand, PlannedPending, SpawnPlan, Target, TmuxOp,
+    min_window_size, plan_preset, plan_window, window_panes,
+};
+pub use relayout::{LAYOUT_OPTION, Relayout};
+pub use server::{DEFAULT_TIMEOUT, Server};
+pub use version::Version;
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Session {
+    /// tmux's id for it (`$N`), which unlike the name stays the same for as long as it lives
+    pub id: String,
     pub name: String,
-    pub windows: String,
-    pub attached: bool,
-    pub active: bool,
+    /// How many windows it has
+    pub windows: u32,
+    /// When it was created and when it last saw activity, as Unix timestamps
+    pub created: Option<u64>,
+    pub activity: Option<u64>,
+    /// How many clients have the session attached, wherever they are
+    pub attached_count: usize,
+    /// Whether it's the session muffin itself runs in
+    pub is_current: bool,
+    /// Most important alert flagged on any of the session's windows
+    pub alert: Option<Alert>,
+    /// Working directory of the session's active pane
+    pub path: Option<String>,
+    /// Name muffin launched it under from a preset, kept in its `@muffin_preset` option
+    pub preset: Option<String>,
+    /// Name muffin last gave it after what runs in it, kept in its `@muffin_autoname` option for
+    /// as long as muffin names it, see [`Server::set_auto_name`]
+    pub auto_name: Option<String>,
+    /// What the user wrote down about it, kept in its `@muffin_note` option, see
+    /// [`Server::set_note`]
+    pub note: Option<String>,
+    /// Command running in the session's active pane
+    pub command: Option<String>,
+    /// Taken from the preset of the same name, if there is one
+    pub color: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl Session {
+    /// Whether muffin launched it from the preset `name`, and it's still named after it
+    pub fn is_preset(&self, name: &str) -> bool {
+        self.name == name && self.preset.as_deref() == Some(name)
+    }
+}
+
+/// Window flags tmux raises in the background, ordered by importance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Alert {
+    Activity,
+    Bell,
+}
+
+impl Alert {
+    /// Reads `#{session_alerts}`, ex: `1#,3!` (activity in window 1, bell in window 3)
+    pub fn from_session_alerts(alerts: &str) -> Option<Alert> {
+        alerts
+            .chars()
+            .filter_map(|flag| match flag {
+                '#' => Some(Alert::Activity),
+                '!' => Some(Alert::Bell),
+                _ => None,
+            })
+            .max()
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum SplitDirection {
     Horizontal,
     Vertical,
 }
 
-#[derive(Clone, Debug)]
+/// A share of a split, always from 1 to 100 percent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Percent(u8);
+
+impl Percent {
+    pub const FULL: Percent = Percent(100);
+
+    pub fn get(self) -> u8 {
+        self.0
+    }
+
+    /// The closest percent to `value`, for shares worked out rather than given
+    pub fn saturating(value: i64) -> Self {
+        Self(value.clamp(1, 100) as u8)
+    }
+
+    /// `part` of `whole`, rounded and kept within 1 to 100 (a `whole` of zero being all of it)
+    pub fn of(part: f32, whole: f32) -> Self {
+        if whole <= 0.0 || part.is_nan() {
+            return Self::FULL;
+        }
+        Self::saturating((part * 100.0 / whole).round() as i64)
+    }
+}
+
+impl TryFrom<i64> for Percent {
+    type Error = LayoutError;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            1..=100 => Ok(Self(value as u8)),
+            _ => Err(LayoutError::InvalidPercent(value)),
+        }
+    }
+}
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// How much room a pane or split takes up in its parent split
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Size {
+    /// Share of the room left by fixed-size siblings
+    Percent(Percent),
+    /// Fixed number of rows or columns, whatever the size of the terminal
+    Cells(u16),
+    /// Equal share of whatever sized siblings leave
+    #[default]
+    Auto,
+}
+
+/// Serialized tagged by `type`, ex: `{"type": "split", "direction": "vertical", ...}`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
 pub enum LayoutNode {
     Pane {
         cwd: String,
         command: Option<String>,
-        size: u8,
+        size: Size,
+        startup: Startup,
+        /// Keeps the pane open once its command exits, so it can be respawned
+        remain_on_exit: bool,
     },
     Split {
         direction: SplitDirection,
         children: Vec<LayoutNode>,
-        size: u8,
+        size: Size,
+        /// Whether the children are resized to their exact shares once they're all created,
+        /// making up for tmux's rounding. Left to the server when unset, see
+        /// [`Server::with_even_splits`].
+        even: Option<bool>,
     },
 }
 
 impl LayoutNode {
-    fn size(&self) -> u8 {
+    pub fn size(&self) -> Size {
         match self {
             LayoutNode::Pane { size, .. } => *size,
             LayoutNode::Split { size, .. } => *size,
         }
     }
+
+    /// Number of panes this node ends up as
+    pub fn pane_count(&self) -> usize {
+        match self {
+            LayoutNode::Pane { .. } => 1,
+            LayoutNode::Split { children, .. } => children.iter().map(Self::pane_count).sum(),
+        }
+    }
+
+    /// How many splits deep this node nests, 0 for a pane
+    pub fn depth(&self) -> usize {
+        match self {
+            LayoutNode::Pane { .. } => 0,
+            LayoutNode::Split { children, .. } => {
+                1 + children.iter().map(Self::depth).max().unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// Checks that a split's children fit in it. Fixed sizes are taken out of the split first, percents
+/// share what's left and children without a size split whatever the percents leave, so those
+/// can't add up to more than all of it.
+pub fn verify_split_sizes(children: &[LayoutNode]) -> Result<(), LayoutError> {
+    if children.is_empty() {
+        return Err(LayoutError::EmptySplit);
+    }
+    let percent_total: u32 = children
+        .iter()
+        .filter_map(|child| match child.size() {
+            Size::Percent(percent) => Some(percent.get() as u32),
+            _ => None,
+        })
+        .sum();
+    let has_auto = children.iter().any(|child| child.size() == Size::Auto);
+
+    if percent_total > 100 {
+        return Err(LayoutError::PercentTotal(percent_total));
+    }
+    if has_auto && percent_total >= 100 {
+        return Err(LayoutError::NoRoomForAuto);
+    }
+    Ok(())
+}
+
+/// Something a pane's command waits for before being sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum WaitFor {
+    /// A TCP port on localhost accepting connections
+    Port(u16),
+    /// A file existing
+    File(String),
+}
+
+impl WaitFor {
+    /// A POSIX shell command succeeding once the condition holds
+    pub(crate) fn shell_condition(&self) -> String {
+        match self {
+            WaitFor::Port(port) => format!("nc -z 127.0.0.1 {port}"),
+            WaitFor::File(path) => format!("test -e {}", server::shell_path(path)),
+        }
+    }
+}
+
+impl fmt::Display for WaitFor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitFor::Port(port) => write!(f, "port {port}"),
+            WaitFor::File(path) => write!(f, "file {path}"),
+        }
+    }
+}
+
+/// When a pane's command is sent, relative to the rest of the preset being set up
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Startup {
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "delay_ms", serialize_with = "millis")
+    )]
+    pub delay: Duration,
+    pub wait_for: Option<WaitFor>,
+    /// How long to wait on `wait_for` before giving up on the command
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "timeout_ms", serialize_with = "millis")
+    )]
+    pub timeout: Duration,
+}
+
+/// Durations as whole milliseconds, as that's what presets give them in
+#[cfg(feature = "serde")]
+fn millis<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+impl Default for Startup {
+    fn default() -> Self {
+        Self {
+            delay: Duration::ZERO,
+            wait_for: None,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Startup {
+    /// Whether the command can be sent as soon as the pane exists
+    pub fn is_immediate(&self) -> bool {
+        self.delay.is_zero() && self.wait_for.is_none()
+    }
 }
 
-#[derive(Debug)]
+/// A pane command held back by its [`Startup`] options, to be sent with [`Server::send_command`]
+#[derive(Debug, Clone)]
+pub struct PendingCommand {
+    /// Target of the pane (`@window_id.index`)
+    pub pane: String,
+    /// `session:window`, for messages about the pane
+    pub label: String,
+    pub command: String,
+    pub startup: Startup,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Window {
     pub name: String,
     pub cwd: String,
     pub layout: LayoutNode,
+    /// Window options set once it's laid out, ex: `("synchronize-panes", "on")`
+    pub options: Vec<(String, String)>,
+    /// Fixed position in the session, counted from 1 whatever tmux's `base-index`. Windows
+    /// without one follow the previous window, in the order they're declared.
+    pub index: Option<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Preset {
     pub name: String,
     pub cwd: String,
     pub running: bool,
     pub windows: Vec<Window>,
+    /// Explicit position in the presets list, lower comes first
+    pub order: Option<i64>,
+    /// Pinned presets are listed before all others
+    pub pinned: bool,
+    /// The file this preset was read from, if it came from one
+    pub source: Option<PathBuf>,
+    /// Where the preset is defined among those of its document, counted from 0
+    pub position: usize,
+    /// Accent color for the preset and its session, ex: `blue` or `#1e90ff`
+    pub color: Option<String>,
+    /// Free-form labels for grouping presets
+    pub tags: Vec<String>,
+    /// A note about the preset, ex: `client X staging env, needs VPN`, possibly several lines
+    pub description: Option<String>,
+    /// Values asked for at launch, standing in for `{name}` placeholders in names, directories
+    /// and commands
+    pub vars: Vec<Var>,
+    /// Session options set once it's created, ex: `("status-position", "top")`
+    pub options: Vec<(String, String)>,
+    /// Socket of the tmux server the session goes on, like `tmux -L <socket>`, instead of
+    /// muffin's
+    pub socket: Option<String>,
+    /// Config file for that server to read if launching the session starts it, like
+    /// `tmux -f <path>`
+    pub tmux_config: Option<String>,
 }
 
-pub fn spawn_preset(preset: &Preset) -> Result<(), String> {
-    create_session(&preset.name)?;
-
-    for (i, window_cfg) in preset.windows.iter().enumerate() {
-        let window_target = if i == 0 {
-            // Use the default window created by new-session
-            run_command(
-                "tmux",
-                &[
-                    "rename-window",
-                    "-t",
-                    &format!("{}:0", preset.name),
-                    &window_cfg.name,
-                ],
-            )?;
-            format!("{}:{}", preset.name, window_cfg.name)
-        } else {
-            // Create a new window and get its name/index
-            run_command(
-                "tmux",
-                &[
-                    "new-window",
-                    "-t",
-                    &preset.name,
-                    "-n",
-                    &window_cfg.name,
-                    "-P",
-                ],
-            )?
-            .trim()
-            .to_string();
-            format!("{}:{}", preset.name, window_cfg.name)
-        };
+/// A value a preset is parameterized with, see [`Preset::vars`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Var {
+    pub name: String,
+    /// Used when launching without giving a value
+    pub default: Option<String>,
+}
 
-        // Initial pane in a new window is always index 0
-        let initial_pane = format!("{}.0", window_target);
-        apply_layout_recursive(&initial_pane, &window_cfg.layout)?;
-    }
+/// A window as it currently exists in a running tmux session
+#[derive(Debug, Clone)]
+pub struct LiveWindow {
+    pub index: usize,
+    pub name: String,
+    /// Command running in the window's active pane
+    pub command: String,
+    /// The window is its session's current one
+    pub active: bool,
+}
 
-    Ok(())
+/// A pane as it currently exists in a running tmux window
+#[derive(Debug, Clone)]
+pub struct LivePane {
+    /// tmux's id for the pane, ex: `%3`, which unlike its index stays the same when it's moved
+    pub id: String,
+    pub index: usize,
+    pub command: String,
+    pub cwd: String,
+    pub active: bool,
+    /// The pane's command exited and the pane was kept open by `remain-on-exit`
+    pub dead: bool,
+    /// The pane fills its window, hiding the others, until it's unzoomed
+    pub zoomed: bool,
+    /// Position of the pane's top left corner in the window, in cells
+    pub left: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
 }
 
-fn apply_layout_recursive(pane_target: &str, node: &LayoutNode) -> Result<(), String> {
-    match node {
-        LayoutNode::Pane { cwd, command, .. } => {
-            run_command(
-                "tmux",
-                &[
-                    "send-keys",
-                    "-t",
-                    pane_target,
-                    &format!("cd {}", cwd),
-                    "Enter",
-                ],
-            )?;
-            // run command if provided
-            if let Some(cmd) = command {
-                run_command("tmux", &["send-keys", "-t", pane_target, cmd, "Enter"])?;
-            }
-            Ok(())
-        }
-        LayoutNode::Split {
-            direction,
-            children,
-            ..
-        } => {
-            let mut current_pane_target = pane_target.to_string();
-            let mut remaining_pct: f32 = children.iter().map(|c| c.size() as f32).sum();
-
-            for (i, child) in children.iter().enumerate() {
-                // If it's the last child, we don't split anymore;
-                // it just occupies whatever is left in current_pane_target
-                if i == children.len() - 1 {
-                    apply_layout_recursive(&current_pane_target, child)?;
-                    break;
-                }
-
-                let child_pct = child.size() as f32;
-
-                // Warning: Borrowed from AI slop for math calculations
-
-                // MATH CALCULATION:
-                // Tmux '-p' is the size of the NEW pane relative to the target.
-                // If child needs 20% of the current area, the NEW pane (the rest)
-                // needs to be 80% of the current target.
-                let split_p = (((remaining_pct - child_pct) / remaining_pct) * 100.0).round() as u8;
-
-                // Split the window.
-                // The 'old' index stays as the 'child', the 'new' index is the 'rest'.
-                let (sess, win, new_index) =
-                    split_window(&current_pane_target, split_p, direction)?;
-
-                let next_pane_target = format!("{}:{}.{}", sess, win, new_index);
-
-                // Recurse into the child we just "carved out"
-                apply_layout_recursive(&current_pane_target, child)?;
-
-                // Move our focus to the newly created pane for the next iteration
-                current_pane_target = next_pane_target;
-                remaining_pct -= child_pct;
-            }
-            Ok(())
-        }
+/// Which way [`Server::resize_pane`] moves a pane's border
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeDirection {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+/// What [`spawn_preset`] or [`create_window`] set up
+#[derive(Debug, Default)]
+pub struct PresetSpawn {
+    /// Commands that are still waiting on their startup options to be sent
+    pub pending: Vec<PendingCommand>,
+    /// What failed without stopping the rest, like options tmux didn't accept
+    pub warnings: Vec<String>,
+}
+
+/// How far [`Server::spawn_preset_with_progress`] got, reported before each tmux command it runs
+#[derive(Debug, Clone)]
+pub struct SpawnProgress {
+    /// Counted from 1
+    pub step: usize,
+    pub total: usize,
+    /// What the step does, ex: `new-window editor`
+    pub detail: String,
+}
+
+/// What [`update_preset`] did to bring a running session in line with its preset
+#[derive(Debug, Default)]
+pub struct PresetUpdate {
+    /// Windows from the preset that were missing and have been created
+    pub added: Vec<String>,
+    /// Windows in the session that the preset doesn't know about (left untouched)
+    pub extra: Vec<String>,
+    /// Commands of the added windows that are still waiting to be sent
+    pub pending: Vec<PendingCommand>,
+    /// See [`PresetSpawn::warnings`]
+    pub warnings: Vec<String>,
+}
+
+/// What [`append_preset`] added to the target session
+#[derive(Debug, Default)]
+pub struct PresetAppend {
+    /// Names the preset's windows were created under, suffixed where the session had them already
+    pub added: Vec<String>,
+    /// Commands of the added windows that are still waiting to be sent
+    pub pending: Vec<PendingCommand>,
+    /// See [`PresetSpawn::warnings`]
+    pub warnings: Vec<String>,
+}
+
+// Thin wrappers targeting the local server, kept for convenience
+
+/// The cwd of panes and windows that start wherever the pane they're split from (or the target
+/// session's current pane, for windows) is at the time.
+///
+/// It's handed to tmux's `-c` as is, for tmux to expand, rather than resolved by muffin.
+pub const INHERIT_CWD: &str = "#{pane_current_path}";
+
+/// A `-t` target for exactly the session `name`, or the one with id `name` if it's one
+/// ([`Session::id`]).
+///
+/// A bare name also matches the sessions it's a prefix of (`api` for `api-v2`) or a pattern, so
+/// every session muffin targets by name goes through this. Window and pane parts come after it:
+/// `={name}:` is the session's current window, `={name}:1.0` its first pane in window 1.
+pub fn exact_target(name: &str) -> String {
+    let is_id = name
+        .strip_prefix('$')
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+    match is_id {
+        true => name.to_string(),
+        false => format!("={name}"),
     }
 }
 
+pub fn spawn_preset(preset: &Preset) -> Result<PresetSpawn, TmuxError> {
+    Server::local().spawn_preset(preset)
+}
+
+pub fn create_window(session: &str, window_cfg: &Window) -> Result<PresetSpawn, TmuxError> {
+    Server::local().create_window(session, window_cfg)
+}
+
+pub fn append_preset(preset: &Preset, target_session: &str) -> Result<PresetAppend, TmuxError> {
+    Server::local().append_preset(preset, target_session)
+}
+
+pub fn update_preset(preset: &Preset) -> Result<PresetUpdate, TmuxError> {
+    Server::local().update_preset(preset)
+}
+
 pub fn split_window(
     target: &str,
-    size: u8,
+    size: Size,
     direction: &SplitDirection,
-) -> Result<(String, String, usize), String> {
-    let direction_flag = match direction {
-        SplitDirection::Horizontal => "-h",
-        SplitDirection::Vertical => "-v",
-    };
-    let output = run_command(
-        "tmux",
-        &[
-            "split-window",
-            "-t",
-            target,
-            direction_flag,
-            "-p",
-            size.to_string().as_str(),
-            "-P",
-        ],
-    )?;
-    let (session_name, rest) = output.trim().split_once(":").ok_or("Unexpected output")?;
-    let (window_name, pane_index) = rest.split_once(".").ok_or("Unexpected output")?;
-    Ok((
-        session_name.into(),
-        window_name.into(),
-        pane_index.parse::<usize>().map_err(|_| "Parsing error")?,
-    ))
-}
-
-pub fn list_sessions() -> Result<Vec<Session>, String> {
-    let output = run_command("tmux", &["list-sessions"])?;
-    let active_session_name = match std::env::var("TMUX_PANE") {
-        Ok(tmux_pane_env) => Some(
-            run_command(
-                "tmux",
-                &["display-message", "-t", &tmux_pane_env, "-p", "'#S'"],
-            )?
-            .trim()
-            .trim_matches('\'')
-            .to_string(),
-        ),
-        Err(_) => None,
-    };
-
-    let active_regex = Regex::new(r"\(attached\)$").unwrap();
-    let windows_regex = Regex::new(r"^(.+?): (\d+).*").unwrap();
-
-    let sessions = output
-        .lines()
-        .map(|line| {
-            let captures = windows_regex.captures(line).unwrap();
-
-            let name = Some(captures[1].to_string());
-
-            Session {
-                windows: captures[2].to_string(),
-                attached: active_regex.is_match(line),
-                active: name == active_session_name,
-                name: name.unwrap(),
-            }
-        })
-        .collect::<Vec<Session>>();
+) -> Result<(String, String), TmuxError> {
+    Server::local().split_window(target, size, direction)
+}
 
-    Ok(sessions)
+pub fn new_window(
+    session: &str,
+    name: Option<&str>,
+    cwd: Option<&str>,
+) -> Result<String, TmuxError> {
+    Server::local().new_window(session, name, cwd)
 }
 
-pub fn switch_session(target: &str) -> Result<(), String> {
-    run_command("tmux", &["switch-client", "-t", target]).map(|_| ())
+pub fn list_sessions() -> Result<Vec<Session>, TmuxError> {
+    Server::local().list_sessions()
 }
 
-pub fn create_session(new_name: &str) -> Result<(), String> {
-    if new_name.is_empty() {
-        run_command("tmux", &["new-session", "-d"]).map(|_| ())
-    } else {
-        run_command("tmux", &["new-session", "-s", new_name, "-d"]).map(|_| ())
-    }
+pub fn list_windows(session: &str) -> Result<Vec<LiveWindow>, TmuxError> {
+    Server::local().list_windows(session)
 }
 
-pub fn rename_session(target: &str, new_name: &str) -> Result<(), String> {
-    run_command("tmux", &["rename-session", "-t", target, new_name]).map(|_| ())
+pub fn list_panes(window: &str) -> Result<Vec<LivePane>, TmuxError> {
+    Server::local().list_panes(window)
 }
 
-pub fn delete_session(target: &str) -> Result<(), String> {
-    run_command("tmux", &["kill-session", "-t", target]).map(|_| ())
+pub fn switch_session(session: &str) -> Result<(), TmuxError> {
+    Server::local().switch_session(session)
 }
 
-fn run_command(command: &str, args: &[&str]) -> Result<String, String> {
-    let output = Command::new(command)
-        .args(args)
-        .output()
-        .map_err(|_| "Error running command")?;
+pub fn create_session(new_name: &str, cwd: Option<&str>) -> Result<String, TmuxError> {
+    Server::local().create_session(new_name, cwd)
+}
+
+pub fn rename_session(session: &str, new_name: &str) -> Result<(), TmuxError> {
+    Server::local().rename_session(session, new_name)
+}
+
+pub fn delete_session(session: &str) -> Result<(), TmuxError> {
+    Server::local().delete_session(session)
+}
+
+pub fn kill_server() -> Result<(), TmuxError> {
+    Server::local().kill_server()
+}
 
-    if output.status.code().is_none_or(|code| code != 0) {
-        return Err(String::from_utf8(output.stderr).map_err(|_| "Error decoding output")?);
+pub fn start_server() -> Result<(), TmuxError> {
+    Server::local().start_server()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(size: Size) -> LayoutNode {
+        LayoutNode::Pane {
+            cwd: "~".to_string(),
+            command: None,
+            size,
+            startup: Startup::default(),
+            remain_on_exit: false,
+        }
+    }
+
+    fn percent(value: i64) -> Size {
+        Size::Percent(Percent::try_from(value).unwrap())
+    }
+
+    #[test]
+    fn percents_are_checked() {
+        for value in [1, 50, 100] {
+            assert_eq!(Percent::try_from(value).unwrap().get() as i64, value);
+        }
+        // 256 and 356 would have wrapped around to 0 and 100 as a u8
+        for value in [0, -1, 101, 200, 256, 356, i64::MIN, i64::MAX] {
+            assert_eq!(
+                Percent::try_from(value),
+                Err(LayoutError::InvalidPercent(value))
+            );
+        }
     }
 
-    Ok(String::from_utf8(output.stdout).map_err(|_| "Error decoding output")?)
+    #[test]
+    fn saturating_percents_stay_in_range() {
+        assert_eq!(Percent::saturating(0).get(), 1);
+        assert_eq!(Percent::saturating(-5).get(), 1);
+        assert_eq!(Percent::saturating(256).get(), 100);
+        assert_eq!(Percent::saturating(i64::MAX).get(), 100);
+    }
+
+    #[test]
+    fn split_totals_dont_overflow() {
+        let sizes = |sizes: &[Size]| {
+            let children = sizes.iter().map(|&size| pane(size)).collect::<Vec<_>>();
+            verify_split_sizes(&children)
+        };
+        assert_eq!(sizes(&[percent(50), percent(50)]), Ok(()));
+        assert_eq!(sizes(&[percent(60), Size::Auto]), Ok(()));
+        assert_eq!(sizes(&[percent(100), Size::Cells(10)]), Ok(()));
+        assert_eq!(
+            sizes(&[percent(100), percent(100)]),
+            Err(LayoutError::PercentTotal(200))
+        );
+        // Adding up to 0 as a u8
+        assert_eq!(
+            sizes(&[percent(100), percent(100), percent(56)]),
+            Err(LayoutError::PercentTotal(256))
+        );
+        // Far past what a u8 holds
+        assert_eq!(
+            sizes(&[percent(100); 300]),
+            Err(LayoutError::PercentTotal(30_000))
+        );
+        assert_eq!(
+            sizes(&[percent(100), Size::Auto]),
+            Err(LayoutError::NoRoomForAuto)
+        );
+        assert_eq!(sizes(&[]), Err(LayoutError::EmptySplit));
+    }
+
+    #[test]
+    fn depth_counts_nested_splits() {
+        let split = |children| LayoutNode::Split {
+            direction: SplitDirection::Horizontal,
+            children,
+            size: Size::Auto,
+            even: None,
+        };
+        assert_eq!(pane(Size::Auto).depth(), 0);
+        assert_eq!(split(vec![pane(Size::Auto); 3]).depth(), 1);
+        // The deepest child counts, whatever its siblings
+        let nested = split(vec![
+            pane(Size::Auto),
+            split(vec![pane(Size::Auto), split(vec![pane(Size::Auto)])]),
+        ]);
+        assert_eq!(nested.depth(), 3);
+        assert_eq!(nested.pane_count(), 3);
+    }
 }
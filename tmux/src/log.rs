@@ -0,0 +1,87 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::format_command;
+
+/// Longest stdout/stderr excerpt written per invocation
+const MAX_OUTPUT_LEN: usize = 200;
+
+static LOG_FILE: OnceLock<Option<Mutex<File>>> = OnceLock::new();
+
+/// Sends the debug log to `path`, overriding `MUFFIN_LOG`.
+///
+/// Must be called before the first tmux invocation, otherwise the environment has already been
+/// consulted and this returns an error.
+pub fn set_log_file(path: &str) -> Result<(), String> {
+    let file = open(path).map_err(|e| format!("Could not open log file '{path}': {e}"))?;
+    LOG_FILE
+        .set(Some(Mutex::new(file)))
+        .map_err(|_| "Log file was already initialized".to_string())
+}
+
+fn open(path: &str) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn log_file() -> Option<&'static Mutex<File>> {
+    LOG_FILE
+        .get_or_init(|| {
+            let path = std::env::var("MUFFIN_LOG").ok()?;
+            open(&path).ok().map(Mutex::new)
+        })
+        .as_ref()
+}
+
+/// Appends a line describing a finished invocation, which took `elapsed`, to the debug log, if
+/// one is configured
+pub(crate) fn log_invocation(
+    command: &[String],
+    code: Option<i32>,
+    elapsed: Duration,
+    stdout: &str,
+    stderr: &str,
+) {
+    let code = code.map_or("none".to_string(), |c| c.to_string());
+    write_line(&format!(
+        "{} -> exit {code} in {}ms; stdout: {:?}; stderr: {:?}",
+        format_command(command),
+        elapsed.as_millis(),
+        truncate(stdout),
+        truncate(stderr),
+    ));
+}
+
+/// Appends `message` to the debug log, if one is configured, for problems that aren't worth
+/// bothering anyone with but may be worth looking into
+pub fn log_message(message: &str) {
+    write_line(message);
+}
+
+fn write_line(line: &str) {
+    let Some(file) = log_file() else {
+        return;
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let line = format!(
+        "[{}.{:03}] {line}\n",
+        timestamp.as_secs(),
+        timestamp.subsec_millis(),
+    );
+
+    if let Ok(mut file) = file.lock() {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+fn truncate(output: &str) -> String {
+    let output = output.trim();
+    match output.char_indices().nth(MAX_OUTPUT_LEN) {
+        Some((idx, _)) => format!("{}...", &output[..idx]),
+        None => output.to_string(),
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{LayoutNode, LivePane, Percent, Size, SplitDirection};
+
+/// Where a pane or split ended up in its window, in cells
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    left: u16,
+    top: u16,
+    right: u16,
+    bottom: u16,
+}
+
+impl Rect {
+    fn union(self, other: Rect) -> Rect {
+        Rect {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+
+    /// Extent along the axis `direction` lays children out on
+    fn extent(&self, direction: SplitDirection) -> u16 {
+        match direction {
+            SplitDirection::Horizontal => self.right - self.left,
+            SplitDirection::Vertical => self.bottom - self.top,
+        }
+    }
+}
+
+/// Reads the sizes `layout`'s panes and splits have in a window laid out from it, as given by
+/// [`Server::list_panes`](crate::Server::list_panes), so they can be written back to the preset.
+///
+/// Sizes come in the order the presets file lists the nodes (depth first, the root left out).
+/// Fixed sizes stay in cells, everything else is turned into percents of the room fixed-size
+/// siblings leave. A split whose proportional children still have about the shares the preset
+/// gives them keeps its sizes as they were.
+///
+/// `None` if the window's panes no longer match the layout, ex: one was closed or added.
+pub fn measure_layout(layout: &LayoutNode, panes: &[LivePane]) -> Option<Vec<Size>> {
+    if panes.len() != layout.pane_count() {
+        return None;
+    }
+    // tmux lists panes in the order the layout was split in, which is the layout's own order
+    let mut panes = panes.to_vec();
+    panes.sort_by_key(|pane| pane.index);
+    let mut rects = panes.iter().map(|pane| Rect {
+        left: pane.left,
+        top: pane.top,
+        right: pane.left + pane.width,
+        bottom: pane.top + pane.height,
+    });
+
+    let mut sizes = vec![];
+    measure_node(layout, &mut rects, &mut sizes);
+    Some(sizes)
+}
+
+/// Pushes the sizes of `node`'s descendants onto `sizes`, returning where `node` itself is
+fn measure_node(
+    node: &LayoutNode,
+    rects: &mut impl Iterator<Item = Rect>,
+    sizes: &mut Vec<Size>,
+) -> Rect {
+    let LayoutNode::Split {
+        direction,
+        children,
+        ..
+    } = node
+    else {
+        return rects.next().expect("pane count was checked");
+    };
+
+    // Each child's size goes right before its own children's
+    let mut child_rects = vec![];
+    let mut child_sizes = vec![];
+    for child in children {
+        let slot = sizes.len();
+        sizes.push(child.size());
+        child_rects.push(measure_node(child, rects, sizes));
+        child_sizes.push(slot);
+    }
+
+    let extents = child_rects
+        .iter()
+        .map(|rect| rect.extent(*direction))
+        .collect::<Vec<_>>();
+    for (size, new) in child_sizes.iter().zip(new_sizes(children, &extents)) {
+        sizes[*size] = new;
+    }
+
+    child_rects
+        .into_iter()
+        .reduce(Rect::union)
+        .expect("splits are never empty")
+}
+
+/// The sizes of a split's `children`, given how many cells each of them takes up
+fn new_sizes(children: &[LayoutNode], extents: &[u16]) -> Vec<Size> {
+    let room: u32 = children
+        .iter()
+        .zip(extents)
+        .filter(|(child, _)| !matches!(child.size(), Size::Cells(_)))
+        .map(|(_, &extent)| extent as u32)
+        .sum();
+    let weights = crate::plan::weights(children);
+
+    let mut percents = children
+        .iter()
+        .zip(extents)
+        .map(|(child, &extent)| match child.size() {
+            Size::Cells(_) => None,
+            _ => Some(Percent::of(extent as f32, room as f32)),
+        })
+        .collect::<Vec<_>>();
+
+    let unchanged = percents
+        .iter()
+        .zip(&weights)
+        .all(|(percent, weight)| percent.is_none_or(|p| (p.get() as f32 - weight).abs() <= 1.0));
+    if unchanged {
+        return children
+            .iter()
+            .zip(extents)
+            .map(|(child, &extent)| match child.size() {
+                Size::Cells(_) => Size::Cells(extent),
+                size => size,
+            })
+            .collect();
+    }
+
+    // Rounding can leave the percents a little off 100, which the largest can make up for
+    let total: i64 = percents.iter().flatten().map(|p| p.get() as i64).sum();
+    if let Some(largest) = percents.iter_mut().flatten().max() {
+        *largest = Percent::saturating(largest.get() as i64 + 100 - total);
+    }
+
+    children
+        .iter()
+        .zip(extents)
+        .zip(percents)
+        .map(
+            |((child, &extent), percent)| match (child.size(), percent) {
+                (Size::Cells(_), _) => Size::Cells(extent),
+                (_, Some(percent)) => Size::Percent(percent),
+                (size, None) => size,
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{INHERIT_CWD, Layout};
+
+    /// A pane at `left`, `top` of `width` by `height` cells
+    fn pane(index: usize, left: u16, top: u16, width: u16, height: u16) -> LivePane {
+        LivePane {
+            id: format!("%{index}"),
+            index,
+            command: "zsh".to_string(),
+            cwd: "~".to_string(),
+            active: false,
+            dead: false,
+            zoomed: false,
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn sizes_are_read_back_from_where_the_panes_are() {
+        // A 30% column next to a stack whose bottom pane is 10 rows
+        let layout = Layout::hsplit([
+            Layout::pane().pct(30),
+            Layout::vsplit([Layout::pane(), Layout::pane().cells(10)]),
+        ])
+        .build(INHERIT_CWD, None)
+        .unwrap();
+        let percent = |value| Size::Percent(Percent::try_from(value).unwrap());
+
+        // As launched in a 100x40 window, listed out of order: about the preset's shares, so kept
+        let launched = [
+            pane(2, 30, 30, 70, 10),
+            pane(0, 0, 0, 29, 40),
+            pane(1, 30, 0, 70, 29),
+        ];
+        assert_eq!(
+            measure_layout(&layout, &launched),
+            Some(vec![percent(30), Size::Auto, Size::Auto, Size::Cells(10)])
+        );
+
+        // Once the column was dragged wider and the bottom pane taller
+        let resized = [
+            pane(0, 0, 0, 59, 40),
+            pane(1, 60, 0, 40, 27),
+            pane(2, 60, 28, 40, 12),
+        ];
+        assert_eq!(
+            measure_layout(&layout, &resized),
+            Some(vec![percent(60), percent(40), Size::Auto, Size::Cells(12)])
+        );
+
+        // A pane closed since
+        assert_eq!(measure_layout(&layout, &resized[..2]), None);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::NameError;
+
+/// Longest session name accepted, in characters, well past anything that still fits in a status
+/// line
+pub const MAX_SESSION_NAME: usize = 128;
+
+/// Checks that tmux takes `name` as a session name as is.
+///
+/// tmux quietly turns `.` into `_`, reads `:` as the end of the session in targets, escapes `\`
+/// and `$` so the name it keeps isn't the one given (and a leading `$` reads as an id in
+/// targets), and keeps whitespace and control characters that then can't be told apart in a list,
+/// so all of those are turned down before anything reaches it.
+pub fn validate_session_name(name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    // Counted from 1, in characters, as they'd be pointed at in the name
+    for (i, ch) in name.chars().enumerate() {
+        let position = i + 1;
+        if matches!(ch, ':' | '.' | '$' | '\\') {
+            return Err(NameError::Forbidden { ch, position });
+        }
+        if ch.is_control() {
+            return Err(NameError::Control { ch, position });
+        }
+    }
+
+    let len = name.chars().count();
+    if let Some(ch) = name.chars().next().filter(|c| c.is_whitespace()) {
+        return Err(NameError::Whitespace { ch, position: 1 });
+    }
+    if let Some(ch) = name.chars().last().filter(|c| c.is_whitespace()) {
+        return Err(NameError::Whitespace { ch, position: len });
+    }
+    if len > MAX_SESSION_NAME {
+        return Err(NameError::TooLong {
+            len,
+            max: MAX_SESSION_NAME,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestServer;
+    use crate::{Server, TmuxError, exact_target};
+
+    #[test]
+    fn forbidden_characters_are_pointed_at() {
+        for (name, ch, position) in [
+            ("my.proj", '.', 3),
+            ("work:api", ':', 5),
+            ("$3", '$', 1),
+            ("a\\b", '\\', 2),
+            // The first one, counted in characters
+            ("ün.i:", '.', 3),
+        ] {
+            assert_eq!(
+                validate_session_name(name),
+                Err(NameError::Forbidden { ch, position }),
+                "{name}"
+            );
+        }
+        assert_eq!(
+            validate_session_name("a\tb"),
+            Err(NameError::Control {
+                ch: '\t',
+                position: 2
+            })
+        );
+    }
+
+    #[test]
+    fn whitespace_only_inside() {
+        assert_eq!(validate_session_name("my proj"), Ok(()));
+        assert_eq!(
+            validate_session_name(" proj"),
+            Err(NameError::Whitespace {
+                ch: ' ',
+                position: 1
+            })
+        );
+        assert_eq!(
+            validate_session_name("my proj "),
+            Err(NameError::Whitespace {
+                ch: ' ',
+                position: 8
+            })
+        );
+        assert_eq!(validate_session_name(""), Err(NameError::Empty));
+        assert_eq!(
+            validate_session_name(&"a".repeat(MAX_SESSION_NAME + 1)),
+            Err(NameError::TooLong {
+                len: MAX_SESSION_NAME + 1,
+                max: MAX_SESSION_NAME
+            })
+        );
+    }
+
+    #[test]
+    fn accepted_names_round_trip_through_tmux() {
+        let scratch = TestServer::new("names");
+        let server = &scratch.server;
+        let names = [
+            "my proj",
+            "a  b",
+            "-d",
+            "=eq",
+            "@1",
+            "%2",
+            "it's",
+            "say \"hi\"",
+            "a#b",
+            "a;b",
+            "{a}",
+            "*",
+            "a?b",
+            "~",
+            "ünï ✓",
+        ];
+        for name in names {
+            // Anything accepted has to be kept by tmux as is
+            assert_eq!(validate_session_name(name), Ok(()), "{name}");
+            assert_eq!(server.create_session(name, None).unwrap(), name);
+            let listed = server.list_sessions().unwrap();
+            assert!(
+                listed.iter().any(|session| session.name == name),
+                "{name} in {:?}",
+                listed.iter().map(|s| &s.name).collect::<Vec<_>>()
+            );
+            // Found by its exact target, and only it
+            assert_eq!(
+                server
+                    .display_message(&format!("{}:", exact_target(name)), "#{session_name}")
+                    .unwrap()
+                    .trim_end_matches('\n'),
+                name
+            );
+            // Keeps the server up for the next name
+            server.create_session("keep", None).ok();
+            server.delete_session(name).unwrap();
+            assert!(
+                !server
+                    .list_sessions()
+                    .unwrap()
+                    .iter()
+                    .any(|session| session.name == name),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn turned_down_names_never_reach_tmux() {
+        // Nothing runs on this socket, so anything reaching tmux would fail otherwise
+        let server = Server::socket(&format!("muffin-no-names-{}", std::process::id()));
+        for name in ["my.proj", "work:api", "$3", "a\\b", " proj"] {
+            let error = server.create_session(name, None).unwrap_err();
+            assert!(
+                matches!(error, TmuxError::InvalidName { .. }),
+                "{name}: {error:?}"
+            );
+            let error = server.rename_session("proj", name).unwrap_err();
+            assert!(
+                matches!(error, TmuxError::InvalidName { .. }),
+                "{name}: {error:?}"
+            );
+        }
+    }
+}
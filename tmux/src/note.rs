@@ -0,0 +1,97 @@
+/// Turns the line breaks and tabs of a session note into escapes, as tmux hands option values
+/// back as they are and `list-sessions` gives a line of tab-separated fields per session
+pub(crate) fn escape_note(note: &str) -> String {
+    let mut escaped = String::with_capacity(note.len());
+    for ch in note.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// The note [`escape_note`] escaped. A backslash before anything else is kept, so a note set
+/// outside muffin reads as it was written.
+pub(crate) fn unescape_note(escaped: &str) -> String {
+    let mut note = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            note.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => note.push('\\'),
+            Some('n') => note.push('\n'),
+            Some('r') => note.push('\r'),
+            Some('t') => note.push('\t'),
+            Some(other) => note.extend(['\\', other]),
+            None => note.push('\\'),
+        }
+    }
+    note
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestServer;
+
+    const NOTES: &[&str] = &[
+        "plain",
+        "two\nlines",
+        "windows\r\nline ends",
+        "a\ttab",
+        "C:\\Users\\me",
+        "a trailing \\",
+        "\\n as written",
+        "\"quoted\" and 'single'",
+        "ünïcödé ✓ 日本語 🚀",
+        "",
+    ];
+
+    #[test]
+    fn escaped_notes_round_trip() {
+        for note in NOTES {
+            let escaped = escape_note(note);
+            assert!(
+                !escaped.contains(['\n', '\r', '\t']),
+                "{note:?} -> {escaped:?}"
+            );
+            assert_eq!(unescape_note(&escaped), *note, "{escaped:?}");
+        }
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(escape_note("a\nb\tc\\d\re"), "a\\nb\\tc\\\\d\\re");
+        assert_eq!(escape_note("\"ü\""), "\"ü\"");
+    }
+
+    #[test]
+    fn stray_backslashes_set_outside_muffin_are_kept() {
+        // ex: `tmux set-option @muffin_note 'C:\dev\x'`
+        assert_eq!(unescape_note("C:\\dev\\x"), "C:\\dev\\x");
+        assert_eq!(unescape_note("ends with \\"), "ends with \\");
+        assert_eq!(unescape_note("\\ü"), "\\ü");
+    }
+
+    #[test]
+    fn notes_round_trip_through_tmux() {
+        let test = TestServer::new("notes");
+        let server = &test.server;
+        server.create_session("proj", None).unwrap();
+        for note in NOTES.iter().filter(|note| !note.is_empty()) {
+            server.set_note("proj", note).unwrap();
+            let sessions = server.list_sessions().unwrap();
+            assert_eq!(sessions[0].note.as_deref(), Some(*note));
+        }
+        // An empty note unsets it
+        server.set_note("proj", "").unwrap();
+        assert_eq!(server.list_sessions().unwrap()[0].note, None);
+    }
+}
@@ -0,0 +1,1437 @@
+use crate::server::{op_argv, shell_path};
+use crate::{
+    INHERIT_CWD, LAYOUT_OPTION, LayoutError, LayoutNode, Percent, Preset, Size, SplitDirection,
+    Startup, Version, Window, exact_target, verify_split_sizes,
+};
+
+/// What a planned command acts on.
+///
+/// Windows are referred to by the order the plan creates them in, since their ids (`@N`) are only
+/// known once tmux created them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Session(String),
+    /// A window index of the session, counted from its `base-index`
+    Index(String, usize),
+    Window(usize),
+    /// A pane of a planned window, by index
+    Pane(usize, usize),
+}
+
+impl Target {
+    /// The tmux target, given the ids of the windows created so far and the session's
+    /// `base-index`
+    pub fn resolve(&self, window_ids: &[String], base: usize) -> String {
+        match self {
+            // `:` for the session's next free window index
+            Target::Session(name) => format!("{}:", exact_target(name)),
+            Target::Index(name, offset) => format!("{}:{}", exact_target(name), base + offset),
+            Target::Window(window) => window_ids[*window].clone(),
+            Target::Pane(window, pane) => format!("{}.{pane}", window_ids[*window]),
+        }
+    }
+}
+
+/// A tmux operation in a [`SpawnPlan`], typed so plans can be looked at without going through
+/// argv. [`PlannedCommand::argv`] renders it for a given tmux.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TmuxOp {
+    /// A detached session, along with its first window when `window` names it, whose id is
+    /// printed
+    NewSession {
+        name: String,
+        window: Option<String>,
+        inherit_cwd: bool,
+    },
+    /// A window at the target, whose id is printed
+    NewWindow { name: String, inherit_cwd: bool },
+    /// Moves the current window of the session `source` (ex: `=proj:`) to the target
+    MoveWindow { source: String },
+    /// Splits the target pane, the new pane taking `size` of it, before it with `before`
+    Split {
+        direction: SplitDirection,
+        size: Size,
+        before: bool,
+        inherit_cwd: bool,
+    },
+    SetOption {
+        scope: OptionScope,
+        name: String,
+        value: String,
+    },
+    /// Types `keys` into the target pane, followed by Enter
+    SendKeys { keys: String },
+}
+
+/// What a [`TmuxOp::SetOption`] sets an option of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionScope {
+    Session,
+    Window,
+    /// Set on the window before tmux 3.0, which had no pane options
+    Pane,
+}
+
+/// A [`TmuxOp`] in a [`SpawnPlan`], along with what it acts on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedCommand {
+    pub op: TmuxOp,
+    /// Passed as `-t`, right after the subcommand
+    pub target: Option<Target>,
+    /// Set on commands whose failure is only worth a warning, like setting an option this tmux
+    /// doesn't know
+    pub optional: bool,
+}
+
+/// Where a split puts the new pane among its window's panes, relative to the pane split
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewPane {
+    After,
+    /// With `-b`
+    Before,
+}
+
+impl PlannedCommand {
+    /// The full argv (without the leading `tmux`) for tmux `version`, given the ids of the
+    /// windows created so far and the session's `base-index`
+    pub fn argv(&self, window_ids: &[String], base: usize, version: Version) -> Vec<String> {
+        let target = self
+            .target
+            .as_ref()
+            .map(|target| target.resolve(window_ids, base));
+        self.argv_to(target, version)
+    }
+
+    /// The full argv, with `target` as its `-t` whatever the planned target
+    pub fn argv_to(&self, target: Option<String>, version: Version) -> Vec<String> {
+        op_argv(&self.op, target, version)
+    }
+
+    /// Whether the command creates a window, printing its id
+    pub fn creates_window(&self) -> bool {
+        matches!(
+            self.op,
+            TmuxOp::NewSession {
+                window: Some(_),
+                ..
+            } | TmuxOp::NewWindow { .. }
+        )
+    }
+
+    /// Where the pane the command splits off goes, for commands splitting a pane
+    pub fn splits(&self) -> Option<NewPane> {
+        match self.op {
+            TmuxOp::Split { before: true, .. } => Some(NewPane::Before),
+            TmuxOp::Split { before: false, .. } => Some(NewPane::After),
+            _ => None,
+        }
+    }
+
+    /// The subcommand and the name it gives, if any, ex: `new-window editor`
+    pub fn describe(&self) -> String {
+        match &self.op {
+            TmuxOp::NewSession { name, .. } => format!("new-session {name}"),
+            TmuxOp::NewWindow { name, .. } => format!("new-window {name}"),
+            TmuxOp::MoveWindow { source } => format!("move-window {source}"),
+            TmuxOp::Split { .. } => "split-window".to_string(),
+            TmuxOp::SetOption { .. } => "set-option".to_string(),
+            TmuxOp::SendKeys { .. } => "send-keys".to_string(),
+        }
+    }
+}
+
+/// A pane command held back by its startup options, as planned
+#[derive(Debug, Clone)]
+pub struct PlannedPending {
+    pub target: Target,
+    pub label: String,
+    pub command: String,
+    pub startup: Startup,
+}
+
+/// The tmux invocations that recreate a preset, worked out without talking to tmux.
+///
+/// Pane indices are predicted rather than read back from tmux, which assumes the default
+/// `pane-base-index` of 0.
+#[derive(Debug)]
+pub struct SpawnPlan {
+    /// Run in order: creates every window and pane first, then sends the commands that don't
+    /// wait on anything
+    pub commands: Vec<PlannedCommand>,
+    /// Commands held back by their startup options, sent once every pane exists
+    pub pending: Vec<PlannedPending>,
+    /// Names of the windows, in the order the plan creates them
+    pub windows: Vec<String>,
+    /// Their layouts, in the same order
+    pub layouts: Vec<LayoutNode>,
+}
+
+/// Where `windows` go in a session whose windows are already at `taken`, all counted from the
+/// session's `base-index`.
+///
+/// Windows with an [`Window::index`] get it, or the next free index if it's taken. The others go
+/// at the first free index after the window declared before them, so they keep their order.
+pub fn window_offsets(windows: &[Window], taken: &[usize]) -> Result<Vec<usize>, LayoutError> {
+    let mut used = taken.to_vec();
+    let next_free = |used: &mut Vec<usize>, from: usize| {
+        let offset = (from..)
+            .find(|offset| !used.contains(offset))
+            .unwrap_or(from);
+        used.push(offset);
+        offset
+    };
+
+    // Fixed indices first, so windows declared before them don't take their place
+    let fixed = windows
+        .iter()
+        .map(|window| match window.index {
+            // Counted from 1
+            Some(0) => Err(LayoutError::ZeroWindowIndex(window.name.clone())),
+            Some(index) => Ok(Some(next_free(&mut used, index - 1))),
+            None => Ok(None),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut next = 0;
+    Ok(fixed
+        .into_iter()
+        .map(|fixed| {
+            let offset = fixed.unwrap_or_else(|| next_free(&mut used, next));
+            next = offset + 1;
+            offset
+        })
+        .collect())
+}
+
+/// Plans a new session for `preset` with all of its windows and panes.
+///
+/// Windows are created at the indices [`window_offsets`] gives them rather than wherever tmux
+/// finds room, so they end up in the declared order. Layouts whose splits don't add up are
+/// refused, as presets built by hand rather than through [`Preset::builder`] may have them.
+pub fn plan_preset(preset: &Preset) -> Result<SpawnPlan, LayoutError> {
+    let mut plan = SpawnPlan::new();
+    let mut panes = vec![];
+
+    if preset.windows.is_empty() {
+        plan.push(
+            None,
+            TmuxOp::NewSession {
+                name: preset.name.clone(),
+                window: None,
+                inherit_cwd: false,
+            },
+        );
+    }
+
+    let offsets = window_offsets(&preset.windows, &[])?;
+    for (i, (window_cfg, &offset)) in preset.windows.iter().zip(offsets.iter()).enumerate() {
+        verify_layout(&window_cfg.layout)?;
+        if i == 0 {
+            // The session's first window comes with it
+            plan.push(
+                None,
+                TmuxOp::NewSession {
+                    name: preset.name.clone(),
+                    window: Some(window_cfg.name.clone()),
+                    inherit_cwd: window_cfg.cwd == INHERIT_CWD,
+                },
+            );
+            // It comes at the session's `base-index`, as the only window so far
+            if offset != 0 {
+                plan.push(
+                    Some(Target::Index(preset.name.clone(), offset)),
+                    TmuxOp::MoveWindow {
+                        source: format!("{}:", exact_target(&preset.name)),
+                    },
+                );
+            }
+        } else {
+            plan.push(
+                Some(Target::Index(preset.name.clone(), offset)),
+                TmuxOp::NewWindow {
+                    name: window_cfg.name.clone(),
+                    inherit_cwd: window_cfg.cwd == INHERIT_CWD,
+                },
+            );
+        }
+        plan_layout(&mut plan, i, &preset.name, window_cfg, &mut panes);
+        plan_window_options(&mut plan, i, window_cfg);
+    }
+
+    let session = Some(Target::Session(preset.name.clone()));
+    // Tells the session apart from one that happens to be named the same, see `Session::preset`
+    plan.push(
+        session.clone(),
+        set_option(OptionScope::Session, "@muffin_preset", &preset.name),
+    );
+    // A user option, so status lines and scripts can pick up the preset's color
+    if let Some(color) = &preset.color {
+        plan.push(
+            session.clone(),
+            set_option(OptionScope::Session, "@muffin_color", color),
+        );
+    }
+    for (name, value) in preset.options.iter() {
+        plan.push_optional(
+            session.clone(),
+            set_option(OptionScope::Session, name, value),
+        );
+    }
+
+    plan_pane_commands(&mut plan, panes);
+    Ok(plan)
+}
+
+/// Plans a new window in the existing `session`, laid out like `window_cfg`.
+///
+/// The window goes at `offset` from the session's `base-index`, or else at its next free index.
+pub fn plan_window(
+    session: &str,
+    window_cfg: &Window,
+    offset: Option<usize>,
+) -> Result<SpawnPlan, LayoutError> {
+    verify_layout(&window_cfg.layout)?;
+    let mut plan = SpawnPlan::new();
+    let mut panes = vec![];
+
+    let target = match offset {
+        Some(offset) => Target::Index(session.to_string(), offset),
+        None => Target::Session(session.to_string()),
+    };
+    plan.push(
+        Some(target),
+        TmuxOp::NewWindow {
+            name: window_cfg.name.clone(),
+            inherit_cwd: window_cfg.cwd == INHERIT_CWD,
+        },
+    );
+    plan_layout(&mut plan, 0, session, window_cfg, &mut panes);
+    plan_window_options(&mut plan, 0, window_cfg);
+
+    plan_pane_commands(&mut plan, panes);
+    Ok(plan)
+}
+
+/// The cwd and command of each of `window_cfg`'s panes, in the order tmux numbers them once it's
+/// laid out
+pub fn window_panes(window_cfg: &Window) -> Vec<(&str, Option<&str>)> {
+    let mut plan = SpawnPlan::new();
+    let mut panes = vec![];
+    plan_layout(&mut plan, 0, "", window_cfg, &mut panes);
+    panes.sort_by_key(|pane| match pane.target {
+        Target::Pane(_, index) => index,
+        _ => 0,
+    });
+    panes
+        .into_iter()
+        .map(|pane| (pane.cwd, pane.command))
+        .collect()
+}
+
+/// Checks every split of `layout` with [`verify_split_sizes`]
+fn verify_layout(layout: &LayoutNode) -> Result<(), LayoutError> {
+    if let LayoutNode::Split { children, .. } = layout {
+        verify_split_sizes(children)?;
+        children.iter().try_for_each(verify_layout)?;
+    }
+    Ok(())
+}
+
+fn set_option(scope: OptionScope, name: &str, value: &str) -> TmuxOp {
+    TmuxOp::SetOption {
+        scope,
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// Sets the window's own options, once it's laid out, and records the layout for
+/// [`Server::relayout_window`](crate::Server::relayout_window)
+fn plan_window_options(plan: &mut SpawnPlan, window: usize, window_cfg: &Window) {
+    plan.push_optional(
+        Some(Target::Window(window)),
+        set_option(
+            OptionScope::Window,
+            LAYOUT_OPTION,
+            &window_cfg.layout.to_compact(),
+        ),
+    );
+    for (name, value) in window_cfg.options.iter() {
+        plan.push_optional(
+            Some(Target::Window(window)),
+            set_option(OptionScope::Window, name, value),
+        );
+    }
+}
+
+impl SpawnPlan {
+    fn new() -> Self {
+        Self {
+            commands: vec![],
+            pending: vec![],
+            windows: vec![],
+            layouts: vec![],
+        }
+    }
+
+    /// The planned operations, in order
+    pub fn ops(&self) -> impl Iterator<Item = &TmuxOp> {
+        self.commands.iter().map(|command| &command.op)
+    }
+
+    /// Whether some command targets a window index, which depends on the session's `base-index`
+    pub fn uses_indices(&self) -> bool {
+        self.commands
+            .iter()
+            .any(|command| matches!(command.target, Some(Target::Index(..))))
+    }
+
+    fn push(&mut self, target: Option<Target>, op: TmuxOp) {
+        self.commands.push(PlannedCommand {
+            op,
+            target,
+            optional: false,
+        });
+    }
+
+    fn push_optional(&mut self, target: Option<Target>, op: TmuxOp) {
+        self.commands.push(PlannedCommand {
+            op,
+            target,
+            optional: true,
+        });
+    }
+
+    /// Splits the pane at `index` of `window` into a new one of `size`, placed `new_pane` of it
+    fn split(
+        &mut self,
+        window: usize,
+        index: usize,
+        direction: SplitDirection,
+        size: Size,
+        new_pane: NewPane,
+    ) {
+        self.push(
+            Some(Target::Pane(window, index)),
+            TmuxOp::Split {
+                direction,
+                size,
+                before: new_pane == NewPane::Before,
+                inherit_cwd: false,
+            },
+        );
+    }
+}
+
+/// A pane from the preset, along with where it ended up
+struct PlannedPane<'a> {
+    target: Target,
+    cwd: &'a str,
+    command: Option<&'a str>,
+    startup: &'a Startup,
+    remain_on_exit: bool,
+    label: String,
+}
+
+/// Structure phase: splits the window's initial pane into the preset's layout
+fn plan_layout<'a>(
+    plan: &mut SpawnPlan,
+    window: usize,
+    session: &str,
+    window_cfg: &'a Window,
+    panes: &mut Vec<PlannedPane<'a>>,
+) {
+    let label = format!("{}:{}", session, window_cfg.name);
+    plan.windows.push(window_cfg.name.clone());
+    plan.layouts.push(window_cfg.layout.clone());
+
+    // Pane ids in tmux's order, so a pane's index is its position. tmux inserts a new pane right
+    // after the one being split, renumbering everything behind it.
+    let mut order = vec![0];
+    let mut layout_panes = vec![];
+    let splits = plan.commands.len();
+    plan_node(
+        plan,
+        window,
+        &window_cfg.layout,
+        0,
+        &mut order,
+        &mut layout_panes,
+    );
+
+    // Which pane a split ends up holding is only known once the window is fully split, so when
+    // any of them inherits its cwd every split passes the current one on. The others are moved
+    // to their own cwd afterwards anyway.
+    if layout_panes.iter().any(|&(_, cwd, ..)| cwd == INHERIT_CWD) {
+        for split in &mut plan.commands[splits..] {
+            if let TmuxOp::Split { inherit_cwd, .. } = &mut split.op {
+                *inherit_cwd = true;
+            }
+        }
+    }
+
+    // Only once the window is fully split are the indices final
+    panes.extend(
+        layout_panes
+            .into_iter()
+            .map(|(id, cwd, command, startup, remain_on_exit)| PlannedPane {
+                target: Target::Pane(window, position(&order, id)),
+                cwd,
+                command,
+                startup,
+                remain_on_exit,
+                label: label.clone(),
+            }),
+    );
+}
+
+type LayoutPane<'a> = (usize, &'a str, Option<&'a str>, &'a Startup, bool);
+
+fn plan_node<'a>(
+    plan: &mut SpawnPlan,
+    window: usize,
+    node: &'a LayoutNode,
+    pane: usize,
+    order: &mut Vec<usize>,
+    panes: &mut Vec<LayoutPane<'a>>,
+) {
+    match node {
+        LayoutNode::Pane {
+            cwd,
+            command,
+            startup,
+            remain_on_exit,
+            ..
+        } => panes.push((pane, cwd, command.as_deref(), startup, *remain_on_exit)),
+        LayoutNode::Split {
+            direction,
+            children,
+            ..
+        } => {
+            let weights = weights(children);
+            let mut current_pane = pane;
+
+            // Fixed-size children at the end are carved off first, so they don't eat into the
+            // share of the proportional ones. tmux inserts the new pane after the one split.
+            let mut last = children.len();
+            while last > 1
+                && let Size::Cells(cells) = children[last - 1].size()
+            {
+                let index = position(order, current_pane);
+                plan.split(
+                    window,
+                    index,
+                    *direction,
+                    Size::Cells(cells),
+                    NewPane::After,
+                );
+                let new_pane = order.len();
+                order.insert(index + 1, new_pane);
+                plan_node(plan, window, &children[last - 1], new_pane, order, panes);
+                last -= 1;
+            }
+
+            let mut remaining_weight: f32 = weights[..last].iter().sum();
+            for (i, child) in children[..last].iter().enumerate() {
+                // If it's the last child, we don't split anymore;
+                // it just occupies whatever is left in current_pane
+                if i == last - 1 {
+                    plan_node(plan, window, child, current_pane, order, panes);
+                    break;
+                }
+
+                let index = position(order, current_pane);
+                let next_pane = order.len();
+
+                if let Size::Cells(cells) = child.size() {
+                    // `-b` puts the new pane before the one split, so it becomes the child and
+                    // the rest stays in current_pane. Only the proportional panes after it lose
+                    // room for it.
+                    plan.split(
+                        window,
+                        index,
+                        *direction,
+                        Size::Cells(cells),
+                        NewPane::Before,
+                    );
+                    order.insert(index, next_pane);
+                    plan_node(plan, window, child, next_pane, order, panes);
+                    continue;
+                }
+
+                let child_weight = weights[i];
+
+                // Warning: Borrowed from AI slop for math calculations
+
+                // MATH CALCULATION:
+                // Tmux '-p' is the size of the NEW pane relative to the target.
+                // If child needs 20% of the current area, the NEW pane (the rest)
+                // needs to be 80% of the current target.
+                let split_p = Percent::of(remaining_weight - child_weight, remaining_weight);
+
+                // Split the window.
+                // The 'old' pane stays as the 'child', the 'new' pane is the 'rest'.
+                plan.split(
+                    window,
+                    index,
+                    *direction,
+                    Size::Percent(split_p),
+                    NewPane::After,
+                );
+                order.insert(index + 1, next_pane);
+
+                // Recurse into the child we just "carved out"
+                plan_node(plan, window, child, current_pane, order, panes);
+
+                // Move our focus to the newly created pane for the next iteration
+                current_pane = next_pane;
+                remaining_weight -= child_weight;
+            }
+        }
+    }
+}
+
+/// Each child's share of the room fixed-size children leave: percents count as given and autos
+/// split what's left of 100 equally. Fixed-size children weigh nothing.
+pub(crate) fn weights(children: &[LayoutNode]) -> Vec<f32> {
+    let percent_total: u32 = children
+        .iter()
+        .filter_map(|child| match child.size() {
+            Size::Percent(percent) => Some(percent.get() as u32),
+            _ => None,
+        })
+        .sum();
+    let autos = children
+        .iter()
+        .filter(|child| child.size() == Size::Auto)
+        .count();
+    let auto_share = 100_u32.saturating_sub(percent_total) as f32 / autos.max(1) as f32;
+
+    children
+        .iter()
+        .map(|child| match child.size() {
+            Size::Percent(percent) => percent.get() as f32,
+            Size::Cells(_) => 0.0,
+            Size::Auto => auto_share,
+        })
+        .collect()
+}
+
+/// How many cells each of a split's `children` takes out of the split's `extent`, after a cell
+/// for each border between two of them: fixed sizes as given, and what they leave shared out by
+/// [`weights`] the way the splits of [`plan_preset`] do
+pub(crate) fn split_extents(children: &[LayoutNode], extent: u16) -> Vec<u16> {
+    let borders = children.len().saturating_sub(1) as u32;
+    let fixed: u32 = children
+        .iter()
+        .map(|child| match child.size() {
+            Size::Cells(cells) => cells as u32,
+            _ => 0,
+        })
+        .sum();
+    let mut left = (extent as u32).saturating_sub(borders + fixed) as f32;
+    let weights = weights(children);
+    let mut remaining_weight: f32 = weights.iter().sum();
+
+    children
+        .iter()
+        .zip(&weights)
+        .map(|(child, &weight)| match child.size() {
+            Size::Cells(cells) => cells,
+            // Each takes its share of what those before it left, so the last one gets the rest
+            // whatever the rounding
+            _ => {
+                let cells = match remaining_weight > 0.0 {
+                    true => (left * weight / remaining_weight).round(),
+                    false => 0.0,
+                };
+                left -= cells;
+                remaining_weight -= weight;
+                cells.max(1.0) as u16
+            }
+        })
+        .collect()
+}
+
+/// The smallest window, in cells (columns, rows), `layout` can be split in before tmux runs out of
+/// room for a pane, estimated from the shares each split gives its children: every pane needs a
+/// cell, and every border between two panes another.
+pub fn min_window_size(layout: &LayoutNode) -> (u16, u16) {
+    let (width, height) = min_node_size(layout);
+    (width.ceil() as u16, height.ceil() as u16)
+}
+
+fn min_node_size(node: &LayoutNode) -> (f32, f32) {
+    let LayoutNode::Split {
+        direction,
+        children,
+        ..
+    } = node
+    else {
+        return (1.0, 1.0);
+    };
+    // Along the split and across it
+    let sizes = children
+        .iter()
+        .map(|child| match (min_node_size(child), direction) {
+            ((width, height), SplitDirection::Horizontal) => (width, height),
+            ((width, height), SplitDirection::Vertical) => (height, width),
+        })
+        .collect::<Vec<_>>();
+
+    // Fixed sizes are taken as given, and the rest must leave each proportional child its share
+    let weights = weights(children);
+    let total_weight: f32 = weights.iter().sum();
+    let mut fixed = 0.0;
+    let mut shared: f32 = 0.0;
+    for ((child, (along, _)), weight) in children.iter().zip(sizes.iter()).zip(weights) {
+        match child.size() {
+            Size::Cells(cells) => fixed += along.max(cells as f32),
+            _ if weight > 0.0 => shared = shared.max(along * total_weight / weight),
+            _ => shared = shared.max(*along),
+        }
+    }
+    let borders = children.len().saturating_sub(1) as f32;
+    let along = fixed + shared + borders;
+    let across = sizes.iter().map(|&(_, across)| across).fold(1.0, f32::max);
+
+    match direction {
+        SplitDirection::Horizontal => (along, across),
+        SplitDirection::Vertical => (across, along),
+    }
+}
+
+/// Command phase: moves every pane into its cwd and sends or holds back its command
+fn plan_pane_commands(plan: &mut SpawnPlan, panes: Vec<PlannedPane>) {
+    for pane in panes.iter().filter(|pane| pane.remain_on_exit) {
+        plan.push_optional(
+            Some(pane.target.clone()),
+            set_option(OptionScope::Pane, "remain-on-exit", "on"),
+        );
+    }
+
+    for pane in panes.iter().filter(|pane| pane.cwd != INHERIT_CWD) {
+        plan.push(
+            Some(pane.target.clone()),
+            TmuxOp::SendKeys {
+                keys: format!("cd {}", shell_path(pane.cwd)),
+            },
+        );
+    }
+
+    for pane in panes {
+        let Some(command) = pane.command else {
+            continue;
+        };
+        let command = match pane.remain_on_exit {
+            true => exiting_command(command),
+            false => command.to_string(),
+        };
+        if pane.startup.is_immediate() {
+            plan.push(Some(pane.target), TmuxOp::SendKeys { keys: command });
+        } else {
+            plan.pending.push(PlannedPending {
+                target: pane.target,
+                label: pane.label,
+                command,
+                startup: pane.startup.clone(),
+            });
+        }
+    }
+}
+
+/// `command` followed by exiting the shell it's typed into, so the pane dies along with it and
+/// `remain-on-exit` has something to keep
+pub(crate) fn exiting_command(command: &str) -> String {
+    format!("{command}; exit")
+}
+
+fn position(order: &[usize], pane: usize) -> usize {
+    order
+        .iter()
+        .position(|&p| p == pane)
+        .expect("planned panes are never removed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Layout;
+
+    const TMUX: Version = Version::new(3, 4);
+
+    /// Every planned command's argv for `version`, with windows given ids `@1`, `@2`, ... in the
+    /// order they're created and a `base-index` of `base`
+    fn argvs_for(plan: &SpawnPlan, base: usize, version: Version) -> Vec<Vec<String>> {
+        let ids = (1..=plan.windows.len())
+            .map(|id| format!("@{id}"))
+            .collect::<Vec<_>>();
+        plan.commands
+            .iter()
+            .map(|command| command.argv(&ids, base, version))
+            .collect()
+    }
+
+    fn argvs(plan: &SpawnPlan, base: usize) -> Vec<Vec<String>> {
+        argvs_for(plan, base, TMUX)
+    }
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn plan_preset_argv() {
+        let preset = Preset::builder("proj")
+            .cwd("/my proj")
+            .color("blue")
+            .window(Window::builder("dev").layout(Layout::hsplit([
+                Layout::pane().cmd("echo \"it's\"").pct(60),
+                Layout::pane(),
+            ])))
+            .window(Window::builder("logs"))
+            .build()
+            .unwrap();
+        let plan = plan_preset(&preset).unwrap();
+
+        assert_eq!(
+            argvs(&plan, 0),
+            vec![
+                argv(&["new-session", "-d", "-s", "proj", "-n", "dev"])
+                    .into_iter()
+                    .chain(argv(&["-P", "-F", "#{window_id}"]))
+                    .collect(),
+                argv(&["split-window", "-t", "@1.0", "-h", "-l", "40%"]),
+                argv(&[
+                    "set-option",
+                    "-t",
+                    "@1",
+                    "-w",
+                    LAYOUT_OPTION,
+                    "h100%(p60%,p)"
+                ]),
+                argv(&[
+                    "new-window",
+                    "-t",
+                    "=proj:1",
+                    "-n",
+                    "logs",
+                    "-P",
+                    "-F",
+                    "#{window_id}"
+                ]),
+                argv(&["set-option", "-t", "@2", "-w", LAYOUT_OPTION, "p100%"]),
+                argv(&["set-option", "-t", "=proj:", "@muffin_preset", "proj"]),
+                argv(&["set-option", "-t", "=proj:", "@muffin_color", "blue"]),
+                argv(&["send-keys", "-t", "@1.0", "cd '/my proj'", "Enter"]),
+                argv(&["send-keys", "-t", "@1.1", "cd '/my proj'", "Enter"]),
+                argv(&["send-keys", "-t", "@2.0", "cd '/my proj'", "Enter"]),
+                argv(&["send-keys", "-t", "@1.0", "echo \"it's\"", "Enter"]),
+            ]
+        );
+        assert_eq!(plan.windows, ["dev", "logs"]);
+        assert!(plan.pending.is_empty());
+    }
+
+    #[test]
+    fn plan_preset_holds_back_delayed_commands() {
+        let preset = Preset::builder("proj")
+            .cwd("~")
+            .window(
+                Window::builder("dev").layout(Layout::vsplit([
+                    Layout::pane().cmd("db"),
+                    Layout::pane()
+                        .cmd("server")
+                        .delay(std::time::Duration::from_secs(1)),
+                ])),
+            )
+            .build()
+            .unwrap();
+        let plan = plan_preset(&preset).unwrap();
+
+        let sent = argvs(&plan, 0)
+            .into_iter()
+            .filter(|argv| argv[0] == "send-keys")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sent,
+            vec![
+                argv(&["send-keys", "-t", "@1.0", "cd ~", "Enter"]),
+                argv(&["send-keys", "-t", "@1.1", "cd ~", "Enter"]),
+                argv(&["send-keys", "-t", "@1.0", "db", "Enter"]),
+            ]
+        );
+        assert_eq!(plan.pending.len(), 1);
+        assert_eq!(plan.pending[0].target, Target::Pane(0, 1));
+        assert_eq!(plan.pending[0].command, "server");
+        assert_eq!(plan.pending[0].label, "proj:dev");
+    }
+
+    #[test]
+    fn plan_preset_quotes_cwds_but_leaves_home() {
+        let preset = Preset::builder("proj").cwd("~/it's here").build().unwrap();
+        let plan = plan_preset(&preset).unwrap();
+        assert!(argvs(&plan, 0).contains(&argv(&[
+            "send-keys",
+            "-t",
+            "@1.0",
+            "cd ~/'it'\\''s here'",
+            "Enter"
+        ])));
+    }
+
+    #[test]
+    fn split_args_follow_the_version() {
+        let split = |size| TmuxOp::Split {
+            direction: SplitDirection::Vertical,
+            size,
+            before: false,
+            inherit_cwd: false,
+        };
+        let percent = split(Size::Percent(Percent::saturating(30)));
+        assert_eq!(
+            op_argv(&percent, None, Version::new(3, 1)),
+            argv(&["split-window", "-v", "-l", "30%"])
+        );
+        assert_eq!(
+            op_argv(&percent, None, Version::new(3, 0)),
+            argv(&["split-window", "-v", "-p", "30"])
+        );
+        // Cells were always `-l`
+        assert_eq!(
+            op_argv(&split(Size::Cells(12)), None, Version::MINIMUM),
+            argv(&["split-window", "-v", "-l", "12"])
+        );
+    }
+
+    #[test]
+    fn remain_on_exit_is_a_pane_option_from_3_0() {
+        let preset = Preset::builder("proj")
+            .window(Window::builder("dev").layout(Layout::pane().cmd("make").remain_on_exit(true)))
+            .build()
+            .unwrap();
+        let option = |version| {
+            argvs_for(&plan_preset(&preset).unwrap(), 0, version)
+                .into_iter()
+                .find(|argv| argv.contains(&"remain-on-exit".to_string()))
+                .unwrap()
+        };
+        assert_eq!(
+            option(Version::new(3, 0)),
+            argv(&["set-option", "-t", "@1.0", "-p", "remain-on-exit", "on"])
+        );
+        assert_eq!(
+            option(Version::MINIMUM),
+            argv(&["set-option", "-t", "@1.0", "-w", "remain-on-exit", "on"])
+        );
+    }
+
+    fn window(layout: Layout) -> Window {
+        Preset::builder("proj")
+            .window(Window::builder("dev").layout(layout))
+            .build()
+            .unwrap()
+            .windows
+            .remove(0)
+    }
+
+    /// The argv of each command of `window`'s plan running `subcommand`
+    fn planned(window: &Window, subcommand: &str) -> Vec<Vec<String>> {
+        argvs(&plan_window("proj", window, None).unwrap(), 0)
+            .into_iter()
+            .filter(|argv| argv[0] == subcommand)
+            .collect()
+    }
+
+    /// The commands of `window`'s panes in the order tmux numbers them
+    fn commands(window: &Window) -> Vec<&str> {
+        window_panes(window)
+            .into_iter()
+            .map(|(_, command)| command.unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn percent_of_rounds_and_stays_in_range() {
+        assert_eq!(Percent::of(1.0, 3.0).get(), 33);
+        assert_eq!(Percent::of(2.0, 3.0).get(), 67);
+        assert_eq!(Percent::of(50.0, 100.0).get(), 50);
+        assert_eq!(Percent::of(100.0 / 3.0, 200.0 / 3.0).get(), 50);
+        // Never nothing, never more than all of it
+        assert_eq!(Percent::of(0.0, 10.0).get(), 1);
+        assert_eq!(Percent::of(150.0, 100.0).get(), 100);
+        assert_eq!(Percent::of(5.0, 0.0), Percent::FULL);
+        assert_eq!(Percent::of(f32::NAN, 1.0), Percent::FULL);
+    }
+
+    #[test]
+    fn equal_shares_are_split_off_one_by_one() {
+        let window = window(Layout::vsplit([
+            Layout::pane().cmd("a"),
+            Layout::pane().cmd("b"),
+            Layout::pane().cmd("c"),
+        ]));
+        // Each split leaves the rest to the new pane: two thirds, then half of that
+        assert_eq!(
+            planned(&window, "split-window"),
+            vec![
+                argv(&["split-window", "-t", "@1.0", "-v", "-l", "67%"]),
+                argv(&["split-window", "-t", "@1.1", "-v", "-l", "50%"]),
+            ]
+        );
+        assert_eq!(commands(&window), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn nested_splits() {
+        let window = window(Layout::hsplit([
+            Layout::pane().cmd("a").pct(30),
+            Layout::vsplit([Layout::pane().cmd("b").pct(25), Layout::pane().cmd("c")]),
+        ]));
+        assert_eq!(
+            planned(&window, "split-window"),
+            vec![
+                argv(&["split-window", "-t", "@1.0", "-h", "-l", "70%"]),
+                argv(&["split-window", "-t", "@1.1", "-v", "-l", "75%"]),
+            ]
+        );
+        assert_eq!(commands(&window), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn splitting_an_earlier_pane_renumbers_the_later_ones() {
+        let window = window(Layout::hsplit([
+            Layout::vsplit([Layout::pane().cmd("a"), Layout::pane().cmd("b")]),
+            Layout::pane().cmd("c"),
+        ]));
+        // `c` is split off first as pane 1, then `b` goes in between
+        assert_eq!(
+            planned(&window, "split-window"),
+            vec![
+                argv(&["split-window", "-t", "@1.0", "-h", "-l", "50%"]),
+                argv(&["split-window", "-t", "@1.0", "-v", "-l", "50%"]),
+            ]
+        );
+        assert_eq!(commands(&window), ["a", "b", "c"]);
+        assert_eq!(
+            planned(&window, "send-keys")
+                .into_iter()
+                .filter(|argv| !argv[3].starts_with("cd "))
+                .collect::<Vec<_>>(),
+            vec![
+                argv(&["send-keys", "-t", "@1.0", "a", "Enter"]),
+                argv(&["send-keys", "-t", "@1.1", "b", "Enter"]),
+                argv(&["send-keys", "-t", "@1.2", "c", "Enter"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn fixed_size_children_before_others_are_split_off_with_b() {
+        let window = window(Layout::vsplit([
+            Layout::pane().cmd("a").cells(5),
+            Layout::pane().cmd("b"),
+            Layout::pane().cmd("c"),
+        ]));
+        // The fixed pane doesn't count towards the shares of the others
+        assert_eq!(
+            planned(&window, "split-window"),
+            vec![
+                argv(&["split-window", "-t", "@1.0", "-b", "-v", "-l", "5"]),
+                argv(&["split-window", "-t", "@1.1", "-v", "-l", "50%"]),
+            ]
+        );
+        assert_eq!(commands(&window), ["a", "b", "c"]);
+
+        let plan = plan_window("proj", &window, None).unwrap();
+        let splits = plan
+            .commands
+            .iter()
+            .filter_map(|command| command.splits())
+            .collect::<Vec<_>>();
+        assert_eq!(splits, [NewPane::Before, NewPane::After]);
+    }
+
+    #[test]
+    fn fixed_size_children_at_the_end_are_split_off_first() {
+        let window = window(Layout::vsplit([
+            Layout::pane().cmd("a"),
+            Layout::pane().cmd("b"),
+            Layout::pane().cmd("c").cells(3),
+        ]));
+        assert_eq!(
+            planned(&window, "split-window"),
+            vec![
+                argv(&["split-window", "-t", "@1.0", "-v", "-l", "3"]),
+                argv(&["split-window", "-t", "@1.0", "-v", "-l", "50%"]),
+            ]
+        );
+        assert_eq!(commands(&window), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn single_pane_window() {
+        let preset = Preset::builder("proj").cwd("/p").build().unwrap();
+        let plan = plan_preset(&preset).unwrap();
+        let argvs = argvs(&plan, 0);
+        assert!(!argvs.iter().any(|argv| argv[0] == "split-window"));
+        assert_eq!(
+            argvs[0],
+            argv(&[
+                "new-session",
+                "-d",
+                "-s",
+                "proj",
+                "-n",
+                "main",
+                "-P",
+                "-F",
+                "#{window_id}"
+            ])
+        );
+        assert!(argvs.contains(&argv(&["send-keys", "-t", "@1.0", "cd /p", "Enter"])));
+        assert_eq!(plan.windows, ["main"]);
+        assert!(!plan.uses_indices());
+    }
+
+    #[test]
+    fn preset_without_windows_is_a_bare_session() {
+        let mut preset = Preset::builder("proj").build().unwrap();
+        preset.windows.clear();
+        let plan = plan_preset(&preset).unwrap();
+        assert_eq!(
+            argvs(&plan, 0),
+            vec![
+                argv(&["new-session", "-d", "-s", "proj"]),
+                argv(&["set-option", "-t", "=proj:", "@muffin_preset", "proj"]),
+            ]
+        );
+    }
+
+    fn windows(indices: &[Option<usize>]) -> Vec<Window> {
+        indices
+            .iter()
+            .enumerate()
+            .map(|(i, index)| {
+                let builder = Window::builder(format!("w{i}"));
+                let builder = match index {
+                    Some(index) => builder.index(*index),
+                    None => builder,
+                };
+                Preset::builder("proj")
+                    .window(builder)
+                    .build()
+                    .unwrap()
+                    .windows
+                    .remove(0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn window_offsets_in_order() {
+        assert_eq!(
+            window_offsets(&windows(&[None, None, None]), &[]).unwrap(),
+            [0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn window_offsets_with_fixed_indices() {
+        // Counted from 1, and later windows follow the fixed one
+        assert_eq!(
+            window_offsets(&windows(&[None, Some(5), None]), &[]).unwrap(),
+            [0, 4, 5]
+        );
+        // Declared first, but the fixed window keeps its place
+        assert_eq!(
+            window_offsets(&windows(&[None, None, Some(2)]), &[]).unwrap(),
+            [0, 2, 1]
+        );
+    }
+
+    #[test]
+    fn window_offsets_around_taken_indices() {
+        assert_eq!(
+            window_offsets(&windows(&[None, None]), &[0, 1, 3]).unwrap(),
+            [2, 4]
+        );
+        // A fixed index that's taken goes to the next free one
+        assert_eq!(
+            window_offsets(&windows(&[Some(1), None]), &[0, 1]).unwrap(),
+            [2, 3]
+        );
+    }
+
+    #[test]
+    fn fixed_indices_next_to_the_window_at_base_index() {
+        // `index=2` is the offset 1 right after the base-index window, not past it
+        assert_eq!(window_offsets(&windows(&[Some(2)]), &[0]).unwrap(), [1]);
+        // `index=1` is the base-index window's, and offset 1 is already given to `index=2`
+        assert_eq!(
+            window_offsets(&windows(&[Some(2), Some(1)]), &[0]).unwrap(),
+            [1, 2]
+        );
+        assert_eq!(
+            window_offsets(&windows(&[Some(1), None, Some(3)]), &[0]).unwrap(),
+            [1, 3, 2]
+        );
+    }
+
+    #[test]
+    fn index_targets_count_from_base_index() {
+        let target = Target::Index("proj".to_string(), 2);
+        assert_eq!(target.resolve(&[], 0), "=proj:2");
+        assert_eq!(target.resolve(&[], 1), "=proj:3");
+        // Only window indices move with it
+        let session = Target::Session("proj".to_string());
+        assert_eq!(session.resolve(&[], 1), "=proj:");
+    }
+
+    /// Where each `new-window` and `move-window` of `plan` goes, with a `base-index` of `base`
+    fn window_targets(plan: &SpawnPlan, base: usize) -> Vec<(String, String)> {
+        argvs(plan, base)
+            .into_iter()
+            .filter(|argv| argv[0] == "new-window" || argv[0] == "move-window")
+            .map(|argv| (argv[0].clone(), argv[2].clone()))
+            .collect()
+    }
+
+    #[test]
+    fn plan_preset_with_base_index_1() {
+        let mut preset = Preset::builder("proj").build().unwrap();
+        preset.windows = windows(&[Some(3), None, Some(2)]);
+        let plan = plan_preset(&preset).unwrap();
+        let target = |subcommand: &str, target: &str| (subcommand.to_string(), target.to_string());
+
+        assert_eq!(
+            window_targets(&plan, 1),
+            [
+                target("move-window", "=proj:3"),
+                target("new-window", "=proj:4"),
+                target("new-window", "=proj:2"),
+            ]
+        );
+        assert_eq!(
+            window_targets(&plan, 0),
+            [
+                target("move-window", "=proj:2"),
+                target("new-window", "=proj:3"),
+                target("new-window", "=proj:1"),
+            ]
+        );
+        // The window moved is still the session's only one, whatever its index
+        let moved = argvs(&plan, 1)
+            .into_iter()
+            .find(|argv| argv[0] == "move-window")
+            .unwrap();
+        assert_eq!(
+            moved,
+            argv(&["move-window", "-t", "=proj:3", "-s", "=proj:"])
+        );
+    }
+
+    #[test]
+    fn auto_windows_fill_in_around_fixed_ones() {
+        // Fixed at offsets 0 and 2, each declared after a window that would have taken its place
+        let mut preset = Preset::builder("proj").build().unwrap();
+        preset.windows = windows(&[None, Some(1), None, Some(3)]);
+        assert_eq!(window_offsets(&preset.windows, &[]).unwrap(), [1, 0, 3, 2]);
+
+        let plan = plan_preset(&preset).unwrap();
+        let target = |subcommand: &str, target: &str| (subcommand.to_string(), target.to_string());
+        assert_eq!(
+            window_targets(&plan, 0),
+            [
+                target("move-window", "=proj:1"),
+                target("new-window", "=proj:0"),
+                target("new-window", "=proj:3"),
+                target("new-window", "=proj:2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn uses_indices_when_windows_go_at_an_index() {
+        let mut preset = Preset::builder("proj").build().unwrap();
+        preset.windows = windows(&[None, None]);
+        assert!(plan_preset(&preset).unwrap().uses_indices());
+        // A single window only moves if it's given an index
+        preset.windows = windows(&[None]);
+        assert!(!plan_preset(&preset).unwrap().uses_indices());
+        preset.windows = windows(&[Some(4)]);
+        assert!(plan_preset(&preset).unwrap().uses_indices());
+
+        let window = &windows(&[None])[0];
+        assert!(!plan_window("proj", window, None).unwrap().uses_indices());
+        assert!(plan_window("proj", window, Some(1)).unwrap().uses_indices());
+    }
+
+    fn split(direction: SplitDirection, size: Size, before: bool) -> TmuxOp {
+        TmuxOp::Split {
+            direction,
+            size,
+            before,
+            inherit_cwd: false,
+        }
+    }
+
+    fn send_keys(keys: &str) -> TmuxOp {
+        TmuxOp::SendKeys {
+            keys: keys.to_string(),
+        }
+    }
+
+    fn percent(percent: i64) -> Size {
+        Size::Percent(Percent::saturating(percent))
+    }
+
+    #[test]
+    fn single_pane_windows_plan_no_splits() {
+        let plan = plan_window("proj", &window(Layout::pane().cmd("htop")), None).unwrap();
+        assert_eq!(
+            plan.ops().cloned().collect::<Vec<_>>(),
+            [
+                TmuxOp::NewWindow {
+                    name: "dev".to_string(),
+                    inherit_cwd: false,
+                },
+                set_option(OptionScope::Window, LAYOUT_OPTION, "p100%"),
+                send_keys("cd ~"),
+                send_keys("htop"),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_splits_plan_their_ops_in_order() {
+        // A fixed-size pane next to an auto one, in a split next to a percent
+        let layout = Layout::hsplit([
+            Layout::pane().pct(30),
+            Layout::vsplit([Layout::pane(), Layout::pane().cells(10)]),
+        ]);
+        let plan = plan_window("proj", &window(layout), None).unwrap();
+        let splits = plan
+            .ops()
+            .filter(|op| matches!(op, TmuxOp::Split { .. }))
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            splits,
+            [
+                split(SplitDirection::Horizontal, percent(70), false),
+                split(SplitDirection::Vertical, Size::Cells(10), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn layouts_that_dont_add_up_are_refused() {
+        let mut preset = Preset::builder("proj")
+            .window(Window::builder("dev"))
+            .build()
+            .unwrap();
+        // Inside another split, which is fine on its own
+        let mut pane = preset.windows[0].layout.clone();
+        if let LayoutNode::Pane { size, .. } = &mut pane {
+            *size = Size::Auto;
+        }
+        preset.windows[0].layout = LayoutNode::Split {
+            direction: SplitDirection::Horizontal,
+            children: vec![
+                pane,
+                LayoutNode::Split {
+                    direction: SplitDirection::Vertical,
+                    children: vec![],
+                    size: Size::Auto,
+                    even: None,
+                },
+            ],
+            size: Size::Percent(Percent::FULL),
+            even: None,
+        };
+        assert_eq!(plan_preset(&preset).unwrap_err(), LayoutError::EmptySplit);
+        assert_eq!(
+            plan_window("proj", &preset.windows[0], None).unwrap_err(),
+            LayoutError::EmptySplit
+        );
+    }
+
+    #[test]
+    fn windows_at_index_zero_are_refused() {
+        let mut preset = Preset::builder("proj").build().unwrap();
+        preset.windows = windows(&[None, Some(1)]);
+        preset.windows[1].index = Some(0);
+        assert_eq!(
+            plan_preset(&preset).unwrap_err(),
+            LayoutError::ZeroWindowIndex("w1".to_string())
+        );
+    }
+
+    #[test]
+    fn min_window_sizes() {
+        for (layout, size) in [
+            (Layout::pane(), (1, 1)),
+            // A cell each and one for the border
+            (Layout::hsplit([Layout::pane(), Layout::pane()]), (3, 1)),
+            (Layout::vsplit([Layout::pane(), Layout::pane()]), (1, 3)),
+            // A 30% share only gets a cell once the window is 4 of them, plus the border
+            (
+                Layout::hsplit([Layout::pane().pct(30), Layout::pane()]),
+                (5, 1),
+            ),
+            // Fixed sizes are taken as given
+            (
+                Layout::vsplit([Layout::pane(), Layout::pane().cells(10)]),
+                (1, 12),
+            ),
+            // Nested splits add up along and take the widest across
+            (
+                Layout::hsplit([
+                    Layout::pane(),
+                    Layout::vsplit([Layout::pane(), Layout::pane(), Layout::pane()]),
+                ]),
+                (3, 5),
+            ),
+        ] {
+            let window = window(layout);
+            assert_eq!(
+                min_window_size(&window.layout),
+                size,
+                "{}",
+                window.layout.to_compact()
+            );
+        }
+    }
+
+    #[test]
+    fn split_extents_share_what_the_borders_leave() {
+        let extents = |layout: Layout, extent| {
+            let LayoutNode::Split { children, .. } = window(layout).layout else {
+                panic!("not a split");
+            };
+            split_extents(&children, extent)
+        };
+        let panes = |count| Layout::hsplit((0..count).map(|_| Layout::pane()));
+        // 79 cells once the two borders are out, the last one getting what rounding leaves
+        assert_eq!(extents(panes(3), 81), [26, 26, 27]);
+        assert_eq!(extents(panes(2), 81), [40, 40]);
+        assert_eq!(
+            extents(
+                Layout::hsplit([Layout::pane().pct(30), Layout::pane().pct(70)]),
+                101
+            ),
+            [30, 70]
+        );
+        // Fixed sizes come out first
+        assert_eq!(
+            extents(
+                Layout::hsplit([Layout::pane().cells(10), Layout::pane()]),
+                50
+            ),
+            [10, 39]
+        );
+        // Every pane keeps a cell, however little room there is
+        assert_eq!(extents(panes(3), 2), [1, 1, 1]);
+    }
+}
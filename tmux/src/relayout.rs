@@ -0,0 +1,175 @@
+use std::fmt;
+
+use crate::plan::split_extents;
+use crate::{LayoutNode, LivePane, SplitDirection};
+
+/// Window option the layout a window was spawned with is recorded in, in the format of
+/// [`LayoutNode::to_compact`]
+pub const LAYOUT_OPTION: &str = "@muffin_layout";
+
+/// How [`Server::relayout_window`](crate::Server::relayout_window) went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Relayout {
+    Applied,
+    /// The window has no layout recorded, as it wasn't spawned from a preset
+    NotRecorded,
+    /// What's recorded couldn't be read
+    Unreadable(String),
+    /// Panes were added or closed since, so they no longer match the layout
+    PanesChanged,
+    /// A pane is zoomed, hiding the sizes of the others
+    Zoomed,
+}
+
+impl fmt::Display for Relayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Relayout::Applied => write!(f, "laid out again"),
+            Relayout::NotRecorded => {
+                write!(f, "no layout recorded, it wasn't launched from a preset")
+            }
+            Relayout::Unreadable(e) => write!(f, "its recorded layout can't be read: {e}"),
+            Relayout::PanesChanged => {
+                write!(f, "panes were added or closed since it was laid out")
+            }
+            Relayout::Zoomed => write!(f, "a pane is zoomed"),
+        }
+    }
+}
+
+/// A `resize-pane` setting the split child holding the pane at `pane` (its position in tmux's
+/// order) to `cells` columns or rows, depending on `direction`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Resize {
+    pub pane: usize,
+    pub direction: SplitDirection,
+    pub cells: u16,
+}
+
+/// The resizes bringing a window of `width` by `height` cells, split like `layout`, back to the
+/// layout's proportions. Each split's children are resized before what's in them, and all but
+/// the last, which gets what the others leave.
+pub(crate) fn plan_resizes(layout: &LayoutNode, width: u16, height: u16) -> Vec<Resize> {
+    let mut resizes = vec![];
+    resize_node(layout, width, height, 0, &mut resizes);
+    resizes
+}
+
+fn resize_node(
+    node: &LayoutNode,
+    width: u16,
+    height: u16,
+    first_pane: usize,
+    resizes: &mut Vec<Resize>,
+) {
+    let LayoutNode::Split {
+        direction,
+        children,
+        ..
+    } = node
+    else {
+        return;
+    };
+    let extent = match direction {
+        SplitDirection::Horizontal => width,
+        SplitDirection::Vertical => height,
+    };
+    let extents = split_extents(children, extent);
+
+    // tmux numbers panes in the layout's own order, so a child's panes follow its elder siblings'
+    let mut first_panes = vec![];
+    let mut pane = first_pane;
+    for child in children {
+        first_panes.push(pane);
+        pane += child.pane_count();
+    }
+
+    for (&pane, &cells) in first_panes.iter().zip(&extents).take(children.len() - 1) {
+        resizes.push(Resize {
+            pane,
+            direction: *direction,
+            cells,
+        });
+    }
+    for ((child, &pane), &cells) in children.iter().zip(&first_panes).zip(&extents) {
+        let (width, height) = match direction {
+            SplitDirection::Horizontal => (cells, height),
+            SplitDirection::Vertical => (width, cells),
+        };
+        resize_node(child, width, height, pane, resizes);
+    }
+}
+
+/// Whether any split in `node` is to be evened out, those that don't say going by `default`
+pub(crate) fn evens_out(node: &LayoutNode, default: bool) -> bool {
+    match node {
+        LayoutNode::Pane { .. } => false,
+        LayoutNode::Split { children, even, .. } => {
+            even.unwrap_or(default) || children.iter().any(|child| evens_out(child, default))
+        }
+    }
+}
+
+/// How many cells `panes` span across `direction`, the borders between them included
+pub(crate) fn span(panes: &[LivePane], direction: SplitDirection) -> u16 {
+    let (start, end) = match direction {
+        SplitDirection::Horizontal => (
+            panes.iter().map(|p| p.left).min(),
+            panes.iter().map(|p| p.left + p.width).max(),
+        ),
+        SplitDirection::Vertical => (
+            panes.iter().map(|p| p.top).min(),
+            panes.iter().map(|p| p.top + p.height).max(),
+        ),
+    };
+    end.unwrap_or_default() - start.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(pane, -x or -y, cells)` of each resize bringing a `width` by `height` window back to
+    /// `compact`
+    fn resizes(compact: &str, width: u16, height: u16) -> Vec<(usize, &'static str, u16)> {
+        let layout = LayoutNode::from_compact(compact).unwrap();
+        plan_resizes(&layout, width, height)
+            .into_iter()
+            .map(|resize| {
+                let flag = match resize.direction {
+                    SplitDirection::Horizontal => "-x",
+                    SplitDirection::Vertical => "-y",
+                };
+                (resize.pane, flag, resize.cells)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn each_child_but_the_last_is_resized() {
+        // 99 columns once the border is out
+        assert_eq!(resizes("h(p30%,p)", 100, 30), [(0, "-x", 30)]);
+        assert_eq!(resizes("h(p,p,p)", 80, 30), [(0, "-x", 26), (1, "-x", 26)]);
+        assert!(resizes("p", 100, 30).is_empty());
+    }
+
+    #[test]
+    fn fixed_sizes_are_kept() {
+        assert_eq!(resizes("v(p,p10c)", 100, 40), [(0, "-y", 29)]);
+        assert_eq!(resizes("v(p10c,p)", 100, 40), [(0, "-y", 10)]);
+    }
+
+    #[test]
+    fn nested_splits_are_resized_after_their_parent() {
+        // The split below starts at pane 1, and is as wide as the window
+        assert_eq!(
+            resizes("v(p,h(p,p))", 81, 25),
+            [(0, "-y", 12), (1, "-x", 40)]
+        );
+        // The second column's panes come after the first's three
+        assert_eq!(
+            resizes("h(v(p,p,p),v(p,p))", 101, 32),
+            [(0, "-x", 50), (0, "-y", 10), (1, "-y", 10), (3, "-y", 16)]
+        );
+    }
+}
@@ -0,0 +1,1799 @@
+use std::fmt;
+use std::io::Read;
+use std::ops::ControlFlow;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::diff::{PresetDiff, compare_session};
+use crate::error::TmuxError;
+use crate::log;
+use crate::note::{escape_note, unescape_note};
+use crate::plan::{
+    NewPane, OptionScope, SpawnPlan, Target, TmuxOp, exiting_command, plan_preset, plan_window,
+    split_extents, window_offsets,
+};
+use crate::relayout::{evens_out, plan_resizes, span};
+use crate::{
+    Alert, INHERIT_CWD, LAYOUT_OPTION, LayoutError, LayoutNode, LivePane, LiveWindow,
+    PendingCommand, Preset, PresetAppend, PresetSpawn, PresetUpdate, Relayout, ResizeDirection,
+    Session, Size, SpawnProgress, SplitDirection, Version, WaitFor, Window, exact_target,
+    validate_session_name,
+};
+
+/// How long a tmux command gets to exit before it's killed and reported as
+/// [`TmuxError::Timeout`]. Ordinary commands take milliseconds, so only a hung server gets there.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The tmux server that commands are sent to.
+///
+/// Defaults to the server muffin runs in (or tmux's default socket), but can point at a server on
+/// another socket (`tmux -L <name>`) and/or on a remote machine reached over ssh.
+#[derive(Debug, Clone, Default)]
+pub struct Server {
+    socket: Option<String>,
+    host: Option<String>,
+    /// Config file the server reads if these commands start it, like `tmux -f <path>`
+    config: Option<String>,
+    /// How long a command gets before it's killed, [`DEFAULT_TIMEOUT`] if unset
+    timeout: Option<Duration>,
+    /// Whether splits that don't say are evened out once spawned, see [`Server::with_even_splits`]
+    even_splits: bool,
+    /// `tmux -V`, only asked once and shared between clones
+    version: Arc<OnceLock<Result<Version, TmuxError>>>,
+}
+
+impl Server {
+    pub fn local() -> Self {
+        Self::default()
+    }
+
+    /// A local server listening on a named socket, like `tmux -L <name>`
+    pub fn socket(name: &str) -> Self {
+        Self {
+            socket: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// The default server on a remote host, driven via `ssh <host> tmux ...`
+    pub fn ssh(host: &str) -> Self {
+        Self {
+            host: Some(host.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Uses a named socket on whichever machine this server points at
+    pub fn with_socket(mut self, name: &str) -> Self {
+        self.socket = Some(name.to_string());
+        self
+    }
+
+    /// Has the server read `path` instead of `~/.tmux.conf` when one of its commands starts it.
+    ///
+    /// A leading `~/` is expanded on this machine, so only for local servers.
+    pub fn with_config(mut self, path: &str) -> Self {
+        self.config = Some(match self.local_home() {
+            Some(home) if path.starts_with("~/") => expand_home(path, &home),
+            _ => path.to_string(),
+        });
+        self
+    }
+
+    /// The home directory a leading `~` stands for, if this machine's is the one. A remote
+    /// server's shell expands it on its own.
+    fn local_home(&self) -> Option<String> {
+        std::env::var("HOME")
+            .ok()
+            .filter(|home| self.is_local() && !home.is_empty())
+    }
+
+    /// `windows` with a leading `~` in their panes' cwds expanded, on a local server, so panes
+    /// are moved to an absolute path
+    fn with_local_cwds(&self, mut windows: Vec<Window>) -> Vec<Window> {
+        fn expand(node: &mut LayoutNode, home: &str) {
+            match node {
+                LayoutNode::Pane { cwd, .. } => *cwd = expand_home(cwd, home),
+                LayoutNode::Split { children, .. } => {
+                    children.iter_mut().for_each(|child| expand(child, home))
+                }
+            }
+        }
+        if let Some(home) = self.local_home() {
+            for window in &mut windows {
+                window.cwd = expand_home(&window.cwd, &home);
+                expand(&mut window.layout, &home);
+            }
+        }
+        windows
+    }
+
+    /// [`Server::with_local_cwds`] for a whole preset
+    fn local_preset(&self, preset: &Preset) -> Preset {
+        let mut preset = preset.clone();
+        if let Some(home) = self.local_home() {
+            preset.cwd = expand_home(&preset.cwd, &home);
+        }
+        preset.windows = self.with_local_cwds(preset.windows);
+        preset
+    }
+
+    /// Gives up on commands that take longer than `timeout`, instead of [`DEFAULT_TIMEOUT`]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Evens out every split that doesn't say otherwise (`even` in presets files) once a preset
+    /// or window is spawned: each split's children are resized to their exact shares, making up
+    /// for tmux rounding every split it makes. Costs a `list-panes` per window, and one per pane
+    /// that came out off.
+    pub fn with_even_splits(mut self, even_splits: bool) -> Self {
+        self.even_splits = even_splits;
+        self
+    }
+
+    /// The server `preset` is launched on: this one, unless the preset names a socket or a tmux
+    /// config of its own ([`Preset::socket`], [`Preset::tmux_config`]).
+    ///
+    /// Preset sockets are on the same machine as this server.
+    pub fn for_preset(&self, preset: &Preset) -> Server {
+        let server = match &preset.socket {
+            Some(socket) => self.clone().with_socket(socket),
+            None => self.clone(),
+        };
+        match &preset.tmux_config {
+            Some(config) => server.with_config(config),
+            None => server,
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.host.is_none()
+    }
+
+    /// The version of tmux on the machine this server runs on
+    pub fn tmux_version(&self) -> Result<Version, TmuxError> {
+        self.version
+            .get_or_init(|| {
+                let output = self.run(&["-V"])?;
+                Version::parse(&output)
+                    .ok_or_else(|| self.parse_error(&["-V"], "Unrecognized tmux version"))
+            })
+            .clone()
+    }
+
+    /// The version to pick flags for. Builds without a version number are development builds, so
+    /// they're assumed to support everything.
+    fn feature_version(&self) -> Version {
+        self.tmux_version()
+            .unwrap_or(Version::new(u32::MAX, u32::MAX))
+    }
+
+    /// Whether this is the server muffin itself is running inside of
+    fn is_current_server(&self) -> bool {
+        if !self.is_local() {
+            return false;
+        }
+        match &self.socket {
+            None => true,
+            // $TMUX looks like `/tmp/tmux-1000/default,1234,0`
+            Some(socket) => std::env::var("TMUX").is_ok_and(|tmux| {
+                tmux.split(',')
+                    .next()
+                    .and_then(|path| path.rsplit('/').next())
+                    .is_some_and(|name| name == socket)
+            }),
+        }
+    }
+
+    /// Whether muffin runs in one of this server's panes (or a popup over one), which killing the
+    /// server would take down with it
+    pub fn hosts_muffin(&self) -> bool {
+        self.is_current_server() && std::env::var_os("TMUX").is_some()
+    }
+
+    /// Size in cells (columns, rows) of the client muffin runs under, if it runs in this server
+    pub fn client_size(&self) -> Option<(u16, u16)> {
+        if !self.hosts_muffin() {
+            return None;
+        }
+        let output = self
+            .run(&["display-message", "-p", "#{client_width} #{client_height}"])
+            .ok()?;
+        let (width, height) = output.trim().split_once(' ')?;
+        Some((width.parse().ok()?, height.parse().ok()?))
+    }
+
+    /// Creates the preset's session with all of its windows and panes.
+    ///
+    /// Every pane is created before any command is sent. Commands without startup options are
+    /// sent right away; the rest are returned so the caller can send them once their delays and
+    /// waits are over. Options tmux refuses are only warned about.
+    ///
+    /// The session goes on the preset's own server if it names one, see [`Server::for_preset`].
+    pub fn spawn_preset(&self, preset: &Preset) -> Result<PresetSpawn, TmuxError> {
+        let server = self.for_preset(preset);
+        let args = ["new-session", "-s", &preset.name];
+        server.check_name(&args, &preset.name)?;
+        let plan = plan_preset(&server.local_preset(preset))
+            .map_err(|error| server.layout_error(&args, error))?;
+        server.execute(plan)
+    }
+
+    /// Like [`Server::spawn_preset`], calling `on_step` before each tmux command. Once it breaks,
+    /// the rest of the commands are skipped, leaving the session as far as it got.
+    pub fn spawn_preset_with_progress(
+        &self,
+        preset: &Preset,
+        on_step: impl FnMut(SpawnProgress) -> ControlFlow<()>,
+    ) -> Result<ControlFlow<(), PresetSpawn>, TmuxError> {
+        let server = self.for_preset(preset);
+        let args = ["new-session", "-s", &preset.name];
+        server.check_name(&args, &preset.name)?;
+        let plan = plan_preset(&server.local_preset(preset))
+            .map_err(|error| server.layout_error(&args, error))?;
+        server.execute_with(plan, on_step)
+    }
+
+    /// The tmux invocations [`Server::spawn_preset`] would run for `preset`, without running them
+    pub fn dry_run(&self, preset: &Preset) -> Result<Vec<String>, LayoutError> {
+        let server = self.for_preset(preset);
+        crate::dry_run(&server.local_preset(preset), server.feature_version())
+    }
+
+    /// Creates a new window in an existing session and lays out its panes.
+    ///
+    /// Like [`Server::spawn_preset`], returns the commands still waiting on their startup options.
+    pub fn create_window(
+        &self,
+        session: &str,
+        window_cfg: &Window,
+    ) -> Result<PresetSpawn, TmuxError> {
+        let window = self.with_local_cwds(vec![window_cfg.clone()]).remove(0);
+        let plan = plan_window(session, &window, None)
+            .map_err(|error| self.layout_error(&["new-window", "-n", &window_cfg.name], error))?;
+        self.execute(plan)
+    }
+
+    /// Creates `windows` in an existing session like [`Server::create_window`], each at the index
+    /// [`window_offsets`] picks around the windows already there
+    fn create_windows<'w>(
+        &self,
+        session: &str,
+        windows: impl IntoIterator<Item = &'w Window>,
+    ) -> Result<PresetSpawn, TmuxError> {
+        let windows = self.with_local_cwds(windows.into_iter().cloned().collect());
+        let base = self.base_index(session)?;
+        let taken = self
+            .list_windows(session)?
+            .iter()
+            .filter_map(|w| w.index.checked_sub(base))
+            .collect::<Vec<_>>();
+
+        let args = ["new-window", "-t", session];
+        let offsets =
+            window_offsets(&windows, &taken).map_err(|error| self.layout_error(&args, error))?;
+
+        let mut spawn = PresetSpawn::default();
+        for (window_cfg, offset) in windows.iter().zip(offsets) {
+            let plan = plan_window(session, window_cfg, Some(offset))
+                .map_err(|error| self.layout_error(&args, error))?;
+            let created = self.execute(plan)?;
+            spawn.pending.extend(created.pending);
+            spawn.warnings.extend(created.warnings);
+        }
+        Ok(spawn)
+    }
+
+    /// The index `session` numbers its windows from
+    fn base_index(&self, session: &str) -> Result<usize, TmuxError> {
+        let target = format!("{}:", exact_target(session));
+        let output = self.display_message(&target, "#{base-index}")?;
+        output
+            .trim()
+            .parse()
+            .map_err(|_| self.parse_error(&["display-message"], "Expected a base-index"))
+    }
+
+    /// Whether `wait_for` holds on the machine this server runs on, checked by a shell tmux
+    /// starts there. Slower than checking locally, but right for servers reached over ssh.
+    pub fn wait_for_holds(&self, wait_for: &WaitFor) -> bool {
+        self.run(&["run-shell", &wait_for.shell_condition()])
+            .is_ok()
+    }
+
+    /// Types `pending`'s command into its pane
+    pub fn send_command(&self, pending: &PendingCommand) -> Result<(), TmuxError> {
+        self.run(&["send-keys", "-t", &pending.pane, &pending.command, "Enter"])?;
+        Ok(())
+    }
+
+    /// Runs every planned command, handing back those that still have to wait
+    fn execute(&self, plan: SpawnPlan) -> Result<PresetSpawn, TmuxError> {
+        match self.execute_with(plan, |_| ControlFlow::Continue(()))? {
+            ControlFlow::Continue(spawn) => Ok(spawn),
+            ControlFlow::Break(()) => unreachable!("nothing stops it"),
+        }
+    }
+
+    /// [`Server::execute`], asking `on_step` before each command whether to go on
+    fn execute_with(
+        &self,
+        plan: SpawnPlan,
+        mut on_step: impl FnMut(SpawnProgress) -> ControlFlow<()>,
+    ) -> Result<ControlFlow<(), PresetSpawn>, TmuxError> {
+        // Ids of the windows created so far, in the order the plan created them
+        let mut window_ids = vec![];
+        // Ids of each window's panes in tmux's order, so splitting one doesn't throw off where
+        // the others are. `None` for panes there wasn't room to split off, along with any pane
+        // that would have been split from them.
+        let mut pane_ids: Vec<Vec<Option<String>>> = vec![];
+        let place = |pane_ids: &mut Vec<Vec<Option<String>>>, target, new_pane, id| {
+            if let Some(Target::Pane(window, index)) = target {
+                let at = match new_pane {
+                    NewPane::After => index + 1,
+                    NewPane::Before => index,
+                };
+                pane_ids[window].insert(at, id);
+            }
+        };
+        let mut warnings = vec![];
+        let version = self.feature_version();
+        // Looked up once the session exists, for the first command targeting a window index
+        let mut base = None;
+        let total = plan.commands.len();
+        for (index, command) in plan.commands.iter().enumerate() {
+            let progress = SpawnProgress {
+                step: index + 1,
+                total,
+                detail: command.describe(),
+            };
+            if on_step(progress).is_break() {
+                return Ok(ControlFlow::Break(()));
+            }
+
+            let base = match (&command.target, base) {
+                (_, Some(base)) => base,
+                (Some(Target::Index(session, _)), None) => *base.insert(self.base_index(session)?),
+                (_, None) => 0,
+            };
+            let target = match &command.target {
+                Some(Target::Pane(window, index)) => match &pane_ids[*window][*index] {
+                    Some(id) => Some(id.clone()),
+                    // Nothing to split or type into
+                    None => {
+                        if let Some(new_pane) = command.splits() {
+                            place(&mut pane_ids, command.target.clone(), new_pane, None);
+                        }
+                        continue;
+                    }
+                },
+                target => target.as_ref().map(|t| t.resolve(&window_ids, base)),
+            };
+            let mut argv = command.argv_to(target, version);
+            // The window's first pane comes with it, and each split prints the pane it made
+            if command.creates_window()
+                && let Some(format) = argv.iter_mut().find(|arg| *arg == "#{window_id}")
+            {
+                *format = "#{window_id} #{pane_id}".to_string();
+            }
+            if command.splits().is_some() {
+                argv.extend(["-P", "-F", "#{pane_id}"].map(String::from));
+            }
+            let args = argv.iter().map(String::as_str).collect::<Vec<_>>();
+            let output = match self.run(&args) {
+                Err(e) if command.optional => {
+                    warnings.push(e.to_string());
+                    continue;
+                }
+                // The window is too small for it, which the rest of the preset needn't suffer
+                Err(e) if command.splits().is_some() && e.is_no_space() => String::new(),
+                output => output?,
+            };
+            if command.creates_window() {
+                let Some((window, pane)) = output.trim().split_once(' ') else {
+                    return Err(self.parse_error(&args, "Expected a window and a pane id"));
+                };
+                if !window.starts_with('@') {
+                    return Err(self.parse_error(&args, "Expected a window id"));
+                }
+                window_ids.push(window.to_string());
+                pane_ids.push(vec![Some(pane.to_string())]);
+            }
+            if let Some(new_pane) = command.splits() {
+                let id = Some(output.trim().to_string()).filter(|id| !id.is_empty());
+                place(&mut pane_ids, command.target.clone(), new_pane, id);
+            }
+        }
+
+        // Correction phase: only now that every pane exists can their sizes be evened out
+        for ((window, layout), panes) in window_ids.iter().zip(&plan.layouts).zip(&pane_ids) {
+            // Panes there wasn't room for throw off which pane is which
+            if panes.iter().all(Option::is_some)
+                && let Err(e) = self.even_out(window, layout)
+            {
+                warnings.push(e.to_string());
+            }
+        }
+
+        for (name, panes) in plan.windows.iter().zip(pane_ids.iter()) {
+            let created = panes.iter().flatten().count();
+            if created < panes.len() {
+                warnings.push(format!(
+                    "window '{name}': created {created} of {} panes — terminal too small",
+                    panes.len()
+                ));
+            }
+        }
+
+        let pending = plan
+            .pending
+            .into_iter()
+            .filter_map(|pending| {
+                let pane = match pending.target {
+                    Target::Pane(window, index) => pane_ids[window][index].clone()?,
+                    target => target.resolve(&window_ids, base.unwrap_or_default()),
+                };
+                Some(PendingCommand {
+                    pane,
+                    label: pending.label,
+                    command: pending.command,
+                    startup: pending.startup,
+                })
+            })
+            .collect();
+        Ok(ControlFlow::Continue(PresetSpawn { pending, warnings }))
+    }
+
+    /// Brings a running preset's session up to date with its definition.
+    ///
+    /// Windows are matched by name: any window from the preset that's missing in the session is
+    /// created, while windows that already exist are left alone even if their layout drifted.
+    /// Windows only present in the session are reported but never killed. Like
+    /// [`Server::spawn_preset`], it goes to the preset's own server if it names one.
+    pub fn update_preset(&self, preset: &Preset) -> Result<PresetUpdate, TmuxError> {
+        let server = self.for_preset(preset);
+        let live_windows = server.list_windows(&preset.name)?;
+        let mut update = PresetUpdate::default();
+
+        let missing = preset
+            .windows
+            .iter()
+            .filter(|window_cfg| !live_windows.iter().any(|w| w.name == window_cfg.name))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let spawn = server.create_windows(&preset.name, missing.iter().copied())?;
+            update.added = missing.iter().map(|w| w.name.clone()).collect();
+            update.pending = spawn.pending;
+            update.warnings = spawn.warnings;
+        }
+
+        update.extra = live_windows
+            .into_iter()
+            .filter(|w| !preset.windows.iter().any(|p| p.name == w.name))
+            .map(|w| w.name)
+            .collect();
+
+        Ok(update)
+    }
+
+    /// Compares a running preset's session with its definition, see [`compare_session`]. Like
+    /// [`Server::update_preset`], it goes to the preset's own server if it names one.
+    pub fn diff_preset(&self, preset: &Preset) -> Result<PresetDiff, TmuxError> {
+        let live = self.for_preset(preset).list_session_panes(&preset.name)?;
+        Ok(compare_session(preset, &live))
+    }
+
+    /// Every window of `session`, along with its panes
+    pub fn list_session_panes(
+        &self,
+        session: &str,
+    ) -> Result<Vec<(LiveWindow, Vec<LivePane>)>, TmuxError> {
+        self.list_windows(session)?
+            .into_iter()
+            .map(|window| {
+                let target = format!("{}:{}", exact_target(session), window.index);
+                Ok((window, self.list_panes(&target)?))
+            })
+            .collect()
+    }
+
+    /// Adds `preset`'s windows to the existing `target_session` instead of a session of its own,
+    /// laid out like [`Server::spawn_preset`] would.
+    ///
+    /// Windows named like one already in the session get a `-2`, `-3`, ... suffix. Like
+    /// [`Server::spawn_preset`], the commands still waiting on their startup options are handed
+    /// back.
+    pub fn append_preset(
+        &self,
+        preset: &Preset,
+        target_session: &str,
+    ) -> Result<PresetAppend, TmuxError> {
+        let mut taken = self
+            .list_windows(target_session)?
+            .into_iter()
+            .map(|w| w.name)
+            .collect::<Vec<_>>();
+        let mut windows = vec![];
+
+        for window_cfg in preset.windows.iter() {
+            let mut name = window_cfg.name.clone();
+            let mut suffix = 1;
+            while taken.contains(&name) {
+                suffix += 1;
+                name = format!("{}-{suffix}", window_cfg.name);
+            }
+            taken.push(name.clone());
+            windows.push(Window {
+                name,
+                ..window_cfg.clone()
+            });
+        }
+
+        let spawn = self.create_windows(target_session, &windows)?;
+        Ok(PresetAppend {
+            added: windows.into_iter().map(|w| w.name).collect(),
+            pending: spawn.pending,
+            warnings: spawn.warnings,
+        })
+    }
+
+    /// Splits the `target` pane, returning the ids of its window (`@N`) and the new pane (`%N`).
+    ///
+    /// `size` is the new pane's, [`Size::Auto`] leaving it to tmux (half of `target`).
+    pub fn split_window(
+        &self,
+        target: &str,
+        size: Size,
+        direction: &SplitDirection,
+    ) -> Result<(String, String), TmuxError> {
+        let split = TmuxOp::Split {
+            direction: *direction,
+            size,
+            before: false,
+            inherit_cwd: false,
+        };
+        let mut argv = op_argv(&split, Some(target.to_string()), self.feature_version());
+        argv.extend(["-P", "-F", "#{window_id} #{pane_id}"].map(String::from));
+        let args = argv.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let output = self.run(&args)?;
+        let (window_id, pane_id) = output
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| self.parse_error(&args, "Unexpected output"))?;
+        Ok((window_id.into(), pane_id.into()))
+    }
+
+    /// Lists the server's sessions, none if it isn't running: killing the last session takes the
+    /// server down with it, which is no reason to fail
+    pub fn list_sessions(&self) -> Result<Vec<Session>, TmuxError> {
+        self.list_sessions_if_running()
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Lists the server's sessions, or `None` if it isn't running (anymore)
+    pub fn list_sessions_if_running(&self) -> Result<Option<Vec<Session>>, TmuxError> {
+        // The server can also go away in between the commands listing takes
+        match self.try_list_sessions() {
+            Err(TmuxError::NoServer { .. }) => Ok(None),
+            result => result.map(Some),
+        }
+    }
+
+    fn try_list_sessions(&self) -> Result<Vec<Session>, TmuxError> {
+        let args = [
+            "list-sessions",
+            "-F",
+            "#{session_id}\t#{session_name}\t#{session_windows}\t#{session_attached}\t\
+             #{session_created}\t#{session_activity}\t#{session_alerts}\t#{@muffin_preset}\t#{@muffin_autoname}\t\
+             #{@muffin_note}\t#{pane_current_command}\t#{pane_current_path}",
+        ];
+        let output = self.run(&args)?;
+        let current_session_name = match std::env::var("TMUX_PANE") {
+            // Pane ids are only meaningful on the server muffin itself runs in
+            Ok(tmux_pane_env) if self.is_current_server() => Some(
+                self.run(&["display-message", "-t", &tmux_pane_env, "-p", "'#S'"])?
+                    .trim()
+                    .trim_matches('\'')
+                    .to_string(),
+            ),
+            // In a popup there's no pane, but the client it's drawn over knows its session
+            Err(_) if self.is_current_server() && std::env::var_os("TMUX").is_some() => Some(
+                self.run(&["display-message", "-p", "#S"])?
+                    .trim()
+                    .to_string(),
+            ),
+            _ => None,
+        };
+
+        let mut sessions = vec![];
+        for line in output.lines() {
+            // The path goes last, as the only field that could have tabs of its own
+            let mut fields = line.splitn(12, '\t');
+            let (
+                Some(id),
+                Some(name),
+                Some(windows),
+                Some(attached),
+                created,
+                activity,
+                alerts,
+                preset,
+                auto_name,
+                note,
+                command,
+                path,
+            ) = (
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+            )
+            else {
+                return Err(self.parse_error(&args, &format!("Unexpected line: {line}")));
+            };
+            // Options and formats that aren't set come out empty
+            let set = |field: Option<&str>| field.filter(|f| !f.is_empty()).map(String::from);
+            let Ok(windows) = windows.parse() else {
+                return Err(self.parse_error(&args, &format!("Unexpected window count: {line}")));
+            };
+
+            sessions.push(Session {
+                id: id.to_string(),
+                windows,
+                created: created.and_then(|t| t.parse().ok()),
+                activity: activity.and_then(|t| t.parse().ok()),
+                attached_count: attached.parse().unwrap_or_default(),
+                is_current: current_session_name.as_deref() == Some(name),
+                alert: alerts.and_then(Alert::from_session_alerts),
+                path: set(path),
+                preset: set(preset),
+                auto_name: set(auto_name),
+                note: set(note).map(|note| unescape_note(&note)),
+                command: set(command),
+                name: name.to_string(),
+                color: None,
+                tags: vec![],
+            });
+        }
+
+        Ok(sessions)
+    }
+
+    pub fn list_windows(&self, session: &str) -> Result<Vec<LiveWindow>, TmuxError> {
+        let target = exact_target(session);
+        let args = [
+            "list-windows",
+            "-t",
+            &target,
+            "-F",
+            "#{window_index}\t#{window_active}\t#{pane_current_command}\t#{window_name}",
+        ];
+        let output = self.run(&args)?;
+        let unexpected = || self.parse_error(&args, "Unexpected output");
+
+        output
+            .lines()
+            .map(|line| {
+                let mut fields = line.splitn(4, '\t');
+                let (Some(index), Some(active), Some(command), Some(name)) =
+                    (fields.next(), fields.next(), fields.next(), fields.next())
+                else {
+                    return Err(unexpected());
+                };
+                Ok(LiveWindow {
+                    index: index.parse::<usize>().map_err(|_| unexpected())?,
+                    name: name.to_string(),
+                    command: command.to_string(),
+                    active: active == "1",
+                })
+            })
+            .collect()
+    }
+
+    pub fn list_panes(&self, window: &str) -> Result<Vec<LivePane>, TmuxError> {
+        let args = [
+            "list-panes",
+            "-t",
+            window,
+            "-F",
+            "#{pane_id}\t#{pane_index}\t#{pane_active}\t#{pane_left}\t#{pane_top}\t#{pane_width}\t\
+             #{pane_height}\t#{pane_dead}\t#{window_zoomed_flag}\t#{pane_current_command}\t\
+             #{pane_current_path}",
+        ];
+        let output = self.run(&args)?;
+        let unexpected = || self.parse_error(&args, "Unexpected output");
+
+        output
+            .lines()
+            .map(|line| {
+                // The path goes last, as the only field that could hold a tab
+                let fields = line.splitn(11, '\t').collect::<Vec<_>>();
+                let [
+                    id,
+                    index,
+                    active,
+                    left,
+                    top,
+                    width,
+                    height,
+                    dead,
+                    zoomed,
+                    command,
+                    cwd,
+                ] = fields[..]
+                else {
+                    return Err(unexpected());
+                };
+                let cells = |field: &str| field.parse::<u16>().map_err(|_| unexpected());
+                Ok(LivePane {
+                    id: id.to_string(),
+                    index: index.parse::<usize>().map_err(|_| unexpected())?,
+                    command: command.to_string(),
+                    cwd: cwd.to_string(),
+                    active: active == "1",
+                    dead: dead == "1",
+                    // Only the active pane shows while its window is zoomed
+                    zoomed: active == "1" && zoomed == "1",
+                    left: cells(left)?,
+                    top: cells(top)?,
+                    width: cells(width)?,
+                    height: cells(height)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Moves the `target` pane's border `cells` rows or columns towards `direction`
+    pub fn resize_pane(
+        &self,
+        target: &str,
+        direction: ResizeDirection,
+        cells: u16,
+    ) -> Result<(), TmuxError> {
+        let flag = match direction {
+            ResizeDirection::Left => "-L",
+            ResizeDirection::Down => "-D",
+            ResizeDirection::Up => "-U",
+            ResizeDirection::Right => "-R",
+        };
+        self.run(&["resize-pane", "-t", target, flag, &cells.to_string()])
+            .map(|_| ())
+    }
+
+    /// Brings the `window`'s panes back to the proportions of the layout it was spawned with
+    /// (recorded in its [`LAYOUT_OPTION`]), for the size the window has now. tmux only keeps
+    /// panes' sizes in cells, so a window spawned in a small terminal stays cramped otherwise.
+    ///
+    /// Windows it can't be done for are left alone, the [`Relayout`] saying why.
+    pub fn relayout_window(&self, window: &str) -> Result<Relayout, TmuxError> {
+        let recorded = self.run(&["show-options", "-wqv", "-t", window, LAYOUT_OPTION])?;
+        let recorded = recorded.trim();
+        if recorded.is_empty() {
+            return Ok(Relayout::NotRecorded);
+        }
+        let layout = match LayoutNode::from_compact(recorded) {
+            Ok(layout) => layout,
+            Err(e) => return Ok(Relayout::Unreadable(e)),
+        };
+
+        let panes = self.panes_in_order(window)?;
+        if panes.len() != layout.pane_count() {
+            return Ok(Relayout::PanesChanged);
+        }
+        if panes.iter().any(|pane| pane.zoomed) {
+            return Ok(Relayout::Zoomed);
+        }
+        let width = panes
+            .iter()
+            .map(|p| p.left + p.width)
+            .max()
+            .unwrap_or_default();
+        let height = panes
+            .iter()
+            .map(|p| p.top + p.height)
+            .max()
+            .unwrap_or_default();
+
+        for resize in plan_resizes(&layout, width, height) {
+            let flag = match resize.direction {
+                SplitDirection::Horizontal => "-x",
+                SplitDirection::Vertical => "-y",
+            };
+            let pane = &panes[resize.pane].id;
+            self.run(&["resize-pane", "-t", pane, flag, &resize.cells.to_string()])?;
+        }
+        Ok(Relayout::Applied)
+    }
+
+    /// Resizes the children of the just spawned `window`'s splits that came out off their share,
+    /// for the splits that are to be evened out (see [`Server::with_even_splits`])
+    fn even_out(&self, window: &str, layout: &LayoutNode) -> Result<(), TmuxError> {
+        if !evens_out(layout, self.even_splits) {
+            return Ok(());
+        }
+        let mut panes = self.panes_in_order(window)?;
+        if panes.len() != layout.pane_count() {
+            return Ok(());
+        }
+        self.even_out_node(window, layout, 0, &mut panes)
+    }
+
+    /// [`Server::even_out`] for the split `node`, whose panes start at `first_pane` of `panes`.
+    /// Each child is measured again after its elder sibling was resized, as that takes from it.
+    fn even_out_node(
+        &self,
+        window: &str,
+        node: &LayoutNode,
+        first_pane: usize,
+        panes: &mut Vec<LivePane>,
+    ) -> Result<(), TmuxError> {
+        let LayoutNode::Split {
+            direction,
+            children,
+            even,
+            ..
+        } = node
+        else {
+            return Ok(());
+        };
+        // The panes of each child, in tmux's order
+        let mut ranges = vec![];
+        let mut start = first_pane;
+        for child in children {
+            ranges.push(start..start + child.pane_count());
+            start += child.pane_count();
+        }
+
+        if even.unwrap_or(self.even_splits) {
+            let extents = split_extents(children, span(&panes[first_pane..start], *direction));
+            // The last child gets what the others leave
+            for (range, &cells) in ranges.iter().zip(&extents).take(children.len() - 1) {
+                if span(&panes[range.clone()], *direction) == cells {
+                    continue;
+                }
+                let flag = match direction {
+                    SplitDirection::Horizontal => "-x",
+                    SplitDirection::Vertical => "-y",
+                };
+                let pane = &panes[range.start].id;
+                self.run(&["resize-pane", "-t", pane, flag, &cells.to_string()])?;
+                *panes = self.panes_in_order(window)?;
+            }
+        }
+        for (child, range) in children.iter().zip(ranges) {
+            self.even_out_node(window, child, range.start, panes)?;
+        }
+        Ok(())
+    }
+
+    /// The panes of `window`, in tmux's order
+    fn panes_in_order(&self, window: &str) -> Result<Vec<LivePane>, TmuxError> {
+        let mut panes = self.list_panes(window)?;
+        panes.sort_by_key(|pane| pane.index);
+        Ok(panes)
+    }
+
+    /// Starts the `target` pane again once its command has exited and `remain-on-exit` kept it
+    /// open. Like [`Server::spawn_preset`] it's moved to `cwd` and `command` is typed into its
+    /// shell, but right away, whatever the pane's startup said.
+    pub fn respawn_pane(
+        &self,
+        target: &str,
+        cwd: &str,
+        command: Option<&str>,
+    ) -> Result<(), TmuxError> {
+        self.run(&["respawn-pane", "-t", target])?;
+        if cwd != INHERIT_CWD {
+            self.run(&[
+                "send-keys",
+                "-t",
+                target,
+                &format!("cd {}", shell_path(cwd)),
+                "Enter",
+            ])?;
+        }
+        if let Some(command) = command {
+            self.run(&[
+                "send-keys",
+                "-t",
+                target,
+                &exiting_command(command),
+                "Enter",
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// Zooms the `target` pane to fill its window, or unzooms it if it already does
+    pub fn zoom_pane(&self, target: &str) -> Result<(), TmuxError> {
+        self.run(&["resize-pane", "-Z", "-t", target]).map(|_| ())
+    }
+
+    /// Moves the `target` pane out into a new window of its own at the end of `session`, staying
+    /// in the current window
+    pub fn break_pane(&self, target: &str, session: &str) -> Result<(), TmuxError> {
+        // Without a session, the window would go to the client's current one
+        let session = format!("{}:", exact_target(session));
+        self.run(&["break-pane", "-d", "-s", target, "-t", &session])
+            .map(|_| ())
+    }
+
+    /// Moves the `source` pane into the `window` window, split off its active pane, staying in the
+    /// current window
+    pub fn join_pane(&self, source: &str, window: &str) -> Result<(), TmuxError> {
+        self.run(&["join-pane", "-d", "-s", source, "-t", window])
+            .map(|_| ())
+    }
+
+    /// Makes `target` the active pane of its window
+    pub fn select_pane(&self, target: &str) -> Result<(), TmuxError> {
+        self.run(&["select-pane", "-t", target]).map(|_| ())
+    }
+
+    /// Makes `target` the current window of its session
+    pub fn select_window(&self, target: &str) -> Result<(), TmuxError> {
+        self.run(&["select-window", "-t", target]).map(|_| ())
+    }
+
+    pub fn switch_session(&self, session: &str) -> Result<(), TmuxError> {
+        self.run(&["switch-client", "-t", &exact_target(session)])
+            .map(|_| ())
+    }
+
+    /// Brings `session` up in this terminal: moves the client muffin runs in over to it when
+    /// that's a client of this server, or else attaches to it
+    pub fn enter_session(&self, session: &str) -> Result<(), TmuxError> {
+        if self.is_current_server() && std::env::var("TMUX").is_ok() {
+            self.switch_session(session)
+        } else {
+            self.attach_session(session)
+        }
+    }
+
+    /// Attaches this terminal to `session`, returning once the client detaches
+    pub fn attach_session(&self, session: &str) -> Result<(), TmuxError> {
+        let mut argv = self.argv(&["attach-session", "-t", &exact_target(session)]);
+        // ssh only gives the remote tmux a terminal when asked to
+        if self.host.is_some() {
+            argv.insert(1, "-t".to_string());
+        }
+
+        let mut command = Command::new(&argv[0]);
+        command.args(&argv[1..]);
+        // tmux refuses to attach from inside one of its panes unless told to, which is what
+        // entering a session on another server from here takes
+        if !self.is_current_server() {
+            command.env_remove("TMUX");
+        }
+        let start = Instant::now();
+        let status = command.status().map_err(|e| TmuxError::Spawn {
+            command: argv.clone(),
+            message: format!("Error running command: {e}"),
+        })?;
+        // The output went straight to the terminal
+        log::log_invocation(&argv, status.code(), start.elapsed(), "", "");
+
+        if !status.success() {
+            return Err(TmuxError::Failed {
+                command: argv,
+                code: status.code(),
+                stderr: String::new(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Runs `command` in a popup over the current client, which closes once the command exits
+    pub fn display_popup(
+        &self,
+        command: &[String],
+        width: &str,
+        height: &str,
+    ) -> Result<(), TmuxError> {
+        // Like new panes, popups hand their command to a shell
+        let command = command
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.run(&["display-popup", "-E", "-w", width, "-h", height, &command])
+            .map(|_| ())
+    }
+
+    /// Creates a detached session, letting tmux pick the name when `new_name` is empty, and
+    /// returns the name it got
+    pub fn create_session(&self, new_name: &str, cwd: Option<&str>) -> Result<String, TmuxError> {
+        let mut args = vec!["new-session", "-d", "-P", "-F", "#{session_name}"];
+        if !new_name.is_empty() {
+            args.extend(["-s", new_name]);
+        }
+        if let Some(cwd) = cwd {
+            args.extend(["-c", cwd]);
+        }
+        if !new_name.is_empty() {
+            self.check_name(&args, new_name)?;
+        }
+        self.run(&args).map(|name| name.trim().to_string())
+    }
+
+    /// Adds a window to the existing `session` without switching to it, returning its id (`@N`).
+    ///
+    /// Without a name tmux picks one (the running command), without a cwd it uses its default.
+    pub fn new_window(
+        &self,
+        session: &str,
+        name: Option<&str>,
+        cwd: Option<&str>,
+    ) -> Result<String, TmuxError> {
+        // `:` for the session's next free window index
+        let target = format!("{}:", exact_target(session));
+        let mut args = vec![
+            "new-window",
+            "-d",
+            "-t",
+            &target,
+            "-P",
+            "-F",
+            "#{window_id}",
+        ];
+        if let Some(name) = name {
+            args.extend(["-n", name]);
+        }
+        if let Some(cwd) = cwd {
+            args.extend(["-c", cwd]);
+        }
+
+        let output = self.run(&args)?;
+        let id = output.trim();
+        if !id.starts_with('@') {
+            return Err(self.parse_error(&args, "Expected a window id"));
+        }
+        Ok(id.to_string())
+    }
+
+    /// Expands the tmux `format` (ex: `#{pane_current_path}`) in the context of `target`
+    pub fn display_message(&self, target: &str, format: &str) -> Result<String, TmuxError> {
+        self.run(&["display-message", "-p", "-t", target, format])
+            .map(|output| output.trim_end_matches('\n').to_string())
+    }
+
+    /// Remembers in `session` the `name` muffin gave it after what runs in it, or with `None`
+    /// leaves its name alone from then on
+    pub fn set_auto_name(&self, session: &str, name: Option<&str>) -> Result<(), TmuxError> {
+        // set-option takes a pane target, which a bare `=2` doesn't find the session `2` as
+        let target = format!("{}:", exact_target(session));
+        match name {
+            Some(name) => self.run(&["set-option", "-t", &target, "@muffin_autoname", name]),
+            None => self.run(&["set-option", "-u", "-t", &target, "@muffin_autoname"]),
+        }
+        .map(|_| ())
+    }
+
+    /// Leaves `note` on `session` for as long as it lives, or with an empty one clears it
+    pub fn set_note(&self, session: &str, note: &str) -> Result<(), TmuxError> {
+        let target = format!("{}:", exact_target(session));
+        match note {
+            "" => self.run(&["set-option", "-u", "-t", &target, "@muffin_note"]),
+            note => self.run(&[
+                "set-option",
+                "-t",
+                &target,
+                "@muffin_note",
+                &escape_note(note),
+            ]),
+        }
+        .map(|_| ())
+    }
+
+    pub fn rename_session(&self, session: &str, new_name: &str) -> Result<(), TmuxError> {
+        let args = ["rename-session", "-t", &exact_target(session), new_name];
+        self.check_name(&args, new_name)?;
+        self.run(&args).map(|_| ())
+    }
+
+    pub fn delete_session(&self, session: &str) -> Result<(), TmuxError> {
+        self.run(&["kill-session", "-t", &exact_target(session)])
+            .map(|_| ())
+    }
+
+    /// Kills the server along with every session, window and process in it
+    pub fn kill_server(&self) -> Result<(), TmuxError> {
+        self.run(&["kill-server"]).map(|_| ())
+    }
+
+    /// Starts the server without creating a session. Unless the tmux config creates one (ex:
+    /// by restoring a saved environment) or turns `exit-empty` off, it exits again right away.
+    pub fn start_server(&self) -> Result<(), TmuxError> {
+        self.run(&["start-server"]).map(|_| ())
+    }
+
+    /// Fails with the argv of `tmux <args>` if `name` isn't one tmux takes as a session name, see
+    /// [`validate_session_name`]
+    fn check_name(&self, args: &[&str], name: &str) -> Result<(), TmuxError> {
+        validate_session_name(name).map_err(|error| TmuxError::InvalidName {
+            command: self.argv(args),
+            name: name.to_string(),
+            error,
+        })
+    }
+
+    /// The full argv used to run `tmux <args>` against this server
+    fn argv(&self, args: &[&str]) -> Vec<String> {
+        // Without a UTF-8 locale tmux swaps the tabs muffin separates formats with for `_`
+        let mut tmux = vec!["tmux".to_string(), "-u".to_string()];
+        if let Some(socket) = &self.socket {
+            tmux.extend(["-L".to_string(), socket.clone()]);
+        }
+        if let Some(config) = &self.config {
+            tmux.extend(["-f".to_string(), config.clone()]);
+        }
+        tmux.extend(args.iter().map(|arg| arg.to_string()));
+
+        match &self.host {
+            // ssh hands its arguments to the remote shell as one string, so quote each of them.
+            // BatchMode keeps ssh from prompting for passwords on top of the TUI.
+            Some(host) => ["ssh", "-o", "BatchMode=yes", host.as_str(), "--"]
+                .into_iter()
+                .map(String::from)
+                .chain(tmux.iter().map(|arg| shell_quote(arg)))
+                .collect(),
+            None => tmux,
+        }
+    }
+
+    /// Runs tmux with `args`, killing it if it doesn't exit within the server's timeout
+    fn run(&self, args: &[&str]) -> Result<String, TmuxError> {
+        let argv = self.argv(args);
+        let spawn_error = |e: std::io::Error| TmuxError::Spawn {
+            command: argv.clone(),
+            message: format!("Error running command: {e}"),
+        };
+
+        let start = Instant::now();
+        let mut child = Command::new(&argv[0])
+            .args(&argv[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(spawn_error)?;
+        // Read while tmux runs, as it can't exit while blocked on a full pipe
+        let stdout = read_pipe(child.stdout.take());
+        let stderr = read_pipe(child.stderr.take());
+
+        let timeout = self.timeout.unwrap_or(DEFAULT_TIMEOUT);
+        let Some(status) = wait_timeout(&mut child, timeout).map_err(spawn_error)? else {
+            // Reaped right away, so hung commands don't pile up as zombies
+            let _ = child.kill();
+            let _ = child.wait();
+            log::log_invocation(&argv, None, start.elapsed(), "", "timed out");
+            return Err(TmuxError::Timeout {
+                command: argv,
+                elapsed: timeout,
+            });
+        };
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+
+        let stdout = String::from_utf8_lossy(&stdout);
+        let stderr = String::from_utf8_lossy(&stderr);
+        log::log_invocation(&argv, status.code(), start.elapsed(), &stdout, &stderr);
+
+        if status.code().is_none_or(|code| code != 0) {
+            // ssh reserves exit code 255 for its own errors, anything else came from tmux
+            if let Some(host) = &self.host
+                && status.code() == Some(255)
+            {
+                return Err(TmuxError::Connection {
+                    command: argv,
+                    host: host.clone(),
+                    stderr: stderr.into_owned(),
+                });
+            }
+            if is_no_server(&stderr) {
+                return Err(TmuxError::NoServer {
+                    command: argv,
+                    stderr: stderr.into_owned(),
+                });
+            }
+            return Err(TmuxError::Failed {
+                command: argv,
+                code: status.code(),
+                stderr: stderr.into_owned(),
+            });
+        }
+
+        Ok(stdout.into_owned())
+    }
+
+    fn layout_error(&self, args: &[&str], error: LayoutError) -> TmuxError {
+        TmuxError::InvalidLayout {
+            command: self.argv(args),
+            error,
+        }
+    }
+
+    fn parse_error(&self, args: &[&str], message: &str) -> TmuxError {
+        TmuxError::Parse {
+            command: self.argv(args),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Reads all of `pipe` on a thread of its own
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = vec![];
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+/// Waits for `child` to exit, or `timeout` to pass, whichever comes first. Polls, starting out
+/// often since most tmux commands are done within a few milliseconds.
+fn wait_timeout(child: &mut Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let start = Instant::now();
+    let mut interval = Duration::from_micros(100);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let left = timeout.saturating_sub(start.elapsed());
+        if left.is_zero() {
+            return Ok(None);
+        }
+        std::thread::sleep(interval.min(left));
+        interval = (interval * 2).min(Duration::from_millis(10));
+    }
+}
+
+// Two handles on the same server are equal whether or not they asked for its version yet
+impl PartialEq for Server {
+    fn eq(&self, other: &Self) -> bool {
+        self.socket == other.socket && self.host == other.host
+    }
+}
+
+impl Eq for Server {}
+
+impl fmt::Display for Server {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.host, &self.socket) {
+            (None, None) => write!(f, "default"),
+            (None, Some(socket)) => write!(f, "socket {socket}"),
+            (Some(host), None) => write!(f, "{host}"),
+            (Some(host), Some(socket)) => write!(f, "{host} (socket {socket})"),
+        }
+    }
+}
+
+/// Whether tmux failed for lack of a server (or of sessions, which make it exit), going by what
+/// the versions of tmux out there print. A server that's shutting down still accepts connections
+/// for a moment, then drops them.
+fn is_no_server(stderr: &str) -> bool {
+    [
+        "no server running",
+        "error connecting to",
+        "failed to connect to server",
+        "no sessions",
+        "server exited unexpectedly",
+    ]
+    .iter()
+    .any(|message| stderr.contains(message))
+}
+
+/// The argv (without the leading `tmux`) running `op` on tmux `version`, with `target` as its `-t`.
+/// Every planned command goes through here, whether it's run, exported or shown.
+pub(crate) fn op_argv(op: &TmuxOp, target: Option<String>, version: Version) -> Vec<String> {
+    let mut argv = vec![];
+    let mut arg = |arg: &str| argv.push(arg.to_string());
+    let subcommand = match op {
+        TmuxOp::NewSession { .. } => "new-session",
+        TmuxOp::NewWindow { .. } => "new-window",
+        TmuxOp::MoveWindow { .. } => "move-window",
+        TmuxOp::Split { .. } => "split-window",
+        TmuxOp::SetOption { .. } => "set-option",
+        TmuxOp::SendKeys { .. } => "send-keys",
+    };
+    arg(subcommand);
+    if let Some(target) = &target {
+        arg("-t");
+        arg(target);
+    }
+    let inherit = |arg: &mut dyn FnMut(&str), inherit_cwd: bool| {
+        if inherit_cwd {
+            arg("-c");
+            arg(INHERIT_CWD);
+        }
+    };
+
+    match op {
+        TmuxOp::NewSession {
+            name,
+            window,
+            inherit_cwd,
+        } => {
+            arg("-d");
+            arg("-s");
+            arg(name);
+            if let Some(window) = window {
+                arg("-n");
+                arg(window);
+                inherit(&mut arg, *inherit_cwd);
+                arg("-P");
+                arg("-F");
+                arg("#{window_id}");
+            }
+        }
+        TmuxOp::NewWindow { name, inherit_cwd } => {
+            arg("-n");
+            arg(name);
+            inherit(&mut arg, *inherit_cwd);
+            arg("-P");
+            arg("-F");
+            arg("#{window_id}");
+        }
+        TmuxOp::MoveWindow { source } => {
+            arg("-s");
+            arg(source);
+        }
+        TmuxOp::Split {
+            direction,
+            size,
+            before,
+            inherit_cwd,
+        } => {
+            if *before {
+                arg("-b");
+            }
+            arg(match direction {
+                SplitDirection::Horizontal => "-h",
+                SplitDirection::Vertical => "-v",
+            });
+            match size {
+                Size::Percent(percent) if version.has_percentage_length() => {
+                    arg("-l");
+                    arg(&format!("{percent}%"));
+                }
+                Size::Percent(percent) => {
+                    arg("-p");
+                    arg(&percent.to_string());
+                }
+                Size::Cells(cells) => {
+                    arg("-l");
+                    arg(&cells.to_string());
+                }
+                // tmux splits the target in half
+                Size::Auto => {}
+            }
+            inherit(&mut arg, *inherit_cwd);
+        }
+        TmuxOp::SetOption { scope, name, value } => {
+            match scope {
+                OptionScope::Session => {}
+                OptionScope::Window => arg("-w"),
+                OptionScope::Pane if version.has_pane_options() => arg("-p"),
+                OptionScope::Pane => arg("-w"),
+            }
+            arg(name);
+            arg(value);
+        }
+        TmuxOp::SendKeys { keys } => {
+            arg(keys);
+            arg("Enter");
+        }
+    }
+    argv
+}
+
+/// `path` with a leading `~` standing for `home`. Other users' `~name` are left alone.
+fn expand_home(path: &str, home: &str) -> String {
+    let home = home.trim_end_matches('/');
+    match path.strip_prefix('~') {
+        // `/` once its trailing slash is gone
+        Some("") if home.is_empty() => "/".to_string(),
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{home}{rest}"),
+        _ => path.to_string(),
+    }
+}
+
+/// Quotes a path for a POSIX shell while leaving a leading `~` for it to expand
+pub(crate) fn shell_path(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => format!("~/{}", shell_quote(rest)),
+        None if path == "~" => path.to_string(),
+        None => shell_quote(path),
+    }
+}
+
+/// Quotes `arg` for a POSIX shell, leaving plain words untouched
+pub(crate) fn shell_quote(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c));
+    if is_plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestServer;
+    use crate::{Layout, Window, exact_target};
+
+    fn names(server: &Server) -> Vec<String> {
+        let mut names = server
+            .list_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|session| session.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn exact_targets() {
+        assert_eq!(exact_target("api"), "=api");
+        assert_eq!(exact_target("$3"), "$3");
+        // Names that only look like ids
+        assert_eq!(exact_target("$"), "=$");
+        assert_eq!(exact_target("$3a"), "=$3a");
+    }
+
+    #[test]
+    fn home_is_expanded_on_this_machine_only() {
+        for (path, expected) in [
+            ("~", "/home/me"),
+            ("~/src/it's here", "/home/me/src/it's here"),
+            ("~other/src", "~other/src"),
+            ("/srv/~", "/srv/~"),
+            ("#{pane_current_path}", "#{pane_current_path}"),
+        ] {
+            assert_eq!(expand_home(path, "/home/me"), expected, "{path}");
+            assert_eq!(expand_home(path, "/home/me/"), expected, "{path}");
+        }
+
+        let preset = Preset::builder("proj")
+            .cwd("~/proj")
+            .window(Window::builder("main").layout(Layout::hsplit([
+                Layout::pane(),
+                Layout::pane().cwd("/srv"),
+                Layout::pane().cwd("~"),
+            ])))
+            .build()
+            .unwrap();
+        let cwds = |preset: &Preset| {
+            crate::window_panes(&preset.windows[0])
+                .into_iter()
+                .map(|(cwd, _)| cwd.to_string())
+                .collect::<Vec<_>>()
+        };
+        let home = std::env::var("HOME").unwrap();
+        let local = Server::socket("never-started").local_preset(&preset);
+        assert_eq!(local.cwd, format!("{home}/proj"));
+        assert_eq!(cwds(&local), [format!("{home}/proj"), "/srv".into(), home]);
+        // Left for the shell on the other machine
+        let remote = Server::ssh("elsewhere").local_preset(&preset);
+        assert_eq!(remote.cwd, "~/proj");
+        assert_eq!(cwds(&remote), ["~/proj", "/srv", "~"]);
+    }
+
+    #[test]
+    fn sessions_are_told_apart_from_those_they_prefix() {
+        let scratch = TestServer::new("prefixes");
+        let server = &scratch.server;
+        server.create_session("api-v2", None).unwrap();
+
+        // Only `api-v2` starts with `api`, which a bare target would have settled for
+        assert!(server.rename_session("api", "web").is_err());
+        assert!(server.delete_session("api").is_err());
+        assert_eq!(names(server), ["api-v2"]);
+
+        server.create_session("api", None).unwrap();
+        server.new_window("api", Some("extra"), None).unwrap();
+        assert_eq!(server.list_windows("api").unwrap().len(), 2);
+        assert_eq!(server.list_windows("api-v2").unwrap().len(), 1);
+
+        server.rename_session("api", "web").unwrap();
+        assert_eq!(names(server), ["api-v2", "web"]);
+        server.rename_session("web", "api").unwrap();
+
+        server.delete_session("api").unwrap();
+        assert_eq!(names(server), ["api-v2"]);
+
+        // A preset's windows go to its own session
+        let preset = Preset::builder("api")
+            .window(Window::builder("one"))
+            .window(Window::builder("two"))
+            .build()
+            .unwrap();
+        let spawn = server.spawn_preset(&preset).unwrap();
+        assert!(spawn.warnings.is_empty(), "{:?}", spawn.warnings);
+        assert_eq!(server.list_windows("api").unwrap().len(), 2);
+        assert_eq!(server.list_windows("api-v2").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn presets_spawn_whole_when_windows_start_at_one() {
+        let scratch = TestServer::new("base-index");
+        let server = &scratch.server;
+        // Options are set on a running server
+        server.create_session("keep", None).unwrap();
+        server
+            .run(&["set-option", "-g", "base-index", "1"])
+            .unwrap();
+
+        let preset = Preset::builder("proj")
+            .window(Window::builder("editor"))
+            .window(
+                Window::builder("logs").layout(Layout::vsplit([Layout::pane(), Layout::pane()])),
+            )
+            .build()
+            .unwrap();
+        let spawn = server.spawn_preset(&preset).unwrap();
+        assert!(spawn.warnings.is_empty(), "{:?}", spawn.warnings);
+
+        let windows = server
+            .list_session_panes("proj")
+            .unwrap()
+            .into_iter()
+            .map(|(window, panes)| (window.index, window.name, panes.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            windows,
+            [(1, "editor".to_string(), 1), (2, "logs".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn startup_conditions_are_checked_where_the_server_runs() {
+        let scratch = TestServer::new("wait-for");
+        let server = &scratch.server;
+        server.create_session("keep", None).unwrap();
+        let dir = std::env::temp_dir().join(format!("muffin-wait-for-{}", std::process::id()));
+        let ready = dir.join("it's ready");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let file = WaitFor::File(ready.display().to_string());
+        assert!(!server.wait_for_holds(&file));
+        std::fs::write(&ready, "").unwrap();
+        assert!(server.wait_for_holds(&file));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // Without nc there's no telling, which counts as not ready yet
+        let has_nc = Command::new("sh")
+            .args(["-c", "command -v nc"])
+            .output()
+            .is_ok_and(|output| output.status.success());
+        assert_eq!(server.wait_for_holds(&WaitFor::Port(port)), has_nc);
+        drop(listener);
+        assert!(!server.wait_for_holds(&WaitFor::Port(port)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn even_splits_come_out_within_a_cell_of_their_shares() {
+        let scratch = TestServer::new("even-splits");
+        // Sessions without a client get the server's default size
+        scratch.server.create_session("keep", None).unwrap();
+        scratch
+            .server
+            .run(&["set-option", "-g", "default-size", "300x24"])
+            .unwrap();
+
+        // Eight columns under a full-width pane, which tmux's rounding of each split's percent
+        // throws off by more than a cell
+        let preset = |name: &str, even: Option<bool>| {
+            let columns = Layout::hsplit((0..8).map(|_| Layout::pane()));
+            let columns = match even {
+                Some(even) => columns.even(even),
+                None => columns,
+            };
+            Preset::builder(name)
+                .window(Window::builder("dev").layout(Layout::vsplit([Layout::pane(), columns])))
+                .build()
+                .unwrap()
+        };
+        // How far the furthest column is from its share of what the borders leave
+        let drift = |server: &Server, preset: Preset| {
+            let spawn = server.spawn_preset(&preset).unwrap();
+            assert!(spawn.warnings.is_empty(), "{:?}", spawn.warnings);
+            let panes = server
+                .panes_in_order(&format!("={}:dev", preset.name))
+                .unwrap();
+            let columns = &panes[1..];
+            assert_eq!(span(columns, SplitDirection::Horizontal), 300);
+            let share = (300 - 7) as f32 / 8.0;
+            columns
+                .iter()
+                .map(|pane| (pane.width as f32 - share).abs())
+                .fold(0.0, f32::max)
+        };
+
+        assert!(drift(&scratch.server, preset("plain", None)) > 1.0);
+        assert!(drift(&scratch.server, preset("asked", Some(true))) <= 1.0);
+        // Servers evening out every split leave the ones that say otherwise
+        let even = scratch.server.clone().with_even_splits(true);
+        assert!(drift(&even, preset("even", None)) <= 1.0);
+        assert!(drift(&even, preset("opted-out", Some(false))) > 1.0);
+    }
+
+    #[test]
+    fn auto_names_and_commands_are_listed_with_their_sessions() {
+        let scratch = TestServer::new("auto-names");
+        let server = &scratch.server;
+        server.create_session("work", None).unwrap();
+        // Only a pane target finds the session `2` rather than the window at index 2
+        server.create_session("2", None).unwrap();
+        server
+            .run(&["new-session", "-d", "-s", "busy", "sleep 30"])
+            .unwrap();
+        let listed = |name: &str| {
+            server
+                .list_sessions()
+                .unwrap()
+                .into_iter()
+                .find(|session| session.name == name)
+                .unwrap()
+        };
+        assert_eq!(listed("work").auto_name, None);
+
+        server.set_auto_name("work", Some("vim")).unwrap();
+        server.set_auto_name("2", Some("cargo test")).unwrap();
+        assert_eq!(listed("work").auto_name.as_deref(), Some("vim"));
+        assert_eq!(listed("2").auto_name.as_deref(), Some("cargo test"));
+        assert_eq!(listed("busy").auto_name, None);
+
+        server.set_auto_name("work", None).unwrap();
+        assert_eq!(listed("work").auto_name, None);
+        assert_eq!(listed("2").auto_name.as_deref(), Some("cargo test"));
+
+        // The shell tmux starts it through can take a moment to make way
+        let started = std::time::Instant::now();
+        while listed("busy").command.as_deref() != Some("sleep") {
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "{:?}",
+                listed("busy")
+            );
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(listed("work").command.is_some());
+    }
+
+    #[test]
+    fn options_are_set_on_their_window_and_session() {
+        let scratch = TestServer::new("options");
+        let server = &scratch.server;
+        let preset = Preset::builder("proj")
+            .option("status-position", "top")
+            .window(
+                Window::builder("deploy")
+                    .option("synchronize-panes", "on")
+                    .option("no-such-option", "on"),
+            )
+            .window(Window::builder("logs").option("monitor-activity", "on"))
+            .build()
+            .unwrap();
+        let spawn = server.spawn_preset(&preset).unwrap();
+        // Refused, yet the rest is still set
+        assert_eq!(spawn.warnings.len(), 1, "{:?}", spawn.warnings);
+        assert!(spawn.warnings[0].contains("no-such-option"));
+
+        let show = |args: &[&str]| server.run(args).unwrap().trim().to_string();
+        let window =
+            |target: &str, option: &str| show(&["show-options", "-wv", "-t", target, option]);
+        assert_eq!(window("=proj:deploy", "synchronize-panes"), "on");
+        assert_eq!(window("=proj:logs", "monitor-activity"), "on");
+        // Left to the global value on the other one
+        assert_eq!(window("=proj:logs", "synchronize-panes"), "");
+        assert_eq!(
+            show(&["show-options", "-v", "-t", "=proj:", "status-position"]),
+            "top"
+        );
+    }
+
+    #[test]
+    fn tiny_windows_get_the_panes_that_fit() {
+        let scratch = TestServer::new("tiny-window");
+        let server = &scratch.server;
+        server.create_session("keep", None).unwrap();
+        server
+            .run(&["set-option", "-g", "default-size", "20x6"])
+            .unwrap();
+
+        // Eight stacked panes need 15 rows, and the window after still gets its own
+        let preset = Preset::builder("proj")
+            .window(Window::builder("stack").layout(Layout::vsplit(
+                (0..8).map(|i| Layout::pane().cmd(format!("echo {i}"))),
+            )))
+            .window(
+                Window::builder("logs").layout(Layout::hsplit([Layout::pane(), Layout::pane()])),
+            )
+            .build()
+            .unwrap();
+        let spawn = server.spawn_preset(&preset).unwrap();
+
+        let panes = server.list_session_panes("proj").unwrap();
+        let counts = panes
+            .iter()
+            .map(|(window, panes)| (window.name.as_str(), panes.len()))
+            .collect::<Vec<_>>();
+        let created = counts[0].1;
+        assert!((1..8).contains(&created), "{counts:?}");
+        assert_eq!(counts[1], ("logs", 2));
+        assert_eq!(
+            spawn.warnings,
+            [format!(
+                "window 'stack': created {created} of 8 panes — terminal too small"
+            )]
+        );
+    }
+
+    #[test]
+    fn relayout_brings_back_the_recorded_sizes() {
+        let scratch = TestServer::new("relayout");
+        let server = &scratch.server;
+        server.create_session("keep", None).unwrap();
+        server
+            .run(&["set-option", "-g", "default-size", "100x30"])
+            .unwrap();
+
+        let preset = Preset::builder("proj")
+            .window(
+                Window::builder("dev")
+                    .layout(Layout::hsplit([Layout::pane().pct(30), Layout::pane()])),
+            )
+            .build()
+            .unwrap();
+        server.spawn_preset(&preset).unwrap();
+        let relayout = |window| server.relayout_window(window).unwrap();
+        let widths = || {
+            server
+                .panes_in_order("=proj:dev")
+                .unwrap()
+                .iter()
+                .map(|pane| pane.width)
+                .collect::<Vec<_>>()
+        };
+        let first = server.panes_in_order("=proj:dev").unwrap()[0].id.clone();
+        server
+            .run(&["resize-pane", "-t", &first, "-x", "60"])
+            .unwrap();
+        assert_eq!(widths(), [60, 39]);
+
+        assert_eq!(relayout("=proj:dev"), Relayout::Applied);
+        assert_eq!(widths(), [30, 69]);
+
+        server.run(&["resize-pane", "-Z", "-t", &first]).unwrap();
+        assert_eq!(relayout("=proj:dev"), Relayout::Zoomed);
+        server.run(&["resize-pane", "-Z", "-t", &first]).unwrap();
+        server.run(&["split-window", "-t", &first]).unwrap();
+        assert_eq!(relayout("=proj:dev"), Relayout::PanesChanged);
+        // Windows that weren't spawned from a preset
+        assert_eq!(relayout("=keep:"), Relayout::NotRecorded);
+    }
+}
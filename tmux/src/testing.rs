@@ -0,0 +1,22 @@
+//! What tests that talk to tmux share
+
+use crate::Server;
+
+/// A tmux server of the test's own, killed along with its sessions once done
+pub struct TestServer {
+    pub server: Server,
+}
+
+impl TestServer {
+    pub fn new(name: &str) -> Self {
+        Self {
+            server: Server::socket(&format!("muffin-{name}-{}", std::process::id())),
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.server.kill_server();
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+
+/// A tmux release, as reported by `tmux -V`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl Version {
+    /// The oldest tmux muffin works with
+    pub const MINIMUM: Version = Version::new(2, 9);
+
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Parses the output of `tmux -V`, ex: `tmux 3.3a`, `tmux next-3.4` or `tmux 3.5-rc`.
+    ///
+    /// Builds that don't report a number (`tmux master`, `tmux openbsd-7.5`) give `None`.
+    pub fn parse(output: &str) -> Option<Version> {
+        let version = output.trim().strip_prefix("tmux ")?;
+        let version = version.strip_prefix("next-").unwrap_or(version);
+
+        let (major, rest) = version.split_once('.')?;
+        // Patch releases are letters (3.3a) and pre-releases suffixes (3.5-rc)
+        let minor = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(rest, |end| &rest[..end]);
+
+        Some(Version::new(major.parse().ok()?, minor.parse().ok()?))
+    }
+
+    /// `-l` takes a percentage (`-l 30%`) from 3.1 on, before that sizes in percent need `-p`
+    pub fn has_percentage_length(self) -> bool {
+        self >= Version::new(3, 1)
+    }
+
+    /// `set-option -p` sets an option of a single pane from 3.0 on, before that panes only have
+    /// their window's
+    pub fn has_pane_options(self) -> bool {
+        self >= Version::new(3, 0)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_table() {
+        let table = [
+            ("tmux 3.3a", Some(Version::new(3, 3))),
+            ("tmux 3.4\n", Some(Version::new(3, 4))),
+            ("tmux next-3.4", Some(Version::new(3, 4))),
+            ("tmux 3.5-rc", Some(Version::new(3, 5))),
+            ("tmux 2.9", Some(Version::new(2, 9))),
+            ("tmux 1.8", Some(Version::new(1, 8))),
+            ("tmux 3.10", Some(Version::new(3, 10))),
+            ("tmux openbsd-7.2", None),
+            ("tmux master", None),
+            ("tmux 3", None),
+            ("tmux a.b", None),
+            ("3.3a", None),
+            ("", None),
+            ("garbage", None),
+        ];
+        for (output, expected) in table {
+            assert_eq!(Version::parse(output), expected, "{output:?}");
+        }
+    }
+
+    #[test]
+    fn versions_order_by_minor_as_a_number() {
+        assert!(Version::new(3, 10) > Version::new(3, 9));
+        assert!(Version::new(3, 0) > Version::new(2, 9));
+    }
+
+    #[test]
+    fn percentage_length_from_3_1() {
+        assert!(!Version::new(2, 9).has_percentage_length());
+        assert!(!Version::new(3, 0).has_percentage_length());
+        assert!(Version::new(3, 1).has_percentage_length());
+        assert!(Version::new(3, 4).has_percentage_length());
+    }
+
+    #[test]
+    fn pane_options_from_3_0() {
+        assert!(!Version::MINIMUM.has_pane_options());
+        assert!(Version::new(3, 0).has_pane_options());
+        assert!(Version::new(4, 0).has_pane_options());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Version::parse("tmux 3.3a").unwrap().to_string(), "3.3");
+    }
+}
@@ -0,0 +1,71 @@
+//! tmux swaps tabs in formats for `_` when it doesn't think the terminal takes UTF-8, which it
+//! decides from the environment, so this gets a test binary of its own
+
+use tmux::Server;
+
+/// Kills the server of the test once done
+struct Scratch(Server);
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = self.0.kill_server();
+    }
+}
+
+fn without_utf8() {
+    // SAFETY: set before anything reads the environment, and tests here only ever set it the
+    // same way
+    unsafe {
+        std::env::set_var("LC_ALL", "C");
+        std::env::remove_var("LC_CTYPE");
+        std::env::remove_var("LANG");
+        // Run from inside tmux, tmux assumes UTF-8 whatever the locale
+        std::env::remove_var("TMUX");
+    }
+}
+
+fn scratch(name: &str) -> Scratch {
+    without_utf8();
+    Scratch(Server::socket(&format!(
+        "muffin-locale-{name}-{}",
+        std::process::id()
+    )))
+}
+
+#[test]
+fn sessions_are_listed_without_a_utf8_locale() {
+    let scratch = scratch("sessions");
+    let server = &scratch.0;
+    server.create_session("my proj", Some("/tmp")).unwrap();
+    server.set_note("my proj", "first\tsecond").unwrap();
+
+    let sessions = server.list_sessions().unwrap();
+    assert_eq!(sessions.len(), 1);
+    let session = &sessions[0];
+    assert_eq!(session.name, "my proj");
+    assert_eq!(session.windows, 1);
+    assert_eq!(session.path.as_deref(), Some("/tmp"));
+    assert_eq!(session.note.as_deref(), Some("first\tsecond"));
+}
+
+#[test]
+fn windows_and_panes_are_listed_without_a_utf8_locale() {
+    let scratch = scratch("windows");
+    let server = &scratch.0;
+    server.create_session("proj", None).unwrap();
+    let window = server
+        .new_window("proj", Some("my editor"), Some("/tmp"))
+        .unwrap();
+
+    let windows = server.list_windows("proj").unwrap();
+    assert_eq!(windows.len(), 2);
+    assert_eq!(windows[1].name, "my editor");
+    assert!(windows[1].index > windows[0].index);
+    // Made without switching to it
+    assert!(windows[0].active && !windows[1].active);
+
+    let panes = server.list_panes(&window).unwrap();
+    assert_eq!(panes.len(), 1);
+    assert_eq!(panes[0].cwd, "/tmp");
+    assert!(panes[0].active && !panes[0].dead);
+}
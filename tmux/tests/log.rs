@@ -0,0 +1,49 @@
+//! The debug log is set up once per process, from `MUFFIN_LOG`, so it gets a test binary of its
+//! own
+
+use tmux::{Server, TmuxError};
+
+#[test]
+fn failing_command_is_logged_with_its_exit_code_and_timing() {
+    let path = std::env::temp_dir().join(format!("muffin-log-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    // SAFETY: set before anything reads the environment, and this is the binary's only test
+    unsafe { std::env::set_var("MUFFIN_LOG", &path) };
+
+    // Nothing runs on this socket, so tmux fails
+    let socket = format!("muffin-log-test-{}", std::process::id());
+    let error = Server::socket(&socket)
+        .rename_session("nope", "still-nope")
+        .unwrap_err();
+    assert!(
+        matches!(error, TmuxError::NoServer { .. } | TmuxError::Failed { .. }),
+        "{error:?}"
+    );
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let line = log.lines().last().unwrap();
+
+    // ex: [1700000000.123] tmux -u -L muffin-log-test-42 rename-session ... -> exit 1 in 2ms; ...
+    let (timestamp, rest) = line.split_once("] ").unwrap();
+    let (secs, millis) = timestamp.trim_start_matches('[').split_once('.').unwrap();
+    assert!(secs.parse::<u64>().unwrap() > 0);
+    assert_eq!(millis.len(), 3);
+    assert!(millis.chars().all(|c| c.is_ascii_digit()));
+
+    assert!(
+        rest.starts_with(&format!(
+            "tmux -u -L {socket} rename-session -t =nope still-nope -> exit 1 in "
+        )),
+        "{rest}"
+    );
+    let elapsed = rest
+        .split(" in ")
+        .nth(1)
+        .unwrap()
+        .split_once("ms;")
+        .unwrap()
+        .0;
+    assert!(elapsed.parse::<u64>().is_ok(), "{rest}");
+    assert!(rest.contains("stderr: \""), "{rest}");
+}
@@ -0,0 +1,78 @@
+//! A hung server is stood in for by a `tmux` that sleeps, put first on `PATH` for the whole
+//! process, so this gets a test binary of its own
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use tmux::{Server, TmuxError};
+
+/// A directory holding a fake `tmux`, which sleeps when run on a socket named `hung-*`, writing
+/// its pid to `<socket>.pid` next to itself first, and exits right away on any other
+fn fake_tmux() -> &'static Path {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("muffin-timeout-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("tmux");
+        // Called as `tmux -u -L <socket> ...`
+        let body = format!(
+            "#!/bin/sh\ncase \"$3\" in hung-*) echo $$ > '{}/'\"$3\".pid; exec sleep 30;; esac\n",
+            dir.display()
+        );
+        std::fs::write(&script, body).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let mut paths = vec![dir.clone()];
+        paths.extend(std::env::split_paths(&path));
+        // SAFETY: set before any test runs tmux, and only ever to the same value
+        unsafe { std::env::set_var("PATH", std::env::join_paths(paths).unwrap()) };
+        dir
+    })
+}
+
+#[test]
+fn hung_commands_are_killed_once_timed_out() {
+    let dir = fake_tmux();
+    let timeout = Duration::from_millis(200);
+    let start = Instant::now();
+    let error = Server::socket("hung-rename")
+        .with_timeout(timeout)
+        .rename_session("a", "b")
+        .unwrap_err();
+    let took = start.elapsed();
+
+    let TmuxError::Timeout { command, elapsed } = &error else {
+        panic!("{error:?}");
+    };
+    assert_eq!(
+        command,
+        &[
+            "tmux",
+            "-u",
+            "-L",
+            "hung-rename",
+            "rename-session",
+            "-t",
+            "=a",
+            "b"
+        ]
+    );
+    assert_eq!(*elapsed, timeout);
+    assert!(took >= timeout && took < Duration::from_secs(5), "{took:?}");
+
+    // Killed and reaped rather than left sleeping
+    let pid = std::fs::read_to_string(dir.join("hung-rename.pid")).unwrap();
+    assert!(!Path::new(&format!("/proc/{}", pid.trim())).exists());
+}
+
+#[test]
+fn commands_within_the_timeout_are_left_to_finish() {
+    fake_tmux();
+    Server::socket("quick")
+        .with_timeout(Duration::from_secs(5))
+        .rename_session("a", "b")
+        .unwrap();
+}